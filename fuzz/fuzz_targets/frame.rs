@@ -0,0 +1,15 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+// The parser is pulled in on its own rather than through the library, sparing the fuzzer libpcap and the OUI download
+#[allow(dead_code)]
+#[path = "../../src/wifi.rs"]
+mod wifi;
+#[allow(dead_code)]
+#[path = "../../src/layer3.rs"]
+mod layer3;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = wifi::Summary::parse(data);
+    let _ = wifi::Frame::parse(data);
+});
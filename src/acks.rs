@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+use eui48::MacAddress;
+
+use crate::wifi::{Kind, Summary};
+
+/// An ACK starts a SIFS after the end of the frame it answers, so one starting much later answers something else
+/// Long enough for a full length frame sent at a low rate
+const WINDOW_MICROS: u64 = 2000;
+
+/// What an ACK says about who is nearby
+pub enum Ack {
+    /// It answered the frame just heard, so it was sent by that frame's receiver
+    Answered {
+        acker: MacAddress
+    },
+    /// It answered a frame that wasn't heard, so its receiver is transmitting out of range
+    Unanswered {
+        receiver: MacAddress,
+        /// The device last heard sending a directed frame to the receiver, most likely the one that sent the ACK
+        peer: Option<MacAddress>
+    }
+}
+
+/// ACK frames name only who they are for, so their sender is worked out from the directed frame heard just before
+pub struct AckTiming {
+    /// The last frame each sensor heard that expects an ACK, as when it started in microseconds, transmitter and receiver
+    last: HashMap<String, (u64, MacAddress, MacAddress)>,
+    /// The device last heard sending a directed frame to each address
+    peers: HashMap<MacAddress, MacAddress>
+}
+impl AckTiming {
+    pub fn new() -> Self {
+        Self {
+            last: HashMap::new(),
+            peers: HashMap::new()
+        }
+    }
+    /// Follow a frame heard by a sensor at a time in microseconds, giving what can be told of an ACK
+    pub fn frame(&mut self, sensor: &str, micros: u64, summary: &Summary) -> Option<Ack> {
+        let last = self.last.remove(sensor);
+        if summary.kind == Kind::Control && summary.subtype == 13 {
+            return Some(match last {
+                Some((start, transmitter, receiver)) if transmitter == summary.receiver && micros.saturating_sub(start) <= WINDOW_MICROS => Ack::Answered {
+                    acker: receiver
+                },
+                _ => Ack::Unanswered {
+                    receiver: summary.receiver,
+                    peer: self.peers.get(&summary.receiver).copied()
+                }
+            })
+        }
+        // Control frames are answered by other control frames if at all, and group addressed frames not at all
+        if let (Some(transmitter), false, true) = (summary.transmitter, summary.kind == Kind::Control, summary.receiver.is_unicast()) {
+            self.last.insert(sensor.to_string(), (micros, transmitter, summary.receiver));
+            self.peers.insert(summary.receiver, transmitter);
+        }
+        None
+    }
+}
@@ -0,0 +1,423 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    io::{self, Write},
+    str::FromStr,
+    time::Duration
+};
+use eui48::MacAddress;
+
+use crate::{DEAUTH_WINDOW, DeviceList, KnownDevice, Role, anonymize::Anonymizer, export, fingerprint, policy::Policy};
+
+/// An access point answering probes for this many networks is taken to be answering for whatever it is asked
+const KARMA_NETWORKS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical
+}
+impl FromStr for Severity {
+    type Err = Error;
+    fn from_str(severity: &str) -> Result<Self, Error> {
+        match severity {
+            "info" => Ok(Self::Info),
+            "warn" | "warning" => Ok(Self::Warning),
+            "critical" => Ok(Self::Critical),
+            _ => Err(Error::UnknownSeverity(severity.to_string()))
+        }
+    }
+}
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", match self {
+            Self::Info => "info",
+            Self::Warning => "warn",
+            Self::Critical => "critical"
+        })
+    }
+}
+
+/// What an alert is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    /// A device looks like a repeater or range extender
+    Repeater,
+    /// A hidden network's SSID was given away by its clients
+    HiddenSsid,
+    /// A device advertised rates no standard radio uses, as some attack tools do
+    OddRates,
+    /// An access point advertised weaker security than the policy for its SSID allows
+    Policy,
+    /// Beacons or probe responses for one BSSID came from more than one transmitter
+    SpoofedBssid,
+    /// A new access point advertised a known SSID with different security from those already advertising it
+    EvilTwin,
+    /// An access point's beacons changed what they say about it, as when it is replaced by an impersonator
+    BeaconChange,
+    /// An access point answered probes for several networks, as KARMA attacks do to lure clients
+    Karma,
+    /// A device on one of the watchlists turned up
+    Watchlist,
+    /// A network's clients were sent a burst of deauthentications, as attacks do to knock them off or capture their handshakes
+    Deauth
+}
+impl Kind {
+    pub const ALL: [Self; 10] = [
+        Self::Repeater,
+        Self::HiddenSsid,
+        Self::OddRates,
+        Self::Policy,
+        Self::SpoofedBssid,
+        Self::EvilTwin,
+        Self::BeaconChange,
+        Self::Karma,
+        Self::Watchlist,
+        Self::Deauth
+    ];
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Repeater => "repeater",
+            Self::HiddenSsid => "hidden-ssid",
+            Self::OddRates => "odd-rates",
+            Self::Policy => "policy",
+            Self::SpoofedBssid => "spoofed-bssid",
+            Self::EvilTwin => "evil-twin",
+            Self::BeaconChange => "beacon-change",
+            Self::Karma => "karma",
+            Self::Watchlist => "watchlist",
+            Self::Deauth => "deauth"
+        }
+    }
+    /// What alerts of this kind are about, as a title for reports
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Repeater => "Repeater or range extender",
+            Self::HiddenSsid => "Hidden network revealed",
+            Self::OddRates => "Non-standard rates advertised",
+            Self::Policy => "Security policy breached",
+            Self::SpoofedBssid => "Spoofed BSSID",
+            Self::EvilTwin => "Evil twin access point",
+            Self::BeaconChange => "Beacon fingerprint changed",
+            Self::Karma => "KARMA access point",
+            Self::Watchlist => "Watched device seen",
+            Self::Deauth => "Deauthentication flood"
+        }
+    }
+    pub fn default_severity(&self) -> Severity {
+        match self {
+            Self::Repeater => Severity::Warning,
+            Self::HiddenSsid => Severity::Info,
+            Self::OddRates => Severity::Warning,
+            Self::Policy => Severity::Warning,
+            Self::SpoofedBssid => Severity::Critical,
+            Self::EvilTwin => Severity::Critical,
+            Self::BeaconChange => Severity::Warning,
+            Self::Karma => Severity::Critical,
+            Self::Watchlist => Severity::Warning,
+            Self::Deauth => Severity::Critical
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Alert {
+    pub kind: Kind,
+    pub address: MacAddress,
+    pub severity: Severity,
+    pub message: String,
+    /// When the alert was raised, since the capture started
+    pub time: Duration,
+    pub acknowledged: bool,
+    /// Made critical by an escalation rule, having been raised often enough
+    pub escalated: bool
+}
+impl Alert {
+    /// Identifies the alert across runs, so it stays acknowledged when raised again
+    pub fn key(&self) -> String {
+        format!("{}/{}", self.kind.name(), self.address.to_hex_string())
+    }
+    /// The alert as a JSON object, for handing on to other programs
+    pub fn json(&self) -> String {
+        export::json_object(vec![
+            ("kind", export::json_string(self.kind.name())),
+            ("severity", export::json_string(&self.severity.to_string())),
+            ("address", export::json_string(&self.address.to_hex_string())),
+            ("message", export::json_string(&self.message)),
+            ("time", format!("{:.3}", self.time.as_secs_f64())),
+            ("escalated", self.escalated.to_string())
+        ])
+    }
+}
+
+/// A plain text line for each alert, for reading or grepping without a JSON parser
+pub struct Log {
+    output: Box<dyn Write>,
+    anonymizer: Anonymizer,
+    /// When the capture started, as seconds since the Unix epoch, to give each line the time it was raised
+    started: u64
+}
+impl Log {
+    pub fn new(output: Box<dyn Write>, anonymizer: Anonymizer, started: u64) -> Self {
+        Self {
+            output,
+            anonymizer,
+            started
+        }
+    }
+    pub fn alert(&mut self, alert: &Alert) -> io::Result<()> {
+        writeln!(
+            self.output,
+            "{} {} {} {} {}{}",
+            self.started + alert.time.as_secs(),
+            alert.severity,
+            alert.kind.name(),
+            self.anonymizer.address_string(&alert.address),
+            self.anonymizer.text(&alert.message),
+            if alert.escalated { " (escalated)" } else { "" }
+        )?;
+        self.output.flush()
+    }
+}
+
+/// Raise alerts of a kind as critical once so many have been raised within a window
+#[derive(Debug, Clone, Copy)]
+pub struct Escalation {
+    pub count: usize,
+    pub window: Duration
+}
+
+/// Findings worth the operator's attention, raised at most once per device and kind
+pub struct Alerts {
+    pub alerts: Vec<Alert>,
+    severities: HashMap<Kind, Severity>,
+    escalations: HashMap<Kind, Escalation>,
+    /// When each alert of each kind was raised, including those since dismissed, for escalation
+    history: HashMap<Kind, Vec<Duration>>,
+    /// Keys of alerts that have been raised, including those since dismissed
+    raised: HashSet<String>,
+    /// Keys of alerts acknowledged, in this run or an earlier one
+    pub acknowledged: HashSet<String>,
+    /// Alerts raised since they were last taken, to be passed on to notifiers
+    fresh: Vec<Alert>,
+    /// The weakest security allowed for networks of each SSID
+    policies: Vec<Policy>
+}
+impl Alerts {
+    /// Track alerts, with some kinds given a different severity from their default or escalated when repeated,
+    /// checking access points against the policies for their SSIDs
+    pub fn new(severities: HashMap<Kind, Severity>, escalations: HashMap<Kind, Escalation>, policies: Vec<Policy>) -> Self {
+        Self {
+            alerts: vec![],
+            severities,
+            escalations,
+            history: HashMap::new(),
+            raised: HashSet::new(),
+            acknowledged: HashSet::new(),
+            fresh: vec![],
+            policies
+        }
+    }
+    /// Parse a `kind=severity` override, such as `repeater=critical`
+    pub fn parse_severity(setting: &str) -> Result<(Kind, Severity), Error> {
+        let (kind, severity) = setting.split_once('=').ok_or_else(|| Error::Malformed(setting.to_string()))?;
+        Ok((parse_kind(kind)?, severity.trim().parse()?))
+    }
+    /// Parse a `kind=count/minutes` escalation rule, such as `odd-rates=3/10` for the third within ten minutes
+    pub fn parse_escalation(setting: &str) -> Result<(Kind, Escalation), Error> {
+        let malformed = || Error::Malformed(setting.to_string());
+        let (kind, rule) = setting.split_once('=').ok_or_else(malformed)?;
+        let (count, minutes) = rule.split_once('/').ok_or_else(malformed)?;
+        Ok((parse_kind(kind)?, Escalation {
+            count: count.trim().parse().map_err(|_| malformed())?,
+            window: Duration::from_secs(minutes.trim().parse::<u64>().map_err(|_| malformed())? * 60)
+        }))
+    }
+    fn alert(&self, kind: Kind, address: MacAddress, message: String, time: Duration) -> Alert {
+        Alert {
+            kind,
+            address,
+            severity: self.severities.get(&kind).copied().unwrap_or_else(|| kind.default_severity()),
+            message,
+            time,
+            acknowledged: false,
+            escalated: false
+        }
+    }
+    fn raise(&mut self, kind: Kind, address: MacAddress, message: String, time: Duration) {
+        let alert = self.alert(kind, address, message, time);
+        self.record(alert);
+    }
+    /// Raise a `watchlist` alert, only listing it if `listed`, and give it back for the watchlist's own command
+    pub fn watched(&mut self, address: MacAddress, message: String, time: Duration, listed: bool) -> Alert {
+        let alert = self.alert(Kind::Watchlist, address, message, time);
+        if listed {
+            self.record(alert.clone());
+        }
+        alert
+    }
+    fn record(&mut self, mut alert: Alert) {
+        let (kind, time) = (alert.kind, alert.time);
+        let key = alert.key();
+        if self.raised.insert(key.clone()) {
+            let history = self.history.entry(kind).or_default();
+            history.push(time);
+            if let Some(escalation) = self.escalations.get(&kind) {
+                let recent = history.iter().filter(|&&raised| raised + escalation.window >= time).count();
+                if recent >= escalation.count {
+                    alert.severity = Severity::Critical;
+                    alert.escalated = true;
+                }
+            }
+            alert.acknowledged = self.acknowledged.contains(&key);
+            self.fresh.push(alert.clone());
+            self.alerts.push(alert);
+        }
+    }
+    /// Look over every device for anything newly worth raising
+    pub fn check(&mut self, devices: &DeviceList, time: Duration) {
+        for (address, reason) in devices.repeaters() {
+            self.raise(Kind::Repeater, address, format!("Looks like a repeater: {}", reason), time);
+        }
+        for (&address, device) in devices.iter() {
+            if let (true, Some(ssid)) = (device.hidden, device.beacon.as_ref().filter(|ssid| !ssid.is_empty())) {
+                self.raise(Kind::HiddenSsid, address, format!("Hidden network revealed as {:?}", ssid), time);
+            }
+            let nonstandard = device.rates.as_deref().map(fingerprint::nonstandard).unwrap_or_default();
+            if !nonstandard.is_empty() {
+                let rates: Vec<_> = nonstandard.into_iter().map(fingerprint::rate_name).collect();
+                self.raise(Kind::OddRates, address, format!("Advertises non-standard rates: {}", rates.join(", ")), time);
+            }
+            if device.advertisers.len() > 1 {
+                let advertisers: Vec<_> = device.advertisers.iter().map(MacAddress::to_hex_string).collect();
+                self.raise(Kind::SpoofedBssid, address, format!("Advertised by {} transmitters: {}", advertisers.len(), advertisers.join(", ")), time);
+            }
+            if !device.fingerprint_changes.is_empty() {
+                self.raise(Kind::BeaconChange, address, format!("Beacons changed: {}", device.fingerprint_changes.join(", ")), time);
+            }
+            if device.responded.len() >= KARMA_NETWORKS {
+                let networks: Vec<_> = device.responded.iter().map(|ssid| format!("{:?}", ssid)).collect();
+                self.raise(Kind::Karma, address, format!("Answered probes for {} networks: {}", networks.len(), networks.join(", ")), time);
+            }
+            if let (Some(ssid), Some(security)) = (&device.beacon, &device.security) {
+                let violations: Vec<_> = self.policies.iter()
+                    .filter(|policy| &policy.ssid == ssid)
+                    .flat_map(|policy| policy.violations(security))
+                    .collect();
+                if !violations.is_empty() {
+                    self.raise(Kind::Policy, address, format!("{:?} breaks its security policy: {}", ssid, violations.join(", ")), time);
+                }
+            }
+        }
+        for flood in devices.deauths.floods.iter() {
+            self.raise(Kind::Deauth, flood.bssid, format!(
+                "{} deauthentications and disassociations within {}s aimed at {}",
+                flood.peak,
+                DEAUTH_WINDOW.as_secs(),
+                if flood.broadcast() { "every client".to_string() } else { flood.client.to_hex_string() }
+            ), time);
+        }
+        self.check_twins(devices, time);
+    }
+    /// Raise an `evil-twin` alert for each access point that turned up advertising a known SSID
+    /// with security none of those already advertising it offer
+    fn check_twins(&mut self, devices: &DeviceList, time: Duration) {
+        let mut networks: HashMap<&str, Vec<(MacAddress, &KnownDevice)>> = HashMap::new();
+        for (&address, device) in devices.iter() {
+            if let (Some(ssid), Some(Role::AccessPoint), Some(_)) = (device.beacon.as_deref().filter(|ssid| !ssid.is_empty()), device.role, device.security) {
+                networks.entry(ssid).or_default().push((address, device));
+            }
+        }
+        for (ssid, mut advertisers) in networks {
+            advertisers.sort_by_key(|&(address, device)| (device.first_seen, address));
+            for index in 1..advertisers.len() {
+                let (address, device) = advertisers[index];
+                let security = match device.security {
+                    Some(security) => security,
+                    None => continue
+                };
+                // The halves of an OWE transition pair and the links of one multi-link device legitimately differ
+                let matched = advertisers[..index].iter().any(|&(other_address, other)| {
+                    device.owe_pair == Some(other_address)
+                        || other.owe_pair == Some(address)
+                        || (device.mld.is_some() && device.mld == other.mld)
+                        || other.security.map_or(false, |other| other.level == security.level && other.enterprise == security.enterprise)
+                });
+                if matched {
+                    continue
+                }
+                let (first_address, first) = advertisers[0];
+                let first_security = first.security.map_or_else(String::new, |security| security.to_string());
+                self.raise(Kind::EvilTwin, address, format!(
+                    "Advertises {:?} with {}, where {} first advertised it with {}",
+                    ssid,
+                    security,
+                    first_address.to_hex_string(),
+                    first_security
+                ), time);
+            }
+        }
+    }
+    /// Alerts raised since this was last called
+    pub fn take_fresh(&mut self) -> Vec<Alert> {
+        std::mem::take(&mut self.fresh)
+    }
+    pub fn set_acknowledged(&mut self, index: usize, acknowledged: bool) {
+        if let Some(alert) = self.alerts.get_mut(index) {
+            alert.acknowledged = acknowledged;
+            if acknowledged {
+                self.acknowledged.insert(alert.key());
+            } else {
+                self.acknowledged.remove(&alert.key());
+            }
+        }
+    }
+    /// Remove an alert from the list, acknowledging it so it isn't counted again
+    pub fn dismiss(&mut self, index: usize) {
+        if index < self.alerts.len() {
+            self.set_acknowledged(index, true);
+            self.alerts.remove(index);
+        }
+    }
+    /// How many alerts are yet to be acknowledged, and the most severe of them
+    pub fn unhandled(&self) -> (usize, Option<Severity>) {
+        let unhandled = self.alerts.iter().filter(|alert| !alert.acknowledged);
+        (unhandled.clone().count(), unhandled.map(|alert| alert.severity).max())
+    }
+    /// Forget the alerts raised so far, so they may be raised again, while remembering what was acknowledged
+    pub fn reset(&mut self) {
+        self.alerts.clear();
+        self.fresh.clear();
+        self.raised.clear();
+        self.history.clear();
+    }
+}
+
+fn parse_kind(kind: &str) -> Result<Kind, Error> {
+    Kind::ALL.iter()
+        .find(|known| known.name() == kind.trim())
+        .copied()
+        .ok_or_else(|| Error::UnknownKind(kind.to_string()))
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Malformed(String),
+    UnknownKind(String),
+    UnknownSeverity(String)
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Malformed(setting) => write!(f, "expected kind=severity or kind=count/minutes, got {:?}", setting),
+            Self::UnknownKind(kind) => write!(
+                f,
+                "unknown alert kind {:?}, expected one of {}",
+                kind,
+                Kind::ALL.iter().map(Kind::name).collect::<Vec<_>>().join(", ")
+            ),
+            Self::UnknownSeverity(severity) => write!(f, "unknown severity {:?}, expected info, warn or critical", severity)
+        }
+    }
+}
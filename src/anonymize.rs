@@ -0,0 +1,328 @@
+use std::{
+    borrow::Cow,
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hash, Hasher}
+};
+use eui48::MacAddress;
+use radiotap::Radiotap;
+use regex::{Captures, Regex};
+
+/// Elements which name a device or network other than by the address and network name, dropped from saved frames:
+/// neighbour reports, SSID lists, mesh names, reduced neighbour reports and the vendor specific elements WPS uses
+const IDENTIFYING: [u8; 5] = [52, 84, 114, 201, 221];
+/// Extension elements dropped from saved frames, being the multi-link element naming a device's other radios
+const IDENTIFYING_EXTENSIONS: [u8; 1] = [107];
+
+/// Replaces addresses and network names with stand-ins derived from them, for sharing a capture without who was in it
+/// The same address or name always gets the same stand-in within a run, but a different one the next run,
+/// so devices can still be followed through everything written without being looked up elsewhere
+#[derive(Debug, Clone)]
+pub struct Anonymizer {
+    /// None to leave everything as it was
+    key: Option<RandomState>,
+    /// Addresses and quoted names in free text, such as alert messages
+    text: Regex
+}
+impl Anonymizer {
+    /// An anonymizer with a fresh key, or one which changes nothing
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            key: Some(()).filter(|_| enabled).map(|_| RandomState::new()),
+            text: Regex::new(r#"(?i)\b[0-9a-f]{2}(?::[0-9a-f]{2}){5}\b|"((?:[^"\\]|\\.)*)""#).unwrap()
+        }
+    }
+    pub fn is_enabled(&self) -> bool {
+        self.key.is_some()
+    }
+    fn hash(&self, kind: &str, data: &[u8]) -> Option<u64> {
+        let mut hasher = self.key.as_ref()?.build_hasher();
+        kind.hash(&mut hasher);
+        data.hash(&mut hasher);
+        Some(hasher.finish())
+    }
+    /// Keeps the manufacturer part, and the group address bits with it, replacing the rest
+    /// Broadcast and multicast addresses say nothing of who sent them and are kept as they are
+    pub fn address(&self, address: MacAddress) -> MacAddress {
+        let hash = match self.hash("address", address.as_bytes()) {
+            Some(hash) if !address.is_multicast() => hash.to_le_bytes(),
+            _ => return address
+        };
+        let bytes = address.to_array();
+        MacAddress::new([bytes[0], bytes[1], bytes[2], hash[0], hash[1], hash[2]])
+    }
+    /// An address as it is written out, in the usual colon separated form
+    pub fn address_string(&self, address: &MacAddress) -> String {
+        self.address(*address).to_hex_string()
+    }
+    /// Hidden names, sent empty or as zeroes, are kept as they are
+    pub fn ssid<'a>(&self, ssid: &'a str) -> Cow<'a, str> {
+        match self.hash("ssid", ssid.as_bytes()) {
+            Some(hash) if !ssid.chars().all(|c| c == '\0') => Cow::Owned(format!("ssid-{:08x}", hash as u32)),
+            _ => Cow::Borrowed(ssid)
+        }
+    }
+    /// Free text with every address and every quoted name in it replaced, as names are quoted in alert messages
+    pub fn text<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if !self.is_enabled() {
+            return Cow::Borrowed(text)
+        }
+        self.text.replace_all(text, |captures: &Captures| match captures.get(1) {
+            Some(quoted) => format!("{:?}", self.ssid(&quoted.as_str().replace("\\\"", "\"").replace("\\\\", "\\"))),
+            None => captures[0].parse().map_or_else(|_| captures[0].to_string(), |address| self.address_string(&address))
+        })
+    }
+    /// A captured frame with its addresses and network names replaced, and with anything else that could identify
+    /// the devices involved taken out: the payloads of data frames, and most of the bodies of management frames
+    pub fn frame<'a>(&self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        if !self.is_enabled() {
+            return Cow::Borrowed(data)
+        }
+        let (header, has_fcs) = match Radiotap::parse(data) {
+            // Only taken to end in a frame check sequence when the flags say so, so no real body bytes are cut off
+            Ok((radiotap, frame)) => (data.len() - frame.len(), radiotap.flags.map_or(false, |flags| flags.fcs)),
+            // Nothing past a header that can't be read can be trusted to be anonymized
+            Err(_) => return Cow::Owned(vec![])
+        };
+        let mut frame = data[header..].to_vec();
+        if has_fcs {
+            frame.truncate(frame.len().saturating_sub(4));
+        }
+        self.rewrite(&mut frame);
+        if has_fcs {
+            let fcs = crc32(&frame);
+            frame.extend_from_slice(&fcs.to_le_bytes());
+        }
+        let mut anonymized = data[..header].to_vec();
+        anonymized.extend(frame);
+        Cow::Owned(anonymized)
+    }
+    /// Replace the addresses in an 802.11 frame without its FCS, cutting it short of anything else identifying
+    fn rewrite(&self, frame: &mut Vec<u8>) {
+        if frame.len() < 10 {
+            frame.clear();
+            return
+        }
+        let (kind, subtype, flags) = ((frame[0] >> 2) & 0b11, frame[0] >> 4, frame[1]);
+        let (addresses, header): (&[usize], usize) = match kind {
+            // Management
+            0 => (&[4, 10, 16], 24),
+            // Control: CTS and ACK name only the receiver, most others a transmitter too
+            1 if subtype == 12 || subtype == 13 => (&[4], 10),
+            1 => (&[4, 10], frame.len()),
+            // Data, with a fourth address when relayed between access points, and QoS control
+            2 if flags & 0b11 == 0b11 => (&[4, 10, 16, 24], if subtype & 0b1000 != 0 { 32 } else { 30 }),
+            2 => (&[4, 10, 16], if subtype & 0b1000 != 0 { 26 } else { 24 }),
+            _ => {
+                frame.clear();
+                return
+            }
+        };
+        for &offset in addresses {
+            self.rewrite_address(frame, offset);
+        }
+        match (kind, subtype) {
+            // Association request, association response, reassociation request (naming the access point being
+            // left), reassociation response, probe request, probe response and beacon, each by its fixed fields
+            (0, 0) => self.rewrite_elements(frame, header + 4),
+            (0, 1) | (0, 3) => self.rewrite_elements(frame, header + 6),
+            (0, 2) => {
+                self.rewrite_address(frame, header + 4);
+                self.rewrite_elements(frame, header + 10)
+            }
+            (0, 4) => self.rewrite_elements(frame, header),
+            (0, 5) | (0, 8) => self.rewrite_elements(frame, header + 12),
+            _ => frame.truncate(header)
+        }
+    }
+    fn rewrite_address(&self, frame: &mut [u8], offset: usize) {
+        if let Some(bytes) = frame.get_mut(offset..offset + 6) {
+            let address = self.address(MacAddress::from_bytes(bytes).unwrap());
+            bytes.copy_from_slice(address.as_bytes());
+        }
+    }
+    /// Rewrite the elements after a management frame's fixed fields, renaming networks and dropping those that
+    /// identify a device, stopping at the first that runs past the end
+    fn rewrite_elements(&self, frame: &mut Vec<u8>, start: usize) {
+        if frame.len() < start {
+            frame.clear();
+            return
+        }
+        let elements = frame.split_off(start);
+        let mut offset = 0;
+        while let Some(&[id, length]) = elements.get(offset..offset + 2) {
+            let value = match elements.get(offset + 2..offset + 2 + length as usize) {
+                Some(value) => value,
+                None => break
+            };
+            offset += 2 + length as usize;
+            if IDENTIFYING.contains(&id) || (id == 255 && value.first().map_or(false, |extension| IDENTIFYING_EXTENSIONS.contains(extension))) {
+                continue
+            }
+            if id == 0 {
+                let ssid = String::from_utf8_lossy(value);
+                let renamed = self.ssid(&ssid);
+                if renamed != ssid {
+                    frame.extend_from_slice(&[id, renamed.len() as u8]);
+                    frame.extend_from_slice(renamed.as_bytes());
+                    continue
+                }
+            }
+            frame.extend_from_slice(&[id, length]);
+            frame.extend_from_slice(value);
+        }
+    }
+}
+
+/// The CRC-32 of IEEE 802.3, which 802.11 uses for its frame check sequence
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| (0..8).fold(crc ^ byte as u32, |crc, _| if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIENT: [u8; 6] = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55];
+    const ACCESS_POINT: [u8; 6] = [0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb];
+
+    /// A radiotap header giving only flags, with or without a frame check sequence, before a frame
+    fn captured(frame: &[u8], fcs: bool) -> Vec<u8> {
+        let mut data = vec![0, 0, 9, 0, 0b10, 0, 0, 0, if fcs { 0x10 } else { 0 }];
+        data.extend_from_slice(frame);
+        if fcs {
+            data.extend_from_slice(&crc32(frame).to_le_bytes());
+        }
+        data
+    }
+    /// A management frame header of a subtype from a transmitter to a receiver in a network
+    fn management(subtype: u8, receiver: [u8; 6], transmitter: [u8; 6], bssid: [u8; 6]) -> Vec<u8> {
+        let mut frame = vec![subtype << 4, 0, 0, 0];
+        frame.extend_from_slice(&receiver);
+        frame.extend_from_slice(&transmitter);
+        frame.extend_from_slice(&bssid);
+        frame.extend_from_slice(&[0x10, 0]);
+        frame
+    }
+
+    #[test]
+    fn disabled() {
+        let anonymizer = Anonymizer::new(false);
+        let address = MacAddress::new(CLIENT);
+        assert_eq!(anonymizer.address(address), address);
+        assert!(matches!(anonymizer.ssid("CoffeeShop"), Cow::Borrowed("CoffeeShop")));
+        assert!(matches!(anonymizer.text("00:11:22:33:44:55 joined \"CoffeeShop\""), Cow::Borrowed(_)));
+        let data = captured(&management(8, [0xff; 6], ACCESS_POINT, ACCESS_POINT), true);
+        assert!(matches!(anonymizer.frame(&data), Cow::Borrowed(frame) if frame == &data[..]));
+    }
+    #[test]
+    fn addresses_and_names() {
+        let (anonymizer, other) = (Anonymizer::new(true), Anonymizer::new(true));
+        let address = MacAddress::new(CLIENT);
+        let replaced = anonymizer.address(address);
+        assert_ne!(replaced, address);
+        assert_eq!(replaced.as_bytes()[..3], CLIENT[..3], "the manufacturer is kept");
+        assert_eq!(anonymizer.address(address), replaced, "the same stand-in within a run");
+        assert_ne!(other.address(address), replaced, "a different stand-in in another run");
+        for kept in [MacAddress::broadcast(), MacAddress::new([0x01, 0x00, 0x5e, 0, 0, 1])] {
+            assert_eq!(anonymizer.address(kept), kept);
+        }
+
+        let name = anonymizer.ssid("CoffeeShop");
+        assert!(name.starts_with("ssid-") && name.len() == 13, "{}", name);
+        assert_eq!(anonymizer.ssid("CoffeeShop"), name);
+        assert_ne!(anonymizer.ssid("CoffeeShop2"), name);
+        assert_eq!(anonymizer.ssid(""), "");
+        assert_eq!(anonymizer.ssid("\0\0\0"), "\0\0\0");
+    }
+    #[test]
+    fn text() {
+        let anonymizer = Anonymizer::new(true);
+        let address = anonymizer.address_string(&MacAddress::new(CLIENT));
+        let text = anonymizer.text(r#"00:11:22:33:44:55 joined "CoffeeShop", then "Say \"hi\"" from 00:11:22:33:44:55"#);
+        assert_eq!(text, format!(
+            "{} joined {:?}, then {:?} from {}",
+            address, anonymizer.ssid("CoffeeShop"), anonymizer.ssid("Say \"hi\""), address
+        ));
+        // Nothing that only looks like part of an address, and no unclosed quote
+        for kept in ["channel 11:00", "id 00:11:22:33:44", "x00:11:22:33:44:55", "an \"unclosed name"] {
+            assert_eq!(anonymizer.text(kept), kept);
+        }
+    }
+    #[test]
+    fn beacon() {
+        let anonymizer = Anonymizer::new(true);
+        let mut frame = management(8, [0xff; 6], ACCESS_POINT, ACCESS_POINT);
+        // Timestamp, beacon interval and capabilities
+        frame.extend_from_slice(&[0; 12]);
+        let elements: &[&[u8]] = &[b"\x00\x0aCoffeeShop", b"\x01\x02\x82\x84", b"\xdd\x04\x00\x50\xf2\x04", b"\xff\x02\x6b\x00", b"\xff\x02\x23\x00"];
+        for element in elements {
+            frame.extend_from_slice(element);
+        }
+        let data = captured(&frame, true);
+        let anonymized = anonymizer.frame(&data).into_owned();
+
+        let (header, rest) = anonymized.split_at(9);
+        assert_eq!(header, &data[..9]);
+        let (body, fcs) = rest.split_at(rest.len() - 4);
+        assert_eq!(fcs, crc32(body).to_le_bytes(), "the frame check sequence matches what was written");
+        let replaced = anonymizer.address(MacAddress::new(ACCESS_POINT));
+        assert_eq!(body[4..10], [0xff; 6]);
+        assert_eq!(body[10..16], *replaced.as_bytes());
+        assert_eq!(body[16..22], *replaced.as_bytes());
+        let name = anonymizer.ssid("CoffeeShop");
+        let mut expected = vec![0, name.len() as u8];
+        expected.extend_from_slice(name.as_bytes());
+        // Rates and the HE capabilities extension are kept, WPS and the multi-link element are dropped
+        expected.extend_from_slice(b"\x01\x02\x82\x84\xff\x02\x23\x00");
+        assert_eq!(body[36..], expected[..]);
+    }
+    #[test]
+    fn data_frames() {
+        let anonymizer = Anonymizer::new(true);
+        // QoS data relayed between access points, with all four addresses, QoS control and a payload
+        let mut frame = vec![0x88, 0b11, 0, 0];
+        for address in [ACCESS_POINT, CLIENT, ACCESS_POINT, CLIENT] {
+            frame.extend_from_slice(&address);
+            if frame.len() == 22 {
+                frame.extend_from_slice(&[0, 0]);
+            }
+        }
+        frame.extend_from_slice(&[0, 0]);
+        frame.extend_from_slice(b"payload");
+        let anonymized = anonymizer.frame(&captured(&frame, false)).into_owned();
+        assert_eq!(anonymized.len(), 9 + 32, "the payload is taken out");
+        assert_eq!(anonymized[9 + 24..9 + 30], *anonymizer.address(MacAddress::new(CLIENT)).as_bytes());
+
+        // An acknowledgement names only its receiver
+        let mut ack = vec![0xd4, 0, 0, 0];
+        ack.extend_from_slice(&CLIENT);
+        let anonymized = anonymizer.frame(&captured(&ack, false)).into_owned();
+        assert_eq!(anonymized[9 + 4..], *anonymizer.address(MacAddress::new(CLIENT)).as_bytes());
+    }
+    #[test]
+    fn malformed() {
+        let anonymizer = Anonymizer::new(true);
+        // A radiotap header that can't be read leaves nothing
+        assert!(anonymizer.frame(&[0, 0, 200, 0, 0, 0, 0, 0]).is_empty());
+        assert!(anonymizer.frame(&[]).is_empty());
+        // Too short for a header, or of the reserved type, leaves only the radiotap header
+        for frame in [&[0x80, 0, 0][..], &[0x0c, 0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8]] {
+            assert_eq!(anonymizer.frame(&captured(frame, false)).len(), 9);
+        }
+        // A beacon cut short in its fixed fields is dropped, and one with an element running past the end stops there
+        let beacon = management(8, [0xff; 6], ACCESS_POINT, ACCESS_POINT);
+        let mut short = beacon.clone();
+        short.extend_from_slice(&[0; 6]);
+        assert_eq!(anonymizer.frame(&captured(&short, false)).len(), 9);
+        let mut overrun = beacon;
+        overrun.extend_from_slice(&[0; 12]);
+        overrun.extend_from_slice(b"\x01\x02\x82\x84\x00\x20Coffee");
+        let anonymized = anonymizer.frame(&captured(&overrun, false)).into_owned();
+        assert_eq!(anonymized[9 + 36..], *b"\x01\x02\x82\x84");
+    }
+    #[test]
+    fn frame_check_sequence() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+}
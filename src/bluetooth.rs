@@ -0,0 +1,183 @@
+// Only scanned with when built with the feature, though the tab showing what was found is always there
+#![cfg_attr(not(feature = "bluetooth"), allow(dead_code))]
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+    time::Duration
+};
+use eui48::MacAddress;
+
+/// How a Bluetooth device's address was assigned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressKind {
+    /// Bluetooth Classic, where addresses are fixed
+    Classic,
+    /// A low energy address assigned by the manufacturer
+    Public,
+    /// A low energy address the device picked itself, usually changed every few minutes for privacy
+    Random
+}
+
+/// What was last advertised by a Bluetooth device, as BlueZ reports it
+#[derive(Debug, Clone)]
+pub struct Advertisement {
+    pub name: Option<String>,
+    pub kind: AddressKind,
+    /// In dBm
+    pub rssi: Option<i16>,
+    pub tx_power: Option<i16>,
+    /// By the company identifier assigned by the Bluetooth SIG
+    pub manufacturer_data: HashMap<u16, Vec<u8>>,
+    /// How many services it advertised
+    pub services: usize
+}
+
+/// A Bluetooth device heard advertising, kept apart from the Wi-Fi devices
+#[derive(Debug, Clone)]
+pub struct Device {
+    pub advertisement: Advertisement,
+    /// The strongest signal heard from it, in dBm
+    pub strongest: Option<i16>,
+    /// How many times its advertisement was heard changing
+    pub updates: u64,
+    /// Since scanning started
+    pub first_seen: Duration,
+    pub last_seen: Duration
+}
+impl Device {
+    fn new(advertisement: Advertisement, time: Duration) -> Self {
+        Self {
+            strongest: advertisement.rssi,
+            advertisement,
+            updates: 1,
+            first_seen: time,
+            last_seen: time
+        }
+    }
+    fn heard(&mut self, advertisement: Advertisement, time: Duration) {
+        self.strongest = self.strongest.max(advertisement.rssi);
+        // A device only names itself in some of its advertisements
+        let name = advertisement.name.clone().or_else(|| self.advertisement.name.take());
+        self.advertisement = Advertisement { name, ..advertisement };
+        self.updates += 1;
+        self.last_seen = time;
+    }
+    /// The manufacturers named in its manufacturer specific data, by name where known
+    pub fn manufacturers(&self) -> Vec<String> {
+        let mut companies: Vec<_> = self.advertisement.manufacturer_data.keys().copied().collect();
+        companies.sort();
+        companies.into_iter()
+            .map(|id| company(id).map_or_else(|| format!("company {:#06x}", id), String::from))
+            .collect()
+    }
+}
+
+/// The more common company identifiers found in manufacturer specific data
+fn company(id: u16) -> Option<&'static str> {
+    Some(match id {
+        0x0000 => "Ericsson",
+        0x0001 => "Nokia",
+        0x0002 => "Intel",
+        0x0006 => "Microsoft",
+        0x000a => "Qualcomm",
+        0x000d => "Texas Instruments",
+        0x000f => "Broadcom",
+        0x004c => "Apple",
+        0x0059 => "Nordic Semiconductor",
+        0x0075 => "Samsung",
+        0x0087 => "Garmin",
+        0x00e0 => "Google",
+        0x0171 => "Amazon",
+        0x02e5 => "Espressif",
+        0x038f => "Xiaomi",
+        0x0499 => "Ruuvi",
+        _ => return None
+    })
+}
+
+/// Every Bluetooth device heard, by address
+pub type BluetoothList = BTreeMap<MacAddress, Device>;
+
+/// Follows Bluetooth Classic and low energy devices through BlueZ from a thread of its own
+#[derive(Clone)]
+pub struct Bluetooth {
+    pub devices: Arc<Mutex<BluetoothList>>,
+    /// Why scanning stopped, if it has
+    pub error: Arc<Mutex<Option<String>>>
+}
+impl Bluetooth {
+    /// Scan with an adapter, or the default one, until it fails
+    /// Must be started before the sandbox is applied, which only confines the thread applying it,
+    /// so that BlueZ can still be reached over D-Bus
+    #[cfg(feature = "bluetooth")]
+    pub fn spawn(adapter: Option<String>) -> Self {
+        let devices = Arc::new(Mutex::new(BTreeMap::new()));
+        let error = Arc::new(Mutex::new(None));
+        let (thread_devices, thread_error) = (devices.clone(), error.clone());
+        std::thread::spawn(move || {
+            let result = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .map_err(|error| error.to_string())
+                .and_then(|runtime| runtime.block_on(scan(adapter, &thread_devices)).map_err(|error| error.to_string()));
+            let message = match result {
+                Ok(()) => "BlueZ stopped reporting devices".to_string(),
+                Err(error) => error
+            };
+            if let Ok(mut error) = thread_error.lock() {
+                *error = Some(message);
+            }
+        });
+        Self {
+            devices,
+            error
+        }
+    }
+}
+
+/// Discover devices until the adapter goes away, reading each one's properties again whenever they change
+#[cfg(feature = "bluetooth")]
+async fn scan(adapter: Option<String>, devices: &Mutex<BluetoothList>) -> bluer::Result<()> {
+    use futures::StreamExt;
+
+    let started = std::time::Instant::now();
+    let session = bluer::Session::new().await?;
+    let adapter = match adapter {
+        Some(name) => session.adapter(&name)?,
+        None => session.default_adapter().await?
+    };
+    adapter.set_powered(true).await?;
+    // Changes to a device's properties, such as its signal strength, come as the device being added again
+    let events = adapter.discover_devices_with_changes().await?;
+    futures::pin_mut!(events);
+    while let Some(event) = events.next().await {
+        let address = match event {
+            bluer::AdapterEvent::DeviceAdded(address) => address,
+            _ => continue
+        };
+        let device = adapter.device(address)?;
+        let advertisement = Advertisement {
+            name: device.name().await?,
+            kind: match device.address_type().await? {
+                bluer::AddressType::BrEdr => AddressKind::Classic,
+                bluer::AddressType::LePublic => AddressKind::Public,
+                bluer::AddressType::LeRandom => AddressKind::Random
+            },
+            rssi: device.rssi().await?,
+            tx_power: device.tx_power().await?,
+            manufacturer_data: device.manufacturer_data().await?.unwrap_or_default(),
+            services: device.uuids().await?.map_or(0, |uuids| uuids.len())
+        };
+        let time = started.elapsed();
+        if let Ok(mut devices) = devices.lock() {
+            match devices.get_mut(&MacAddress::new(address.0)) {
+                Some(known) => known.heard(advertisement, time),
+                None => {
+                    devices.insert(MacAddress::new(address.0), Device::new(advertisement, time));
+                }
+            }
+        }
+    }
+    Ok(())
+}
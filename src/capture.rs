@@ -0,0 +1,514 @@
+use std::{
+    env, fmt,
+    io::{self, BufReader, BufWriter, Read, Write},
+    process::{Child, Command, Stdio},
+    sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}, mpsc::{self, Receiver, TryRecvError}},
+    thread,
+    time::{Duration, Instant}
+};
+use pcap::{Active, Capture, Linktype, Packet, PacketHeader};
+
+use crate::{hopper::Hopper, nl80211, privileges, stats, watchdog::Watchdog};
+
+/// The most frames read ahead of being handled, after which the capture thread waits and leaves libpcap to buffer or drop the rest
+const QUEUE: usize = 10_000;
+
+/// A captured frame, owned so it can be passed between threads and processes
+#[derive(Clone)]
+pub struct Frame {
+    pub header: PacketHeader,
+    pub data: Vec<u8>
+}
+impl Frame {
+    pub fn packet(&self) -> Packet {
+        Packet::new(&self.header, &self.data)
+    }
+}
+impl<'a> From<Packet<'a>> for Frame {
+    fn from(packet: Packet<'a>) -> Self {
+        Self {
+            header: *packet.header,
+            data: packet.data.to_vec()
+        }
+    }
+}
+
+/// Open a non-blocking radiotap capture on the named device, only keeping frames matching a BPF filter if given
+pub fn open(device: &str, monitor: bool, filter: Option<&str>) -> Result<Capture<Active>> {
+    let capture = Capture::from_device(device).map_err(Error::Open)?
+        .promisc(true)
+        .rfmon(monitor)
+        .immediate_mode(true);
+    let capture = capture.open().map_err(Error::Activate)?;
+    let mut capture = capture.setnonblock().map_err(Error::NonBlocking)?;
+
+    if capture.get_datalink() != Linktype::IEEE802_11_RADIOTAP {
+        if !capture.list_datalinks().map_err(Error::Datalink)?.contains(&Linktype::IEEE802_11_RADIOTAP) {
+            return Err(Error::NoRadiotap)
+        }
+        capture.set_datalink(Linktype::IEEE802_11_RADIOTAP).map_err(Error::Datalink)?;
+    }
+    // Compiled against the datalink, so only once it is settled
+    if let Some(filter) = filter {
+        capture.filter(filter).map_err(Error::Filter)?;
+    }
+    Ok(capture)
+}
+
+/// Where captured frames come from
+pub enum Source {
+    /// A capture opened by this process and read on the thread handling its frames, so frames can be injected into it
+    Local(Capture<Active>),
+    /// A capture opened by this process and read on a thread of its own, so a quiet channel never holds up the interface
+    Threaded(Reader),
+    /// A privileged helper process capturing on our behalf
+    Helper(Helper),
+    /// Frames streamed from remote sensors
+    Thread(Receiver<Result<Frame>>)
+}
+impl Source {
+    /// Read a capture on a separate thread, so several interfaces can be listened to at once without waiting on any
+    pub fn threaded(capture: Capture<Active>) -> Self {
+        Self::Threaded(Reader::spawn(capture))
+    }
+
+    /// A source that never gives a frame, for when frames come from a replayed session instead
+    pub fn idle() -> Self {
+        Self::Thread(mpsc::channel().1)
+    }
+
+    /// Read frames streamed from a remote sensor on a separate thread
+    pub fn remote(input: impl Read + Send + 'static) -> Self {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut input = BufReader::new(input);
+            loop {
+                let frame = match read_record(&mut input) {
+                    Ok(Some(Record::Frame(frame))) => Ok(frame),
+                    // Restarts and hops are left to the sensor
+                    Ok(Some(_)) => continue,
+                    Ok(None) => Err(Error::Disconnected),
+                    Err(error) => Err(Error::Io(error))
+                };
+                let stop = frame.is_err();
+                if tx.send(frame).is_err() || stop {
+                    break
+                }
+            }
+        });
+        Self::Thread(rx)
+    }
+
+    /// Get the next frame if one is available without blocking
+    pub fn next(&mut self) -> Result<Option<Frame>> {
+        match self {
+            Self::Local(capture) => match capture.next() {
+                Err(pcap::Error::NoMorePackets) | Err(pcap::Error::TimeoutExpired) => Ok(None),
+                Err(error) => Err(Error::Next(error)),
+                Ok(packet) => Ok(Some(Frame::from(packet)))
+            },
+            Self::Helper(helper) => helper.next(),
+            Self::Threaded(Reader { frames, .. }) | Self::Thread(frames) => match frames.try_recv() {
+                Ok(frame) => frame.map(Some),
+                Err(TryRecvError::Empty) => Ok(None),
+                // Only after the error that stopped the thread has been passed on
+                Err(TryRecvError::Disconnected) => Ok(None)
+            }
+        }
+    }
+    /// Transmit a frame with its radiotap header, which needs the capture to be open in this thread
+    pub fn inject(&mut self, frame: &[u8]) -> Result<()> {
+        match self {
+            Self::Local(capture) => capture.sendpacket(frame).map_err(Error::Inject),
+            Self::Helper(_) | Self::Threaded(_) | Self::Thread(_) => Err(Error::CannotInject)
+        }
+    }
+    /// libpcap's receive and drop counters, if the capture was opened by this process
+    pub fn drops(&mut self) -> Option<stats::Drops> {
+        match self {
+            Self::Local(capture) => capture.stats().ok().map(|stat| stats::Drops {
+                received: stat.received,
+                dropped: stat.dropped,
+                interface: stat.if_dropped
+            }),
+            Self::Threaded(reader) => *reader.drops.lock().unwrap(),
+            Self::Helper(_) | Self::Thread(_) => None
+        }
+    }
+}
+
+/// A capture read on a thread of its own, passing frames over a bounded channel, which stops once this is dropped
+pub struct Reader {
+    frames: Receiver<Result<Frame>>,
+    /// libpcap's counters, as the thread last read them
+    drops: Arc<Mutex<Option<stats::Drops>>>,
+    stop: Arc<AtomicBool>
+}
+impl Reader {
+    pub fn spawn(mut capture: Capture<Active>) -> Self {
+        let (tx, rx) = mpsc::sync_channel(QUEUE);
+        let drops = Arc::new(Mutex::new(None));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (shared_drops, stopped) = (drops.clone(), stop.clone());
+        thread::spawn(move || {
+            let mut counted: Option<Instant> = None;
+            while !stopped.load(Ordering::Relaxed) {
+                if counted.map_or(true, |counted| counted.elapsed() >= Duration::from_secs(1)) {
+                    *shared_drops.lock().unwrap() = capture.stats().ok().map(|stat| stats::Drops {
+                        received: stat.received,
+                        dropped: stat.dropped,
+                        interface: stat.if_dropped
+                    });
+                    counted = Some(Instant::now());
+                }
+                let frame = match capture.next() {
+                    Err(pcap::Error::NoMorePackets) | Err(pcap::Error::TimeoutExpired) => {
+                        thread::sleep(Duration::from_millis(1));
+                        continue
+                    }
+                    Err(error) => Err(Error::Next(error)),
+                    Ok(packet) => Ok(Frame::from(packet))
+                };
+                // Waits while the queue is full, so frames are dropped by libpcap where they are counted
+                let stop = frame.is_err();
+                if tx.send(frame).is_err() || stop {
+                    break
+                }
+            }
+        });
+        Self {
+            frames: rx,
+            drops,
+            stop
+        }
+    }
+}
+impl Drop for Reader {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed)
+    }
+}
+
+const RECORD_FRAME: u8 = 0;
+const RECORD_RESTARTED: u8 = 1;
+const RECORD_RESTART_FAILED: u8 = 2;
+const RECORD_CHANNEL: u8 = 3;
+/// The longest frame taken from the other end, which is the most libpcap will capture of one
+const MAX_CAPLEN: u32 = 262_144;
+/// The longest error taken from the other end, more than enough to say why a restart failed
+const MAX_ERROR: u32 = 4096;
+
+/// A message sent from the capture helper or a remote sensor to the analysis process
+enum Record {
+    Frame(Frame),
+    Restarted(io::Result<()>),
+    Channel(u32)
+}
+
+fn write_frame(output: &mut impl Write, packet: &Packet) -> io::Result<()> {
+    output.write_all(&[RECORD_FRAME])?;
+    output.write_all(&(packet.header.ts.tv_sec as i64).to_le_bytes())?;
+    output.write_all(&(packet.header.ts.tv_usec as i64).to_le_bytes())?;
+    output.write_all(&packet.header.len.to_le_bytes())?;
+    output.write_all(&(packet.data.len() as u32).to_le_bytes())?;
+    output.write_all(packet.data)?;
+    output.flush()
+}
+fn write_restart(output: &mut impl Write, error: Option<String>) -> io::Result<()> {
+    match error {
+        None => output.write_all(&[RECORD_RESTARTED])?,
+        Some(error) => {
+            // Cut short rather than refused by the other end
+            let error = &error.as_bytes()[..error.len().min(MAX_ERROR as usize)];
+            output.write_all(&[RECORD_RESTART_FAILED])?;
+            output.write_all(&(error.len() as u32).to_le_bytes())?;
+            output.write_all(error)?;
+        }
+    }
+    output.flush()
+}
+fn write_channel(output: &mut impl Write, channel: u32) -> io::Result<()> {
+    output.write_all(&[RECORD_CHANNEL])?;
+    output.write_all(&channel.to_le_bytes())?;
+    output.flush()
+}
+/// Read the next record, or None once the helper has closed the stream
+fn read_record(input: &mut impl Read) -> io::Result<Option<Record>> {
+    fn read_u32(input: &mut impl Read) -> io::Result<u32> {
+        let mut bytes = [0; 4];
+        input.read_exact(&mut bytes)?;
+        Ok(u32::from_le_bytes(bytes))
+    }
+    fn read_i64(input: &mut impl Read) -> io::Result<i64> {
+        let mut bytes = [0; 8];
+        input.read_exact(&mut bytes)?;
+        Ok(i64::from_le_bytes(bytes))
+    }
+
+    let mut kind = [0];
+    if input.read(&mut kind)? == 0 {
+        return Ok(None)
+    }
+    Ok(Some(match kind[0] {
+        RECORD_FRAME => {
+            let tv_sec = read_i64(input)?;
+            let tv_usec = read_i64(input)?;
+            let len = read_u32(input)?;
+            let caplen = read_u32(input)?;
+            // Checked before anything is allocated, as a remote sensor may not be trusted
+            if caplen > MAX_CAPLEN {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("a frame of {} bytes is longer than any captured", caplen)))
+            }
+            let mut data = vec![0; caplen as usize];
+            input.read_exact(&mut data)?;
+            Record::Frame(Frame {
+                header: PacketHeader {
+                    ts: libc::timeval {
+                        tv_sec: tv_sec as libc::time_t,
+                        tv_usec: tv_usec as libc::suseconds_t
+                    },
+                    caplen,
+                    len
+                },
+                data
+            })
+        }
+        RECORD_RESTARTED => Record::Restarted(Ok(())),
+        RECORD_RESTART_FAILED => {
+            let length = read_u32(input)?;
+            if length > MAX_ERROR {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("an error of {} bytes is too long", length)))
+            }
+            let mut error = vec![0; length as usize];
+            input.read_exact(&mut error)?;
+            Record::Restarted(Err(io::Error::new(io::ErrorKind::Other, String::from_utf8_lossy(&error).to_string())))
+        }
+        RECORD_CHANNEL => Record::Channel(read_u32(input)?),
+        kind => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown record type {}", kind)))
+    }))
+}
+
+/// Options passed through to the capture helper process
+pub struct HelperOptions<'a> {
+    pub interface: &'a str,
+    pub monitor: bool,
+    pub channel: Option<u32>,
+    pub watchdog: Option<u64>,
+    pub bounce: bool,
+    /// Hop across these channels, or all of them if empty
+    pub hop: Option<Vec<u32>>,
+    /// Milliseconds to stay on each channel while hopping
+    pub dwell: u64,
+    pub filter: Option<&'a str>
+}
+
+/// The analysis side of a capture helper process
+/// The helper keeps the privileges needed to capture while this process, which parses the frames, gives them up
+pub struct Helper {
+    child: Child,
+    records: Receiver<Result<Record>>,
+    /// Interface restarts performed by the helper's watchdog, not yet handled
+    pub restarts: Vec<io::Result<()>>,
+    /// The channel the helper last hopped to
+    pub channel: Option<u32>
+}
+impl Helper {
+    pub fn spawn(options: HelperOptions) -> Result<Self> {
+        let mut command = Command::new(env::current_exe().map_err(Error::Io)?);
+        command.arg("capture-helper").arg(options.interface);
+        if !options.monitor {
+            command.arg("--dont-monitor");
+        }
+        if let Some(channel) = options.channel {
+            command.arg("--channel").arg(channel.to_string());
+        }
+        command.arg("--watchdog").arg(options.watchdog.unwrap_or(0).to_string());
+        if options.bounce {
+            command.arg("--watchdog-bounce");
+        }
+        if let Some(filter) = options.filter {
+            command.arg("--bpf").arg(filter);
+        }
+        if let Some(channels) = &options.hop {
+            command.arg("--hop").arg("--dwell").arg(options.dwell.to_string());
+            if !channels.is_empty() {
+                let channels: Vec<_> = channels.iter().map(u32::to_string).collect();
+                command.arg("--hop-channels").arg(channels.join(","));
+            }
+        }
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(Error::Io)?;
+        let stdout = child.stdout.take().expect("Helper stdout is piped");
+        let mut stderr = child.stderr.take().expect("Helper stderr is piped");
+
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut input = BufReader::new(stdout);
+            loop {
+                let record = match read_record(&mut input) {
+                    Ok(Some(record)) => Ok(record),
+                    // The helper only exits early on failure, in which case it explains why on stderr
+                    Ok(None) => {
+                        let mut reason = String::new();
+                        let _ = stderr.read_to_string(&mut reason);
+                        Err(Error::Helper(reason.trim().to_string()))
+                    }
+                    Err(error) => Err(Error::Io(error))
+                };
+                let stop = record.is_err();
+                if tx.send(record).is_err() || stop {
+                    break
+                }
+            }
+        });
+        Ok(Self {
+            child,
+            records: rx,
+            restarts: vec![],
+            channel: None
+        })
+    }
+    fn next(&mut self) -> Result<Option<Frame>> {
+        loop {
+            match self.records.try_recv() {
+                Ok(Ok(Record::Frame(frame))) => return Ok(Some(frame)),
+                Ok(Ok(Record::Restarted(result))) => self.restarts.push(result),
+                Ok(Ok(Record::Channel(channel))) => self.channel = Some(channel),
+                Ok(Err(error)) => return Err(error),
+                Err(TryRecvError::Empty) => return Ok(None),
+                Err(TryRecvError::Disconnected) => return Err(Error::Helper("the capture helper exited".to_string()))
+            }
+        }
+    }
+}
+impl Drop for Helper {
+    fn drop(&mut self) {
+        // May fail if the helper runs as a more privileged user, in which case it notices our exit itself
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// A capture along with the watchdog and channel hopper looking after it, streamed by the capture helper and remote sensors
+pub struct Streamer<'a> {
+    options: HelperOptions<'a>,
+    capture: Capture<Active>,
+    watchdog: Watchdog,
+    hopper: Option<Hopper>
+}
+impl<'a> Streamer<'a> {
+    pub fn open(options: HelperOptions<'a>) -> Result<Self> {
+        let capture = open(options.interface, options.monitor, options.filter)?;
+        let watchdog = Watchdog::new(options.watchdog.map(Duration::from_secs).filter(|timeout| !timeout.is_zero()), options.bounce);
+        let hopper = match &options.hop {
+            Some(channels) => Some(Hopper::new(
+                options.interface,
+                Some(channels.as_slice()).filter(|channels| !channels.is_empty()),
+                Duration::from_millis(options.dwell)
+            ).map_err(|error| Error::Helper(format!("unable to hop channels: {}", error)))?),
+            None => None
+        };
+        Ok(Self {
+            options,
+            capture,
+            watchdog,
+            hopper
+        })
+    }
+    /// Give up every privilege except those needed to restart the interface or hop channels
+    pub fn drop_privileges(&self) -> Result<()> {
+        let keep: &[u32] = if self.watchdog.bounce {
+            &[privileges::CAP_NET_ADMIN, privileges::CAP_NET_RAW]
+        } else if self.hopper.is_some() {
+            &[privileges::CAP_NET_ADMIN]
+        } else {
+            &[]
+        };
+        privileges::drop(keep).map_err(Error::Io)
+    }
+    /// Write every frame to an output along with interface restarts and channel hops, only returning on failure
+    pub fn stream(&mut self, output: &mut impl Write) -> Result<()> {
+        let options = &self.options;
+        loop {
+            if let Some(up) = self.watchdog.restart(options.interface) {
+                if up {
+                    self.capture = open(options.interface, options.monitor, options.filter)?;
+                    if let Some(channel) = options.channel {
+                        let _ = nl80211::tune(options.interface, channel);
+                    }
+                }
+                write_restart(output, self.watchdog.bounce_error.clone()).map_err(Error::Io)?;
+            }
+            if let Some(hopper) = &mut self.hopper {
+                let channel = hopper.channel;
+                // A failed hop is tried again on the next channel
+                let _ = hopper.tick();
+                if let (Some(channel), true) = (hopper.channel, hopper.channel != channel) {
+                    write_channel(output, channel).map_err(Error::Io)?;
+                }
+            }
+            match self.capture.next() {
+                Err(pcap::Error::NoMorePackets) | Err(pcap::Error::TimeoutExpired) => thread::sleep(Duration::from_millis(1)),
+                Err(error) => return Err(Error::Next(error)),
+                Ok(packet) => {
+                    self.watchdog.frame();
+                    write_frame(output, &packet).map_err(Error::Io)?
+                }
+            }
+        }
+    }
+}
+
+/// The body of the capture helper process: capture on an interface and stream every frame to stdout
+/// Only returns on failure, as the helper is killed along with its parent
+pub fn helper(options: HelperOptions) -> Result<()> {
+    let mut streamer = Streamer::open(options)?;
+    streamer.drop_privileges()?;
+    // Changing credentials resets the parent death signal, so it must be requested afterwards
+    unsafe { libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL as libc::c_ulong, 0 as libc::c_ulong, 0 as libc::c_ulong, 0 as libc::c_ulong); }
+
+    let stdout = io::stdout();
+    streamer.stream(&mut BufWriter::new(stdout.lock()))
+}
+
+type Result<T> = std::result::Result<T, Error>;
+#[derive(Debug)]
+pub enum Error {
+    Open(pcap::Error),
+    Activate(pcap::Error),
+    NonBlocking(pcap::Error),
+    Datalink(pcap::Error),
+    NoRadiotap,
+    Filter(pcap::Error),
+    Next(pcap::Error),
+    Inject(pcap::Error),
+    /// Frames can only be sent through a capture opened by this process
+    CannotInject,
+    Io(io::Error),
+    Helper(String),
+    /// A remote sensor closed the connection
+    Disconnected
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Open(error) => write!(f, "unable to open capture device: {}", error),
+            Self::Activate(error) => write!(f, "unable to start listening on capture device: {}", error),
+            Self::NonBlocking(error) => write!(f, "unable to capture packets in a non-blocking fashion: {}", error),
+            Self::Datalink(error) => write!(f, "unable to set the datalink layer: {}", error),
+            Self::NoRadiotap => write!(f, "the interface does not support the radiotap datalink layer required by this program"),
+            Self::Filter(error) => write!(f, "invalid capture filter: {}", error),
+            Self::Next(error) => write!(f, "unable to get next packet: {}", error),
+            Self::Inject(error) => write!(f, "unable to send a frame: {}", error),
+            Self::CannotInject => write!(f, "frames can only be sent from an interface captured on directly, not through --privsep, a remote sensor or a replay"),
+            Self::Io(error) => write!(f, "{}", error),
+            Self::Helper(reason) if reason.is_empty() => write!(f, "the capture helper exited unexpectedly"),
+            Self::Helper(reason) => write!(f, "capture helper: {}", reason),
+            Self::Disconnected => write!(f, "the sensor closed the connection")
+        }
+    }
+}
@@ -0,0 +1,836 @@
+use std::{collections::HashMap, fmt, path::{Path, PathBuf}};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use eui48::MacAddress;
+
+use crate::{alerts, capture, control, diff, evidence, interfaces, manuf, policy::Policy, reanalyse, remote, selftest};
+
+/// Every option and subcommand, which the config file supplies defaults for by parsing again with them added
+pub fn app() -> App<'static, 'static> {
+    App::new("Blockade Recon 2")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about(env!("CARGO_PKG_DESCRIPTION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .arg(
+            Arg::with_name("interface")
+                .short("i")
+                .long("interface")
+                .help("Sniff on these wireless interfaces rather than a default one, or pick one from a list if none are named")
+                .value_name("INTERFACE")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .min_values(0)
+        )
+        .arg(
+            Arg::with_name("dont_monitor")
+                .short("m")
+                .long("dont-monitor")
+                .help("Don't try entering monitor mode using libpcap")
+        )
+        .arg(
+            Arg::with_name("auto_monitor")
+                .long("auto-monitor")
+                .help("Put the interfaces into monitor mode before capturing, with nl80211 or else iw, and back as they were on exit")
+                .conflicts_with("dont_monitor")
+        )
+        .arg(
+            Arg::with_name("channel")
+                .short("c")
+                .long("channel")
+                .help("Tune the interface to a channel, checking that the adapter supports it; given again, tunes each further interface in turn")
+                .value_name("CHANNEL")
+                .multiple(true)
+                .number_of_values(1)
+        )
+        .arg(
+            Arg::with_name("hop")
+                .long("hop")
+                .help("Hop across every usable 2.4GHz and 5GHz channel, starting locked to --channel if given")
+        )
+        .arg(
+            Arg::with_name("hop_channels")
+                .long("hop-channels")
+                .help("Hop across only these channels, eg. 1,6,11")
+                .value_name("CHANNELS")
+        )
+        .arg(
+            Arg::with_name("region")
+                .long("region")
+                .help("Only tune to channels permitted in this country, eg. GB, hopping across those the radio also supports")
+                .value_name("COUNTRY")
+        )
+        .arg(
+            Arg::with_name("no_dfs")
+                .long("no-dfs")
+                .help("Leave out the region's channels that need DFS, which radar has priority on")
+                .requires("region")
+        )
+        .arg(
+            Arg::with_name("dwell")
+                .long("dwell")
+                .help("How long to stay on each channel while hopping [default: 250]")
+                .value_name("MILLISECONDS")
+        )
+        .arg(
+            Arg::with_name("watchdog")
+                .long("watchdog")
+                .help("Warn when no frames arrive for this many seconds, or 0 to disable [default: 30]")
+                .value_name("SECONDS")
+        )
+        .arg(
+            Arg::with_name("watchdog_bounce")
+                .long("watchdog-bounce")
+                .help("Restart the interface when the watchdog notices the capture has gone quiet")
+        )
+        .arg(
+            Arg::with_name("keep_privileges")
+                .long("keep-privileges")
+                .help("Don't drop root privileges and capabilities once the capture is open")
+        )
+        .arg(
+            Arg::with_name("privsep")
+                .long("privsep")
+                .help("Capture in a separate privileged helper process so frames are parsed without privileges")
+        )
+        .arg(
+            Arg::with_name("active")
+                .long("active")
+                .help("Transmit directed probe requests from the interface, so hidden networks give their names sooner; this is no longer passive and can be noticed")
+                .conflicts_with_all(&["privsep", "replay", "read"])
+        )
+        .arg(
+            Arg::with_name("probe_ssid")
+                .long("probe-ssid")
+                .help("With --active, also ask every access point in range for this network, and try it on hidden ones")
+                .value_name("SSID")
+                .multiple(true)
+                .number_of_values(1)
+                .requires("active")
+        )
+        .arg(
+            Arg::with_name("connect")
+                .long("connect")
+                .help("Also listen to a remote sensor started with the sensor subcommand, eg. pi.local:4411, in place of a local interface if none is named")
+                .value_name("HOST:PORT")
+                .multiple(true)
+                .number_of_values(1)
+        )
+        .arg(
+            Arg::with_name("session")
+                .long("session")
+                .help("Record every frame taken in, with when it came, the channel and the GPS position, to a file for --replay")
+                .value_name("FILE")
+        )
+        .arg(
+            Arg::with_name("replay")
+                .long("replay")
+                .help("Play back a file recorded with --session through the interface, in place of capturing")
+                .value_name("FILE")
+                .conflicts_with_all(&["interface", "connect"])
+        )
+        .arg(
+            Arg::with_name("read")
+                .long("read")
+                .help("Take in the frames of a pcap file with radiotap headers, saved here or anywhere else, in place of capturing")
+                .value_name("FILE")
+                .conflicts_with_all(&["interface", "connect", "replay"])
+        )
+        .arg(
+            Arg::with_name("speed")
+                .long("speed")
+                .help("How many times faster than it was recorded to play back a session [default: 1], or a file given with --read, which is otherwise taken in as fast as it can be")
+                .value_name("TIMES")
+        )
+        .arg(
+            Arg::with_name("connect_config")
+                .long("connect-config")
+                .help("Read the token to present to remote sensors, and the certificate authority to check their TLS certificates against, from a file")
+                .value_name("FILE")
+        )
+        .arg(
+            Arg::with_name("no_sandbox")
+                .long("no-sandbox")
+                .help("Don't restrict network and filesystem access of the thread parsing frames, which is all that is sandboxed")
+        )
+        .arg(
+            Arg::with_name("survey")
+                .long("survey")
+                .help("Name the survey or site this session belongs to")
+                .value_name("NAME")
+        )
+        .arg(
+            Arg::with_name("operator")
+                .long("operator")
+                .help("Record who is running the capture")
+                .value_name("NAME")
+        )
+        .arg(
+            Arg::with_name("location")
+                .long("location")
+                .help("Describe where the capture is taking place")
+                .value_name("NOTE")
+        )
+        .arg(
+            Arg::with_name("vendor_stats")
+                .long("vendor-stats")
+                .help("On exit, write how many devices of each manufacturer were seen over time, as CSV or JSON by extension")
+                .value_name("FILE")
+        )
+        .arg(
+            Arg::with_name("vendor_interval")
+                .long("vendor-interval")
+                .help("The length of each time bucket for --vendor-stats [default: 15]")
+                .value_name("MINUTES")
+        )
+        .arg(
+            Arg::with_name("export")
+                .long("export")
+                .help("Write every device seen to a file on exit and when e is pressed, as CSV or JSON by extension [default: devices.csv on e]")
+                .value_name("FILE")
+        )
+        .arg(
+            Arg::with_name("findings")
+                .long("findings")
+                .help("Write every alert as a finding with its evidence on exit and when e is pressed, as SARIF, or as JSON for DefectDojo or Dradis by extension")
+                .value_name("FILE")
+        )
+        .arg(
+            Arg::with_name("bpf")
+                .long("bpf")
+                .help("Only capture frames matching a BPF filter, eg. `type mgt subtype probe-req`, lightening the load before anything is parsed")
+                .value_name("FILTER")
+        )
+        .arg(
+            Arg::with_name("filter")
+                .long("filter")
+                .help("Only show frames matching a display filter in the packet log, eg. `subtype == probe-req && signal > -60`")
+                .value_name("FILTER")
+        )
+        .arg(
+            Arg::with_name("ingest_filter")
+                .long("ingest-filter")
+                .help("Only track devices from frames matching a display filter")
+                .value_name("FILTER")
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .help("Save captured frames to this pcap file, or a pcapng file commented with what is known of each frame if it ends in .pcapng [default: capture.pcap]")
+                .value_name("FILE")
+        )
+        .arg(
+            Arg::with_name("no_save")
+                .long("no-save")
+                .help("Don't save captured frames at all")
+                .conflicts_with_all(&["output", "handshakes", "rotate_size", "rotate_interval"])
+        )
+        .arg(
+            Arg::with_name("handshakes")
+                .long("handshakes")
+                .help("Save WPA handshakes to this file [default: handshakes.pcap beside the save file]")
+                .value_name("FILE")
+        )
+        .arg(
+            Arg::with_name("anonymize")
+                .long("anonymize")
+                .help("Replace addresses, keeping the manufacturer part, and network names consistently in exports and the save file, which then keeps no payloads")
+                .conflicts_with_all(&["handshakes", "incidents", "session"])
+        )
+        .arg(
+            Arg::with_name("rotate_size")
+                .long("rotate-size")
+                .help("Start a new numbered save file once the current one reaches this size, eg. 100M")
+                .value_name("SIZE")
+        )
+        .arg(
+            Arg::with_name("rotate_interval")
+                .long("rotate-interval")
+                .help("Start a new numbered save file after this many minutes")
+                .value_name("MINUTES")
+        )
+        .arg(
+            Arg::with_name("start_at")
+                .long("start-at")
+                .help("Wait until this time of day, eg. 22:30, or Unix time before starting to capture")
+                .value_name("TIME")
+        )
+        .arg(
+            Arg::with_name("duration")
+                .long("duration")
+                .help("Stop after capturing for this many minutes, writing exports as on any other exit")
+                .value_name("MINUTES")
+        )
+        .arg(
+            Arg::with_name("segment")
+                .long("segment")
+                .help("Every this many minutes, start afresh with a new save file and exports, each named for the Unix time its segment started")
+                .value_name("MINUTES")
+                .conflicts_with_all(&["rotate_size", "rotate_interval"])
+        )
+        .arg(
+            Arg::with_name("alert_severity")
+                .long("alert-severity")
+                .help("Change how severe a kind of alert is, eg. repeater=critical; kinds are repeater, hidden-ssid, odd-rates, policy, spoofed-bssid, evil-twin, beacon-change, karma, watchlist and deauth, severities info, warn and critical")
+                .value_name("KIND=SEVERITY")
+                .multiple(true)
+                .number_of_values(1)
+        )
+        .arg(
+            Arg::with_name("alert_escalate")
+                .long("alert-escalate")
+                .help("Make alerts of a kind critical once so many are raised within a number of minutes, eg. odd-rates=3/10")
+                .value_name("KIND=COUNT/MINUTES")
+                .multiple(true)
+                .number_of_values(1)
+        )
+        .arg(
+            Arg::with_name("deauth_threshold")
+                .long("deauth-threshold")
+                .help("Take this many deauthentications and disassociations to one client of a network within ten seconds as a flood [default: 20]")
+                .value_name("FRAMES")
+        )
+        .arg(
+            Arg::with_name("alert_log")
+                .long("alert-log")
+                .help("Append a line to this file for each alert raised")
+                .value_name("FILE")
+        )
+        .arg(
+            Arg::with_name("smtp")
+                .long("smtp")
+                .help("Mail digests of critical alerts through this mail server, eg. localhost:25; no TLS or authentication, so use a local relay")
+                .value_name("HOST:PORT")
+                .requires_all(&["email_from", "email_to"])
+        )
+        .arg(
+            Arg::with_name("email_from")
+                .long("email-from")
+                .help("The address alert digests are mailed from")
+                .value_name("ADDRESS")
+                .requires("smtp")
+        )
+        .arg(
+            Arg::with_name("email_to")
+                .long("email-to")
+                .help("An address to mail alert digests to")
+                .value_name("ADDRESS")
+                .multiple(true)
+                .number_of_values(1)
+                .requires("smtp")
+        )
+        .arg(
+            Arg::with_name("email_interval")
+                .long("email-interval")
+                .help("Gather critical alerts for this many minutes before mailing them together [default: 10]")
+                .value_name("MINUTES")
+                .requires("smtp")
+        )
+        .arg(
+            Arg::with_name("alert_command")
+                .long("alert-command")
+                .help("Run this shell command for every alert raised, with the alert as JSON on its standard input")
+                .value_name("COMMAND")
+        )
+        .arg(
+            Arg::with_name("alert_command_timeout")
+                .long("alert-command-timeout")
+                .help("Kill the alert command if it runs for longer than this many seconds [default: 10]")
+                .value_name("SECONDS")
+                .requires("alert_command")
+        )
+        .arg(
+            Arg::with_name("alert_command_interval")
+                .long("alert-command-interval")
+                .help("Skip alerts raised within this many seconds of the alert command last running [default: 5]")
+                .value_name("SECONDS")
+                .requires("alert_command")
+        )
+        .arg(
+            Arg::with_name("webhook")
+                .long("webhook")
+                .help("POST new devices, captured handshakes and alerts to this URL as JSON, one event at a time")
+                .value_name("URL")
+                .multiple(true)
+                .number_of_values(1)
+        )
+        .arg(
+            Arg::with_name("mqtt")
+                .long("mqtt")
+                .help("Publish new devices, captured handshakes and alerts as JSON to a topic on an MQTT broker, eg. localhost:1883/blockade-recon")
+                .value_name("BROKER/TOPIC")
+                .multiple(true)
+                .number_of_values(1)
+        )
+        .arg(
+            Arg::with_name("ring")
+                .long("ring")
+                .help("Keep the last this many seconds of frames in memory, saved to a file beside the save file when D is pressed")
+                .value_name("SECONDS")
+        )
+        .arg(
+            Arg::with_name("ring_size")
+                .long("ring-size")
+                .help("Keep at most this many bytes of frames in memory for D, optionally followed by K, M or G [default: 512M]")
+                .value_name("SIZE")
+        )
+        .arg(
+            Arg::with_name("incidents")
+                .long("incidents")
+                .help("Save the frames around every critical alert to an incident file beside the save file")
+        )
+        .arg(
+            Arg::with_name("incident_before")
+                .long("incident-before")
+                .help("Keep this many seconds of frames from before a critical alert in its incident file [default: 30]")
+                .value_name("SECONDS")
+                .requires("incidents")
+        )
+        .arg(
+            Arg::with_name("incident_after")
+                .long("incident-after")
+                .help("Carry on writing the incident file for this many seconds after a critical alert [default: 30]")
+                .value_name("SECONDS")
+                .requires("incidents")
+        )
+        .arg(
+            Arg::with_name("gps")
+                .long("gps")
+                .alias("gpsd")
+                .help("Follow the sensor's position from gpsd, eg. localhost:2947, or a serial receiver sending NMEA, eg. /dev/ttyACM0, to map where devices were heard")
+                .value_name("HOST:PORT|DEVICE")
+        )
+        .arg(
+            Arg::with_name("gps_baud")
+                .long("gps-baud")
+                .help("Read the serial GPS receiver at this speed, one of 4800, 9600, 19200, 38400, 57600 or 115200, rather than as the device is already set")
+                .value_name("BAUD")
+                .requires("gps")
+        )
+        .arg(
+            Arg::with_name("bluetooth")
+                .long("bluetooth")
+                .help("Also scan for Bluetooth Classic and low energy devices through BlueZ, listed on a tab of their own")
+        )
+        .arg(
+            Arg::with_name("bluetooth_adapter")
+                .long("bluetooth-adapter")
+                .help("The Bluetooth adapter to scan with, eg. hci1, instead of the default")
+                .value_name("ADAPTER")
+                .requires("bluetooth")
+        )
+        .arg(
+            Arg::with_name("geo_export")
+                .long("geo-export")
+                .help("Write where each device was heard to a file on exit and when g is pressed, as KML or GeoJSON by extension [default: devices.geojson on g]")
+                .value_name("FILE")
+                .requires("gps")
+        )
+        .arg(
+            Arg::with_name("wardrive")
+                .long("wardrive")
+                .help("Write every access point to a file on exit and when e is pressed, for WiGLE as CSV, or as Kismet netxml by extension")
+                .value_name("FILE")
+        )
+        .arg(
+            Arg::with_name("max_devices")
+                .long("max-devices")
+                .help("Keep at most this many devices, forgetting those heard least recently to make room, to bound memory on long or busy captures, from 100 up")
+                .value_name("COUNT")
+        )
+        .arg(
+            Arg::with_name("min_rssi")
+                .long("min-rssi")
+                .help("Only add devices heard at or above this signal in dBm, eg. -80, keeping those already tracked however weak they get")
+                .value_name("DBM")
+                .allow_hyphen_values(true)
+        )
+        .arg(
+            Arg::with_name("collapse_beacons")
+                .long("collapse-beacons")
+                .help("Only count beacons unchanged since the access point's last, taking one in whole every 10 seconds")
+        )
+        .arg(
+            Arg::with_name("ignore_acked")
+                .long("ignore-acked")
+                .help("Leave out devices only heard of through the acknowledgements sent to them")
+        )
+        .arg(
+            Arg::with_name("fps")
+                .long("fps")
+                .help("Redraw the interface at most this many times a second, however fast frames arrive [default: 20]")
+                .value_name("FRAMES")
+        )
+        .arg(
+            Arg::with_name("active_window")
+                .long("active-window")
+                .help("Dim devices not heard for this many seconds, and hide them while A is toggled on the Devices tab [default: 300]")
+                .value_name("SECONDS")
+        )
+        .arg(
+            Arg::with_name("pause_mode")
+                .long("pause-mode")
+                .help("Whether frames arriving while paused with space are held until resuming or thrown away [default: buffer]")
+                .possible_values(&["buffer", "discard"])
+                .value_name("MODE")
+        )
+        .arg(
+            Arg::with_name("sample_above")
+                .long("sample-above")
+                .help("Above this many frames a second, handle only one in every --sample-every data frames, or 0 to handle every frame [default: 5000]")
+                .value_name("FRAMES")
+        )
+        .arg(
+            Arg::with_name("sample_every")
+                .long("sample-every")
+                .help("How many data frames to count for each one handled while sampling [default: 10]")
+                .value_name("N")
+        )
+        .arg(
+            Arg::with_name("headless")
+                .long("headless")
+                .help("Run without the interface, writing new devices, networks and alerts to stdout as newline delimited JSON, or to --events")
+        )
+        .arg(
+            Arg::with_name("events")
+                .long("events")
+                .help("Write new devices, networks and alerts to this file as newline delimited JSON")
+                .value_name("FILE")
+        )
+        .arg(
+            Arg::with_name("store")
+                .long("store")
+                .help("Keep every device in a store as it changes: memory, jsonl:PATH, or sqlite:PATH when built with the sqlite feature")
+                .value_name("STORE")
+        )
+        .arg(
+            Arg::with_name("profile")
+                .long("profile")
+                .help("Time each stage of handling frames, from capture to drawing, and show the time spent per second on the Stats tab")
+        )
+        .arg(
+            Arg::with_name("no_state")
+                .long("no-state")
+                .help("Start with the default layout and don't remember it for next time")
+        )
+        .arg(
+            Arg::with_name("control")
+                .long("control")
+                .help("Accept remote control requests over HTTP on this address, eg. 127.0.0.1:8080")
+                .value_name("ADDRESS")
+        )
+        .arg(
+            Arg::with_name("control_config")
+                .long("control-config")
+                .help("Read the token required of remote control requests, and a certificate and key to serve HTTPS with, from a file")
+                .value_name("FILE")
+        )
+        .arg(
+            Arg::with_name("metrics_listen")
+                .long("metrics-listen")
+                .help("Serve counters for Prometheus to scrape at /metrics on this address, eg. 0.0.0.0:9184, when built with the metrics feature")
+                .value_name("ADDRESS")
+        )
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .help("Read default options, key bindings and colours from this file rather than ~/.config/blockade-recon/config.toml")
+                .value_name("FILE")
+        )
+        .arg(
+            Arg::with_name("database")
+                .short("-d")
+                .long("database")
+                .visible_alias("oui-db")
+                .help("Specify the path to the OUI database file, in Wireshark's manuf format")
+                .value_name("FILE")
+        )
+        .subcommand(
+            SubCommand::with_name("interfaces")
+                .about("List wireless interfaces and the capabilities of their adapters")
+        )
+        .subcommand(
+            SubCommand::with_name("update-oui")
+                .about("Download the latest OUI database, used from then on unless --oui-db is given")
+        )
+        .subcommand(
+            SubCommand::with_name("reanalyse")
+                .visible_alias("reanalyze")
+                .about("Run saved captures back through the current detectors and list what they raise")
+                .arg(
+                    Arg::with_name("session")
+                        .help("A capture, or a folder of rotated captures, each replayed as a session of its own")
+                        .value_name("SESSION")
+                        .multiple(true)
+                        .required(true)
+                )
+                .arg(
+                    Arg::with_name("known")
+                        .long("known")
+                        .help("Only list alerts missing from this events file, as written by --events or --headless at the time")
+                        .value_name("FILE")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("extract")
+                .about("Copy the frames naming one device or network out of saved captures into a pcap of their own, to share without the rest")
+                .arg(
+                    Arg::with_name("address")
+                        .help("The MAC address of the device, or the BSSID of the network")
+                        .value_name("ADDRESS")
+                        .required(true)
+                )
+                .arg(
+                    Arg::with_name("session")
+                        .help("A capture, or a folder of rotated captures")
+                        .value_name("SESSION")
+                        .multiple(true)
+                        .required(true)
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .short("o")
+                        .help("Where to write the frames [default: <address>.pcap]")
+                        .value_name("FILE")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("diff")
+                .about("List the devices and SSIDs that appeared, disappeared or changed between two surveys")
+                .arg(
+                    Arg::with_name("before")
+                        .help("The earlier survey, a JSON export or a JSONL or SQLite device store")
+                        .value_name("BEFORE")
+                        .required(true)
+                )
+                .arg(
+                    Arg::with_name("after")
+                        .help("The later survey, in any of the same formats")
+                        .value_name("AFTER")
+                        .required(true)
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("Print the differences as JSON")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("selftest")
+                .about("Run a built in sample capture through the parser, detectors and exporters, reporting whether each works")
+        )
+        .subcommand(
+            SubCommand::with_name("sensor")
+                .about("Capture on an interface and stream frames to whoever connects with --connect, for sensors away from the display")
+                .arg(Arg::with_name("interface").help("The wireless interface to capture on").required(true))
+                .arg(
+                    Arg::with_name("listen")
+                        .long("listen")
+                        .help("Accept a connection on this address, eg. 0.0.0.0:4411")
+                        .value_name("ADDRESS")
+                        .required(true)
+                )
+                .arg(
+                    Arg::with_name("sensor_config")
+                        .long("sensor-config")
+                        .help("Read the token required of clients, and a certificate and key to serve TLS with, from a file")
+                        .value_name("FILE")
+                )
+                .arg(Arg::with_name("dont_monitor").long("dont-monitor").help("Don't attempt to put the interface into monitor mode"))
+                .arg(Arg::with_name("channel").long("channel").help("Tune the interface to a channel").value_name("CHANNEL"))
+                .arg(Arg::with_name("hop").long("hop").help("Hop across every usable 2.4GHz and 5GHz channel"))
+                .arg(Arg::with_name("hop_channels").long("hop-channels").help("Hop across only these channels, eg. 1,6,11").value_name("CHANNELS"))
+                .arg(Arg::with_name("dwell").long("dwell").help("How long to stay on each channel while hopping [default: 250]").value_name("MILLISECONDS"))
+                .arg(Arg::with_name("watchdog").long("watchdog").help("Restart the interface after this many seconds without frames, with --watchdog-bounce").value_name("SECONDS"))
+                .arg(Arg::with_name("watchdog_bounce").long("watchdog-bounce").help("Restart the interface when the capture goes quiet"))
+                .arg(Arg::with_name("bpf").long("bpf").help("Only capture frames matching a BPF filter").value_name("FILTER"))
+        )
+        .subcommand(
+            SubCommand::with_name("capture-helper")
+                .setting(AppSettings::Hidden)
+                .about("Capture on an interface and stream frames to stdout, used by --privsep")
+                .arg(Arg::with_name("interface").required(true))
+                .arg(Arg::with_name("dont_monitor").long("dont-monitor"))
+                .arg(Arg::with_name("channel").long("channel").takes_value(true))
+                .arg(Arg::with_name("watchdog").long("watchdog").takes_value(true))
+                .arg(Arg::with_name("watchdog_bounce").long("watchdog-bounce"))
+                .arg(Arg::with_name("hop").long("hop"))
+                .arg(Arg::with_name("hop_channels").long("hop-channels").takes_value(true))
+                .arg(Arg::with_name("dwell").long("dwell").takes_value(true))
+                .arg(Arg::with_name("bpf").long("bpf").takes_value(true))
+        )
+}
+
+/// Run a subcommand that works without the terminal interface, giving whether there was one
+pub fn subcommand(args: &ArgMatches, policies: &[Policy]) -> bool {
+    if args.subcommand_matches("interfaces").is_some() {
+        if let Err(error) = interfaces::print() {
+            eprintln!("Unable to list wireless interfaces: {}", error);
+            std::process::exit(1)
+        }
+        return true
+    }
+    if args.subcommand_matches("update-oui").is_some() {
+        match manuf::update() {
+            Ok(path) => println!("Saved the latest OUI database to {}", path.display()),
+            Err(error) => {
+                eprintln!("Unable to update the OUI database: {}", error);
+                std::process::exit(1)
+            }
+        }
+        return true
+    }
+    if let Some(reanalyse_args) = args.subcommand_matches("reanalyse") {
+        let fail = |message: &str, error: &dyn std::fmt::Display| -> ! {
+            eprintln!("{}: {}", message, error);
+            std::process::exit(1)
+        };
+        let known = reanalyse_args.value_of("known").map(|path| reanalyse::known(Path::new(path)).unwrap_or_else(|error| fail("Unable to read the events file", &error)));
+        let oui_db = manuf::load_default().unwrap_or_else(|error| fail("Unable to load the default OUI database", &error));
+        let sessions: Vec<_> = reanalyse_args.values_of("session").into_iter().flatten().map(PathBuf::from).collect();
+        let alerts = alerts::Alerts::new(HashMap::new(), HashMap::new(), policies.to_vec());
+        let findings = reanalyse::replay(&sessions, &oui_db, alerts).unwrap_or_else(|error| fail("Unable to replay the session", &error));
+        let total = findings.len();
+        let mut new = 0;
+        for (session, alert) in findings {
+            if known.as_ref().map_or(false, |known| known.contains(&alert.key())) {
+                continue
+            }
+            new += 1;
+            println!("{}\t{:.3}\t{}\t{}\t{}\t{}", session.display(), alert.time.as_secs_f64(), alert.severity, alert.kind.name(), alert.address.to_hex_string(), alert.message);
+        }
+        if known.is_some() {
+            eprintln!("{} alert(s) raised, {} of them new", total, new);
+        }
+        return true
+    }
+    if let Some(extract_args) = args.subcommand_matches("extract") {
+        let fail = |message: &str, error: &dyn std::fmt::Display| -> ! {
+            eprintln!("{}: {}", message, error);
+            std::process::exit(1)
+        };
+        let address = extract_args.value_of("address").unwrap();
+        let address = MacAddress::parse_str(address).unwrap_or_else(|_| fail("Not a MAC address", &address));
+        let mut captures = vec![];
+        for session in extract_args.values_of("session").into_iter().flatten() {
+            captures.extend(reanalyse::captures(Path::new(session)).unwrap_or_else(|error| fail("Unable to read the session", &error)));
+        }
+        let output = extract_args.value_of("output").map_or_else(|| PathBuf::from(format!("{}.pcap", address.to_hex_string().replace(':', "-"))), PathBuf::from);
+        let copied = evidence::extract(address, &captures, &output).unwrap_or_else(|error| fail("Unable to extract the frames", &error));
+        eprintln!("Copied {} frame(s) naming {} to {}", copied, address.to_hex_string(), output.display());
+        return true
+    }
+    if let Some(diff_args) = args.subcommand_matches("diff") {
+        let load = |name| {
+            let path = Path::new(diff_args.value_of(name).unwrap());
+            diff::load(path).unwrap_or_else(|error| {
+                eprintln!("Unable to read {}: {}", path.display(), error);
+                std::process::exit(1)
+            })
+        };
+        let diff = diff::Diff::between(&load("before"), &load("after"));
+        if diff_args.is_present("json") {
+            println!("{}", diff.json());
+        } else {
+            print!("{}", diff);
+        }
+        return true
+    }
+    if args.subcommand_matches("selftest").is_some() {
+        let checks = selftest::run();
+        for check in checks.iter() {
+            match &check.result {
+                Ok(detail) => println!("PASS  {:<16} {}", check.component, detail),
+                Err(problem) => println!("FAIL  {:<16} {}", check.component, problem)
+            }
+        }
+        if checks.iter().any(|check| check.result.is_err()) {
+            std::process::exit(1)
+        }
+        return true
+    }
+    if let Some(sensor_args) = args.subcommand_matches("sensor") {
+        let config = match sensor_args.value_of("sensor_config").map(control::Config::load).transpose() {
+            Ok(config) => config.unwrap_or_default(),
+            Err(error) => {
+                eprintln!("Unable to load the sensor configuration: {}", error);
+                std::process::exit(1)
+            }
+        };
+        fn number<T: std::str::FromStr>(value: Option<&str>, meaning: &str) -> Option<T> {
+            value.map(|value| value.parse().unwrap_or_else(|_| {
+                eprintln!("{} is not {}", value, meaning);
+                std::process::exit(1)
+            }))
+        }
+        let hop = sensor_args.value_of("hop_channels").map(|channels| parse_channels(channels).unwrap_or_else(|_| {
+            eprintln!("The channels to hop across are not a list of numbers");
+            std::process::exit(1)
+        }));
+        let options = capture::HelperOptions {
+            interface: sensor_args.value_of("interface").unwrap_or_default(),
+            monitor: !sensor_args.is_present("dont_monitor"),
+            channel: number(sensor_args.value_of("channel"), "a channel number"),
+            watchdog: number(sensor_args.value_of("watchdog"), "a number of seconds"),
+            bounce: sensor_args.is_present("watchdog_bounce"),
+            hop: hop.or_else(|| Some(vec![]).filter(|_| sensor_args.is_present("hop"))),
+            dwell: number(sensor_args.value_of("dwell"), "a number of milliseconds").unwrap_or(250),
+            filter: sensor_args.value_of("bpf")
+        };
+        if let Err(error) = remote::serve(sensor_args.value_of("listen").unwrap_or_default(), config, options) {
+            eprintln!("{}", error);
+            std::process::exit(1)
+        }
+        return true
+    }
+    if let Some(helper_args) = args.subcommand_matches("capture-helper") {
+        let options = capture::HelperOptions {
+            interface: helper_args.value_of("interface").unwrap_or_default(),
+            monitor: !helper_args.is_present("dont_monitor"),
+            channel: helper_args.value_of("channel").and_then(|channel| channel.parse().ok()),
+            watchdog: helper_args.value_of("watchdog").and_then(|seconds| seconds.parse().ok()),
+            bounce: helper_args.is_present("watchdog_bounce"),
+            hop: Some(helper_args.value_of("hop_channels").and_then(|channels| parse_channels(channels).ok()).unwrap_or_default())
+                .filter(|_| helper_args.is_present("hop")),
+            dwell: helper_args.value_of("dwell").and_then(|dwell| dwell.parse().ok()).unwrap_or(250),
+            filter: helper_args.value_of("bpf")
+        };
+        if let Err(error) = capture::helper(options) {
+            eprintln!("{}", error);
+            std::process::exit(1)
+        }
+        return true
+    }
+    false
+}
+
+/// Something asked for on the command line that couldn't be done, and why, for the interface to report
+#[derive(Debug)]
+pub struct Failure {
+    pub message: String,
+    pub reason: String
+}
+impl Failure {
+    pub fn new(message: impl Into<String>, reason: impl fmt::Display) -> Self {
+        Self {
+            message: message.into(),
+            reason: reason.to_string()
+        }
+    }
+}
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.message, self.reason)
+    }
+}
+
+/// Describe an error as a failure at what was being done, for `map_err`
+pub fn failed<E: fmt::Display>(message: &'static str) -> impl Fn(E) -> Failure {
+    move |error| Failure::new(message, error)
+}
+
+/// Parse a comma separated list of channels
+pub fn parse_channels(channels: &str) -> Result<Vec<u32>, std::num::ParseIntError> {
+    channels.split(',').map(|channel| channel.trim().parse()).collect()
+}
@@ -0,0 +1,128 @@
+use std::{collections::{BTreeSet, HashSet}, time::Duration};
+use eui48::MacAddress;
+
+use crate::{DeviceList, KnownDevice, fingerprint};
+
+/// How long a device may go quiet before a new address is no longer taken to be it
+const GAP: Duration = Duration::from_secs(300);
+
+/// Groups of randomised addresses that most likely belong to one device, each in the order they were first heard
+/// Phones pick a new local address every so often while probing, which would otherwise count them many times over,
+/// but the layout of their probe requests, their rates and the networks they ask for stay the same
+/// Addresses merged or split by hand are grouped as the operator said, whatever they have in common
+pub fn probable_devices(devices: &DeviceList) -> Vec<Vec<MacAddress>> {
+    let overrides = devices.overrides();
+    let overrides = overrides.borrow();
+    let mut candidates: Vec<_> = devices.iter()
+        .filter(|(address, device)| device.sent && device.probing && address.is_local() && device.probe_layout.is_some())
+        .filter(|(address, _)| !overrides.apart.contains(address))
+        .collect();
+    candidates.sort_by_key(|(address, device)| (device.first_seen, **address));
+
+    let mut clusters: Vec<Vec<(&MacAddress, &KnownDevice)>> = vec![];
+    for (address, device) in candidates {
+        let cluster = clusters.iter_mut().find(|cluster| {
+            let (_, last) = cluster.last().unwrap(/* Never empty */);
+            let latest = cluster.iter().filter_map(|(_, member)| member.last_seen).max();
+            last.probe_layout == device.probe_layout
+                && last.rates.as_deref().map(fingerprint::rate_set) == device.rates.as_deref().map(fingerprint::rate_set)
+                && same_networks(last, device)
+                && match (latest, device.first_seen) {
+                    (Some(latest), Some(first_seen)) => first_seen <= latest + GAP,
+                    _ => false
+                }
+        });
+        match cluster {
+            Some(cluster) => cluster.push((address, device)),
+            None => clusters.push(vec![(address, device)])
+        }
+    }
+
+    let mut clusters: Vec<BTreeSet<MacAddress>> = clusters.into_iter()
+        .map(|cluster| cluster.into_iter().map(|(&address, _)| address).collect())
+        .collect();
+    // A merge takes in every address already grouped with either side
+    for merged in overrides.merged.iter() {
+        let mut group: BTreeSet<_> = merged.iter().filter(|address| devices.contains_key(address)).copied().collect();
+        clusters.retain(|cluster| if cluster.is_disjoint(&group) {
+            true
+        } else {
+            group.extend(cluster);
+            false
+        });
+        clusters.push(group);
+    }
+
+    let mut clusters: Vec<Vec<MacAddress>> = clusters.into_iter()
+        .filter(|cluster| cluster.len() > 1)
+        .map(|cluster| {
+            let mut cluster: Vec<_> = cluster.into_iter().collect();
+            cluster.sort_by_key(|address| (devices[address].first_seen, *address));
+            cluster
+        })
+        .collect();
+    clusters.sort();
+    clusters
+}
+
+/// The operator's own say on which addresses belong to one device, such as a phone's randomised address and its real one,
+/// or addresses wrongly taken for one device
+#[derive(Debug, Default)]
+pub struct Overrides {
+    /// Groups of addresses merged by hand
+    merged: Vec<BTreeSet<MacAddress>>,
+    /// Addresses split off from whatever they would otherwise be grouped with
+    apart: BTreeSet<MacAddress>
+}
+impl Overrides {
+    /// Take two addresses to be one device, along with anything either was merged with before
+    pub fn merge(&mut self, l: MacAddress, r: MacAddress) {
+        self.apart.remove(&l);
+        self.apart.remove(&r);
+        let mut group: BTreeSet<_> = [l, r].iter().copied().collect();
+        self.merged.retain(|merged| if merged.contains(&l) || merged.contains(&r) {
+            group.extend(merged);
+            false
+        } else {
+            true
+        });
+        self.merged.push(group);
+    }
+    /// Keep an address on its own, undoing any merge it was part of
+    pub fn split(&mut self, address: MacAddress) {
+        for merged in self.merged.iter_mut() {
+            merged.remove(&address);
+        }
+        self.merged.retain(|merged| merged.len() > 1);
+        self.apart.insert(address);
+    }
+    pub fn is_merged(&self, address: &MacAddress) -> bool {
+        self.merged.iter().any(|merged| merged.contains(address))
+    }
+    pub fn is_empty(&self) -> bool {
+        self.merged.is_empty() && self.apart.is_empty()
+    }
+    /// Merged groups as `+` separated addresses, separated by `;`, then the split addresses separated by `;`
+    pub fn save(&self) -> (String, String) {
+        let join = |addresses: &BTreeSet<MacAddress>, separator| addresses.iter().map(MacAddress::to_hex_string).collect::<Vec<_>>().join(separator);
+        (
+            self.merged.iter().map(|merged| join(merged, "+")).collect::<Vec<_>>().join(";"),
+            join(&self.apart, ";")
+        )
+    }
+    /// Read back what `save` wrote, skipping anything that isn't an address
+    pub fn restore(merged: &str, apart: &str) -> Self {
+        let addresses = |list: &str, separator| list.split(separator).filter_map(|address| MacAddress::parse_str(address.trim()).ok()).collect::<BTreeSet<_>>();
+        Self {
+            merged: merged.split(';').map(|group| addresses(group, '+')).filter(|group| group.len() > 1).collect(),
+            apart: addresses(apart, ';')
+        }
+    }
+}
+
+/// Whether two devices could be asking for the same networks, most phones now only sending wildcard probes
+fn same_networks(l: &KnownDevice, r: &KnownDevice) -> bool {
+    let l: HashSet<_> = l.probes.keys().collect();
+    let r: HashSet<_> = r.probes.keys().collect();
+    l == r || !l.is_disjoint(&r)
+}
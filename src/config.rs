@@ -0,0 +1,487 @@
+use std::{
+    env,
+    ffi::OsString,
+    fmt,
+    fs,
+    io,
+    path::{Path, PathBuf}
+};
+use clap::ArgMatches;
+use tui::style::Color;
+
+use crate::{keymap::{self, Action}, page::Column, policy::Policy, rules::Watch, store::Selection, ui::Key};
+
+/// A value in the config file
+#[derive(Debug, Clone)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>)
+}
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::String(string) => write!(f, "{}", string),
+            Self::Integer(integer) => write!(f, "{}", integer),
+            Self::Boolean(boolean) => write!(f, "{}", boolean),
+            Self::Array(values) => {
+                let values: Vec<_> = values.iter().map(Value::to_string).collect();
+                write!(f, "{}", values.join(","))
+            }
+        }
+    }
+}
+impl Value {
+    /// As written in the config file
+    fn to_toml(&self) -> String {
+        match self {
+            Self::String(string) => {
+                let mut toml = String::from('"');
+                for c in string.chars() {
+                    match c {
+                        '"' => toml.push_str("\\\""),
+                        '\\' => toml.push_str("\\\\"),
+                        '\n' => toml.push_str("\\n"),
+                        '\t' => toml.push_str("\\t"),
+                        '\r' => toml.push_str("\\r"),
+                        c if c.is_control() => toml.push_str(&format!("\\u{:04X}", c as u32)),
+                        c => toml.push(c)
+                    }
+                }
+                toml + "\""
+            }
+            Self::Array(values) => {
+                let values: Vec<_> = values.iter().map(Value::to_toml).collect();
+                format!("[{}]", values.join(", "))
+            }
+            value => value.to_string()
+        }
+    }
+}
+
+/// Defaults for command line options, along with key bindings and colours, read from a TOML file
+/// Top level keys are named after the long options they stand in for, such as `hop-channels = "1,6,11"`,
+/// and options given on the command line take precedence
+#[derive(Debug, Default)]
+pub struct Config {
+    options: Vec<(String, Value)>,
+    /// Keys bound to actions, such as `q` to quit, taking precedence over the defaults
+    pub keys: Vec<(Key, Action)>,
+    /// Colours drawn as another, for terminals where the defaults are hard to read
+    pub colours: Vec<(Color, Color)>,
+    /// The weakest security allowed for networks of each SSID, from `[policy."SSID"]` sections
+    pub policies: Vec<Policy>,
+    /// Devices to look out for, from `[watch."name"]` sections
+    pub watches: Vec<Watch>,
+    /// The fields each export and the device store write, from the `[fields]` section
+    pub fields: Selection,
+    /// The columns the Devices tab shows, in order, from `columns` in the `[devices]` section
+    pub columns: Option<Vec<Column>>
+}
+impl Config {
+    /// Where the config file is looked for, following the XDG base directory specification
+    pub fn path() -> Option<PathBuf> {
+        let base = match env::var_os("XDG_CONFIG_HOME").filter(|path| !path.is_empty()) {
+            Some(path) => PathBuf::from(path),
+            None => PathBuf::from(env::var_os("HOME")?).join(".config")
+        };
+        Some(base.join("blockade-recon/config.toml"))
+    }
+    /// Read a config file, where a missing file is the same as an empty one
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(Error::Read(error))
+        }
+    }
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut config = Self::default();
+        let mut section = String::new();
+        for (index, line) in text.lines().enumerate() {
+            let malformed = |reason: &str| Error::Malformed(index + 1, reason.to_string());
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue
+            }
+            if line.starts_with('[') {
+                section = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')).ok_or_else(|| malformed("unclosed section header"))?.trim().to_string();
+                if let Some(ssid) = section.strip_prefix("policy.") {
+                    config.policies.push(Policy::new(section_name(ssid)));
+                } else if let Some(name) = section.strip_prefix("watch.") {
+                    config.watches.push(Watch::new(section_name(name)));
+                } else if !["", "keys", "colours", "colors", "fields", "devices"].contains(&section.as_str()) {
+                    return Err(malformed("unknown section, expected [keys], [colours], [fields], [devices], [policy.\"SSID\"] or [watch.\"name\"]"))
+                }
+                continue
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| malformed("expected key = value"))?;
+            let key = key.trim().trim_matches('"').to_string();
+            let (value, rest) = parse_value(value.trim()).ok_or_else(|| malformed("unreadable value"))?;
+            if !rest.trim().is_empty() {
+                return Err(malformed("unexpected text after the value"))
+            }
+            match section.as_str() {
+                "" => config.options.push((key, value)),
+                // An action may be given a list of keys
+                "keys" => {
+                    let action = Action::parse(&key).ok_or_else(|| malformed("unknown action"))?;
+                    let keys = match value {
+                        Value::Array(values) => values,
+                        value => vec![value]
+                    };
+                    for key in keys {
+                        config.keys.push((keymap::parse(&key.to_string()).ok_or_else(|| malformed("unknown key"))?, action));
+                    }
+                }
+                "colours" | "colors" => config.colours.push((
+                    parse_colour(&key).ok_or_else(|| malformed("unknown colour"))?,
+                    parse_colour(&value.to_string()).ok_or_else(|| malformed("unknown colour"))?
+                )),
+                "fields" => config.fields.set(&key, &value.to_string()).map_err(|error| malformed(&error.to_string()))?,
+                "devices" if key == "columns" => {
+                    let names = match value {
+                        Value::Array(values) => values,
+                        value => vec![value]
+                    };
+                    let columns = names.iter()
+                        .map(|name| Column::parse(&name.to_string()).ok_or_else(|| malformed("unknown column")))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if columns.is_empty() {
+                        return Err(malformed("at least one column must be shown"))
+                    }
+                    config.columns = Some(columns);
+                }
+                "devices" => return Err(malformed("unknown setting, expected columns")),
+                _ if section.starts_with("watch.") => if let Some(watch) = config.watches.last_mut() {
+                    watch.set(&key, &value.to_string()).map_err(|error| malformed(&error.to_string()))?
+                }
+                _ => if let Some(policy) = config.policies.last_mut() {
+                    policy.set(&key, &value.to_string()).map_err(|error| malformed(&error.to_string()))?
+                }
+            }
+        }
+        Ok(config)
+    }
+    /// Command line arguments for every option set in the file that wasn't given on the command line
+    pub fn args(&self, given: &ArgMatches) -> Vec<OsString> {
+        let mut args = vec![];
+        for (option, value) in self.options.iter() {
+            if given.is_present(option.replace('-', "_")) {
+                continue
+            }
+            let flag = OsString::from(format!("--{}", option));
+            match value {
+                Value::Boolean(true) => args.push(flag),
+                Value::Boolean(false) => (),
+                // A list stands for an option given once for each item
+                Value::Array(values) => for value in values {
+                    args.push(flag.clone());
+                    args.push(value.to_string().into());
+                }
+                value => {
+                    args.push(flag);
+                    args.push(value.to_string().into());
+                }
+            }
+        }
+        args
+    }
+}
+
+/// Set a key in a section of a config file, replacing it if it is already there and leaving the rest of the file as it was
+pub fn write(path: &Path, section: &str, key: &str, value: &Value) -> Result<(), Error> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(error) => return Err(Error::Read(error))
+    };
+    let setting = format!("{} = {}", key, value.to_toml());
+    let mut lines: Vec<_> = text.lines().map(String::from).collect();
+    let (mut current, mut header, mut existing) = (String::new(), None, None);
+    for (index, line) in lines.iter().enumerate() {
+        let line = strip_comment(line).trim();
+        if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            current = name.trim().to_string();
+            if current == section {
+                header = Some(index);
+            }
+        } else if current == section && line.split_once('=').map_or(false, |(name, _)| name.trim().trim_matches('"') == key) {
+            existing = Some(index);
+        }
+    }
+    match (existing, header) {
+        (Some(index), _) => lines[index] = setting,
+        (None, Some(index)) => lines.insert(index + 1, setting),
+        // Top level keys go after any already there, before the first section
+        (None, None) if section.is_empty() => {
+            let mut index = lines.iter().position(|line| strip_comment(line).trim().starts_with('[')).unwrap_or(lines.len());
+            while index > 0 && lines[index - 1].trim().is_empty() {
+                index -= 1;
+            }
+            lines.insert(index, setting);
+        }
+        (None, None) => {
+            if lines.last().map_or(false, |line| !line.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push(format!("[{}]", section));
+            lines.push(setting);
+        }
+    }
+    if let Some(folder) = path.parent() {
+        fs::create_dir_all(folder).map_err(Error::Write)?;
+    }
+    fs::write(path, lines.join("\n") + "\n").map_err(Error::Write)
+}
+
+/// The name in a section header such as `[policy."SSID"]`, which may be quoted
+fn section_name(name: &str) -> String {
+    match parse_value(name) {
+        Some((Value::String(name), rest)) if rest.is_empty() => name,
+        _ => name.to_string()
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut chars = line.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match (c, quote) {
+            ('"', None) | ('\'', None) => quote = Some(c),
+            // Escapes only apply in basic strings, where `\"` doesn't close it
+            ('\\', Some('"')) => {
+                chars.next();
+            }
+            (c, Some(open)) if c == open => quote = None,
+            ('#', None) => return &line[..index],
+            _ => ()
+        }
+    }
+    line
+}
+
+/// Parse a value from the start of some text, giving back what follows it
+fn parse_value(text: &str) -> Option<(Value, &str)> {
+    if let Some(rest) = text.strip_prefix('"') {
+        let mut string = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '"' => return Some((Value::String(string), &rest[index + 1..])),
+                '\\' => string.push(match chars.next()?.1 {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    'b' => '\u{8}',
+                    'f' => '\u{c}',
+                    // As `\uXXXX` or `\UXXXXXXXX`
+                    escape @ ('u' | 'U') => {
+                        let length = if escape == 'u' { 4 } else { 8 };
+                        let start = index + 2;
+                        let code = u32::from_str_radix(rest.get(start..start + length)?, 16).ok()?;
+                        for _ in 0..length {
+                            chars.next();
+                        }
+                        char::from_u32(code)?
+                    }
+                    c => c
+                }),
+                c => string.push(c)
+            }
+        }
+        None
+    } else if let Some(rest) = text.strip_prefix('\'') {
+        let end = rest.find('\'')?;
+        Some((Value::String(rest[..end].to_string()), &rest[end + 1..]))
+    } else if let Some(mut rest) = text.strip_prefix('[') {
+        let mut values = vec![];
+        loop {
+            rest = rest.trim_start();
+            if let Some(rest) = rest.strip_prefix(']') {
+                return Some((Value::Array(values), rest))
+            }
+            let (value, after) = parse_value(rest)?;
+            values.push(value);
+            rest = after.trim_start();
+            rest = rest.strip_prefix(',').unwrap_or(rest);
+        }
+    } else {
+        let end = text.find(|c: char| c == ',' || c == ']' || c.is_whitespace()).unwrap_or(text.len());
+        let (word, rest) = text.split_at(end);
+        match word {
+            "true" => Some((Value::Boolean(true), rest)),
+            "false" => Some((Value::Boolean(false), rest)),
+            word => Some((Value::Integer(word.replace('_', "").parse().ok()?), rest))
+        }
+    }
+}
+
+/// A colour by name, such as `light-green`, or as `#rrggbb`
+fn parse_colour(colour: &str) -> Option<Color> {
+    Some(match colour.to_lowercase().replace('_', "-").as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "dark-gray" | "dark-grey" => Color::DarkGray,
+        "light-red" => Color::LightRed,
+        "light-green" => Color::LightGreen,
+        "light-yellow" => Color::LightYellow,
+        "light-blue" => Color::LightBlue,
+        "light-magenta" => Color::LightMagenta,
+        "light-cyan" => Color::LightCyan,
+        "white" => Color::White,
+        hex if hex.len() == 7 && hex.starts_with('#') => {
+            let channel = |range| u8::from_str_radix(hex.get(range)?, 16).ok();
+            Color::Rgb(channel(1..3)?, channel(3..5)?, channel(5..7)?)
+        }
+        _ => return None
+    })
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Read(io::Error),
+    Write(io::Error),
+    /// A line that couldn't be understood, by line number, and why
+    Malformed(usize, String)
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Read(error) => write!(f, "unable to read the config file: {}", error),
+            Self::Write(error) => write!(f, "unable to write the config file: {}", error),
+            Self::Malformed(line, reason) => write!(f, "line {} of the config file: {}", line, reason)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(text: &str) -> String {
+        match parse_value(text) {
+            Some((Value::String(string), "")) => string,
+            value => panic!("{} didn't parse as a string: {:?}", text, value)
+        }
+    }
+    /// A config file unique to the test, removed when dropped
+    struct Temporary(PathBuf);
+    impl Temporary {
+        fn new(name: &str, text: &str) -> Self {
+            let path = env::temp_dir().join(format!("blockade-recon-{}-{}.toml", name, std::process::id()));
+            fs::write(&path, text).unwrap();
+            Self(path)
+        }
+        fn read(&self) -> String {
+            fs::read_to_string(&self.0).unwrap()
+        }
+    }
+    impl Drop for Temporary {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(string(r#""a \"quoted\" \\ string\n""#), "a \"quoted\" \\ string\n");
+        assert_eq!(string(r#""\u00e9\U0001F4E1\r""#), "é📡\r");
+        assert_eq!(string(r"'C:\raw # not a comment'"), r"C:\raw # not a comment");
+        assert!(matches!(parse_value("1_000 # comment"), Some((Value::Integer(1000), " # comment"))));
+        assert!(matches!(parse_value("-40"), Some((Value::Integer(-40), ""))));
+        assert!(matches!(parse_value("true,"), Some((Value::Boolean(true), ","))));
+        match parse_value("[1, 'two', [false]] rest") {
+            Some((Value::Array(values), " rest")) => {
+                assert_eq!(values.len(), 3);
+                assert!(matches!(values[0], Value::Integer(1)));
+                assert!(matches!(&values[1], Value::String(string) if string == "two"));
+                assert!(matches!(&values[2], Value::Array(inner) if matches!(inner[..], [Value::Boolean(false)])));
+            }
+            value => panic!("array didn't parse: {:?}", value)
+        }
+    }
+    #[test]
+    fn malformed_values() {
+        for text in ["", "\"unterminated", "'unterminated", "[1, 2", "\"\\", "\"\\u12\"", "\"\\uD800\"", "yes", "1.5"] {
+            assert!(parse_value(text).is_none(), "{:?} parsed", text);
+        }
+    }
+    #[test]
+    fn comments() {
+        assert_eq!(strip_comment("key = 1 # comment"), "key = 1 ");
+        assert_eq!(strip_comment(r##"key = "# kept" # comment"##), r##"key = "# kept" "##);
+        assert_eq!(strip_comment(r##"key = '"# kept' # comment"##), r##"key = '"# kept' "##);
+        assert_eq!(strip_comment(r##"key = "\"# kept\\" # comment"##), r##"key = "\"# kept\\" "##);
+        assert_eq!(strip_comment("# comment"), "");
+    }
+    #[test]
+    fn config() {
+        let config = Config::parse(r##"
+            # Options
+            hop-channels = "1,6,11"
+            quiet = true
+            channels = [1, 6]
+
+            [keys]
+            quit = ["q", "esc"]
+
+            [colours]
+            red = "#ff8000"
+        "##).unwrap();
+        assert_eq!(config.options.len(), 3);
+        assert!(matches!(&config.options[0], (key, Value::String(value)) if key == "hop-channels" && value == "1,6,11"));
+        assert!(matches!(&config.options[2].1, Value::Array(values) if values.len() == 2));
+        assert_eq!(config.keys, [(Key::Char('q'), Action::Quit), (Key::Esc, Action::Quit)]);
+        assert_eq!(config.colours, [(Color::Red, Color::Rgb(0xff, 0x80, 0))]);
+    }
+    #[test]
+    fn malformed_config() {
+        for (text, line) in [
+            ("[unknown]", 1),
+            ("\n[keys", 2),
+            ("a = 1\nno value", 2),
+            ("a = \"unterminated", 1),
+            ("a = 1 2", 1),
+            ("[keys]\nnot-an-action = \"q\"", 2),
+            ("[colours]\nred = \"#12345\"", 2),
+            ("[devices]\ncolumns = []", 2)
+        ] {
+            match Config::parse(text) {
+                Err(Error::Malformed(number, _)) => assert_eq!(number, line, "{:?}", text),
+                result => panic!("{:?} gave {:?}", text, result.map(|_| ()))
+            }
+        }
+    }
+    #[test]
+    fn write_round_trip() {
+        let file = Temporary::new("config", "# Kept as it was\nquiet = true\n\n[keys]\nquit = \"q\" # old\n");
+        let awkward = "tab\tquote\" slash\\ bell\u{7} # not a comment";
+        write(&file.0, "", "hop-channels", &Value::String(awkward.to_string())).unwrap();
+        write(&file.0, "keys", "quit", &Value::Array(vec![Value::String("x".to_string())])).unwrap();
+        write(&file.0, "devices", "columns", &Value::Array(vec![Value::String("ssids".to_string())])).unwrap();
+        assert_eq!(file.read(), concat!(
+            "# Kept as it was\n",
+            "quiet = true\n",
+            "hop-channels = \"tab\\tquote\\\" slash\\\\ bell\\u0007 # not a comment\"\n",
+            "\n",
+            "[keys]\n",
+            "quit = [\"x\"]\n",
+            "\n",
+            "[devices]\n",
+            "columns = [\"ssids\"]\n"
+        ));
+        let config = Config::load(&file.0).unwrap();
+        assert!(matches!(&config.options[1], (key, Value::String(value)) if key == "hop-channels" && value == awkward));
+        assert_eq!(config.keys, [(Key::Char('x'), Action::Quit)]);
+        assert_eq!(config.columns.map(|columns| columns.len()), Some(1));
+    }
+}
@@ -0,0 +1,492 @@
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, BufReader, Read, Write},
+    net::{IpAddr, TcpListener, TcpStream},
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant}
+};
+use eui48::MacAddress;
+
+use crate::export;
+
+/// The most a request line and headers may take up
+const MAX_REQUEST: usize = 8192;
+/// How long a client has to send its whole request before it is hung up on
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Most connections waiting to send their requests at once, beyond which new ones are hung up on straight away
+const MAX_PENDING: usize = 32;
+
+/// A change asked for by a remote console
+#[derive(Debug)]
+pub enum Command {
+    Status,
+    /// Tune to a channel and stay there
+    Channel(u32),
+    /// Stop or resume hopping
+    Lock(bool),
+    /// Stop or resume writing frames to the save file
+    Saving(bool),
+    Tag(MacAddress, String),
+    /// The devices, access points or alerts as JSON, for the dashboard
+    Devices,
+    Networks,
+    Alerts,
+    /// The dashboard itself, which is served without a token as it asks for one
+    Dashboard
+}
+
+/// Credentials for the control endpoint or a remote sensor, read from `key=value` lines
+///
+/// `token` is required of every request as `Authorization: Bearer <token>`, or of every client of a sensor,
+/// and `cert` and `key` name PEM files to serve HTTPS or TLS with
+#[derive(Default)]
+pub struct Config {
+    pub token: Option<String>,
+    pub tls: Option<Arc<rustls::ServerConfig>>
+}
+impl Config {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let contents = fs::read_to_string(path).map_err(Error::Config)?;
+        let (mut token, mut cert, mut key) = (None, None, None);
+        for line in contents.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let mut parts = line.splitn(2, '=');
+            let (name, value) = (parts.next().unwrap_or_default().trim(), parts.next().map(str::trim));
+            match (name, value) {
+                ("token", Some(value)) if !value.is_empty() => token = Some(value.to_string()),
+                ("cert", Some(value)) => cert = Some(value.to_string()),
+                ("key", Some(value)) => key = Some(value.to_string()),
+                _ => return Err(Error::Setting(line.to_string()))
+            }
+        }
+        let tls = match (cert, key) {
+            (None, None) => None,
+            (Some(cert), Some(key)) => Some(Arc::new(tls_config(&cert, &key)?)),
+            _ => return Err(Error::Certificate("both cert and key are needed to serve TLS".to_string()))
+        };
+        Ok(Self {
+            token,
+            tls
+        })
+    }
+}
+
+/// Every item in a PEM file
+pub fn read_pem(path: &str) -> Result<Vec<rustls_pemfile::Item>> {
+    let mut reader = BufReader::new(File::open(path).map_err(Error::Config)?);
+    let mut items = vec![];
+    while let Some(item) = rustls_pemfile::read_one(&mut reader).map_err(Error::Config)? {
+        items.push(item)
+    }
+    Ok(items)
+}
+
+fn tls_config(cert: &str, key: &str) -> Result<rustls::ServerConfig> {
+    let certs: Vec<_> = read_pem(cert)?.into_iter()
+        .filter_map(|item| match item {
+            rustls_pemfile::Item::X509Certificate(cert) => Some(rustls::Certificate(cert)),
+            _ => None
+        })
+        .collect();
+    if certs.is_empty() {
+        return Err(Error::Certificate(format!("no certificates in {}", cert)))
+    }
+    let private_key = read_pem(key)?.into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::PKCS8Key(key) | rustls_pemfile::Item::RSAKey(key) | rustls_pemfile::Item::ECKey(key) => Some(rustls::PrivateKey(key)),
+            _ => None
+        })
+        .ok_or_else(|| Error::Certificate(format!("no private key in {}", key)))?;
+    rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs, private_key)
+        .map_err(Error::Tls)
+}
+
+/// Accepts control requests over HTTP or HTTPS without blocking the capture
+///
+/// `GET /status`, `GET /devices`, `GET /networks`, `GET /alerts`, `POST /channel/<channel>`, `POST /lock`, `POST /unlock`,
+/// `POST /saving/start`, `POST /saving/stop` and `POST /devices/<address>/tags/<tag>` are understood, along with the dashboard at `GET /`
+pub struct Server {
+    /// Bound up front, as the sandbox forbids opening network sockets but not accepting on one
+    listener: TcpListener,
+    config: Config,
+    /// Connections still sending their requests, read from a little at a time so a slow one can't stall the capture
+    pending: Vec<Pending>
+}
+impl Server {
+    /// Listen on an address, refusing to accept requests from other hosts without a token
+    pub fn bind(address: &str, config: Config) -> Result<Self> {
+        let listener = TcpListener::bind(address).map_err(Error::Io)?;
+        let local = listener.local_addr().map_err(Error::Io)?;
+        if config.token.is_none() && !local.ip().is_loopback() {
+            return Err(Error::Unauthenticated)
+        }
+        listener.set_nonblocking(true).map_err(Error::Io)?;
+        Ok(Self {
+            listener,
+            config,
+            pending: vec![]
+        })
+    }
+    /// Every request that has arrived in full, answering any that don't make sense straight away
+    pub fn poll(&mut self) -> Vec<Request> {
+        while let Ok((stream, _)) = self.listener.accept() {
+            if self.pending.len() >= MAX_PENDING {
+                continue
+            }
+            if let Ok(stream) = self.open(stream) {
+                self.pending.push(Pending {
+                    stream,
+                    request: vec![],
+                    deadline: Instant::now() + REQUEST_TIMEOUT
+                })
+            }
+        }
+
+        let mut requests = vec![];
+        let token = self.config.token.as_deref();
+        for mut pending in std::mem::take(&mut self.pending) {
+            let command = match pending.read() {
+                Ok(false) if Instant::now() < pending.deadline => {
+                    self.pending.push(pending);
+                    continue
+                }
+                // Hung up on without an answer, as it hasn't finished asking
+                Ok(false) => continue,
+                Ok(true) => parse_command(&pending.request, token),
+                Err(error) => Err(error)
+            };
+            let mut stream = pending.stream;
+            // Answered in one go, with only a short wait for a client that won't take the response
+            if stream.tcp().set_nonblocking(false).is_err() {
+                continue
+            }
+            match command {
+                Ok(command) => requests.push(Request { stream, command }),
+                Err(error) => {
+                    let _ = respond(&mut stream, error.status(), JSON, &export::json_object(vec![("error", export::json_string(&error.to_string()))]));
+                }
+            }
+        }
+        requests
+    }
+    fn open(&self, stream: TcpStream) -> Result<Stream> {
+        // Read without waiting, but a client slow to take the response shouldn't hold up the capture for long
+        stream.set_nonblocking(true).map_err(Error::Io)?;
+        stream.set_write_timeout(Some(Duration::from_millis(500))).map_err(Error::Io)?;
+        Ok(match &self.config.tls {
+            Some(tls) => {
+                let connection = rustls::ServerConnection::new(tls.clone()).map_err(Error::Tls)?;
+                Stream::Tls(Box::new(rustls::StreamOwned::new(connection, stream)))
+            }
+            None => Stream::Plain(stream)
+        })
+    }
+}
+
+/// A connection whose request hasn't arrived in full yet
+struct Pending {
+    stream: Stream,
+    /// What has been read of the request so far
+    request: Vec<u8>,
+    deadline: Instant
+}
+impl Pending {
+    /// Read whatever has arrived, returning true once the request line and headers are all here or the client stops sending
+    fn read(&mut self) -> Result<bool> {
+        let mut buffer = [0; 1024];
+        while !self.request.windows(4).any(|window| window == b"\r\n\r\n") {
+            if self.request.len() > MAX_REQUEST {
+                return Err(Error::TooLarge)
+            }
+            match self.stream.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => self.request.extend_from_slice(&buffer[..read]),
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => (),
+                Err(error) => return Err(Error::Io(error))
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// A connection, with the handshake carried out as it is first read from if it is HTTPS
+enum Stream {
+    Plain(TcpStream),
+    Tls(Box<rustls::StreamOwned<rustls::ServerConnection, TcpStream>>)
+}
+impl Stream {
+    fn tcp(&self) -> &TcpStream {
+        match self {
+            Self::Plain(stream) => stream,
+            Self::Tls(stream) => &stream.sock
+        }
+    }
+    fn close(&mut self) -> io::Result<()> {
+        if let Self::Tls(stream) = self {
+            stream.conn.send_close_notify();
+            stream.conn.complete_io(&mut stream.sock)?;
+        }
+        Ok(())
+    }
+}
+impl Read for Stream {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(stream) => stream.read(buffer),
+            Self::Tls(stream) => stream.read(buffer)
+        }
+    }
+}
+impl Write for Stream {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(stream) => stream.write(buffer),
+            Self::Tls(stream) => stream.write(buffer)
+        }
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(stream) => stream.flush(),
+            Self::Tls(stream) => stream.flush()
+        }
+    }
+}
+
+/// A command waiting on its response
+pub struct Request {
+    stream: Stream,
+    pub command: Command
+}
+impl Request {
+    /// Answer with a JSON body
+    pub fn respond(mut self, status: u16, body: &str) {
+        let _ = respond(&mut self.stream, status, JSON, body);
+    }
+    /// Answer with a page to show in a browser
+    pub fn page(mut self, body: &str) {
+        let _ = respond(&mut self.stream, 200, "text/html; charset=utf-8", body);
+    }
+    pub fn ok(self, fields: Vec<(&str, String)>) {
+        self.respond(200, &export::json_object(fields))
+    }
+    pub fn error(self, status: u16, message: &str) {
+        self.respond(status, &export::json_object(vec![("error", export::json_string(message))]))
+    }
+}
+
+fn parse_command(request: &[u8], token: Option<&str>) -> Result<Command> {
+    let request = String::from_utf8_lossy(request);
+    let mut lines = request.lines();
+    let mut parts = lines.next().unwrap_or_default().split(' ');
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().ok_or(Error::Malformed)?;
+    let segments: Vec<_> = path.split('?').next().unwrap_or_default().trim_matches('/').split('/').collect();
+    if method == "GET" && segments == [""] {
+        return Ok(Command::Dashboard)
+    }
+    let headers: Vec<_> = lines
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| {
+            let mut header = line.splitn(2, ':');
+            Some((header.next()?.trim(), header.next()?.trim()))
+        })
+        .collect();
+    let header = |wanted: &'static str| headers.iter().filter(move |(name, _)| name.eq_ignore_ascii_case(wanted)).map(|(_, value)| *value);
+    match token {
+        Some(token) => if !header("authorization").any(|value| matches!(value.strip_prefix("Bearer "), Some(given) if same(given.trim(), token))) {
+            return Err(Error::Unauthorised)
+        }
+        // Only listening on loopback, but a web page could still send requests here from the browser, either to a
+        // name of its own rebound to this host, or across sites, which browsers mark with where they came from
+        None => {
+            let origin_local = header("origin").all(|origin| origin.split_once("://").map_or(false, |(_, host)| loopback(host)));
+            if !header("host").any(loopback) || !origin_local {
+                return Err(Error::Forbidden)
+            }
+        }
+    }
+    Ok(match (method, segments.as_slice()) {
+        ("GET", ["status"]) => Command::Status,
+        ("GET", ["devices"]) => Command::Devices,
+        ("GET", ["networks"]) => Command::Networks,
+        ("GET", ["alerts"]) => Command::Alerts,
+        ("POST", ["channel", channel]) => Command::Channel(channel.parse().map_err(|_| Error::Malformed)?),
+        ("POST", ["lock"]) => Command::Lock(true),
+        ("POST", ["unlock"]) => Command::Lock(false),
+        ("POST", ["saving", "start"]) => Command::Saving(true),
+        ("POST", ["saving", "stop"]) => Command::Saving(false),
+        ("POST", ["devices", address, "tags", tag]) => {
+            let address = MacAddress::parse_str(&percent_decode(address)?).map_err(|_| Error::Malformed)?;
+            let tag = percent_decode(tag)?;
+            if tag.is_empty() {
+                return Err(Error::Malformed)
+            }
+            Command::Tag(address, tag)
+        }
+        ("GET", _) | ("POST", _) => return Err(Error::NotFound(path.to_string())),
+        _ => return Err(Error::Method(method.to_string()))
+    })
+}
+
+const JSON: &str = "application/json";
+
+fn respond(stream: &mut Stream, status: u16, content_type: &str, body: &str) -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        _ => "Internal Server Error"
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+        status, reason, content_type, body.len(), if status == 401 { "WWW-Authenticate: Bearer\r\n" } else { "" }, body
+    )?;
+    stream.flush()?;
+    stream.close()
+}
+
+/// Whether a `Host` header, or the host in an `Origin`, names this host by a loopback address or as `localhost`
+fn loopback(host: &str) -> bool {
+    let name = match host.strip_prefix('[') {
+        Some(bracketed) => bracketed.split(']').next().unwrap_or_default(),
+        None => host.rsplit_once(':').map_or(host, |(name, _)| name)
+    };
+    name.trim_end_matches('.').eq_ignore_ascii_case("localhost") || name.parse::<IpAddr>().map_or(false, |address| address.is_loopback())
+}
+
+/// Compare a token without giving away how much of it matched through timing
+pub fn same(given: &str, expected: &str) -> bool {
+    given.len() == expected.len() && given.bytes().zip(expected.bytes()).fold(0, |difference, (l, r)| difference | (l ^ r)) == 0
+}
+
+/// Undo URL encoding of a path segment
+fn percent_decode(segment: &str) -> Result<String> {
+    let mut bytes = vec![];
+    let mut rest = segment.bytes();
+    while let Some(byte) = rest.next() {
+        bytes.push(match byte {
+            b'%' => {
+                let hex = [rest.next().ok_or(Error::Malformed)?, rest.next().ok_or(Error::Malformed)?];
+                let hex = std::str::from_utf8(&hex).map_err(|_| Error::Malformed)?;
+                u8::from_str_radix(hex, 16).map_err(|_| Error::Malformed)?
+            }
+            byte => byte
+        });
+    }
+    String::from_utf8(bytes).map_err(|_| Error::Malformed)
+}
+
+type Result<T> = std::result::Result<T, Error>;
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    TooLarge,
+    Malformed,
+    NotFound(String),
+    Method(String),
+    Unauthorised,
+    /// Sent from a web page, or addressed to a name other than this host's, without a token
+    Forbidden,
+    /// Listening beyond this host without a token
+    Unauthenticated,
+    Config(io::Error),
+    Setting(String),
+    Certificate(String),
+    Tls(rustls::Error)
+}
+impl Error {
+    fn status(&self) -> u16 {
+        match self {
+            Self::Io(_) | Self::Malformed => 400,
+            Self::Unauthorised => 401,
+            Self::Forbidden => 403,
+            Self::TooLarge => 413,
+            Self::NotFound(_) => 404,
+            Self::Method(_) => 405,
+            _ => 500
+        }
+    }
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "unable to read the request: {}", error),
+            Self::TooLarge => write!(f, "the request is too large"),
+            Self::Malformed => write!(f, "the request is malformed"),
+            Self::NotFound(path) => write!(f, "nothing at {}", path),
+            Self::Method(method) => write!(f, "{} is not supported", method),
+            Self::Unauthorised => write!(f, "a valid bearer token is required"),
+            Self::Forbidden => write!(f, "without a token, only requests to localhost that don't come from another site are accepted"),
+            Self::Unauthenticated => write!(f, "a token must be set with --control-config to listen on anything but a loopback address"),
+            Self::Config(error) => write!(f, "unable to read the credentials: {}", error),
+            Self::Setting(line) => write!(f, "unknown setting `{}`, expected token, cert or key", line),
+            Self::Certificate(problem) => write!(f, "{}", problem),
+            Self::Tls(error) => write!(f, "unable to set up TLS: {}", error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(request: &str, token: Option<&str>) -> Result<Command> {
+        parse_command(request.replace('\n', "\r\n").as_bytes(), token)
+    }
+
+    #[test]
+    fn commands() {
+        let local = |line: &str| command(&format!("{}\nHost: localhost:8080\n\n", line), None);
+        assert!(matches!(local("GET /status HTTP/1.1"), Ok(Command::Status)));
+        assert!(matches!(local("POST /channel/6 HTTP/1.1"), Ok(Command::Channel(6))));
+        assert!(matches!(local("POST /unlock HTTP/1.1"), Ok(Command::Lock(false))));
+        assert!(matches!(
+            local("POST /devices/aa%3Abb%3Acc%3Add%3Aee%3Aff/tags/two%20words HTTP/1.1"),
+            Ok(Command::Tag(address, tag)) if address.to_hex_string() == "aa:bb:cc:dd:ee:ff" && tag == "two words"
+        ));
+        assert!(matches!(local("POST /channel/six HTTP/1.1"), Err(Error::Malformed)));
+        assert!(matches!(local("POST /devices/aa:bb/tags/x HTTP/1.1"), Err(Error::Malformed)));
+        assert!(matches!(local("GET /nothing HTTP/1.1"), Err(Error::NotFound(_))));
+        assert!(matches!(local("DELETE /lock HTTP/1.1"), Err(Error::Method(_))));
+        assert!(matches!(command("", None), Err(Error::Malformed)));
+    }
+    #[test]
+    fn token() {
+        let token = Some("secret");
+        assert!(matches!(command("POST /lock HTTP/1.1\nHost: sensor\nAuthorization: Bearer secret\n\n", token), Ok(Command::Lock(true))));
+        assert!(matches!(command("POST /lock HTTP/1.1\nHost: sensor\nAuthorization: Bearer wrong\n\n", token), Err(Error::Unauthorised)));
+        assert!(matches!(command("POST /lock HTTP/1.1\nHost: localhost\n\n", token), Err(Error::Unauthorised)));
+        // The dashboard asks for the token itself
+        assert!(matches!(command("GET / HTTP/1.1\nHost: sensor\n\n", token), Ok(Command::Dashboard)));
+    }
+    #[test]
+    fn only_from_this_host_without_a_token() {
+        for host in ["localhost", "LOCALHOST:8080", "localhost.:8080", "127.0.0.1:8080", "127.1.2.3", "[::1]:8080"] {
+            let request = format!("POST /lock HTTP/1.1\nHost: {}\nOrigin: http://{}\n\n", host, host);
+            assert!(matches!(command(&request, None), Ok(Command::Lock(true))), "{}", host);
+        }
+        for request in [
+            // No host, as an HTTP/1.0 client might send, and a name rebound to this host
+            "POST /lock HTTP/1.0\n\n",
+            "POST /lock HTTP/1.1\nHost: attacker.example:8080\n\n",
+            "GET /devices HTTP/1.1\nHost: 192.168.1.10:8080\n\n",
+            "POST /lock HTTP/1.1\nHost: localhost.attacker.example\n\n",
+            // A form on another site, or in a sandboxed frame
+            "POST /lock HTTP/1.1\nHost: localhost:8080\nOrigin: https://attacker.example\n\n",
+            "POST /lock HTTP/1.1\nHost: localhost:8080\nOrigin: null\n\n"
+        ] {
+            assert!(matches!(command(request, None), Err(Error::Forbidden)), "{:?}", request);
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use crate::{DeviceList, Role, alerts::Alert, anonymize::Anonymizer, export, store::{Fields, Record}, survey::Survey};
+
+/// The dashboard served at `GET /`, which fetches the rest from the control API with the token after the `#` in its address
+pub const PAGE: &str = include_str!("dashboard.html");
+
+/// Every device as a JSON array of store records, for `GET /devices`
+pub fn devices(devices: &DeviceList, survey: &Survey, fields: &Fields) -> String {
+    array(Record::all(devices, survey).iter().map(|record| record.json(fields)))
+}
+
+/// Every access point with what it offers and how many clients it has, for `GET /networks`
+pub fn networks(devices: &DeviceList, anonymizer: &Anonymizer) -> String {
+    let null = || "null".to_string();
+    let mut networks: Vec<_> = devices.iter().filter(|(_, device)| device.role == Some(Role::AccessPoint)).collect();
+    networks.sort_by_key(|(address, _)| **address);
+    array(networks.into_iter().map(|(address, device)| {
+        let clients = devices.values().filter(|client| client.associated == Some(*address)).count();
+        let channel = device.sensors.values().filter(|sighting| sighting.signal.is_some()).max_by_key(|sighting| sighting.signal)
+            .or_else(|| device.sensors.values().next())
+            .and_then(|sighting| sighting.channel());
+        export::json_object(vec![
+            ("address", export::json_string(&anonymizer.address_string(address))),
+            ("ssid", device.beacon.as_deref().filter(|ssid| !ssid.is_empty()).map_or_else(null, |ssid| export::json_string(&anonymizer.ssid(ssid)))),
+            ("hidden", device.hidden.to_string()),
+            ("security", device.security.as_ref().map_or_else(null, |security| export::json_string(&security.to_string()))),
+            ("channel", channel.map_or_else(null, |channel| channel.to_string())),
+            ("clients", clients.to_string()),
+            ("signal", device.signal_strength.last.map_or_else(null, |signal| signal.to_string()))
+        ])
+    }))
+}
+
+/// Every alert raised, newest first, for `GET /alerts`
+pub fn alerts(alerts: &[Alert], anonymizer: &Anonymizer) -> String {
+    array(alerts.iter().rev().map(|alert| Alert {
+        address: anonymizer.address(alert.address),
+        message: anonymizer.text(&alert.message).into_owned(),
+        ..alert.clone()
+    }.json()))
+}
+
+fn array(items: impl Iterator<Item = String>) -> String {
+    format!("[{}]", items.collect::<Vec<_>>().join(","))
+}
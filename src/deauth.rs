@@ -0,0 +1,134 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    time::Duration
+};
+use eui48::MacAddress;
+
+use crate::DEAUTH_WINDOW;
+
+/// Frames to one client of one network within DEAUTH_WINDOW taken to be a flood, unless changed with --deauth-threshold
+pub const DEFAULT_THRESHOLD: u32 = 20;
+
+/// A burst of deauthentications and disassociations aimed at a client of a network, or every client when broadcast,
+/// as attacks send to knock clients off or capture their handshakes when they reconnect
+#[derive(Debug, Clone)]
+pub struct Flood {
+    pub bssid: MacAddress,
+    pub client: MacAddress,
+    /// The transmitters the frames claimed to come from, usually the access point or client spoofed
+    pub senders: BTreeSet<MacAddress>,
+    pub deauthentications: u64,
+    pub disassociations: u64,
+    /// The reason codes given, where protected management frames didn't hide them
+    pub reasons: BTreeSet<u16>,
+    /// When the window that crossed the threshold started and when the last frame was heard, since the capture started
+    pub started: Duration,
+    pub last: Duration,
+    /// The most frames within any one DEAUTH_WINDOW
+    pub peak: u32
+}
+impl Flood {
+    pub fn frames(&self) -> u64 {
+        self.deauthentications + self.disassociations
+    }
+    /// True if the frames went to every client of the network at once
+    pub fn broadcast(&self) -> bool {
+        self.client.is_broadcast()
+    }
+}
+
+/// Frames counted towards a client's current window
+#[derive(Debug, Clone, Default)]
+struct Window {
+    started: Duration,
+    deauthentications: u64,
+    disassociations: u64,
+    senders: BTreeSet<MacAddress>,
+    reasons: BTreeSet<u16>
+}
+impl Window {
+    fn frames(&self) -> u32 {
+        (self.deauthentications + self.disassociations) as u32
+    }
+}
+
+/// Counts deauthentications and disassociations to each client of each network, picking out floods
+#[derive(Debug)]
+pub struct Floods {
+    pub threshold: u32,
+    /// Every flood heard, oldest first
+    pub floods: Vec<Flood>,
+    /// The frames heard within the current window of each network and client
+    windows: HashMap<(MacAddress, MacAddress), Window>,
+    /// The flood each network and client are in the middle of, as an index into `floods`
+    ongoing: HashMap<(MacAddress, MacAddress), usize>
+}
+impl Default for Floods {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_THRESHOLD,
+            floods: vec![],
+            windows: HashMap::new(),
+            ongoing: HashMap::new()
+        }
+    }
+}
+impl Floods {
+    /// Count a deauthentication or disassociation, sent by either end, which is where the flood is told apart from the odd disconnection
+    pub fn frame(&mut self, bssid: MacAddress, source: MacAddress, destination: MacAddress, reason: Option<u16>, disassociation: bool, time: Duration) {
+        let client = if destination == bssid { source } else { destination };
+        let key = (bssid, client);
+        let window = self.windows.entry(key).or_default();
+        if window.frames() == 0 || time >= window.started + DEAUTH_WINDOW {
+            *window = Window {
+                started: time,
+                ..Default::default()
+            };
+        }
+        if disassociation {
+            window.disassociations += 1
+        } else {
+            window.deauthentications += 1
+        }
+        window.senders.insert(source);
+        window.reasons.extend(reason);
+
+        // A flood goes on for as long as frames keep coming without a whole window's gap
+        if let Some(&index) = self.ongoing.get(&key) {
+            let flood = &mut self.floods[index];
+            if time < flood.last + DEAUTH_WINDOW {
+                if disassociation {
+                    flood.disassociations += 1
+                } else {
+                    flood.deauthentications += 1
+                }
+                flood.senders.insert(source);
+                flood.reasons.extend(reason);
+                flood.last = time;
+                flood.peak = flood.peak.max(window.frames());
+                return
+            }
+            self.ongoing.remove(&key);
+        }
+        if window.frames() >= self.threshold.max(1) {
+            self.ongoing.insert(key, self.floods.len());
+            self.floods.push(Flood {
+                bssid,
+                client,
+                senders: window.senders.clone(),
+                deauthentications: window.deauthentications,
+                disassociations: window.disassociations,
+                reasons: window.reasons.clone(),
+                started: window.started,
+                last: time,
+                peak: window.frames()
+            });
+        }
+    }
+    /// Forget every flood and window, keeping the threshold
+    pub fn clear(&mut self) {
+        self.floods.clear();
+        self.windows.clear();
+        self.ongoing.clear();
+    }
+}
@@ -0,0 +1,186 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    fs::File,
+    io::Read,
+    path::Path
+};
+
+use crate::{export::{self, Json}, store::{self, Error, Fields, Record, Store}};
+
+/// What every SQLite database starts with
+const SQLITE_MAGIC: &[u8; 16] = b"SQLite format 3\0";
+
+/// Read every device from a JSON export, a JSONL store or an SQLite store, told apart by their contents
+pub fn load(path: &Path) -> Result<Vec<Record>, Error> {
+    let mut contents = vec![];
+    File::open(path)?.read_to_end(&mut contents)?;
+    if contents.starts_with(SQLITE_MAGIC) {
+        #[cfg(feature = "sqlite")]
+        return store::Sqlite::open(path)?.all();
+        #[cfg(not(feature = "sqlite"))]
+        return Err(Error::Unsupported(format!("sqlite:{}", path.display())))
+    }
+    match Json::parse(&String::from_utf8_lossy(&contents)) {
+        Some(export) => match export.get("devices") {
+            Some(Json::Array(devices)) => devices.iter()
+                .enumerate()
+                .map(|(index, device)| Record::from_json(device).ok_or(Error::Malformed(index + 1)))
+                .collect(),
+            // A JSONL store of a single line
+            _ => store::Jsonl::open(path, Fields::default())?.all()
+        },
+        None => store::Jsonl::open(path, Fields::default())?.all()
+    }
+}
+
+/// A field of a device that differs between two surveys
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub field: &'static str,
+    pub before: Option<String>,
+    pub after: Option<String>
+}
+
+/// How a later survey differs from an earlier one
+#[derive(Debug, Default)]
+pub struct Diff {
+    pub appeared: Vec<Record>,
+    pub disappeared: Vec<Record>,
+    /// Devices in both, as in the later survey, with what changed about them
+    pub changed: Vec<(Record, Vec<Change>)>,
+    /// Network names advertised by access points in only the later survey
+    pub ssids_appeared: Vec<String>,
+    pub ssids_disappeared: Vec<String>
+}
+impl Diff {
+    pub fn between(before: &[Record], after: &[Record]) -> Self {
+        let earlier: BTreeMap<_, _> = before.iter().map(|record| (record.address.as_str(), record)).collect();
+        let later: BTreeMap<_, _> = after.iter().map(|record| (record.address.as_str(), record)).collect();
+        let mut diff = Self::default();
+        for (address, &record) in later.iter() {
+            match earlier.get(address) {
+                Some(&earlier) => {
+                    let changes = changes(earlier, record);
+                    if !changes.is_empty() {
+                        diff.changed.push((record.clone(), changes));
+                    }
+                }
+                None => diff.appeared.push(record.clone())
+            }
+        }
+        diff.disappeared = earlier.iter()
+            .filter(|(address, _)| !later.contains_key(*address))
+            .map(|(_, &record)| record.clone())
+            .collect();
+        let (earlier, later) = (ssids(before), ssids(after));
+        diff.ssids_appeared = later.difference(&earlier).cloned().collect();
+        diff.ssids_disappeared = earlier.difference(&later).cloned().collect();
+        diff
+    }
+    pub fn is_empty(&self) -> bool {
+        self.appeared.is_empty() && self.disappeared.is_empty() && self.changed.is_empty()
+            && self.ssids_appeared.is_empty() && self.ssids_disappeared.is_empty()
+    }
+    pub fn json(&self) -> String {
+        let null = || "null".to_string();
+        let records = |records: &[Record]| format!("[{}]", records.iter().map(|record| record.json(&Fields::default())).collect::<Vec<_>>().join(","));
+        let strings = |strings: &[String]| format!("[{}]", strings.iter().map(|string| export::json_string(string)).collect::<Vec<_>>().join(","));
+        let changed: Vec<_> = self.changed.iter()
+            .map(|(record, changes)| export::json_object(vec![
+                ("address", export::json_string(&record.address)),
+                ("changes", format!("[{}]", changes.iter()
+                    .map(|change| export::json_object(vec![
+                        ("field", export::json_string(change.field)),
+                        ("before", change.before.as_deref().map_or_else(null, export::json_string)),
+                        ("after", change.after.as_deref().map_or_else(null, export::json_string))
+                    ]))
+                    .collect::<Vec<_>>()
+                    .join(",")))
+            ]))
+            .collect();
+        export::json_object(vec![
+            ("appeared", records(&self.appeared)),
+            ("disappeared", records(&self.disappeared)),
+            ("changed", format!("[{}]", changed.join(","))),
+            ("ssids", export::json_object(vec![
+                ("appeared", strings(&self.ssids_appeared)),
+                ("disappeared", strings(&self.ssids_disappeared))
+            ]))
+        ])
+    }
+}
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No differences")
+        }
+        for (heading, sign, records) in [("appeared", '+', &self.appeared), ("disappeared", '-', &self.disappeared)].iter() {
+            if !records.is_empty() {
+                writeln!(f, "{} device(s) {}", records.len(), heading)?;
+                for record in records.iter() {
+                    writeln!(f, "  {} {}", sign, describe(record))?;
+                }
+            }
+        }
+        if !self.changed.is_empty() {
+            writeln!(f, "{} device(s) changed", self.changed.len())?;
+            for (record, changes) in self.changed.iter() {
+                writeln!(f, "  ~ {}", describe(record))?;
+                for change in changes {
+                    let value = |value: &Option<String>| value.clone().unwrap_or_else(|| "none".to_string());
+                    writeln!(f, "      {}: {} -> {}", change.field, value(&change.before), value(&change.after))?;
+                }
+            }
+        }
+        for (heading, sign, ssids) in [("appeared", '+', &self.ssids_appeared), ("disappeared", '-', &self.ssids_disappeared)].iter() {
+            if !ssids.is_empty() {
+                writeln!(f, "{} SSID(s) {}", ssids.len(), heading)?;
+                for ssid in ssids.iter() {
+                    writeln!(f, "  {} {:?}", sign, ssid)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The fields that differ between two records of a device, ignoring those the later one doesn't know,
+/// as a client that wasn't associated this time hasn't left its access point for another
+fn changes(before: &Record, after: &Record) -> Vec<Change> {
+    [
+        ("manufacturer", &before.manufacturer, &after.manufacturer),
+        ("role", &before.role, &after.role),
+        ("ssid", &before.ssid, &after.ssid),
+        ("bssid", &before.bssid, &after.bssid)
+    ].iter()
+        .filter(|(_, before, after)| after.is_some() && after != before)
+        .map(|&(field, before, after)| Change {
+            field,
+            before: before.clone(),
+            after: after.clone()
+        })
+        .collect()
+}
+
+/// Every network name advertised by an access point, leaving out hidden ones
+fn ssids(records: &[Record]) -> BTreeSet<String> {
+    records.iter()
+        .filter(|record| record.role.as_deref() == Some("ap"))
+        .filter_map(|record| record.ssid.clone())
+        .filter(|ssid| !ssid.is_empty())
+        .collect()
+}
+
+/// An address with the manufacturer, role and SSID known for it
+fn describe(record: &Record) -> String {
+    let mut description = record.address.clone();
+    for value in [&record.role, &record.manufacturer].iter().copied().flatten() {
+        description.push_str("  ");
+        description.push_str(value);
+    }
+    if let Some(ssid) = record.ssid.as_deref().filter(|ssid| !ssid.is_empty()) {
+        description.push_str(&format!("  {:?}", ssid));
+    }
+    description
+}
@@ -0,0 +1,156 @@
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::{Arc, Mutex, mpsc::{self, RecvTimeoutError, Sender}},
+    thread,
+    time::{Duration, Instant}
+};
+
+use crate::alerts::{Alert, Severity};
+
+/// How long to wait on the mail server before giving up on a digest
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where and how often digests of critical alerts are mailed
+pub struct Config {
+    /// The mail server, such as a local relay on localhost:25
+    pub server: String,
+    pub from: String,
+    pub to: Vec<String>,
+    /// How long alerts are gathered before being sent together
+    pub interval: Duration,
+    /// Starts every subject, to tell sensors apart
+    pub subject: String
+}
+
+/// Mails critical alerts in batches from a thread of its own
+pub struct Mailer {
+    alerts: Sender<Alert>,
+    /// Why the last digest couldn't be sent, cleared once one is
+    pub error: Arc<Mutex<Option<String>>>
+}
+impl Mailer {
+    /// Must be started before the sandbox is applied, which only confines the thread applying it,
+    /// so that the mailer can still reach the mail server
+    pub fn spawn(config: Config) -> Self {
+        let (tx, rx) = mpsc::channel::<Alert>();
+        let error = Arc::new(Mutex::new(None));
+        let thread_error = error.clone();
+        thread::spawn(move || {
+            let mut batch = vec![];
+            let mut due = Instant::now() + config.interval;
+            loop {
+                match rx.recv_timeout(due.saturating_duration_since(Instant::now())) {
+                    Ok(alert) => batch.push(alert),
+                    Err(RecvTimeoutError::Timeout) => {
+                        if !batch.is_empty() {
+                            let result = send(&config, &batch);
+                            if let Ok(mut error) = thread_error.lock() {
+                                *error = result.err().map(|error| error.to_string());
+                            }
+                            batch.clear();
+                        }
+                        due = Instant::now() + config.interval;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break
+                }
+            }
+        });
+        Self {
+            alerts: tx,
+            error
+        }
+    }
+    /// Queue an alert for the next digest if it is critical
+    pub fn alert(&self, alert: &Alert) {
+        if alert.severity == Severity::Critical {
+            let _ = self.alerts.send(alert.clone());
+        }
+    }
+}
+
+fn send(config: &Config, alerts: &[Alert]) -> Result<(), Error> {
+    let address = config.server.to_socket_addrs().map_err(Error::Io)?
+        .next()
+        .ok_or_else(|| Error::Io(io::Error::new(io::ErrorKind::NotFound, "the mail server's name didn't resolve")))?;
+    let stream = TcpStream::connect_timeout(&address, TIMEOUT).map_err(Error::Io)?;
+    stream.set_read_timeout(Some(TIMEOUT)).map_err(Error::Io)?;
+    stream.set_write_timeout(Some(TIMEOUT)).map_err(Error::Io)?;
+    let mut session = Session {
+        reader: BufReader::new(stream.try_clone().map_err(Error::Io)?),
+        writer: stream
+    };
+
+    session.expect(220)?;
+    session.command("EHLO blockade-recon", 250)?;
+    session.command(&format!("MAIL FROM:<{}>", config.from), 250)?;
+    for to in config.to.iter() {
+        session.command(&format!("RCPT TO:<{}>", to), 250)?;
+    }
+    session.command("DATA", 354)?;
+    let mut message = format!(
+        "From: {}\r\nTo: {}\r\nSubject: {} {} critical alert(s)\r\n\r\n",
+        config.from,
+        config.to.join(", "),
+        config.subject,
+        alerts.len()
+    );
+    for alert in alerts {
+        let line = format!("{:>9.3}s {} {}{}", alert.time.as_secs_f64(), alert.address.to_hex_string(), alert.message, if alert.escalated { " (escalated)" } else { "" });
+        // A line starting with a dot would otherwise end the message early
+        if line.starts_with('.') {
+            message.push('.');
+        }
+        message.push_str(&line);
+        message.push_str("\r\n");
+    }
+    message.push_str(".\r\n");
+    session.writer.write_all(message.as_bytes()).map_err(Error::Io)?;
+    session.expect(250)?;
+    session.command("QUIT", 221)
+}
+
+struct Session {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream
+}
+impl Session {
+    fn command(&mut self, command: &str, code: u16) -> Result<(), Error> {
+        write!(self.writer, "{}\r\n", command).map_err(Error::Io)?;
+        self.expect(code)
+    }
+    /// Read a reply, which may run over several lines, and check its code
+    fn expect(&mut self, code: u16) -> Result<(), Error> {
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line).map_err(Error::Io)? == 0 {
+                return Err(Error::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "the mail server closed the connection")))
+            }
+            let reply = line.get(..3).and_then(|reply| reply.parse::<u16>().ok()).ok_or_else(|| Error::Reply(line.trim().to_string()))?;
+            // A dash after the code means more lines follow
+            if line.as_bytes().get(3) == Some(&b'-') {
+                continue
+            }
+            if reply != code {
+                return Err(Error::Reply(line.trim().to_string()))
+            }
+            return Ok(())
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// The server refused a command, with what it said
+    Reply(String)
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "unable to reach the mail server: {}", error),
+            Self::Reply(reply) => write!(f, "the mail server refused: {}", reply)
+        }
+    }
+}
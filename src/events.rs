@@ -0,0 +1,114 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, Write},
+    time::Duration
+};
+use eui48::MacAddress;
+
+use crate::{DeviceList, KnownDevice, Role, alerts::Alert, anonymize::Anonymizer, export};
+
+/// Writes what is found as newline delimited JSON, one event per line, for scripts to follow as the capture runs
+pub struct Events {
+    output: Box<dyn Write>,
+    /// Devices and network names already announced
+    devices: HashSet<MacAddress>,
+    ssids: HashSet<(MacAddress, String)>,
+    /// How many of each client's roams have been announced
+    roams: HashMap<MacAddress, u64>,
+    anonymizer: Anonymizer
+}
+impl Events {
+    pub fn new(output: Box<dyn Write>, anonymizer: Anonymizer) -> Self {
+        Self {
+            output,
+            anonymizer,
+            devices: HashSet::new(),
+            ssids: HashSet::new(),
+            roams: HashMap::new()
+        }
+    }
+    /// Announce every device, network name and roam not announced before
+    pub fn check(&mut self, devices: &DeviceList, time: Duration) -> io::Result<()> {
+        for (&address, device) in devices.iter() {
+            if device.sent && self.devices.insert(address) {
+                writeln!(self.output, "{}", device_event(&self.anonymizer, address, device, time))?;
+            }
+            if let Some(ssid) = device.beacon.as_ref().filter(|ssid| !ssid.is_empty()) {
+                if self.ssids.insert((address, ssid.clone())) {
+                    let event = event("ssid", time, vec![
+                        ("bssid", export::json_string(&self.anonymizer.address_string(&address))),
+                        ("ssid", export::json_string(&self.anonymizer.ssid(ssid))),
+                        ("hidden", device.hidden.to_string())
+                    ]);
+                    writeln!(self.output, "{}", event)?;
+                }
+            }
+            let announced = self.roams.entry(address).or_default();
+            let unannounced = (device.mobility.roamed - *announced) as usize;
+            if unannounced > 0 {
+                // Any that came and went between checks beyond those kept are left out
+                for roam in device.mobility.roams.iter().skip(device.mobility.roams.len().saturating_sub(unannounced)) {
+                    let event = event("roam", roam.time, vec![
+                        ("address", export::json_string(&self.anonymizer.address_string(&address))),
+                        ("from", export::json_string(&self.anonymizer.address_string(&roam.from))),
+                        ("to", export::json_string(&self.anonymizer.address_string(&roam.to)))
+                    ]);
+                    writeln!(self.output, "{}", event)?;
+                }
+                *announced = device.mobility.roamed;
+            }
+        }
+        self.output.flush()
+    }
+    /// An access point announcing it is about to move from the channel it was heard on to another
+    pub fn channel_switch(&mut self, bssid: MacAddress, from: Option<u32>, to: u32, time: Duration) -> io::Result<()> {
+        let event = event("channel-switch", time, vec![
+            ("bssid", export::json_string(&self.anonymizer.address_string(&bssid))),
+            ("from", from.map_or_else(|| "null".to_string(), |channel| channel.to_string())),
+            ("to", to.to_string())
+        ]);
+        writeln!(self.output, "{}", event)?;
+        self.output.flush()
+    }
+    pub fn alert(&mut self, alert: &Alert) -> io::Result<()> {
+        writeln!(self.output, "{}", alert_event(&self.anonymizer, alert))?;
+        self.output.flush()
+    }
+    /// Forget what has been announced, so everything is announced again after the device list is cleared
+    pub fn reset(&mut self) {
+        self.devices.clear();
+        self.ssids.clear();
+        self.roams.clear();
+    }
+}
+
+/// A device heard for the first time
+pub fn device_event(anonymizer: &Anonymizer, address: MacAddress, device: &KnownDevice, time: Duration) -> String {
+    event("device", time, vec![
+        ("address", export::json_string(&anonymizer.address_string(&address))),
+        ("manufacturer", device.manufacturer.as_ref().map_or_else(|| "null".to_string(), |entry| export::json_string(&entry.name_short))),
+        ("role", match device.role {
+            Some(Role::AccessPoint) => export::json_string("access-point"),
+            Some(Role::Station) => export::json_string("station"),
+            None => "null".to_string()
+        })
+    ])
+}
+
+pub fn alert_event(anonymizer: &Anonymizer, alert: &Alert) -> String {
+    let alert = Alert {
+        address: anonymizer.address(alert.address),
+        message: anonymizer.text(&alert.message).into_owned(),
+        ..alert.clone()
+    };
+    event("alert", alert.time, vec![("alert", alert.json())])
+}
+
+pub fn event(kind: &str, time: Duration, fields: Vec<(&str, String)>) -> String {
+    let mut object = vec![
+        ("event", export::json_string(kind)),
+        ("time", format!("{:.3}", time.as_secs_f64()))
+    ];
+    object.extend(fields);
+    export::json_object(object)
+}
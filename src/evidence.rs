@@ -0,0 +1,122 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf}
+};
+use eui48::MacAddress;
+use oui::OuiEntry;
+use pcap::{Capture, Linktype};
+use radiotap::Radiotap;
+
+use crate::{KnownDevice, Role, export, fingerprint, survey::Survey};
+
+/// Gather everything known about one device into a folder of its own:
+/// what was learnt about it in `device.json`, and every frame naming it in the given save files in `frames.pcap`
+pub fn package(address: MacAddress, device: &KnownDevice, captures: &[PathBuf], survey: &Survey) -> Result<PathBuf, Error> {
+    let folder = PathBuf::from(format!("device-{}", address.to_hex_string().replace(':', "-")));
+    fs::create_dir_all(&folder).map_err(Error::Io)?;
+    fs::write(folder.join("device.json"), describe(address, device, survey)).map_err(Error::Io)?;
+    // Nothing to extract when frames aren't being saved
+    if !captures.is_empty() {
+        extract(address, captures, folder.join("frames.pcap"))?;
+    }
+    Ok(folder)
+}
+
+fn describe(address: MacAddress, device: &KnownDevice, survey: &Survey) -> String {
+    let null = || "null".to_string();
+    let unix = |time: Option<std::time::Duration>| time.map_or_else(null, |time| (survey.started_unix() + time.as_secs()).to_string());
+    let list = |items: Vec<String>| format!("[{}]", items.join(","));
+
+    let mut probes: Vec<_> = device.probes.iter().collect();
+    probes.sort();
+    let mut sensors: Vec<_> = device.sensors.iter().collect();
+    sensors.sort_by(|(l, _), (r, _)| l.cmp(r));
+    let mut knows: Vec<_> = device.knows.iter().collect();
+    knows.sort();
+    export::json_object(vec![
+        ("survey", export::json_object(survey.fields().into_iter().map(|(key, value)| (key, export::json_string(&value))))),
+        ("address", export::json_string(&address.to_hex_string())),
+        ("manufacturer", device.manufacturer.as_ref().map_or_else(null, |OuiEntry { name_short, name_long, .. }| export::json_object(vec![
+            ("short", export::json_string(name_short)),
+            ("long", name_long.as_deref().map_or_else(null, export::json_string))
+        ]))),
+        ("role", device.role.map_or_else(null, |role| export::json_string(match role {
+            Role::AccessPoint => "ap",
+            Role::Station => "client"
+        }))),
+        ("associated", device.associated.map_or_else(null, |bssid| export::json_string(&bssid.to_hex_string()))),
+        ("ssid", device.beacon.as_deref().map_or_else(null, export::json_string)),
+        ("hidden", device.hidden.to_string()),
+        ("access", device.access.map_or_else(null, |access| export::json_string(&access.to_string()))),
+        ("power", device.power.map_or_else(null, |power| export::json_object(vec![
+            ("reported", power.reported.map_or_else(null, |reported| reported.to_string())),
+            ("limit", power.limit.map_or_else(null, |limit| limit.to_string())),
+            ("constraint", power.constraint.map_or_else(null, |constraint| constraint.to_string()))
+        ]))),
+        ("identity", device.identity.as_ref().map_or_else(null, |identity| export::json_string(&identity.to_string()))),
+        ("hostnames", list(device.hostnames.iter().map(|hostname| export::json_string(hostname)).collect())),
+        ("addresses", list(device.ip_addresses.iter().map(|address| export::json_string(&address.to_string())).collect())),
+        ("rates", device.rates.as_ref().map_or_else(null, |rates| list(rates.iter().map(|&rate| fingerprint::rate_name(rate)).map(|rate| export::json_string(&rate)).collect()))),
+        ("probes", export::json_object(probes.into_iter().map(|(ssid, count)| (ssid, count.to_string())))),
+        ("sightings", list(sensors.into_iter()
+            .map(|(sensor, sighting)| export::json_object(vec![
+                ("sensor", export::json_string(sensor)),
+                ("frames", sighting.frames.to_string()),
+                ("signal", sighting.signal.map_or_else(null, |signal| signal.to_string())),
+                ("channel", sighting.channel().map_or_else(null, |channel| channel.to_string())),
+                ("band", sighting.band().map_or_else(null, |band| export::json_string(&band.to_string()))),
+                ("frequency", sighting.frequency.map_or_else(null, |frequency| frequency.to_string()))
+            ]))
+            .collect())),
+        ("first_seen", unix(device.first_seen)),
+        ("last_seen", unix(device.last_seen)),
+        ("tags", list(device.tags.iter().map(|tag| export::json_string(tag)).collect())),
+        ("knows", list(knows.into_iter().map(|known| export::json_string(&known.to_hex_string())).collect()))
+    ])
+}
+
+/// Copy every frame in the captures that names the address in any of its address fields, giving how many were copied
+/// A BSSID is named by every frame of its network, so this keeps a network's whole exchange as well as a device's
+pub fn extract(address: MacAddress, captures: &[PathBuf], output: impl AsRef<Path>) -> Result<u64, Error> {
+    let mut savefile = Capture::dead(Linktype::IEEE802_11_RADIOTAP)
+        .and_then(|dead| dead.savefile(output))
+        .map_err(Error::Pcap)?;
+    let address = address.as_bytes();
+    let mut copied = 0;
+    for capture in captures {
+        let mut capture = Capture::from_file(capture).map_err(Error::Pcap)?;
+        loop {
+            let packet = match capture.next() {
+                Ok(packet) => packet,
+                Err(pcap::Error::NoMorePackets) => break,
+                Err(error) => return Err(Error::Pcap(error))
+            };
+            let data = match Radiotap::parse(packet.data) {
+                Ok((_, data)) => data,
+                Err(_) => continue
+            };
+            let names = [4..10, 10..16, 16..22].iter()
+                .any(|range| data.get(range.clone()) == Some(address));
+            if names {
+                savefile.write(&packet);
+                copied += 1;
+            }
+        }
+    }
+    savefile.flush().map_err(Error::Pcap)?;
+    Ok(copied)
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Pcap(pcap::Error)
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{}", error),
+            Self::Pcap(error) => write!(f, "unable to copy frames from the capture: {}", error)
+        }
+    }
+}
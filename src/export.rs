@@ -0,0 +1,254 @@
+use std::path::Path;
+
+/// A structured file format data can be exported as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Json
+}
+impl Format {
+    /// Pick a format from the extension of a path, defaulting to CSV
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("json") => Self::Json,
+            _ => Self::Csv
+        }
+    }
+}
+
+/// Quote a CSV field if it contains anything that would otherwise break the row
+pub fn csv_field(value: &str) -> String {
+    if value.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Escape text for an XML element or attribute
+pub fn xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            // Not allowed in XML 1.0 even escaped, yet SSIDs may hold them, as the NULs hidden networks beacon
+            c if (c as u32) < 0x20 || c == '\u{fffe}' || c == '\u{ffff}' => escaped.push(char::REPLACEMENT_CHARACTER),
+            c => escaped.push(c)
+        }
+    }
+    escaped
+}
+
+/// A JSON string literal, including the surrounding quotes
+pub fn json_string(value: &str) -> String {
+    let mut string = String::with_capacity(value.len() + 2);
+    string.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => string.push_str("\\\""),
+            '\\' => string.push_str("\\\\"),
+            '\n' => string.push_str("\\n"),
+            '\r' => string.push_str("\\r"),
+            '\t' => string.push_str("\\t"),
+            c if (c as u32) < 0x20 => string.push_str(&format!("\\u{:04x}", c as u32)),
+            c => string.push(c)
+        }
+    }
+    string.push('"');
+    string
+}
+
+/// A JSON object from already encoded values
+pub fn json_object<K: AsRef<str>>(fields: impl IntoIterator<Item = (K, String)>) -> String {
+    let fields: Vec<_> = fields.into_iter()
+        .map(|(key, value)| format!("{}:{}", json_string(key.as_ref()), value))
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+/// How deeply arrays and objects may nest when parsing, so a file of `[[[[…` can't overflow the stack
+const MAX_DEPTH: usize = 64;
+
+/// A parsed JSON value, for reading back what was written with the functions above
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>)
+}
+impl Json {
+    pub fn parse(text: &str) -> Option<Self> {
+        let (value, rest) = Self::parse_value(text.trim_start(), 0)?;
+        Some(value).filter(|_| rest.trim().is_empty())
+    }
+    /// The value of a field, if this is an object with it
+    pub fn get(&self, key: &str) -> Option<&Self> {
+        match self {
+            Self::Object(fields) => fields.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None
+        }
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(string) => Some(string),
+            _ => None
+        }
+    }
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(number) => Some(*number),
+            _ => None
+        }
+    }
+    /// Parse a value from the start of some text, giving back what follows it
+    fn parse_value(text: &str, depth: usize) -> Option<(Self, &str)> {
+        if depth > MAX_DEPTH {
+            return None
+        }
+        let text = text.trim_start();
+        let mut chars = text.chars();
+        match chars.next()? {
+            'n' => Some((Self::Null, text.strip_prefix("null")?)),
+            't' => Some((Self::Bool(true), text.strip_prefix("true")?)),
+            'f' => Some((Self::Bool(false), text.strip_prefix("false")?)),
+            '"' => {
+                let (string, rest) = Self::parse_string(&text[1..])?;
+                Some((Self::String(string), rest))
+            }
+            '[' => {
+                let mut items = vec![];
+                let mut rest = text[1..].trim_start();
+                if let Some(rest) = rest.strip_prefix(']') {
+                    return Some((Self::Array(items), rest))
+                }
+                loop {
+                    let (item, after) = Self::parse_value(rest, depth + 1)?;
+                    items.push(item);
+                    let after = after.trim_start();
+                    match after.chars().next()? {
+                        ',' => rest = &after[1..],
+                        ']' => return Some((Self::Array(items), &after[1..])),
+                        _ => return None
+                    }
+                }
+            }
+            '{' => {
+                let mut fields = vec![];
+                let mut rest = text[1..].trim_start();
+                if let Some(rest) = rest.strip_prefix('}') {
+                    return Some((Self::Object(fields), rest))
+                }
+                loop {
+                    let (key, after) = Self::parse_string(rest.trim_start().strip_prefix('"')?)?;
+                    let (value, after) = Self::parse_value(after.trim_start().strip_prefix(':')?, depth + 1)?;
+                    fields.push((key, value));
+                    let after = after.trim_start();
+                    match after.chars().next()? {
+                        ',' => rest = &after[1..],
+                        '}' => return Some((Self::Object(fields), &after[1..])),
+                        _ => return None
+                    }
+                }
+            }
+            _ => {
+                let end = text.find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c))).unwrap_or(text.len());
+                Some((Self::Number(text[..end].parse().ok()?), &text[end..]))
+            }
+        }
+    }
+    /// Parse the rest of a string after its opening quote
+    fn parse_string(text: &str) -> Option<(String, &str)> {
+        let mut string = String::new();
+        let mut chars = text.char_indices();
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '"' => return Some((string, &text[index + 1..])),
+                '\\' => string.push(match chars.next()?.1 {
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    'b' => '\u{8}',
+                    'f' => '\u{c}',
+                    'u' => {
+                        let hex: String = (0..4).filter_map(|_| chars.next().map(|(_, c)| c)).collect();
+                        char::from_u32(u32::from_str_radix(&hex, 16).ok()?).unwrap_or('\u{fffd}')
+                    }
+                    c => c
+                }),
+                c => string.push(c)
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xml_escapes_markup() {
+        assert_eq!(xml(r#"<Tom & Jerry's "Wi-Fi">"#), "&lt;Tom &amp; Jerry&apos;s &quot;Wi-Fi&quot;&gt;");
+        assert_eq!(xml("Café\tWi-Fi"), "Café\tWi-Fi");
+    }
+
+    #[test]
+    fn xml_replaces_characters_xml_forbids() {
+        // As a hidden network beacons in place of its name
+        let hidden = "\0".repeat(8);
+        assert_eq!(xml(&hidden), "\u{fffd}".repeat(8));
+        assert_eq!(xml("a\u{1b}b\u{ffff}"), "a\u{fffd}b\u{fffd}");
+        assert!(!xml(&hidden).chars().any(|c| (c as u32) < 0x20));
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let written = json_object(vec![
+            ("ssid", json_string("Café \"Wi-Fi\"\n\t\\ \u{1}")),
+            ("signal", "-61.5".to_string()),
+            ("hidden", "false".to_string()),
+            ("tags", format!("[{},{}]", json_string("suspect"), json_string("roaming"))),
+            ("vendor", "null".to_string()),
+            ("nested", json_object(vec![("empty", "[]".to_string()), ("none", "{}".to_string())]))
+        ]);
+        let parsed = Json::parse(&written).unwrap();
+        assert_eq!(parsed.get("ssid").and_then(Json::as_str), Some("Café \"Wi-Fi\"\n\t\\ \u{1}"));
+        assert_eq!(parsed.get("signal").and_then(Json::as_f64), Some(-61.5));
+        assert_eq!(parsed.get("hidden"), Some(&Json::Bool(false)));
+        assert_eq!(parsed.get("tags"), Some(&Json::Array(vec![Json::String("suspect".to_string()), Json::String("roaming".to_string())])));
+        assert_eq!(parsed.get("vendor"), Some(&Json::Null));
+        assert_eq!(parsed.get("nested").and_then(|nested| nested.get("empty")), Some(&Json::Array(vec![])));
+        assert_eq!(parsed.get("nested").and_then(|nested| nested.get("none")), Some(&Json::Object(vec![])));
+        assert_eq!(parsed.get("missing"), None);
+    }
+
+    #[test]
+    fn json_whitespace_and_escapes() {
+        let parsed = Json::parse(" { \"a\" : [ 1 , 2e3 , true ] , \"b\" : \"\\u00e9\\b\\f\" } ").unwrap();
+        assert_eq!(parsed.get("a"), Some(&Json::Array(vec![Json::Number(1.0), Json::Number(2000.0), Json::Bool(true)])));
+        assert_eq!(parsed.get("b").and_then(Json::as_str), Some("é\u{8}\u{c}"));
+    }
+
+    #[test]
+    fn json_malformed() {
+        for text in &["", "{", "[1,", "[1 2]", "{\"a\" 1}", "{a:1}", "\"open", "nul", "tru", "-", "[1]]", "{\"a\":1,}", "\"\\u12\""] {
+            assert_eq!(Json::parse(text), None, "{:?} parsed", text);
+        }
+    }
+
+    #[test]
+    fn json_nesting_is_bounded() {
+        assert!(Json::parse(&format!("{}{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH))).is_some());
+        // Refused rather than overflowing the stack
+        assert_eq!(Json::parse(&"[".repeat(1_000_000)), None);
+        assert_eq!(Json::parse(&"{\"a\":".repeat(100_000)), None);
+    }
+}
@@ -0,0 +1,386 @@
+use std::fmt;
+use regex::Regex;
+
+use crate::{page::LogEntry, wifi::Kind};
+
+/// A display filter over captured frames, such as `src == aa:bb:cc:dd:ee:ff && subtype == probe-req`
+///
+/// Comparisons take the form `field op value`, with `==`, `!=`, `<`, `<=`, `>`, `>=` and `~` for a regex match,
+/// and may be combined with `&&` (`and`), `||` (`or`), `!` (`not`) and parentheses
+#[derive(Debug)]
+pub struct Filter {
+    source: String,
+    expression: Expression
+}
+impl Filter {
+    pub fn parse(source: &str) -> Result<Self> {
+        let tokens = tokenise(source)?;
+        let mut parser = Parser { tokens: &tokens, position: 0 };
+        let expression = parser.or()?;
+        if let Some(token) = parser.peek() {
+            return Err(Error::Unexpected(token.to_string()))
+        }
+        Ok(Self {
+            source: source.to_string(),
+            expression
+        })
+    }
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        self.expression.matches(entry)
+    }
+}
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Source,
+    Destination,
+    Type,
+    Subtype,
+    Signal,
+    Length,
+    Ssid,
+    Sensor
+}
+impl Field {
+    fn parse(name: &str) -> Result<Self> {
+        Ok(match name {
+            "src" | "source" | "ta" => Self::Source,
+            "dst" | "destination" | "ra" => Self::Destination,
+            "type" => Self::Type,
+            "subtype" => Self::Subtype,
+            "signal" => Self::Signal,
+            "len" | "length" => Self::Length,
+            "ssid" => Self::Ssid,
+            "sensor" => Self::Sensor,
+            _ => return Err(Error::UnknownField(name.to_string()))
+        })
+    }
+    fn numeric(self) -> bool {
+        self == Self::Signal || self == Self::Length
+    }
+    fn value(self, entry: &LogEntry) -> Option<Value> {
+        let summary = &entry.summary;
+        Some(match self {
+            Self::Source => Value::Text(summary.transmitter?.to_hex_string()),
+            Self::Destination => Value::Text(summary.receiver.to_hex_string()),
+            Self::Type => Value::Text(match summary.kind {
+                Kind::Management => "mgmt",
+                Kind::Control => "ctrl",
+                Kind::Data => "data",
+                Kind::Extension => "ext"
+            }.to_string()),
+            Self::Subtype => Value::Text(summary.subtype_name().to_string()),
+            Self::Signal => Value::Number(entry.signal? as i64),
+            Self::Length => Value::Number(summary.length as i64),
+            Self::Ssid => Value::Text(entry.ssid.clone()?),
+            Self::Sensor => Value::Text(entry.sensor.clone())
+        })
+    }
+}
+
+#[derive(Debug)]
+enum Value {
+    Text(String),
+    Number(i64)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual
+}
+
+#[derive(Debug)]
+enum Expression {
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>),
+    Compare(Field, Operator, Value),
+    Matches(Field, Regex)
+}
+impl Expression {
+    fn matches(&self, entry: &LogEntry) -> bool {
+        match self {
+            Self::And(left, right) => left.matches(entry) && right.matches(entry),
+            Self::Or(left, right) => left.matches(entry) || right.matches(entry),
+            Self::Not(expression) => !expression.matches(entry),
+            // A frame without the field never matches, as in Wireshark
+            Self::Compare(field, operator, expected) => match (field.value(entry), expected) {
+                (Some(Value::Number(actual)), Value::Number(expected)) => match operator {
+                    Operator::Equal => actual == *expected,
+                    Operator::NotEqual => actual != *expected,
+                    Operator::Less => actual < *expected,
+                    Operator::LessEqual => actual <= *expected,
+                    Operator::Greater => actual > *expected,
+                    Operator::GreaterEqual => actual >= *expected
+                },
+                (Some(Value::Text(actual)), Value::Text(expected)) => match operator {
+                    Operator::Equal => actual.eq_ignore_ascii_case(expected),
+                    Operator::NotEqual => !actual.eq_ignore_ascii_case(expected),
+                    _ => false
+                },
+                _ => false
+            },
+            Self::Matches(field, regex) => match field.value(entry) {
+                Some(Value::Text(actual)) => regex.is_match(&actual),
+                Some(Value::Number(actual)) => regex.is_match(&actual.to_string()),
+                None => false
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Operator(&'static str),
+    And,
+    Or,
+    Not,
+    Open,
+    Close
+}
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Word(word) => write!(f, "{}", word),
+            Self::Quoted(text) => write!(f, "{:?}", text),
+            Self::Operator(operator) => write!(f, "{}", operator),
+            Self::And => write!(f, "&&"),
+            Self::Or => write!(f, "||"),
+            Self::Not => write!(f, "!"),
+            Self::Open => write!(f, "("),
+            Self::Close => write!(f, ")")
+        }
+    }
+}
+
+fn tokenise(source: &str) -> Result<Vec<Token>> {
+    const OPERATORS: [&str; 9] = ["==", "!=", "<=", ">=", "&&", "||", "<", ">", "~"];
+    let mut tokens = vec![];
+    let mut rest = source.trim_start();
+    while let Some(c) = rest.chars().next() {
+        if let Some(&operator) = OPERATORS.iter().find(|&&operator| rest.starts_with(operator)) {
+            tokens.push(match operator {
+                "&&" => Token::And,
+                "||" => Token::Or,
+                operator => Token::Operator(operator)
+            });
+            rest = &rest[operator.len()..];
+        } else if c == '!' {
+            tokens.push(Token::Not);
+            rest = &rest[1..];
+        } else if c == '(' || c == ')' {
+            tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            rest = &rest[1..];
+        } else if c == '"' {
+            let end = rest[1..].find('"').ok_or(Error::UnterminatedString)?;
+            tokens.push(Token::Quoted(rest[1..1 + end].to_string()));
+            rest = &rest[end + 2..];
+        } else {
+            let end = rest.find(|c: char| c.is_whitespace() || "()!=<>~&|\"".contains(c)).unwrap_or(rest.len());
+            // A lone `=`, `&` or `|` starts no operator or word, and would otherwise never be consumed
+            if end == 0 {
+                return Err(Error::Unexpected(c.to_string()))
+            }
+            let word = &rest[..end];
+            tokens.push(match word {
+                "and" => Token::And,
+                "or" => Token::Or,
+                "not" => Token::Not,
+                word => Token::Word(word.to_string())
+            });
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize
+}
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position)
+    }
+    fn next(&mut self) -> Result<&'a Token> {
+        let token = self.peek().ok_or(Error::UnexpectedEnd)?;
+        self.position += 1;
+        Ok(token)
+    }
+    fn or(&mut self) -> Result<Expression> {
+        let mut expression = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.position += 1;
+            expression = Expression::Or(Box::new(expression), Box::new(self.and()?));
+        }
+        Ok(expression)
+    }
+    fn and(&mut self) -> Result<Expression> {
+        let mut expression = self.unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.position += 1;
+            expression = Expression::And(Box::new(expression), Box::new(self.unary()?));
+        }
+        Ok(expression)
+    }
+    fn unary(&mut self) -> Result<Expression> {
+        match self.next()? {
+            Token::Not => Ok(Expression::Not(Box::new(self.unary()?))),
+            Token::Open => {
+                let expression = self.or()?;
+                match self.next()? {
+                    Token::Close => Ok(expression),
+                    token => Err(Error::Unexpected(token.to_string()))
+                }
+            }
+            Token::Word(field) => self.comparison(Field::parse(field)?),
+            token => Err(Error::Unexpected(token.to_string()))
+        }
+    }
+    fn comparison(&mut self, field: Field) -> Result<Expression> {
+        let operator = match self.next()? {
+            Token::Operator(operator) => *operator,
+            token => return Err(Error::Unexpected(token.to_string()))
+        };
+        let value = match self.next()? {
+            Token::Word(value) | Token::Quoted(value) => value,
+            token => return Err(Error::Unexpected(token.to_string()))
+        };
+        if operator == "~" {
+            return Ok(Expression::Matches(field, Regex::new(value).map_err(Error::Regex)?))
+        }
+        let operator = match operator {
+            "==" => Operator::Equal,
+            "!=" => Operator::NotEqual,
+            "<" => Operator::Less,
+            "<=" => Operator::LessEqual,
+            ">" => Operator::Greater,
+            _ => Operator::GreaterEqual
+        };
+        let value = if field.numeric() {
+            Value::Number(value.parse().map_err(|_| Error::NotANumber(value.to_string()))?)
+        } else {
+            // Addresses may be written with dashes as well as colons
+            Value::Text(if field == Field::Source || field == Field::Destination { value.replace('-', ":") } else { value.to_string() })
+        };
+        Ok(Expression::Compare(field, operator, value))
+    }
+}
+
+type Result<T> = std::result::Result<T, Error>;
+#[derive(Debug)]
+pub enum Error {
+    UnexpectedEnd,
+    Unexpected(String),
+    UnterminatedString,
+    UnknownField(String),
+    NotANumber(String),
+    Regex(regex::Error)
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd => write!(f, "the filter ends unexpectedly"),
+            Self::Unexpected(token) => write!(f, "unexpected `{}`", token),
+            Self::UnterminatedString => write!(f, "a quoted value is missing its closing quote"),
+            Self::UnknownField(field) => write!(f, "unknown field `{}`, expected one of src, dst, type, subtype, signal, len, ssid or sensor", field),
+            Self::NotANumber(value) => write!(f, "`{}` is not a number", value),
+            Self::Regex(error) => write!(f, "invalid regex: {}", error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use super::*;
+    use crate::wifi::Summary;
+
+    /// A beacon from a4:c3:f0:11:22:33, heard at -60dBm
+    fn beacon() -> LogEntry {
+        let frame = [
+            0x80, 0x00, 0x00, 0x00,
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            0xa4, 0xc3, 0xf0, 0x11, 0x22, 0x33,
+            0xa4, 0xc3, 0xf0, 0x11, 0x22, 0x33,
+            0x10, 0x00
+        ];
+        LogEntry {
+            time: Duration::from_secs(1),
+            sensor: "wlan0".to_string(),
+            signal: Some(-60),
+            summary: Summary::parse(&frame).unwrap(),
+            ssid: Some("CoffeeShop".to_string()),
+            channel: Some(6),
+            airtime: Duration::from_micros(100)
+        }
+    }
+    fn matches(filter: &str) -> bool {
+        Filter::parse(filter).unwrap_or_else(|error| panic!("{} didn't parse: {}", filter, error)).matches(&beacon())
+    }
+
+    #[test]
+    fn comparisons() {
+        assert!(matches("src == a4:c3:f0:11:22:33"));
+        assert!(matches("src == A4-C3-F0-11-22-33"));
+        assert!(matches("dst != a4:c3:f0:11:22:33"));
+        assert!(matches("type == mgmt"));
+        assert!(matches("subtype == beacon"));
+        assert!(matches("signal < -50"));
+        assert!(matches("signal <= -60"));
+        assert!(matches("signal > -70"));
+        assert!(matches("signal >= -60"));
+        assert!(!matches("signal > -60"));
+        assert!(matches("len == 24"));
+        assert!(matches(r#"ssid == "coffeeshop""#));
+        assert!(matches("ssid ~ Coffee"));
+        assert!(matches("sensor == wlan0"));
+        // Text can only be compared for equality
+        assert!(!matches("ssid < z"));
+    }
+
+    #[test]
+    fn precedence() {
+        // And binds tighter than or, and not tighter than either
+        assert!(matches("subtype == beacon || subtype == ack && signal > -10"));
+        assert!(!matches("(subtype == beacon || subtype == ack) && signal > -10"));
+        assert!(matches("!subtype == beacon || signal < 0"));
+        assert!(!matches("!(subtype == beacon || signal < 0)"));
+        assert!(matches("not subtype == ack and (signal < 0 or len > 1000)"));
+    }
+
+    #[test]
+    fn missing_fields_never_match() {
+        let mut entry = beacon();
+        entry.signal = None;
+        assert!(!Filter::parse("signal < 0").unwrap().matches(&entry));
+        assert!(!Filter::parse("signal >= 0").unwrap().matches(&entry));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(Filter::parse("ssid = x"), Err(Error::Unexpected(token)) if token == "="));
+        assert!(matches!(Filter::parse("a & b"), Err(Error::Unexpected(token)) if token == "&"));
+        assert!(matches!(Filter::parse("a | b"), Err(Error::Unexpected(token)) if token == "|"));
+        assert!(matches!(Filter::parse("="), Err(Error::Unexpected(_))));
+        assert!(matches!(Filter::parse("src =="), Err(Error::UnexpectedEnd)));
+        assert!(matches!(Filter::parse("(src == x"), Err(Error::UnexpectedEnd)));
+        assert!(matches!(Filter::parse("src == x)"), Err(Error::Unexpected(token)) if token == ")"));
+        assert!(matches!(Filter::parse(r#"ssid == "open"#), Err(Error::UnterminatedString)));
+        assert!(matches!(Filter::parse("colour == red"), Err(Error::UnknownField(field)) if field == "colour"));
+        assert!(matches!(Filter::parse("signal > loud"), Err(Error::NotANumber(value)) if value == "loud"));
+    }
+}
@@ -0,0 +1,171 @@
+use std::{
+    fs::File,
+    io::{self, Seek, SeekFrom, Write},
+    path::Path,
+    time::Duration
+};
+use oui::OuiEntry;
+
+use crate::{DeviceList, Role, alerts::{Alert, Kind, Severity}, export, survey::Survey};
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// How findings are written, for the reporting platform they are headed for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// SARIF 2.1.0, with a rule for each kind of alert and a result for each alert
+    Sarif,
+    /// A list of findings with a title, severity and description each, as DefectDojo's generic importer and Dradis plugins read
+    Json
+}
+impl Format {
+    /// Pick a format from the extension of a path, defaulting to SARIF
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("json") => Self::Json,
+            _ => Self::Sarif
+        }
+    }
+}
+
+/// Replace the contents of a file with every alert raised so far
+/// The file is rewritten rather than reopened so it can be kept open from before the sandbox was applied
+pub fn rewrite(file: &mut File, format: Format, alerts: &[Alert], devices: &DeviceList, survey: &Survey) -> io::Result<()> {
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    write(file, format, alerts, devices, survey)
+}
+
+/// Write every alert as a finding, with what was known of the device it is about as evidence
+pub fn write(output: &mut impl Write, format: Format, alerts: &[Alert], devices: &DeviceList, survey: &Survey) -> io::Result<()> {
+    let findings = match format {
+        Format::Sarif => sarif(alerts, devices, survey),
+        Format::Json => export::json_object(vec![
+            ("findings", format!("[{}]", alerts.iter().map(|alert| finding(alert, devices, survey)).collect::<Vec<_>>().join(",")))
+        ])
+    };
+    writeln!(output, "{}", findings)
+}
+
+fn sarif(alerts: &[Alert], devices: &DeviceList, survey: &Survey) -> String {
+    let rules: Vec<_> = Kind::ALL.iter()
+        .map(|kind| export::json_object(vec![
+            ("id", export::json_string(kind.name())),
+            ("shortDescription", export::json_object(vec![("text", export::json_string(kind.description()))])),
+            ("defaultConfiguration", export::json_object(vec![("level", export::json_string(level(kind.default_severity())))]))
+        ]))
+        .collect();
+    let results: Vec<_> = alerts.iter()
+        .map(|alert| export::json_object(vec![
+            ("ruleId", export::json_string(alert.kind.name())),
+            ("level", export::json_string(level(alert.severity))),
+            ("message", export::json_object(vec![("text", export::json_string(&survey.anonymizer.text(&alert.message)))])),
+            ("locations", format!("[{}]", export::json_object(vec![
+                ("logicalLocations", format!("[{}]", export::json_object(vec![
+                    ("name", export::json_string(&survey.anonymizer.address_string(&alert.address))),
+                    ("kind", export::json_string("device"))
+                ])))
+            ]))),
+            ("properties", evidence(alert, devices, survey))
+        ]))
+        .collect();
+    let survey_fields = survey.fields().into_iter().map(|(key, value)| (key, export::json_string(&value)));
+    export::json_object(vec![
+        ("$schema", export::json_string(SARIF_SCHEMA)),
+        ("version", export::json_string("2.1.0")),
+        ("runs", format!("[{}]", export::json_object(vec![
+            ("tool", export::json_object(vec![("driver", export::json_object(vec![
+                ("name", export::json_string("blockade-recon")),
+                ("version", export::json_string(env!("CARGO_PKG_VERSION"))),
+                ("rules", format!("[{}]", rules.join(",")))
+            ]))])),
+            ("invocations", format!("[{}]", export::json_object(vec![
+                ("executionSuccessful", "true".to_string()),
+                ("startTimeUtc", export::json_string(&utc(survey.started_unix())))
+            ]))),
+            ("properties", export::json_object(survey_fields)),
+            ("results", format!("[{}]", results.join(",")))
+        ])))
+    ])
+}
+
+/// A finding in the generic form most reporting platforms can import
+fn finding(alert: &Alert, devices: &DeviceList, survey: &Survey) -> String {
+    let anonymizer = &survey.anonymizer;
+    let device = devices.get(&alert.address);
+    let subject = match device.and_then(|device| device.beacon.as_deref()).filter(|ssid| !ssid.is_empty()) {
+        Some(ssid) => format!("{} ({:?})", anonymizer.address_string(&alert.address), anonymizer.ssid(ssid)),
+        None => anonymizer.address_string(&alert.address)
+    };
+    let raised = survey.started_unix() + alert.time.as_secs();
+    export::json_object(vec![
+        ("title", export::json_string(&format!("{}: {}", alert.kind.description(), subject))),
+        ("severity", export::json_string(match alert.severity {
+            Severity::Info => "Info",
+            Severity::Warning => "Medium",
+            Severity::Critical => "High"
+        })),
+        ("description", export::json_string(&anonymizer.text(&alert.message))),
+        ("date", export::json_string(&utc(raised)[..10])),
+        ("unique_id_from_tool", export::json_string(&anonymizer.text(&alert.key()))),
+        ("vuln_id_from_tool", export::json_string(alert.kind.name())),
+        ("evidence", evidence(alert, devices, survey))
+    ])
+}
+
+/// When an alert was raised and what was known of its device, for a reviewer to go on without the capture
+fn evidence(alert: &Alert, devices: &DeviceList, survey: &Survey) -> String {
+    let null = || "null".to_string();
+    let at = |time: Duration| export::json_string(&utc(survey.started_unix() + time.as_secs()));
+    let anonymizer = &survey.anonymizer;
+    let device = devices.get(&alert.address);
+    let mut fields = vec![
+        ("address", export::json_string(&anonymizer.address_string(&alert.address))),
+        ("raised", at(alert.time)),
+        ("acknowledged", alert.acknowledged.to_string()),
+        ("escalated", alert.escalated.to_string())
+    ];
+    if let Some(device) = device {
+        let strongest = device.strongest_sensor();
+        fields.extend(vec![
+            ("manufacturer", device.manufacturer.as_ref().map_or_else(null, |OuiEntry { name_short, .. }| export::json_string(name_short))),
+            ("role", device.role.map_or_else(null, |role| export::json_string(match role {
+                Role::AccessPoint => "ap",
+                Role::Station => "client"
+            }))),
+            ("ssid", device.beacon.as_deref().map_or_else(null, |ssid| export::json_string(&anonymizer.ssid(ssid)))),
+            ("bssid", device.associated.map_or_else(null, |bssid| export::json_string(&anonymizer.address_string(&bssid)))),
+            ("security", device.security.map_or_else(null, |security| export::json_string(&security.to_string()))),
+            ("first_seen", device.first_seen.map_or_else(null, at)),
+            ("last_seen", device.last_seen.map_or_else(null, at)),
+            ("sensor", strongest.map_or_else(null, |(sensor, _)| export::json_string(sensor))),
+            ("signal", strongest.and_then(|(_, sighting)| sighting.signal).map_or_else(null, |signal| signal.to_string())),
+            ("channel", strongest.and_then(|(_, sighting)| sighting.channel()).map_or_else(null, |channel| channel.to_string()))
+        ]);
+    }
+    export::json_object(fields)
+}
+
+fn level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Info => "note",
+        Severity::Warning => "warning",
+        Severity::Critical => "error"
+    }
+}
+
+/// Seconds since the Unix epoch as an RFC 3339 time in UTC, such as `2021-03-14T15:09:26Z`
+fn utc(unix: u64) -> String {
+    let (days, seconds) = (unix / 86400, unix % 86400);
+    // Howard Hinnant's days-to-civil algorithm, on eras of 400 years starting from the 1st of March
+    let days = days as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
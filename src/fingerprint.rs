@@ -0,0 +1,157 @@
+use std::fmt;
+
+use crate::{DeviceList, KnownDevice, wifi::Tag};
+
+/// The rates defined by 802.11b, in units of 500kbps
+const DSSS_RATES: [u8; 4] = [2, 4, 11, 22];
+/// The rates defined by 802.11a and g
+const OFDM_RATES: [u8; 8] = [12, 18, 24, 36, 48, 72, 96, 108];
+/// Values from here up stand for a required feature, such as HT or SAE, rather than a rate
+const MEMBERSHIP_SELECTORS: u8 = 122;
+
+/// What the rates a device advertises give away about its radio
+/// Drivers fill these in from fixed tables, so an odd or rare set is a quick tell for a chipset or an attack tool
+pub struct Fingerprint {
+    /// The distinct rates, ignoring which are basic, in ascending order
+    pub rates: Vec<u8>,
+    /// How many other devices advertise exactly the same rates
+    pub shared: usize,
+    pub oddities: Vec<String>
+}
+impl Fingerprint {
+    /// Fingerprint a device against every other one seen, or None if it has never advertised its rates
+    pub fn of(device: &KnownDevice, devices: &DeviceList) -> Option<Self> {
+        let rates = rate_set(device.rates.as_ref()?);
+        let shared = devices.values()
+            .filter(|other| other.rates.as_deref().map_or(false, |other| rate_set(other) == rates))
+            .count()
+            .saturating_sub(1);
+
+        let mut oddities = vec![];
+        let advertised = device.rates.as_ref().unwrap();
+        let rates_only: Vec<_> = advertised.iter().map(|rate| rate & 0x7f).filter(|&rate| rate < MEMBERSHIP_SELECTORS).collect();
+        if rates_only.is_empty() {
+            oddities.push("Advertises no rates at all".to_string());
+        } else if rates_only.iter().all(|rate| DSSS_RATES.contains(rate)) {
+            oddities.push("Only supports 802.11b rates".to_string());
+        }
+        let unknown: Vec<_> = nonstandard(&rates).into_iter().map(rate_name).collect();
+        if !unknown.is_empty() {
+            oddities.push(format!("Advertises non-standard rates: {}", unknown.join(", ")));
+        }
+        if rates.len() != advertised.len() {
+            oddities.push("Repeats a rate".to_string());
+        }
+        Some(Self {
+            rates,
+            shared,
+            oddities
+        })
+    }
+}
+
+/// The distinct rates in a set, without the basic rate flag, so sets can be compared
+pub fn rate_set(rates: &[u8]) -> Vec<u8> {
+    let mut rates: Vec<_> = rates.iter().map(|rate| rate & 0x7f).collect();
+    rates.sort_unstable();
+    rates.dedup();
+    rates
+}
+
+/// Rates in a set that no 802.11b, a or g radio uses
+pub fn nonstandard(rates: &[u8]) -> Vec<u8> {
+    rate_set(rates).into_iter()
+        .filter(|rate| *rate < MEMBERSHIP_SELECTORS && !DSSS_RATES.contains(rate) && !OFDM_RATES.contains(rate))
+        .collect()
+}
+
+/// A rate in Mbps, or the feature a membership selector requires
+pub fn rate_name(rate: u8) -> String {
+    match rate & 0x7f {
+        127 => "HT".to_string(),
+        126 => "VHT".to_string(),
+        123 => "SAE hash-to-element".to_string(),
+        122 => "EPD".to_string(),
+        rate if rate % 2 == 0 => format!("{}", rate / 2),
+        rate => format!("{}.5", rate / 2)
+    }
+}
+
+/// Vendor elements added by the maker of a phone or computer, which give away its platform
+const PLATFORMS: [([u8; 3], &str); 2] = [
+    ([0x00, 0x17, 0xf2], "Apple iPhone, iPad or Mac"),
+    ([0x00, 0x00, 0xf0], "Samsung Android")
+];
+/// Vendor elements added by the Wi-Fi chipset's driver whatever device it is in
+const CHIPSETS: [([u8; 3], &str); 7] = [
+    ([0x00, 0x10, 0x18], "Broadcom"),
+    ([0x00, 0x90, 0x4c], "Broadcom"),
+    ([0x00, 0x03, 0x7f], "Qualcomm Atheros"),
+    ([0x8c, 0xfd, 0xf0], "Qualcomm"),
+    ([0x00, 0x0c, 0xe7], "MediaTek"),
+    ([0x00, 0x0c, 0x43], "Ralink"),
+    ([0x00, 0xe0, 0x4c], "Realtek")
+];
+/// The Wi-Fi Alliance OUI and vendor element type announcing Wi-Fi Direct, which phones add to their probes far more than laptops
+const WFA: [u8; 3] = [0x50, 0x6f, 0x9a];
+const P2P: u8 = 0x09;
+
+/// What a client is, going by the vendor and WPS elements in its probe and association requests
+/// Unlike the manufacturer of its address, these stay the same when a client randomises its address
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Identity {
+    /// The maker and model the device names in WPS
+    pub device: Option<String>,
+    /// The kind of device its vendor elements point to
+    pub platform: Option<&'static str>,
+    pub chipset: Option<&'static str>
+}
+impl Identity {
+    /// Identify a client from the elements of one request, if there is anything to go on
+    pub fn of(tags: &[Tag]) -> Option<Self> {
+        let mut identity = Self::default();
+        let mut wifi_direct = false;
+        for tag in tags {
+            match tag {
+                Tag::Wps { manufacturer, model, model_number, device_name, .. } => {
+                    let mut parts: Vec<&str> = vec![];
+                    parts.extend(manufacturer.as_deref());
+                    parts.extend(model.as_deref().or(model_number.as_deref()));
+                    let mut device = parts.join(" ");
+                    if let Some(name) = device_name.as_deref().filter(|name| !parts.contains(name)) {
+                        device = if device.is_empty() { format!("{:?}", name) } else { format!("{} {:?}", device, name) };
+                    }
+                    identity.device = Some(device).filter(|device| !device.is_empty());
+                }
+                Tag::VendorSpecific { vendor: WFA, kind: Some(P2P) } => wifi_direct = true,
+                Tag::VendorSpecific { vendor, .. } => {
+                    let find = |table: &[([u8; 3], &'static str)]| table.iter().find(|(oui, _)| oui == vendor).map(|&(_, name)| name);
+                    identity.platform = identity.platform.or_else(|| find(&PLATFORMS));
+                    identity.chipset = identity.chipset.or_else(|| find(&CHIPSETS));
+                }
+                _ => ()
+            }
+        }
+        if wifi_direct && identity.platform.is_none() {
+            identity.platform = Some("Android");
+        }
+        Some(identity).filter(|identity| *identity != Self::default())
+    }
+    /// Add what was learnt from another request, keeping what this one already knew
+    pub fn merge(&mut self, other: Self) {
+        self.device = self.device.take().or(other.device);
+        self.platform = self.platform.or(other.platform);
+        self.chipset = self.chipset.or(other.chipset);
+    }
+}
+impl fmt::Display for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let chipset = self.chipset.map(|chipset| format!("{} chipset", chipset));
+        match (self.device.as_deref().or(self.platform), chipset) {
+            (Some(device), Some(chipset)) => write!(f, "{}, {}", device, chipset),
+            (Some(device), None) => write!(f, "{}", device),
+            (None, Some(chipset)) => write!(f, "{}", chipset),
+            (None, None) => write!(f, "Unknown")
+        }
+    }
+}
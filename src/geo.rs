@@ -0,0 +1,154 @@
+use std::{
+    fs::File,
+    io::{self, Seek, SeekFrom, Write},
+    path::Path,
+    time::Duration
+};
+
+use crate::{DeviceList, Role, anonymize::Anonymizer, export, gps::Fix};
+
+/// A track only gains a point once the sensor has moved this far from the last, in metres
+const MIN_MOVE: f64 = 10.0;
+/// Points kept per device, beyond which only the strongest signal is still updated
+const MAX_POINTS: usize = 1000;
+/// The mean radius of the Earth in metres, for distances between fixes
+const EARTH_RADIUS: f64 = 6_371_000.0;
+
+/// Where a device was heard from, and how well
+#[derive(Debug, Clone, Copy)]
+pub struct Point {
+    pub fix: Fix,
+    pub signal: Option<i8>,
+    /// Since the capture started
+    pub time: Duration
+}
+
+/// Where a device has been heard from as the sensor moved
+#[derive(Debug, Default)]
+pub struct Track {
+    /// Where it was heard loudest, which for a fixed access point is the best guess of where it is
+    pub strongest: Option<Point>,
+    pub points: Vec<Point>
+}
+impl Track {
+    pub fn heard(&mut self, fix: Fix, signal: Option<i8>, time: Duration) {
+        let point = Point { fix, signal, time };
+        if self.strongest.map_or(true, |strongest| signal > strongest.signal) {
+            self.strongest = Some(point);
+        }
+        let moved = self.points.last().map_or(true, |last| distance(last.fix, fix) >= MIN_MOVE);
+        if moved && self.points.len() < MAX_POINTS {
+            self.points.push(point);
+        }
+    }
+}
+
+/// The great circle distance between two fixes in metres
+fn distance(from: Fix, to: Fix) -> f64 {
+    let (from_latitude, to_latitude) = (from.latitude.to_radians(), to.latitude.to_radians());
+    let half_chord = ((to_latitude - from_latitude) / 2.0).sin().powi(2)
+        + from_latitude.cos() * to_latitude.cos() * ((to.longitude - from.longitude).to_radians() / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * half_chord.sqrt().asin()
+}
+
+/// A file format for mapping tools
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// For Google Earth
+    Kml,
+    /// For QGIS and most web maps
+    GeoJson
+}
+impl Format {
+    /// Pick a format from the extension of a path, defaulting to GeoJSON
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("kml") => Self::Kml,
+            _ => Self::GeoJson
+        }
+    }
+}
+
+/// Replace the contents of a file with every device heard with a fix
+/// The file is rewritten rather than reopened so it can be kept open from before the sandbox was applied
+pub fn rewrite(file: &mut File, format: Format, devices: &DeviceList, anonymizer: &Anonymizer) -> io::Result<()> {
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    write(file, format, devices, anonymizer)
+}
+
+/// Write a point where each access point was heard loudest, and a line along where each other device was heard
+pub fn write(output: &mut impl Write, format: Format, devices: &DeviceList, anonymizer: &Anonymizer) -> io::Result<()> {
+    let mut located: Vec<_> = devices.iter()
+        .filter_map(|(address, device)| Some((address, device, device.geo.strongest?)))
+        .collect();
+    located.sort_by_key(|&(address, _, _)| *address);
+    match format {
+        Format::GeoJson => {
+            let mut features = vec![];
+            for (address, device, strongest) in located {
+                let mut properties = vec![
+                    ("address", export::json_string(&anonymizer.address_string(address))),
+                    ("ssid", device.beacon.as_deref().map_or_else(|| "null".to_string(), |ssid| export::json_string(&anonymizer.ssid(ssid)))),
+                    ("manufacturer", device.manufacturer.as_ref().map_or_else(|| "null".to_string(), |entry| export::json_string(&entry.name_short))),
+                    ("signal", strongest.signal.map_or_else(|| "null".to_string(), |signal| signal.to_string())),
+                    ("time", format!("{:.3}", strongest.time.as_secs_f64()))
+                ];
+                if device.role == Some(Role::AccessPoint) {
+                    properties.push(("kind", export::json_string("strongest")));
+                    features.push(feature("Point", coordinates(strongest.fix), properties));
+                } else if device.geo.points.len() > 1 {
+                    properties.push(("kind", export::json_string("track")));
+                    let line: Vec<_> = device.geo.points.iter().map(|point| coordinates(point.fix)).collect();
+                    features.push(feature("LineString", format!("[{}]", line.join(",")), properties));
+                } else {
+                    properties.push(("kind", export::json_string("sighting")));
+                    features.push(feature("Point", coordinates(strongest.fix), properties));
+                }
+            }
+            writeln!(output, "{}", export::json_object(vec![
+                ("type", export::json_string("FeatureCollection")),
+                ("features", format!("[{}]", features.join(",")))
+            ]))
+        }
+        Format::Kml => {
+            writeln!(output, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+            writeln!(output, r#"<kml xmlns="http://www.opengis.net/kml/2.2"><Document><name>blockade-recon</name>"#)?;
+            for (address, device, strongest) in located {
+                let name = match device.beacon.as_deref().filter(|ssid| !ssid.is_empty()) {
+                    Some(ssid) => format!("{} {}", anonymizer.address_string(address), anonymizer.ssid(ssid)),
+                    None => anonymizer.address_string(address)
+                };
+                let description = format!(
+                    "{}{}",
+                    device.manufacturer.as_ref().map_or_else(String::new, |entry| format!("{}, ", entry.name_short)),
+                    strongest.signal.map_or_else(|| "no signal reported".to_string(), |signal| format!("strongest {}dBm", signal))
+                );
+                let geometry = if device.role != Some(Role::AccessPoint) && device.geo.points.len() > 1 {
+                    let line: Vec<_> = device.geo.points.iter().map(|point| format!("{},{}", point.fix.longitude, point.fix.latitude)).collect();
+                    format!("<LineString><coordinates>{}</coordinates></LineString>", line.join(" "))
+                } else {
+                    format!("<Point><coordinates>{},{}</coordinates></Point>", strongest.fix.longitude, strongest.fix.latitude)
+                };
+                writeln!(output, "<Placemark><name>{}</name><description>{}</description>{}</Placemark>", export::xml(&name), export::xml(&description), geometry)?;
+            }
+            writeln!(output, "</Document></kml>")
+        }
+    }
+}
+
+/// GeoJSON orders coordinates longitude first
+fn coordinates(fix: Fix) -> String {
+    format!("[{},{}]", fix.longitude, fix.latitude)
+}
+
+fn feature(kind: &str, coordinates: String, properties: Vec<(&str, String)>) -> String {
+    export::json_object(vec![
+        ("type", export::json_string("Feature")),
+        ("geometry", export::json_object(vec![
+            ("type", export::json_string(kind)),
+            ("coordinates", coordinates)
+        ])),
+        ("properties", export::json_object(properties))
+    ])
+}
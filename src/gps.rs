@@ -0,0 +1,264 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+    mem,
+    net::{TcpStream, ToSocketAddrs},
+    os::unix::io::AsRawFd,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant}
+};
+
+use crate::export::Json;
+
+/// How long to wait on gpsd before giving up on connecting
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// How long to wait before connecting again after gpsd goes away
+const RETRY: Duration = Duration::from_secs(5);
+/// A fix older than this is no longer taken to be where the sensor is
+const STALE: Duration = Duration::from_secs(5);
+/// Asks gpsd to stream reports as JSON
+const WATCH: &[u8] = b"?WATCH={\"enable\":true,\"json\":true};\n";
+/// The speeds a serial GPS receiver can be read at, in baud
+pub const BAUD_RATES: [u32; 6] = [4800, 9600, 19200, 38400, 57600, 115200];
+
+/// Where the sensor was, in degrees
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fix {
+    pub latitude: f64,
+    pub longitude: f64
+}
+
+/// Where positions come from
+#[derive(Debug, Clone)]
+pub enum Receiver {
+    /// gpsd, at a HOST:PORT address
+    Gpsd(String),
+    /// A receiver giving NMEA sentences over a serial device, at a speed in baud or whatever the device is already set to
+    Serial(PathBuf, Option<u32>)
+}
+impl Receiver {
+    /// A serial device if given a path, or else gpsd's address
+    pub fn parse(receiver: &str, baud: Option<u32>) -> Self {
+        if receiver.starts_with('/') {
+            Self::Serial(PathBuf::from(receiver), baud)
+        } else {
+            Self::Gpsd(receiver.to_string())
+        }
+    }
+}
+
+/// Follows the position reported by gpsd or a serial receiver from a thread of its own
+pub struct Gps {
+    fix: Arc<Mutex<Option<(Fix, Instant)>>>,
+    /// Why gpsd or the receiver last couldn't be reached or read, cleared once a fix comes in
+    pub error: Arc<Mutex<Option<String>>>
+}
+impl Gps {
+    /// Must be started before the sandbox is applied, which only confines the thread applying it,
+    /// so that gpsd can still be reached or the serial device opened again
+    pub fn spawn(receiver: Receiver) -> Self {
+        let fix = Arc::new(Mutex::new(None));
+        let error = Arc::new(Mutex::new(None));
+        let (thread_fix, thread_error) = (fix.clone(), error.clone());
+        thread::spawn(move || loop {
+            let fixed = |latest: Fix| {
+                if let Ok(mut fix) = thread_fix.lock() {
+                    *fix = Some((latest, Instant::now()));
+                }
+                if let Ok(mut error) = thread_error.lock() {
+                    *error = None;
+                }
+            };
+            let result = match &receiver {
+                Receiver::Gpsd(server) => follow(server, fixed).map(|_| "gpsd closed the connection"),
+                Receiver::Serial(path, baud) => read_serial(path, *baud, fixed).map(|_| "the GPS receiver stopped sending")
+            };
+            if let Ok(mut error) = thread_error.lock() {
+                *error = Some(match result {
+                    Ok(reason) => reason.to_string(),
+                    Err(error) => error.to_string()
+                });
+            }
+            thread::sleep(RETRY);
+        });
+        Self {
+            fix,
+            error
+        }
+    }
+    /// The latest fix, unless it has gone stale
+    pub fn fix(&self) -> Option<Fix> {
+        self.fix.lock().ok()?
+            .filter(|(_, received)| received.elapsed() < STALE)
+            .map(|(fix, _)| fix)
+    }
+}
+
+/// Stream reports from gpsd until it goes away, handing on every 2D or 3D fix
+fn follow(server: &str, mut fixed: impl FnMut(Fix)) -> io::Result<()> {
+    let address = server.to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "gpsd's address didn't resolve"))?;
+    let mut stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+    stream.write_all(WATCH)?;
+    for line in BufReader::new(stream).lines() {
+        let report = match Json::parse(&line?) {
+            Some(report) => report,
+            None => continue
+        };
+        // Time-position-velocity reports carry the fix, with a mode of 2 or more once there is one
+        if report.get("class").and_then(Json::as_str) != Some("TPV") || report.get("mode").and_then(Json::as_f64).unwrap_or(0.0) < 2.0 {
+            continue
+        }
+        if let (Some(latitude), Some(longitude)) = (report.get("lat").and_then(Json::as_f64), report.get("lon").and_then(Json::as_f64)) {
+            fixed(Fix { latitude, longitude });
+        }
+    }
+    Ok(())
+}
+
+/// Read NMEA sentences from a serial receiver until it stops, handing on every fix
+fn read_serial(path: &Path, baud: Option<u32>, mut fixed: impl FnMut(Fix)) -> io::Result<()> {
+    let device = File::open(path)?;
+    if let Some(baud) = baud {
+        set_speed(&device, baud)?;
+    }
+    // Receivers may send a partial or garbled line as they start, which is no reason to stop
+    for line in BufReader::new(device).split(b'\n') {
+        if let Some(fix) = nmea(&String::from_utf8_lossy(&line?)) {
+            fixed(fix);
+        }
+    }
+    Ok(())
+}
+
+/// Switch a serial device to raw input at a speed
+fn set_speed(device: &File, baud: u32) -> io::Result<()> {
+    let speed = match baud {
+        4800 => libc::B4800,
+        9600 => libc::B9600,
+        19200 => libc::B19200,
+        38400 => libc::B38400,
+        57600 => libc::B57600,
+        115200 => libc::B115200,
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} baud isn't supported", baud)))
+    };
+    unsafe {
+        let mut settings: libc::termios = mem::zeroed();
+        if libc::tcgetattr(device.as_raw_fd(), &mut settings) < 0 {
+            return Err(io::Error::last_os_error())
+        }
+        libc::cfmakeraw(&mut settings);
+        libc::cfsetispeed(&mut settings, speed);
+        libc::cfsetospeed(&mut settings, speed);
+        if libc::tcsetattr(device.as_raw_fd(), libc::TCSANOW, &settings) < 0 {
+            return Err(io::Error::last_os_error())
+        }
+    }
+    Ok(())
+}
+
+/// The fix in a GGA or RMC sentence from any talker, unless its checksum is wrong or the receiver has no fix
+fn nmea(sentence: &str) -> Option<Fix> {
+    let (body, checksum) = sentence.trim().strip_prefix('$')?.split_once('*')?;
+    if checksum.len() != 2 || u8::from_str_radix(checksum, 16).ok()? != body.bytes().fold(0, |sum, byte| sum ^ byte) {
+        return None
+    }
+    let fields: Vec<_> = body.split(',').collect();
+    // Where the latitude is, followed by its hemisphere, the longitude and its hemisphere
+    let latitude = match fields.first()?.get(2..)? {
+        // A quality of 0 is no fix
+        "GGA" if fields.get(6)?.parse::<u8>().ok()? > 0 => 2,
+        // A status of V is a warning that there is no fix
+        "RMC" if *fields.get(2)? == "A" => 3,
+        _ => return None
+    };
+    let fix = Fix {
+        latitude: coordinate(fields.get(latitude)?, fields.get(latitude + 1)?, 2)?,
+        longitude: coordinate(fields.get(latitude + 2)?, fields.get(latitude + 3)?, 3)?
+    };
+    Some(fix).filter(|fix| fix.latitude.abs() <= 90.0 && fix.longitude.abs() <= 180.0)
+}
+
+/// Degrees from NMEA's whole degrees, given in so many digits, followed by decimal minutes, negative to the south and west
+fn coordinate(value: &str, hemisphere: &str, degree_digits: usize) -> Option<f64> {
+    // Parsing as a float would also take a sign, an exponent or `inf`
+    if !value.bytes().all(|byte| byte.is_ascii_digit() || byte == b'.') {
+        return None
+    }
+    let degrees: f64 = value.get(..degree_digits)?.parse().ok()?;
+    let minutes: f64 = value.get(degree_digits..)?.parse().ok().filter(|minutes| *minutes < 60.0)?;
+    match hemisphere {
+        "N" | "E" => Some(degrees + minutes / 60.0),
+        "S" | "W" => Some(-(degrees + minutes / 60.0)),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A sentence with its checksum
+    fn sentence(body: &str) -> String {
+        format!("${}*{:02X}", body, body.bytes().fold(0, |sum, byte| sum ^ byte))
+    }
+    fn close(fix: Fix, latitude: f64, longitude: f64) -> bool {
+        (fix.latitude - latitude).abs() < 1e-6 && (fix.longitude - longitude).abs() < 1e-6
+    }
+
+    #[test]
+    fn fixes() {
+        let gga = nmea("$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47\r\n").unwrap();
+        assert!(close(gga, 48.1173, 11.516_666_7), "{:?}", gga);
+        let rmc = nmea("$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A").unwrap();
+        assert_eq!(gga, rmc);
+        // Any talker, and hemispheres to the south and west
+        let fix = nmea(&sentence("GNRMC,010203.00,A,3351.5000,S,15112.7500,W,0.0,,010124,,,A")).unwrap();
+        assert!(close(fix, -33.858_333_3, -151.2125), "{:?}", fix);
+    }
+    #[test]
+    fn no_fix() {
+        for body in [
+            "GPGGA,123519,4807.038,N,01131.000,E,0,00,,,M,,M,,",
+            "GPRMC,123519,V,4807.038,N,01131.000,E,,,230394,,",
+            "GPGGA,123519,,,,,1,08,0.9,545.4,M,46.9,M,,",
+            "GPGSV,3,1,11,03,03,111,00",
+            "GPRMC,123519,A,4807.038,N"
+        ] {
+            assert_eq!(nmea(&sentence(body)), None, "{}", body);
+        }
+    }
+    #[test]
+    fn malformed() {
+        let valid = "GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,";
+        for text in [
+            "",
+            "$",
+            "*47",
+            valid,
+            "$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*48",
+            "$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*+47",
+            "$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*047",
+            "\u{fffd}\u{fffd}$GPGGA,12\u{fffd}",
+            "$éé*00"
+        ] {
+            assert_eq!(nmea(text), None, "{:?}", text);
+        }
+        for (latitude, longitude) in [
+            ("4807.038", "011inf"),
+            ("48NaN", "01131.000"),
+            ("-4807.038", "01131.000"),
+            ("4807e1", "01131.000"),
+            ("4860.000", "01131.000"),
+            ("9530.000", "01131.000"),
+            ("4807.038", "19000.000"),
+            ("4", "01131.000")
+        ] {
+            let body = format!("GPRMC,123519,A,{},N,{},E,,,230394,,", latitude, longitude);
+            assert_eq!(nmea(&sentence(&body)), None, "{}", body);
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf}
+};
+use eui48::MacAddress;
+use pcap::Savefile;
+
+use crate::{capture::Frame, recorder};
+
+/// Writes the frames of WPA handshakes to a file of their own, along with a beacon from each access point involved
+/// as offline tools need one to know the network's name
+pub struct Handshakes {
+    path: PathBuf,
+    /// Created when the first handshake frame arrives, so there is no empty file when none are heard
+    savefile: Option<Savefile>,
+    /// The latest beacon from every access point, until it has been written
+    beacons: HashMap<MacAddress, Frame>,
+    /// Access points whose beacon has been written
+    written: HashSet<MacAddress>,
+    /// How many handshake frames have been written
+    pub frames: u64
+}
+impl Handshakes {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            savefile: None,
+            beacons: HashMap::new(),
+            written: HashSet::new(),
+            frames: 0
+        }
+    }
+    pub fn beacon(&mut self, bssid: MacAddress, frame: &Frame) {
+        if !self.written.contains(&bssid) {
+            self.beacons.insert(bssid, frame.clone());
+        }
+    }
+    pub fn eapol(&mut self, bssid: MacAddress, frame: &Frame) -> Result<(), recorder::Error> {
+        if self.savefile.is_none() {
+            self.savefile = Some(recorder::open(&self.path)?);
+        }
+        let savefile = self.savefile.as_mut().unwrap();
+        if let Some(beacon) = self.beacons.remove(&bssid) {
+            savefile.write(&beacon.packet());
+            self.written.insert(bssid);
+        }
+        savefile.write(&frame.packet());
+        self.frames += 1;
+        // Flushed straight away, as handshakes are few and worth not losing
+        savefile.flush().map_err(recorder::Error::Flush)
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
@@ -0,0 +1,97 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+    sync::{Arc, Mutex, mpsc::{self, Sender}},
+    thread,
+    time::{Duration, Instant}
+};
+
+use crate::alerts::Alert;
+
+/// How often a running command is checked on while waiting for it to finish
+const POLL: Duration = Duration::from_millis(50);
+
+/// A command to run for every alert raised
+pub struct Config {
+    /// Run with `sh -c`, so it may be a pipeline
+    pub command: String,
+    /// The command is killed if it runs for longer than this
+    pub timeout: Duration,
+    /// Alerts raised sooner than this after the command last ran are skipped, so a burst can't flood the system
+    pub interval: Duration
+}
+
+/// What has happened to the alerts handed to the command
+#[derive(Debug, Default, Clone)]
+pub struct Outcome {
+    /// Alerts skipped by rate limiting
+    pub skipped: u64,
+    /// Why the command last failed, cleared once it succeeds
+    pub error: Option<String>
+}
+
+/// Runs a command with each alert as JSON on its standard input, from a thread of its own
+pub struct Hook {
+    alerts: Sender<Alert>,
+    pub outcome: Arc<Mutex<Outcome>>
+}
+impl Hook {
+    /// Must be started before the sandbox is applied, which forbids running programs from the thread applying it
+    pub fn spawn(config: Config) -> Self {
+        let (tx, rx) = mpsc::channel::<Alert>();
+        let outcome = Arc::new(Mutex::new(Outcome::default()));
+        let thread_outcome = outcome.clone();
+        thread::spawn(move || {
+            let mut last_run: Option<Instant> = None;
+            for alert in rx {
+                if last_run.map_or(false, |last_run| last_run.elapsed() < config.interval) {
+                    if let Ok(mut outcome) = thread_outcome.lock() {
+                        outcome.skipped += 1;
+                    }
+                    continue
+                }
+                last_run = Some(Instant::now());
+                let result = run(&config, &alert);
+                if let Ok(mut outcome) = thread_outcome.lock() {
+                    outcome.error = result.err();
+                }
+            }
+        });
+        Self {
+            alerts: tx,
+            outcome
+        }
+    }
+    pub fn alert(&self, alert: &Alert) {
+        let _ = self.alerts.send(alert.clone());
+    }
+}
+
+fn run(config: &Config, alert: &Alert) -> Result<(), String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(&config.command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|error| format!("unable to run the alert command: {}", error))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A command that doesn't read its input closes the pipe early, which is no reason to fail
+        let _ = writeln!(stdin, "{}", alert.json());
+    }
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return Ok(()),
+            Ok(Some(status)) => return Err(format!("the alert command failed with {}", status)),
+            Ok(None) if started.elapsed() >= config.timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("the alert command took longer than {}s and was killed", config.timeout.as_secs()))
+            }
+            Ok(None) => thread::sleep(POLL),
+            Err(error) => return Err(format!("unable to wait for the alert command: {}", error))
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use std::time::{Duration, Instant};
+
+use crate::nl80211::{self, Interface, Nl80211};
+
+/// Cycles an interface across channels, staying on each for a dwell time
+pub struct Hopper {
+    /// Kept open so hopping continues once the process may no longer open new sockets
+    nl80211: Nl80211,
+    interface: Interface,
+    /// Centre frequencies to visit, in MHz
+    frequencies: Vec<u32>,
+    dwell: Duration,
+    index: usize,
+    hopped: Instant,
+    /// Stay on the current channel rather than hopping
+    pub locked: bool,
+    /// The channel most recently tuned to
+    pub channel: Option<u32>
+}
+impl Hopper {
+    /// Prepare to hop the named interface across the given channels, or every usable 2.4GHz and 5GHz channel
+    pub fn new(interface: &str, channels: Option<&[u32]>, dwell: Duration) -> nl80211::Result<Self> {
+        let mut nl80211 = Nl80211::connect()?;
+        let interface = nl80211.interface(interface)?;
+        let wiphy = nl80211.wiphy(interface.wiphy)?;
+        let frequencies = match channels {
+            Some(channels) => channels.iter()
+                .map(|&channel| {
+                    let mhz = nl80211::channel_to_frequency(channel).ok_or(nl80211::Error::InvalidChannel(channel))?;
+                    match wiphy.frequency(mhz) {
+                        None => Err(nl80211::Error::UnsupportedChannel(channel)),
+                        Some(frequency) if frequency.disabled => Err(nl80211::Error::DisabledChannel(channel)),
+                        Some(_) => Ok(mhz)
+                    }
+                })
+                .collect::<nl80211::Result<Vec<_>>>()?,
+            None => wiphy.usable_frequencies()
+                .map(|frequency| frequency.mhz)
+                .filter(|&mhz| mhz < 5900)
+                .collect()
+        };
+        let channel = interface.frequency.and_then(nl80211::frequency_to_channel);
+        Ok(Self {
+            nl80211,
+            interface,
+            frequencies,
+            dwell,
+            index: 0,
+            hopped: Instant::now(),
+            locked: false,
+            channel
+        })
+    }
+    /// Move on to the next channel if the dwell time has passed
+    pub fn tick(&mut self) -> nl80211::Result<()> {
+        if self.locked || self.frequencies.is_empty() || self.hopped.elapsed() < self.dwell {
+            return Ok(())
+        }
+        self.hopped = Instant::now();
+        let mhz = self.frequencies[self.index];
+        self.index = (self.index + 1) % self.frequencies.len();
+        self.nl80211.set_frequency(&self.interface, mhz)?;
+        self.channel = nl80211::frequency_to_channel(mhz);
+        Ok(())
+    }
+    /// Tune to a channel the radio supports and stay there
+    pub fn tune(&mut self, channel: u32) -> nl80211::Result<()> {
+        let name = self.interface.name.clone();
+        self.nl80211.set_channel(&name, channel)?;
+        self.locked = true;
+        self.channel = Some(channel);
+        Ok(())
+    }
+}
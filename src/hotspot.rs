@@ -0,0 +1,148 @@
+use std::fmt;
+use eui48::MacAddress;
+
+use crate::wifi::{Anqp, Tag};
+
+/// The Wi-Fi Alliance OUI and vendor element type announcing Hotspot 2.0 support
+const WFA: [u8; 3] = [0x50, 0x6f, 0x9a];
+const HOTSPOT_20: u8 = 0x10;
+/// Roaming consortium identifiers issued to OpenRoaming start with this
+const OPENROAMING: [u8; 3] = [0x5a, 0x03, 0xba];
+/// The privacy bit of the beacon capability field, set when any encryption is required
+const CAPABILITY_PRIVACY: u16 = 1 << 4;
+
+/// How a network admits clients, as far as can be told from its beacons
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Requires a key, with nothing to suggest public access
+    Secured,
+    /// No encryption and no hint of a hotspot
+    Open,
+    /// Open, but advertising interworking, usually in front of a captive portal
+    Hotspot,
+    /// Advertises Hotspot 2.0, admitting clients by their home provider's credentials
+    Passpoint,
+    /// Passpoint as part of the OpenRoaming federation
+    OpenRoaming
+}
+impl Access {
+    pub fn from_beacon(capabilities: u16, tags: &[Tag]) -> Self {
+        let open = capabilities & CAPABILITY_PRIVACY == 0 && !tags.iter().any(|tag| matches!(tag, Tag::Rsn { .. }));
+        let interworking = tags.iter().any(|tag| matches!(tag, Tag::Interworking { .. }));
+        let passpoint = tags.iter().any(|tag| matches!(tag, Tag::VendorSpecific { vendor: WFA, kind: Some(HOTSPOT_20) }));
+        let openroaming = tags.iter().any(|tag| match tag {
+            Tag::RoamingConsortium { ois } => ois.iter().any(|oi| oi.starts_with(&OPENROAMING)),
+            _ => false
+        });
+        match (open, interworking, passpoint, openroaming) {
+            (_, _, true, true) => Self::OpenRoaming,
+            (_, _, true, false) => Self::Passpoint,
+            (true, true, false, _) => Self::Hotspot,
+            (true, false, false, _) => Self::Open,
+            (false, _, false, _) => Self::Secured
+        }
+    }
+    /// Whether this is worth calling out, as anyone can connect or credentials are brokered by a third party
+    pub fn notable(&self) -> bool {
+        *self != Self::Secured
+    }
+}
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", match self {
+            Self::Secured => "Secured",
+            Self::Open => "Open",
+            Self::Hotspot => "Open hotspot",
+            Self::Passpoint => "Passpoint",
+            Self::OpenRoaming => "OpenRoaming"
+        })
+    }
+}
+
+/// Access network types from the interworking element
+const ACCESS_NETWORK_TYPES: [&str; 6] = [
+    "Private",
+    "Private with guest access",
+    "Chargeable public",
+    "Free public",
+    "Personal device",
+    "Emergency services only"
+];
+/// Venue groups from the interworking element
+const VENUE_GROUPS: [&str; 12] = [
+    "Unspecified",
+    "Assembly",
+    "Business",
+    "Educational",
+    "Factory and industrial",
+    "Institutional",
+    "Mercantile",
+    "Residential",
+    "Storage",
+    "Utility and miscellaneous",
+    "Vehicular",
+    "Outdoor"
+];
+
+/// What a hotspot says about itself through interworking elements and ANQP
+#[derive(Debug, Default)]
+pub struct Details {
+    pub access_network_type: Option<u8>,
+    pub internet: bool,
+    pub venue: Option<(u8, u8)>,
+    pub hessid: Option<MacAddress>,
+    /// Roaming consortium identifiers from both beacons and ANQP, in the order first seen
+    pub roaming_consortium: Vec<Vec<u8>>,
+    pub venue_names: Vec<String>,
+    pub domains: Vec<String>,
+    pub realms: Vec<String>
+}
+impl Details {
+    pub fn is_empty(&self) -> bool {
+        self.access_network_type.is_none() && self.roaming_consortium.is_empty() && self.venue_names.is_empty() && self.domains.is_empty() && self.realms.is_empty()
+    }
+    pub fn update_from_beacon(&mut self, tags: &[Tag]) {
+        for tag in tags {
+            match tag {
+                &Tag::Interworking { access_network_type, internet, venue, hessid } => {
+                    self.access_network_type = Some(access_network_type);
+                    self.internet = internet;
+                    self.venue = venue.or(self.venue);
+                    self.hessid = hessid.or(self.hessid);
+                }
+                Tag::RoamingConsortium { ois } => merge(&mut self.roaming_consortium, ois),
+                _ => ()
+            }
+        }
+    }
+    pub fn update_from_anqp(&mut self, elements: &[Anqp]) {
+        for element in elements {
+            match element {
+                Anqp::VenueName(names) => merge(&mut self.venue_names, names),
+                Anqp::RoamingConsortium(ois) => merge(&mut self.roaming_consortium, ois),
+                Anqp::NaiRealm(realms) => merge(&mut self.realms, realms),
+                Anqp::DomainName(domains) => merge(&mut self.domains, domains),
+                Anqp::Unknown => ()
+            }
+        }
+    }
+    pub fn access_network_type_name(&self) -> Option<&'static str> {
+        self.access_network_type.map(|kind| match kind {
+            14 => "Test or experimental",
+            15 => "Wildcard",
+            kind => ACCESS_NETWORK_TYPES.get(kind as usize).copied().unwrap_or("Reserved")
+        })
+    }
+    /// The venue group's name and the type number within it
+    pub fn venue_description(&self) -> Option<String> {
+        self.venue.map(|(group, kind)| format!("{}, type {}", VENUE_GROUPS.get(group as usize).copied().unwrap_or("Reserved"), kind))
+    }
+}
+
+fn merge<T: Clone + PartialEq>(into: &mut Vec<T>, values: &[T]) {
+    for value in values {
+        if !into.contains(value) {
+            into.push(value.clone())
+        }
+    }
+}
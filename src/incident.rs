@@ -0,0 +1,81 @@
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime}
+};
+use pcap::Savefile;
+
+use crate::{alerts::Alert, capture::Frame, recorder};
+
+/// The most frames kept in memory, however short the lead-up, so a flood can't exhaust it
+const MAX_BUFFERED: usize = 100_000;
+
+/// Keeps the last few seconds of frames so that when a critical alert fires, the lead-up to it and what follows
+/// can be saved to an incident file of its own
+pub struct Incidents {
+    before: Duration,
+    after: Duration,
+    /// Incident files are created in this folder
+    folder: PathBuf,
+    buffer: VecDeque<(Instant, Frame)>,
+    /// The incident being written, and when it ends
+    recording: Option<(Savefile, Instant)>,
+    /// Every incident file created so far, the last being the one in use while recording
+    pub files: Vec<PathBuf>
+}
+impl Incidents {
+    pub fn new(before: Duration, after: Duration, folder: &Path) -> Self {
+        Self {
+            before,
+            after,
+            folder: folder.to_path_buf(),
+            buffer: VecDeque::new(),
+            recording: None,
+            files: vec![]
+        }
+    }
+    pub fn frame(&mut self, frame: &Frame) -> Result<(), recorder::Error> {
+        self.check()?;
+        let now = Instant::now();
+        if let Some((savefile, _)) = &mut self.recording {
+            savefile.write(&frame.packet());
+        }
+        self.buffer.push_back((now, frame.clone()));
+        while self.buffer.front().map_or(false, |(arrived, _)| now.duration_since(*arrived) > self.before) || self.buffer.len() > MAX_BUFFERED {
+            self.buffer.pop_front();
+        }
+        Ok(())
+    }
+    /// Finish the incident being written once enough has followed the alert
+    pub fn check(&mut self) -> Result<(), recorder::Error> {
+        if let Some((savefile, until)) = &mut self.recording {
+            if Instant::now() >= *until {
+                let result = savefile.flush().map_err(recorder::Error::Flush);
+                self.recording = None;
+                return result
+            }
+        }
+        Ok(())
+    }
+    /// Start an incident file with the frames leading up to an alert, or carry on the current one for longer
+    pub fn trigger(&mut self, alert: &Alert) -> Result<(), recorder::Error> {
+        let until = Instant::now() + self.after;
+        if let Some((_, current)) = &mut self.recording {
+            *current = until;
+            return Ok(())
+        }
+        let started = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |since| since.as_secs());
+        let path = self.folder.join(format!("incident-{}-{}.pcap", started, alert.address.to_hex_string().replace(':', "")));
+        let mut savefile = recorder::open(&path)?;
+        for (_, frame) in self.buffer.iter() {
+            savefile.write(&frame.packet());
+        }
+        self.files.push(path);
+        self.recording = Some((savefile, until));
+        Ok(())
+    }
+    /// The incident file being written, if any
+    pub fn recording(&self) -> Option<&Path> {
+        self.recording.as_ref().and(self.files.last().map(PathBuf::as_path))
+    }
+}
@@ -0,0 +1,104 @@
+use std::{fmt, fs, path::Path};
+use pcap::{Capture, Linktype};
+
+use crate::nl80211::{self, InterfaceType, Nl80211};
+
+/// How fit an interface is to capture on, probed before it is picked so a poor choice is caught early
+#[derive(Debug, Clone, Copy)]
+pub struct Capability {
+    /// Its current mode, or None if it isn't a wireless interface
+    pub mode: Option<InterfaceType>,
+    /// Whether its radio can enter monitor mode
+    pub monitor: bool,
+    /// Whether it gives radiotap headers as it is now, or None if it couldn't be opened to check
+    pub radiotap: Option<bool>
+}
+impl Capability {
+    /// Look up an interface over nl80211, given a connection, and briefly open it to see what headers it gives
+    pub fn probe(nl80211: Option<&mut Nl80211>, name: &str) -> Self {
+        let (mode, monitor) = match nl80211.and_then(|nl80211| {
+            let interface = nl80211.interface(name).ok()?;
+            Some((interface.iftype, nl80211.wiphy(interface.wiphy).ok()?.supports_monitor))
+        }) {
+            Some((mode, monitor)) => (Some(mode), monitor || mode == InterfaceType::Monitor),
+            None => (None, false)
+        };
+        let radiotap = Capture::from_device(name)
+            .and_then(|capture| capture.open())
+            .and_then(|capture| Ok(capture.get_datalink() == Linktype::IEEE802_11_RADIOTAP
+                || capture.list_datalinks()?.contains(&Linktype::IEEE802_11_RADIOTAP)))
+            .ok();
+        Self {
+            mode,
+            monitor,
+            radiotap
+        }
+    }
+    /// Why capturing on the interface is likely to fail, if it is, given whether monitor mode will be asked for
+    pub fn problem(&self, entering_monitor: bool) -> Option<&'static str> {
+        let in_monitor = self.mode == Some(InterfaceType::Monitor);
+        match self.mode {
+            None => Some("it is not a wireless interface"),
+            _ if self.radiotap == Some(true) => None,
+            _ if in_monitor => Some("it is in monitor mode but gives no radiotap headers"),
+            _ if !self.monitor => Some("its radio doesn't support monitor mode"),
+            _ if !entering_monitor => Some("it gives no radiotap headers until it is in monitor mode, which --dont-monitor skips"),
+            _ => None
+        }
+    }
+}
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mode = match self.mode {
+            Some(mode) => mode,
+            None => return write!(f, "not wireless")
+        };
+        write!(f, "{}, monitor {}, radiotap {}", mode, if self.monitor { "supported" } else { "not supported" }, match self.radiotap {
+            Some(true) => "now",
+            Some(false) if self.monitor => "in monitor mode",
+            Some(false) => "none",
+            None => "unknown"
+        })
+    }
+}
+
+/// Print every wireless interface along with the capabilities of its radio
+pub fn print() -> Result<(), nl80211::Error> {
+    let mut nl80211 = Nl80211::connect()?;
+    let interfaces = nl80211.interfaces()?;
+    if interfaces.is_empty() {
+        println!("No wireless interfaces found");
+        return Ok(())
+    }
+    for interface in interfaces {
+        let wiphy = nl80211.wiphy(interface.wiphy)?;
+        println!("{}", interface.name);
+        println!("  phy:      {}", if wiphy.name.is_empty() { format!("phy#{}", interface.wiphy) } else { wiphy.name.clone() });
+        println!("  driver:   {}", driver(&interface.name).unwrap_or_else(|| "unknown".to_string()));
+        println!("  mode:     {}", interface.iftype);
+        if let Some(channel) = interface.frequency.and_then(nl80211::frequency_to_channel) {
+            println!("  channel:  {} ({} MHz)", channel, interface.frequency.unwrap_or(0));
+        }
+        println!("  monitor:  {}", if wiphy.supports_monitor { "supported" } else { "not supported" });
+        for band in wiphy.bands.iter() {
+            let channels: Vec<_> = band.frequencies.iter()
+                .filter(|frequency| !frequency.disabled)
+                .map(|frequency| match (frequency.channel(), frequency.radar) {
+                    (Some(channel), true) => format!("{}*", channel),
+                    (Some(channel), false) => channel.to_string(),
+                    (None, _) => format!("{}MHz", frequency.mhz)
+                })
+                .collect();
+            println!("  {:9} {}", format!("{}:", band.kind), channels.join(" "));
+        }
+        println!();
+    }
+    println!("* requires radar detection (DFS)");
+    Ok(())
+}
+
+/// The name of the kernel driver bound to an interface
+fn driver(interface: &str) -> Option<String> {
+    let link = fs::read_link(Path::new("/sys/class/net").join(interface).join("device/driver")).ok()?;
+    link.file_name().map(|name| name.to_string_lossy().to_string())
+}
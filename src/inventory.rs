@@ -0,0 +1,91 @@
+use std::{
+    fs::File,
+    io::{self, Seek, SeekFrom, Write}
+};
+
+use crate::{DeviceList, export::{self, Format}, store::{FIELDS, Fields, Record}, survey::Survey};
+
+/// Replace the contents of a file with every device tracked so far
+/// The file is rewritten rather than reopened so it can be kept open from before the sandbox was applied
+pub fn rewrite(file: &mut File, format: Format, fields: &Fields, devices: &DeviceList, survey: &Survey) -> io::Result<()> {
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    write(file, format, fields, devices, survey)
+}
+
+/// Write the chosen fields of every device tracked, with what it advertised and when and how well it was heard
+pub fn write(output: &mut impl Write, format: Format, fields: &Fields, devices: &DeviceList, survey: &Survey) -> io::Result<()> {
+    let rows = Record::all(devices, survey).into_iter();
+    match format {
+        Format::Csv => {
+            let columns: Vec<_> = FIELDS.iter().copied().filter(|&field| field != "sightings" && fields.includes(field)).collect();
+            let sightings = fields.includes("sightings");
+            let mut header: Vec<_> = columns.iter()
+                .flat_map(|&column| match column {
+                    "location" => vec!["latitude", "longitude", "location_signal", "location_time"],
+                    column => vec![column]
+                })
+                .collect();
+            if sightings {
+                header.extend(&["sensor", "sensor_frames", "sensor_signal", "channel", "band", "frequency"]);
+            }
+            writeln!(output, "{}", header.join(","))?;
+            let optional = |value: Option<String>| value.unwrap_or_default();
+            for row in rows {
+                let device: Vec<_> = columns.iter()
+                    .map(|&column| match column {
+                        "address" => row.address.clone(),
+                        "device" => row.device.clone().unwrap_or_default(),
+                        "manufacturer" => export::csv_field(row.manufacturer.as_deref().unwrap_or_default()),
+                        "role" => row.role.clone().unwrap_or_default(),
+                        "ssid" => export::csv_field(row.ssid.as_deref().unwrap_or_default()),
+                        "bssid" => row.bssid.clone().unwrap_or_default(),
+                        "probes" => export::csv_field(&row.probes.join(";")),
+                        "tags" => export::csv_field(&row.tags.join(";")),
+                        "frames" => row.frames.to_string(),
+                        "first_seen" => optional(row.first_seen.map(|time| time.to_string())),
+                        "last_seen" => optional(row.last_seen.map(|time| time.to_string())),
+                        "location" => match row.location {
+                            Some(location) => format!(
+                                "{},{},{},{}",
+                                location.latitude,
+                                location.longitude,
+                                optional(location.signal.map(|signal| signal.to_string())),
+                                location.time
+                            ),
+                            None => ",,,".to_string()
+                        },
+                        _ => optional(row.signal.map(|signal| signal.to_string()))
+                    })
+                    .collect();
+                if !sightings {
+                    writeln!(output, "{}", device.join(","))?;
+                    continue
+                }
+                // A row for each sensor that heard the device, so the channel each heard it on is kept
+                if row.sightings.is_empty() {
+                    writeln!(output, "{}", device.iter().cloned().chain(vec![String::new(); 6]).collect::<Vec<_>>().join(","))?;
+                }
+                for sighting in row.sightings.iter() {
+                    let sighting = vec![
+                        export::csv_field(&sighting.sensor),
+                        sighting.frames.to_string(),
+                        optional(sighting.signal.map(|signal| signal.to_string())),
+                        optional(sighting.channel().map(|channel| channel.to_string())),
+                        optional(sighting.band()),
+                        optional(sighting.frequency.map(|frequency| frequency.to_string()))
+                    ];
+                    writeln!(output, "{}", device.iter().cloned().chain(sighting).collect::<Vec<_>>().join(","))?;
+                }
+            }
+        }
+        Format::Json => {
+            let rows: Vec<_> = rows.map(|row| row.json(fields)).collect();
+            writeln!(output, "{}", export::json_object(vec![
+                ("survey", export::json_object(survey.fields().into_iter().map(|(key, value)| (key, export::json_string(&value))))),
+                ("devices", format!("[{}]", rows.join(",")))
+            ]))?;
+        }
+    }
+    output.flush()
+}
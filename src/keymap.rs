@@ -0,0 +1,232 @@
+use crate::ui::Key;
+
+/// Something done with a key wherever it is pressed, as opposed to the keys each page handles itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    NextTab,
+    Up,
+    Down,
+    Left,
+    Right,
+    Top,
+    Bottom,
+    Pause,
+    Freeze,
+    Reset,
+    Export,
+    Snapshot,
+    Extract,
+    Map,
+    Dump,
+    Lock,
+    Unblock,
+    Track,
+    Search,
+    SearchNext,
+    SearchPrevious,
+    Help
+}
+impl Action {
+    pub const ALL: [Self; 23] = [
+        Self::Quit,
+        Self::NextTab,
+        Self::Up,
+        Self::Down,
+        Self::Left,
+        Self::Right,
+        Self::Top,
+        Self::Bottom,
+        Self::Pause,
+        Self::Freeze,
+        Self::Reset,
+        Self::Export,
+        Self::Snapshot,
+        Self::Extract,
+        Self::Map,
+        Self::Dump,
+        Self::Lock,
+        Self::Unblock,
+        Self::Track,
+        Self::Search,
+        Self::SearchNext,
+        Self::SearchPrevious,
+        Self::Help
+    ];
+    /// What the action is called in the `[keys]` section of the config file
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Quit => "quit",
+            Self::NextTab => "next-tab",
+            Self::Up => "up",
+            Self::Down => "down",
+            Self::Left => "left",
+            Self::Right => "right",
+            Self::Top => "top",
+            Self::Bottom => "bottom",
+            Self::Pause => "pause",
+            Self::Freeze => "freeze",
+            Self::Reset => "reset",
+            Self::Export => "export",
+            Self::Snapshot => "snapshot",
+            Self::Extract => "extract",
+            Self::Map => "map",
+            Self::Dump => "dump",
+            Self::Lock => "lock",
+            Self::Unblock => "unblock",
+            Self::Track => "track",
+            Self::Search => "search",
+            Self::SearchNext => "search-next",
+            Self::SearchPrevious => "search-previous",
+            Self::Help => "help"
+        }
+    }
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.iter().find(|action| action.name() == name).copied()
+    }
+    /// What the action does, for the help overlay
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Quit => "Quit",
+            Self::NextTab => "Next tab",
+            Self::Up => "Move up",
+            Self::Down => "Move down",
+            Self::Left => "Scroll left",
+            Self::Right => "Scroll right",
+            Self::Top => "Go to the top",
+            Self::Bottom => "Go to the bottom, following new entries",
+            Self::Pause => "Pause or resume the display",
+            Self::Freeze => "Freeze the display while frames are still taken in, or thaw it",
+            Self::Reset => "Clear every device and counter",
+            Self::Export => "Export the device list",
+            Self::Snapshot => "Write a snapshot of the device list",
+            Self::Extract => "Export everything known about the selected device",
+            Self::Map => "Map where devices were heard",
+            Self::Dump => "Save the frames held in memory with --ring",
+            Self::Lock => "Lock onto the current channel or resume hopping",
+            Self::Unblock => "Unblock the interface if soft blocked",
+            Self::Track => "Track the selected device",
+            Self::Search => "Search devices, networks and manufacturers on every page",
+            Self::SearchNext => "Go to the next match",
+            Self::SearchPrevious => "Go to the previous match",
+            Self::Help => "Show or hide this help"
+        }
+    }
+    /// The keys bound to the action unless the config file binds them to something else
+    fn defaults(&self) -> &'static [Key] {
+        match self {
+            Self::Quit => &[Key::Esc],
+            Self::NextTab => &[Key::Char('\t')],
+            Self::Up => &[Key::Up, Key::Char('w')],
+            Self::Down => &[Key::Down, Key::Char('s')],
+            Self::Left => &[Key::Left, Key::Char('a')],
+            Self::Right => &[Key::Right, Key::Char('d')],
+            Self::Top => &[Key::PageUp],
+            Self::Bottom => &[Key::PageDown],
+            Self::Pause => &[Key::Char(' ')],
+            Self::Freeze => &[Key::Char('z')],
+            Self::Reset => &[Key::Char('r')],
+            Self::Export => &[Key::Char('e')],
+            Self::Snapshot => &[Key::Char('p')],
+            Self::Extract => &[Key::Char('x')],
+            Self::Map => &[Key::Char('g')],
+            Self::Dump => &[Key::Char('D')],
+            Self::Lock => &[Key::Char('l')],
+            Self::Unblock => &[Key::Char('u')],
+            Self::Track => &[Key::Char('t')],
+            Self::Search => &[Key::Char('/')],
+            Self::SearchNext => &[Key::Char('n')],
+            Self::SearchPrevious => &[Key::Char('N')],
+            Self::Help => &[Key::Char('?')]
+        }
+    }
+}
+
+/// Which action each key stands for
+pub struct Keymap {
+    /// Those from the config file first, so a key bound there is taken from the action it was bound to by default
+    bindings: Vec<(Key, Action)>
+}
+impl Keymap {
+    /// The default bindings, with keys bound in the config file added on top
+    pub fn new(bound: &[(Key, Action)]) -> Self {
+        let mut bindings = bound.to_vec();
+        for &action in Action::ALL.iter() {
+            bindings.extend(action.defaults().iter().map(|&key| (key, action)));
+        }
+        Self {
+            bindings
+        }
+    }
+    pub fn action(&self, key: Key) -> Option<Action> {
+        self.bindings.iter().find(|&&(bound, _)| bound == key).map(|&(_, action)| action)
+    }
+    /// The keys that still stand for an action, in the order they were bound
+    pub fn keys(&self, action: Action) -> Vec<Key> {
+        let mut keys = vec![];
+        for &(key, bound) in self.bindings.iter() {
+            if bound == action && self.action(key) == Some(action) && !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        keys
+    }
+    /// Every action with the keys bound to it and what it does, for the help overlay
+    pub fn help(&self) -> Vec<(String, &'static str)> {
+        Action::ALL.iter()
+            .map(|&action| {
+                let keys: Vec<_> = self.keys(action).into_iter().map(name).collect();
+                (if keys.is_empty() { "unbound".to_string() } else { keys.join(" ") }, action.description())
+            })
+            .collect()
+    }
+}
+
+/// A key by the name it is given in the config file
+pub fn parse(key: &str) -> Option<Key> {
+    Some(match key {
+        "esc" => Key::Esc,
+        "tab" => Key::Char('\t'),
+        "space" => Key::Char(' '),
+        "enter" => Key::Char('\n'),
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "backspace" => Key::Backspace,
+        "delete" => Key::Delete,
+        key if key.chars().count() == 1 => Key::Char(key.chars().next()?),
+        _ => return None
+    })
+}
+
+/// A key by the name it is shown by
+pub fn name(key: Key) -> String {
+    match key {
+        Key::Esc => "Esc".to_string(),
+        Key::Char('\t') => "Tab".to_string(),
+        Key::Char(' ') => "Space".to_string(),
+        Key::Char('\n') => "Enter".to_string(),
+        Key::Char(c) => c.to_string(),
+        Key::Ctrl(c) => format!("Ctrl-{}", c),
+        Key::Alt(c) => format!("Alt-{}", c),
+        Key::F(n) => format!("F{}", n),
+        Key::Up => "Up".to_string(),
+        Key::Down => "Down".to_string(),
+        Key::Left => "Left".to_string(),
+        Key::Right => "Right".to_string(),
+        Key::PageUp => "PageUp".to_string(),
+        Key::PageDown => "PageDown".to_string(),
+        Key::Home => "Home".to_string(),
+        Key::End => "End".to_string(),
+        Key::BackTab => "Shift-Tab".to_string(),
+        Key::Backspace => "Backspace".to_string(),
+        Key::Delete => "Delete".to_string(),
+        Key::Insert => "Insert".to_string(),
+        key => format!("{:?}", key)
+    }
+}
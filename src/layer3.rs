@@ -0,0 +1,352 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use eui48::MacAddress;
+
+/// The LLC and SNAP headers that come before the EtherType of a data frame's body
+const SNAP: [u8; 6] = [0xaa, 0xaa, 0x03, 0x00, 0x00, 0x00];
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_ARP: u16 = 0x0806;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const UDP: u8 = 17;
+const DHCP_SERVER: u16 = 67;
+const DHCP_CLIENT: u16 = 68;
+const MDNS: u16 = 5353;
+/// Caps how many labels and pointers are followed in a DNS name, so a crafted loop can't run forever
+const MAX_LABELS: usize = 128;
+
+/// Something a device gives away about itself on the network
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Detail {
+    Address(IpAddr),
+    /// A name a device goes by, as given to DHCP or announced over mDNS
+    Hostname(String)
+}
+
+/// A detail and the device it was given away by
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Announcement {
+    pub device: MacAddress,
+    pub detail: Detail
+}
+
+/// What the unencrypted body of a data frame sent by `source` gives away, starting from its LLC header
+/// Only ARP, DHCP and mDNS are read, as anything else could as easily be about a host beyond the access point
+pub fn parse(source: MacAddress, body: &[u8]) -> Vec<Announcement> {
+    if body.get(..6) != Some(&SNAP) {
+        return vec![]
+    }
+    let payload = body.get(8..).unwrap_or_default();
+    match be16(body, 6) {
+        Some(ETHERTYPE_ARP) => arp(payload).into_iter().collect(),
+        Some(ETHERTYPE_IPV4) => ipv4(source, payload).unwrap_or_default(),
+        Some(ETHERTYPE_IPV6) => ipv6(source, payload).unwrap_or_default(),
+        _ => vec![]
+    }
+}
+
+/// The address claimed by the sender of an ARP request or reply, skipping probes sent before it has one
+fn arp(packet: &[u8]) -> Option<Announcement> {
+    if be16(packet, 2)? != ETHERTYPE_IPV4 || *packet.get(4)? != 6 || *packet.get(5)? != 4 {
+        return None
+    }
+    let address = ipv4_at(packet, 14)?;
+    if address.is_unspecified() {
+        return None
+    }
+    Some(Announcement {
+        device: MacAddress::from_bytes(packet.get(8..14)?).ok()?,
+        detail: Detail::Address(IpAddr::V4(address))
+    })
+}
+
+fn ipv4(source: MacAddress, packet: &[u8]) -> Option<Vec<Announcement>> {
+    let header = (*packet.first()? & 0x0f) as usize * 4;
+    // Only the first fragment starts with the UDP header
+    if header < 20 || be16(packet, 6)? & 0x1fff != 0 || *packet.get(9)? != UDP {
+        return None
+    }
+    let (source_port, destination_port, payload) = udp(packet.get(header..)?)?;
+    Some(match (source_port, destination_port) {
+        (DHCP_CLIENT, DHCP_SERVER) | (DHCP_SERVER, DHCP_CLIENT) => dhcp(payload)?,
+        (MDNS, _) => mdns(source, payload)?,
+        _ => vec![]
+    })
+}
+
+fn ipv6(source: MacAddress, packet: &[u8]) -> Option<Vec<Announcement>> {
+    // Extension headers are rare enough on mDNS to not be worth following
+    if *packet.get(6)? != UDP {
+        return None
+    }
+    match udp(packet.get(40..)?)? {
+        (MDNS, _, payload) => mdns(source, payload),
+        _ => None
+    }
+}
+
+/// The source and destination ports and the payload of a UDP datagram
+fn udp(datagram: &[u8]) -> Option<(u16, u16, &[u8])> {
+    Some((be16(datagram, 0)?, be16(datagram, 2)?, datagram.get(8..)?))
+}
+
+/// The hostname a client asks for, and the address a server hands it, keyed by the client's own hardware address
+fn dhcp(message: &[u8]) -> Option<Vec<Announcement>> {
+    const BOOTREQUEST: u8 = 1;
+    const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+    const PAD: u8 = 0;
+    const HOSTNAME: u8 = 12;
+    const MESSAGE_TYPE: u8 = 53;
+    const END: u8 = 255;
+    const ACK: u8 = 5;
+    if message.get(236..240)? != MAGIC_COOKIE {
+        return None
+    }
+    let client = MacAddress::from_bytes(message.get(28..34)?).ok()?;
+    let mut hostname = None;
+    let mut message_type = None;
+    let mut options = message.get(240..)?;
+    while let Some(&option) = options.first() {
+        match option {
+            PAD => {
+                options = &options[1..];
+                continue
+            }
+            END => break,
+            _ => ()
+        }
+        let length = *options.get(1)? as usize;
+        let value = options.get(2..2 + length)?;
+        match option {
+            HOSTNAME => hostname = Some(String::from_utf8_lossy(value).trim_matches(char::from(0)).to_string()),
+            MESSAGE_TYPE => message_type = value.first().copied(),
+            _ => ()
+        }
+        options = &options[2 + length..];
+    }
+
+    let mut announcements = vec![];
+    if let Some(hostname) = hostname.filter(|hostname| !hostname.is_empty()) {
+        announcements.push(Announcement { device: client, detail: Detail::Hostname(hostname) });
+    }
+    let assigned = ipv4_at(message, 16)?;
+    if message[0] != BOOTREQUEST && message_type == Some(ACK) && !assigned.is_unspecified() {
+        announcements.push(Announcement { device: client, detail: Detail::Address(IpAddr::V4(assigned)) });
+    }
+    Some(announcements)
+}
+
+/// The `.local` names and addresses in an mDNS response, which devices send about themselves
+fn mdns(source: MacAddress, message: &[u8]) -> Option<Vec<Announcement>> {
+    const RESPONSE: u16 = 0x8000;
+    const A: u16 = 1;
+    const AAAA: u16 = 28;
+    if be16(message, 2)? & RESPONSE == 0 {
+        return None
+    }
+    let questions = be16(message, 4)?;
+    let records = be16(message, 6)? as usize + be16(message, 8)? as usize + be16(message, 10)? as usize;
+    let mut offset = 12;
+    for _ in 0..questions {
+        offset = name(message, offset)?.1 + 4;
+    }
+    let mut announcements = vec![];
+    for _ in 0..records {
+        let (owner, end) = name(message, offset)?;
+        let kind = be16(message, end)?;
+        let length = be16(message, end + 8)? as usize;
+        let data = message.get(end + 10..end + 10 + length)?;
+        offset = end + 10 + length;
+        let address = match (kind, data.len()) {
+            (A, 4) => IpAddr::V4(ipv4_at(data, 0)?),
+            (AAAA, 16) => {
+                let mut octets = [0; 16];
+                octets.copy_from_slice(data);
+                IpAddr::V6(Ipv6Addr::from(octets))
+            }
+            _ => continue
+        };
+        if let Some(hostname) = owner.strip_suffix(".local") {
+            let hostname = Detail::Hostname(hostname.to_string());
+            if !announcements.iter().any(|announcement: &Announcement| announcement.detail == hostname) {
+                announcements.push(Announcement { device: source, detail: hostname });
+            }
+        }
+        announcements.push(Announcement { device: source, detail: Detail::Address(address) });
+    }
+    Some(announcements)
+}
+
+/// A DNS name starting at an offset into the message, with the offset just past it, following compression pointers
+fn name(message: &[u8], mut offset: usize) -> Option<(String, usize)> {
+    let mut labels = vec![];
+    let mut end = None;
+    for _ in 0..MAX_LABELS {
+        let length = *message.get(offset)? as usize;
+        match length {
+            0 => return Some((labels.join("."), end.unwrap_or(offset + 1))),
+            // The top two bits mark a pointer to the rest of the name elsewhere in the message
+            _ if length & 0xc0 == 0xc0 => {
+                end = end.or(Some(offset + 2));
+                offset = (length & 0x3f) << 8 | *message.get(offset + 1)? as usize;
+            }
+            _ => {
+                labels.push(String::from_utf8_lossy(message.get(offset + 1..offset + 1 + length)?).to_string());
+                offset += 1 + length;
+            }
+        }
+    }
+    None
+}
+
+fn be16(data: &[u8], index: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*data.get(index)?, *data.get(index + 1)?]))
+}
+fn ipv4_at(data: &[u8], index: usize) -> Option<Ipv4Addr> {
+    let octets = data.get(index..index + 4)?;
+    Some(Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIENT: [u8; 6] = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55];
+    const SERVER: [u8; 6] = [0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb];
+
+    fn client() -> MacAddress {
+        MacAddress::new(CLIENT)
+    }
+    /// A frame body carrying a packet of an EtherType
+    fn body(ethertype: u16, packet: &[u8]) -> Vec<u8> {
+        let mut body = SNAP.to_vec();
+        body.extend_from_slice(&ethertype.to_be_bytes());
+        body.extend_from_slice(packet);
+        body
+    }
+    /// An IPv4 packet carrying a UDP datagram between two ports
+    fn ipv4_udp(source_port: u16, destination_port: u16, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0x45, 0, 0, 0, 0, 0, 0, 0, 64, UDP, 0, 0, 0, 0, 0, 0, 224, 0, 0, 251];
+        packet.extend_from_slice(&source_port.to_be_bytes());
+        packet.extend_from_slice(&destination_port.to_be_bytes());
+        packet.extend_from_slice(&[0; 4]);
+        packet.extend_from_slice(payload);
+        body(ETHERTYPE_IPV4, &packet)
+    }
+    /// A DHCP message from the client, or to it when given the address it is assigned, with options following the cookie
+    fn dhcp_message(assigned: Option<Ipv4Addr>, options: &[u8]) -> Vec<u8> {
+        let mut message = vec![0; 236];
+        message[0] = if assigned.is_some() { 2 } else { 1 };
+        if let Some(assigned) = assigned {
+            message[16..20].copy_from_slice(&assigned.octets());
+        }
+        message[28..34].copy_from_slice(&CLIENT);
+        message.extend_from_slice(&[99, 130, 83, 99]);
+        message.extend_from_slice(options);
+        message
+    }
+    /// An mDNS response announcing `printer.local` by an A and an AAAA record, the second naming it by a pointer
+    fn mdns_response() -> Vec<u8> {
+        let mut message = vec![0, 0, 0x84, 0, 0, 0, 0, 2, 0, 0, 0, 0];
+        message.extend_from_slice(b"\x07printer\x05local\x00");
+        message.extend_from_slice(&[0, 1, 0x80, 1, 0, 0, 0, 120, 0, 4, 192, 168, 1, 20]);
+        message.extend_from_slice(&[0xc0, 12]);
+        message.extend_from_slice(&[0, 28, 0x80, 1, 0, 0, 0, 120, 0, 16]);
+        message.extend_from_slice(&Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 0x20).octets());
+        message
+    }
+
+    #[test]
+    fn arp() {
+        let mut packet = vec![0, 1, 0x08, 0, 6, 4, 0, 2];
+        packet.extend_from_slice(&CLIENT);
+        packet.extend_from_slice(&[192, 168, 1, 10]);
+        packet.extend_from_slice(&SERVER);
+        packet.extend_from_slice(&[192, 168, 1, 1]);
+        assert_eq!(parse(MacAddress::new(SERVER), &body(ETHERTYPE_ARP, &packet)), [Announcement {
+            device: client(),
+            detail: Detail::Address(Ipv4Addr::new(192, 168, 1, 10).into())
+        }]);
+        // A probe, sent before the device has an address
+        packet[14..18].copy_from_slice(&[0; 4]);
+        assert!(parse(client(), &body(ETHERTYPE_ARP, &packet)).is_empty());
+    }
+    #[test]
+    fn dhcp() {
+        let request = dhcp_message(None, b"\x35\x01\x03\x00\x0c\x06laptop\xff");
+        assert_eq!(parse(client(), &ipv4_udp(DHCP_CLIENT, DHCP_SERVER, &request)), [Announcement {
+            device: client(),
+            detail: Detail::Hostname("laptop".to_string())
+        }]);
+        // Only an acknowledgement gives the address for certain, rather than an offer
+        let assigned = Ipv4Addr::new(192, 168, 1, 10);
+        let ack = dhcp_message(Some(assigned), b"\x35\x01\x05\xff");
+        assert_eq!(parse(MacAddress::new(SERVER), &ipv4_udp(DHCP_SERVER, DHCP_CLIENT, &ack)), [Announcement {
+            device: client(),
+            detail: Detail::Address(assigned.into())
+        }]);
+        let offer = dhcp_message(Some(assigned), b"\x35\x01\x02\xff");
+        assert!(parse(client(), &ipv4_udp(DHCP_SERVER, DHCP_CLIENT, &offer)).is_empty());
+        // A hostname of nothing but padding
+        let request = dhcp_message(None, b"\x0c\x02\x00\x00\xff");
+        assert!(parse(client(), &ipv4_udp(DHCP_CLIENT, DHCP_SERVER, &request)).is_empty());
+    }
+    #[test]
+    fn mdns() {
+        let expected = [
+            Announcement { device: client(), detail: Detail::Hostname("printer".to_string()) },
+            Announcement { device: client(), detail: Detail::Address(Ipv4Addr::new(192, 168, 1, 20).into()) },
+            Announcement { device: client(), detail: Detail::Address(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 0x20).into()) }
+        ];
+        assert_eq!(parse(client(), &ipv4_udp(MDNS, MDNS, &mdns_response())), expected);
+
+        let mut packet = vec![0x60, 0, 0, 0, 0, 0, UDP, 255];
+        packet.extend_from_slice(&[0; 32]);
+        packet.extend_from_slice(&MDNS.to_be_bytes());
+        packet.extend_from_slice(&MDNS.to_be_bytes());
+        packet.extend_from_slice(&[0; 4]);
+        packet.extend_from_slice(&mdns_response());
+        assert_eq!(parse(client(), &body(ETHERTYPE_IPV6, &packet)), expected);
+
+        // Queries say nothing about the sender
+        let mut query = mdns_response();
+        query[2] = 0;
+        assert!(parse(client(), &ipv4_udp(MDNS, MDNS, &query)).is_empty());
+    }
+    #[test]
+    fn names() {
+        let message = b"\x03www\x07example\x00\x04mail\xc0\x04\xc0\x0f";
+        assert_eq!(name(message, 0), Some(("www.example".to_string(), 13)));
+        assert_eq!(name(message, 13), Some(("mail.example".to_string(), 20)));
+        // A pointer to the pointer itself loops, and a label past the end is cut short
+        assert_eq!(name(b"\xc0\x00", 0), None);
+        assert_eq!(name(message, 20), None);
+        assert_eq!(name(b"\x05abc", 0), None);
+    }
+    #[test]
+    fn malformed() {
+        let whole: &[Vec<u8>] = &[
+            ipv4_udp(MDNS, MDNS, &mdns_response()),
+            ipv4_udp(DHCP_CLIENT, DHCP_SERVER, &dhcp_message(None, b"\x0c\x06laptop\xff"))
+        ];
+        // Anything cut short gives away no more than the whole would, without reading past the end
+        for body in whole {
+            let expected = parse(client(), body);
+            assert!(!expected.is_empty());
+            for end in 0..body.len() {
+                let announcements = parse(client(), &body[..end]);
+                assert!(announcements.iter().all(|announcement| expected.contains(announcement)), "{:?}", announcements);
+            }
+        }
+        assert!(parse(client(), &body(ETHERTYPE_IPV4, &[])).is_empty());
+        assert!(parse(client(), b"\xaa\xaa\x03\x00\x00").is_empty());
+        // Not LLC and SNAP, a header shorter than IPv4 allows, and a later fragment
+        let mut other = whole[0].clone();
+        other[0] = 0;
+        assert!(parse(client(), &other).is_empty());
+        let mut short = whole[0].clone();
+        short[8] = 0x44;
+        assert!(parse(client(), &short).is_empty());
+        let mut fragment = whole[0].clone();
+        fragment[8 + 7] = 1;
+        assert!(parse(client(), &fragment).is_empty());
+    }
+}
@@ -0,0 +1,1218 @@
+//! The capture, parsing and tracking behind blockade-recon, without its terminal interface, for embedding in other tools
+//!
+//! Frames are read from a [`capture::Source`], parsed by [`wifi::Frame::parse`], and handed with how they were heard to
+//! [`DeviceList::ingest`], which keeps a [`KnownDevice`] for every address heard. Alerts are raised over the list by
+//! [`alerts::Alerts::check`], and it can be written out by [`inventory::write`], [`findings::write`] or [`geo::write`].
+//!
+//! ```no_run
+//! use std::{thread, time::{Duration, Instant}};
+//! use blockade_recon::{capture, manuf, stats, wifi, DeviceList, Heard};
+//! use radiotap::Radiotap;
+//!
+//! let oui_db = manuf::load_default().unwrap();
+//! let mut source = capture::Source::threaded(capture::open("wlan0", true, None).unwrap());
+//! let mut devices = DeviceList::default();
+//! let started = Instant::now();
+//! while started.elapsed() < Duration::from_secs(60) {
+//!     // Frames are read on a thread of their own, so there may be nothing yet
+//!     let frame = match source.next().unwrap() {
+//!         Some(frame) => frame,
+//!         None => {
+//!             thread::sleep(Duration::from_millis(2));
+//!             continue
+//!         }
+//!     };
+//!     let (radiotap, data) = match Radiotap::parse(&frame.data) {
+//!         Ok(parsed) => parsed,
+//!         Err(_) => continue
+//!     };
+//!     let heard = Heard {
+//!         sensor: "wlan0",
+//!         time: started.elapsed(),
+//!         signal: radiotap.antenna_signal.map(|signal| signal.value),
+//!         frequency: radiotap.channel.map(|channel| channel.freq as u32),
+//!         noise: radiotap.antenna_noise.map(|noise| noise.value),
+//!         snr: stats::snr(&radiotap),
+//!         quality: radiotap.lock_quality.map(|quality| quality.value)
+//!     };
+//!     devices.ingest(&oui_db, heard, None, wifi::Frame::parse(data).ok());
+//! }
+//! println!("{} devices heard", devices.len());
+//! ```
+
+use std::{cell::RefCell, collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque}, ops::{Deref, DerefMut}, net::IpAddr, rc::Rc, time::Duration};
+use eui48::MacAddress;
+use oui::{OuiDatabase, OuiEntry};
+pub mod wifi;
+pub mod layer3;
+pub mod nl80211;
+pub mod interfaces;
+pub mod netdev;
+pub mod monitor;
+pub mod watchdog;
+pub mod rfkill;
+pub mod privileges;
+pub mod capture;
+pub mod sandbox;
+pub mod survey;
+pub mod export;
+pub mod vendors;
+pub mod privacy;
+pub mod hotspot;
+pub mod hopper;
+pub mod control;
+pub mod dashboard;
+pub mod deauth;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod inventory;
+pub mod evidence;
+pub mod fingerprint;
+pub mod stats;
+pub mod pcapng;
+pub mod recorder;
+pub mod regions;
+pub mod session;
+pub mod alerts;
+pub mod bluetooth;
+pub mod findings;
+pub mod geo;
+pub mod wardrive;
+pub mod gps;
+pub mod clusters;
+pub mod email;
+pub mod hook;
+pub mod notify;
+pub mod rules;
+pub mod incident;
+pub mod ring;
+pub mod handshake;
+pub mod policy;
+pub mod twins;
+pub mod standards;
+pub mod diff;
+pub mod power;
+pub mod manuf;
+pub mod acks;
+pub mod events;
+pub mod store;
+pub mod suppress;
+pub mod reanalyse;
+pub mod selftest;
+pub mod remote;
+pub mod anonymize;
+pub mod prober;
+pub mod movement;
+pub mod schedule;
+pub mod cli;
+pub mod outputs;
+pub mod radio;
+
+/// The lowest --max-devices allowed, so the devices a frame names are never forgotten while it is being handled
+const MIN_DEVICES: usize = 100;
+/// How far back each device's signal is remembered, for the chart on the Devices tab
+pub const SIGNAL_HISTORY: Duration = Duration::from_secs(10 * 60);
+/// How long a device can go unheard before it is taken to have left, and a new visit starts when it is next heard
+pub const PRESENCE_GAP: Duration = Duration::from_secs(60);
+/// How long a burst of deauthentications is counted over
+pub const DEAUTH_WINDOW: Duration = Duration::from_secs(10);
+
+/// A device tracked by blockade
+/// Tracks metadata relating to the device
+#[derive(Debug)]
+pub struct KnownDevice {
+    pub manufacturer: Option<OuiEntry>,
+    /// The SSID of the beacon, or None if not a beacon
+    pub beacon: Option<String>,
+    /// True if the beacon leaves out the SSID, which may since have been revealed by other frames
+    pub hidden: bool,
+    /// How the network advertised by the beacon admits clients
+    pub access: Option<hotspot::Access>,
+    /// The encryption and authentication the network advertised by the beacon offers
+    pub security: Option<policy::Security>,
+    /// The Wi-Fi generation, channel widths and spatial streams this access point last advertised
+    pub standards: Option<standards::Standards>,
+    /// What this access point's beacons last said about it
+    pub fingerprint: Option<twins::Fingerprint>,
+    /// What changed the last time its beacons changed, which they shouldn't while it keeps running
+    pub fingerprint_changes: Vec<String>,
+    /// The SSIDs this access point has given in probe responses, where answering for many is a sign of a KARMA attack
+    pub responded: BTreeSet<String>,
+    /// The station count and channel utilisation out of 255 last reported by this access point
+    pub load: Option<(u16, u8)>,
+    /// Where it was heard from, as the sensor moved with --gps
+    pub geo: geo::Track,
+    /// What this access point last said about its transmit power
+    pub power: Option<power::Power>,
+    /// Hotspot 2.0 details advertised by this access point
+    pub hotspot: hotspot::Details,
+    /// False if this device is known only by reference from another device, ie. has not sent any data
+    pub sent: bool,
+    /// The devices that this one has referenced
+    pub knows: HashSet<MacAddress>,
+    /// The capture interfaces that have heard this device transmit, by name
+    pub sensors: HashMap<String, Sighting>,
+    /// The strongest signal heard each second over the last SIGNAL_HISTORY, by any sensor, oldest first
+    pub signal_history: VecDeque<(Duration, i8)>,
+    /// The last, weakest, strongest and average signal heard by any sensor, to judge how close it is
+    pub signal_strength: stats::SignalStrength,
+    /// True if this device has sent probe requests, ie. is a client looking for networks
+    pub probing: bool,
+    /// The networks this device has asked for by name, with how many requests named each
+    pub probes: HashMap<String, u64>,
+    /// Labels given to this device by the operator
+    pub tags: BTreeSet<String>,
+    /// Whether this device has acted as an access point or a client
+    pub role: Option<Role>,
+    /// When this device was first and last heard, since the capture started
+    pub first_seen: Option<Duration>,
+    pub last_seen: Option<Duration>,
+    /// When each visit started and ended, oldest first, as periods heard without a gap of PRESENCE_GAP
+    pub presence: Vec<(Duration, Duration)>,
+    /// The access point this client last exchanged data with
+    pub associated: Option<MacAddress>,
+    /// The access points this client has roamed between, and whether it seems to be moving
+    pub mobility: movement::Mobility,
+    /// The rates last advertised, in units of 500kbps with the top bit marking basic rates
+    pub rates: Option<Vec<u8>>,
+    /// The channel this access point last said it is on, which can differ from where it was heard when a neighbouring channel bleeds over
+    pub announced_channel: Option<u8>,
+    /// What this access point last advertised of Wi-Fi Protected Setup, if it offers it
+    pub wps: Option<Wps>,
+    /// The clients this access point has held frames for while they dozed, as its beacons' TIMs list them
+    pub power_save: PowerSave,
+    /// True if this device has relayed frames between access points over a wireless distribution system
+    pub wds: bool,
+    /// The layout of the elements in the last probe request sent, as given by `wifi::Tag::layout`
+    pub probe_layout: Option<String>,
+    /// What this client is, going by the elements of its probe and association requests
+    pub identity: Option<fingerprint::Identity>,
+    /// Data frames this device has sent and received
+    pub traffic: Traffic,
+    /// Frames this device has sent, by their subtype as the packet log names it
+    pub subtypes: BTreeMap<&'static str, u64>,
+    /// How reliably this access point's beacons have been heard
+    pub beacons: BeaconStats,
+    /// True if this device has advertised 802.11be, or Wi-Fi 7, support
+    pub eht: bool,
+    /// The multi-link device this access point is one link of, by its MLD address
+    pub mld: Option<MacAddress>,
+    /// The addresses of other links of the same multi-link device, as announced by this access point
+    pub links: BTreeSet<MacAddress>,
+    /// The messages of the WPA 4-way handshake heard between this access point and each client, one bit per message
+    pub handshakes: BTreeMap<MacAddress, u8>,
+    /// The other half of an OWE transition mode pair, being the open network if this is the encrypted one or vice versa
+    pub owe_pair: Option<MacAddress>,
+    /// ACKs heard for frames this device sent that weren't heard, and who most likely sent them
+    pub unanswered_acks: u64,
+    pub ack_peer: Option<MacAddress>,
+    /// Deauthentication and disassociation frames sent
+    pub deauths: u64,
+    /// The most deauthentications sent within any one DEAUTH_WINDOW, where a burst is the mark of an attack
+    pub deauth_burst: u32,
+    /// When the burst under way started, and how many it has counted
+    deauth_window: Option<(Duration, u32)>,
+    /// Every address heard sending beacons or probe responses for this BSSID, where more than one points to spoofing
+    pub advertisers: BTreeSet<MacAddress>,
+    /// Names this device went by and addresses it used, as given away by its unencrypted ARP, DHCP and mDNS traffic
+    pub hostnames: BTreeSet<String>,
+    pub ip_addresses: BTreeSet<IpAddr>,
+    /// The watchlist this device is on, where that watchlist highlights its devices
+    pub watched: Option<String>,
+    /// The channel an access point last announced it was moving to, and the one it was heard on at the time
+    pub channel_switch: Option<(Option<u32>, u32)>
+}
+impl KnownDevice {
+    fn new(address: MacAddress, oui_db: &OuiDatabase) -> Self {
+        Self {
+            manufacturer: oui_db.query_by_mac(&address).unwrap(/* Library should never be able to return an error */),
+            beacon: None,
+            hidden: false,
+            access: None,
+            security: None,
+            standards: None,
+            fingerprint: None,
+            fingerprint_changes: vec![],
+            responded: BTreeSet::new(),
+            load: None,
+            power: None,
+            geo: geo::Track::default(),
+            hotspot: Default::default(),
+            sent: false,
+            knows: HashSet::new(),
+            sensors: HashMap::new(),
+            signal_history: VecDeque::new(),
+            signal_strength: Default::default(),
+            probing: false,
+            probes: HashMap::new(),
+            tags: BTreeSet::new(),
+            role: None,
+            first_seen: None,
+            last_seen: None,
+            presence: vec![],
+            associated: None,
+            mobility: Default::default(),
+            rates: None,
+            announced_channel: None,
+            wps: None,
+            power_save: PowerSave::default(),
+            wds: false,
+            probe_layout: None,
+            identity: None,
+            traffic: Traffic::default(),
+            subtypes: BTreeMap::new(),
+            beacons: BeaconStats::default(),
+            eht: false,
+            mld: None,
+            links: BTreeSet::new(),
+            handshakes: BTreeMap::new(),
+            owe_pair: None,
+            unanswered_acks: 0,
+            deauths: 0,
+            deauth_burst: 0,
+            deauth_window: None,
+            ack_peer: None,
+            advertisers: BTreeSet::new(),
+            hostnames: BTreeSet::new(),
+            ip_addresses: BTreeSet::new(),
+            watched: None,
+            channel_switch: None
+        }
+    }
+    /// Known only from ACKs answering frames it sent out of range, rather than from anything it was heard sending
+    pub fn inferred(&self) -> bool {
+        !self.sent && self.unanswered_acks > 0
+    }
+    fn advertised_by(&mut self, transmitter: MacAddress) -> &mut Self {
+        self.advertisers.insert(transmitter);
+        self
+    }
+    fn unanswered_ack(&mut self, peer: Option<MacAddress>) -> &mut Self {
+        self.unanswered_acks += 1;
+        self.ack_peer = peer.or(self.ack_peer);
+        self
+    }
+    fn deauthenticated(&mut self, time: Duration) -> &mut Self {
+        self.deauths += 1;
+        let window = match self.deauth_window {
+            Some((start, sent)) if time < start + DEAUTH_WINDOW => (start, sent + 1),
+            _ => (time, 1)
+        };
+        self.deauth_window = Some(window);
+        self.deauth_burst = self.deauth_burst.max(window.1);
+        self
+    }
+    fn sent(&mut self) -> &mut Self {
+        self.sent = true;
+        self
+    }
+    fn knows(&mut self, address: MacAddress) -> &mut Self {
+        self.knows.insert(address);
+        self
+    }
+    fn beacon(&mut self, ssid: String, access: hotspot::Access) -> &mut Self {
+        // A hidden network sends an empty SSID, or one of the right length but blanked out
+        if ssid.chars().all(|c| c == '\0') {
+            self.hidden = true;
+            self.beacon.get_or_insert_with(String::new);
+        } else {
+            self.beacon = Some(ssid);
+        }
+        self.access = Some(access);
+        self
+    }
+    fn security(&mut self, capabilities: u16, tags: &[wifi::Tag]) -> &mut Self {
+        self.security = Some(policy::Security::from_beacon(capabilities, tags));
+        self
+    }
+    fn fingerprint(&mut self, fingerprint: twins::Fingerprint) -> &mut Self {
+        if let Some(earlier) = &self.fingerprint {
+            let changes = fingerprint.changes(earlier, self.channel_switch.map(|(_, to)| to));
+            if !changes.is_empty() {
+                self.fingerprint_changes = changes;
+            }
+        }
+        self.fingerprint = Some(fingerprint);
+        self
+    }
+    fn standards(&mut self, tags: &[wifi::Tag]) -> &mut Self {
+        self.standards = Some(standards::Standards::from_tags(tags));
+        self
+    }
+    fn responded(&mut self, ssid: &str) -> &mut Self {
+        if !ssid.chars().all(|c| c == '\0') {
+            self.responded.insert(ssid.to_string());
+        }
+        self
+    }
+    fn power(&mut self, tags: &[wifi::Tag], frequency: Option<u32>) -> &mut Self {
+        if let Some(power) = power::Power::from_beacon(tags, frequency.and_then(nl80211::frequency_to_channel)) {
+            self.power = Some(power);
+        }
+        self
+    }
+    fn load(&mut self, tags: &[wifi::Tag]) -> &mut Self {
+        if let Some(load) = tags.iter().find_map(|tag| match *tag {
+            wifi::Tag::BssLoad { stations, utilisation } => Some((stations, utilisation)),
+            _ => None
+        }) {
+            self.load = Some(load);
+        }
+        self
+    }
+    fn rates(&mut self, tags: &[wifi::Tag]) -> &mut Self {
+        let rates = wifi::Tag::rates(tags);
+        if !rates.is_empty() {
+            self.rates = Some(rates);
+        }
+        self
+    }
+    fn announced_channel(&mut self, tags: &[wifi::Tag]) -> &mut Self {
+        if let Some(channel) = tags.iter().find_map(|tag| match *tag {
+            wifi::Tag::DsParameter { channel } => Some(channel),
+            _ => None
+        }) {
+            self.announced_channel = Some(channel);
+        }
+        self
+    }
+    fn tim(&mut self, tags: &[wifi::Tag], time: Duration) -> &mut Self {
+        for tag in tags {
+            if let wifi::Tag::Tim { dtim_period, aids, .. } = tag {
+                self.power_save.dtim_period = Some(*dtim_period);
+                for &aid in aids {
+                    self.power_save.buffered.insert(aid, time);
+                }
+            }
+        }
+        self
+    }
+    fn wps(&mut self, tags: &[wifi::Tag]) -> &mut Self {
+        self.wps = Wps::from_beacon(tags);
+        self
+    }
+    /// Learn the SSID of a hidden network from frames naming it
+    fn reveal(&mut self, ssid: String) -> &mut Self {
+        if self.hidden && !ssid.chars().all(|c| c == '\0') {
+            self.beacon = Some(ssid);
+        }
+        self
+    }
+    fn hotspot(&mut self, update: impl FnOnce(&mut hotspot::Details)) -> &mut Self {
+        update(&mut self.hotspot);
+        self
+    }
+    fn probe(&mut self, ssid: String, tags: &[wifi::Tag]) -> &mut Self {
+        self.probing = true;
+        self.probe_layout = Some(wifi::Tag::layout(tags));
+        // An empty SSID is a wildcard probe for any network
+        if !ssid.is_empty() {
+            *self.probes.entry(ssid).or_default() += 1;
+        }
+        self
+    }
+    fn identify(&mut self, tags: &[wifi::Tag]) -> &mut Self {
+        if let Some(identity) = fingerprint::Identity::of(tags) {
+            match &mut self.identity {
+                Some(known) => known.merge(identity),
+                None => self.identity = Some(identity)
+            }
+        }
+        self
+    }
+    /// A device that has ever acted as an access point stays one, as phones sharing their connection are also clients
+    fn role(&mut self, role: Role) -> &mut Self {
+        if self.role != Some(Role::AccessPoint) {
+            self.role = Some(role);
+        }
+        self
+    }
+    fn wds(&mut self) -> &mut Self {
+        self.wds = true;
+        self
+    }
+    fn multi_link(&mut self, tags: &[wifi::Tag]) -> &mut Self {
+        for tag in tags {
+            match tag {
+                wifi::Tag::EhtCapabilities => self.eht = true,
+                wifi::Tag::MultiLink { mld: Some(mld), links } => {
+                    self.mld = Some(*mld);
+                    self.links.extend(links.iter().filter_map(|(_, address)| *address));
+                }
+                _ => ()
+            }
+        }
+        self
+    }
+    fn owe_pair(&mut self, bssid: MacAddress) -> &mut Self {
+        self.owe_pair = Some(bssid);
+        self
+    }
+    fn announced(&mut self, detail: layer3::Detail) -> &mut Self {
+        match detail {
+            layer3::Detail::Address(address) => self.ip_addresses.insert(address),
+            layer3::Detail::Hostname(hostname) => self.hostnames.insert(hostname)
+        };
+        self
+    }
+    fn handshake(&mut self, station: MacAddress, message: u8) -> &mut Self {
+        *self.handshakes.entry(station).or_default() |= 1 << (message - 1);
+        self
+    }
+    /// Whether enough of a handshake with any client was heard to test a passphrase against offline,
+    /// which takes the first two messages or the second and third
+    pub fn handshake_complete(&self) -> bool {
+        self.handshakes.values().any(|&messages| handshake_complete(messages))
+    }
+    fn beacon_heard(&mut self, sensor: &str, interval: u16, time: Duration) -> &mut Self {
+        self.beacons.heard(sensor, interval, time);
+        self
+    }
+    fn sent_data(&mut self, length: usize, qos: bool) -> &mut Self {
+        self.traffic.sent_frames += 1;
+        self.traffic.sent_bytes += length as u64;
+        self.traffic.qos |= qos;
+        self
+    }
+    fn received_data(&mut self, length: usize) -> &mut Self {
+        self.traffic.received_frames += 1;
+        self.traffic.received_bytes += length as u64;
+        self
+    }
+    fn associate(&mut self, bssid: MacAddress, time: Duration) -> &mut Self {
+        if let Some(from) = self.associated.filter(|&from| from != bssid) {
+            self.mobility.roam(from, bssid, time);
+        }
+        self.associated = Some(bssid);
+        self
+    }
+    fn seen_by(&mut self, heard: Heard) -> &mut Self {
+        self.first_seen = self.first_seen.or(Some(heard.time));
+        self.last_seen = Some(heard.time);
+        match self.presence.last_mut() {
+            Some((_, until)) if heard.time <= *until + PRESENCE_GAP => *until = heard.time.max(*until),
+            _ => self.presence.push((heard.time, heard.time))
+        }
+        if !self.sensors.contains_key(heard.sensor) {
+            self.sensors.insert(heard.sensor.to_string(), Sighting::default());
+        }
+        let sighting = self.sensors.get_mut(heard.sensor).unwrap();
+        sighting.frames += 1;
+        if heard.signal.is_some() {
+            sighting.signal = heard.signal;
+        }
+        if heard.frequency.is_some() {
+            sighting.frequency = heard.frequency;
+        }
+        sighting.signal_quality.heard(heard.snr, heard.noise);
+        if heard.quality.is_some() {
+            sighting.quality = heard.quality;
+        }
+        if let Some(signal) = heard.signal {
+            self.signal_strength.heard(signal);
+            match self.signal_history.back_mut() {
+                Some((time, strongest)) if time.as_secs() == heard.time.as_secs() => *strongest = signal.max(*strongest),
+                _ => {
+                    self.signal_history.push_back((heard.time, signal));
+                    // Judged once a second rather than for every frame
+                    if self.role == Some(Role::Station) {
+                        self.mobility.assess(&self.signal_history, heard.time);
+                    }
+                }
+            }
+            while self.signal_history.front().map_or(false, |&(time, _)| time + SIGNAL_HISTORY < heard.time) {
+                self.signal_history.pop_front();
+            }
+        }
+        self
+    }
+    /// The sensor that last heard this device the loudest
+    pub fn strongest_sensor(&self) -> Option<(&str, &Sighting)> {
+        self.sensors.iter()
+            .max_by_key(|(name, sighting)| (sighting.signal.unwrap_or(i8::MIN), std::cmp::Reverse(name.as_str())))
+            .map(|(name, sighting)| (name.as_str(), sighting))
+    }
+}
+
+/// The part a device plays in a network
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    AccessPoint,
+    Station
+}
+
+/// Whether the messages of a handshake, one bit each, are enough to test a passphrase against
+pub fn handshake_complete(messages: u8) -> bool {
+    messages & 0b0011 == 0b0011 || messages & 0b0110 == 0b0110
+}
+
+/// Data frames passed to and from a device, counting whole frames
+#[derive(Debug, Default)]
+pub struct Traffic {
+    pub sent_frames: u64,
+    pub sent_bytes: u64,
+    pub received_frames: u64,
+    pub received_bytes: u64,
+    /// True if the device has sent QoS data, as WMM and anything from 802.11n on does
+    pub qos: bool
+}
+impl Traffic {
+    pub fn bytes(&self) -> u64 {
+        self.sent_bytes + self.received_bytes
+    }
+}
+
+/// What an access point's traffic indication maps say about its clients that doze to save power
+/// A client listed there is associated even when it is too quiet or far off to be heard itself
+#[derive(Debug, Default)]
+pub struct PowerSave {
+    /// How many beacons apart it delivers group traffic, as its TIM says
+    pub dtim_period: Option<u8>,
+    /// The association ID of each client, as given in association responses and PS-Polls heard
+    pub aids: BTreeMap<u16, MacAddress>,
+    /// Every association ID it has held frames for, with when it was last listed
+    pub buffered: BTreeMap<u16, Duration>
+}
+impl PowerSave {
+    fn assigned(&mut self, aid: u16, station: MacAddress) {
+        // A client keeps one ID at a time, and is given another when it reassociates
+        self.aids.retain(|_, client| *client != station);
+        self.aids.insert(aid, station);
+    }
+    /// Association IDs held for without any client heard being given them, with when each was last listed
+    pub fn unheard(&self) -> impl Iterator<Item = (u16, Duration)> + '_ {
+        self.buffered.iter()
+            .filter(move |(aid, _)| !self.aids.contains_key(aid))
+            .map(|(&aid, &time)| (aid, time))
+    }
+}
+
+/// Wi-Fi Protected Setup as an access point advertises it, which lets a device join by PIN or button rather than passphrase
+/// and, while PINs are taken, lets anyone in range try to guess one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Wps {
+    /// Whether it has been set up, if it says
+    pub configured: Option<bool>,
+    /// It has stopped taking PINs
+    pub locked: bool,
+    /// The maker and model it names
+    pub device: Option<String>
+}
+impl Wps {
+    pub fn from_beacon(tags: &[wifi::Tag]) -> Option<Self> {
+        tags.iter().find_map(|tag| match tag {
+            wifi::Tag::Wps { configured, locked, .. } => Some(Self {
+                configured: *configured,
+                locked: *locked,
+                device: fingerprint::Identity::of(tags).and_then(|identity| identity.device)
+            }),
+            _ => None
+        })
+    }
+}
+
+/// Beacons heard from an access point against how many it should have sent, going by its beacon interval
+/// Missing beacons point to interference or a weak signal, and malformed ones to a buggy or spoofed access point
+#[derive(Debug, Default)]
+pub struct BeaconStats {
+    /// Only beacons heard by one sensor are counted, as several would hear the same beacon
+    pub sensor: Option<String>,
+    pub interval: Option<Duration>,
+    pub received: u64,
+    pub first: Option<Duration>,
+    pub last: Option<Duration>,
+    pub malformed: u64
+}
+impl BeaconStats {
+    fn heard(&mut self, sensor: &str, interval: u16, time: Duration) {
+        if self.sensor.get_or_insert_with(|| sensor.to_string()) != sensor {
+            return
+        }
+        // An interval of 0 is meaningless, and would make every beacon seem lost
+        if interval > 0 {
+            self.interval = Some(Duration::from_micros(interval as u64 * 1024));
+        }
+        self.received += 1;
+        self.first = self.first.or(Some(time));
+        self.last = Some(time);
+    }
+    /// How many beacons should have arrived between the first and last heard
+    pub fn expected(&self) -> Option<u64> {
+        let span = self.last? - self.first?;
+        Some((span.as_micros() / self.interval?.as_micros()) as u64 + 1)
+    }
+    /// The fraction of expected beacons that weren't heard
+    pub fn loss(&self) -> Option<f64> {
+        let expected = self.expected()?;
+        Some(expected.saturating_sub(self.received) as f64 / expected as f64)
+    }
+}
+
+/// How a single sensor has heard a device
+#[derive(Debug, Default)]
+pub struct Sighting {
+    /// Frames from the device captured by this sensor
+    pub frames: u64,
+    /// The most recent signal strength in dBm, if the adapter reports it
+    pub signal: Option<i8>,
+    /// The frequency in MHz the device was last heard on, if the adapter reports it
+    pub frequency: Option<u32>,
+    /// Signal to noise ratios and noise floors of the device's frames heard by this sensor
+    pub signal_quality: stats::SignalQuality,
+    /// The most recent quality the adapter gave the signal, on a scale of its own
+    pub quality: Option<u16>
+}
+impl Sighting {
+    pub fn channel(&self) -> Option<u32> {
+        self.frequency.and_then(nl80211::frequency_to_channel)
+    }
+    pub fn band(&self) -> Option<nl80211::BandKind> {
+        self.frequency.and_then(nl80211::frequency_to_band)
+    }
+}
+
+/// A frame as it was heard: by which sensor, when since the capture started, and how loud and on what frequency
+#[derive(Debug, Clone, Copy)]
+pub struct Heard<'a> {
+    pub sensor: &'a str,
+    pub time: Duration,
+    pub signal: Option<i8>,
+    pub frequency: Option<u32>,
+    /// The noise floor in dBm, if the adapter reports it
+    pub noise: Option<i8>,
+    /// How far the signal stood above the noise in dB
+    pub snr: Option<i16>,
+    /// The adapter's own measure of the signal's quality, with no set scale
+    pub quality: Option<u16>
+}
+
+/// Every device tracked, along with the operator's merges and splits of them, which outlast clearing the list
+#[derive(Debug, Default)]
+pub struct DeviceList {
+    devices: HashMap<MacAddress, KnownDevice>,
+    overrides: Rc<RefCell<clusters::Overrides>>,
+    /// Whether any device may have changed since the interface was last drawn
+    changed: bool,
+    /// The most devices kept, beyond which those heard least recently are forgotten
+    limit: Option<usize>,
+    /// How many devices are kept from each manufacturer, counted as they are added and forgotten
+    manufacturers: HashMap<String, u64>,
+    /// How many devices have been forgotten to stay within the limit
+    pub evicted: u64,
+    /// What is left out to keep busy venues' noise out of the list
+    pub suppression: suppress::Suppression,
+    /// Bursts of deauthentications and disassociations, counted for each client of each network
+    pub deauths: deauth::Floods
+}
+impl DeviceList {
+    /// Keep at most this many devices, forgetting those heard least recently to make room
+    pub fn limit(&mut self, limit: usize) {
+        self.limit = Some(limit.max(MIN_DEVICES));
+    }
+    /// True if the list may have changed since `drawn` was last called, as it has been borrowed mutably since
+    pub fn changed(&self) -> bool {
+        self.changed
+    }
+    pub fn drawn(&mut self) {
+        self.changed = false
+    }
+    /// Shared with the Devices tab, where merges and splits are made
+    pub fn overrides(&self) -> Rc<RefCell<clusters::Overrides>> {
+        self.overrides.clone()
+    }
+    /// Forget every device, keeping the operator's merges and splits
+    pub fn clear(&mut self) {
+        self.devices.clear();
+        self.manufacturers.clear();
+        self.evicted = 0;
+        self.suppression.clear();
+        self.deauths.clear();
+        self.changed = true;
+    }
+    /// The device with an address, added if it hasn't been heard before
+    pub fn get_or_default(&mut self, address: MacAddress, oui_db: &OuiDatabase) -> &mut KnownDevice {
+        self.changed = true;
+        if !self.devices.contains_key(&address) {
+            if self.limit.map_or(false, |limit| self.devices.len() >= limit) {
+                self.evict();
+            }
+            let device = KnownDevice::new(address, oui_db);
+            if let Some(OuiEntry { name_short, .. }) = &device.manufacturer {
+                *self.manufacturers.entry(name_short.clone()).or_default() += 1;
+            }
+            self.devices.insert(address, device);
+        }
+        self.devices.get_mut(&address).unwrap()
+    }
+    /// Whether a device not yet tracked may be added, having been heard sending at a signal, or only inferred
+    /// from an acknowledgement
+    fn admits(&mut self, address: MacAddress, signal: Option<i8>, acked: bool) -> bool {
+        if self.devices.contains_key(&address) {
+            return true
+        }
+        if acked && self.suppression.ignore_acked {
+            self.suppression.acked += 1;
+            return false
+        }
+        !self.suppression.too_weak(signal)
+    }
+    /// Forget the devices heard least recently, a tenth of the limit at a time so the list isn't sorted for every new device
+    /// Devices the operator has tagged are kept
+    fn evict(&mut self) {
+        let limit = match self.limit {
+            Some(limit) => limit,
+            None => return
+        };
+        let mut candidates: Vec<_> = self.devices.iter()
+            .filter(|(_, device)| device.tags.is_empty())
+            .map(|(&address, device)| (device.last_seen, address))
+            .collect();
+        let count = (self.devices.len() + 1 - limit + limit / 10).min(candidates.len());
+        if count == 0 {
+            return
+        }
+        candidates.select_nth_unstable(count - 1);
+        for (_, address) in candidates.into_iter().take(count) {
+            if let Some(OuiEntry { name_short, .. }) = self.devices.remove(&address).and_then(|device| device.manufacturer) {
+                if let Some(devices) = self.manufacturers.get_mut(&name_short) {
+                    *devices -= 1;
+                    if *devices == 0 {
+                        self.manufacturers.remove(&name_short);
+                    }
+                }
+            }
+            self.evicted += 1;
+        }
+    }
+    /// Learn what a frame says about the devices it names, along with who an ACK heard with it came from
+    /// A frame that couldn't be parsed may still have an ACK to account for
+    pub fn ingest(&mut self, oui_db: &OuiDatabase, heard: Heard, ack: Option<acks::Ack>, frame: Option<wifi::Frame>) {
+        match ack {
+            Some(acks::Ack::Answered { acker }) => if self.admits(acker, heard.signal, true) {
+                self.get_or_default(acker, oui_db)
+                    .sent()
+                    .seen_by(heard);
+            }
+            // Not heard at all, so only its absence says anything about how near it is
+            Some(acks::Ack::Unanswered { receiver, peer }) => if self.admits(receiver, None, true) {
+                self.get_or_default(receiver, oui_db).unanswered_ack(peer);
+            }
+            _ => ()
+        }
+        // Counted whoever sent them, as a flood is worth knowing about however weak or spoofed its sender
+        if let Some(&wifi::Frame::Deauthentication { destination, source, bssid, reason, disassociation }) = frame.as_ref() {
+            self.deauths.frame(bssid, source, destination, reason, disassociation, heard.time);
+        }
+        if let Some(transmitter) = frame.as_ref().and_then(wifi::Frame::transmitter) {
+            if !self.admits(transmitter, heard.signal, false) {
+                return
+            }
+        }
+        use wifi::Frame::*;
+        if let Some(frame) = frame {
+            match frame {
+                Beacon {
+                    source,
+                    destination,
+                    bssid,
+                    ssid,
+                    capabilities,
+                    interval,
+                    tags
+                } => {
+                    let fingerprint = twins::Fingerprint::from_beacon(&ssid, capabilities, interval, &tags);
+                    let earlier = self.devices.get(&source).and_then(|device| device.fingerprint.as_ref());
+                    if self.suppression.collapse(source, &fingerprint, earlier, heard.time) {
+                        self.get_or_default(source, oui_db)
+                            .seen_by(heard)
+                            .beacon_heard(heard.sensor, interval, heard.time)
+                            .tim(&tags, heard.time);
+                        return
+                    }
+                    self.get_or_default(bssid, oui_db)
+                        .role(Role::AccessPoint)
+                        .advertised_by(source);
+                    self.get_or_default(source, oui_db)
+                        .sent()
+                        .seen_by(heard)
+                        .beacon_heard(heard.sensor, interval, heard.time)
+                        .role(Role::AccessPoint)
+                        .fingerprint(fingerprint)
+                        .beacon(ssid, hotspot::Access::from_beacon(capabilities, &tags))
+                        .security(capabilities, &tags)
+                        .power(&tags, heard.frequency)
+                        .load(&tags)
+                        .rates(&tags)
+                        .announced_channel(&tags)
+                        .wps(&tags)
+                        .tim(&tags, heard.time)
+                        .standards(&tags)
+                        .multi_link(&tags)
+                        .hotspot(|hotspot| hotspot.update_from_beacon(&tags))
+                        .knows(destination);
+                    self.get_or_default(destination, oui_db);
+                    // The encrypted half of the pair usually hides its SSID, which the open half gives away
+                    for tag in tags.iter() {
+                        if let wifi::Tag::OweTransition { bssid, ssid } = tag {
+                            self.get_or_default(source, oui_db).owe_pair(*bssid);
+                            self.get_or_default(*bssid, oui_db)
+                                .owe_pair(source)
+                                .reveal(ssid.clone());
+                        }
+                    }
+                }
+                AssociationResponse {
+                    destination,
+                    source,
+                    bssid,
+                    status,
+                    aid
+                } => {
+                    const SUCCESS: u16 = 0;
+                    self.get_or_default(source, oui_db)
+                        .sent()
+                        .seen_by(heard)
+                        .role(Role::AccessPoint)
+                        .knows(destination);
+                    if status == SUCCESS {
+                        self.get_or_default(bssid, oui_db).power_save.assigned(aid, destination);
+                        self.get_or_default(destination, oui_db)
+                            .role(Role::Station)
+                            .associate(bssid, heard.time);
+                    }
+                }
+                AssociationRequest {
+                    source,
+                    bssid,
+                    ssid,
+                    tags
+                } => {
+                    self.get_or_default(source, oui_db)
+                        .sent()
+                        .seen_by(heard)
+                        .role(Role::Station)
+                        .rates(&tags)
+                        .identify(&tags)
+                        .knows(bssid);
+                    self.get_or_default(bssid, oui_db)
+                        .role(Role::AccessPoint)
+                        .reveal(ssid);
+                }
+                ProbeResponse {
+                    source,
+                    destination,
+                    bssid,
+                    ssid,
+                    capabilities,
+                    tags
+                } => {
+                    self.get_or_default(bssid, oui_db)
+                        .role(Role::AccessPoint)
+                        .advertised_by(source);
+                    self.get_or_default(source, oui_db)
+                        .sent()
+                        .seen_by(heard)
+                        .role(Role::AccessPoint)
+                        .responded(&ssid)
+                        .beacon(ssid, hotspot::Access::from_beacon(capabilities, &tags))
+                        .security(capabilities, &tags)
+                        .power(&tags, heard.frequency)
+                        .load(&tags)
+                        .rates(&tags)
+                        .announced_channel(&tags)
+                        .wps(&tags)
+                        .standards(&tags)
+                        .knows(destination);
+                    self.get_or_default(destination, oui_db)
+                        .role(Role::Station);
+                }
+                Data {
+                    bssid,
+                    station,
+                    from_station,
+                    qos,
+                    length,
+                    eapol,
+                    announcements
+                } => {
+                    if let Some(message) = eapol {
+                        self.get_or_default(bssid, oui_db).handshake(station, message);
+                    }
+                    // Hosts beyond the access point are only heard of this way, so aren't added as devices
+                    for announcement in announcements {
+                        if let Some(device) = self.get_mut(&announcement.device) {
+                            device.announced(announcement.detail);
+                        }
+                    }
+                    let (source, destination) = if from_station { (station, bssid) } else { (bssid, station) };
+                    self.get_or_default(source, oui_db)
+                        .sent()
+                        .seen_by(heard)
+                        .sent_data(length, qos)
+                        .knows(destination);
+                    if destination.is_unicast() {
+                        self.get_or_default(destination, oui_db)
+                            .received_data(length);
+                    }
+                    self.get_or_default(bssid, oui_db)
+                        .role(Role::AccessPoint);
+                    // Group addressed frames from the access point don't name a client
+                    if station.is_unicast() {
+                        self.get_or_default(station, oui_db)
+                            .role(Role::Station)
+                            .associate(bssid, heard.time);
+                    }
+                }
+                Wds {
+                    receiver,
+                    transmitter
+                } => {
+                    self.get_or_default(transmitter, oui_db)
+                        .sent()
+                        .seen_by(heard)
+                        .role(Role::AccessPoint)
+                        .wds()
+                        .knows(receiver);
+                    self.get_or_default(receiver, oui_db)
+                        .role(Role::AccessPoint)
+                        .wds();
+                }
+                ProbeRequest {
+                    source,
+                    destination,
+                    ssid,
+                    tags,
+                    ..
+                } => {
+                    self.get_or_default(source, oui_db)
+                        .sent()
+                        .seen_by(heard)
+                        .role(Role::Station)
+                        .probe(ssid, &tags)
+                        .rates(&tags)
+                        .identify(&tags)
+                        .knows(destination);
+                }
+                Deauthentication {
+                    source,
+                    ..
+                } => {
+                    self.get_or_default(source, oui_db)
+                        .sent()
+                        .seen_by(heard)
+                        .deauthenticated(heard.time);
+                }
+                ChannelSwitch {
+                    source,
+                    ..
+                } => {
+                    self.get_or_default(source, oui_db)
+                        .sent()
+                        .seen_by(heard)
+                        .role(Role::AccessPoint);
+                }
+                GasResponse {
+                    source,
+                    destination,
+                    elements
+                } => {
+                    self.get_or_default(source, oui_db)
+                        .sent()
+                        .seen_by(heard)
+                        .hotspot(|hotspot| hotspot.update_from_anqp(&elements))
+                        .knows(destination);
+                }
+                Ack {
+                    receiver
+                } | Cts {
+                    receiver
+                } => if self.admits(receiver, None, true) {
+                    self.get_or_default(receiver, oui_db);
+                }
+                Control {
+                    receiver,
+                    transmitter
+                } => {
+                    self.get_or_default(transmitter, oui_db)
+                        .sent()
+                        .seen_by(heard)
+                        .knows(receiver);
+                    self.get_or_default(receiver, oui_db);
+                    // Either side may be an access point already known from its beacons, making the other its client
+                    for &(access_point, station) in [(receiver, transmitter), (transmitter, receiver)].iter() {
+                        if self[&access_point].role == Some(Role::AccessPoint) && self[&station].role != Some(Role::AccessPoint) {
+                            self.get_or_default(station, oui_db)
+                                .role(Role::Station)
+                                .associate(access_point, heard.time);
+                        }
+                    }
+                }
+                PsPoll {
+                    bssid,
+                    station,
+                    aid
+                } => {
+                    self.get_or_default(station, oui_db)
+                        .sent()
+                        .seen_by(heard)
+                        .role(Role::Station)
+                        .associate(bssid, heard.time)
+                        .knows(bssid);
+                    self.get_or_default(bssid, oui_db)
+                        .role(Role::AccessPoint)
+                        .power_save
+                        .assigned(aid, station);
+                }
+                _ => ()
+            }
+        }
+    }
+    pub fn bar_data(&self) -> Vec<(&str, u64)> {
+        let mut values: Vec<(&str, u64)> = self.manufacturers.iter().map(|(name, &count)| (name.as_str(), count)).collect();
+        values.sort_by(|(nl, l), (nr, r)| l.cmp(r).then_with(|| nl.cmp(nr)));
+        values.reverse();
+        values
+    }
+    /// Every sensor that has heard any device, in name order
+    pub fn sensors(&self) -> Vec<&str> {
+        let mut sensors: Vec<&str> = self.values()
+            .flat_map(|device| device.sensors.keys().map(String::as_str))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        sensors.sort();
+        sensors
+    }
+    /// Every access point with the clients associated with it, along with clients whose access point is unknown
+    pub fn networks(&self) -> (Vec<(MacAddress, Vec<MacAddress>)>, Vec<MacAddress>) {
+        let mut networks: HashMap<MacAddress, Vec<MacAddress>> = self.iter()
+            .filter(|(_, device)| device.role == Some(Role::AccessPoint))
+            .map(|(&address, _)| (address, vec![]))
+            .collect();
+        let mut unassociated = vec![];
+        for (&address, device) in self.iter() {
+            match (device.role, device.associated) {
+                // A repeater's client side is nested under the access point it relays
+                (Some(_), Some(bssid)) => networks.entry(bssid).or_default().push(address),
+                (Some(Role::Station), None) => unassociated.push(address),
+                _ => ()
+            }
+        }
+        let mut networks: Vec<_> = networks.into_iter().collect();
+        for (_, clients) in networks.iter_mut() {
+            clients.sort();
+        }
+        networks.sort_by(|(l, l_clients), (r, r_clients)| r_clients.len().cmp(&l_clients.len()).then(l.cmp(r)));
+        unassociated.sort();
+        (networks, unassociated)
+    }
+    /// Every network name probed for, with the clients asking for it and how many requests each sent,
+    /// most asked for first
+    pub fn probed(&self) -> Vec<(&str, Vec<(MacAddress, u64)>)> {
+        let mut probed: HashMap<&str, Vec<(MacAddress, u64)>> = HashMap::new();
+        for (&address, device) in self.iter() {
+            for (ssid, &requests) in device.probes.iter() {
+                probed.entry(ssid.as_str()).or_default().push((address, requests));
+            }
+        }
+        let mut probed: Vec<_> = probed.into_iter().collect();
+        for (_, clients) in probed.iter_mut() {
+            clients.sort();
+        }
+        probed.sort_by(|(l, l_clients), (r, r_clients)| r_clients.len().cmp(&l_clients.len()).then(l.cmp(r)));
+        probed
+    }
+    /// Groups of addresses from one manufacturer that count up together and were heard at the same time,
+    /// most likely the radios of one physical device such as a dual band access point or a fleet of modules
+    pub fn units(&self) -> Vec<Vec<MacAddress>> {
+        /// The furthest apart two radios of one unit are assumed to be numbered
+        const MAX_STEP: u32 = 8;
+        /// How far apart the sightings of two radios may be while still counting as heard together
+        const SLACK: Duration = Duration::from_secs(60);
+
+        fn serial(address: &MacAddress) -> u32 {
+            let bytes = address.as_bytes();
+            u32::from_be_bytes([0, bytes[3], bytes[4], bytes[5]])
+        }
+        let heard_together = |l: &KnownDevice, r: &KnownDevice| match (l.first_seen, l.last_seen, r.first_seen, r.last_seen) {
+            (Some(l_first), Some(l_last), Some(r_first), Some(r_last)) => l_first <= r_last + SLACK && r_first <= l_last + SLACK,
+            _ => false
+        };
+
+        // Randomised addresses are not numbered by the manufacturer, so only vendor assigned ones are grouped
+        let mut addresses: Vec<_> = self.iter()
+            .filter(|(address, device)| device.sent && address.is_universal())
+            .map(|(&address, _)| address)
+            .collect();
+        addresses.sort();
+        let mut units = vec![];
+        let mut unit: Vec<MacAddress> = vec![];
+        for address in addresses {
+            let joins = unit.last().map_or(false, |last| {
+                last.as_bytes()[..3] == address.as_bytes()[..3]
+                    && serial(&address) - serial(last) <= MAX_STEP
+                    && heard_together(&self[last], &self[&address])
+            });
+            if !joins {
+                if unit.len() > 1 {
+                    units.push(unit);
+                }
+                unit = vec![];
+            }
+            unit.push(address);
+        }
+        if unit.len() > 1 {
+            units.push(unit);
+        }
+        units
+    }
+    /// The access points making up each multi-link device, by MLD address, including links announced but not heard
+    pub fn multi_link_devices(&self) -> HashMap<MacAddress, BTreeSet<MacAddress>> {
+        let mut devices: HashMap<MacAddress, BTreeSet<MacAddress>> = HashMap::new();
+        for (&address, device) in self.iter() {
+            if let Some(mld) = device.mld {
+                let links = devices.entry(mld).or_default();
+                links.insert(address);
+                links.extend(device.links.iter().copied());
+            }
+        }
+        devices
+    }
+    /// Devices that look like repeaters or range extenders, with why
+    pub fn repeaters(&self) -> HashMap<MacAddress, String> {
+        let mut repeaters = HashMap::new();
+        for (&address, device) in self.iter() {
+            if device.wds {
+                repeaters.insert(address, "relays frames over WDS".to_string());
+            } else if device.role == Some(Role::AccessPoint) && (device.probing || device.associated.is_some()) {
+                repeaters.insert(address, "acts as both an access point and a client".to_string());
+            }
+        }
+        // Extenders often run their access point and client sides under addresses one bit apart
+        for (&address, _) in self.iter().filter(|(_, device)| device.role == Some(Role::AccessPoint)) {
+            let bytes = address.to_array();
+            for bit in 0..48 {
+                let mut twin = bytes;
+                twin[bit / 8] ^= 0x80 >> (bit % 8);
+                let twin = MacAddress::new(twin);
+                if self.get(&twin).map_or(false, |other| other.role == Some(Role::Station)) {
+                    repeaters.entry(address).or_insert_with(|| format!("shares all but one bit of its address with client {}", twin.to_hex_string()));
+                    repeaters.entry(twin).or_insert_with(|| format!("shares all but one bit of its address with access point {}", address.to_hex_string()));
+                }
+            }
+        }
+        repeaters
+    }
+}
+impl Deref for DeviceList {
+    type Target = HashMap<MacAddress, KnownDevice>;
+    fn deref(&self) -> &Self::Target {
+        &self.devices
+    }
+}
+impl DerefMut for DeviceList {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.changed = true;
+        &mut self.devices
+    }
+}
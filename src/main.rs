@@ -1,268 +1,1102 @@
-use std::{collections::{HashMap, HashSet}, ops::{Deref, DerefMut}, fs};
-use eui48::MacAddress;
-use pcap::{Capture, Device};
+use std::{cell::RefCell, collections::VecDeque, env, ffi::OsString, fs, path::{Path, PathBuf}, rc::Rc, sync::atomic::{AtomicBool, Ordering}, thread, time::{Duration, Instant}};
+use pcap::Device;
 use radiotap::Radiotap;
-use oui::{OuiDatabase, OuiEntry};
-use clap::{Arg, App};
-use termion::{event::Key, input::MouseTerminal, raw::IntoRawMode, screen::AlternateScreen};
+use oui::OuiDatabase;
 use tui::{
-    backend::TermionBackend,
-    layout::{Constraint, Direction, Layout},
-    widgets::{Block, Borders, List, ListItem, Tabs},
+    layout::{Constraint, Direction, Layout, Rect},
+    widgets::{Block, Borders, List, ListItem, Paragraph, Tabs},
     style::{Style, Modifier, Color},
-    text::Spans
+    text::{Span, Spans}
 };
+use ui::Key;
+use keymap::Action;
 
 mod ui;
-mod wifi;
 mod page;
+mod filter;
+mod state;
+mod config;
+mod keymap;
+mod wizard;
+
+use blockade_recon::{
+    acks, alerts, anonymize, bluetooth, cli, clusters, control, gps, incident, interfaces, manuf, monitor, nl80211, outputs, privileges, prober, radio,
+    remote, rfkill, rules, sandbox, schedule, session, stats, store, survey, wifi,
+    DEAUTH_WINDOW, DeviceList, Heard, KnownDevice, PRESENCE_GAP, Role, Sighting, SIGNAL_HISTORY, fingerprint, handshake_complete, hotspot, movement, policy, privacy
+};
+#[cfg(feature = "metrics")]
+use blockade_recon::metrics;
 
 fn main() {
-    let args = App::new("Blockade Recon 2")
-        .version(env!("CARGO_PKG_VERSION"))
-        .about(env!("CARGO_PKG_DESCRIPTION"))
-        .author(env!("CARGO_PKG_AUTHORS"))
-        .arg(
-            Arg::with_name("interface")
-                .short("i")
-                .long("interface")
-                .help("Don't pick a default wireless interface to sniff traffic on")
-        )
-        .arg(
-            Arg::with_name("dont_monitor")
-                .short("m")
-                .long("dont-monitor")
-                .help("Don't try entering monitor mode using libpcap")
-        )
-        .arg(
-            Arg::with_name("database")
-                .short("-d")
-                .long("database")
-                .help("Specify the path to the OUI database file")
-                .value_name("FILE")
-        )
-        .get_matches();
+    ui::defer_panic_messages();
+    // Unwinding drops the UI, leaving raw mode and the alternate screen before anything is printed
+    if let Err(payload) = std::panic::catch_unwind(run) {
+        match payload.downcast::<ui::Fatal>() {
+            Ok(fatal) => {
+                eprintln!("{}", fatal);
+                std::process::exit(1)
+            }
+            Err(_) => {
+                eprintln!("{}", ui::panic_message().unwrap_or_else(|| "blockade-recon panicked".to_string()));
+                std::process::exit(101)
+            }
+        }
+    }
+}
 
-    let mut ui = ui::Ui::new();
+fn run() {
+    let app = cli::app();
+    let args = app.clone().get_matches();
+
+    // A first run with no config file and no interface named is walked through choosing one, rather than guessing
+    let mut first_run_ui = None;
+    let first_run = args.subcommand_name().is_none()
+        && !["headless", "config", "interface", "connect", "read"].iter().any(|option| args.is_present(option));
+    if let Some(path) = config::Config::path().filter(|path| first_run && !path.exists()) {
+        let mut ui = ui::Ui::new();
+        if !expect!(ui => wizard::run(&mut ui, &path), "Unable to write the config file") {
+            return
+        }
+        first_run_ui = Some(ui);
+    }
+
+    // Options missing from the command line are taken from the config file, by parsing again with them added
+    let config_path = args.value_of("config").map(PathBuf::from).or_else(config::Config::path);
+    let config = match config_path.as_deref().map(config::Config::load).transpose() {
+        Ok(config) => config.unwrap_or_default(),
+        Err(error) => {
+            eprintln!("{}: {}", config_path.unwrap_or_default().display(), error);
+            std::process::exit(1)
+        }
+    };
+    let defaults = config.args(&args);
+    let args = if args.subcommand_name().is_none() && !defaults.is_empty() {
+        let mut argv: Vec<OsString> = env::args_os().take(1).collect();
+        argv.extend(defaults);
+        argv.extend(env::args_os().skip(1));
+        match app.get_matches_from_safe(argv) {
+            Ok(args) => args,
+            Err(error) => {
+                eprintln!("Some options were read from {}", config_path.unwrap_or_default().display());
+                error.exit()
+            }
+        }
+    } else {
+        args
+    };
+    if cli::subcommand(&args, &config.policies) {
+        return
+    }
+
+    let headless = args.is_present("headless");
+    let mut ui = if headless { ui::Ui::headless() } else { first_run_ui.unwrap_or_else(ui::Ui::new) };
 
     let oui_db = if let Some(oui_path) = args.value_of("database") {
         let user_db = expect!(ui => fs::read_to_string(oui_path), "Unable to open specified OUI database file");
         expect!(ui => OuiDatabase::new_from_str(&user_db), "Unable to parse specified OUI database file")
     } else {
-        expect!(ui => OuiDatabase::new_from_export(include_bytes!("../manuf")), "Unable to parse default OUI database")
+        expect!(ui => manuf::load_default(), "Unable to load the default OUI database")
     };
     
-    let device = if args.is_present("interface") {
+    let named_interfaces: Vec<String> = args.values_of("interface").map(|names| names.map(str::to_string).collect()).unwrap_or_default();
+    let remote_sensors: Vec<String> = args.values_of("connect").map(|addresses| addresses.map(str::to_string).collect()).unwrap_or_default();
+    let speed = args.value_of("speed").map(|speed| {
+        let speed = expect!(ui => speed.parse::<f64>(), "The replay speed is not a number");
+        expect!(ui => Some(speed).filter(|speed| *speed > 0.0).ok_or("it must be more than 0"), "The replay speed is not a number")
+    });
+    // A pcap file read with --read is replayed like a session with a single sensor, knowing nothing of channels or positions
+    let mut replay = match (args.value_of("replay"), args.value_of("read")) {
+        (Some(path), _) => Some(expect!(ui => session::Replay::open(path, speed.or(Some(1.0))), "Unable to open the session to replay")),
+        (None, Some(path)) => Some(expect!(ui => session::Replay::pcap(path, speed), "Unable to read the capture file")),
+        (None, None) => None
+    };
+    // With only remote sensors the first of them takes the place of the local interface,
+    // and a replayed session stands in for every interface, so nothing local is touched either way
+    let remote_only = (!args.is_present("interface") && !remote_sensors.is_empty()) || replay.is_some();
+    let monitor = !args.is_present("dont_monitor");
+    let device_name = if let Some(replay) = &replay {
+        replay.sensors[0].clone()
+    } else if remote_only {
+        remote_sensors[0].clone()
+    } else if let Some(name) = named_interfaces.first() {
         let devices = expect!(ui => Device::list(), "Unable to find devices");
-        let devices_names: Vec<_> = devices.iter().map(|d| ListItem::new(vec![Spans::from(d.name.as_str())])).collect();
-        let list = List::new(devices_names)
-            .block(Block::default().borders(Borders::ALL).title("Select a WiFi Device"))
-            .highlight_style(Style::default().bg(Color::Reset).add_modifier(Modifier::REVERSED))
-            .highlight_symbol("> ");
-        let mut list_state = ui::ListState::with_item_count(devices.len());
-
-        fn draw(ui: &mut ui::Ui, list: &List, list_state: &mut ui::ListState) {
-            expect!(
-                ui =>
-                    ui.terminal.draw(|f| f.render_stateful_widget(list.clone(), f.size(), list_state)), 
-                    "Unable to create list widget"
-            )
+        match devices.into_iter().find(|device| &device.name == name) {
+            Some(device) => device.name,
+            // Fall back on the picker rather than giving up over a typo
+            None if !headless => {
+                ui.warning("Unable to find the specified interface", &format!("no interface named {:?}", name));
+                match pick_interface(&mut ui, monitor) {
+                    Some(name) => name,
+                    None => return
+                }
+            }
+            None => expect!(ui => Err("no such interface"), "Unable to find the specified interface")
         }
-        draw(&mut ui, &list, &mut list_state);
-        'select_device: loop {
-            for key in ui.input.stdin.iter() {
-                match key {
-                    Key::Esc => return,
-                    Key::Up | Key::Char('w') => list_state.up(),
-                    Key::Down | Key::Char('s') => list_state.down(),
-                    Key::PageUp => list_state.top(),
-                    Key::PageDown => list_state.bottom(),
-                    Key::Char('\n') => break 'select_device devices[list_state.selected().unwrap()].clone(),
-                    _ => continue
+    } else if args.is_present("interface") && !headless {
+        match pick_interface(&mut ui, monitor) {
+            Some(name) => name,
+            None => return
+        }
+    } else {
+        match Device::lookup() {
+            Ok(device) => device.name,
+            Err(error) if !headless => {
+                ui.warning("Unable to choose a default device", &error);
+                match pick_interface(&mut ui, monitor) {
+                    Some(name) => name,
+                    None => return
                 }
-                // Control flow will return after mutably borrowing the ui
-                break
             }
-            draw(&mut ui, &list, &mut list_state);
+            Err(error) => expect!(ui => Err(error), "Unable to choose a default device")
         }
+    };
+
+    // The first interface also drives the watchdog, hopping, rfkill and the remote control, the rest just listen
+    let sensor_names: Vec<String> = match &replay {
+        Some(replay) => replay.sensors.clone(),
+        None => std::iter::once(device_name.clone())
+            .chain(named_interfaces.iter().skip(1).cloned())
+            .chain(remote_sensors.iter().skip(if remote_only { 1 } else { 0 }).cloned())
+            .collect()
+    };
+    let local_sensors = if remote_only { 0 } else { named_interfaces.len().max(1) };
+    let connect_config = match args.value_of("connect_config") {
+        Some(path) => expect!(ui => remote::ClientConfig::load(path), "Unable to load the remote sensor configuration"),
+        None => remote::ClientConfig::default()
+    };
+    let mut rfkill = if remote_only { None } else { rfkill::Switch::find(&device_name).unwrap_or(None) };
+    if let Some(switch) = &mut rfkill {
+        if switch.hard {
+            let _: () = expect!(ui => Err(switch.describe()), "Unable to capture while the radio is blocked");
+        } else if switch.soft && ui.confirm(switch.describe(), &format!("Unblock {} and continue?", device_name)) {
+            if let Err(error) = switch.unblock() {
+                ui.warning("Unable to unblock the radio", &error)
+            }
+        }
+    }
+    let mut rfkill_checked = Instant::now();
+    if !remote_only {
+        expect!(ui => privileges::check(monitor), "Insufficient privileges to capture wireless traffic");
+    }
+    // Waited for before anything is opened or reconfigured, so nothing is captured or changed in the meantime
+    if let Some(start_at) = args.value_of("start_at") {
+        let start = expect!(ui => schedule::parse_start(start_at), "Unable to schedule the capture");
+        if !ui.wait_until(start, &format!("Waiting until {} to start capturing on {}", start_at, device_name)) {
+            return
+        }
+    }
+    // Dropped on the way out to put them back
+    let mut monitors: Vec<monitor::Monitor> = if args.is_present("auto_monitor") && !remote_only {
+        std::iter::once(&device_name).chain(named_interfaces.iter().skip(1))
+            .filter_map(|name| expect!(ui => monitor::Monitor::enter(name), "Unable to put the interface into monitor mode"))
+            .collect()
+    } else {
+        vec![]
+    };
+    let settings = expect!(ui => radio::Settings::parse(&args, &device_name, remote_only));
+    let mut radio = expect!(ui => radio::Radio::open(device_name.clone(), settings, Some(&connect_config).filter(|_| remote_only), replay.is_some()));
+    let mut prober = if args.is_present("active") {
+        Some(prober::Prober::new(args.values_of("probe_ssid").map_or_else(Vec::new, |ssids| ssids.map(String::from).collect())))
+    } else {
+        None
+    };
+    let mut schedule = schedule::Schedule::new(
+        args.value_of("duration")
+            .map(|minutes| expect!(ui => minutes.parse::<u64>(), "The capture duration is not a number of minutes"))
+            .map(|minutes| Duration::from_secs(minutes * 60)),
+        args.value_of("segment")
+            .map(|minutes| expect!(ui => minutes.parse::<u64>(), "The segment length is not a number of minutes"))
+            .map(|minutes| Duration::from_secs(minutes.max(1) * 60))
+    );
+    let anonymizer = anonymize::Anonymizer::new(args.is_present("anonymize"));
+    let survey = survey::Survey {
+        anonymizer: anonymizer.clone(),
+        ..survey::Survey::new(args.value_of("survey"), args.value_of("operator"), args.value_of("location"))
+    };
+    let mut captures = expect!(ui => outputs::Captures::open(&args, &schedule, &sensor_names, &survey, replay.is_some()));
+    let mut extra_sources = if replay.is_some() {
+        vec![]
     } else {
-        expect!(ui => Device::lookup(), "Unable to choose a default device")
+        expect!(ui => radio::listeners(&sensor_names, local_sensors, &radio.settings, &connect_config))
     };
+    for failure in radio.tune(&sensor_names, local_sensors) {
+        ui.warning(&failure.message, &failure.reason)
+    }
+    // Bound before dropping privileges so a low port may be used
+    let mut control = args.value_of("control").map(|address| {
+        let config = match args.value_of("control_config") {
+            Some(path) => expect!(ui => control::Config::load(path), "Unable to load the remote control configuration"),
+            None => control::Config::default()
+        };
+        expect!(ui => control::Server::bind(address, config), "Unable to listen for remote control requests")
+    });
+    #[cfg(feature = "metrics")]
+    let mut metrics = args.value_of("metrics_listen").map(|address| expect!(ui => metrics::Server::bind(address), "Unable to listen for metrics scrapes"));
+    #[cfg(not(feature = "metrics"))]
+    if args.is_present("metrics_listen") {
+        expect!(ui => Err::<(), _>("metrics support was not built in, rebuild with --features metrics"), "Unable to listen for metrics scrapes");
+    }
+
+    if !args.is_present("keep_privileges") {
+        if let Err(error) = privileges::drop(radio.privileges(!monitors.is_empty())) {
+            ui.warning("Unable to drop privileges after opening the capture", &error)
+        }
+    }
+    // Once privileges are dropped, so the threads putting interfaces back keep CAP_NET_ADMIN, but before the sandbox
+    for monitor in monitors.iter_mut() {
+        monitor.standby()
+    }
 
-    let capture = expect!(ui => Capture::from_device(device), "Unable to open capture device")
-        .promisc(true)
-        .rfmon(!args.is_present("dont_monitor"))
-        .immediate_mode(true);
-    let capture = expect!(ui => capture.open(), "Unable to start listening on capture device");
-    let mut capture = expect!(ui => capture.setnonblock(), "Unable to capture packets in a non-blocking fashion");
-    let mut savefile = expect!(ui => capture.savefile("capture.pcap"), "Unable to create save file for packet capture");
+    // Created up front so the sandbox need not allow writing wherever they are, or reaching whoever alerts are sent to
+    let mut reports = expect!(ui => outputs::Reports::open(&args, &schedule, survey, config.fields.clone(), &sensor_names, headless));
+    let mut export_result: Option<Result<String, String>> = None;
 
-    if capture.get_datalink() != pcap::Linktype::IEEE802_11_RADIOTAP {
-        let mut ok = false;
-        for datalink in expect!(ui => capture.list_datalinks(), "Unable to determine supported datalink layers") {
-            if datalink == pcap::Linktype::IEEE802_11_RADIOTAP {
-                ok = true;
-                expect!(ui => capture.set_datalink(datalink), "Unable to set the datalink layer")
+    // Opened before the sandbox is applied, as it lives outside of the current directory
+    let (mut ui_state_file, mut ui_state) = if args.is_present("no_state") {
+        (None, state::State::default())
+    } else {
+        match state::State::open() {
+            Ok((file, state)) => (Some(file), state),
+            Err(error) => {
+                ui.warning("Unable to restore the layout from the last run", &error);
+                (None, state::State::default())
             }
         }
-        if !ok {
-            let _: () = expect!(ui => Err(""), "The interface does not support the radiotap datalink layer required by this program");
+    };
+    if let Some(filter) = args.value_of("filter") {
+        expect!(ui => filter::Filter::parse(filter), "Invalid packet log filter");
+        ui_state.set("packets.filter", filter);
+    }
+
+    // Started before the sandbox is applied, so gpsd or the GPS receiver can still be reached
+    let gps_baud = args.value_of("gps_baud")
+        .map(|baud| expect!(ui => baud.parse::<u32>().ok().filter(|baud| gps::BAUD_RATES.contains(baud)).ok_or(baud), "The GPS receiver's speed isn't a supported baud rate"));
+    let gps = args.value_of("gps").map(|receiver| gps::Gps::spawn(gps::Receiver::parse(receiver, gps_baud)));
+    #[cfg(feature = "bluetooth")]
+    let bluetooth = Some(()).filter(|_| args.is_present("bluetooth")).map(|_| bluetooth::Bluetooth::spawn(args.value_of("bluetooth_adapter").map(String::from)));
+    #[cfg(not(feature = "bluetooth"))]
+    let bluetooth = if args.is_present("bluetooth") {
+        expect!(ui => Err("Bluetooth support was not built in, rebuild with --features bluetooth"), "Unable to scan for Bluetooth devices")
+    } else {
+        None
+    };
+    let mut rules = rules::Rules::spawn(config.watches.clone());
+
+    if !args.is_present("no_sandbox") {
+        if radio.reopens() {
+            ui.warning("Not sandboxing the frame parser", &"restarting the interface needs network access, use --privsep to restart it from a separate process");
+        } else if let Err(error) = sandbox::apply(&{
+            let mut outputs: Vec<&Path> = captures.output().into_iter().collect();
+            if schedule.segment.is_some() {
+                outputs.extend(reports.given());
+            }
+            // Columns chosen on the Devices tab are saved to the config file on the way out
+            outputs.extend(config_path.as_deref().filter(|path| path.parent().map_or(false, Path::exists)));
+            outputs
+        }) {
+            ui.warning("Unable to sandbox the frame parser", &error)
         }
     }
 
     let mut devices = DeviceList::default();
-    let pages: &mut [&mut dyn page::Page] = &mut [&mut page::Devices::new(), &mut page::Manufacturers::new()];
-    let mut tabs = ui::TabState::new(pages.iter().map(|p| Spans::from(p.name())).collect());
+    if let Some(limit) = args.value_of("max_devices") {
+        devices.limit(expect!(ui => limit.parse::<usize>(), "The device limit is not a number of devices"));
+    }
+    devices.suppression.min_signal = args.value_of("min_rssi").map(|signal| expect!(ui => signal.parse::<i8>(), "The minimum signal is not a number of dBm"));
+    devices.suppression.collapse_beacons = args.is_present("collapse_beacons");
+    devices.suppression.ignore_acked = args.is_present("ignore_acked");
+    if let Some(threshold) = args.value_of("deauth_threshold") {
+        devices.deauths.threshold = expect!(ui => threshold.parse::<u32>(), "The deauthentication threshold is not a number of frames").max(1);
+    }
+    let mut packets = page::Packets::new();
+    let ingest_filter = args.value_of("ingest_filter").map(|filter| expect!(ui => filter::Filter::parse(filter), "Invalid ingest filter"));
+    let capture_started = Instant::now();
+    let active_window = expect!(ui => args.value_of("active_window").unwrap_or("300").parse::<u64>(), "The active window is not a number of seconds");
+    let stats = Rc::new(RefCell::new(stats::Stats::new(capture_started)));
+    if args.is_present("profile") {
+        stats.borrow_mut().profile();
+    }
+    let mut ack_timing = acks::AckTiming::new();
+    let mut drops_checked = Instant::now();
+    let severities = args.values_of("alert_severity")
+        .map(|settings| settings.map(|setting| expect!(ui => alerts::Alerts::parse_severity(setting), "Invalid alert severity")).collect())
+        .unwrap_or_default();
+    let escalations = args.values_of("alert_escalate")
+        .map(|settings| settings.map(|setting| expect!(ui => alerts::Alerts::parse_escalation(setting), "Invalid alert escalation rule")).collect())
+        .unwrap_or_default();
+    let alerts = Rc::new(RefCell::new(alerts::Alerts::new(severities, escalations, config.policies.clone())));
+    let mut alerts_checked = Instant::now();
+    let target = Rc::new(RefCell::new(None));
+    let drill_down = Rc::new(RefCell::new(None));
+    let detail = Rc::new(RefCell::new(None));
+    let initial_columns = config.columns.clone().unwrap_or_else(|| page::Column::DEFAULT.to_vec());
+    let columns = Rc::new(RefCell::new(initial_columns.clone()));
+    let pages: &mut [&mut dyn page::Page] = &mut [
+        &mut page::Devices::new(capture_started, Duration::from_secs(active_window), devices.overrides(), drill_down.clone(), columns.clone()),
+        &mut page::Manufacturers::new(drill_down.clone()),
+        &mut packets,
+        &mut page::Networks::new(),
+        &mut page::Probes::new(),
+        &mut page::Security::new(),
+        &mut page::Spectrum::new(),
+        &mut page::Stats::new(stats.clone()),
+        &mut page::Alerts::new(alerts.clone()),
+        &mut page::Events::new(),
+        &mut page::Timeline::new(capture_started),
+        &mut page::Bluetooth::new(bluetooth),
+        &mut page::Track::new(target.clone()),
+        &mut page::DeviceDetail::new(capture_started, detail.clone())
+    ];
+    let devices_tab = 0;
+    let track_tab = pages.len() - 2;
+    let detail_tab = pages.len() - 1;
+    // Where the device page was opened from, for going back to
+    let mut opened_from = devices_tab;
+    let buffer_while_paused = args.value_of("pause_mode") != Some("discard");
+    let sample_above = expect!(ui => args.value_of("sample_above").unwrap_or("5000").parse::<u64>(), "The sampling threshold is not a number of frames a second");
+    let sample_every = expect!(ui => args.value_of("sample_every").unwrap_or("10").parse::<u64>(), "The sampling interval is not a number of frames").max(1);
+    let fps = expect!(ui => args.value_of("fps").unwrap_or("20").parse::<u32>(), "The redraw rate is not a number of frames a second").max(1);
+    let redraw_every = Duration::from_secs(1) / fps;
+    // Whether anything shown has changed since the last redraw, other than the devices, which keep track themselves
+    let mut redraw = true;
+    let mut drawn = Instant::now();
+    let mut drawn_channel = String::new();
+    // Data frames seen since sampling started, of which every `sample_every`th is handled
+    let mut sampling: Option<u64> = None;
+    let mut paused = false;
+    // Whether the display only changes when a key is pressed, while frames are still taken in
+    let mut frozen = false;
+    // Frames waiting to be parsed, with when they arrived
+    let mut held = VecDeque::new();
+    let mut dropped = 0usize;
+    let mut reset_requested = false;
+    let keymap = keymap::Keymap::new(&config.keys);
+    let mut help = false;
+    // The search being typed, and the last one made
+    let mut searching: Option<String> = None;
+    let mut search: Option<page::Search> = None;
+    let mut tabs = ui::TabState::new(
+        pages.iter().map(|p| Spans::from(p.name())).collect(),
+        pages.iter().map(|p| Spans::from(p.short_name())).collect()
+    );
+    for page in pages.iter_mut() {
+        page.restore(&ui_state)
+    }
+    if let Some(tab) = ui_state.get("tab").and_then(|tab| tab.parse().ok()) {
+        tabs.select(tab)
+    }
+    for signal in [libc::SIGINT, libc::SIGTERM].iter() {
+        unsafe {
+            libc::signal(*signal, stop as extern "C" fn(libc::c_int) as libc::sighandler_t);
+        }
+    }
     'sniff: loop {
+        if STOPPING.load(Ordering::Relaxed) || schedule.finished() {
+            break 'sniff
+        }
+        // Reported once the keys are handled, as the warning waits on a key of its own
+        let mut unblock_error = None;
         for key in ui.input.stdin.try_iter() {
-            match key {
-                Key::Esc => break 'sniff,
-                Key::F(i) => tabs.select(i as usize),
-                Key::Char('\t') => tabs.next(),
-                Key::Up | Key::Char('w') => pages[tabs.index].up(),
-                Key::Down | Key::Char('s') => pages[tabs.index].down(),
-                Key::PageUp => pages[tabs.index].top(),
-                Key::PageDown => pages[tabs.index].bottom(),
-                _ => ()
+            redraw = true;
+            // Any key closes the help overlay, without doing anything else
+            if help {
+                help = false;
+                continue
+            }
+            if let Some(text) = &mut searching {
+                match key {
+                    Key::Char('\n') => {
+                        let text = searching.take().unwrap_or_default();
+                        search = Some(text.trim().to_string()).filter(|text| !text.is_empty())
+                            .map(|text| page::Search::run(text, pages, tabs.index, &devices));
+                        show_match(search.as_ref(), pages, &mut tabs);
+                    }
+                    Key::Char(c) => text.push(c),
+                    Key::Backspace => {
+                        text.pop();
+                    }
+                    Key::Esc => searching = None,
+                    _ => ()
+                }
+                continue
+            }
+            if pages[tabs.index].grabs_input() {
+                pages[tabs.index].key(key);
+                continue
+            }
+            match (keymap.action(key), key) {
+                (Some(Action::Quit), _) => break 'sniff,
+                (Some(Action::Help), _) => help = true,
+                (Some(Action::NextTab), _) => tabs.next(),
+                (Some(Action::Up), _) => pages[tabs.index].up(),
+                (Some(Action::Down), _) => pages[tabs.index].down(),
+                (Some(Action::Top), _) => pages[tabs.index].top(),
+                (Some(Action::Bottom), _) => pages[tabs.index].bottom(),
+                (Some(Action::Left), _) => pages[tabs.index].left(),
+                (Some(Action::Right), _) => pages[tabs.index].right(),
+                (Some(Action::Pause), _) => {
+                    paused = !paused;
+                    dropped = 0
+                }
+                (Some(Action::Freeze), _) => {
+                    frozen = !frozen;
+                    for page in pages.iter_mut() {
+                        page.freeze(frozen)
+                    }
+                }
+                (Some(Action::Search), _) => searching = Some(search.as_ref().map(|search| search.text.clone()).unwrap_or_default()),
+                (Some(Action::SearchNext), _) => if let Some(search) = &mut search {
+                    search.next();
+                    show_match(Some(search), pages, &mut tabs);
+                },
+                (Some(Action::SearchPrevious), _) => if let Some(search) = &mut search {
+                    search.previous();
+                    show_match(Some(search), pages, &mut tabs);
+                },
+                (Some(Action::Track), _) => if let Some(address) = pages[tabs.index].selected() {
+                    *target.borrow_mut() = Some(address);
+                    tabs.select(track_tab)
+                }
+                // Confirmed once input is no longer being read from
+                (Some(Action::Reset), _) => reset_requested = true,
+                (Some(Action::Export), _) => export_result = Some(reports.export(&devices, &alerts.borrow().alerts)),
+                (Some(Action::Map), _) => export_result = Some(reports.map(&devices)),
+                (Some(Action::Dump), _) => export_result = Some(captures.dump()),
+                // Named for when it was taken so each is kept, where `e` writes over the last export
+                (Some(Action::Snapshot), _) => export_result = Some(reports.snapshot(&devices)),
+                (Some(Action::Extract), _) if anonymizer.is_enabled() => {
+                    export_result = Some(Err("A device's evidence names it, so isn't extracted with --anonymize".to_string()));
+                }
+                (Some(Action::Extract), _) => if let Some(address) = pages[tabs.index].selected().filter(|address| devices.contains_key(address)) {
+                    export_result = Some(captures.extract(address, &devices[&address], &reports.survey));
+                },
+                (Some(Action::Lock), _) => if let Some(hopper) = &mut radio.hopper {
+                    hopper.locked = !hopper.locked
+                },
+                (Some(Action::Unblock), _) => if let Some(switch) = &mut rfkill {
+                    if switch.soft && !switch.hard {
+                        unblock_error = switch.unblock().err();
+                    }
+                },
+                (None, Key::F(i)) => tabs.select(i as usize),
+                (None, key) => pages[tabs.index].key(key)
+            }
+            // A manufacturer picked to have its devices listed, which the Devices tab takes up when next drawn
+            if drill_down.borrow().is_some() {
+                tabs.select(devices_tab)
             }
+            match pages[tabs.index].navigate() {
+                Some(page::Navigation::Device(address)) => {
+                    *detail.borrow_mut() = Some(address);
+                    opened_from = tabs.index;
+                    tabs.select(detail_tab)
+                }
+                Some(page::Navigation::Back) => tabs.select(opened_from),
+                None => ()
+            }
+        }
+        if let Some(error) = unblock_error {
+            ui.warning("Unable to unblock the radio", &error)
         }
 
-        expect!(
-            ui =>
-                ui.terminal.draw(|frame| {
-                    let areas = Layout::default()
-                        .direction(Direction::Vertical)
-                        .margin(0)
-                        .constraints([Constraint::Length(2), Constraint::Min(0)])
-                        .split(frame.size());
-                    frame.render_widget(
-                        Tabs::new(tabs.titles.clone())
-                            .block(Block::default().borders(Borders::BOTTOM))
-                            .select(tabs.index)
-                            .style(Style::reset())
-                            .highlight_style(Style::reset().add_modifier(Modifier::BOLD | Modifier::REVERSED)),
-                        areas[0]
-                    );
-                    pages[tabs.index].render(frame, areas[1], &mut devices)
-                }),
-                "Unable to draw to stdout"
-        );
+        // The segment just finished has its exports completed, and the next starts afresh in files named for when it started
+        let segment_ended = schedule.next_segment();
+        if segment_ended {
+            if let Err(error) = captures.next_segment(&schedule) {
+                ui.warning("Unable to start the next segment's save file", &error)
+            }
+            for failure in reports.next_segment(&schedule, &devices, &alerts.borrow().alerts) {
+                ui.warning(&failure.message, &failure.reason)
+            }
+        }
+        if reset_requested || segment_ended {
+            reset_requested = false;
+            if segment_ended || ui.confirm("Clear every device and counter?", &format!("{} device(s) will be forgotten, the capture file is kept", devices.len())) {
+                devices.clear();
+                rules.reset();
+                reports.reset();
+                held.clear();
+                dropped = 0;
+                export_result = None;
+                for page in pages.iter_mut() {
+                    page.reset();
+                    page.freeze(frozen)
+                }
+            }
+        }
 
-        match capture.next() {
-            Err(pcap::Error::NoMorePackets) | Err(pcap::Error::TimeoutExpired) => (),
-            Err(error) => expect!(ui => Err(error), "Unable to get next packet"),
-            Ok(packet) => {
-                savefile.write(&packet);
-        
-                let (radiotap, data) = expect!(ui => Radiotap::parse(packet.data), "Unable to parse radiotap header");
-                use wifi::Frame::*;
-                if let Ok(frame) = wifi::Frame::parse(data) {
-                    match frame {
-                        Beacon {
-                            source,
-                            destination,
-                            ssid,
-                            ..
-                        } => {
-                            devices.get_or_default(source, &oui_db)
-                                .sent()
-                                .beacon(ssid)
-                                .knows(destination);
-                            devices.get_or_default(destination, &oui_db);
+        if rfkill_checked.elapsed() >= Duration::from_secs(2) {
+            if let Some(switch) = &mut rfkill {
+                let _ = switch.refresh();
+            }
+            rfkill_checked = Instant::now();
+        }
+        if drops_checked.elapsed() >= Duration::from_secs(1) {
+            let mut stats = stats.borrow_mut();
+            if let Some(drops) = radio.source.drops() {
+                stats.drops.insert(radio.name.clone(), drops);
+            }
+            for (sensor, extra) in extra_sources.iter_mut() {
+                if let Some(drops) = extra.drops() {
+                    stats.drops.insert(sensor_names[*sensor].clone(), drops);
+                }
+            }
+            drops_checked = Instant::now();
+        }
+        if alerts_checked.elapsed() >= Duration::from_secs(1) {
+            let mut alerts = alerts.borrow_mut();
+            alerts.check(&devices, capture_started.elapsed());
+            if rules.check(&mut devices, &mut alerts, capture_started.elapsed()) {
+                ui.bell();
+            }
+            let mut stopped = reports.check(&devices, capture_started.elapsed());
+            for alert in alerts.take_fresh() {
+                stopped.extend(reports.alert(&alert));
+                captures.alert(&alert);
+            }
+            captures.check();
+            for failure in stopped {
+                ui.warning(&failure.message, &failure.reason)
+            }
+            alerts_checked = Instant::now();
+        }
+
+        expect!(ui => radio.tick());
+        #[cfg(feature = "metrics")]
+        if let Some(server) = &mut metrics {
+            server.poll(|| metrics::render(&devices, &stats.borrow(), &alerts.borrow()));
+        }
+        if let Some(server) = &mut control {
+            for request in server.poll() {
+                radio.answer(request, &mut devices, &mut captures, &reports, &alerts.borrow().alerts)
+            }
+        }
+        let channel_label = match (radio.channel, &radio.hopper) {
+            (Some(channel), Some(hopper)) if hopper.locked => format!("Channel {} (locked, l to hop)", channel),
+            (Some(channel), Some(_)) => format!("Channel {} (hopping, l to lock)", channel),
+            (Some(channel), None) if radio.settings.hop.is_some() => format!("Channel {} (hopping)", channel),
+            (Some(channel), None) => format!("Channel {}", channel),
+            (None, _) => String::new()
+        };
+
+        // Every frame is still counted, so the rate is of what is heard rather than what is handled
+        let rate = stats.borrow().last_second();
+        sampling = match sampling {
+            _ if sample_above == 0 || rate <= sample_above => None,
+            Some(seen) => Some(seen),
+            None => Some(0)
+        };
+
+        let mut status = vec![Span::styled(sensor_names.join(" + "), Style::reset().add_modifier(Modifier::BOLD))];
+        if reports.survey.is_named() {
+            status.push(Span::raw(format!(" | {}", reports.survey)));
+        }
+        let narrow = ui.size().map_or(false, ui::narrow);
+        // A replay goes quiet when the session did, or once it has played out, with nothing wrong
+        if let Some(silence) = radio.watchdog.silence().filter(|_| replay.is_none()) {
+            status.push(Span::styled(
+                if narrow {
+                    format!(" | No frames for {}s", silence.as_secs())
+                } else {
+                    format!(" | No frames for {}s: the adapter may be wedged, on a quiet channel or blocked by rfkill", silence.as_secs())
+                },
+                Style::reset().fg(Color::Yellow)
+            ));
+        }
+        if let Some(switch) = rfkill.as_ref().filter(|switch| switch.blocked()) {
+            status.push(Span::styled(format!(" | {}", switch.describe()), Style::reset().fg(Color::Red)));
+            if !switch.hard && switch.can_unblock() {
+                status.push(Span::raw(", press u to unblock"));
+            }
+        }
+        if schedule.segment.is_some() {
+            status.push(Span::styled(format!(" | Segment {}", schedule.segments), Style::reset().fg(Color::Cyan)));
+        }
+        if let Some(remaining) = schedule.remaining() {
+            status.push(Span::styled(format!(" | Stopping in {}m", (remaining.as_secs() + 59) / 60), Style::reset().fg(Color::Cyan)));
+        }
+        match &prober {
+            Some(prober) if prober.error.is_some() => status.push(Span::styled(
+                format!(" | Active probing failed: {}", prober.error.as_deref().unwrap_or_default()),
+                Style::reset().fg(Color::Red)
+            )),
+            Some(prober) => status.push(Span::styled(
+                format!(" | Probing actively, {} probe request(s) sent", prober.sent),
+                Style::reset().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+            )),
+            None => ()
+        }
+        if sampling.is_some() {
+            status.push(Span::styled(
+                format!(" | Sampling 1 in {} data frames at {} frames/s", sample_every, stats.borrow().last_second()),
+                Style::reset().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+            ));
+        }
+        if frozen {
+            status.push(Span::styled(" | Frozen, z to thaw", Style::reset().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
+        }
+        if paused {
+            status.push(Span::styled(
+                match (buffer_while_paused, dropped) {
+                    (true, 0) => format!(" | Paused, {} frame(s) held", held.len()),
+                    (true, dropped) => format!(" | Paused, {} frame(s) held and {} dropped", held.len(), dropped),
+                    (false, dropped) => format!(" | Paused, {} frame(s) discarded", dropped)
+                },
+                Style::reset().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+            ));
+        }
+        match &export_result {
+            Some(Ok(message)) => status.push(Span::raw(format!(" | {}", message))),
+            Some(Err(error)) => status.push(Span::styled(format!(" | Export failed: {}", error), Style::reset().fg(Color::Red))),
+            None => ()
+        }
+        match &captures.recorder {
+            Some(recorder) if captures.saving => status.push(Span::raw(format!(
+                " | Saving to {}",
+                recorder.current().file_name().map_or_else(|| recorder.current().display().to_string(), |name| name.to_string_lossy().to_string())
+            ))),
+            _ => status.push(Span::styled(" | Not saving frames", Style::reset().fg(Color::Yellow)))
+        }
+        if let (unhandled, Some(severity)) = alerts.borrow().unhandled() {
+            status.push(Span::styled(
+                format!(" | {} unhandled alert(s)", unhandled),
+                Style::reset().fg(page::severity_colour(severity)).add_modifier(Modifier::BOLD)
+            ));
+        }
+        if let Some(replay) = &replay {
+            let seconds = replay.position.as_secs();
+            status.push(Span::raw(match (replay.finished, replay.speed) {
+                (true, _) => " | Replay finished".to_string(),
+                (false, Some(speed)) => format!(" | Replaying {}:{:02} at {}x", seconds / 60, seconds % 60, speed),
+                (false, None) => format!(" | Reading {}:{:02}", seconds / 60, seconds % 60)
+            }));
+        }
+        if let Some(error) = &captures.session_error {
+            status.push(Span::styled(format!(" | Recording the session failed: {}", error), Style::reset().fg(Color::Red)));
+        }
+        if let Some(gps) = &gps {
+            match (gps.fix(), gps.error.lock().ok().and_then(|error| error.clone())) {
+                (Some(fix), _) => status.push(Span::raw(format!(" | {:.5}, {:.5}", fix.latitude, fix.longitude))),
+                (None, Some(error)) => status.push(Span::styled(format!(" | GPS failed: {}", error), Style::reset().fg(Color::Red))),
+                (None, None) => status.push(Span::styled(" | No GPS fix", Style::reset().fg(Color::LightYellow)))
+            }
+        }
+        if let Some(error) = reports.mailer.as_ref().and_then(|mailer| mailer.error.lock().ok()?.clone()) {
+            status.push(Span::styled(format!(" | Email failed: {}", error), Style::reset().fg(Color::Red)));
+        }
+        if let Some(error) = reports.notifier.as_ref().and_then(|notifier| notifier.error.lock().ok()?.clone()) {
+            status.push(Span::styled(format!(" | Notifying failed: {}", error), Style::reset().fg(Color::Red)));
+        }
+        if let Some(recording) = captures.incidents.as_ref().and_then(incident::Incidents::recording) {
+            status.push(Span::styled(
+                format!(" | Recording incident to {}", recording.file_name().map_or_else(|| recording.display().to_string(), |name| name.to_string_lossy().to_string())),
+                Style::reset().fg(Color::LightRed).add_modifier(Modifier::BOLD)
+            ));
+        }
+        if let Some(handshakes) = captures.handshakes.as_ref().filter(|handshakes| handshakes.frames > 0) {
+            let complete = devices.values().filter(|device| device.handshake_complete()).count();
+            status.push(Span::styled(
+                format!(" | {} network(s) with handshakes in {}", complete, handshakes.path().display()),
+                Style::reset().fg(Color::LightMagenta)
+            ));
+        }
+        if let Some(error) = &captures.handshake_error {
+            status.push(Span::styled(format!(" | Saving handshakes failed: {}", error), Style::reset().fg(Color::Red)));
+        }
+        if let Some(error) = &captures.incident_error {
+            status.push(Span::styled(format!(" | Incident capture failed: {}", error), Style::reset().fg(Color::Red)));
+        }
+        if let Some(outcome) = reports.hook.as_ref().and_then(|hook| Some(hook.outcome.lock().ok()?.clone())) {
+            if outcome.skipped > 0 {
+                status.push(Span::raw(format!(" | {} alert command run(s) skipped", outcome.skipped)));
+            }
+            if let Some(error) = outcome.error {
+                status.push(Span::styled(format!(" | Alert command failed: {}", error), Style::reset().fg(Color::Red)));
+            }
+        }
+        let malformed = stats.borrow().malformed;
+        if malformed > 0 {
+            status.push(Span::raw(format!(" | {} malformed frame(s) skipped", malformed)));
+        }
+        if devices.evicted > 0 {
+            status.push(Span::raw(format!(" | {} device(s) forgotten to stay under --max-devices", devices.evicted)));
+        }
+        if radio.watchdog.bounces > 0 {
+            status.push(Span::raw(format!(" | Interface restarted {} time(s)", radio.watchdog.bounces)));
+        }
+        if let Some(error) = &radio.hop_error {
+            status.push(Span::styled(format!(" | Hop failed: {}", error), Style::reset().fg(Color::Red)));
+        }
+        if let Some(error) = &radio.watchdog.bounce_error {
+            status.push(Span::styled(format!(" | Restart failed: {}", error), Style::reset().fg(Color::Red)));
+        }
+        match (&searching, &search) {
+            // The prompt takes the place of everything else while typing
+            (Some(text), _) => status = vec![
+                Span::styled("Search devices, networks and manufacturers: ", Style::reset().add_modifier(Modifier::BOLD)),
+                Span::raw(text.clone()),
+                Span::styled(" ", Style::reset().add_modifier(Modifier::REVERSED))
+            ],
+            (None, Some(search)) if search.found.is_empty() => status.push(Span::styled(format!(" | Nothing matches {:?}", search.text), Style::reset().fg(Color::Yellow))),
+            (None, Some(search)) => status.push(Span::raw(format!(" | Match {} of {} for {:?}", search.position + 1, search.found.len(), search.text))),
+            (None, None) => ()
+        }
+
+        // Redrawn no more often than asked however fast frames come in, and only when something has changed,
+        // except for once a second so the times and ages shown keep up. While frozen only keys redraw
+        let changed = redraw || (!frozen && (devices.changed() || channel_label != drawn_channel));
+        if (changed && drawn.elapsed() >= redraw_every) || (!frozen && drawn.elapsed() >= IDLE_REDRAW) {
+            let rendering = Instant::now();
+            expect!(
+                ui =>
+                    ui.draw(|frame| {
+                        let areas = Layout::default()
+                            .direction(Direction::Vertical)
+                            .margin(0)
+                            .constraints([Constraint::Length(2), Constraint::Min(0), Constraint::Length(1)])
+                            .split(frame.size());
+                        frame.render_widget(
+                            Tabs::new(tabs.titles_for(frame.size()))
+                                .block(Block::default().borders(Borders::BOTTOM))
+                                .select(tabs.index)
+                                .style(Style::reset())
+                                .highlight_style(Style::reset().add_modifier(Modifier::BOLD | Modifier::REVERSED)),
+                            areas[0]
+                        );
+                        let label_width = (channel_label.len() as u16).min(areas[0].width);
+                        frame.render_widget(
+                            Paragraph::new(Span::styled(channel_label.as_str(), Style::reset().add_modifier(Modifier::BOLD))),
+                            Rect::new(areas[0].right() - label_width, areas[0].y, label_width, 1)
+                        );
+                        pages[tabs.index].render(frame, areas[1], &mut devices);
+                        if help {
+                            let mut global = keymap.help();
+                            global.push(("F1-F12".to_string(), "Go to a tab by its position"));
+                            let page = pages[tabs.index].keys().into_iter().map(|(key, description)| (key.to_string(), description)).collect();
+                            frame.render_widget(ui::Help(vec![("Everywhere", global), (pages[tabs.index].name(), page)]), areas[1]);
                         }
-                        ProbeRequest {
-                            source,
-                            destination,
-                            ..
-                        } => {
-                            devices.get_or_default(source, &oui_db)
-                                .sent()
-                                .knows(destination);
+                        frame.render_widget(Paragraph::new(Spans::from(status)), areas[2]);
+                        frame.render_widget(ui::Palette(&config.colours), frame.size())
+                    }),
+                    "Unable to draw to stdout"
+            );
+            stats.borrow_mut().time(stats::Stage::Render, rendering.elapsed());
+            devices.drawn();
+            redraw = false;
+            drawn = Instant::now();
+            drawn_channel = channel_label;
+        }
+
+        if let Some(prober) = &mut prober {
+            for probe in prober.due(&devices, &sensor_names[0], radio.channel) {
+                match radio.source.inject(&probe) {
+                    Ok(()) => {
+                        prober.sent += 1;
+                        prober.error = None
+                    }
+                    Err(error) => prober.error = Some(error.to_string())
+                }
+            }
+        }
+        let capturing = Instant::now();
+        // Each with the channel its sensor was on and where it was, as a replay recalls them or as they are now
+        let mut arrived = vec![];
+        let fix = gps.as_ref().and_then(gps::Gps::fix);
+        if let Some(replay) = &mut replay {
+            for record in expect!(ui => replay.due(paused), "Unable to read the session being replayed") {
+                radio.watchdog.frame();
+                if record.sensor == 0 && record.channel.is_some() {
+                    radio.channel = record.channel;
+                }
+                arrived.push((record.sensor, record.frame, record.channel, record.fix));
+            }
+        }
+        // Everything waiting is taken at once, up to a limit so keys are still seen during a flood
+        while arrived.len() < MAX_BATCH {
+            match expect!(ui => radio.source.next(), "Unable to get next packet") {
+                Some(captured) => {
+                    radio.watchdog.frame();
+                    arrived.push((0, captured, radio.channel, fix));
+                }
+                None => break
+            }
+        }
+        let mut failed = None;
+        for (position, (sensor, extra)) in extra_sources.iter_mut().enumerate() {
+            for _ in 0..MAX_BATCH {
+                match extra.next() {
+                    // Only the first interface is tuned and hopped from here
+                    Ok(Some(captured)) => arrived.push((*sensor, captured, None, fix)),
+                    Ok(None) => break,
+                    Err(error) => {
+                        failed = Some((position, error));
+                        break
+                    }
+                }
+            }
+        }
+        // Losing one of several interfaces leaves the rest worth listening to
+        if let Some((position, error)) = failed {
+            let (sensor, _) = extra_sources.remove(position);
+            ui.warning(&format!("Stopped listening on {}", sensor_names[sensor]), &error)
+        }
+        stats.borrow_mut().time(stats::Stage::Capture, capturing.elapsed());
+        // Every source is drained rather than waited on, so a quiet channel would otherwise spin
+        if arrived.is_empty() {
+            thread::sleep(IDLE_WAIT);
+        }
+        redraw |= !arrived.is_empty() && !frozen;
+        for (sensor, captured, channel, fix) in arrived {
+            if let Err(error) = captures.frame(sensor, &captured, || outputs::annotation(&captured.data, channel, fix, &devices, &anonymizer)) {
+                ui.warning("Stopped saving frames", &error)
+            }
+            if paused && (!buffer_while_paused || held.len() >= MAX_HELD) {
+                dropped += 1
+            } else {
+                held.push_back((capture_started.elapsed(), sensor, captured, channel, fix))
+            }
+        }
+        // Everything held while paused is caught up on at once
+        let ready: Vec<_> = if paused { vec![] } else { held.drain(..).collect() };
+        for (time, sensor, captured, channel, fix) in ready {
+            captures.record(time, sensor, channel, fix, &captured);
+            let primary = sensor == 0;
+            let sensor = &sensor_names[sensor];
+            // A frame without a readable radiotap header is skipped rather than ending the capture
+            let parsing = Instant::now();
+            let parsed = Radiotap::parse(&captured.data);
+            stats.borrow_mut().time(stats::Stage::Radiotap, parsing.elapsed());
+            let (radiotap, data) = match parsed {
+                Ok(parsed) => parsed,
+                Err(_) => {
+                    stats.borrow_mut().malformed();
+                    continue
+                }
+            };
+            let signal = radiotap.antenna_signal.map(|signal| signal.value);
+            let parsing = Instant::now();
+            let frame = wifi::Frame::parse(data);
+            let summary = wifi::Summary::parse(data);
+            let transmitter = summary.as_ref().ok().and_then(|summary| summary.transmitter);
+            let subtype = summary.as_ref().ok().map(wifi::Summary::subtype_name);
+            stats.borrow_mut().time(stats::Stage::Parse, parsing.elapsed());
+            // Probe requests sent with --active are heard back, but aren't a device in the area
+            if transmitter.is_some() && transmitter == prober.as_ref().map(|prober| prober.address) {
+                continue
+            }
+            // Handshakes and hostnames are rare and worth keeping, so only data frames giving away neither are sampled
+            if let (Some(seen), Ok(wifi::Frame::Data { eapol: None, announcements, .. })) = (&mut sampling, &frame) {
+                *seen += 1;
+                if *seen % sample_every != 0 && announcements.is_empty() {
+                    if let Ok(summary) = &summary {
+                        let mut stats = stats.borrow_mut();
+                        stats.frame(time, summary);
+                        stats.sampled_out += 1;
+                    }
+                    continue
+                }
+            }
+            let updating = Instant::now();
+            let mut ingest = true;
+            match (&summary, &frame) {
+                (Ok(summary), Ok(_)) => stats.borrow_mut().frame(time, summary),
+                (Ok(summary), Err(error)) => {
+                    let mut stats = stats.borrow_mut();
+                    stats.frame(time, summary);
+                    stats.unreadable(error);
+                }
+                (Err(_), _) => stats.borrow_mut().malformed()
+            }
+            let noise = radiotap.antenna_noise.map(|noise| noise.value);
+            let snr = stats::snr(&radiotap);
+            stats.borrow_mut().signal_quality(radiotap.channel.map(|channel| channel.freq as u32), snr, noise);
+            // Timed by the radio where it says, as frames reach the capture in bursts
+            let micros = radiotap.tsft.map_or(captured.header.ts.tv_sec as u64 * 1_000_000 + captured.header.ts.tv_usec as u64, |tsft| tsft.value);
+            let ack = summary.as_ref().ok().and_then(|summary| ack_timing.frame(sensor, micros, summary));
+            // Counted against the access point even though the rest of the beacon can't be read
+            let malformed_beacon = match (&frame, &summary) {
+                (Err(_), Ok(summary)) if summary.is_beacon() => summary.transmitter,
+                _ => None
+            };
+            if let Ok(summary) = summary {
+                let entry = page::LogEntry {
+                    time,
+                    sensor: sensor.clone(),
+                    signal,
+                    summary,
+                    ssid: match &frame {
+                        Ok(wifi::Frame::Beacon { ssid, .. })
+                            | Ok(wifi::Frame::ProbeRequest { ssid, .. })
+                            | Ok(wifi::Frame::ProbeResponse { ssid, .. })
+                            | Ok(wifi::Frame::AssociationRequest { ssid, .. }) => Some(ssid.clone()),
+                        _ => None
+                    },
+                    // Only the first interface is tuned and hopped from here
+                    channel: radiotap.channel
+                        .and_then(|channel| nl80211::frequency_to_channel(channel.freq as u32))
+                        .or(channel),
+                    airtime: stats::airtime(data.len(), radiotap.rate.map(|rate| rate.value))
+                };
+                for page in pages.iter_mut() {
+                    page.captured(&entry)
+                }
+                ingest = ingest_filter.as_ref().map_or(true, |filter| filter.matches(&entry));
+            }
+            if let (Some(transmitter), true) = (malformed_beacon, ingest) {
+                devices.get_or_default(transmitter, &oui_db).beacons.malformed += 1;
+            }
+            if ingest {
+                // What is kept of the frame itself, rather than of the devices that sent it
+                if let Ok(parsed) = &frame {
+                    captures.handshake(parsed, &captured);
+                }
+                let recorded = match &frame {
+                    Ok(wifi::Frame::Beacon { source, .. }) | Ok(wifi::Frame::ProbeRequest { source, .. }) => Some(*source),
+                    _ => None
+                };
+                let heard = Heard {
+                    sensor,
+                    time,
+                    signal,
+                    frequency: radiotap.channel.map(|channel| channel.freq as u32),
+                    noise,
+                    snr,
+                    quality: radiotap.lock_quality.map(|quality| quality.value)
+                };
+                let switch = frame.as_ref().ok().and_then(wifi::Frame::channel_switch);
+                devices.ingest(&oui_db, heard, ack, frame.ok());
+                if let (Some(subtype), Some(device)) = (subtype, transmitter.and_then(|transmitter| devices.get_mut(&transmitter))) {
+                    *device.subtypes.entry(subtype).or_default() += 1;
+                }
+                if let (Some(fix), Some(device)) = (fix, transmitter.and_then(|transmitter| devices.get_mut(&transmitter))) {
+                    device.geo.heard(fix, signal, time);
+                }
+                // Beacons repeat the announcement while counting down, so each move is only acted on once
+                if let Some((bssid, channel)) = switch {
+                    let channel = channel as u32;
+                    let from = heard.frequency.and_then(nl80211::frequency_to_channel);
+                    let device = devices.get_or_default(bssid, &oui_db);
+                    if device.channel_switch.map(|(_, to)| to) != Some(channel) {
+                        device.channel_switch = Some((from, channel));
+                        if let Some(failure) = reports.channel_switch(bssid, from, channel, time) {
+                            ui.warning(&failure.message, &failure.reason)
                         }
-                        Ack {
-                            receiver
-                        } => {
-                            devices.get_or_default(receiver, &oui_db);
+                        // Following the target, or the access point it is associated with, when locked onto its channel
+                        let followed = target.borrow().map_or(false, |target| {
+                            target == bssid || devices.get(&target).map_or(false, |device| device.associated == Some(bssid))
+                        });
+                        if followed && primary {
+                            if let Some(result) = radio.follow(bssid, channel) {
+                                export_result = Some(result);
+                            }
                         }
-                        _ => ()
                     }
                 }
+                // Missing when the transmitter was turned away as too weak
+                if let Some((source, device)) = recorded.and_then(|source| Some((source, devices.get(&source)?))) {
+                    reports.vendor(source, device);
+                }
             }
+            stats.borrow_mut().time(stats::Stage::Update, updating.elapsed());
         }
     }
-}
 
-/// A device tracked by blockade
-/// Tracks metadata relating to the device
-#[derive(Debug)]
-pub struct KnownDevice {
-    manufacturer: Option<OuiEntry>,
-    /// The SSID of the beacon, or None if not a beacon
-    beacon: Option<String>,
-    /// False if this device is known only by reference from another device, ie. has not sent any data
-    sent: bool,
-    /// The devices that this one has referenced
-    knows: HashSet<MacAddress>
-}
-impl KnownDevice {
-    fn new(address: MacAddress, oui_db: &OuiDatabase) -> Self {
-        Self {
-            manufacturer: oui_db.query_by_mac(&address).unwrap(/* Library should never be able to return an error */),
-            beacon: None,
-            sent: false,
-            knows: HashSet::new()
+    if let Some(file) = &mut ui_state_file {
+        for page in pages.iter() {
+            page.save(&mut ui_state)
+        }
+        ui_state.set("tab", tabs.index);
+        if let Err(error) = ui_state.save(file) {
+            ui.warning("Unable to save the layout for next time", &error)
         }
     }
-    fn sent(&mut self) -> &mut Self {
-        self.sent = true;
-        self
-    }
-    fn knows(&mut self, address: MacAddress) -> &mut Self {
-        self.knows.insert(address);
-        self
+    if let Some(path) = config_path.as_deref().filter(|_| *columns.borrow() != initial_columns) {
+        let names = columns.borrow().iter().map(|column| config::Value::String(column.name().to_string())).collect();
+        if let Err(error) = config::write(path, "devices", "columns", &config::Value::Array(names)) {
+            ui.warning("Unable to save the device columns to the config file", &error)
+        }
     }
-    fn beacon(&mut self, ssid: String) -> &mut Self {
-        self.beacon = Some(ssid);
-        self
+    expect!(ui => reports.finish(&devices, &alerts.borrow().alerts));
+    expect!(ui => captures.finish());
+    for monitor in monitors.iter_mut() {
+        if let Err(error) = monitor.restore() {
+            ui.warning(&format!("Unable to take {} out of monitor mode", monitor.interface), &error)
+        }
     }
 }
 
-#[derive(Debug, Default)]
-pub struct DeviceList(HashMap<MacAddress, KnownDevice>);
-impl DeviceList {
-    fn get_or_default(&mut self, address: MacAddress, oui_db: &OuiDatabase) -> &mut KnownDevice {
-        if self.contains_key(&address) {
-            self.get_mut(&address).unwrap()
-        } else {
-            self.insert(address, KnownDevice::new(address, oui_db));
-            self.get_mut(&address).unwrap()
-        }
+/// Set by SIGINT or SIGTERM, so a headless capture still saves its exports and state on the way out
+static STOPPING: AtomicBool = AtomicBool::new(false);
+extern "C" fn stop(_: libc::c_int) {
+    STOPPING.store(true, Ordering::Relaxed)
+}
+
+/// The most frames held while paused before any more are dropped
+const MAX_HELD: usize = 100_000;
+/// Most frames taken from each interface between looking for keys and redrawing
+const MAX_BATCH: usize = 1000;
+/// The interface is redrawn at least this often, even with nothing new
+const IDLE_REDRAW: Duration = Duration::from_secs(1);
+/// How long to wait before polling again when no frames have arrived
+const IDLE_WAIT: Duration = Duration::from_millis(2);
+
+/// List every device with what it is capable of and let one be picked, asking again about any unlikely to capture
+/// None if the user backed out with escape
+fn pick_interface(ui: &mut ui::Ui, monitor: bool) -> Option<String> {
+    let devices = expect!(ui => Device::list(), "Unable to find devices");
+    let mut nl80211 = nl80211::Nl80211::connect().ok();
+    let capabilities: Vec<_> = devices.iter()
+        .map(|device| interfaces::Capability::probe(nl80211.as_mut(), &device.name))
+        .collect();
+    let width = devices.iter().map(|device| device.name.len()).max().unwrap_or(0);
+    let items: Vec<_> = devices.iter().zip(capabilities.iter())
+        .map(|(device, capability)| {
+            let style = if capability.problem(monitor).is_some() { Style::default().add_modifier(Modifier::DIM) } else { Style::default() };
+            ListItem::new(Spans::from(vec![
+                Span::styled(format!("{:width$}  ", device.name, width = width), style.add_modifier(Modifier::BOLD)),
+                Span::styled(capability.to_string(), style)
+            ]))
+        })
+        .collect();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("Select a WiFi Device"))
+        .highlight_style(Style::default().bg(Color::Reset).add_modifier(Modifier::REVERSED))
+        .highlight_symbol("> ");
+    let mut list_state = ui::ListState::with_item_count(devices.len());
+
+    fn draw(ui: &mut ui::Ui, list: &List, list_state: &mut ui::ListState) {
+        expect!(
+            ui =>
+                ui.draw(|f| f.render_stateful_widget(list.clone(), f.size(), list_state)), 
+                "Unable to create list widget"
+        )
     }
-    pub fn bar_data(&self) -> Vec<(&str, u64)> {
-        let mut manufacturers = HashMap::new();
-        for device in self.values() {
-            if let Some(OuiEntry { name_short, ..}) = &device.manufacturer {
-                if let Some(count) = manufacturers.get_mut(name_short.as_str()) {
-                    *count += 1
-                } else {
-                    manufacturers.insert(name_short.as_str(), 1u64);
+    draw(ui, &list, &mut list_state);
+    loop {
+        let key = ui.input.stdin.recv().ok()?;
+        match key {
+            Key::Esc => return None,
+            Key::Up | Key::Char('w') => list_state.up(),
+            Key::Down | Key::Char('s') => list_state.down(),
+            Key::PageUp => list_state.top(),
+            Key::PageDown => list_state.bottom(),
+            Key::Char('\n') => if let Some(index) = list_state.selected() {
+                let name = &devices[index].name;
+                match capabilities[index].problem(monitor) {
+                    Some(problem) if !ui.confirm(&format!("{} is unlikely to capture", name), &format!("Because {}. Use it anyway?", problem)) => (),
+                    _ => return Some(name.clone())
                 }
-            }
+            },
+            _ => continue
         }
-        let mut values: Vec<(&str, u64)> = manufacturers.iter().map(|(&name, &count)| (name, count)).collect();
-        values.sort_by(|(nl, l), (nr, r)| l.cmp(r).then_with(|| nl.cmp(nr)));
-        values.reverse();
-        values
+        draw(ui, &list, &mut list_state);
     }
 }
-impl Deref for DeviceList {
-    type Target = HashMap<MacAddress, KnownDevice>;
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
-}
-impl DerefMut for DeviceList {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+
+/// Go to the page the current match of a search is on, and pick it out there
+fn show_match(search: Option<&page::Search>, pages: &mut [&mut dyn page::Page], tabs: &mut ui::TabState) {
+    if let Some((index, found)) = search.and_then(page::Search::current) {
+        tabs.select(*index);
+        pages[*index].select(found);
     }
 }
@@ -0,0 +1,65 @@
+use std::{env, fmt, fs, io, path::PathBuf};
+use oui::OuiDatabase;
+
+/// Wireshark's compilation of the IEEE registries, the same source the built in database is made from
+const URL: &str = "https://gitlab.com/wireshark/wireshark/raw/master/manuf";
+
+/// Where a downloaded OUI database is kept, following the XDG base directory specification
+pub fn cache_path() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_DATA_HOME").filter(|path| !path.is_empty()) {
+        Some(path) => PathBuf::from(path),
+        None => PathBuf::from(env::var_os("HOME")?).join(".local/share")
+    };
+    Some(base.join("blockade-recon/manuf"))
+}
+
+/// The OUI database to use when none is specified, being a downloaded one if there is one or else the built in one
+pub fn load_default() -> Result<OuiDatabase, Error> {
+    match cache_path().map(fs::read) {
+        Some(Ok(export)) => OuiDatabase::new_from_export(&export).map_err(|error| Error::Parse(error.to_string())),
+        Some(Err(error)) if error.kind() != io::ErrorKind::NotFound => Err(Error::Read(error)),
+        _ => OuiDatabase::new_from_export(include_bytes!("../manuf")).map_err(|error| Error::Parse(error.to_string()))
+    }
+}
+
+/// Download the latest OUI database and keep it for later runs, returning where it was saved
+pub fn update() -> Result<PathBuf, Error> {
+    let path = cache_path().ok_or(Error::NoHome)?;
+    let text = reqwest::blocking::get(URL)
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.text())
+        .map_err(Error::Download)?;
+    let export = OuiDatabase::new_from_str(&text)
+        .and_then(|db| db.export())
+        .map_err(|error| Error::Parse(error.to_string()))?;
+    if let Some(folder) = path.parent() {
+        fs::create_dir_all(folder).map_err(Error::Write)?;
+    }
+    // Written alongside then moved into place, so a failed write doesn't leave a broken database behind
+    let partial = path.with_extension("partial");
+    fs::write(&partial, export)
+        .and_then(|_| fs::rename(&partial, &path))
+        .map_err(Error::Write)?;
+    Ok(path)
+}
+
+#[derive(Debug)]
+pub enum Error {
+    NoHome,
+    Read(io::Error),
+    Download(reqwest::Error),
+    /// The database couldn't be read, as described by the OUI library
+    Parse(String),
+    Write(io::Error)
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NoHome => write!(f, "no home directory to keep the OUI database in"),
+            Self::Read(error) => write!(f, "unable to read the downloaded OUI database: {}", error),
+            Self::Download(error) => write!(f, "unable to download the OUI database: {}", error),
+            Self::Parse(error) => write!(f, "invalid OUI database: {}", error),
+            Self::Write(error) => write!(f, "unable to save the OUI database: {}", error)
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream},
+    time::Duration
+};
+
+use crate::{DeviceList, Role, alerts::Alerts, stats::Stats, wifi::Kind};
+
+/// The most a request line and headers may take up
+const MAX_REQUEST: usize = 8192;
+
+/// Answers Prometheus scrapes of `GET /metrics` without blocking the capture
+pub struct Server {
+    /// Bound up front, as the sandbox forbids opening network sockets but not accepting on one
+    listener: TcpListener
+}
+impl Server {
+    pub fn bind(address: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener
+        })
+    }
+    /// Answer every scrape waiting, rendering the metrics once if any asked for them
+    pub fn poll(&mut self, render: impl FnOnce() -> String) {
+        let mut render = Some(render);
+        let mut body = None;
+        while let Ok((mut stream, _)) = self.listener.accept() {
+            let scrape = read_path(&mut stream).map(|path| path == "/metrics" || path.starts_with("/metrics?"));
+            let _ = match scrape {
+                Ok(true) => {
+                    let body = body.get_or_insert_with(|| render.take().map_or_else(String::new, |render| render()));
+                    respond(&mut stream, "200 OK", body)
+                }
+                Ok(false) => respond(&mut stream, "404 Not Found", "Only /metrics is served\n"),
+                Err(_) => respond(&mut stream, "400 Bad Request", "")
+            };
+        }
+    }
+}
+
+/// The path of a GET request
+fn read_path(stream: &mut TcpStream) -> io::Result<String> {
+    // A slow client shouldn't hold up the capture for long
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_millis(500)))?;
+    stream.set_write_timeout(Some(Duration::from_millis(500)))?;
+    let mut request = vec![];
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") && request.len() <= MAX_REQUEST {
+        match stream.read(&mut buffer)? {
+            0 => break,
+            read => request.extend_from_slice(&buffer[..read])
+        }
+    }
+    let request = String::from_utf8_lossy(&request);
+    let mut parts = request.lines().next().unwrap_or_default().split(' ');
+    match (parts.next(), parts.next()) {
+        (Some("GET"), Some(path)) => Ok(path.to_string()),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, "not a GET request"))
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: &str, body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, body.len(), body
+    )?;
+    stream.flush()
+}
+
+/// Every metric in Prometheus' text format
+pub fn render(devices: &DeviceList, stats: &Stats, alerts: &Alerts) -> String {
+    let mut output = String::new();
+
+    let mut roles: BTreeMap<&str, u64> = BTreeMap::new();
+    let mut manufacturers: BTreeMap<&str, u64> = BTreeMap::new();
+    for device in devices.values() {
+        *roles.entry(match device.role {
+            Some(Role::AccessPoint) => "access_point",
+            Some(Role::Station) => "station",
+            None => "unknown"
+        }).or_default() += 1;
+        *manufacturers.entry(device.manufacturer.as_ref().map_or("unknown", |entry| entry.name_short.as_str())).or_default() += 1;
+    }
+    family(&mut output, "blockade_devices", "gauge", "Devices seen since the capture started or was last reset, by role");
+    for (role, count) in roles {
+        sample(&mut output, "blockade_devices", &[("role", role)], count);
+    }
+    family(&mut output, "blockade_manufacturer_devices", "gauge", "Devices seen, by the manufacturer their address was assigned to");
+    for (manufacturer, count) in manufacturers {
+        sample(&mut output, "blockade_manufacturer_devices", &[("manufacturer", manufacturer)], count);
+    }
+
+    family(&mut output, "blockade_frames_total", "counter", "Frames captured, by type and subtype");
+    for ((kind, _), (subtype, count)) in stats.subtypes.iter() {
+        let kind = match kind {
+            Kind::Management => "mgmt",
+            Kind::Control => "ctrl",
+            Kind::Data => "data",
+            Kind::Extension => "ext"
+        };
+        sample(&mut output, "blockade_frames_total", &[("type", kind), ("subtype", subtype)], *count);
+    }
+    family(&mut output, "blockade_bytes_total", "counter", "Bytes captured");
+    sample(&mut output, "blockade_bytes_total", &[], stats.bytes);
+    family(&mut output, "blockade_malformed_frames_total", "counter", "Frames too short or broken to read the headers of");
+    sample(&mut output, "blockade_malformed_frames_total", &[], stats.malformed);
+    family(&mut output, "blockade_unreadable_frames_total", "counter", "Frames whose body couldn't be parsed, by why");
+    for (reason, count) in stats.unreadable.iter() {
+        sample(&mut output, "blockade_unreadable_frames_total", &[("reason", reason)], *count);
+    }
+
+    family(&mut output, "blockade_pcap_received_total", "counter", "Frames libpcap received, by sensor");
+    family(&mut output, "blockade_pcap_dropped_total", "counter", "Frames dropped because the capture couldn't keep up, by sensor");
+    family(&mut output, "blockade_pcap_interface_dropped_total", "counter", "Frames dropped by the interface or its driver, by sensor");
+    for (sensor, drops) in stats.drops.iter() {
+        sample(&mut output, "blockade_pcap_received_total", &[("sensor", sensor)], drops.received as u64);
+        sample(&mut output, "blockade_pcap_dropped_total", &[("sensor", sensor)], drops.dropped as u64);
+        sample(&mut output, "blockade_pcap_interface_dropped_total", &[("sensor", sensor)], drops.interface as u64);
+    }
+
+    let mut raised: BTreeMap<(&str, String), u64> = BTreeMap::new();
+    for alert in alerts.alerts.iter() {
+        *raised.entry((alert.kind.name(), alert.severity.to_string())).or_default() += 1;
+    }
+    family(&mut output, "blockade_alerts", "gauge", "Alerts listed, by kind and severity");
+    for ((kind, severity), count) in raised {
+        sample(&mut output, "blockade_alerts", &[("kind", kind), ("severity", &severity)], count);
+    }
+    output
+}
+
+fn family(output: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(output, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+}
+
+fn sample(output: &mut String, name: &str, labels: &[(&str, &str)], value: u64) {
+    let labels: Vec<_> = labels.iter().map(|(label, value)| format!("{}=\"{}\"", label, escape(value))).collect();
+    let _ = if labels.is_empty() {
+        writeln!(output, "{} {}", name, value)
+    } else {
+        writeln!(output, "{}{{{}}} {}", name, labels.join(","), value)
+    };
+}
+
+/// Escape a label value, in which backslashes, quotes and newlines are special
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
@@ -0,0 +1,161 @@
+use std::{
+    fmt, io,
+    process::Command,
+    sync::mpsc::{self, Receiver, Sender},
+    thread
+};
+
+use crate::{netdev, nl80211::{self, InterfaceType, Nl80211}};
+
+/// How an interface's mode was changed, so it is put back the same way
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Method {
+    Nl80211,
+    /// Running `iw`, as airmon-ng does, for when nl80211 requests are refused but the tool still works
+    Iw
+}
+
+/// The mode an interface was in, and how it was changed
+struct Original {
+    iftype: InterfaceType,
+    was_up: bool,
+    method: Method
+}
+impl Original {
+    fn restore(&self, interface: &str) -> Result<(), Error> {
+        set_type(interface, self.iftype, self.method)?;
+        if !self.was_up {
+            netdev::set_up(interface, false)?;
+        }
+        Ok(())
+    }
+}
+
+/// An interface put into monitor mode by --auto-monitor, put back as it was when restored or dropped
+pub struct Monitor {
+    pub interface: String,
+    /// How to put the interface back, until it is handed to the restoring thread or put back
+    original: Option<Original>,
+    /// Asks the thread started before the sandbox to put the interface back, and hears how it went
+    restorer: Option<(Sender<()>, Receiver<Result<(), Error>>)>
+}
+impl Monitor {
+    /// Put an interface into monitor mode, or None if it already is
+    pub fn enter(interface: &str) -> Result<Option<Self>, Error> {
+        let original = Nl80211::connect()?.interface(interface)?.iftype;
+        if original == InterfaceType::Monitor {
+            return Ok(None)
+        }
+        let was_up = netdev::is_up(interface)?;
+        let method = match set_type(interface, InterfaceType::Monitor, Method::Nl80211) {
+            Ok(()) => Method::Nl80211,
+            Err(error) => set_type(interface, InterfaceType::Monitor, Method::Iw)
+                .map(|_| Method::Iw)
+                .map_err(|_| error)?
+        };
+
+        Ok(Some(Self {
+            interface: interface.to_string(),
+            original: Some(Original { iftype: original, was_up, method }),
+            restorer: None
+        }))
+    }
+    /// Keep a thread back to put the interface back, as the sandbox keeps the main thread from reconfiguring interfaces
+    /// Must be started after privileges are dropped, which takes the capabilities of every thread but the one dropping them
+    /// when switching back to the user that ran sudo, and before the sandbox, which the thread would otherwise be held to
+    pub fn standby(&mut self) {
+        let original = match self.original.take() {
+            Some(original) => original,
+            None => return
+        };
+        let (restore, requested) = mpsc::channel();
+        let (reply, restored) = mpsc::channel();
+        let name = self.interface.clone();
+        thread::spawn(move || {
+            // Also restore if the monitor was dropped without asking, as when unwinding from an error
+            let _ = requested.recv();
+            let _ = reply.send(original.restore(&name));
+        });
+        self.restorer = Some((restore, restored));
+    }
+    /// Put the interface back in the mode it was in, and down again if it was down
+    pub fn restore(&mut self) -> Result<(), Error> {
+        if let Some(original) = self.original.take() {
+            return original.restore(&self.interface)
+        }
+        let (restore, restored) = match self.restorer.take() {
+            Some(restorer) => restorer,
+            None => return Ok(())
+        };
+        let _ = restore.send(());
+        restored.recv().unwrap_or(Err(Error::Gone))
+    }
+}
+impl Drop for Monitor {
+    fn drop(&mut self) {
+        let _ = self.restore();
+    }
+}
+
+/// Take an interface down, change its mode and bring it back up
+fn set_type(interface: &str, iftype: InterfaceType, method: Method) -> Result<(), Error> {
+    netdev::set_up(interface, false)?;
+    let result = match method {
+        Method::Nl80211 => Nl80211::connect().map_err(nl80211::Error::Io).and_then(|mut nl80211| nl80211.set_type(interface, iftype)).map_err(Error::Nl80211),
+        Method::Iw => iw(interface, iftype)
+    };
+    // Brought back up even if the change failed, so a failure doesn't leave the interface down
+    netdev::set_up(interface, true)?;
+    result
+}
+
+fn iw(interface: &str, iftype: InterfaceType) -> Result<(), Error> {
+    let name = match iftype {
+        InterfaceType::Station => "managed",
+        InterfaceType::AdHoc => "ibss",
+        InterfaceType::Monitor => "monitor",
+        InterfaceType::MeshPoint => "mesh",
+        InterfaceType::Wds => "wds",
+        InterfaceType::AccessPoint => "__ap",
+        other => return Err(Error::Unsupported(other))
+    };
+    let output = Command::new("iw").args(&["dev", interface, "set", "type", name]).output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(Error::Iw(String::from_utf8_lossy(&output.stderr).trim().to_string()))
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Nl80211(nl80211::Error),
+    /// `iw` ran but refused, with what it said
+    Iw(String),
+    /// A mode `iw` has no name for
+    Unsupported(InterfaceType),
+    /// The thread kept to restore the interface went away
+    Gone
+}
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+impl From<nl80211::Error> for Error {
+    fn from(error: nl80211::Error) -> Self {
+        Self::Nl80211(error)
+    }
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{}", error),
+            Self::Nl80211(error) => write!(f, "{}", error),
+            Self::Iw(error) => write!(f, "iw failed: {}", error),
+            Self::Unsupported(iftype) => write!(f, "iw can't set an interface to {} mode", iftype),
+            Self::Gone => write!(f, "the interface could no longer be restored")
+        }
+    }
+}
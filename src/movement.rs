@@ -0,0 +1,92 @@
+use std::{collections::VecDeque, fmt, time::Duration};
+use eui48::MacAddress;
+
+/// How far back a client's signal and roams are looked over to judge whether it is moving
+const WINDOW: Duration = Duration::from_secs(2 * 60);
+/// Seconds heard within the window before anything is judged
+const MIN_SAMPLES: usize = 10;
+/// A change in the average signal between the start and end of the window, in dB, taken as moving
+const TREND: f64 = 10.0;
+/// A spread of signal, as its standard deviation in dB, taken as moving, or as staying put when under the steady limit
+const SPREAD: f64 = 8.0;
+const STEADY: f64 = 4.0;
+/// Roams kept per client, beyond which the oldest are forgotten
+const MAX_ROAMS: usize = 20;
+
+/// Whether a client seems to be moving about or staying put
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Movement {
+    Stationary,
+    Moving
+}
+impl fmt::Display for Movement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Stationary => write!(f, "stationary"),
+            Self::Moving => write!(f, "moving")
+        }
+    }
+}
+
+/// A client moving from one access point to another
+#[derive(Debug, Clone, Copy)]
+pub struct Roam {
+    pub from: MacAddress,
+    pub to: MacAddress,
+    /// Since the capture started
+    pub time: Duration
+}
+
+/// The access points a client has roamed between and whether it seems to be moving, to tell passers-by from residents
+#[derive(Debug, Default)]
+pub struct Mobility {
+    /// The most recent roams, oldest first
+    pub roams: VecDeque<Roam>,
+    /// Every roam heard, including those since forgotten
+    pub roamed: u64,
+    /// None until the client has been heard for long enough to tell
+    pub movement: Option<Movement>,
+    /// Why the client was last judged to be moving or not
+    pub reason: String
+}
+impl Mobility {
+    pub fn roam(&mut self, from: MacAddress, to: MacAddress, time: Duration) {
+        if self.roams.len() >= MAX_ROAMS {
+            self.roams.pop_front();
+        }
+        self.roams.push_back(Roam { from, to, time });
+        self.roamed += 1;
+    }
+    /// Judge whether the client is moving from the strongest signal heard each second, oldest first
+    /// A client roaming, or whose signal drifts or swings widely, is moving, and one with a steady signal is staying put
+    pub fn assess(&mut self, signal_history: &VecDeque<(Duration, i8)>, now: Duration) {
+        if let Some(roam) = self.roams.back().filter(|roam| roam.time + WINDOW >= now) {
+            self.movement = Some(Movement::Moving);
+            self.reason = format!("roamed to {} {}s ago", roam.to.to_hex_string(), (now - roam.time).as_secs());
+            return
+        }
+        let recent: Vec<f64> = signal_history.iter()
+            .filter(|(time, _)| *time + WINDOW >= now)
+            .map(|&(_, signal)| signal as f64)
+            .collect();
+        if recent.len() < MIN_SAMPLES {
+            return
+        }
+        let mean = |signals: &[f64]| signals.iter().sum::<f64>() / signals.len() as f64;
+        let average = mean(&recent);
+        let spread = (recent.iter().map(|signal| (signal - average).powi(2)).sum::<f64>() / recent.len() as f64).sqrt();
+        let third = recent.len() / 3;
+        let trend = mean(&recent[recent.len() - third..]) - mean(&recent[..third]);
+        let (movement, reason) = if trend.abs() >= TREND {
+            (Movement::Moving, format!("signal {} by {:.0}dB over the last {} minutes", if trend > 0.0 { "rose" } else { "fell" }, trend.abs(), WINDOW.as_secs() / 60))
+        } else if spread >= SPREAD {
+            (Movement::Moving, format!("signal swinging by {:.0}dB", spread))
+        } else if spread < STEADY {
+            (Movement::Stationary, format!("signal steady within {:.0}dB", spread))
+        } else {
+            return
+        };
+        self.movement = Some(movement);
+        self.reason = reason;
+    }
+}
@@ -0,0 +1,70 @@
+use std::{ffi::CString, io, mem};
+
+/// The subset of `struct ifreq` used to get and set interface flags
+#[repr(C)]
+struct InterfaceRequest {
+    name: [libc::c_char; libc::IFNAMSIZ],
+    flags: libc::c_short,
+    _padding: [u8; 22]
+}
+impl InterfaceRequest {
+    fn new(interface: &str) -> io::Result<Self> {
+        let c_name = CString::new(interface).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "interface name contains a null byte"))?;
+        let bytes = c_name.as_bytes_with_nul();
+        if bytes.len() > libc::IFNAMSIZ {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "interface name is too long"))
+        }
+        let mut request: Self = unsafe { mem::zeroed() };
+        for (i, &byte) in bytes.iter().enumerate() {
+            request.name[i] = byte as libc::c_char;
+        }
+        Ok(request)
+    }
+}
+
+/// A socket used only as a handle for interface ioctls
+struct ControlSocket(libc::c_int);
+impl ControlSocket {
+    fn open() -> io::Result<Self> {
+        match unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) } {
+            fd if fd < 0 => Err(io::Error::last_os_error()),
+            fd => Ok(Self(fd))
+        }
+    }
+    fn flags(&self, interface: &str) -> io::Result<InterfaceRequest> {
+        let mut request = InterfaceRequest::new(interface)?;
+        if unsafe { libc::ioctl(self.0, libc::SIOCGIFFLAGS, &mut request as *mut InterfaceRequest) } < 0 {
+            return Err(io::Error::last_os_error())
+        }
+        Ok(request)
+    }
+    fn set_flags(&self, request: &mut InterfaceRequest) -> io::Result<()> {
+        if unsafe { libc::ioctl(self.0, libc::SIOCSIFFLAGS, request as *mut InterfaceRequest) } < 0 {
+            return Err(io::Error::last_os_error())
+        }
+        Ok(())
+    }
+}
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.0); }
+    }
+}
+
+/// Whether an interface is administratively up
+pub fn is_up(interface: &str) -> io::Result<bool> {
+    let socket = ControlSocket::open()?;
+    Ok(socket.flags(interface)?.flags & libc::IFF_UP as libc::c_short != 0)
+}
+
+/// Bring an interface administratively up or down
+pub fn set_up(interface: &str, up: bool) -> io::Result<()> {
+    let socket = ControlSocket::open()?;
+    let mut request = socket.flags(interface)?;
+    if up {
+        request.flags |= libc::IFF_UP as libc::c_short;
+    } else {
+        request.flags &= !(libc::IFF_UP as libc::c_short);
+    }
+    socket.set_flags(&mut request)
+}
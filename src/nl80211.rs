@@ -0,0 +1,558 @@
+use std::{ffi::CString, fmt, io, mem, os::unix::io::RawFd};
+
+const GENL_ID_CTRL: u16 = 0x10;
+const CTRL_CMD_GETFAMILY: u8 = 3;
+const CTRL_ATTR_FAMILY_ID: u16 = 1;
+const CTRL_ATTR_FAMILY_NAME: u16 = 2;
+
+const NL80211_CMD_GET_WIPHY: u8 = 1;
+const NL80211_CMD_SET_WIPHY: u8 = 2;
+const NL80211_CMD_GET_INTERFACE: u8 = 5;
+const NL80211_CMD_SET_INTERFACE: u8 = 6;
+
+const NL80211_ATTR_WIPHY: u16 = 1;
+const NL80211_ATTR_WIPHY_NAME: u16 = 2;
+const NL80211_ATTR_IFINDEX: u16 = 3;
+const NL80211_ATTR_IFNAME: u16 = 4;
+const NL80211_ATTR_IFTYPE: u16 = 5;
+const NL80211_ATTR_WIPHY_BANDS: u16 = 22;
+const NL80211_ATTR_SUPPORTED_IFTYPES: u16 = 32;
+const NL80211_ATTR_WIPHY_FREQ: u16 = 38;
+const NL80211_ATTR_WIPHY_CHANNEL_TYPE: u16 = 39;
+const NL80211_ATTR_SPLIT_WIPHY_DUMP: u16 = 174;
+
+const NL80211_BAND_ATTR_FREQS: u16 = 1;
+const NL80211_FREQUENCY_ATTR_FREQ: u16 = 1;
+const NL80211_FREQUENCY_ATTR_DISABLED: u16 = 2;
+const NL80211_FREQUENCY_ATTR_NO_IR: u16 = 3;
+const NL80211_FREQUENCY_ATTR_RADAR: u16 = 5;
+const NL80211_FREQUENCY_ATTR_MAX_TX_POWER: u16 = 6;
+
+const NL80211_IFTYPE_MONITOR: u16 = 6;
+const NL80211_CHAN_NO_HT: u32 = 0;
+
+const NLMSG_HDRLEN: usize = 16;
+const GENL_HDRLEN: usize = 4;
+const NLA_HDRLEN: usize = 4;
+const NLA_TYPE_MASK: u16 = 0x3fff;
+
+fn align(length: usize) -> usize {
+    (length + 3) & !3
+}
+
+/// Convert an 802.11 channel number to its centre frequency in MHz
+/// Channels 1 to 14 are taken as 2.4GHz and 32 to 177 as 5GHz
+pub fn channel_to_frequency(channel: u32) -> Option<u32> {
+    match channel {
+        1..=13 => Some(2407 + 5 * channel),
+        14 => Some(2484),
+        32..=177 => Some(5000 + 5 * channel),
+        _ => None
+    }
+}
+/// Convert a centre frequency in MHz to its 802.11 channel number
+pub fn frequency_to_channel(frequency: u32) -> Option<u32> {
+    match frequency {
+        2412..=2472 => Some((frequency - 2407) / 5),
+        2484 => Some(14),
+        5160..=5885 => Some((frequency - 5000) / 5),
+        5955..=7115 => Some((frequency - 5950) / 5),
+        _ => None
+    }
+}
+
+/// The band a centre frequency in MHz lies in
+pub fn frequency_to_band(frequency: u32) -> Option<BandKind> {
+    match frequency {
+        2400..=2500 => Some(BandKind::Ghz2),
+        5150..=5895 => Some(BandKind::Ghz5),
+        5925..=7125 => Some(BandKind::Ghz6),
+        57000..=71000 => Some(BandKind::Ghz60),
+        _ => None
+    }
+}
+
+/// Tune the named interface to a channel, checking the adapter supports it
+pub fn tune(interface: &str, channel: u32) -> Result<()> {
+    Nl80211::connect()?.set_channel(interface, channel)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterfaceType {
+    Unspecified,
+    AdHoc,
+    Station,
+    AccessPoint,
+    ApVlan,
+    Wds,
+    Monitor,
+    MeshPoint,
+    P2pClient,
+    P2pGo,
+    P2pDevice,
+    Ocb,
+    Nan,
+    Other(u32)
+}
+impl InterfaceType {
+    fn new(iftype: u32) -> Self {
+        match iftype {
+            0 => Self::Unspecified,
+            1 => Self::AdHoc,
+            2 => Self::Station,
+            3 => Self::AccessPoint,
+            4 => Self::ApVlan,
+            5 => Self::Wds,
+            6 => Self::Monitor,
+            7 => Self::MeshPoint,
+            8 => Self::P2pClient,
+            9 => Self::P2pGo,
+            10 => Self::P2pDevice,
+            11 => Self::Ocb,
+            12 => Self::Nan,
+            other => Self::Other(other)
+        }
+    }
+    fn code(self) -> u32 {
+        match self {
+            Self::Unspecified => 0,
+            Self::AdHoc => 1,
+            Self::Station => 2,
+            Self::AccessPoint => 3,
+            Self::ApVlan => 4,
+            Self::Wds => 5,
+            Self::Monitor => 6,
+            Self::MeshPoint => 7,
+            Self::P2pClient => 8,
+            Self::P2pGo => 9,
+            Self::P2pDevice => 10,
+            Self::Ocb => 11,
+            Self::Nan => 12,
+            Self::Other(iftype) => iftype
+        }
+    }
+}
+impl fmt::Display for InterfaceType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Unspecified => write!(f, "unspecified"),
+            Self::AdHoc => write!(f, "ad-hoc"),
+            Self::Station => write!(f, "managed"),
+            Self::AccessPoint => write!(f, "AP"),
+            Self::ApVlan => write!(f, "AP/VLAN"),
+            Self::Wds => write!(f, "WDS"),
+            Self::Monitor => write!(f, "monitor"),
+            Self::MeshPoint => write!(f, "mesh point"),
+            Self::P2pClient => write!(f, "P2P-client"),
+            Self::P2pGo => write!(f, "P2P-GO"),
+            Self::P2pDevice => write!(f, "P2P-device"),
+            Self::Ocb => write!(f, "outside context of a BSS"),
+            Self::Nan => write!(f, "NAN"),
+            Self::Other(iftype) => write!(f, "unknown ({})", iftype)
+        }
+    }
+}
+
+/// A network interface as reported by nl80211
+#[derive(Debug, Clone)]
+pub struct Interface {
+    pub index: u32,
+    pub name: String,
+    /// The index of the physical radio backing this interface
+    pub wiphy: u32,
+    pub iftype: InterfaceType,
+    /// The frequency the interface is currently tuned to in MHz, if known
+    pub frequency: Option<u32>
+}
+
+/// A frequency a radio is able to tune to
+#[derive(Debug, Clone)]
+pub struct Frequency {
+    /// Centre frequency in MHz
+    pub mhz: u32,
+    /// Disabled by the regulatory domain or driver
+    pub disabled: bool,
+    /// Transmission is not permitted, listening is
+    pub no_ir: bool,
+    /// Radar detection (DFS) is required
+    pub radar: bool,
+    /// Maximum transmission power in mBm
+    pub max_power: Option<u32>
+}
+impl Frequency {
+    pub fn channel(&self) -> Option<u32> {
+        frequency_to_channel(self.mhz)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BandKind {
+    Ghz2,
+    Ghz5,
+    Ghz60,
+    Ghz6,
+    Other(u16)
+}
+impl BandKind {
+    fn new(band: u16) -> Self {
+        match band {
+            0 => Self::Ghz2,
+            1 => Self::Ghz5,
+            2 => Self::Ghz60,
+            3 => Self::Ghz6,
+            other => Self::Other(other)
+        }
+    }
+}
+impl fmt::Display for BandKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Ghz2 => write!(f, "2.4GHz"),
+            Self::Ghz5 => write!(f, "5GHz"),
+            Self::Ghz60 => write!(f, "60GHz"),
+            Self::Ghz6 => write!(f, "6GHz"),
+            Self::Other(band) => write!(f, "band {}", band)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Band {
+    pub kind: BandKind,
+    pub frequencies: Vec<Frequency>
+}
+
+/// A physical radio and its capabilities
+#[derive(Debug, Clone, Default)]
+pub struct Wiphy {
+    pub index: u32,
+    pub name: String,
+    pub bands: Vec<Band>,
+    pub supports_monitor: bool
+}
+impl Wiphy {
+    pub fn frequency(&self, mhz: u32) -> Option<&Frequency> {
+        self.bands.iter().flat_map(|band| band.frequencies.iter()).find(|frequency| frequency.mhz == mhz)
+    }
+    /// All frequencies that are not disabled
+    pub fn usable_frequencies(&self) -> impl Iterator<Item = &Frequency> {
+        self.bands.iter().flat_map(|band| band.frequencies.iter()).filter(|frequency| !frequency.disabled)
+    }
+}
+
+/// A generic netlink socket bound to the nl80211 family
+pub struct Nl80211 {
+    fd: RawFd,
+    family: u16,
+    sequence: u32
+}
+impl Nl80211 {
+    pub fn connect() -> io::Result<Self> {
+        let fd = unsafe { libc::socket(libc::AF_NETLINK, libc::SOCK_RAW | libc::SOCK_CLOEXEC, libc::NETLINK_GENERIC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error())
+        }
+        let mut address: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        let bound = unsafe {
+            libc::bind(fd, &address as *const libc::sockaddr_nl as *const libc::sockaddr, mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t)
+        };
+        // Construct before checking so the socket is closed on error
+        let mut socket = Self {
+            fd,
+            family: GENL_ID_CTRL,
+            sequence: 0
+        };
+        if bound < 0 {
+            return Err(io::Error::last_os_error())
+        }
+
+        let mut attributes = Attributes::default();
+        attributes.string(CTRL_ATTR_FAMILY_NAME, "nl80211");
+        let replies = socket.request(GENL_ID_CTRL, CTRL_CMD_GETFAMILY, false, attributes)?;
+        socket.family = replies.iter()
+            .flat_map(|reply| parse_attributes(reply))
+            .find(|&(kind, _)| kind == CTRL_ATTR_FAMILY_ID)
+            .and_then(|(_, value)| read_u16(value))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "nl80211 is not available"))?;
+        Ok(socket)
+    }
+
+    /// Look up a wireless interface by name
+    pub fn interface(&mut self, name: &str) -> Result<Interface> {
+        let index = interface_index(name)?;
+        let mut attributes = Attributes::default();
+        attributes.u32(NL80211_ATTR_IFINDEX, index);
+        let replies = self.request(self.family, NL80211_CMD_GET_INTERFACE, false, attributes)?;
+        replies.first().map(|reply| parse_interface(reply)).ok_or_else(|| Error::NoSuchInterface(name.to_string()))
+    }
+    /// List every wireless interface known to the kernel
+    pub fn interfaces(&mut self) -> Result<Vec<Interface>> {
+        let replies = self.request(self.family, NL80211_CMD_GET_INTERFACE, true, Attributes::default())?;
+        let mut interfaces: Vec<_> = replies.iter().map(|reply| parse_interface(reply)).collect();
+        interfaces.sort_by(|l, r| l.name.cmp(&r.name));
+        Ok(interfaces)
+    }
+
+    /// Query the capabilities of a physical radio
+    pub fn wiphy(&mut self, index: u32) -> Result<Wiphy> {
+        let mut attributes = Attributes::default();
+        attributes.u32(NL80211_ATTR_WIPHY, index);
+        attributes.flag(NL80211_ATTR_SPLIT_WIPHY_DUMP);
+        let replies = self.request(self.family, NL80211_CMD_GET_WIPHY, true, attributes)?;
+        let mut wiphy = Wiphy {
+            index,
+            ..Default::default()
+        };
+        // A split dump spreads the radio over many messages, each continuing where the last left off
+        for reply in replies.iter() {
+            let attributes = parse_attributes(reply);
+            if attributes.iter().any(|&(kind, value)| kind == NL80211_ATTR_WIPHY && read_u32(value) != Some(index)) {
+                continue
+            }
+            for (kind, value) in attributes {
+                match kind {
+                    NL80211_ATTR_WIPHY_NAME => wiphy.name = read_string(value),
+                    NL80211_ATTR_SUPPORTED_IFTYPES => if parse_attributes(value).iter().any(|&(iftype, _)| iftype == NL80211_IFTYPE_MONITOR) {
+                        wiphy.supports_monitor = true
+                    },
+                    NL80211_ATTR_WIPHY_BANDS => for (band, value) in parse_attributes(value) {
+                        let kind = BandKind::new(band);
+                        let frequencies = parse_attributes(value)
+                            .into_iter()
+                            .filter(|&(kind, _)| kind == NL80211_BAND_ATTR_FREQS)
+                            .flat_map(|(_, value)| parse_attributes(value))
+                            .filter_map(|(_, value)| parse_frequency(value));
+                        if let Some(existing) = wiphy.bands.iter_mut().find(|existing| existing.kind == kind) {
+                            existing.frequencies.extend(frequencies)
+                        } else {
+                            wiphy.bands.push(Band {
+                                kind,
+                                frequencies: frequencies.collect()
+                            })
+                        }
+                    },
+                    _ => ()
+                }
+            }
+        }
+        Ok(wiphy)
+    }
+
+    /// Tune an interface to a frequency in MHz
+    pub fn set_frequency(&mut self, interface: &Interface, mhz: u32) -> Result<()> {
+        let mut attributes = Attributes::default();
+        attributes.u32(NL80211_ATTR_IFINDEX, interface.index);
+        attributes.u32(NL80211_ATTR_WIPHY_FREQ, mhz);
+        attributes.u32(NL80211_ATTR_WIPHY_CHANNEL_TYPE, NL80211_CHAN_NO_HT);
+        self.request(self.family, NL80211_CMD_SET_WIPHY, false, attributes)?;
+        Ok(())
+    }
+
+    /// Change the mode of an interface, which most drivers only allow while it is down
+    pub fn set_type(&mut self, name: &str, iftype: InterfaceType) -> Result<()> {
+        let mut attributes = Attributes::default();
+        attributes.u32(NL80211_ATTR_IFINDEX, interface_index(name)?);
+        attributes.u32(NL80211_ATTR_IFTYPE, iftype.code());
+        self.request(self.family, NL80211_CMD_SET_INTERFACE, false, attributes)?;
+        Ok(())
+    }
+
+    /// Tune an interface to a channel, first checking that the radio supports it
+    pub fn set_channel(&mut self, name: &str, channel: u32) -> Result<()> {
+        let mhz = channel_to_frequency(channel).ok_or(Error::InvalidChannel(channel))?;
+        let interface = self.interface(name)?;
+        let wiphy = self.wiphy(interface.wiphy)?;
+        match wiphy.frequency(mhz) {
+            None => Err(Error::UnsupportedChannel(channel)),
+            Some(frequency) if frequency.disabled => Err(Error::DisabledChannel(channel)),
+            Some(_) => self.set_frequency(&interface, mhz)
+        }
+    }
+
+    /// Send a request and collect the generic netlink payload of every reply
+    fn request(&mut self, family: u16, command: u8, dump: bool, attributes: Attributes) -> io::Result<Vec<Vec<u8>>> {
+        self.sequence = self.sequence.wrapping_add(1);
+        let flags = libc::NLM_F_REQUEST | if dump { libc::NLM_F_DUMP } else { libc::NLM_F_ACK };
+        let length = NLMSG_HDRLEN + GENL_HDRLEN + attributes.0.len();
+        let mut message = Vec::with_capacity(length);
+        message.extend_from_slice(&(length as u32).to_ne_bytes());
+        message.extend_from_slice(&family.to_ne_bytes());
+        message.extend_from_slice(&(flags as u16).to_ne_bytes());
+        message.extend_from_slice(&self.sequence.to_ne_bytes());
+        message.extend_from_slice(&0u32.to_ne_bytes());
+        message.extend_from_slice(&[command, 1, 0, 0]);
+        message.extend_from_slice(&attributes.0);
+        if unsafe { libc::send(self.fd, message.as_ptr() as *const libc::c_void, message.len(), 0) } < 0 {
+            return Err(io::Error::last_os_error())
+        }
+
+        let mut replies = vec![];
+        let mut buffer = vec![0u8; 65536];
+        loop {
+            let received = unsafe { libc::recv(self.fd, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len(), 0) };
+            if received < 0 {
+                return Err(io::Error::last_os_error())
+            }
+            let mut data = &buffer[..received as usize];
+            while data.len() >= NLMSG_HDRLEN {
+                let length = u32::from_ne_bytes([data[0], data[1], data[2], data[3]]) as usize;
+                let kind = u16::from_ne_bytes([data[4], data[5]]) as libc::c_int;
+                let sequence = u32::from_ne_bytes([data[8], data[9], data[10], data[11]]);
+                if length < NLMSG_HDRLEN || length > data.len() {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "truncated netlink message"))
+                }
+                let payload = &data[NLMSG_HDRLEN..length];
+                data = &data[align(length).min(data.len())..];
+                if sequence != self.sequence {
+                    continue
+                }
+                match kind {
+                    libc::NLMSG_DONE => return Ok(replies),
+                    libc::NLMSG_ERROR => {
+                        let error = payload.get(..4).map(|e| i32::from_ne_bytes([e[0], e[1], e[2], e[3]])).unwrap_or(0);
+                        return if error == 0 {
+                            Ok(replies)
+                        } else {
+                            Err(io::Error::from_raw_os_error(-error))
+                        }
+                    }
+                    libc::NLMSG_NOOP => (),
+                    _ => if payload.len() >= GENL_HDRLEN {
+                        replies.push(payload[GENL_HDRLEN..].to_vec())
+                    }
+                }
+            }
+        }
+    }
+}
+impl Drop for Nl80211 {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd); }
+    }
+}
+
+/// A buffer of netlink attributes to be sent
+#[derive(Default)]
+struct Attributes(Vec<u8>);
+impl Attributes {
+    fn put(&mut self, kind: u16, value: &[u8]) {
+        self.0.extend_from_slice(&((NLA_HDRLEN + value.len()) as u16).to_ne_bytes());
+        self.0.extend_from_slice(&kind.to_ne_bytes());
+        self.0.extend_from_slice(value);
+        self.0.resize(align(self.0.len()), 0);
+    }
+    fn u32(&mut self, kind: u16, value: u32) {
+        self.put(kind, &value.to_ne_bytes())
+    }
+    fn string(&mut self, kind: u16, value: &str) {
+        let mut bytes = value.as_bytes().to_vec();
+        bytes.push(0);
+        self.put(kind, &bytes)
+    }
+    fn flag(&mut self, kind: u16) {
+        self.put(kind, &[])
+    }
+}
+
+/// Split a buffer into its attribute types and values, ignoring anything malformed
+fn parse_attributes(mut data: &[u8]) -> Vec<(u16, &[u8])> {
+    let mut attributes = vec![];
+    while data.len() >= NLA_HDRLEN {
+        let length = u16::from_ne_bytes([data[0], data[1]]) as usize;
+        let kind = u16::from_ne_bytes([data[2], data[3]]) & NLA_TYPE_MASK;
+        if length < NLA_HDRLEN || length > data.len() {
+            break
+        }
+        attributes.push((kind, &data[NLA_HDRLEN..length]));
+        data = &data[align(length).min(data.len())..];
+    }
+    attributes
+}
+fn parse_interface(data: &[u8]) -> Interface {
+    let mut interface = Interface {
+        index: 0,
+        name: String::new(),
+        wiphy: 0,
+        iftype: InterfaceType::Unspecified,
+        frequency: None
+    };
+    for (kind, value) in parse_attributes(data) {
+        match kind {
+            NL80211_ATTR_IFINDEX => interface.index = read_u32(value).unwrap_or(0),
+            NL80211_ATTR_WIPHY => interface.wiphy = read_u32(value).unwrap_or(0),
+            NL80211_ATTR_IFNAME => interface.name = read_string(value),
+            NL80211_ATTR_IFTYPE => interface.iftype = InterfaceType::new(read_u32(value).unwrap_or(0)),
+            NL80211_ATTR_WIPHY_FREQ => interface.frequency = read_u32(value),
+            _ => ()
+        }
+    }
+    interface
+}
+fn parse_frequency(data: &[u8]) -> Option<Frequency> {
+    let mut frequency = Frequency {
+        mhz: 0,
+        disabled: false,
+        no_ir: false,
+        radar: false,
+        max_power: None
+    };
+    for (kind, value) in parse_attributes(data) {
+        match kind {
+            NL80211_FREQUENCY_ATTR_FREQ => frequency.mhz = read_u32(value)?,
+            NL80211_FREQUENCY_ATTR_DISABLED => frequency.disabled = true,
+            NL80211_FREQUENCY_ATTR_NO_IR => frequency.no_ir = true,
+            NL80211_FREQUENCY_ATTR_RADAR => frequency.radar = true,
+            NL80211_FREQUENCY_ATTR_MAX_TX_POWER => frequency.max_power = read_u32(value),
+            _ => ()
+        }
+    }
+    if frequency.mhz == 0 {
+        None
+    } else {
+        Some(frequency)
+    }
+}
+fn read_u16(value: &[u8]) -> Option<u16> {
+    Some(u16::from_ne_bytes([*value.get(0)?, *value.get(1)?]))
+}
+fn read_u32(value: &[u8]) -> Option<u32> {
+    Some(u32::from_ne_bytes([*value.get(0)?, *value.get(1)?, *value.get(2)?, *value.get(3)?]))
+}
+fn read_string(value: &[u8]) -> String {
+    String::from_utf8_lossy(value.split(|&b| b == 0).next().unwrap_or(&[])).to_string()
+}
+
+fn interface_index(name: &str) -> Result<u32> {
+    let c_name = CString::new(name).map_err(|_| Error::NoSuchInterface(name.to_string()))?;
+    match unsafe { libc::if_nametoindex(c_name.as_ptr()) } {
+        0 => Err(Error::NoSuchInterface(name.to_string())),
+        index => Ok(index)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    NoSuchInterface(String),
+    InvalidChannel(u32),
+    UnsupportedChannel(u32),
+    DisabledChannel(u32)
+}
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "nl80211 request failed: {}", error),
+            Self::NoSuchInterface(name) => write!(f, "no wireless interface named {:?}", name),
+            Self::InvalidChannel(channel) => write!(f, "{} is not a valid 2.4GHz or 5GHz channel", channel),
+            Self::UnsupportedChannel(channel) => write!(f, "channel {} is not supported by this adapter", channel),
+            Self::DisabledChannel(channel) => write!(f, "channel {} is disabled in the current regulatory domain", channel)
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use std::{
+    collections::HashSet,
+    fmt,
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    process,
+    sync::{Arc, Mutex, mpsc::{self, Sender}},
+    thread,
+    time::Duration
+};
+use eui48::MacAddress;
+
+use crate::{DeviceList, alerts::Alert, anonymize::Anonymizer, events, export};
+
+/// How long to wait on a webhook or broker before giving up on an event
+const TIMEOUT: Duration = Duration::from_secs(10);
+const MQTT_PORT: u16 = 1883;
+
+/// Where events are sent
+#[derive(Debug, Clone)]
+pub enum Target {
+    /// POSTed one at a time as JSON
+    Webhook(String),
+    /// Published to a topic on an MQTT broker, such as the one Home Assistant uses
+    Mqtt {
+        /// The broker, such as localhost or localhost:1883
+        broker: String,
+        topic: String
+    }
+}
+
+/// Sends new devices, captured handshakes and alerts, watchlist hits and deauthentication floods among them,
+/// to webhooks and MQTT brokers from a thread of its own
+pub struct Notifier {
+    events: Sender<String>,
+    /// Why the last event couldn't be sent, cleared once one is
+    pub error: Arc<Mutex<Option<String>>>,
+    /// Devices and access points with handshakes already announced
+    devices: HashSet<MacAddress>,
+    handshakes: HashSet<MacAddress>,
+    anonymizer: Anonymizer
+}
+impl Notifier {
+    /// Must be started before the sandbox is applied, which only confines the thread applying it,
+    /// so that the notifier can still reach the network
+    pub fn spawn(targets: Vec<Target>, anonymizer: Anonymizer) -> Self {
+        let (tx, rx) = mpsc::channel::<String>();
+        let error = Arc::new(Mutex::new(None));
+        let thread_error = error.clone();
+        thread::spawn(move || {
+            let client = reqwest::blocking::Client::builder().timeout(TIMEOUT).build();
+            // Kept open between events, and opened again once the broker drops it
+            let mut brokers: Vec<Option<TcpStream>> = targets.iter().map(|_| None).collect();
+            for event in rx {
+                let mut failed = None;
+                for (target, broker) in targets.iter().zip(brokers.iter_mut()) {
+                    let result = match (target, &client) {
+                        (Target::Webhook(url), Ok(client)) => client.post(url)
+                            .header("Content-Type", "application/json")
+                            .body(event.clone())
+                            .send()
+                            .and_then(|response| response.error_for_status())
+                            .map(drop)
+                            .map_err(Error::Webhook),
+                        (Target::Webhook(_), Err(error)) => Err(Error::Client(error.to_string())),
+                        (Target::Mqtt { broker: address, topic }, _) => {
+                            // A connection left idle may have been closed since, so one failure is retried afresh
+                            publish(broker, address, topic, &event).or_else(|_| {
+                                *broker = None;
+                                publish(broker, address, topic, &event)
+                            })
+                        }
+                    };
+                    if let Err(error) = result {
+                        failed = Some(error.to_string());
+                    }
+                }
+                if let Ok(mut error) = thread_error.lock() {
+                    *error = failed;
+                }
+            }
+        });
+        Self {
+            events: tx,
+            error,
+            devices: HashSet::new(),
+            handshakes: HashSet::new(),
+            anonymizer
+        }
+    }
+    /// Send every device and handshake not sent before
+    pub fn check(&mut self, devices: &DeviceList, time: Duration) {
+        for (&address, device) in devices.iter() {
+            if device.sent && self.devices.insert(address) {
+                let _ = self.events.send(events::device_event(&self.anonymizer, address, device, time));
+            }
+            if device.handshake_complete() && self.handshakes.insert(address) {
+                let event = events::event("handshake", time, vec![
+                    ("bssid", export::json_string(&self.anonymizer.address_string(&address))),
+                    ("ssid", device.beacon.as_ref().map_or_else(|| "null".to_string(), |ssid| export::json_string(&self.anonymizer.ssid(ssid))))
+                ]);
+                let _ = self.events.send(event);
+            }
+        }
+    }
+    pub fn alert(&self, alert: &Alert) {
+        let _ = self.events.send(events::alert_event(&self.anonymizer, alert));
+    }
+    /// Forget what has been sent, so everything is sent again after the device list is cleared
+    pub fn reset(&mut self) {
+        self.devices.clear();
+        self.handshakes.clear();
+    }
+}
+
+/// Publish an event at most once, connecting to the broker first if need be
+fn publish(connection: &mut Option<TcpStream>, broker: &str, topic: &str, event: &str) -> Result<(), Error> {
+    if connection.is_none() {
+        *connection = Some(connect(broker)?);
+    }
+    let stream = connection.as_mut().unwrap(/* Connected above */);
+    let mut body = string(topic);
+    body.extend_from_slice(event.as_bytes());
+    stream.write_all(&packet(0x30, &body)).map_err(Error::Io)
+}
+
+/// Connect to an MQTT 3.1.1 broker with a clean session and no keep alive, as nothing is subscribed to
+fn connect(broker: &str) -> Result<TcpStream, Error> {
+    let broker = if broker.contains(':') { broker.to_string() } else { format!("{}:{}", broker, MQTT_PORT) };
+    let address = broker.to_socket_addrs().map_err(Error::Io)?
+        .next()
+        .ok_or_else(|| Error::Io(io::Error::new(io::ErrorKind::NotFound, "the broker's name didn't resolve")))?;
+    let mut stream = TcpStream::connect_timeout(&address, TIMEOUT).map_err(Error::Io)?;
+    stream.set_read_timeout(Some(TIMEOUT)).map_err(Error::Io)?;
+    stream.set_write_timeout(Some(TIMEOUT)).map_err(Error::Io)?;
+    let mut body = string("MQTT");
+    // Protocol level 4, a clean session and a keep alive of 0
+    body.extend_from_slice(&[4, 0b10, 0, 0]);
+    body.extend(string(&format!("blockade-recon-{}", process::id())));
+    stream.write_all(&packet(0x10, &body)).map_err(Error::Io)?;
+    let mut connack = [0; 4];
+    stream.read_exact(&mut connack).map_err(Error::Io)?;
+    match connack {
+        [0x20, 2, _, 0] => Ok(stream),
+        [0x20, 2, _, code] => Err(Error::Refused(code)),
+        _ => Err(Error::Io(io::Error::new(io::ErrorKind::InvalidData, "the broker didn't acknowledge the connection")))
+    }
+}
+
+/// A control packet of some type, prefixed with its length
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![header];
+    // Seven bits at a time, the top bit set while more follow
+    let mut length = body.len();
+    loop {
+        let byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            packet.push(byte | 0x80);
+        } else {
+            packet.push(byte);
+            break
+        }
+    }
+    packet.extend_from_slice(body);
+    packet
+}
+
+/// A UTF-8 string as MQTT encodes them, prefixed with its length
+fn string(text: &str) -> Vec<u8> {
+    let mut string = (text.len() as u16).to_be_bytes().to_vec();
+    string.extend_from_slice(text.as_bytes());
+    string
+}
+
+/// Parse a `--mqtt` broker and topic, such as `localhost:1883/blockade-recon`
+pub fn parse_mqtt(setting: &str) -> Result<Target, ParseError> {
+    match setting.split_once('/') {
+        Some((broker, topic)) if !broker.is_empty() && !topic.is_empty() => Ok(Target::Mqtt {
+            broker: broker.to_string(),
+            topic: topic.to_string()
+        }),
+        _ => Err(ParseError(setting.to_string()))
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseError(String);
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} is not a broker and topic such as localhost:1883/blockade-recon", self.0)
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Client(String),
+    Webhook(reqwest::Error),
+    Io(io::Error),
+    /// The broker refused the connection, with its return code
+    Refused(u8)
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Client(error) => write!(f, "Unable to set up the webhook client: {}", error),
+            Self::Webhook(error) => write!(f, "Webhook: {}", error),
+            Self::Io(error) => write!(f, "MQTT broker: {}", error),
+            Self::Refused(code) => write!(f, "MQTT broker refused the connection with code {}", code)
+        }
+    }
+}
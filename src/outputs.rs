@@ -0,0 +1,548 @@
+use std::{fs::{File, OpenOptions}, io, path::{Path, PathBuf}, time::{Duration, SystemTime}};
+use clap::ArgMatches;
+use eui48::MacAddress;
+use radiotap::Radiotap;
+
+use crate::{
+    alerts::{self, Alert, Severity}, anonymize::Anonymizer, capture::Frame, cli::{failed, Failure}, email, events, evidence, export, findings, geo,
+    gps::Fix, handshake, hook, incident, inventory, nl80211, notify, recorder, ring, schedule::Schedule, session, store, survey::Survey, vendors,
+    wardrive, wifi, DeviceList, KnownDevice, Role
+};
+
+/// Everything frames are kept in as they arrive, opened before privileges are dropped
+pub struct Captures {
+    pub recorder: Option<recorder::Recorder>,
+    /// Whether frames are saved, until stopped from the remote control or by a failed write
+    pub saving: bool,
+    pub incidents: Option<incident::Incidents>,
+    pub incident_error: Option<String>,
+    pub ring: Option<ring::Ring>,
+    pub handshakes: Option<handshake::Handshakes>,
+    pub handshake_error: Option<String>,
+    pub session: Option<session::Writer>,
+    pub session_error: Option<String>,
+    /// The capture file as named, which segments, incidents, the ring and handshakes are saved beside
+    output: PathBuf
+}
+impl Captures {
+    /// Open everything asked for on the command line to keep frames from `sensors` in
+    pub fn open(args: &ArgMatches, schedule: &Schedule, sensors: &[String], survey: &Survey, replaying: bool) -> Result<Self, Failure> {
+        let output = Path::new(args.value_of("output").unwrap_or("capture.pcap"));
+        let rotation = recorder::Rotation {
+            size: args.value_of("rotate_size").map(recorder::parse_size).transpose()
+                .map_err(failed("The rotation size is not a number of bytes, optionally followed by K, M or G"))?,
+            interval: args.value_of("rotate_interval").map(str::parse::<u64>).transpose()
+                .map_err(failed("The rotation interval is not a number of minutes"))?
+                .map(|minutes| Duration::from_secs(minutes * 60))
+        };
+        // A replay's frames are already saved in its session, and would only overwrite the capture it came from
+        let recorder = if args.is_present("no_save") || replaying {
+            None
+        } else {
+            Some(recorder::Recorder::create(schedule.path(output), rotation, sensors, survey.anonymizer.clone())
+                .map_err(failed("Unable to create save file for packet capture"))?)
+        };
+        let folder = output.parent().filter(|folder| !folder.as_os_str().is_empty()).unwrap_or_else(|| Path::new("."));
+        let incidents = if args.is_present("incidents") {
+            let before = args.value_of("incident_before").unwrap_or("30").parse::<u64>().map_err(failed("The incident lead-up is not a number of seconds"))?;
+            let after = args.value_of("incident_after").unwrap_or("30").parse::<u64>().map_err(failed("The incident follow-up is not a number of seconds"))?;
+            Some(incident::Incidents::new(Duration::from_secs(before), Duration::from_secs(after), folder))
+        } else {
+            None
+        };
+        let ring = if args.is_present("ring") || args.is_present("ring_size") {
+            let span = args.value_of("ring").map(str::parse::<u64>).transpose().map_err(failed("The ring length is not a number of seconds"))?;
+            let size = args.value_of("ring_size").map(recorder::parse_size).transpose()
+                .map_err(failed("The ring size is not a number of bytes, optionally followed by K, M or G"))?;
+            Some(ring::Ring::new(span.map(Duration::from_secs), size, folder, survey.anonymizer.clone()))
+        } else {
+            None
+        };
+        // Handshakes are kept whole to be cracked, which would give away what was anonymized
+        let handshakes = if args.is_present("no_save") || replaying || survey.anonymizer.is_enabled() {
+            None
+        } else {
+            let path = args.value_of("handshakes").map_or_else(|| output.with_file_name("handshakes.pcap"), PathBuf::from);
+            Some(handshake::Handshakes::new(&path))
+        };
+        if survey.is_named() {
+            // A pcap file has nowhere to keep session details, so they sit alongside it
+            survey.write(output.with_extension("meta")).map_err(failed("Unable to save the survey details"))?;
+        }
+        let session = args.value_of("session").map(|path| session::Writer::create(path, sensors)).transpose()
+            .map_err(failed("Unable to create the session file"))?;
+        Ok(Self {
+            recorder,
+            saving: true,
+            incidents,
+            incident_error: None,
+            ring,
+            handshakes,
+            handshake_error: None,
+            session,
+            session_error: None,
+            output: output.to_path_buf()
+        })
+    }
+
+    /// The capture file frames are saved to or beside, if any are kept
+    pub fn output(&self) -> Option<&Path> {
+        Some(self.output.as_path()).filter(|_| self.recorder.is_some() || self.incidents.is_some() || self.handshakes.is_some() || self.ring.is_some())
+    }
+
+    /// Keep a frame as it arrives, with `comment` saved alongside it if the save file takes comments
+    /// Failing to save is most likely for want of disk space, so saving stops rather than trying every frame
+    pub fn frame(&mut self, sensor: usize, captured: &Frame, comment: impl FnOnce() -> String) -> Result<(), recorder::Error> {
+        if let Some(incidents) = &mut self.incidents {
+            if let Err(error) = incidents.frame(captured) {
+                self.incident_error = Some(error.to_string());
+            }
+        }
+        if let Some(ring) = &mut self.ring {
+            ring.frame(captured);
+        }
+        if let (Some(recorder), true) = (&mut self.recorder, self.saving) {
+            let comment = Some(()).filter(|_| recorder.annotates()).map(|_| comment());
+            if let Err(error) = recorder.write(sensor, &captured.packet(), comment.as_deref()) {
+                self.saving = false;
+                return Err(error)
+            }
+        }
+        Ok(())
+    }
+
+    /// Record a frame in the session as it is handled, with the channel and position it was heard at
+    pub fn record(&mut self, time: Duration, sensor: usize, channel: Option<u32>, fix: Option<Fix>, captured: &Frame) {
+        if let (Some(writer), None) = (&mut self.session, &self.session_error) {
+            if let Err(error) = writer.write(time, sensor, channel, fix, captured) {
+                self.session_error = Some(error.to_string());
+            }
+        }
+    }
+
+    /// Keep a beacon or a frame of a handshake, for the handshake to be cracked with
+    pub fn handshake(&mut self, frame: &wifi::Frame, captured: &Frame) {
+        if let Some(handshakes) = &mut self.handshakes {
+            match frame {
+                wifi::Frame::Beacon { source, .. } => handshakes.beacon(*source, captured),
+                wifi::Frame::Data { bssid, eapol: Some(_), .. } => if let Err(error) = handshakes.eapol(*bssid, captured) {
+                    self.handshake_error = Some(error.to_string());
+                },
+                _ => ()
+            }
+        }
+    }
+
+    /// Capture an incident around a critical alert
+    pub fn alert(&mut self, alert: &Alert) {
+        if let (Some(incidents), Severity::Critical) = (&mut self.incidents, alert.severity) {
+            self.incident_error = incidents.trigger(alert).err().map(|error| error.to_string());
+        }
+    }
+
+    /// Finish capturing any incident whose follow-up has passed
+    pub fn check(&mut self) {
+        if let Some(incidents) = &mut self.incidents {
+            if let Err(error) = incidents.check() {
+                self.incident_error = Some(error.to_string());
+            }
+        }
+    }
+
+    /// Save what is held in memory, saying what was saved where
+    pub fn dump(&mut self) -> Result<String, String> {
+        match &mut self.ring {
+            Some(ring) => {
+                let (frames, covers) = (ring.len(), ring.covers());
+                ring.dump()
+                    .map(|path| format!("Saved {} frame(s) from the last {}s to {}", frames, covers.as_secs(), path.display()))
+                    .map_err(|error| error.to_string())
+            }
+            None => Err("Nothing is held in memory to save without --ring or --ring-size".to_string())
+        }
+    }
+
+    /// Package a device's frames and what is known of it as evidence, saying where
+    pub fn extract(&mut self, address: MacAddress, device: &KnownDevice, survey: &Survey) -> Result<String, String> {
+        self.recorder.as_mut().map_or(Ok(()), recorder::Recorder::flush).map_err(|error| error.to_string())?;
+        let captures = self.recorder.as_ref().map_or(&[][..], recorder::Recorder::files);
+        evidence::package(address, device, captures, survey)
+            .map(|folder| format!("Exported {} to {}", address.to_hex_string(), folder.display()))
+            .map_err(|error| error.to_string())
+    }
+
+    /// Start saving frames to the next segment's file
+    pub fn next_segment(&mut self, schedule: &Schedule) -> Result<(), recorder::Error> {
+        match &mut self.recorder {
+            Some(recorder) => recorder.switch(schedule.path(&self.output)),
+            None => Ok(())
+        }
+    }
+
+    /// Finish the session file as the capture ends
+    pub fn finish(&mut self) -> Result<(), Failure> {
+        if let (Some(writer), None) = (&mut self.session, &self.session_error) {
+            writer.flush().map_err(failed("Unable to finish the session file"))?;
+        }
+        Ok(())
+    }
+}
+
+/// A report written over as the capture goes on, and a last time as it ends
+struct Report<F> {
+    /// Where it is being written, which changes with each segment
+    path: String,
+    /// Where it was asked to be written, which each segment's file is named from
+    given: PathBuf,
+    file: File,
+    format: F
+}
+impl<F> Report<F> {
+    fn create(args: &ArgMatches, option: &str, schedule: &Schedule, format: impl Fn(&Path) -> F, message: &'static str) -> Result<Option<Self>, Failure> {
+        let given = match args.value_of(option) {
+            Some(given) => PathBuf::from(given),
+            None => return Ok(None)
+        };
+        let path = schedule.path(&given);
+        let file = File::create(&path).map_err(failed(message))?;
+        Ok(Some(Self {
+            format: format(&path),
+            path: path.display().to_string(),
+            given,
+            file
+        }))
+    }
+
+    /// Start writing to the next segment's file, the last having been completed
+    fn next_segment(&mut self, schedule: &Schedule) -> io::Result<()> {
+        let next = schedule.path(&self.given);
+        self.file = File::create(&next)?;
+        self.path = next.display().to_string();
+        Ok(())
+    }
+}
+
+/// Everything written about what was heard rather than the frames themselves, and everywhere alerts are sent,
+/// opened once privileges are dropped but before the sandbox is applied, as they may be anywhere or reach across the network
+pub struct Reports {
+    pub survey: Survey,
+    pub fields: store::Selection,
+    devices: Option<Report<export::Format>>,
+    findings: Option<Report<findings::Format>>,
+    geo: Option<Report<geo::Format>>,
+    wardrive: Option<Report<wardrive::Format>>,
+    vendors: Option<(File, export::Format, vendors::VendorTimeline)>,
+    events: Option<events::Events>,
+    alert_log: Option<alerts::Log>,
+    store: Option<store::Mirror>,
+    pub mailer: Option<email::Mailer>,
+    pub notifier: Option<notify::Notifier>,
+    pub hook: Option<hook::Hook>
+}
+impl Reports {
+    /// Open everything asked for on the command line, with events written to stdout when `headless` if not to a file
+    pub fn open(args: &ArgMatches, schedule: &Schedule, survey: Survey, fields: store::Selection, sensors: &[String], headless: bool) -> Result<Self, Failure> {
+        let anonymizer = &survey.anonymizer;
+        let vendors = match args.value_of("vendor_stats") {
+            Some(path) => {
+                let minutes = args.value_of("vendor_interval").unwrap_or("15").parse::<u64>()
+                    .map_err(failed("The vendor statistics interval is not a number of minutes"))?;
+                let file = File::create(path).map_err(failed("Unable to create the vendor statistics file"))?;
+                Some((file, export::Format::from_path(path), vendors::VendorTimeline::new(Duration::from_secs(minutes * 60))))
+            }
+            None => None
+        };
+        let devices = Report::create(args, "export", schedule, |path| export::Format::from_path(path), "Unable to create the device export file")?;
+        let findings = Report::create(args, "findings", schedule, |path| findings::Format::from_path(path), "Unable to create the findings file")?;
+        let geo = Report::create(args, "geo_export", schedule, |path| geo::Format::from_path(path), "Unable to create the map export file")?;
+        let wardrive = Report::create(args, "wardrive", schedule, |path| wardrive::Format::from_path(path), "Unable to create the wardriving export file")?;
+        let events = match args.value_of("events") {
+            Some(path) => Some(events::Events::new(Box::new(File::create(path).map_err(failed("Unable to create the events file"))?), anonymizer.clone())),
+            None if headless => Some(events::Events::new(Box::new(io::stdout()), anonymizer.clone())),
+            None => None
+        };
+        let alert_log = match args.value_of("alert_log") {
+            Some(path) => Some(alerts::Log::new(
+                Box::new(OpenOptions::new().create(true).append(true).open(path).map_err(failed("Unable to open the alert log"))?),
+                anonymizer.clone(),
+                survey.started_unix()
+            )),
+            None => None
+        };
+        let store = args.value_of("store")
+            .map(|spec| store::open(spec, &fields.store).and_then(|store| store::Mirror::new(store, fields.store.clone())))
+            .transpose()
+            .map_err(failed("Unable to open the device store"))?;
+
+        // Started before the sandbox is applied, so the mailer's thread can still reach the mail server
+        let mailer = match args.value_of("smtp") {
+            Some(server) => {
+                let minutes = args.value_of("email_interval").unwrap_or("10").parse::<u64>().map_err(failed("The email interval is not a number of minutes"))?;
+                Some(email::Mailer::spawn(email::Config {
+                    server: server.to_string(),
+                    from: args.value_of("email_from").unwrap().to_string(),
+                    to: args.values_of("email_to").unwrap().map(String::from).collect(),
+                    interval: Duration::from_secs(minutes.max(1) * 60),
+                    subject: format!("[{} on {}]", survey, sensors.join(" + "))
+                }))
+            }
+            None => None
+        };
+        // Likewise for webhooks and MQTT brokers
+        let mut targets: Vec<_> = args.values_of("webhook").into_iter().flatten().map(|url| notify::Target::Webhook(url.to_string())).collect();
+        for setting in args.values_of("mqtt").into_iter().flatten() {
+            targets.push(notify::parse_mqtt(setting).map_err(failed("Invalid MQTT broker"))?);
+        }
+        let notifier = if targets.is_empty() { None } else { Some(notify::Notifier::spawn(targets, anonymizer.clone())) };
+        // Likewise, as the sandbox forbids running programs
+        let hook = match args.value_of("alert_command") {
+            Some(command) => {
+                let timeout = args.value_of("alert_command_timeout").unwrap_or("10").parse::<u64>()
+                    .map_err(failed("The alert command timeout is not a number of seconds"))?;
+                let interval = args.value_of("alert_command_interval").unwrap_or("5").parse::<u64>()
+                    .map_err(failed("The alert command interval is not a number of seconds"))?;
+                Some(hook::Hook::spawn(hook::Config {
+                    command: command.to_string(),
+                    timeout: Duration::from_secs(timeout),
+                    interval: Duration::from_secs(interval)
+                }))
+            }
+            None => None
+        };
+        Ok(Self { survey, fields, devices, findings, geo, wardrive, vendors, events, alert_log, store, mailer, notifier, hook })
+    }
+
+    /// The reports as they were named, which each segment's files are written beside
+    pub fn given(&self) -> Vec<&Path> {
+        let mut given = vec![];
+        given.extend(self.devices.as_ref().map(|report| report.given.as_path()));
+        given.extend(self.findings.as_ref().map(|report| report.given.as_path()));
+        given.extend(self.geo.as_ref().map(|report| report.given.as_path()));
+        given.extend(self.wardrive.as_ref().map(|report| report.given.as_path()));
+        given
+    }
+
+    /// Write the device list, along with the findings and access points if they are exported too, saying what was written where
+    /// Without --export the list is written beside the capture, which the sandbox allows
+    pub fn export(&mut self, devices: &DeviceList, alerts: &[Alert]) -> Result<String, String> {
+        let result = match &mut self.devices {
+            Some(report) => inventory::rewrite(&mut report.file, report.format, self.fields.format(report.format), devices, &self.survey)
+                .map(|_| report.path.clone()),
+            None => File::create("devices.csv")
+                .and_then(|mut file| inventory::write(&mut file, export::Format::Csv, &self.fields.csv, devices, &self.survey))
+                .map(|_| "devices.csv".to_string())
+        };
+        let result = result.and_then(|path| match &mut self.findings {
+            Some(report) => findings::rewrite(&mut report.file, report.format, alerts, devices, &self.survey)
+                .map(|_| format!("{} and {} alert(s) to {}", path, alerts.len(), report.path)),
+            None => Ok(path)
+        });
+        let result = result.and_then(|path| match &mut self.wardrive {
+            Some(report) => wardrive::rewrite(&mut report.file, report.format, devices, &self.survey)
+                .map(|_| format!("{} and the access points to {}", path, report.path)),
+            None => Ok(path)
+        });
+        result
+            .map(|path| format!("Exported {} device(s) to {}", devices.len(), path))
+            .map_err(|error| error.to_string())
+    }
+
+    /// Write where each device was heard from, beside the capture without --geo-export, saying how many were mapped where
+    pub fn map(&mut self, devices: &DeviceList) -> Result<String, String> {
+        let result = match &mut self.geo {
+            Some(report) => geo::rewrite(&mut report.file, report.format, devices, &self.survey.anonymizer).map(|_| report.path.clone()),
+            None => File::create("devices.geojson")
+                .and_then(|mut file| geo::write(&mut file, geo::Format::GeoJson, devices, &self.survey.anonymizer))
+                .map(|_| "devices.geojson".to_string())
+        };
+        let located = devices.values().filter(|device| device.geo.strongest.is_some()).count();
+        result
+            .map(|path| format!("Mapped {} device(s) to {}", located, path))
+            .map_err(|error| error.to_string())
+    }
+
+    /// Write the device list to a file named for when it was taken, so each is kept where an export writes over the last
+    pub fn snapshot(&self, devices: &DeviceList) -> Result<String, String> {
+        let format = self.devices.as_ref().map_or(export::Format::Csv, |report| report.format);
+        let taken = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |since| since.as_secs());
+        let path = format!("snapshot-{}.{}", taken, match format {
+            export::Format::Csv => "csv",
+            export::Format::Json => "json"
+        });
+        File::create(&path)
+            .and_then(|mut file| inventory::write(&mut file, format, self.fields.format(format), devices, &self.survey))
+            .map(|_| format!("Snapshot of {} device(s) written to {}", devices.len(), path))
+            .map_err(|error| error.to_string())
+    }
+
+    /// Complete each report for the segment just finished and start it afresh in a file named for the next,
+    /// stopping any that can't be, which are returned to be warned about
+    pub fn next_segment(&mut self, schedule: &Schedule, devices: &DeviceList, alerts: &[Alert]) -> Vec<Failure> {
+        let mut stopped = vec![];
+        if let Some(report) = &mut self.devices {
+            let finished = inventory::rewrite(&mut report.file, report.format, self.fields.format(report.format), devices, &self.survey);
+            if let Err(error) = finished.and_then(|_| report.next_segment(schedule)) {
+                self.devices = None;
+                stopped.push(Failure::new("Stopped exporting the device list", error));
+            }
+        }
+        if let Some(report) = &mut self.findings {
+            let finished = findings::rewrite(&mut report.file, report.format, alerts, devices, &self.survey);
+            if let Err(error) = finished.and_then(|_| report.next_segment(schedule)) {
+                self.findings = None;
+                stopped.push(Failure::new("Stopped writing findings", error));
+            }
+        }
+        if let Some(report) = &mut self.geo {
+            let finished = geo::rewrite(&mut report.file, report.format, devices, &self.survey.anonymizer);
+            if let Err(error) = finished.and_then(|_| report.next_segment(schedule)) {
+                self.geo = None;
+                stopped.push(Failure::new("Stopped writing the map export", error));
+            }
+        }
+        if let Some(report) = &mut self.wardrive {
+            let finished = wardrive::rewrite(&mut report.file, report.format, devices, &self.survey);
+            if let Err(error) = finished.and_then(|_| report.next_segment(schedule)) {
+                self.wardrive = None;
+                stopped.push(Failure::new("Stopped writing the wardriving export", error));
+            }
+        }
+        stopped
+    }
+
+    /// Write events for what has changed, notify of it and bring the device store up to date,
+    /// stopping whatever can't be written to, which is returned to be warned about
+    pub fn check(&mut self, devices: &DeviceList, time: Duration) -> Vec<Failure> {
+        let mut stopped = vec![];
+        if let Some(writer) = &mut self.events {
+            if let Err(error) = writer.check(devices, time) {
+                self.events = None;
+                stopped.push(Failure::new("Stopped writing events", error));
+            }
+        }
+        if let Some(notifier) = &mut self.notifier {
+            notifier.check(devices, time);
+        }
+        if let Some(mirror) = &mut self.store {
+            if let Err(error) = mirror.sync(devices, &self.survey) {
+                self.store = None;
+                stopped.push(Failure::new("Stopped storing devices", error));
+            }
+        }
+        stopped
+    }
+
+    /// Send an alert everywhere alerts go, stopping whatever can't be written to
+    pub fn alert(&mut self, alert: &Alert) -> Vec<Failure> {
+        let mut stopped = vec![];
+        if let Some(writer) = &mut self.events {
+            if let Err(error) = writer.alert(alert) {
+                self.events = None;
+                stopped.push(Failure::new("Stopped writing events", error));
+            }
+        }
+        if let Some(log) = &mut self.alert_log {
+            if let Err(error) = log.alert(alert) {
+                self.alert_log = None;
+                stopped.push(Failure::new("Stopped writing the alert log", error));
+            }
+        }
+        if let Some(mailer) = &self.mailer {
+            mailer.alert(alert);
+        }
+        if let Some(notifier) = &self.notifier {
+            notifier.alert(alert);
+        }
+        if let Some(hook) = &self.hook {
+            hook.alert(alert);
+        }
+        stopped
+    }
+
+    /// Write an event for a network announcing it is moving channel, stopping the events if they can't be written
+    pub fn channel_switch(&mut self, bssid: MacAddress, from: Option<u32>, to: u32, time: Duration) -> Option<Failure> {
+        let error = self.events.as_mut()?.channel_switch(bssid, from, to, time).err()?;
+        self.events = None;
+        Some(Failure::new("Stopped writing events", error))
+    }
+
+    /// Count a device towards the vendor statistics
+    pub fn vendor(&mut self, address: MacAddress, device: &KnownDevice) {
+        if let Some((_, _, vendors)) = &mut self.vendors {
+            vendors.record(address, device);
+        }
+    }
+
+    /// Forget what has been reported of the devices, as they are cleared
+    pub fn reset(&mut self) {
+        if let Some(events) = &mut self.events {
+            events.reset();
+        }
+        if let Some(notifier) = &mut self.notifier {
+            notifier.reset();
+        }
+    }
+
+    /// Write everything a last time as the capture ends
+    pub fn finish(&mut self, devices: &DeviceList, alerts: &[Alert]) -> Result<(), Failure> {
+        if let Some(mirror) = &mut self.store {
+            mirror.sync(devices, &self.survey).map_err(failed("Unable to store the device list"))?;
+        }
+        if let Some(report) = &mut self.devices {
+            inventory::rewrite(&mut report.file, report.format, self.fields.format(report.format), devices, &self.survey)
+                .map_err(failed("Unable to export the device list"))?;
+        }
+        if let Some(report) = &mut self.geo {
+            geo::rewrite(&mut report.file, report.format, devices, &self.survey.anonymizer).map_err(failed("Unable to write the map export"))?;
+        }
+        if let Some(report) = &mut self.wardrive {
+            wardrive::rewrite(&mut report.file, report.format, devices, &self.survey).map_err(failed("Unable to write the wardriving export"))?;
+        }
+        if let Some(report) = &mut self.findings {
+            findings::rewrite(&mut report.file, report.format, alerts, devices, &self.survey).map_err(failed("Unable to write the findings"))?;
+        }
+        if let Some((file, format, vendors)) = &mut self.vendors {
+            vendors.write(file, *format, &self.survey).map_err(failed("Unable to write the vendor statistics"))?;
+        }
+        Ok(())
+    }
+}
+
+/// What is known of a frame as it arrives, for the comment kept with it in a pcapng file:
+/// the channel, where the sensor was, and whether it came from an access point or a client and of which network
+pub fn annotation(data: &[u8], channel: Option<u32>, fix: Option<Fix>, devices: &DeviceList, anonymizer: &Anonymizer) -> String {
+    let mut annotation = vec![];
+    let parsed = Radiotap::parse(data).ok();
+    let channel = parsed.as_ref()
+        .and_then(|(radiotap, _)| radiotap.channel)
+        .and_then(|channel| nl80211::frequency_to_channel(channel.freq as u32))
+        .or(channel);
+    if let Some(channel) = channel {
+        annotation.push(format!("channel={}", channel));
+    }
+    if let Some(fix) = fix {
+        annotation.push(format!("lat={:.6} lon={:.6}", fix.latitude, fix.longitude));
+    }
+    let transmitter = parsed.and_then(|(_, data)| wifi::Summary::parse(data).ok()).and_then(|summary| summary.transmitter);
+    if let Some((address, device)) = transmitter.and_then(|address| Some((address, devices.get(&address)?))) {
+        // Known from frames before this one, so the first beacon of a network isn't named yet
+        let network = match device.role {
+            Some(Role::AccessPoint) => {
+                annotation.push("role=ap".to_string());
+                Some((address, device))
+            }
+            Some(Role::Station) => {
+                annotation.push("role=client".to_string());
+                device.associated.and_then(|bssid| Some((bssid, devices.get(&bssid)?)))
+            }
+            None => None
+        };
+        if let Some((bssid, access_point)) = network {
+            annotation.push(format!("bssid={}", anonymizer.address_string(&bssid)));
+            if let Some(ssid) = access_point.beacon.as_deref().filter(|ssid| !ssid.is_empty()) {
+                annotation.push(format!("ssid={:?}", anonymizer.ssid(ssid)));
+            }
+        }
+    }
+    annotation.join(" ")
+}
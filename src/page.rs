@@ -1,21 +1,138 @@
-use termion::{input::MouseTerminal, raw::RawTerminal, screen::AlternateScreen};
+use eui48::MacAddress;
 use tui::{
-    backend::TermionBackend,
     layout::Rect,
     terminal::Frame
 };
 
+mod alerts;
+mod bluetooth;
+mod detail;
 mod devices;
+mod events;
 mod manufacturers;
+mod networks;
+mod packets;
+mod probes;
+mod security;
+mod spectrum;
+mod stats;
+mod timeline;
+mod track;
 
-pub use devices::Devices;
+pub use alerts::{Alerts, severity_colour};
+pub use bluetooth::Bluetooth;
+pub use detail::DeviceDetail;
+pub use devices::{Column, Devices};
+pub use events::Events;
 pub use manufacturers::Manufacturers;
+pub use networks::Networks;
+pub use packets::{LogEntry, Packets};
+pub use probes::Probes;
+pub use security::Security;
+pub use spectrum::Spectrum;
+pub use stats::Stats;
+pub use timeline::Timeline;
+pub use track::Track;
 
-use crate::{DeviceList, ui};
+use crate::{DeviceList, state::State, ui::{self, Key}};
+
+/// The device on any of the rows first heard most recently, for lists following new devices
+fn newest(rows: &[Option<MacAddress>], devices: &DeviceList) -> Option<MacAddress> {
+    rows.iter()
+        .flatten()
+        .filter_map(|address| Some((devices.get(address)?.first_seen?, *address)))
+        .max()
+        .map(|(_, address)| address)
+}
+
+/// Added to the title of a list while it follows new devices
+fn following(state: &ui::ListState) -> &'static str {
+    if state.newest { ", following new devices" } else { "" }
+}
+
+/// Something a search turned up on a page, by what the page picks it out by
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Found {
+    Device(MacAddress),
+    Manufacturer(String)
+}
+
+/// Another page a key on this one asks to be taken to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Navigation {
+    /// The page of everything known about one device
+    Device(MacAddress),
+    /// The tab the page was opened from
+    Back
+}
+
+/// A search across every page that can be searched, stepped through with `n` and `N`
+pub struct Search {
+    pub text: String,
+    /// Each match with the index of the page it is on, in tab order from the page searched from
+    pub found: Vec<(usize, Found)>,
+    pub position: usize
+}
+impl Search {
+    pub fn run(text: String, pages: &[&mut dyn Page], from: usize, devices: &DeviceList) -> Self {
+        let found = (0..pages.len())
+            .map(|offset| (from + offset) % pages.len())
+            .flat_map(|index| pages[index].search(&text, devices).into_iter().map(move |found| (index, found)))
+            .collect();
+        Self {
+            text,
+            found,
+            position: 0
+        }
+    }
+    pub fn current(&self) -> Option<&(usize, Found)> {
+        self.found.get(self.position)
+    }
+    pub fn next(&mut self) {
+        if !self.found.is_empty() {
+            self.position = (self.position + 1) % self.found.len();
+        }
+    }
+    pub fn previous(&mut self) {
+        if !self.found.is_empty() {
+            self.position = (self.position + self.found.len() - 1) % self.found.len();
+        }
+    }
+}
+
+/// Whether text contains search text, ignoring case
+fn contains(text: &str, search: &str) -> bool {
+    text.to_lowercase().contains(&search.to_lowercase())
+}
 
 pub trait Page {
     fn name(&self) -> &'static str;
+    /// An abbreviated name for narrow terminals
+    fn short_name(&self) -> &'static str;
     fn render(&mut self, frame: &mut Frame<ui::Backend>, area: Rect, devices: &mut DeviceList);
+    /// Called for every frame captured, whether or not the page is shown
+    fn captured(&mut self, entry: &LogEntry);
+    /// Called for keys not already bound to an action
+    fn key(&mut self, key: Key);
+    /// The keys this page handles itself, by name, with what each does, for the help overlay
+    fn keys(&self) -> Vec<(&'static str, &'static str)>;
+    /// True while the page wants every key, such as when text is being entered
+    fn grabs_input(&self) -> bool;
+    /// Remember settings the operator has chosen for the next run
+    fn save(&self, state: &mut State);
+    fn restore(&mut self, state: &State);
+    /// Forget everything gathered from captured frames, keeping the operator's settings
+    fn reset(&mut self);
+    /// Keep rows where they are while the display is frozen, though what they show is still brought up to date
+    fn freeze(&mut self, frozen: bool);
+    /// The device picked out on this page, if it shows devices
+    fn selected(&self) -> Option<MacAddress>;
+    /// Everything on this page matching search text, in the order it is listed, or nothing if the page can't be searched
+    fn search(&self, text: &str, devices: &DeviceList) -> Vec<Found>;
+    /// Pick out something a search of this page turned up
+    fn select(&mut self, found: &Found);
+    /// The page the last key asked to be taken to, given once
+    fn navigate(&mut self) -> Option<Navigation>;
     fn up(&mut self);
     fn down(&mut self);
     fn top(&mut self);
@@ -0,0 +1,153 @@
+use std::{cell::RefCell, rc::Rc};
+use eui48::MacAddress;
+use tui::{
+    layout::Rect,
+    widgets::{Block, Borders, List, ListItem},
+    style::{Style, Modifier, Color},
+    text::{Span, Spans},
+    terminal::Frame
+};
+
+use super::{Found, LogEntry, Navigation, Page};
+use crate::{DeviceList, alerts::{self, Severity}, state::State, ui::{self, Key}};
+
+/// Colour an alert by how severe it is
+pub fn severity_colour(severity: Severity) -> Color {
+    match severity {
+        Severity::Info => Color::LightCyan,
+        Severity::Warning => Color::LightYellow,
+        Severity::Critical => Color::LightRed
+    }
+}
+
+/// Everything raised so far, newest last, where alerts are acknowledged or dismissed
+pub struct Alerts {
+    /// Shared with the capture loop, which raises them
+    alerts: Rc<RefCell<alerts::Alerts>>,
+    state: ui::ListState
+}
+impl Alerts {
+    pub fn new(alerts: Rc<RefCell<alerts::Alerts>>) -> Self {
+        Self {
+            alerts,
+            state: Default::default()
+        }
+    }
+}
+impl Page for Alerts {
+    fn name(&self) -> &'static str {
+        "Alerts"
+    }
+    fn short_name(&self) -> &'static str {
+        "Alr"
+    }
+
+    fn render(&mut self, frame: &mut Frame<ui::Backend>, area: Rect, _: &mut DeviceList) {
+        let alerts = self.alerts.borrow();
+        let items: Vec<_> = alerts.alerts.iter().map(|alert| {
+            let style = if alert.acknowledged {
+                Style::reset().add_modifier(Modifier::DIM)
+            } else {
+                Style::reset()
+            };
+            ListItem::new(Spans::from(vec![
+                Span::styled(format!("{:>9.3} ", alert.time.as_secs_f64()), style),
+                Span::styled(format!("{:<8} ", alert.severity), style.fg(severity_colour(alert.severity))),
+                Span::styled(format!("{} ", alert.address.to_hex_string()), style.fg(Color::LightGreen)),
+                Span::styled(alert.message.as_str(), style),
+                Span::styled(if alert.escalated { " (escalated)" } else { "" }, style.fg(Color::LightRed)),
+                Span::styled(if alert.acknowledged { " (acknowledged)" } else { "" }, style)
+            ]))
+        }).collect();
+        let (unhandled, _) = alerts.unhandled();
+
+        self.state.set_item_count(items.len());
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(format!(
+                "Alerts ({} unhandled, Enter to acknowledge, Backspace to dismiss)",
+                unhandled
+            )))
+            .highlight_style(Style::default().bg(Color::Reset).add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.state)
+    }
+
+    fn captured(&mut self, _: &LogEntry) {
+
+    }
+    fn key(&mut self, key: Key) {
+        let index = match self.state.selected() {
+            Some(index) => index,
+            None => return
+        };
+        let mut alerts = self.alerts.borrow_mut();
+        match key {
+            Key::Char('\n') => {
+                let acknowledged = alerts.alerts.get(index).map_or(false, |alert| alert.acknowledged);
+                alerts.set_acknowledged(index, !acknowledged)
+            }
+            Key::Backspace | Key::Delete => alerts.dismiss(index),
+            _ => ()
+        }
+    }
+    fn keys(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("Enter", "Acknowledge the selected alert, or take it back"),
+            ("Backspace", "Dismiss the selected alert")
+        ]
+    }
+    fn grabs_input(&self) -> bool {
+        false
+    }
+    fn save(&self, state: &mut State) {
+        let mut acknowledged: Vec<_> = self.alerts.borrow().acknowledged.iter().cloned().collect();
+        acknowledged.sort();
+        state.set("alerts.acknowledged", acknowledged.join(","));
+    }
+    fn restore(&mut self, state: &State) {
+        if let Some(acknowledged) = state.get("alerts.acknowledged") {
+            self.alerts.borrow_mut().acknowledged = acknowledged.split(',')
+                .filter(|key| !key.is_empty())
+                .map(String::from)
+                .collect();
+        }
+    }
+    fn reset(&mut self) {
+        self.alerts.borrow_mut().reset();
+        self.state.top()
+    }
+    fn freeze(&mut self, _: bool) {
+
+    }
+    fn selected(&self) -> Option<MacAddress> {
+        let index = self.state.selected()?;
+        self.alerts.borrow().alerts.get(index).map(|alert| alert.address)
+    }
+    fn search(&self, _: &str, _: &DeviceList) -> Vec<Found> {
+        vec![]
+    }
+    fn select(&mut self, _: &Found) {
+        
+    }
+    fn navigate(&mut self) -> Option<Navigation> {
+        None
+    }
+
+    fn up(&mut self) {
+        self.state.up()
+    }
+    fn top(&mut self) {
+        self.state.top()
+    }
+    fn down(&mut self) {
+        self.state.down()
+    }
+    fn bottom(&mut self) {
+        self.state.bottom()
+    }
+    fn left(&mut self) {
+
+    }
+    fn right(&mut self) {
+
+    }
+}
@@ -0,0 +1,166 @@
+use eui48::MacAddress;
+use tui::{
+    layout::Rect,
+    widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
+    style::{Style, Modifier, Color},
+    text::{Span, Spans},
+    terminal::Frame
+};
+
+use super::{Found, LogEntry, Navigation, Page};
+use crate::{DeviceList, bluetooth::{self, AddressKind}, state::State, ui::{self, Key}};
+
+/// Bluetooth Classic and low energy devices heard advertising, alongside the Wi-Fi devices
+pub struct Bluetooth {
+    /// None unless scanning was asked for with --bluetooth
+    scanner: Option<bluetooth::Bluetooth>,
+    state: ui::ListState,
+    /// List the strongest first rather than in order of address
+    by_signal: bool
+}
+impl Bluetooth {
+    pub fn new(scanner: Option<bluetooth::Bluetooth>) -> Self {
+        Self {
+            scanner,
+            state: Default::default(),
+            by_signal: false
+        }
+    }
+}
+impl Page for Bluetooth {
+    fn name(&self) -> &'static str {
+        "Bluetooth"
+    }
+    fn short_name(&self) -> &'static str {
+        "BT"
+    }
+
+    fn render(&mut self, frame: &mut Frame<ui::Backend>, area: Rect, _: &mut DeviceList) {
+        let scanner = match &self.scanner {
+            Some(scanner) => scanner,
+            None => {
+                let help = Paragraph::new("Start with --bluetooth to scan for Bluetooth devices through BlueZ, which needs blockade-recon to be built with --features bluetooth")
+                    .wrap(Wrap { trim: true })
+                    .block(Block::default().borders(Borders::ALL).title("Bluetooth"));
+                return frame.render_widget(help, area)
+            }
+        };
+        let error = scanner.error.lock().ok().and_then(|error| error.clone());
+        let devices = match scanner.devices.lock() {
+            Ok(devices) => devices.clone(),
+            Err(_) => return
+        };
+        let mut sorted: Vec<_> = devices.iter().collect();
+        if self.by_signal {
+            sorted.sort_by_key(|(_, device)| std::cmp::Reverse(device.advertisement.rssi));
+        }
+        let latest = devices.values().map(|device| device.last_seen).max().unwrap_or_default();
+        let items: Vec<_> = sorted.iter()
+            .map(|(address, device)| {
+                let advertisement = &device.advertisement;
+                let (kind, colour) = match advertisement.kind {
+                    AddressKind::Classic => ("classic", Color::LightBlue),
+                    AddressKind::Public => ("public", Color::LightGreen),
+                    AddressKind::Random => ("random", Color::Gray)
+                };
+                let mut spans = vec![
+                    Span::styled(address.to_hex_string(), Style::reset().fg(Color::LightYellow)),
+                    Span::styled(format!(" [{}]", kind), Style::reset().fg(colour))
+                ];
+                if let Some(name) = &advertisement.name {
+                    spans.push(Span::styled(format!(" {:?}", name), Style::reset().fg(Color::LightCyan)));
+                }
+                let manufacturers = device.manufacturers();
+                if !manufacturers.is_empty() {
+                    spans.push(Span::raw(format!(" {}", manufacturers.join(", "))));
+                }
+                if let Some(rssi) = advertisement.rssi {
+                    spans.push(Span::styled(format!(" {}dBm", rssi), Style::reset().fg(Color::LightMagenta)));
+                }
+                let mut details = vec![];
+                if let Some(tx_power) = advertisement.tx_power {
+                    details.push(format!("transmitting at {}dBm", tx_power));
+                }
+                if advertisement.services > 0 {
+                    details.push(format!("{} service(s)", advertisement.services));
+                }
+                details.push(format!("{} update(s)", device.updates));
+                details.push(format!("last heard {}s ago", latest.saturating_sub(device.last_seen).as_secs()));
+                spans.push(Span::styled(format!(" ({})", details.join(", ")), Style::reset().add_modifier(Modifier::DIM)));
+                ListItem::new(Spans::from(spans))
+            })
+            .collect();
+        self.state.set_item_count(items.len());
+        let title = match error {
+            Some(error) => format!("Bluetooth Devices ({}), scanning stopped: {}", devices.len(), error),
+            None => format!("Bluetooth Devices ({}){}", devices.len(), if self.by_signal { ", strongest first" } else { "" })
+        };
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(Style::default().bg(Color::Reset).add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(list, area, &mut self.state)
+    }
+
+    fn captured(&mut self, _: &LogEntry) {
+
+    }
+    fn key(&mut self, key: Key) {
+        if key == Key::Char('o') {
+            self.by_signal = !self.by_signal
+        }
+    }
+    fn keys(&self) -> Vec<(&'static str, &'static str)> {
+        vec![("o", "Order by signal strength, or by address")]
+    }
+    fn grabs_input(&self) -> bool {
+        false
+    }
+    fn save(&self, state: &mut State) {
+        state.set("bluetooth.by_signal", self.by_signal);
+    }
+    fn restore(&mut self, state: &State) {
+        self.by_signal = state.flag("bluetooth.by_signal").unwrap_or(self.by_signal);
+    }
+    fn reset(&mut self) {
+        if let Some(mut devices) = self.scanner.as_ref().and_then(|scanner| scanner.devices.lock().ok()) {
+            devices.clear();
+        }
+        self.state.restart()
+    }
+    fn freeze(&mut self, _: bool) {
+
+    }
+    /// Bluetooth devices aren't heard by the Wi-Fi capture, so none can be tracked or extracted
+    fn selected(&self) -> Option<MacAddress> {
+        None
+    }
+    fn search(&self, _: &str, _: &DeviceList) -> Vec<Found> {
+        vec![]
+    }
+    fn select(&mut self, _: &Found) {
+        
+    }
+    fn navigate(&mut self) -> Option<Navigation> {
+        None
+    }
+
+    fn up(&mut self) {
+        self.state.up()
+    }
+    fn top(&mut self) {
+        self.state.top()
+    }
+    fn down(&mut self) {
+        self.state.down()
+    }
+    fn bottom(&mut self) {
+        self.state.bottom()
+    }
+    fn left(&mut self) {
+
+    }
+    fn right(&mut self) {
+
+    }
+}
@@ -0,0 +1,280 @@
+use std::{cell::RefCell, rc::Rc, time::Instant};
+use eui48::MacAddress;
+use tui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    widgets::{Block, Borders, Paragraph, Sparkline},
+    style::{Style, Modifier, Color},
+    text::{Span, Spans},
+    terminal::Frame
+};
+
+use super::{Found, LogEntry, Navigation, Page, devices::{SIGNAL_FLOOR, format_age, signal_columns}};
+use crate::{DeviceList, Role, SIGNAL_HISTORY, state::State, ui::{self, Key}};
+
+/// Everything known about one device on a page of its own, opened with Enter on the Devices tab
+pub struct DeviceDetail {
+    /// Shared with the capture loop, which sets it when another page asks for a device's page
+    device: Rc<RefCell<Option<MacAddress>>>,
+    /// The device shown as of the last render, to start again from the top when it changes
+    showing: Option<MacAddress>,
+    /// When the capture started, which sighting times count from
+    started: Instant,
+    /// Lines scrolled past
+    scroll: u16,
+    /// The tab the page was opened from, when asked to go back with Backspace, until it is taken to
+    navigation: Option<Navigation>
+}
+impl DeviceDetail {
+    pub fn new(started: Instant, device: Rc<RefCell<Option<MacAddress>>>) -> Self {
+        Self {
+            device,
+            showing: None,
+            started,
+            scroll: 0,
+            navigation: None
+        }
+    }
+}
+impl Page for DeviceDetail {
+    fn name(&self) -> &'static str {
+        "Device"
+    }
+    fn short_name(&self) -> &'static str {
+        "Dev"
+    }
+
+    fn render(&mut self, frame: &mut Frame<ui::Backend>, area: Rect, devices: &mut DeviceList) {
+        fn header(title: &str) -> Spans {
+            Spans::from(vec![Span::styled(title, Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD))])
+        }
+        fn address(address: &MacAddress) -> Span<'static> {
+            Span::styled(address.to_hex_string(), Style::reset().fg(Color::LightCyan))
+        }
+        fn ssid(ssid: &str) -> Span {
+            Span::styled(format!("{:?}", ssid), Style::reset().fg(Color::LightCyan))
+        }
+
+        let showing = *self.device.borrow();
+        if showing != self.showing {
+            self.showing = showing;
+            self.scroll = 0;
+        }
+        let (mac, device) = match showing.and_then(|mac| Some((mac, devices.get(&mac)?))) {
+            Some(shown) => shown,
+            None => {
+                let help = Paragraph::new("Select a device on the Devices tab and press Enter to see everything known about it")
+                    .block(Block::default().borders(Borders::ALL).title("Device"));
+                return frame.render_widget(help, area)
+            }
+        };
+        let now = self.started.elapsed();
+        let mut lines = vec![];
+
+        lines.push(header("Device"));
+        lines.push(Spans::from(format!(
+            "  Manufacturer: {}",
+            device.manufacturer.as_ref().map_or("Unknown", |entry| entry.name_long.as_deref().unwrap_or(&entry.name_short))
+        )));
+        if let Some(identity) = &device.identity {
+            lines.push(Spans::from(format!("  Identified as: {}", identity)));
+        }
+        lines.push(Spans::from(match (device.role, device.associated) {
+            (Some(Role::AccessPoint), _) => vec![Span::raw("  Access point")],
+            (Some(Role::Station), Some(bssid)) => vec![Span::raw("  Client of "), address(&bssid)],
+            (Some(Role::Station), None) => vec![Span::raw("  Client, not seen associated")],
+            (None, _) => vec![Span::raw("  Role not known")]
+        }));
+        if !device.tags.is_empty() {
+            lines.push(Spans::from(format!("  Tags: {}", device.tags.iter().cloned().collect::<Vec<_>>().join(", "))));
+        }
+
+        lines.push(header("Seen"));
+        if let (Some(first), Some(last)) = (device.first_seen, device.last_seen) {
+            lines.push(Spans::from(format!("  First: {}s into the capture, {} ago", first.as_secs(), format_age(now.saturating_sub(first)))));
+            lines.push(Spans::from(format!("  Last: {}s into the capture, {} ago", last.as_secs(), format_age(now.saturating_sub(last)))));
+        } else {
+            lines.push(Spans::from("  Never heard transmitting"));
+        }
+        if device.presence.len() > 1 {
+            lines.push(Spans::from(format!("  Visits: {}", device.presence.len())));
+        }
+        let mut sensors: Vec<_> = device.sensors.iter().collect();
+        sensors.sort_by(|(l, _), (r, _)| l.cmp(r));
+        for (sensor, sighting) in sensors {
+            lines.push(Spans::from(format!(
+                "  By {}: {} frame(s){}{}",
+                sensor,
+                sighting.frames,
+                sighting.signal.map_or_else(String::new, |signal| format!(", {}dBm", signal)),
+                sighting.channel().map_or_else(String::new, |channel| format!(" on channel {}", channel))
+            )));
+        }
+
+        let mut ssids = vec![];
+        if let Some(beacon) = device.beacon.as_deref() {
+            ssids.push(Spans::from(if beacon.is_empty() {
+                vec![Span::raw("  Advertises "), Span::styled("(hidden)", Style::reset().fg(Color::LightYellow))]
+            } else {
+                vec![Span::raw("  Advertises "), ssid(beacon)]
+            }));
+        }
+        for responded in device.responded.iter().filter(|responded| Some(responded.as_str()) != device.beacon.as_deref()) {
+            ssids.push(Spans::from(vec![Span::raw("  Answered for "), ssid(responded)]));
+        }
+        let mut probes: Vec<_> = device.probes.iter().collect();
+        probes.sort_by(|(l_ssid, l_count), (r_ssid, r_count)| r_count.cmp(l_count).then(l_ssid.cmp(r_ssid)));
+        for (probe, count) in probes {
+            ssids.push(Spans::from(vec![
+                Span::raw("  Probed for "),
+                if probe.is_empty() { Span::raw("any network") } else { ssid(probe) },
+                Span::raw(format!(" {} time(s)", count))
+            ]));
+        }
+        if !ssids.is_empty() {
+            lines.push(header("SSIDs"));
+            lines.extend(ssids);
+        }
+
+        if device.role == Some(Role::AccessPoint) {
+            let mut clients: Vec<_> = devices.iter().filter(|(_, client)| client.associated == Some(mac)).collect();
+            clients.sort_by_key(|(client, _)| **client);
+            let unheard: Vec<_> = device.power_save.unheard().collect();
+            lines.push(header("Clients"));
+            if clients.is_empty() && unheard.is_empty() {
+                lines.push(Spans::from("  None seen"));
+            }
+            for (client, known) in clients {
+                lines.push(Spans::from(vec![
+                    Span::raw("  "),
+                    address(client),
+                    Span::raw(known.last_seen.map_or_else(String::new, |last| format!(" last heard {} ago", format_age(now.saturating_sub(last)))))
+                ]));
+            }
+            // Only known from the access point holding frames for them while they doze
+            for (aid, time) in unheard {
+                lines.push(Spans::from(vec![
+                    Span::styled(format!("  AID {}", aid), Style::reset().fg(Color::LightYellow)),
+                    Span::raw(format!(" never heard, frames held for it {} ago", format_age(now.saturating_sub(time))))
+                ]));
+            }
+        } else if !device.mobility.roams.is_empty() {
+            lines.push(header("Access Points"));
+            for roam in device.mobility.roams.iter().rev() {
+                lines.push(Spans::from(vec![
+                    Span::raw("  "),
+                    address(&roam.from),
+                    Span::raw(" to "),
+                    address(&roam.to),
+                    Span::raw(format!(" {} ago", format_age(now.saturating_sub(roam.time))))
+                ]));
+            }
+        }
+
+        if !device.subtypes.is_empty() {
+            let mut subtypes: Vec<_> = device.subtypes.iter().collect();
+            subtypes.sort_by(|(l_name, l_count), (r_name, r_count)| r_count.cmp(l_count).then(l_name.cmp(r_name)));
+            lines.push(header("Frames Sent"));
+            for (subtype, count) in subtypes {
+                lines.push(Spans::from(format!("  {:<16} {}", subtype, count)));
+            }
+        }
+        let traffic = &device.traffic;
+        if traffic.bytes() > 0 {
+            lines.push(Spans::from(format!(
+                "  Data: {} sent, {} received",
+                ui::format_bytes(traffic.sent_bytes),
+                ui::format_bytes(traffic.received_bytes)
+            )));
+        }
+
+        let title = format!("{}{}", mac.to_hex_string(), device.manufacturer.as_ref().map_or_else(String::new, |entry| format!(" {}", entry.name_short)));
+        let strength = &device.signal_strength;
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(if strength.last.is_some() { 7 } else { 0 }), Constraint::Min(0)])
+            .split(area);
+        if let (Some(last), Some(lowest), Some(highest)) = (strength.last, strength.lowest, strength.highest) {
+            let columns = signal_columns(device, now, areas[0].width.saturating_sub(2) as usize);
+            frame.render_widget(
+                Sparkline::default()
+                    .block(Block::default().borders(Borders::ALL).title(format!(
+                        "Signal, last {} min (last {}dBm, {} to {}dBm{})",
+                        SIGNAL_HISTORY.as_secs() / 60,
+                        last,
+                        lowest,
+                        highest,
+                        strength.average().map_or_else(String::new, |average| format!(", averaging {}dBm", average))
+                    )))
+                    .style(Style::reset().fg(Color::LightGreen))
+                    .max(SIGNAL_FLOOR.unsigned_abs() as u64)
+                    .data(&columns),
+                areas[0]
+            );
+        }
+        // Kept from scrolling past the end as the lines shrink
+        self.scroll = self.scroll.min(lines.len().saturating_sub(1) as u16);
+        let details = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .scroll((self.scroll, 0));
+        frame.render_widget(details, areas[1])
+    }
+
+    fn captured(&mut self, _: &LogEntry) {
+
+    }
+    fn key(&mut self, key: Key) {
+        if key == Key::Backspace {
+            self.navigation = Some(Navigation::Back)
+        }
+    }
+    fn keys(&self) -> Vec<(&'static str, &'static str)> {
+        vec![("Backspace", "Go back to the tab the device was picked on")]
+    }
+    fn grabs_input(&self) -> bool {
+        false
+    }
+    fn save(&self, _: &mut State) {
+
+    }
+    fn restore(&mut self, _: &State) {
+
+    }
+    fn reset(&mut self) {
+        self.scroll = 0
+    }
+    fn freeze(&mut self, _: bool) {
+
+    }
+    fn selected(&self) -> Option<MacAddress> {
+        self.showing
+    }
+    fn search(&self, _: &str, _: &DeviceList) -> Vec<Found> {
+        vec![]
+    }
+    fn select(&mut self, _: &Found) {
+
+    }
+    fn navigate(&mut self) -> Option<Navigation> {
+        self.navigation.take()
+    }
+
+    fn up(&mut self) {
+        self.scroll = self.scroll.saturating_sub(1)
+    }
+    fn down(&mut self) {
+        self.scroll = self.scroll.saturating_add(1)
+    }
+    fn top(&mut self) {
+        self.scroll = 0
+    }
+    fn bottom(&mut self) {
+        // Brought back to the last line when next drawn
+        self.scroll = u16::MAX
+    }
+    fn left(&mut self) {
+
+    }
+    fn right(&mut self) {
+
+    }
+}
@@ -1,88 +1,876 @@
+use std::{cell::RefCell, cmp::Ordering, collections::{HashMap, HashSet}, rc::Rc, time::{Duration, Instant}};
+use eui48::MacAddress;
 use oui::{OuiEntry};
-use termion::{input::MouseTerminal, raw::RawTerminal, screen::AlternateScreen};
 use tui::{
-    backend::TermionBackend,
-    layout::{Rect, Constraint, Direction, Layout},
-    widgets::{Paragraph, Block, Borders, List, ListItem},
+    layout::{Constraint, Direction, Layout, Rect},
+    widgets::{Paragraph, Block, Borders, Cell, Clear, Row, Sparkline, Table, TableState},
     style::{Style, Modifier, Color},
     text::{Span, Spans},
     terminal::Frame
 };
 
-use super::Page;
-use crate::{DeviceList, ui};
+use super::{Found, LogEntry, Navigation, Page};
+use crate::{DeviceList, KnownDevice, Role, SIGNAL_HISTORY, Sighting, clusters, handshake_complete, fingerprint::{self, Fingerprint}, hotspot::Access, movement::Movement, privacy::Assessment, state::State, ui::{self, Key}};
 
 pub struct Devices {
-    device_state: ui::ListState
+    device_state: ui::ListState,
+    table_state: TableState,
+    /// The columns shown, in order, shared so they can be saved to the config file on the way out
+    columns: Rc<RefCell<Vec<Column>>>,
+    /// The column picked in the column chooser, while it is open
+    choosing: Option<usize>,
+    /// Every sensor seen so far, as of the last render
+    sensors: Vec<String>,
+    /// Only show devices heard by this sensor
+    sensor_filter: Option<String>,
+    /// Only show devices from this manufacturer, as picked on the Manufacturers tab
+    manufacturer: Option<String>,
+    /// A manufacturer picked on the Manufacturers tab, waiting to be filtered by
+    drill_down: Rc<RefCell<Option<String>>>,
+    /// Show the selected device's details beside the list
+    split: bool,
+    /// The device shown in the detail pane, as of the last render
+    selected: Option<MacAddress>,
+    /// Only show devices whose address starts with, or whose manufacturer or SSIDs contain, this text
+    search: Option<String>,
+    /// The search being typed, while editing
+    editing: Option<String>,
+    /// When the capture started, which sighting times count from
+    started: Instant,
+    /// Devices not heard for this long are dimmed, or hidden while only showing active devices
+    active_window: Duration,
+    only_active: bool,
+    /// What the list is ordered by, and whether the largest come first
+    sort: SortKey,
+    descending: bool,
+    /// Show each probable device, made up of randomised addresses, as a single row
+    clustered: bool,
+    /// Probable devices whose addresses are listed in full, by their first address
+    expanded: HashSet<MacAddress>,
+    /// The probable device the selected row belongs to, by its first address, as of the last render
+    selected_cluster: Option<MacAddress>,
+    /// The device on the selected row, whether or not its details are shown, as of the last render
+    highlighted: Option<MacAddress>,
+    /// A device picked to be merged with the next one picked
+    marked: Option<MacAddress>,
+    /// Merges and splits made here, shared with the device list so exports follow them
+    overrides: Rc<RefCell<clusters::Overrides>>,
+    /// The order of the rows when the display was frozen, taken at the first render after, or None while it isn't
+    frozen: Option<Vec<MacAddress>>,
+    /// The device's own page, when asked for with Enter, until it is taken to
+    navigation: Option<Navigation>
 }
 impl Devices {
-    pub fn new() -> Self {
+    pub fn new(started: Instant, active_window: Duration, overrides: Rc<RefCell<clusters::Overrides>>, drill_down: Rc<RefCell<Option<String>>>, columns: Rc<RefCell<Vec<Column>>>) -> Self {
         Self {
-            device_state: Default::default()
+            device_state: Default::default(),
+            table_state: TableState::default(),
+            columns,
+            choosing: None,
+            sensors: vec![],
+            sensor_filter: None,
+            manufacturer: None,
+            drill_down,
+            split: true,
+            selected: None,
+            search: None,
+            editing: None,
+            started,
+            active_window,
+            only_active: false,
+            sort: SortKey::Address,
+            descending: false,
+            clustered: true,
+            expanded: HashSet::new(),
+            selected_cluster: None,
+            highlighted: None,
+            marked: None,
+            overrides,
+            frozen: None,
+            navigation: None
         }
     }
+    /// The devices the list is narrowed to, in order, before probable devices are grouped
+    fn listed<'a>(&self, devices: &'a DeviceList) -> Vec<(&'a MacAddress, &'a KnownDevice)> {
+        let now = self.started.elapsed();
+        let is_active = |device: &KnownDevice| device.last_seen.map_or(false, |last_seen| last_seen + self.active_window >= now);
+        let mut listed: Vec<_> = devices.iter()
+            .filter(|(_, device)| !self.only_active || is_active(device))
+            .filter(|(_, device)| self.sensor_filter.as_ref().map_or(true, |sensor| device.sensors.contains_key(sensor)))
+            .filter(|(_, device)| self.manufacturer.as_ref().map_or(true, |manufacturer| {
+                device.manufacturer.as_ref().map_or(false, |OuiEntry { name_short, .. }| name_short == manufacturer)
+            }))
+            .filter(|(address, device)| self.search.as_ref().map_or(true, |search| matches(search, address, device)))
+            .collect();
+        // In address order the radios of one unit, being numbered in sequence, end up next to each other
+        let (sort, descending) = (self.sort, self.descending);
+        listed.sort_by(|&(l_address, l), &(r_address, r)| {
+            let order = sort.compare((l_address, l), (r_address, r));
+            if descending { order.reverse() } else { order }
+        });
+        listed
+    }
+    /// Step the sensor filter through no filter followed by each sensor in turn
+    fn cycle_sensor_filter(&mut self, forward: bool) {
+        let position = self.sensor_filter.as_ref().and_then(|filter| self.sensors.iter().position(|sensor| sensor == filter));
+        let count = self.sensors.len();
+        let next = match (position, forward) {
+            (None, true) => self.sensors.first(),
+            (None, false) => self.sensors.last(),
+            (Some(index), true) if index + 1 < count => self.sensors.get(index + 1),
+            (Some(index), false) if index > 0 => self.sensors.get(index - 1),
+            (Some(_), _) => None
+        };
+        self.sensor_filter = next.cloned();
+        self.device_state.restart()
+    }
+    /// Every column, those shown first in the order shown, and whether each is shown
+    fn choices(&self) -> Vec<(Column, bool)> {
+        let shown = self.columns.borrow();
+        shown.iter()
+            .map(|column| (*column, true))
+            .chain(Column::ALL.iter().filter(|column| !shown.contains(column)).map(|column| (*column, false)))
+            .collect()
+    }
+}
+
+/// What the device list can be ordered by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortKey {
+    Address,
+    Manufacturer,
+    Signal,
+    AverageSignal,
+    LastSeen,
+    Frames,
+    Traffic
+}
+impl SortKey {
+    const ALL: [Self; 7] = [Self::Address, Self::Manufacturer, Self::Signal, Self::AverageSignal, Self::LastSeen, Self::Frames, Self::Traffic];
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Address => "address",
+            Self::Manufacturer => "manufacturer",
+            Self::Signal => "signal",
+            Self::AverageSignal => "average signal",
+            Self::LastSeen => "last seen",
+            Self::Frames => "frames",
+            Self::Traffic => "traffic"
+        }
+    }
+    fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|key| key == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+    /// Counts and signal are most useful largest first, names in alphabetical order
+    fn descending_by_default(&self) -> bool {
+        !matches!(self, Self::Address | Self::Manufacturer)
+    }
+    fn compare(&self, (l_address, l): (&MacAddress, &KnownDevice), (r_address, r): (&MacAddress, &KnownDevice)) -> Ordering {
+        let signal = |device: &KnownDevice| device.strongest_sensor().and_then(|(_, sighting)| sighting.signal);
+        let frames = |device: &KnownDevice| device.sensors.values().map(|sighting| sighting.frames).sum::<u64>();
+        let manufacturer = |device: &KnownDevice| device.manufacturer.as_ref().map(|manufacturer| manufacturer.name_short.to_lowercase());
+        match self {
+            Self::Address => Ordering::Equal,
+            Self::Manufacturer => manufacturer(l).cmp(&manufacturer(r)),
+            Self::Signal => signal(l).cmp(&signal(r)),
+            Self::AverageSignal => l.signal_strength.average().cmp(&r.signal_strength.average()),
+            Self::LastSeen => l.last_seen.cmp(&r.last_seen),
+            Self::Frames => frames(l).cmp(&frames(r)),
+            Self::Traffic => l.traffic.bytes().cmp(&r.traffic.bytes())
+        }.then(l_address.cmp(r_address))
+    }
+}
+
+/// What the device table can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Address,
+    Manufacturer,
+    Ssids,
+    Signal,
+    SignalRange,
+    Channel,
+    Frames,
+    LastSeen,
+    Sensor,
+    Traffic,
+    Security,
+    Notes
+}
+impl Column {
+    pub const ALL: [Self; 12] = [
+        Self::Address, Self::Manufacturer, Self::Ssids, Self::Signal, Self::SignalRange, Self::Channel, Self::Frames,
+        Self::LastSeen, Self::Sensor, Self::Traffic, Self::Security, Self::Notes
+    ];
+    /// Shown until others are chosen
+    pub const DEFAULT: [Self; 10] = [
+        Self::Address, Self::Manufacturer, Self::Ssids, Self::Signal, Self::SignalRange, Self::Channel, Self::Frames,
+        Self::LastSeen, Self::Security, Self::Notes
+    ];
+    /// As given in the config file
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Address => "address",
+            Self::Manufacturer => "manufacturer",
+            Self::Ssids => "ssids",
+            Self::Signal => "signal",
+            Self::SignalRange => "signal-range",
+            Self::Channel => "channel",
+            Self::Frames => "frames",
+            Self::LastSeen => "last-seen",
+            Self::Sensor => "sensor",
+            Self::Traffic => "traffic",
+            Self::Security => "security",
+            Self::Notes => "notes"
+        }
+    }
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.trim().to_lowercase().replace('_', "-");
+        Self::ALL.iter().copied().find(|column| column.name() == name)
+    }
+    fn title(&self) -> &'static str {
+        match self {
+            Self::Address => "Address",
+            Self::Manufacturer => "Manufacturer",
+            Self::Ssids => "SSIDs",
+            Self::Signal => "Signal",
+            Self::SignalRange => "Min/Avg/Max",
+            Self::Channel => "Ch",
+            Self::Frames => "Frames",
+            Self::LastSeen => "Seen",
+            Self::Sensor => "Sensor",
+            Self::Traffic => "Traffic",
+            Self::Security => "Security",
+            Self::Notes => "Notes"
+        }
+    }
+    /// Room for an address with its unit and probable device markers, and for the widest of each count
+    fn width(&self, narrow: bool) -> Constraint {
+        match self {
+            Self::Address => Constraint::Length(25),
+            Self::Manufacturer => Constraint::Length(if narrow { 8 } else { 28 }),
+            Self::Ssids => Constraint::Min(12),
+            Self::Signal => Constraint::Length(7),
+            Self::SignalRange => Constraint::Length(12),
+            Self::Channel => Constraint::Length(3),
+            Self::Frames => Constraint::Length(7),
+            Self::LastSeen => Constraint::Length(4),
+            Self::Sensor => Constraint::Length(8),
+            Self::Traffic => Constraint::Length(15),
+            Self::Security => Constraint::Length(13),
+            Self::Notes => Constraint::Min(8)
+        }
+    }
+}
+
+/// How long ago something was, in its largest whole unit, eg. 12s or 3h
+pub(super) fn format_age(age: Duration) -> String {
+    match age.as_secs() {
+        seconds if seconds >= 86400 => format!("{}d", seconds / 86400),
+        seconds if seconds >= 3600 => format!("{}h", seconds / 3600),
+        seconds if seconds >= 60 => format!("{}m", seconds / 60),
+        seconds => format!("{}s", seconds)
+    }
+}
+
+/// Whether a device matches search text, ignoring case
+fn matches(search: &str, address: &MacAddress, device: &KnownDevice) -> bool {
+    let search = search.to_lowercase();
+    let contains = |text: &str| text.to_lowercase().contains(&search);
+    address.to_hex_string().starts_with(&search.replace('-', ":"))
+        || device.manufacturer.as_ref().map_or(false, |manufacturer| {
+            contains(&manufacturer.name_short) || manufacturer.name_long.as_deref().map_or(false, contains)
+        })
+        || device.beacon.as_deref().map_or(false, contains)
+        || device.identity.as_ref().map_or(false, |identity| contains(&identity.to_string()))
+        || device.hostnames.iter().any(|hostname| contains(hostname))
+        || device.probes.keys().any(|ssid| contains(ssid))
 }
 impl Page for Devices {
     fn name(&self) -> &'static str {
         "Devices"
     }
+    fn short_name(&self) -> &'static str {
+        "Dev"
+    }
 
-    fn render(&mut self, frame: &mut Frame<TermionBackend<AlternateScreen<MouseTerminal<RawTerminal<std::io::Stdout>>>>>, area: Rect, devices: &mut DeviceList) {
+    fn render(&mut self, frame: &mut Frame<ui::Backend>, area: Rect, devices: &mut DeviceList) {
         fn format_string(value: &str) -> Span {
             Span::styled(format!("{:?}", value), Style::reset().fg(Color::LightCyan))
         }
         fn format_header(title: &str) -> Spans {
             Spans::from(vec![Span::styled(title, Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD))])
         }
+        fn format_access(access: Access) -> Span<'static> {
+            let colour = match access {
+                Access::Secured => Color::Reset,
+                Access::Open | Access::Hotspot => Color::LightRed,
+                Access::Passpoint | Access::OpenRoaming => Color::LightMagenta
+            };
+            Span::styled(access.to_string(), Style::reset().fg(colour))
+        }
+        fn format_grade(assessment: &Assessment) -> Span<'static> {
+            let colour = match assessment.grade() {
+                'A' | 'B' => Color::LightGreen,
+                'C' => Color::LightYellow,
+                _ => Color::LightRed
+            };
+            Span::styled(assessment.grade().to_string(), Style::reset().fg(colour).add_modifier(Modifier::BOLD))
+        }
         
-        self.device_state.set_item_count(devices.len());
-        let device_list = List::new(
-            devices.iter().map(|(mac, device)| {
-                let mut spans = vec![];
-                let colour = if device.sent {
-                    Color::LightGreen
-                } else {
-                    Color::LightYellow
-                };
-                spans.push(Span::styled(mac.to_hex_string(), Style::reset().fg(colour)));
-                if let Some(OuiEntry { name_short, name_long, ..}) = &device.manufacturer {
-                    spans.push(Span::styled(format!(" | {:8} ", name_short), Style::reset()));
-                    if let Some(name_long) = name_long {
-                        spans.push(Span::styled(format!("{}", name_long), Style::reset().fg(Color::LightCyan)));
+        let (area, prompt_area) = if self.editing.is_some() {
+            let areas = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(0), Constraint::Length(3)])
+                .split(area);
+            (areas[0], Some(areas[1]))
+        } else {
+            (area, None)
+        };
+        if let (Some(prompt_area), Some(editing)) = (prompt_area, &self.editing) {
+            let prompt = Paragraph::new(Spans::from(vec![Span::raw(editing.as_str()), Span::styled(" ", Style::reset().add_modifier(Modifier::REVERSED))]))
+                .block(Block::default().borders(Borders::ALL).title("Filter by address prefix, manufacturer or SSID (Enter to apply, empty to clear)"));
+            frame.render_widget(prompt, prompt_area)
+        }
+
+        if let Some(manufacturer) = self.drill_down.borrow_mut().take() {
+            self.manufacturer = Some(manufacturer);
+            self.device_state.restart()
+        }
+        self.sensors = devices.sensors().into_iter().map(String::from).collect();
+        let narrow = ui::narrow(area);
+        // Only worth a column once there is more than one sensor to tell apart
+        let show_sensor = self.sensors.len() > 1 && !narrow;
+        let now = self.started.elapsed();
+        let active_window = self.active_window;
+        let is_active = |device: &KnownDevice| device.last_seen.map_or(false, |last_seen| last_seen + active_window >= now);
+        let mut visible = self.listed(devices);
+        let (sort, descending) = (self.sort, self.descending);
+        let show_traffic = !narrow && visible.iter().any(|(_, device)| device.traffic.bytes() > 0);
+        let units = devices.units();
+        let unit_of: HashMap<_, _> = units.iter()
+            .flat_map(|unit| unit.iter().enumerate().map(move |(index, address)| (*address, (index, unit))))
+            .collect();
+        let probable = clusters::probable_devices(devices);
+        let cluster_of: HashMap<_, _> = probable.iter()
+            .flat_map(|cluster| cluster.iter().map(move |address| (*address, cluster)))
+            .collect();
+        // The row standing in for each probable device, followed by the rest of its addresses when expanded
+        let mut heads = HashSet::new();
+        if self.clustered {
+            let shown: HashSet<_> = visible.iter().map(|(address, _)| **address).collect();
+            let mut ordered = vec![];
+            for &(address, device) in visible.iter() {
+                match cluster_of.get(address) {
+                    Some(cluster) => {
+                        let mut members = cluster.iter().filter(|member| shown.contains(member));
+                        if members.next() == Some(address) {
+                            heads.insert(*address);
+                            ordered.push((address, device));
+                            if self.expanded.contains(&cluster[0]) {
+                                ordered.extend(members.filter_map(|member| devices.get_key_value(member)));
+                            }
+                        }
                     }
+                    None => ordered.push((address, device))
                 }
-                ListItem::new(vec![
-                    Spans::from(spans)
-                ])
             }
-            ).collect::<Vec<_>>()
+            visible = ordered;
+        }
+        // Rows stay where they were when frozen, with devices turning up since listed after them in the usual order
+        if let Some(order) = &mut self.frozen {
+            if order.is_empty() {
+                order.extend(visible.iter().map(|(address, _)| **address));
+            }
+            let position: HashMap<_, _> = order.iter().enumerate().map(|(position, address)| (*address, position)).collect();
+            visible.sort_by_key(|(address, _)| position.get(*address).copied().unwrap_or(usize::MAX));
+        }
+
+        self.device_state.set_item_count(visible.len());
+        let rows: Vec<_> = visible.iter().map(|(address, _)| Some(**address)).collect();
+        self.device_state.follow(&rows, || super::newest(&rows, devices));
+        // Sensors and traffic are only worth room once there is something to tell apart
+        let columns: Vec<_> = self.columns.borrow().iter()
+            .copied()
+            .filter(|column| match column {
+                Column::Sensor => show_sensor,
+                Column::Traffic => show_traffic,
+                _ => true
+            })
+            .collect();
+        let widths: Vec<_> = columns.iter().map(|column| column.width(narrow)).collect();
+        let device_table = Table::new(
+            visible.iter().map(|(mac, device)| {
+                let strongest = device.strongest_sensor();
+                let cells = columns.iter().map(|column| {
+                    let mut spans = match column {
+                        Column::Address => {
+                            let colour = if device.sent {
+                                Color::LightGreen
+                            } else if device.inferred() {
+                                Color::LightMagenta
+                            } else {
+                                Color::LightYellow
+                            };
+                            let mut spans = vec![Span::styled(mac.to_hex_string(), Style::reset().fg(colour))];
+                            if device.inferred() {
+                                spans.push(Span::styled("?", Style::reset().fg(colour).add_modifier(Modifier::BOLD)));
+                            }
+                            spans.push(Span::styled(match unit_of.get(mac).filter(|_| sort == SortKey::Address && !descending) {
+                                Some((0, _)) => " ┐",
+                                Some((index, unit)) if index + 1 == unit.len() => " ┘",
+                                Some(_) => " │",
+                                None => "  "
+                            }, Style::reset().fg(Color::LightMagenta)));
+                            if let (Some(cluster), true) = (cluster_of.get(mac), self.clustered) {
+                                spans.push(Span::styled(if heads.contains(*mac) {
+                                    format!("[{}{}]", if self.expanded.contains(&cluster[0]) { '-' } else { '+' }, cluster.len())
+                                } else {
+                                    " ↳ ".to_string()
+                                }, Style::reset().fg(Color::LightBlue)));
+                            }
+                            spans
+                        }
+                        Column::Manufacturer => match &device.manufacturer {
+                            Some(OuiEntry { name_short, name_long, .. }) => {
+                                let mut spans = vec![Span::styled(name_short.clone(), Style::reset())];
+                                if let (Some(name_long), false) = (name_long, narrow) {
+                                    spans.push(Span::styled(format!(" {}", name_long), Style::reset().fg(Color::LightCyan)));
+                                }
+                                spans
+                            }
+                            None => vec![]
+                        },
+                        // What an access point calls its network, or the networks a client has asked for
+                        Column::Ssids => match &device.beacon {
+                            Some(ssid) if ssid.is_empty() => vec![Span::styled("hidden", Style::reset().fg(Color::DarkGray))],
+                            Some(ssid) => vec![format_string(ssid)],
+                            None => {
+                                let mut probes: Vec<_> = device.probes.keys().filter(|ssid| !ssid.is_empty()).map(|ssid| format!("{:?}", ssid)).collect();
+                                probes.sort();
+                                vec![Span::styled(probes.join(" "), Style::reset().fg(Color::LightCyan))]
+                            }
+                        },
+                        Column::Signal => match strongest {
+                            Some((_, Sighting { signal: Some(signal), .. })) => vec![Span::raw(format!("{:>4}dBm", signal))],
+                            _ => vec![]
+                        },
+                        Column::SignalRange => match (device.signal_strength.lowest, device.signal_strength.average(), device.signal_strength.highest) {
+                            (Some(lowest), Some(average), Some(highest)) => vec![Span::raw(format!("{:>4}/{:>3}/{:>3}", lowest, average, highest))],
+                            _ => vec![]
+                        },
+                        Column::Channel => strongest.and_then(|(_, sighting)| sighting.channel())
+                            .map_or_else(Vec::new, |channel| vec![Span::raw(format!("{:>3}", channel))]),
+                        Column::Frames => vec![Span::raw(format!("{:>7}", device.sensors.values().map(|sighting| sighting.frames).sum::<u64>()))],
+                        Column::LastSeen => device.last_seen
+                            .map_or_else(Vec::new, |last_seen| vec![Span::raw(format!("{:>4}", format_age(now.saturating_sub(last_seen))))]),
+                        Column::Sensor => strongest.map_or_else(Vec::new, |(sensor, _)| vec![Span::raw(sensor.to_string())]),
+                        Column::Traffic => vec![Span::styled(
+                            format!("↑{:>6} ↓{:>6}", ui::format_bytes(device.traffic.sent_bytes), ui::format_bytes(device.traffic.received_bytes)),
+                            Style::reset().fg(if device.traffic.bytes() > 0 { Color::LightCyan } else { Color::DarkGray })
+                        )],
+                        Column::Security => {
+                            let mut spans = vec![];
+                            if let Some(assessment) = Assessment::of(**mac, device) {
+                                spans.push(format_grade(&assessment));
+                            }
+                            if let Some(access) = device.access.filter(|access| access.notable()) {
+                                spans.push(Span::raw(" "));
+                                spans.push(format_access(access));
+                            }
+                            spans
+                        }
+                        Column::Notes => {
+                            let mut spans = vec![];
+                            if let Some(identity) = &device.identity {
+                                spans.push(Span::styled(format!("{} ", identity), Style::reset().fg(Color::LightMagenta)));
+                            }
+                            if let Some(watch) = &device.watched {
+                                spans.push(Span::styled(format!("watched: {}", watch), Style::reset().fg(Color::LightRed).add_modifier(Modifier::BOLD)));
+                            }
+                            spans
+                        }
+                    };
+                    if !is_active(device) {
+                        for span in spans.iter_mut() {
+                            span.style = span.style.add_modifier(Modifier::DIM);
+                        }
+                    }
+                    Cell::from(Spans::from(spans))
+                });
+                Row::new(cells)
+            }).collect::<Vec<_>>()
         )
-            .block(Block::default().borders(Borders::ALL).title("Devices"))
+            .header(Row::new(columns.iter().map(|column| column.title())).style(Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD)))
+            .widths(&widths)
+            .block(Block::default().borders(Borders::ALL).title(format!(
+                "{}{}{}{}{}{}",
+                match (&self.sensor_filter, &self.search) {
+                    (Some(sensor), Some(search)) => format!("Devices heard by {} matching {:?}", sensor, search),
+                    (Some(sensor), None) => format!("Devices heard by {}", sensor),
+                    (None, Some(search)) => format!("Devices matching {:?}", search),
+                    (None, None) => "Devices".to_string()
+                },
+                self.manufacturer.as_ref().map_or_else(String::new, |manufacturer| format!(", made by {}, Backspace for every maker", manufacturer)),
+                if self.only_active { format!(", active in the last {}s", active_window.as_secs()) } else { String::new() },
+                match (sort, descending) {
+                    (SortKey::Address, false) => String::new(),
+                    (sort, descending) => format!(", by {} {}", sort.name(), if descending { "↓" } else { "↑" })
+                },
+                super::following(&self.device_state),
+                self.marked.map_or_else(String::new, |marked| format!(", m on another device to merge it with {}", marked.to_hex_string()))
+            )))
             .highlight_style(Style::default().bg(Color::Reset).add_modifier(Modifier::REVERSED))
             .highlight_symbol("> ");
+        // Kept between renders so the table scrolls no more than it needs to
+        self.table_state.select(self.device_state.selected());
         
-        if let Some((device_mac, device)) = devices.iter().nth(self.device_state.selected().unwrap()) {
-            let areas = Layout::default()
-                .direction(Direction::Horizontal)
-                .margin(0)
-                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-                .split(area);
+        self.selected_cluster = visible.get(self.device_state.selected().unwrap())
+            .and_then(|(address, _)| cluster_of.get(address))
+            .map(|cluster| cluster[0]);
+        self.highlighted = visible.get(self.device_state.selected().unwrap()).map(|(address, _)| **address);
+        let selected = visible.get(self.device_state.selected().unwrap()).filter(|_| self.split);
+        self.selected = selected.map(|(address, _)| **address);
+        if let Some((device_mac, device)) = selected {
             let mut device_info = vec![];
 
-            if !device.sent {
+            if device.inferred() {
+                device_info.push(Spans::from(vec![
+                    Span::styled("Inferred, never heard transmitting", Style::default().fg(Color::LightMagenta).add_modifier(Modifier::BOLD))
+                ]));
+                device_info.push(Spans::from(format!("  {} ACK(s) answered frames it sent out of range", device.unanswered_acks)));
+                if let Some(peer) = device.ack_peer {
+                    device_info.push(Spans::from(vec![
+                        Span::raw("  Most likely acknowledged by "),
+                        Span::styled(peer.to_hex_string(), Style::reset().fg(Color::LightCyan))
+                    ]));
+                }
+            } else if !device.sent {
                 device_info.push(Spans::from(vec![
                     Span::styled("Known by reference from other devices only", Style::default().fg(Color::LightYellow).add_modifier(Modifier::BOLD))
                 ]));
                 
             }
-            if let Some(ssid) = &device.beacon {
-                device_info.push(format_header("Beacon"));
+            if let Some(role) = device.role {
+                device_info.push(format_header("Role"));
+                device_info.push(Spans::from(match (role, device.associated) {
+                    (Role::AccessPoint, _) => vec![Span::raw("  Access point")],
+                    (Role::Station, Some(bssid)) => vec![
+                        Span::raw("  Client of "),
+                        Span::styled(bssid.to_hex_string(), Style::reset().fg(Color::LightCyan))
+                    ],
+                    (Role::Station, None) => vec![Span::raw("  Client, not seen associated")]
+                }));
+            }
+            if let Some((from, to)) = device.channel_switch {
+                device_info.push(format_header("Channel Switch"));
                 device_info.push(Spans::from(vec![
-                    Span::raw("  SSID: "),
-                    format_string(ssid)
+                    Span::raw(from.map_or_else(|| "  Announced a move to channel ".to_string(), |from| format!("  Announced a move from channel {} to ", from))),
+                    Span::styled(to.to_string(), Style::reset().fg(Color::LightYellow).add_modifier(Modifier::BOLD))
                 ]));
+            }
+            if let Some(movement) = device.mobility.movement {
+                device_info.push(format_header("Movement"));
+                device_info.push(Spans::from(vec![
+                    Span::raw("  "),
+                    Span::styled(movement.to_string(), Style::reset().fg(match movement {
+                        Movement::Moving => Color::LightYellow,
+                        Movement::Stationary => Color::LightGreen
+                    }).add_modifier(Modifier::BOLD)),
+                    Span::raw(format!(", {}", device.mobility.reason))
+                ]));
+            }
+            if !device.mobility.roams.is_empty() {
+                device_info.push(format_header("Roams"));
+                device_info.push(Spans::from(format!("  {} between access points, latest first", device.mobility.roamed)));
+                for roam in device.mobility.roams.iter().rev().take(5) {
+                    device_info.push(Spans::from(vec![
+                        Span::raw("  "),
+                        Span::styled(roam.from.to_hex_string(), Style::reset().fg(Color::LightCyan)),
+                        Span::raw(" to "),
+                        Span::styled(roam.to.to_hex_string(), Style::reset().fg(Color::LightCyan)),
+                        Span::raw(format!(" {}s ago", now.saturating_sub(roam.time).as_secs()))
+                    ]));
+                }
+            }
+            if let Some((_, unit)) = unit_of.get(device_mac) {
+                device_info.push(format_header("Same Unit"));
+                device_info.push(Spans::from(format!("  Likely one of {} radios in one physical device, numbered in sequence", unit.len())));
+                for address in unit.iter().filter(|address| address != device_mac) {
+                    device_info.push(Spans::from(vec![
+                        Span::raw("  "),
+                        Span::styled(address.to_hex_string(), Style::reset().fg(Color::LightCyan)),
+                        Span::raw(match devices.get(address).and_then(|device| device.beacon.as_ref()) {
+                            Some(ssid) if !ssid.is_empty() => format!(" {:?}", ssid),
+                            _ => String::new()
+                        })
+                    ]));
+                }
+            }
+            if !device.handshakes.is_empty() {
+                device_info.push(format_header("Handshakes"));
+                for (station, &messages) in device.handshakes.iter() {
+                    let heard: Vec<_> = (1..=4).filter(|message| messages & 1 << (message - 1) != 0).map(|message| message.to_string()).collect();
+                    device_info.push(Spans::from(vec![
+                        Span::raw("  "),
+                        Span::styled(station.to_hex_string(), Style::reset().fg(Color::LightCyan)),
+                        Span::raw(format!(" message(s) {}", heard.join(", "))),
+                        if handshake_complete(messages) {
+                            Span::styled(" (complete)", Style::reset().fg(Color::LightMagenta))
+                        } else {
+                            Span::raw("")
+                        }
+                    ]));
+                }
+            }
+            if let Some(mld) = device.mld {
+                device_info.push(format_header("Multi-Link Device"));
+                device_info.push(Spans::from(vec![
+                    Span::raw("  MLD Address: "),
+                    Span::styled(mld.to_hex_string(), Style::reset().fg(Color::LightCyan))
+                ]));
+                let links = devices.multi_link_devices().remove(&mld).unwrap_or_default();
+                for address in links.iter().filter(|address| address != device_mac) {
+                    device_info.push(Spans::from(vec![
+                        Span::raw("  Link: "),
+                        Span::styled(address.to_hex_string(), Style::reset().fg(Color::LightCyan)),
+                        Span::raw(match devices.get(address).and_then(|device| device.beacon.as_ref()) {
+                            Some(ssid) if !ssid.is_empty() => format!(" {:?}", ssid),
+                            Some(_) => String::new(),
+                            None => " (not heard)".to_string()
+                        })
+                    ]));
+                }
+            } else if device.eht {
+                device_info.push(format_header("Multi-Link Device"));
+                device_info.push(Spans::from("  Supports 802.11be, but hasn't announced any other links"));
+            }
+            if let Some(cluster) = cluster_of.get(device_mac) {
+                device_info.push(format_header("Probable Device"));
+                if self.overrides.borrow().is_merged(device_mac) {
+                    device_info.push(Spans::from(format!("  One device behind {} addresses, merged by hand (M to split this one off)", cluster.len())));
+                } else {
+                    device_info.push(Spans::from(format!("  Likely one device behind {} randomised addresses, probing alike in turn (M to split this one off)", cluster.len())));
+                }
+                for address in cluster.iter() {
+                    let seen = devices.get(address).and_then(|device| Some((device.first_seen?, device.last_seen?)));
+                    device_info.push(Spans::from(vec![
+                        Span::raw("  "),
+                        Span::styled(address.to_hex_string(), Style::reset().fg(if address == *device_mac { Color::LightGreen } else { Color::LightCyan })),
+                        Span::raw(seen.map_or_else(String::new, |(first, last)| format!(" heard {}s to {}s", first.as_secs(), last.as_secs())))
+                    ]));
+                }
+            }
+            if let Some(watch) = &device.watched {
+                device_info.push(format_header("Watchlist"));
+                device_info.push(Spans::from(vec![
+                    Span::raw("  On "),
+                    Span::styled(format!("{:?}", watch), Style::reset().fg(Color::LightRed).add_modifier(Modifier::BOLD))
+                ]));
+            }
+            if !device.tags.is_empty() {
+                device_info.push(format_header("Tags"));
+                for tag in device.tags.iter() {
+                    device_info.push(Spans::from(vec![Span::raw("  "), format_string(tag)]));
+                }
+            }
+            if let Some(ssid) = &device.beacon {
+                device_info.push(format_header("Beacon"));
+                device_info.push(Spans::from(match (device.hidden, ssid.is_empty()) {
+                    (true, true) => vec![Span::raw("  SSID: "), Span::styled("(hidden)", Style::reset().fg(Color::LightYellow))],
+                    (true, false) => vec![
+                        Span::raw("  SSID: "),
+                        format_string(ssid),
+                        Span::styled(" (hidden, revealed)", Style::reset().fg(Color::LightYellow))
+                    ],
+                    (false, _) => vec![Span::raw("  SSID: "), format_string(ssid)]
+                }));
+                if let Some(access) = device.access {
+                    device_info.push(Spans::from(vec![
+                        Span::raw("  Access: "),
+                        format_access(access)
+                    ]));
+                }
+                if device.advertisers.len() > 1 {
+                    device_info.push(Spans::from(Span::styled(
+                        format!("  Advertised by {} transmitters, a sign of spoofing:", device.advertisers.len()),
+                        Style::reset().fg(Color::LightRed)
+                    )));
+                    for advertiser in device.advertisers.iter() {
+                        device_info.push(Spans::from(vec![
+                            Span::raw("    "),
+                            Span::styled(advertiser.to_hex_string(), Style::reset().fg(Color::LightCyan))
+                        ]));
+                    }
+                }
+                if let Some(security) = device.security {
+                    device_info.push(Spans::from(format!("  Security: {}", security)));
+                }
+                if let Some(channel) = device.announced_channel {
+                    // Heard elsewhere when the sensor picks it up bleeding over onto a neighbouring channel
+                    let heard = device.strongest_sensor().and_then(|(_, sighting)| sighting.channel()).filter(|&heard| heard != channel as u32);
+                    device_info.push(Spans::from(vec![
+                        Span::raw("  Channel: "),
+                        Span::styled(channel.to_string(), Style::reset().fg(Color::LightCyan)),
+                        Span::raw(heard.map_or_else(String::new, |heard| format!(", heard on {}", heard)))
+                    ]));
+                }
+                if let Some(rates) = &device.rates {
+                    let rates: Vec<_> = rates.iter()
+                        .map(|&rate| if rate & 0x80 != 0 { format!("{}*", fingerprint::rate_name(rate)) } else { fingerprint::rate_name(rate) })
+                        .collect();
+                    device_info.push(Spans::from(vec![
+                        Span::raw("  Rates: "),
+                        Span::styled(rates.join(" "), Style::reset().fg(Color::LightCyan)),
+                        Span::styled(" (* basic)", Style::reset().add_modifier(Modifier::DIM))
+                    ]));
+                }
+                if let Some(wps) = &device.wps {
+                    let mut spans = vec![Span::raw("  WPS: ")];
+                    spans.push(match wps.configured {
+                        Some(false) => Span::styled("not configured", Style::reset().fg(Color::LightRed)),
+                        Some(true) => Span::raw("configured"),
+                        None => Span::raw("on")
+                    });
+                    spans.push(if wps.locked {
+                        Span::raw(", locked")
+                    } else {
+                        Span::styled(", taking PINs", Style::reset().fg(Color::LightYellow))
+                    });
+                    if let Some(name) = &wps.device {
+                        spans.push(Span::raw(format!(", {}", name)));
+                    }
+                    device_info.push(Spans::from(spans));
+                }
+                if let Some(standards) = device.standards {
+                    device_info.push(Spans::from(vec![
+                        Span::raw("  Standard: "),
+                        Span::styled(standards.to_string(), Style::reset().fg(Color::LightCyan))
+                    ]));
+                }
+                if let Some(pair) = device.owe_pair {
+                    device_info.push(Spans::from(vec![
+                        Span::raw("  OWE Transition: one network with "),
+                        Span::styled(pair.to_hex_string(), Style::reset().fg(Color::LightCyan))
+                    ]));
+                }
+                let beacons = &device.beacons;
+                if let (Some(expected), Some(loss)) = (beacons.expected(), beacons.loss()) {
+                    device_info.push(Spans::from(vec![
+                        Span::raw("  Beacons: "),
+                        Span::styled(
+                            format!("{} of {} expected, {:.1}% lost", beacons.received, expected, loss * 100.0),
+                            Style::reset().fg(if loss > 0.1 { Color::LightYellow } else { Color::LightCyan })
+                        )
+                    ]));
+                }
+                if beacons.malformed > 0 {
+                    device_info.push(Spans::from(vec![
+                        Span::raw("  Malformed Beacons: "),
+                        Span::styled(beacons.malformed.to_string(), Style::reset().fg(Color::LightYellow))
+                    ]));
+                }
+                if let Some((stations, utilisation)) = device.load {
+                    device_info.push(Spans::from(vec![
+                        Span::raw("  Reported Load: "),
+                        Span::styled(
+                            format!("{} station(s), {}% channel utilisation", stations, utilisation as u32 * 100 / 255),
+                            Style::reset().fg(Color::LightCyan)
+                        )
+                    ]));
+                }
+                let power_save = &device.power_save;
+                if let Some(period) = power_save.dtim_period {
+                    device_info.push(Spans::from(format!("  DTIM: every {} beacon(s)", period)));
+                }
+                if !power_save.buffered.is_empty() {
+                    device_info.push(Spans::from(format!(
+                        "  Held Frames: for {} dozing client(s), {} never heard",
+                        power_save.buffered.len(),
+                        power_save.unheard().count()
+                    )));
+                    for (aid, time) in power_save.buffered.iter() {
+                        device_info.push(Spans::from(vec![
+                            Span::raw(format!("    AID {} ", aid)),
+                            match power_save.aids.get(aid) {
+                                Some(client) => Span::styled(client.to_hex_string(), Style::reset().fg(Color::LightCyan)),
+                                None => Span::styled("not heard", Style::reset().fg(Color::LightYellow))
+                            },
+                            Span::raw(format!(", listed {} ago", format_age(now.saturating_sub(*time))))
+                        ]));
+                    }
+                }
                 
             }
+            if let Some(power) = device.power {
+                device_info.push(format_header("Transmit Power"));
+                if let Some(reported) = power.reported {
+                    device_info.push(Spans::from(vec![
+                        Span::raw("  Reported: "),
+                        Span::styled(format!("{}dBm", reported), Style::reset().fg(Color::LightCyan))
+                    ]));
+                }
+                if let Some(limit) = power.limit {
+                    device_info.push(Spans::from(format!(
+                        "  Country Limit: {}dBm{}",
+                        limit,
+                        power.constraint.map_or_else(String::new, |constraint| format!(", {}dBm for clients after a {}dB constraint", power.allowed().unwrap_or(limit), constraint))
+                    )));
+                } else if let Some(constraint) = power.constraint {
+                    device_info.push(Spans::from(format!("  Constraint: {}dB below the country limit", constraint)));
+                }
+                if let Some(excess) = power.excess() {
+                    device_info.push(Spans::from(Span::styled(
+                        format!("  Reports {}dB above what its country allows", excess),
+                        Style::reset().fg(Color::LightRed).add_modifier(Modifier::BOLD)
+                    )));
+                }
+                // A rough figure, as walls, antennas and the adapter's own calibration all throw it off
+                let strongest = device.strongest_sensor().and_then(|(_, sighting)| Some((sighting.signal?, sighting.frequency?)));
+                if let Some((loss, metres)) = strongest.and_then(|(signal, frequency)| power.link_budget(signal, frequency)) {
+                    device_info.push(Spans::from(format!("  Path Loss: {}dB, about {:.0}m in free space", loss, metres)));
+                }
+            }
+            if !device.hotspot.is_empty() {
+                let hotspot = &device.hotspot;
+                device_info.push(format_header("Hotspot 2.0"));
+                if let Some(access_network_type) = hotspot.access_network_type_name() {
+                    device_info.push(Spans::from(vec![
+                        Span::raw("  Network Type: "),
+                        format_string(access_network_type),
+                        Span::raw(if hotspot.internet { " with internet" } else { "" })
+                    ]));
+                }
+                if let Some(venue) = hotspot.venue_description() {
+                    device_info.push(Spans::from(vec![
+                        Span::raw("  Venue: "),
+                        Span::styled(format!("{:?}", venue), Style::reset().fg(Color::LightCyan))
+                    ]));
+                }
+                for name in hotspot.venue_names.iter() {
+                    device_info.push(Spans::from(vec![Span::raw("  Venue Name: "), format_string(name)]));
+                }
+                if let Some(hessid) = hotspot.hessid {
+                    device_info.push(Spans::from(vec![
+                        Span::raw("  HESSID: "),
+                        Span::styled(hessid.to_hex_string(), Style::reset().fg(Color::LightCyan))
+                    ]));
+                }
+                for oi in hotspot.roaming_consortium.iter() {
+                    let oi: Vec<_> = oi.iter().map(|byte| format!("{:02x}", byte)).collect();
+                    device_info.push(Spans::from(vec![
+                        Span::raw("  Roaming Consortium: "),
+                        Span::styled(oi.join(":"), Style::reset().fg(Color::LightCyan))
+                    ]));
+                }
+                for domain in hotspot.domains.iter() {
+                    device_info.push(Spans::from(vec![Span::raw("  Domain: "), format_string(domain)]));
+                }
+                for realm in hotspot.realms.iter() {
+                    device_info.push(Spans::from(vec![Span::raw("  NAI Realm: "), format_string(realm)]));
+                }
+            }
             if let Some(manufacturer) = &device.manufacturer {
                 device_info.push(format_header("Manufacturer"));
                 device_info.push(Spans::from(vec![
@@ -102,6 +890,116 @@ impl Page for Devices {
                     ]))
                 }
             }
+            if let Some(identity) = &device.identity {
+                device_info.push(format_header("Identity"));
+                for (name, value) in [("Device", identity.device.as_deref()), ("Platform", identity.platform), ("Chipset", identity.chipset)].iter() {
+                    if let Some(value) = value {
+                        device_info.push(Spans::from(vec![Span::raw(format!("  {}: ", name)), format_string(value)]));
+                    }
+                }
+            }
+            if !device.hostnames.is_empty() || !device.ip_addresses.is_empty() {
+                device_info.push(format_header("Network"));
+                for hostname in device.hostnames.iter() {
+                    device_info.push(Spans::from(vec![Span::raw("  Hostname: "), format_string(hostname)]));
+                }
+                for address in device.ip_addresses.iter() {
+                    device_info.push(Spans::from(vec![
+                        Span::raw("  Address: "),
+                        Span::styled(address.to_string(), Style::reset().fg(Color::LightCyan))
+                    ]));
+                }
+            }
+            if let Some(assessment) = Assessment::of(**device_mac, device) {
+                device_info.push(format_header("Privacy"));
+                device_info.push(Spans::from(vec![
+                    Span::raw("  Grade: "),
+                    format_grade(&assessment),
+                    Span::raw(format!(" ({}/100)", assessment.score))
+                ]));
+                for reason in assessment.reasons.iter() {
+                    device_info.push(Spans::from(format!("  - {}", reason)));
+                }
+            }
+            if let Some(fingerprint) = Fingerprint::of(device, devices) {
+                device_info.push(format_header("Rates"));
+                let rates: Vec<_> = fingerprint.rates.iter().map(|&rate| fingerprint::rate_name(rate)).collect();
+                device_info.push(Spans::from(vec![Span::raw("  "), Span::styled(rates.join(" "), Style::reset().fg(Color::LightCyan))]));
+                device_info.push(Spans::from(format!("  Shared by {} other device(s)", fingerprint.shared)));
+                for oddity in fingerprint.oddities {
+                    device_info.push(Spans::from(vec![Span::raw("  "), Span::styled(oddity, Style::reset().fg(Color::LightYellow))]));
+                }
+            }
+            let traffic = &device.traffic;
+            if traffic.bytes() > 0 {
+                device_info.push(format_header("Traffic"));
+                device_info.push(Spans::from(vec![
+                    Span::raw("  Sent: "),
+                    Span::styled(format!("{} frame(s), {}", traffic.sent_frames, ui::format_bytes(traffic.sent_bytes)), Style::reset().fg(Color::LightCyan))
+                ]));
+                device_info.push(Spans::from(vec![
+                    Span::raw("  Received: "),
+                    Span::styled(format!("{} frame(s), {}", traffic.received_frames, ui::format_bytes(traffic.received_bytes)), Style::reset().fg(Color::LightCyan))
+                ]));
+                if traffic.qos {
+                    device_info.push(Spans::from("  Sends QoS data"));
+                }
+            }
+            if !device.probes.is_empty() {
+                device_info.push(format_header("Probed For"));
+                // The networks asked for most often are likely the ones the client prefers
+                let mut probes: Vec<_> = device.probes.iter().collect();
+                probes.sort_by(|(l_ssid, l_count), (r_ssid, r_count)| r_count.cmp(l_count).then(l_ssid.cmp(r_ssid)));
+                for (ssid, count) in probes {
+                    device_info.push(Spans::from(vec![
+                        Span::raw("  "),
+                        format_string(ssid),
+                        Span::raw(format!(" ({} request(s))", count))
+                    ]));
+                }
+            }
+            if device.signal_strength.last.is_some() {
+                device_info.push(format_header("Signal Strength"));
+                device_info.push(Spans::from(vec![
+                    Span::raw("  "),
+                    Span::styled(device.signal_strength.to_string(), Style::reset().fg(Color::LightCyan))
+                ]));
+            }
+            if !device.sensors.is_empty() {
+                device_info.push(format_header("Heard By"));
+                let mut sensors: Vec<_> = device.sensors.iter().collect();
+                sensors.sort_by(|(l, _), (r, _)| l.cmp(r));
+                for (sensor, sighting) in sensors {
+                    let signal = sighting.signal.map_or_else(|| "no signal reported".to_string(), |signal| format!("{}dBm", signal));
+                    let channel = match (sighting.channel(), sighting.band()) {
+                        (Some(channel), Some(band)) => format!(", channel {} ({})", channel, band),
+                        _ => String::new()
+                    };
+                    device_info.push(Spans::from(vec![
+                        Span::raw(format!("  {}: ", sensor)),
+                        Span::styled(format!("{}, {} frame(s){}", signal, sighting.frames, channel), Style::reset().fg(Color::LightCyan))
+                    ]))
+                }
+                // The noise is only reported by some adapters, so quality is shown by whichever sensors gave it
+                let mut qualities: Vec<_> = device.sensors.iter()
+                    .filter(|(_, sighting)| !sighting.signal_quality.is_empty() || sighting.quality.is_some())
+                    .collect();
+                if !qualities.is_empty() {
+                    device_info.push(format_header("Signal Quality"));
+                    qualities.sort_by(|(l, _), (r, _)| l.cmp(r));
+                    for (sensor, sighting) in qualities {
+                        let mut quality = sighting.signal_quality.to_string();
+                        if let Some(lock) = sighting.quality {
+                            let separator = if quality.is_empty() { "" } else { ", " };
+                            quality = format!("{}{}lock quality {}", quality, separator, lock);
+                        }
+                        device_info.push(Spans::from(vec![
+                            Span::raw(format!("  {}: ", sensor)),
+                            Span::styled(quality, Style::reset().fg(Color::LightCyan))
+                        ]))
+                    }
+                }
+            }
             if device.knows.len() > 0{
                 device_info.push(format_header("Knows Devices"));
                 let mut devices: Vec<_> = device.knows.iter().collect();
@@ -116,13 +1014,245 @@ impl Page for Devices {
 
             let device_info = Paragraph::new(device_info)
                 .block(Block::default().borders(Borders::ALL).title(device_mac.to_hex_string()));
-            frame.render_stateful_widget(device_list, areas[0], &mut self.device_state);
-            frame.render_widget(device_info, areas[1])
+            let (list_area, detail_area) = ui::split(area);
+            frame.render_stateful_widget(device_table, list_area, &mut self.table_state);
+            // A signal that rises and falls away is a device passing by, where a steady one is staying put
+            let signals: Vec<_> = device.signal_history.iter().map(|&(_, signal)| signal).collect();
+            if let (Some(&last), Some(&weakest), Some(&strongest)) = (signals.last(), signals.iter().min(), signals.iter().max()) {
+                let areas = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(5), Constraint::Min(0)])
+                    .split(detail_area);
+                let columns = signal_columns(device, self.started.elapsed(), areas[0].width.saturating_sub(2) as usize);
+                frame.render_widget(
+                    Sparkline::default()
+                        .block(Block::default().borders(Borders::ALL).title(format!(
+                            "Signal, last {} min (last {}dBm, {} to {}dBm)", SIGNAL_HISTORY.as_secs() / 60, last, weakest, strongest
+                        )))
+                        .style(Style::reset().fg(Color::LightGreen))
+                        .max(SIGNAL_FLOOR.unsigned_abs() as u64)
+                        .data(&columns),
+                    areas[0]
+                );
+                frame.render_widget(device_info, areas[1])
+            } else {
+                frame.render_widget(device_info, detail_area)
+            }
         } else {
-            frame.render_stateful_widget(device_list, area, &mut self.device_state);
+            frame.render_stateful_widget(device_table, area, &mut self.table_state);
+        }
+
+        if let Some(cursor) = self.choosing {
+            let lines: Vec<_> = self.choices().into_iter().enumerate().map(|(index, (column, shown))| {
+                let style = if index == cursor { Style::reset().add_modifier(Modifier::REVERSED) } else { Style::reset() };
+                Spans::from(Span::styled(format!("[{}] {}", if shown { 'x' } else { ' ' }, column.name()), style))
+            }).collect();
+            let title = "Columns, Space to show or hide, [ and ] to move";
+            // Centred, and only as big as it needs to be
+            let width = (title.chars().count() as u16 + 4).min(area.width);
+            let height = (lines.len() as u16 + 2).min(area.height);
+            let popup = Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 2, width, height);
+            frame.render_widget(Clear, popup);
+            frame.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title)).style(Style::reset()), popup)
         }
+    }
+
+    fn captured(&mut self, _: &LogEntry) {
         
     }
+    fn key(&mut self, key: Key) {
+        if let Some(cursor) = self.choosing {
+            let choices = self.choices();
+            let (column, _) = choices[cursor];
+            let mut columns = self.columns.borrow().clone();
+            let position = columns.iter().position(|shown| *shown == column);
+            match key {
+                Key::Up | Key::Char('w') => self.choosing = Some(cursor.saturating_sub(1)),
+                Key::Down | Key::Char('s') => self.choosing = Some((cursor + 1).min(choices.len() - 1)),
+                // One column is always left to pick rows by
+                Key::Char(' ') | Key::Char('\n') => match position {
+                    Some(index) if columns.len() > 1 => {
+                        columns.remove(index);
+                    }
+                    Some(_) => (),
+                    None => columns.push(column)
+                },
+                Key::Char('[') => if let Some(index) = position.filter(|&index| index > 0) {
+                    columns.swap(index, index - 1)
+                },
+                Key::Char(']') => if let Some(index) = position.filter(|&index| index + 1 < columns.len()) {
+                    columns.swap(index, index + 1)
+                },
+                Key::Esc | Key::Char('C') => self.choosing = None,
+                _ => ()
+            }
+            // The cursor stays on the column as it moves
+            if columns != *self.columns.borrow() {
+                *self.columns.borrow_mut() = columns;
+                self.choosing = self.choices().iter().position(|(choice, _)| *choice == column);
+            }
+            return
+        }
+        if let Some(editing) = &mut self.editing {
+            match key {
+                Key::Char('\n') => {
+                    let search = self.editing.take().unwrap_or_default();
+                    self.search = Some(search.trim().to_string()).filter(|search| !search.is_empty());
+                    self.device_state.restart()
+                }
+                Key::Char(c) => editing.push(c),
+                Key::Backspace => {
+                    editing.pop();
+                }
+                Key::Esc => self.editing = None,
+                _ => ()
+            }
+            return
+        }
+        match key {
+            Key::Char('F') => self.editing = Some(self.search.clone().unwrap_or_default()),
+            Key::Char('v') => self.split = !self.split,
+            Key::Char('C') => self.choosing = Some(0),
+            Key::Char('f') => self.device_state.newest = !self.device_state.newest,
+            Key::Char('A') => {
+                self.only_active = !self.only_active;
+                self.device_state.restart()
+            }
+            Key::Char('o') => {
+                self.sort = self.sort.next();
+                self.descending = self.sort.descending_by_default();
+                self.device_state.restart()
+            }
+            Key::Char('O') => {
+                self.descending = !self.descending;
+                self.device_state.restart()
+            }
+            Key::Char('c') => {
+                self.clustered = !self.clustered;
+                self.device_state.restart()
+            }
+            Key::Char('m') => match (self.marked, self.highlighted) {
+                (Some(marked), Some(highlighted)) if marked != highlighted => {
+                    self.overrides.borrow_mut().merge(marked, highlighted);
+                    self.marked = None
+                }
+                (Some(_), _) => self.marked = None,
+                (None, highlighted) => self.marked = highlighted
+            },
+            Key::Char('M') => if let Some(highlighted) = self.highlighted {
+                self.overrides.borrow_mut().split(highlighted);
+                self.expanded.remove(&highlighted);
+            },
+            Key::Backspace => {
+                self.manufacturer = None;
+                self.device_state.restart()
+            }
+            Key::Char('\n') => if let Some(cluster) = self.selected_cluster.filter(|_| self.clustered) {
+                if !self.expanded.remove(&cluster) {
+                    self.expanded.insert(cluster);
+                }
+            } else if let Some(highlighted) = self.highlighted {
+                self.navigation = Some(Navigation::Device(highlighted))
+            }
+            _ => ()
+        }
+    }
+    fn keys(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("F", "Filter by address, manufacturer or SSID"),
+            ("v", "Show or hide the detail pane"),
+            ("C", "Choose the columns shown and their order, saved to the config file on the way out"),
+            ("A", "Show only devices heard recently, or every device"),
+            ("f", "Follow new devices as they turn up, or stay on the selected one"),
+            ("o", "Change the order"),
+            ("O", "Reverse the order"),
+            ("c", "Group randomised addresses into probable devices, or not"),
+            ("Enter", "Open the selected device's page, or expand or collapse the selected probable device"),
+            ("m", "Mark a device, then merge it with another"),
+            ("M", "Split the selected address off its probable device"),
+            ("Backspace", "Show every manufacturer's devices again, after picking one on the Manufacturers tab")
+        ]
+    }
+    fn grabs_input(&self) -> bool {
+        self.editing.is_some() || self.choosing.is_some()
+    }
+    fn save(&self, state: &mut State) {
+        match &self.sensor_filter {
+            Some(sensor) => state.set("devices.sensor", sensor),
+            None => state.remove("devices.sensor")
+        }
+        state.set("devices.split", self.split);
+        state.set("devices.active", self.only_active);
+        state.set("devices.clustered", self.clustered);
+        state.set("devices.follow", self.device_state.newest);
+        state.set("devices.sort", self.sort.name());
+        state.set("devices.descending", self.descending);
+        match &self.search {
+            Some(search) => state.set("devices.search", search),
+            None => state.remove("devices.search")
+        }
+        let overrides = self.overrides.borrow();
+        if overrides.is_empty() {
+            state.remove("devices.merged");
+            state.remove("devices.apart");
+        } else {
+            let (merged, apart) = overrides.save();
+            state.set("devices.merged", merged);
+            state.set("devices.apart", apart);
+        }
+    }
+    fn restore(&mut self, state: &State) {
+        self.sensor_filter = state.get("devices.sensor").map(String::from);
+        self.split = state.flag("devices.split").unwrap_or(self.split);
+        self.only_active = state.flag("devices.active").unwrap_or(self.only_active);
+        self.clustered = state.flag("devices.clustered").unwrap_or(self.clustered);
+        self.device_state.newest = state.flag("devices.follow").unwrap_or(self.device_state.newest);
+        if let Some(sort) = state.get("devices.sort").and_then(|name| SortKey::ALL.iter().find(|key| key.name() == name)) {
+            self.sort = *sort;
+        }
+        self.descending = state.flag("devices.descending").unwrap_or(self.descending);
+        self.search = state.get("devices.search").map(String::from);
+        *self.overrides.borrow_mut() = clusters::Overrides::restore(state.get("devices.merged").unwrap_or_default(), state.get("devices.apart").unwrap_or_default());
+    }
+    fn reset(&mut self) {
+        self.expanded.clear();
+        self.marked = None;
+        self.device_state.restart()
+    }
+    fn freeze(&mut self, frozen: bool) {
+        self.frozen = Some(vec![]).filter(|_| frozen)
+    }
+    fn selected(&self) -> Option<MacAddress> {
+        self.selected
+    }
+    fn search(&self, text: &str, devices: &DeviceList) -> Vec<Found> {
+        let listed = self.listed(devices);
+        let shown: HashSet<_> = listed.iter().map(|(address, _)| **address).collect();
+        let probable = if self.clustered { clusters::probable_devices(devices) } else { vec![] };
+        let cluster_of: HashMap<_, _> = probable.iter()
+            .flat_map(|cluster| cluster.iter().map(move |address| (*address, cluster)))
+            .collect();
+        let mut found = vec![];
+        for (address, _) in listed.into_iter().filter(|(address, device)| matches(text, address, device)) {
+            // An address within a collapsed probable device is found on the row standing in for it
+            let row = match cluster_of.get(address) {
+                Some(cluster) if !self.expanded.contains(&cluster[0]) => cluster.iter().find(|member| shown.contains(member)).unwrap_or(address),
+                _ => address
+            };
+            if !found.contains(&Found::Device(*row)) {
+                found.push(Found::Device(*row));
+            }
+        }
+        found
+    }
+    fn select(&mut self, found: &Found) {
+        if let Found::Device(address) = found {
+            self.device_state.pin(*address)
+        }
+    }
+    fn navigate(&mut self) -> Option<Navigation> {
+        self.navigation.take()
+    }
 
     fn up(&mut self) {
         self.device_state.up()
@@ -137,9 +1267,29 @@ impl Page for Devices {
         self.device_state.bottom()
     }
     fn left(&mut self) {
-        
+        self.cycle_sensor_filter(false)
     }
     fn right(&mut self) {
-        
+        self.cycle_sensor_filter(true)
     }
-}
\ No newline at end of file
+}
+
+/// Signals at or below this many dBm are drawn as nothing, and 0dBm as the full height of the chart
+pub(super) const SIGNAL_FLOOR: i8 = -100;
+
+/// The strongest signal heard in each column's share of the signal history up to now, above the floor
+/// Columns where the device wasn't heard are left empty
+pub(super) fn signal_columns(device: &KnownDevice, now: Duration, columns: usize) -> Vec<u64> {
+    let mut values = vec![0; columns];
+    if columns == 0 {
+        return values
+    }
+    let start = now.saturating_sub(SIGNAL_HISTORY);
+    for &(time, signal) in device.signal_history.iter().filter(|&&(time, _)| time >= start) {
+        let column = ((time - start).as_secs_f64() / SIGNAL_HISTORY.as_secs_f64() * columns as f64) as usize;
+        let value = (signal as i16 - SIGNAL_FLOOR as i16).max(0) as u64;
+        let slot = &mut values[column.min(columns - 1)];
+        *slot = value.max(*slot);
+    }
+    values
+}
@@ -0,0 +1,143 @@
+use eui48::MacAddress;
+use tui::{
+    layout::Rect,
+    widgets::{Block, Borders, List, ListItem},
+    style::{Style, Modifier, Color},
+    text::{Span, Spans},
+    terminal::Frame
+};
+
+use super::{Found, LogEntry, Navigation, Page};
+use crate::{DEAUTH_WINDOW, DeviceList, state::State, ui::{self, Key}};
+
+/// Deauthentication and disassociation floods, newest first, with who they were aimed at and who claimed to send them
+pub struct Events {
+    state: ui::ListState,
+    /// The network on each line of the list, as of the last render
+    rows: Vec<Option<MacAddress>>
+}
+impl Events {
+    pub fn new() -> Self {
+        Self {
+            state: Default::default(),
+            rows: vec![]
+        }
+    }
+}
+impl Page for Events {
+    fn name(&self) -> &'static str {
+        "Events"
+    }
+    fn short_name(&self) -> &'static str {
+        "Evt"
+    }
+
+    fn render(&mut self, frame: &mut Frame<ui::Backend>, area: Rect, devices: &mut DeviceList) {
+        let floods = &devices.deauths.floods;
+        self.rows = floods.iter().rev().map(|flood| Some(flood.bssid)).collect();
+        let items: Vec<_> = floods.iter().rev().map(|flood| {
+            let ssid = devices.get(&flood.bssid).and_then(|device| device.beacon.as_deref()).filter(|ssid| !ssid.is_empty());
+            let client = if flood.broadcast() { "every client".to_string() } else { flood.client.to_hex_string() };
+            let senders: Vec<_> = flood.senders.iter().map(MacAddress::to_hex_string).collect();
+            let reasons: Vec<_> = flood.reasons.iter().map(u16::to_string).collect();
+            let mut spans = vec![
+                Span::raw(format!("{:>9.3} ", flood.started.as_secs_f64())),
+                Span::styled(format!("{:>5}s ", (flood.last - flood.started).as_secs()), Style::reset().add_modifier(Modifier::DIM)),
+                Span::styled(flood.bssid.to_hex_string(), Style::reset().fg(Color::LightGreen)),
+                Span::styled(ssid.map_or_else(String::new, |ssid| format!(" {:?}", ssid)), Style::reset().fg(Color::LightCyan)),
+                Span::raw(" → "),
+                Span::styled(client, Style::reset().fg(Color::LightYellow)),
+                Span::styled(format!(
+                    "  {} deauth, {} disassoc, up to {} in {}s",
+                    flood.deauthentications,
+                    flood.disassociations,
+                    flood.peak,
+                    DEAUTH_WINDOW.as_secs()
+                ), Style::reset().fg(Color::LightRed)),
+                Span::styled(format!("  from {}", senders.join(", ")), Style::reset().add_modifier(Modifier::DIM))
+            ];
+            if !reasons.is_empty() {
+                spans.push(Span::styled(format!(", reason {}", reasons.join(", ")), Style::reset().add_modifier(Modifier::DIM)));
+            }
+            ListItem::new(Spans::from(spans))
+        }).collect();
+
+        self.state.set_item_count(items.len());
+        self.state.follow(&self.rows, || None);
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(format!(
+                "Deauthentication Floods ({}, {} or more frames to a client within {}s)",
+                floods.len(),
+                devices.deauths.threshold,
+                DEAUTH_WINDOW.as_secs()
+            )))
+            .highlight_style(Style::default().bg(Color::Reset).add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(list, area, &mut self.state)
+    }
+
+    fn captured(&mut self, _: &LogEntry) {
+
+    }
+    fn key(&mut self, _: Key) {
+
+    }
+    fn keys(&self) -> Vec<(&'static str, &'static str)> {
+        vec![]
+    }
+    fn grabs_input(&self) -> bool {
+        false
+    }
+    fn save(&self, _: &mut State) {
+
+    }
+    fn restore(&mut self, _: &State) {
+
+    }
+    fn reset(&mut self) {
+        self.state.restart()
+    }
+    fn freeze(&mut self, _: bool) {
+
+    }
+    fn selected(&self) -> Option<MacAddress> {
+        self.state.selected().and_then(|index| self.rows.get(index).copied().flatten())
+    }
+    fn search(&self, text: &str, devices: &DeviceList) -> Vec<Found> {
+        let matches = |address: &MacAddress| {
+            address.to_hex_string().starts_with(&text.to_lowercase().replace('-', ":"))
+                || devices.get(address).and_then(|device| device.beacon.as_deref()).map_or(false, |ssid| super::contains(ssid, text))
+        };
+        devices.deauths.floods.iter().rev()
+            .filter(|flood| matches(&flood.bssid) || matches(&flood.client) || flood.senders.iter().any(matches))
+            .map(|flood| Found::Device(flood.bssid))
+            .collect()
+    }
+    fn select(&mut self, found: &Found) {
+        if let Found::Device(address) = found {
+            self.state.pin(*address)
+        }
+    }
+    fn navigate(&mut self) -> Option<Navigation> {
+        None
+    }
+
+    fn up(&mut self) {
+        self.state.up()
+    }
+    fn top(&mut self) {
+        self.state.top()
+    }
+    fn down(&mut self) {
+        self.state.down()
+    }
+    fn bottom(&mut self) {
+        self.state.bottom()
+    }
+    fn left(&mut self) {
+
+    }
+    fn right(&mut self) {
+
+    }
+}
@@ -1,22 +1,69 @@
-use termion::{input::MouseTerminal, raw::RawTerminal, screen::AlternateScreen};
+use std::{cell::RefCell, collections::{HashMap, HashSet}, rc::Rc, time::Duration};
+use eui48::MacAddress;
 use tui::{
-    backend::TermionBackend,
-    layout::Rect,
-    widgets::{BarChart, Block, Borders},
+    layout::{Constraint, Direction, Layout, Rect},
+    widgets::{Axis, BarChart, Block, Borders, Chart, Dataset, GraphType, List, ListItem, ListState},
     style::{Style, Modifier, Color},
+    symbols::Marker,
+    text::{Span, Spans},
     terminal::Frame
 };
 
-use super::Page;
-use crate::DeviceList;
+use super::{Found, LogEntry, Navigation, Page};
+use crate::{DeviceList, state::State, ui::{self, Key}};
 
 pub struct Manufacturers {
-
+    /// Every transmitter heard so far
+    seen: HashSet<MacAddress>,
+    /// Minutes since the capture started, of the minute being counted
+    minute: u64,
+    /// Transmitters heard this minute
+    current: HashSet<MacAddress>,
+    /// Transmitters heard for the first time this minute
+    new: u64,
+    /// Distinct and newly seen transmitters for each minute gone by
+    history: Vec<(u64, u64)>,
+    /// The manufacturer picked out, by moving along the bars or by a search, which the chart keeps in view
+    selected: Option<String>,
+    /// Every manufacturer charted, most devices first, as of the last render
+    names: Vec<String>,
+    /// When the latest frame was heard, since the capture started
+    latest: Duration,
+    /// A manufacturer whose devices were asked to be listed, taken up by the Devices tab
+    drill_down: Rc<RefCell<Option<String>>>
 }
 impl Manufacturers {
-    pub fn new() -> Self {
+    pub fn new(drill_down: Rc<RefCell<Option<String>>>) -> Self {
         Self {
-            
+            seen: HashSet::new(),
+            minute: 0,
+            current: HashSet::new(),
+            new: 0,
+            history: vec![],
+            selected: None,
+            names: vec![],
+            latest: Duration::from_secs(0),
+            drill_down
+        }
+    }
+    /// Pick the manufacturer an offset along from the one picked, or the first if none is
+    fn step(&mut self, offset: isize) {
+        let position = self.selected.as_ref().and_then(|selected| self.names.iter().position(|name| name == selected));
+        let next = match position {
+            Some(position) => (position as isize + offset).max(0).min(self.names.len() as isize - 1) as usize,
+            None => 0
+        };
+        if let Some(name) = self.names.get(next) {
+            self.selected = Some(name.clone())
+        }
+    }
+    /// Close off minutes until the given one is current
+    fn advance(&mut self, minute: u64) {
+        while self.minute < minute {
+            self.history.push((self.current.len() as u64, self.new));
+            self.current.clear();
+            self.new = 0;
+            self.minute += 1;
         }
     }
 }
@@ -24,36 +71,183 @@ impl Page for Manufacturers {
     fn name(&self) -> &'static str {
         "Manufacturers"
     }
+    fn short_name(&self) -> &'static str {
+        "Mfr"
+    }
 
-    fn render(&mut self, frame: &mut Frame<TermionBackend<AlternateScreen<MouseTerminal<RawTerminal<std::io::Stdout>>>>>, area: Rect, devices: &mut DeviceList) {
+    fn render(&mut self, frame: &mut Frame<ui::Backend>, area: Rect, devices: &mut DeviceList) {
         let bar_data = devices.bar_data();
+        self.names = bar_data.iter().map(|(name, _)| name.to_string()).collect();
+        let selected = self.selected.as_ref().and_then(|selected| bar_data.iter().position(|(name, _)| name == selected));
+        // Devices first heard within the last minute, by manufacturer
+        let since = self.latest.saturating_sub(Duration::from_secs(60));
+        let mut recent: HashMap<&str, u64> = HashMap::new();
+        for device in devices.values().filter(|device| device.first_seen.map_or(false, |first_seen| first_seen >= since)) {
+            if let Some(manufacturer) = &device.manufacturer {
+                *recent.entry(manufacturer.name_short.as_str()).or_default() += 1;
+            }
+        }
+        let title = match selected {
+            Some(position) => format!(
+                "Manufacturers, {} with {} device(s), +{} in the last minute, Enter to list them",
+                bar_data[position].0, bar_data[position].1, recent.get(bar_data[position].0).copied().unwrap_or(0)
+            ),
+            None => "Manufacturers".to_string()
+        };
+
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(area);
+        // The counts are listed beside the chart, with how many are new, when there is room
+        let (chart_area, list_area) = if ui::narrow(areas[0]) {
+            (areas[0], None)
+        } else {
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(0), Constraint::Length(40)])
+                .split(areas[0]);
+            (columns[0], Some(columns[1]))
+        };
+
+        // Scrolled along only as far as keeps the picked bar in view, marked by its label
+        let visible = (chart_area.width.saturating_sub(2) / 9).max(1) as usize;
+        let offset = selected.map_or(0, |selected| (selected + 1).saturating_sub(visible));
+        let labels: Vec<String> = bar_data.iter().enumerate()
+            .map(|(index, (name, _))| if Some(index) == selected { format!(">{}", name) } else { name.to_string() })
+            .collect();
+        let data: Vec<(&str, u64)> = labels.iter().zip(bar_data.iter()).map(|(label, &(_, count))| (label.as_str(), count)).collect();
         let barchart = BarChart::default()
-            .block(Block::default().borders(Borders::ALL).title("Manufacturers"))
-            .data(&bar_data)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .data(&data[offset.min(data.len())..])
             .bar_width(8)
             .bar_gap(1)
             .bar_style(Style::reset().fg(Color::Blue))
             .value_style(Style::reset().fg(Color::Blue).add_modifier(Modifier::REVERSED));
+        frame.render_widget(barchart, chart_area);
 
-        frame.render_widget(barchart, area);
+        if let Some(list_area) = list_area {
+            let items: Vec<_> = bar_data.iter().map(|&(name, count)| {
+                let mut spans = vec![
+                    Span::raw(name.to_string()),
+                    Span::styled(format!(" {}", count), Style::reset().fg(Color::Blue))
+                ];
+                if let Some(new) = recent.get(name) {
+                    spans.push(Span::styled(format!(" +{}", new), Style::reset().fg(Color::LightYellow)));
+                }
+                ListItem::new(Spans::from(spans))
+            }).collect();
+            let list = List::new(items)
+                .block(Block::default().borders(Borders::ALL).title("Devices, new in the last minute"))
+                .highlight_style(Style::default().bg(Color::Reset).add_modifier(Modifier::REVERSED))
+                .highlight_symbol("> ");
+            let mut state = ListState::default();
+            state.select(selected);
+            frame.render_stateful_widget(list, list_area, &mut state);
+        }
+
+        // The minute in progress is shown as it stands
+        let minutes = self.history.iter().copied().chain(Some((self.current.len() as u64, self.new)));
+        let (devices, new): (Vec<_>, Vec<_>) = minutes.enumerate()
+            .map(|(minute, (devices, new))| ((minute as f64, devices as f64), (minute as f64, new as f64)))
+            .unzip();
+        let peak = devices.iter().chain(new.iter()).map(|&(_, count)| count).fold(1.0, f64::max);
+        let span = (devices.len() as f64 - 1.0).max(1.0);
+        let chart = Chart::new(vec![
+            Dataset::default()
+                .name("Devices")
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::reset().fg(Color::Blue))
+                .data(&devices),
+            Dataset::default()
+                .name("New")
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::reset().fg(Color::LightYellow))
+                .data(&new)
+        ])
+            .block(Block::default().borders(Borders::ALL).title("Devices per Minute"))
+            .x_axis(Axis::default()
+                .bounds([0.0, span])
+                .labels(vec![Span::raw("0m"), Span::raw(format!("{}m", span as u64))]))
+            .y_axis(Axis::default()
+                .bounds([0.0, peak])
+                .labels(vec![Span::raw("0"), Span::raw(format!("{}", peak as u64))]));
+        frame.render_widget(chart, areas[1]);
     }
 
-    fn up(&mut self) {
+    fn captured(&mut self, entry: &LogEntry) {
+        self.latest = entry.time;
+        self.advance(entry.time.as_secs() / 60);
+        if let Some(transmitter) = entry.summary.transmitter {
+            self.current.insert(transmitter);
+            if self.seen.insert(transmitter) {
+                self.new += 1
+            }
+        }
+    }
+    fn key(&mut self, key: Key) {
+        if let (Key::Char('\n'), Some(selected)) = (key, &self.selected) {
+            *self.drill_down.borrow_mut() = Some(selected.clone())
+        }
+    }
+    fn keys(&self) -> Vec<(&'static str, &'static str)> {
+        vec![("Enter", "List the devices of the picked manufacturer on the Devices tab")]
+    }
+    fn grabs_input(&self) -> bool {
+        false
+    }
+    fn save(&self, _: &mut State) {
         
     }
-    fn top(&mut self) {
+    fn restore(&mut self, _: &State) {
         
+    }
+    fn reset(&mut self) {
+        // The minute carries on, as frame times still count from the start of the capture
+        self.seen.clear();
+        self.current.clear();
+        self.new = 0;
+        self.history.clear();
+    }
+    fn freeze(&mut self, _: bool) {
+
+    }
+    fn selected(&self) -> Option<MacAddress> {
+        None
+    }
+    fn search(&self, text: &str, devices: &DeviceList) -> Vec<Found> {
+        devices.bar_data().into_iter()
+            .filter(|(name, _)| super::contains(name, text))
+            .map(|(name, _)| Found::Manufacturer(name.to_string()))
+            .collect()
+    }
+    fn select(&mut self, found: &Found) {
+        if let Found::Manufacturer(name) = found {
+            self.selected = Some(name.clone())
+        }
+    }
+    fn navigate(&mut self) -> Option<Navigation> {
+        None
+    }
+
+    fn up(&mut self) {
+        self.step(-1)
+    }
+    fn top(&mut self) {
+        self.selected = None
     }
     fn down(&mut self) {
-        
+        self.step(1)
     }
     fn bottom(&mut self) {
-        
+        self.selected = self.names.last().cloned()
     }
     fn left(&mut self) {
-        
+        self.step(-1)
     }
     fn right(&mut self) {
-        
+        self.step(1)
     }
 }
\ No newline at end of file
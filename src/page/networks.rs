@@ -0,0 +1,237 @@
+use eui48::MacAddress;
+use oui::OuiEntry;
+use tui::{
+    layout::Rect,
+    widgets::{Block, Borders, List, ListItem},
+    style::{Style, Modifier, Color},
+    text::{Span, Spans},
+    terminal::Frame
+};
+
+use super::{Found, LogEntry, Navigation, Page};
+use crate::{DeviceList, KnownDevice, state::State, ui::{self, Key}};
+
+/// Access points with their associated clients nested beneath them
+pub struct Networks {
+    state: ui::ListState,
+    /// The device on each line of the list, as of the last render
+    rows: Vec<Option<MacAddress>>
+}
+impl Networks {
+    pub fn new() -> Self {
+        Self {
+            state: Default::default(),
+            rows: vec![]
+        }
+    }
+}
+impl Page for Networks {
+    fn name(&self) -> &'static str {
+        "Networks"
+    }
+    fn short_name(&self) -> &'static str {
+        "Net"
+    }
+
+    fn render(&mut self, frame: &mut Frame<ui::Backend>, area: Rect, devices: &mut DeviceList) {
+        fn manufacturer(device: Option<&KnownDevice>) -> Span<'static> {
+            match device.and_then(|device| device.manufacturer.as_ref()) {
+                Some(OuiEntry { name_short, .. }) => Span::raw(format!(" {}", name_short)),
+                None => Span::raw("")
+            }
+        }
+
+        let (networks, unassociated) = devices.networks();
+        // The links of a multi-link device are listed together, as one access point spread across bands
+        let mut grouped: Vec<&(MacAddress, Vec<MacAddress>)> = vec![];
+        for network in networks.iter() {
+            if grouped.iter().any(|(bssid, _)| *bssid == network.0) {
+                continue
+            }
+            grouped.push(network);
+            if let Some(mld) = devices.get(&network.0).and_then(|device| device.mld) {
+                grouped.extend(networks.iter().filter(|(bssid, _)| *bssid != network.0 && devices.get(bssid).and_then(|device| device.mld) == Some(mld)));
+            }
+            // Likewise the open and encrypted halves of an OWE transition network
+            let pair = devices.get(&network.0)
+                .and_then(|device| device.owe_pair)
+                .filter(|pair| !grouped.iter().any(|(bssid, _)| bssid == pair));
+            if let Some(pair) = pair {
+                grouped.extend(networks.iter().find(|(bssid, _)| *bssid == pair));
+            }
+        }
+        let multi_link = devices.multi_link_devices();
+        let repeaters = devices.repeaters();
+        let narrow = ui::narrow(area);
+        // Repeaters blur which access point a client really sits behind, so they are called out
+        let repeater = |address: &MacAddress| match repeaters.get(address) {
+            Some(reason) if !narrow => Span::styled(format!(" [repeater: {}]", reason), Style::reset().fg(Color::LightMagenta)),
+            Some(_) => Span::styled(" [repeater]", Style::reset().fg(Color::LightMagenta)),
+            None => Span::raw("")
+        };
+        let mut items = vec![];
+        self.rows.clear();
+        for (bssid, clients) in grouped {
+            let access_point = devices.get(bssid);
+            let mut spans = vec![Span::styled(bssid.to_hex_string(), Style::reset().fg(Color::LightGreen))];
+            if let Some(device) = access_point {
+                match (&device.beacon, device.hidden) {
+                    (Some(ssid), true) if ssid.is_empty() => spans.push(Span::styled(" (hidden)", Style::reset().fg(Color::LightYellow))),
+                    (Some(ssid), hidden) => {
+                        spans.push(Span::styled(format!(" {:?}", ssid), Style::reset().fg(Color::LightCyan)));
+                        if hidden {
+                            spans.push(Span::styled(" (hidden, revealed)", Style::reset().fg(Color::LightYellow)));
+                        }
+                    }
+                    (None, _) => ()
+                }
+            }
+            if let Some(standards) = access_point.and_then(|device| device.standards) {
+                spans.push(Span::styled(format!(" [{}]", standards.badge()), Style::reset().fg(Color::LightBlue)));
+            }
+            spans.push(manufacturer(access_point));
+            spans.push(repeater(bssid));
+            // Anything else sending beacons for this BSSID is impersonating it, or being impersonated by it
+            if let Some(advertisers) = access_point.map(|device| device.advertisers.len()).filter(|&advertisers| advertisers > 1) {
+                spans.push(Span::styled(format!(" [{} transmitters]", advertisers), Style::reset().fg(Color::LightRed).add_modifier(Modifier::BOLD)));
+            }
+            if let Some(excess) = access_point.and_then(|device| device.power?.excess()) {
+                spans.push(Span::styled(format!(" [{}dB over power limit]", excess), Style::reset().fg(Color::LightRed).add_modifier(Modifier::BOLD)));
+            }
+            if let Some(pair) = access_point.and_then(|device| device.owe_pair) {
+                spans.push(Span::styled(
+                    if narrow { " [OWE]".to_string() } else { format!(" [OWE transition with {}]", pair.to_hex_string()) },
+                    Style::reset().fg(Color::LightBlue)
+                ));
+            }
+            match access_point.map(|device| (device.handshake_complete(), device.handshakes.is_empty())) {
+                Some((true, _)) => spans.push(Span::styled(" [handshake]", Style::reset().fg(Color::LightMagenta).add_modifier(Modifier::BOLD))),
+                Some((false, false)) => spans.push(Span::styled(" [partial handshake]", Style::reset().fg(Color::LightMagenta))),
+                _ => ()
+            }
+            if let Some((mld, links)) = access_point.and_then(|device| device.mld).and_then(|mld| Some((mld, multi_link.get(&mld)?))) {
+                spans.push(Span::styled(
+                    if narrow { " [MLD]".to_string() } else { format!(" [MLD {}, {} link(s)]", mld.to_hex_string(), links.len()) },
+                    Style::reset().fg(Color::LightBlue)
+                ));
+            }
+            // The access point's own count includes clients out of earshot
+            let reported = match access_point.and_then(|device| device.load) {
+                Some((stations, utilisation)) => format!(", {} reported, {}% busy", stations, utilisation as u32 * 100 / 255),
+                None => String::new()
+            };
+            spans.push(Span::styled(format!(" ({} client(s){})", clients.len(), reported), Style::reset().add_modifier(Modifier::DIM)));
+            items.push(ListItem::new(Spans::from(spans)));
+            self.rows.push(Some(*bssid));
+            for (index, client) in clients.iter().enumerate() {
+                let branch = if index + 1 == clients.len() { "  └ " } else { "  ├ " };
+                items.push(ListItem::new(Spans::from(vec![
+                    Span::raw(branch),
+                    Span::styled(client.to_hex_string(), Style::reset().fg(Color::LightYellow)),
+                    manufacturer(devices.get(client)),
+                    repeater(client)
+                ])));
+                self.rows.push(Some(*client));
+            }
+        }
+        if !unassociated.is_empty() {
+            items.push(ListItem::new(Spans::from(Span::styled(
+                format!("Not associated ({} client(s))", unassociated.len()),
+                Style::reset().add_modifier(Modifier::DIM)
+            ))));
+            self.rows.push(None);
+            for client in unassociated.iter() {
+                items.push(ListItem::new(Spans::from(vec![
+                    Span::raw("    "),
+                    Span::styled(client.to_hex_string(), Style::reset().fg(Color::LightYellow)),
+                    manufacturer(devices.get(client)),
+                    repeater(client)
+                ])));
+                self.rows.push(Some(*client));
+            }
+        }
+
+        self.state.set_item_count(items.len());
+
+        let rows = &self.rows;
+
+        self.state.follow(rows, || super::newest(rows, devices));
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(format!("Networks ({} access point(s)){}", networks.len(), super::following(&self.state))))
+            .highlight_style(Style::default().bg(Color::Reset).add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(list, area, &mut self.state)
+    }
+
+    fn captured(&mut self, _: &LogEntry) {
+
+    }
+    fn key(&mut self, key: Key) {
+        if key == Key::Char('f') {
+            self.state.newest = !self.state.newest
+        }
+    }
+    fn keys(&self) -> Vec<(&'static str, &'static str)> {
+        vec![("f", "Follow new devices as they turn up, or stay on the selected one")]
+    }
+    fn grabs_input(&self) -> bool {
+        false
+    }
+    fn save(&self, state: &mut State) {
+        state.set("networks.follow", self.state.newest);
+    }
+    fn restore(&mut self, state: &State) {
+        self.state.newest = state.flag("networks.follow").unwrap_or(self.state.newest);
+    }
+    fn reset(&mut self) {
+        self.state.restart()
+    }
+    fn freeze(&mut self, _: bool) {
+
+    }
+    fn selected(&self) -> Option<MacAddress> {
+        self.state.selected().and_then(|index| self.rows.get(index).copied().flatten())
+    }
+    fn search(&self, text: &str, devices: &DeviceList) -> Vec<Found> {
+        let matches = |address: &MacAddress| {
+            let device = devices.get(address);
+            address.to_hex_string().starts_with(&text.to_lowercase().replace('-', ":"))
+                || device.and_then(|device| device.manufacturer.as_ref()).map_or(false, |manufacturer| super::contains(&manufacturer.name_short, text))
+                || device.and_then(|device| device.beacon.as_deref()).map_or(false, |ssid| super::contains(ssid, text))
+        };
+        let (networks, unassociated) = devices.networks();
+        networks.iter()
+            .flat_map(|(bssid, clients)| Some(bssid).into_iter().chain(clients.iter()))
+            .chain(unassociated.iter())
+            .filter(|address| matches(address))
+            .map(|address| Found::Device(*address))
+            .collect()
+    }
+    fn select(&mut self, found: &Found) {
+        if let Found::Device(address) = found {
+            self.state.pin(*address)
+        }
+    }
+    fn navigate(&mut self) -> Option<Navigation> {
+        None
+    }
+
+    fn up(&mut self) {
+        self.state.up()
+    }
+    fn top(&mut self) {
+        self.state.top()
+    }
+    fn down(&mut self) {
+        self.state.down()
+    }
+    fn bottom(&mut self) {
+        self.state.bottom()
+    }
+    fn left(&mut self) {
+
+    }
+    fn right(&mut self) {
+
+    }
+}
@@ -0,0 +1,349 @@
+use std::{collections::VecDeque, time::Duration};
+use eui48::MacAddress;
+use tui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    widgets::{Block, Borders, List, ListItem, Paragraph},
+    style::{Style, Modifier, Color},
+    text::{Span, Spans},
+    terminal::Frame
+};
+
+use super::{Found, Navigation, Page};
+use crate::{DeviceList, filter::Filter, state::State, ui::{self, Key}, wifi::{Kind, Summary}};
+
+/// How many frames the log keeps before dropping the oldest
+const CAPACITY: usize = 5000;
+
+/// A frame as shown in the packet log
+#[derive(Clone)]
+pub struct LogEntry {
+    /// Time since the capture started
+    pub time: Duration,
+    pub sensor: String,
+    pub signal: Option<i8>,
+    pub summary: Summary,
+    pub ssid: Option<String>,
+    /// The channel the frame was heard on, from radiotap or else the channel the interface was tuned to
+    pub channel: Option<u32>,
+    /// Roughly how long the frame took to send
+    pub airtime: Duration
+}
+
+/// Which classes of frame are shown
+struct Toggles {
+    beacons: bool,
+    management: bool,
+    control: bool,
+    data: bool
+}
+impl Toggles {
+    fn shows(&self, summary: &Summary) -> bool {
+        match summary.kind {
+            Kind::Management if summary.is_beacon() => self.beacons,
+            Kind::Management => self.management,
+            Kind::Control => self.control,
+            Kind::Data => self.data,
+            Kind::Extension => self.management
+        }
+    }
+}
+
+pub struct Packets {
+    entries: VecDeque<LogEntry>,
+    toggles: Toggles,
+    filter: Option<Filter>,
+    /// Show the selected frame's details beside the log
+    split: bool,
+    /// The filter being typed, while editing
+    editing: Option<String>,
+    /// Why the last filter entered could not be used
+    filter_error: Option<String>,
+    state: ui::ListState,
+    /// Keep the newest frame selected as more arrive
+    follow: bool
+}
+impl Packets {
+    pub fn new() -> Self {
+        Self {
+            entries: VecDeque::with_capacity(CAPACITY),
+            toggles: Toggles {
+                beacons: true,
+                management: true,
+                control: true,
+                data: true
+            },
+            filter: None,
+            split: false,
+            editing: None,
+            filter_error: None,
+            state: Default::default(),
+            follow: true
+        }
+    }
+    pub fn set_filter(&mut self, filter: Option<Filter>) {
+        self.filter = filter;
+        self.filter_error = None;
+    }
+    fn apply_edit(&mut self) {
+        let source = self.editing.take().unwrap_or_default();
+        if source.trim().is_empty() {
+            self.set_filter(None)
+        } else {
+            match Filter::parse(&source) {
+                Ok(filter) => self.set_filter(Some(filter)),
+                Err(error) => {
+                    self.filter_error = Some(error.to_string());
+                    // Let the mistake be fixed rather than typed out again
+                    self.editing = Some(source);
+                }
+            }
+        }
+    }
+}
+impl Page for Packets {
+    fn name(&self) -> &'static str {
+        "Packets"
+    }
+    fn short_name(&self) -> &'static str {
+        "Pkt"
+    }
+
+    fn render(&mut self, frame: &mut Frame<ui::Backend>, area: Rect, _: &mut DeviceList) {
+        fn toggle(key: &str, name: &str, on: bool) -> Span<'static> {
+            let style = if on {
+                Style::reset().add_modifier(Modifier::BOLD)
+            } else {
+                Style::reset().fg(Color::DarkGray)
+            };
+            Span::styled(format!(" [{}] {} ", key, name), style)
+        }
+
+        let (area, prompt_area) = if self.editing.is_some() {
+            let areas = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(0), Constraint::Length(3)])
+                .split(area);
+            (areas[0], Some(areas[1]))
+        } else {
+            (area, None)
+        };
+
+        let toggles = &self.toggles;
+        let filter = &self.filter;
+        let visible: Vec<_> = self.entries.iter()
+            .filter(|entry| toggles.shows(&entry.summary) && filter.as_ref().map_or(true, |filter| filter.matches(entry)))
+            .collect();
+        self.state.set_item_count(visible.len());
+        if self.follow {
+            self.state.bottom()
+        }
+
+        let narrow = ui::narrow(area);
+        let items: Vec<_> = visible.iter().map(|entry| {
+            let colour = match entry.summary.kind {
+                Kind::Management => Color::LightGreen,
+                Kind::Control => Color::LightYellow,
+                Kind::Data => Color::LightCyan,
+                Kind::Extension => Color::Reset
+            };
+            let mut spans = vec![
+                Span::raw(format!("{:>9.3} ", entry.time.as_secs_f64())),
+                Span::raw(match entry.signal {
+                    Some(signal) => format!("{:>4}dBm ", signal),
+                    None => "        ".to_string()
+                }),
+                Span::styled(format!("{:<16} ", entry.summary.subtype_name()), Style::reset().fg(colour)),
+                Span::raw(format!(
+                    "{} -> {} ",
+                    entry.summary.transmitter.map(|address| address.to_hex_string()).unwrap_or_else(|| format!("{:17}", "")),
+                    entry.summary.receiver.to_hex_string()
+                ))
+            ];
+            if !narrow {
+                spans.push(Span::raw(format!("{:>5}B", entry.summary.length)));
+            }
+            if let Some(ssid) = &entry.ssid {
+                spans.push(Span::styled(format!(" {:?}", ssid), Style::reset().fg(Color::LightCyan)));
+            }
+            ListItem::new(Spans::from(spans))
+        }).collect();
+
+        let mut title = if narrow {
+            vec![
+                toggle("1", "Bcn", toggles.beacons),
+                toggle("2", "Mgmt", toggles.management),
+                toggle("3", "Ctl", toggles.control),
+                toggle("4", "Data", toggles.data),
+                toggle("F", "Flt", self.filter.is_some())
+            ]
+        } else {
+            vec![
+                Span::raw("Packets "),
+                toggle("1", "Beacons", toggles.beacons),
+                toggle("2", "Management", toggles.management),
+                toggle("3", "Control", toggles.control),
+                toggle("4", "Data", toggles.data),
+                toggle("F", "Filter", self.filter.is_some())
+            ]
+        };
+        if let Some(filter) = &self.filter {
+            title.push(Span::styled(format!("{} ", filter), Style::reset().fg(Color::LightCyan)));
+        }
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(Spans::from(title)))
+            .highlight_style(Style::default().bg(Color::Reset).add_modifier(Modifier::REVERSED));
+
+        let selected = self.state.selected().and_then(|index| visible.get(index)).filter(|_| self.split);
+        if let Some(entry) = selected {
+            fn field<'a>(name: &'a str, value: String) -> Spans<'a> {
+                Spans::from(vec![
+                    Span::raw(format!("  {}: ", name)),
+                    Span::styled(value, Style::reset().fg(Color::LightCyan))
+                ])
+            }
+            let summary = &entry.summary;
+            let mut detail = vec![
+                field("Time", format!("{:.6}s", entry.time.as_secs_f64())),
+                field("Sensor", entry.sensor.clone()),
+                field("Signal", entry.signal.map_or_else(|| "not reported".to_string(), |signal| format!("{}dBm", signal))),
+                field("Type", format!("{:?}", summary.kind)),
+                field("Subtype", format!("{} ({})", summary.subtype_name(), summary.subtype)),
+                field("Receiver", summary.receiver.to_hex_string())
+            ];
+            if let Some(transmitter) = summary.transmitter {
+                detail.push(field("Transmitter", transmitter.to_hex_string()));
+            }
+            detail.push(field("Length", format!("{} bytes", summary.length)));
+            if let Some(ssid) = &entry.ssid {
+                detail.push(field("SSID", format!("{:?}", ssid)));
+            }
+            let detail = Paragraph::new(detail)
+                .block(Block::default().borders(Borders::ALL).title("Frame"));
+            let (list_area, detail_area) = ui::split(area);
+            frame.render_stateful_widget(list, list_area, &mut self.state);
+            frame.render_widget(detail, detail_area)
+        } else {
+            frame.render_stateful_widget(list, area, &mut self.state);
+        }
+
+        if let (Some(prompt_area), Some(editing)) = (prompt_area, &self.editing) {
+            let mut prompt = vec![Span::raw(editing.as_str()), Span::styled(" ", Style::reset().add_modifier(Modifier::REVERSED))];
+            if let Some(error) = &self.filter_error {
+                prompt.push(Span::styled(format!("  {}", error), Style::reset().fg(Color::Red)));
+            }
+            let prompt = Paragraph::new(Spans::from(prompt))
+                .block(Block::default().borders(Borders::ALL).title("Filter (Enter to apply, empty to clear)"));
+            frame.render_widget(prompt, prompt_area)
+        }
+    }
+
+    fn captured(&mut self, entry: &LogEntry) {
+        if self.entries.len() >= CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry.clone())
+    }
+    fn key(&mut self, key: Key) {
+        if let Some(editing) = &mut self.editing {
+            match key {
+                Key::Char('\n') => self.apply_edit(),
+                Key::Char(c) => editing.push(c),
+                Key::Backspace => {
+                    editing.pop();
+                }
+                Key::Esc => {
+                    self.editing = None;
+                    self.filter_error = None;
+                }
+                _ => ()
+            }
+            return
+        }
+        match key {
+            Key::Char('F') => self.editing = Some(self.filter.as_ref().map(|filter| filter.to_string()).unwrap_or_default()),
+            Key::Char('1') => self.toggles.beacons = !self.toggles.beacons,
+            Key::Char('2') => self.toggles.management = !self.toggles.management,
+            Key::Char('3') => self.toggles.control = !self.toggles.control,
+            Key::Char('4') => self.toggles.data = !self.toggles.data,
+            Key::Char('v') => self.split = !self.split,
+            _ => ()
+        }
+    }
+    fn keys(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("F", "Edit the display filter"),
+            ("1", "Show or hide beacons"),
+            ("2", "Show or hide other management frames"),
+            ("3", "Show or hide control frames"),
+            ("4", "Show or hide data frames"),
+            ("v", "Show or hide the detail pane")
+        ]
+    }
+
+    fn grabs_input(&self) -> bool {
+        self.editing.is_some()
+    }
+    fn save(&self, state: &mut State) {
+        state.set("packets.beacons", self.toggles.beacons);
+        state.set("packets.management", self.toggles.management);
+        state.set("packets.control", self.toggles.control);
+        state.set("packets.data", self.toggles.data);
+        state.set("packets.split", self.split);
+        match &self.filter {
+            Some(filter) => state.set("packets.filter", filter),
+            None => state.remove("packets.filter")
+        }
+    }
+    fn restore(&mut self, state: &State) {
+        let toggles = &mut self.toggles;
+        toggles.beacons = state.flag("packets.beacons").unwrap_or(toggles.beacons);
+        toggles.management = state.flag("packets.management").unwrap_or(toggles.management);
+        toggles.control = state.flag("packets.control").unwrap_or(toggles.control);
+        toggles.data = state.flag("packets.data").unwrap_or(toggles.data);
+        self.split = state.flag("packets.split").unwrap_or(self.split);
+        // A filter that no longer parses, perhaps from an older version, is dropped
+        self.filter = state.get("packets.filter").and_then(|filter| Filter::parse(filter).ok());
+    }
+    fn reset(&mut self) {
+        self.entries.clear();
+        self.state.top();
+        self.follow = true
+    }
+    fn freeze(&mut self, _: bool) {
+
+    }
+    fn selected(&self) -> Option<MacAddress> {
+        None
+    }
+    fn search(&self, _: &str, _: &DeviceList) -> Vec<Found> {
+        vec![]
+    }
+    fn select(&mut self, _: &Found) {
+        
+    }
+    fn navigate(&mut self) -> Option<Navigation> {
+        None
+    }
+
+    fn up(&mut self) {
+        self.follow = false;
+        self.state.up()
+    }
+    fn top(&mut self) {
+        self.follow = false;
+        self.state.top()
+    }
+    fn down(&mut self) {
+        self.state.down()
+    }
+    fn bottom(&mut self) {
+        self.follow = true;
+        self.state.bottom()
+    }
+    fn left(&mut self) {
+
+    }
+    fn right(&mut self) {
+
+    }
+}
@@ -0,0 +1,140 @@
+use eui48::MacAddress;
+use oui::OuiEntry;
+use tui::{
+    layout::Rect,
+    widgets::{Block, Borders, List, ListItem},
+    style::{Style, Modifier, Color},
+    text::{Span, Spans},
+    terminal::Frame
+};
+
+use super::{Found, LogEntry, Navigation, Page};
+use crate::{DeviceList, state::State, ui::{self, Key}};
+
+/// Network names clients have probed for, with the clients asking for each nested beneath them
+pub struct Probes {
+    state: ui::ListState,
+    /// The client on each line of the list, as of the last render
+    rows: Vec<Option<MacAddress>>
+}
+impl Probes {
+    pub fn new() -> Self {
+        Self {
+            state: Default::default(),
+            rows: vec![]
+        }
+    }
+}
+impl Page for Probes {
+    fn name(&self) -> &'static str {
+        "Probes"
+    }
+    fn short_name(&self) -> &'static str {
+        "Prb"
+    }
+
+    fn render(&mut self, frame: &mut Frame<ui::Backend>, area: Rect, devices: &mut DeviceList) {
+        let probed = devices.probed();
+        let mut items = vec![];
+        self.rows.clear();
+        for (ssid, clients) in probed.iter() {
+            // Nearby access points answering to the name show whether the network is here or somewhere the clients have been
+            let nearby = devices.values().filter(|device| device.beacon.as_deref() == Some(*ssid)).count();
+            let mut spans = vec![
+                Span::styled(format!("{:?}", ssid), Style::reset().fg(Color::LightCyan)),
+                Span::styled(format!(" ({} client(s))", clients.len()), Style::reset().add_modifier(Modifier::DIM))
+            ];
+            if nearby > 0 {
+                spans.push(Span::styled(format!(" [{} access point(s) nearby]", nearby), Style::reset().fg(Color::LightGreen)));
+            }
+            items.push(ListItem::new(Spans::from(spans)));
+            self.rows.push(None);
+            for (index, (client, requests)) in clients.iter().enumerate() {
+                let branch = if index + 1 == clients.len() { "  └ " } else { "  ├ " };
+                let manufacturer = match devices.get(client).and_then(|device| device.manufacturer.as_ref()) {
+                    Some(OuiEntry { name_short, .. }) => format!(" {}", name_short),
+                    None => String::new()
+                };
+                // Most clients probe from randomised addresses, which have no manufacturer
+                let identity = devices.get(client).and_then(|device| device.identity.as_ref()).map_or_else(String::new, |identity| format!(" {}", identity));
+                items.push(ListItem::new(Spans::from(vec![
+                    Span::raw(branch),
+                    Span::styled(client.to_hex_string(), Style::reset().fg(Color::LightYellow)),
+                    Span::raw(manufacturer),
+                    Span::styled(identity, Style::reset().fg(Color::LightMagenta)),
+                    Span::styled(format!(" ({} request(s))", requests), Style::reset().add_modifier(Modifier::DIM))
+                ])));
+                self.rows.push(Some(*client));
+            }
+        }
+
+        self.state.set_item_count(items.len());
+
+        let rows = &self.rows;
+
+        self.state.follow(rows, || super::newest(rows, devices));
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(format!("Probed Networks ({} SSID(s)){}", probed.len(), super::following(&self.state))))
+            .highlight_style(Style::default().bg(Color::Reset).add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(list, area, &mut self.state)
+    }
+
+    fn captured(&mut self, _: &LogEntry) {
+
+    }
+    fn key(&mut self, key: Key) {
+        if key == Key::Char('f') {
+            self.state.newest = !self.state.newest
+        }
+    }
+    fn keys(&self) -> Vec<(&'static str, &'static str)> {
+        vec![("f", "Follow new devices as they turn up, or stay on the selected one")]
+    }
+    fn grabs_input(&self) -> bool {
+        false
+    }
+    fn save(&self, state: &mut State) {
+        state.set("probes.follow", self.state.newest);
+    }
+    fn restore(&mut self, state: &State) {
+        self.state.newest = state.flag("probes.follow").unwrap_or(self.state.newest);
+    }
+    fn reset(&mut self) {
+        self.state.restart()
+    }
+    fn freeze(&mut self, _: bool) {
+
+    }
+    fn selected(&self) -> Option<MacAddress> {
+        self.state.selected().and_then(|index| self.rows.get(index).copied().flatten())
+    }
+    fn search(&self, _: &str, _: &DeviceList) -> Vec<Found> {
+        vec![]
+    }
+    fn select(&mut self, _: &Found) {
+        
+    }
+    fn navigate(&mut self) -> Option<Navigation> {
+        None
+    }
+
+    fn up(&mut self) {
+        self.state.up()
+    }
+    fn top(&mut self) {
+        self.state.top()
+    }
+    fn down(&mut self) {
+        self.state.down()
+    }
+    fn bottom(&mut self) {
+        self.state.bottom()
+    }
+    fn left(&mut self) {
+
+    }
+    fn right(&mut self) {
+
+    }
+}
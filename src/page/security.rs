@@ -0,0 +1,186 @@
+use eui48::MacAddress;
+use tui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    widgets::{Block, Borders, List, ListItem, Paragraph},
+    style::{Style, Modifier, Color},
+    text::{Span, Spans},
+    terminal::Frame
+};
+
+use super::{Found, LogEntry, Navigation, Page};
+use crate::{DeviceList, policy::{Level, Pmf}, state::State, ui::{self, Key}};
+
+/// Every level of security, weakest first
+const LEVELS: [Level; 6] = [Level::Open, Level::Owe, Level::Wep, Level::Wpa, Level::Wpa2, Level::Wpa3];
+
+/// How the networks around are secured, with those anyone can join or read listed out
+pub struct Security {
+    state: ui::ListState,
+    /// The access point on each line of the list, as of the last render
+    rows: Vec<Option<MacAddress>>
+}
+impl Security {
+    pub fn new() -> Self {
+        Self {
+            state: Default::default(),
+            rows: vec![]
+        }
+    }
+}
+
+/// Open and WEP networks are as good as unencrypted, and WPA close behind
+fn colour(level: Level) -> Color {
+    match level {
+        Level::Open | Level::Wep => Color::LightRed,
+        Level::Wpa => Color::LightYellow,
+        Level::Owe | Level::Wpa2 => Color::LightCyan,
+        Level::Wpa3 => Color::LightGreen
+    }
+}
+
+impl Page for Security {
+    fn name(&self) -> &'static str {
+        "Security"
+    }
+    fn short_name(&self) -> &'static str {
+        "Sec"
+    }
+
+    fn render(&mut self, frame: &mut Frame<ui::Backend>, area: Rect, devices: &mut DeviceList) {
+        let mut networks: Vec<_> = devices.iter()
+            .filter_map(|(&address, device)| Some((address, device.security?, device)))
+            .collect();
+        networks.sort_by_key(|&(address, security, _)| (security.level, address));
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(LEVELS.len() as u16 + 4), Constraint::Min(0)])
+            .split(area);
+
+        let total = networks.len().max(1);
+        // Leaving room for the name, count and percentage
+        let width = areas[0].width.saturating_sub(30) as usize;
+        let mut summary = vec![];
+        for &level in LEVELS.iter() {
+            let count = networks.iter().filter(|(_, security, _)| security.level == level).count();
+            let enterprise = networks.iter().filter(|(_, security, _)| security.level == level && security.enterprise).count();
+            summary.push(Spans::from(vec![
+                Span::styled(format!("  {:<5}", level.to_string()), Style::reset().fg(colour(level)).add_modifier(Modifier::BOLD)),
+                Span::raw(format!("{:>6} {:>5.1}% ", count, count as f64 * 100.0 / total as f64)),
+                Span::styled("█".repeat(count * width / total), Style::reset().fg(colour(level))),
+                Span::styled(
+                    if enterprise > 0 { format!(" ({} enterprise)", enterprise) } else { String::new() },
+                    Style::reset().add_modifier(Modifier::DIM)
+                )
+            ]));
+        }
+        let pmf_required = networks.iter().filter(|(_, security, _)| security.pmf == Pmf::Required).count();
+        let tkip = networks.iter().filter(|(_, security, _)| security.tkip).count();
+        summary.push(Spans::from(""));
+        summary.push(Spans::from(Span::styled(
+            format!("  {} require PMF, {} still allow TKIP", pmf_required, tkip),
+            Style::reset().add_modifier(Modifier::DIM)
+        )));
+        let summary = Paragraph::new(summary)
+            .block(Block::default().borders(Borders::ALL).title(format!("Security ({} network(s))", networks.len())));
+        frame.render_widget(summary, areas[0]);
+
+        // Networks anyone in range can join or read are listed out, grouped by how they are secured
+        let mut items = vec![];
+        self.rows.clear();
+        for &level in [Level::Open, Level::Wep].iter() {
+            let weak: Vec<_> = networks.iter().filter(|(_, security, _)| security.level == level).collect();
+            if weak.is_empty() {
+                continue
+            }
+            items.push(ListItem::new(Span::styled(
+                format!("{} ({})", level, weak.len()),
+                Style::reset().fg(colour(level)).add_modifier(Modifier::BOLD)
+            )));
+            self.rows.push(None);
+            for (address, _, device) in weak {
+                let ssid = match device.beacon.as_deref() {
+                    Some("") | None => Span::styled(" (hidden)", Style::reset().fg(Color::LightYellow)),
+                    Some(ssid) => Span::styled(format!(" {:?}", ssid), Style::reset().fg(Color::LightRed))
+                };
+                let manufacturer = device.manufacturer.as_ref().map_or_else(String::new, |entry| format!(" {}", entry.name_short));
+                items.push(ListItem::new(Spans::from(vec![
+                    Span::raw("  "),
+                    Span::styled(address.to_hex_string(), Style::reset().fg(Color::LightRed)),
+                    ssid,
+                    Span::styled(manufacturer, Style::reset().add_modifier(Modifier::DIM))
+                ])));
+                self.rows.push(Some(*address));
+            }
+        }
+        if items.is_empty() {
+            items.push(ListItem::new(Span::styled("No open or WEP networks heard", Style::reset().add_modifier(Modifier::DIM))));
+            self.rows.push(None);
+        }
+        self.state.set_item_count(items.len());
+        let rows = &self.rows;
+        self.state.follow(rows, || super::newest(rows, devices));
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(format!("Open and WEP Networks{}", super::following(&self.state))))
+            .highlight_style(Style::default().bg(Color::Reset).add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(list, areas[1], &mut self.state)
+    }
+
+    fn captured(&mut self, _: &LogEntry) {
+
+    }
+    fn key(&mut self, key: Key) {
+        if key == Key::Char('f') {
+            self.state.newest = !self.state.newest
+        }
+    }
+    fn keys(&self) -> Vec<(&'static str, &'static str)> {
+        vec![("f", "Follow new devices as they turn up, or stay on the selected one")]
+    }
+    fn grabs_input(&self) -> bool {
+        false
+    }
+    fn save(&self, state: &mut State) {
+        state.set("security.follow", self.state.newest);
+    }
+    fn restore(&mut self, state: &State) {
+        self.state.newest = state.flag("security.follow").unwrap_or(self.state.newest);
+    }
+    fn reset(&mut self) {
+        self.state.restart()
+    }
+    fn freeze(&mut self, _: bool) {
+
+    }
+    fn selected(&self) -> Option<MacAddress> {
+        self.state.selected().and_then(|index| self.rows.get(index).copied().flatten())
+    }
+    fn search(&self, _: &str, _: &DeviceList) -> Vec<Found> {
+        vec![]
+    }
+    fn select(&mut self, _: &Found) {
+        
+    }
+    fn navigate(&mut self) -> Option<Navigation> {
+        None
+    }
+
+    fn up(&mut self) {
+        self.state.up()
+    }
+    fn top(&mut self) {
+        self.state.top()
+    }
+    fn down(&mut self) {
+        self.state.down()
+    }
+    fn bottom(&mut self) {
+        self.state.bottom()
+    }
+    fn left(&mut self) {
+
+    }
+    fn right(&mut self) {
+
+    }
+}
@@ -0,0 +1,149 @@
+use std::{collections::BTreeMap, time::Duration};
+use eui48::MacAddress;
+use tui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    widgets::{BarChart, Block, Borders, List, ListItem},
+    style::{Style, Modifier, Color},
+    text::{Span, Spans},
+    terminal::Frame
+};
+
+use super::{Found, LogEntry, Navigation, Page};
+use crate::{DeviceList, nl80211, state::State, ui::{self, Key}};
+
+/// How busy each channel is, for picking which to lock onto
+pub struct Spectrum {
+    state: ui::ListState,
+    /// Frames heard and the estimated time they took to send, by channel
+    channels: BTreeMap<u32, (u64, Duration)>,
+    /// Frames whose channel isn't known
+    unknown: u64
+}
+impl Spectrum {
+    pub fn new() -> Self {
+        Self {
+            state: Default::default(),
+            channels: BTreeMap::new(),
+            unknown: 0
+        }
+    }
+}
+impl Page for Spectrum {
+    fn name(&self) -> &'static str {
+        "Spectrum"
+    }
+    fn short_name(&self) -> &'static str {
+        "Spc"
+    }
+
+    fn render(&mut self, frame: &mut Frame<ui::Backend>, area: Rect, _: &mut DeviceList) {
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(area);
+
+        let labels: Vec<_> = self.channels.keys().map(u32::to_string).collect();
+        let bar_data: Vec<_> = labels.iter()
+            .zip(self.channels.values())
+            .map(|(label, (_, airtime))| (label.as_str(), airtime.as_millis() as u64))
+            .collect();
+        let barchart = BarChart::default()
+            .block(Block::default().borders(Borders::ALL).title("Airtime per Channel (ms)"))
+            .data(&bar_data)
+            .bar_width(4)
+            .bar_gap(1)
+            .bar_style(Style::reset().fg(Color::LightGreen))
+            .value_style(Style::reset().fg(Color::LightGreen).add_modifier(Modifier::REVERSED));
+        frame.render_widget(barchart, areas[0]);
+
+        // Busiest first, as those are the channels worth listening to
+        let total: Duration = self.channels.values().map(|&(_, airtime)| airtime).sum();
+        let mut channels: Vec<_> = self.channels.iter().collect();
+        channels.sort_by(|(l, (_, l_airtime)), (r, (_, r_airtime))| r_airtime.cmp(l_airtime).then(l.cmp(r)));
+        let mut items: Vec<_> = channels.into_iter()
+            .map(|(&channel, &(frames, airtime))| {
+                let band = nl80211::channel_to_frequency(channel).and_then(nl80211::frequency_to_band).map_or_else(|| "?".to_string(), |band| band.to_string());
+                ListItem::new(Spans::from(vec![
+                    Span::styled(format!("{:>4}", channel), Style::reset().fg(Color::LightGreen).add_modifier(Modifier::BOLD)),
+                    Span::styled(format!(" {:<7}", band), Style::reset().add_modifier(Modifier::DIM)),
+                    Span::styled(format!("{:>10} frame(s)", frames), Style::reset().fg(Color::LightCyan)),
+                    Span::raw(format!("{:>10.3}s", airtime.as_secs_f64())),
+                    Span::raw(format!(" {:>5.1}%", airtime.as_secs_f64() * 100.0 / total.as_secs_f64().max(f64::EPSILON)))
+                ]))
+            })
+            .collect();
+        if self.unknown > 0 {
+            items.push(ListItem::new(Span::styled(format!("{} frame(s) on an unknown channel", self.unknown), Style::reset().add_modifier(Modifier::DIM))));
+        }
+        self.state.set_item_count(items.len());
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("Channels by Airtime"))
+            .highlight_style(Style::default().bg(Color::Reset).add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, areas[1], &mut self.state)
+    }
+
+    fn captured(&mut self, entry: &LogEntry) {
+        match entry.channel {
+            Some(channel) => {
+                let (frames, airtime) = self.channels.entry(channel).or_default();
+                *frames += 1;
+                *airtime += entry.airtime;
+            }
+            None => self.unknown += 1
+        }
+    }
+    fn key(&mut self, _: Key) {
+
+    }
+    fn keys(&self) -> Vec<(&'static str, &'static str)> {
+        vec![]
+    }
+    fn grabs_input(&self) -> bool {
+        false
+    }
+    fn save(&self, _: &mut State) {
+
+    }
+    fn restore(&mut self, _: &State) {
+
+    }
+    fn reset(&mut self) {
+        self.channels.clear();
+        self.unknown = 0;
+        self.state.top()
+    }
+    fn freeze(&mut self, _: bool) {
+
+    }
+    fn selected(&self) -> Option<MacAddress> {
+        None
+    }
+    fn search(&self, _: &str, _: &DeviceList) -> Vec<Found> {
+        vec![]
+    }
+    fn select(&mut self, _: &Found) {
+        
+    }
+    fn navigate(&mut self) -> Option<Navigation> {
+        None
+    }
+
+    fn up(&mut self) {
+        self.state.up()
+    }
+    fn top(&mut self) {
+        self.state.top()
+    }
+    fn down(&mut self) {
+        self.state.down()
+    }
+    fn bottom(&mut self) {
+        self.state.bottom()
+    }
+    fn left(&mut self) {
+
+    }
+    fn right(&mut self) {
+
+    }
+}
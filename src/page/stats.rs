@@ -0,0 +1,217 @@
+use std::{cell::RefCell, rc::Rc};
+use eui48::MacAddress;
+use tui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    widgets::{Block, Borders, List, ListItem, Paragraph, Sparkline},
+    style::{Style, Modifier, Color},
+    text::{Span, Spans},
+    terminal::Frame
+};
+
+use super::{Found, LogEntry, Navigation, Page};
+use crate::{DeviceList, state::State, stats, ui::{self, Key}, wifi::Kind};
+
+/// Counts of each kind of frame heard, throughput graphs and how much the capture has dropped
+pub struct Stats {
+    /// Shared with the capture loop, which does the counting
+    stats: Rc<RefCell<stats::Stats>>,
+    state: ui::ListState
+}
+impl Stats {
+    pub fn new(stats: Rc<RefCell<stats::Stats>>) -> Self {
+        Self {
+            stats,
+            state: Default::default()
+        }
+    }
+}
+impl Page for Stats {
+    fn name(&self) -> &'static str {
+        "Stats"
+    }
+    fn short_name(&self) -> &'static str {
+        "Sta"
+    }
+
+    fn render(&mut self, frame: &mut Frame<ui::Backend>, area: Rect, devices: &mut DeviceList) {
+        fn total<'a>(name: &'a str, value: String) -> Spans<'a> {
+            Spans::from(vec![
+                Span::raw(format!("  {}: ", name)),
+                Span::styled(value, Style::reset().fg(Color::LightCyan))
+            ])
+        }
+
+        let mut stats = self.stats.borrow_mut();
+        stats.advance();
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(6), Constraint::Length(6), Constraint::Min(0)])
+            .split(area);
+
+        let (frames, bytes) = stats.throughput(areas[0].width.saturating_sub(2) as usize);
+        let last = |values: &[u64]| values.last().copied().unwrap_or(0);
+        let peak = |values: &[u64]| values.iter().copied().max().unwrap_or(0);
+        frame.render_widget(
+            Sparkline::default()
+                .block(Block::default().borders(Borders::ALL).title(format!("Frames/s (now {}, peak {})", last(&frames), peak(&frames))))
+                .style(Style::reset().fg(Color::LightGreen))
+                .data(&frames),
+            areas[0]
+        );
+        frame.render_widget(
+            Sparkline::default()
+                .block(Block::default().borders(Borders::ALL).title(format!("Bytes/s (now {}, peak {})", last(&bytes), peak(&bytes))))
+                .style(Style::reset().fg(Color::LightCyan))
+                .data(&bytes),
+            areas[1]
+        );
+
+        let mut items = vec![];
+        let mut kind = None;
+        for (&(frame_kind, _), &(name, count)) in stats.subtypes.iter() {
+            if kind != Some(frame_kind) {
+                kind = Some(frame_kind);
+                let colour = match frame_kind {
+                    Kind::Management => Color::LightGreen,
+                    Kind::Control => Color::LightYellow,
+                    Kind::Data => Color::LightCyan,
+                    Kind::Extension => Color::Reset
+                };
+                items.push(ListItem::new(Span::styled(format!("{:?}", frame_kind), Style::reset().fg(colour).add_modifier(Modifier::BOLD))));
+            }
+            items.push(ListItem::new(Spans::from(vec![
+                Span::raw(format!("  {:<18}", name)),
+                Span::styled(format!("{:>10}", count), Style::reset().fg(Color::LightCyan)),
+                Span::raw(format!(" {:>5.1}%", count as f64 * 100.0 / stats.frames.max(1) as f64))
+            ])));
+        }
+        self.state.set_item_count(items.len());
+        let counters = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("Frame Types"))
+            .highlight_style(Style::default().bg(Color::Reset).add_modifier(Modifier::REVERSED));
+
+        let mut totals = vec![
+            total("Frames", stats.frames.to_string()),
+            total("Bytes", stats.bytes.to_string()),
+            total("Malformed", stats.malformed.to_string())
+        ];
+        if stats.sampled_out > 0 {
+            totals.push(total("Data frames skipped while sampling", stats.sampled_out.to_string()));
+        }
+        if !stats.unreadable.is_empty() {
+            totals.push(total("Unreadable bodies", stats.unreadable.values().sum::<u64>().to_string()));
+            for (reason, count) in stats.unreadable.iter() {
+                totals.push(Spans::from(vec![
+                    Span::raw(format!("    {}: ", reason)),
+                    Span::styled(count.to_string(), Style::reset().fg(Color::LightCyan))
+                ]));
+            }
+        }
+        let suppression = &devices.suppression;
+        if suppression.is_enabled() {
+            totals.push(total("Frames from weak untracked devices", suppression.weak.to_string()));
+            totals.push(total("Repeated beacons only counted", suppression.collapsed.to_string()));
+            totals.push(total("Acknowledgements to untracked devices", suppression.acked.to_string()));
+        }
+        // Only some adapters report the noise floor, so many captures have nothing to show here
+        if !stats.channels.is_empty() {
+            totals.push(Spans::from(""));
+            totals.push(Spans::from(Span::styled("Signal to noise by channel", Style::reset().add_modifier(Modifier::BOLD))));
+            for (channel, quality) in stats.channels.iter() {
+                totals.push(Spans::from(vec![
+                    Span::raw(format!("  {}: ", channel)),
+                    Span::styled(quality.to_string(), Style::reset().fg(Color::LightCyan)),
+                    Span::raw(format!(" over {} frame(s)", quality.frames))
+                ]));
+            }
+        }
+        if let Some(profile) = &stats.profile {
+            totals.push(Spans::from(""));
+            totals.push(Spans::from(Span::styled("Time spent in the last second", Style::reset().add_modifier(Modifier::BOLD))));
+            // The slowest stage is the one limiting how many frames can be handled
+            let slowest = profile.last.iter().max().copied().unwrap_or_default();
+            for &stage in stats::Stage::ALL.iter() {
+                let spent = profile.last[stage as usize];
+                let colour = if spent == slowest && !spent.is_zero() { Color::LightYellow } else { Color::LightCyan };
+                totals.push(Spans::from(vec![
+                    Span::raw(format!("  {}: ", stage.name())),
+                    Span::styled(format!("{:.1}ms", spent.as_secs_f64() * 1000.0), Style::reset().fg(colour)),
+                    Span::raw(format!(" {:.1}%", spent.as_secs_f64() * 100.0))
+                ]));
+            }
+        }
+        if stats.drops.is_empty() {
+            totals.push(Spans::from(Span::styled("  Drop counts not available from a capture helper", Style::reset().add_modifier(Modifier::DIM))));
+        }
+        for (sensor, drops) in stats.drops.iter() {
+            totals.push(Spans::from(""));
+            totals.push(Spans::from(Span::styled(sensor.as_str(), Style::reset().add_modifier(Modifier::BOLD))));
+            totals.push(total("Received", drops.received.to_string()));
+            totals.push(total("Dropped", drops.dropped.to_string()));
+            totals.push(total("Dropped by interface", drops.interface.to_string()));
+        }
+        let totals = Paragraph::new(totals)
+            .block(Block::default().borders(Borders::ALL).title("Totals"));
+
+        let (counters_area, totals_area) = ui::split(areas[2]);
+        frame.render_stateful_widget(counters, counters_area, &mut self.state);
+        frame.render_widget(totals, totals_area)
+    }
+
+    fn captured(&mut self, _: &LogEntry) {
+
+    }
+    fn key(&mut self, _: Key) {
+
+    }
+    fn keys(&self) -> Vec<(&'static str, &'static str)> {
+        vec![]
+    }
+    fn grabs_input(&self) -> bool {
+        false
+    }
+    fn save(&self, _: &mut State) {
+
+    }
+    fn restore(&mut self, _: &State) {
+
+    }
+    fn reset(&mut self) {
+        self.stats.borrow_mut().reset();
+        self.state.top()
+    }
+    fn freeze(&mut self, _: bool) {
+
+    }
+    fn selected(&self) -> Option<MacAddress> {
+        None
+    }
+    fn search(&self, _: &str, _: &DeviceList) -> Vec<Found> {
+        vec![]
+    }
+    fn select(&mut self, _: &Found) {
+        
+    }
+    fn navigate(&mut self) -> Option<Navigation> {
+        None
+    }
+
+    fn up(&mut self) {
+        self.state.up()
+    }
+    fn top(&mut self) {
+        self.state.top()
+    }
+    fn down(&mut self) {
+        self.state.down()
+    }
+    fn bottom(&mut self) {
+        self.state.bottom()
+    }
+    fn left(&mut self) {
+
+    }
+    fn right(&mut self) {
+
+    }
+}
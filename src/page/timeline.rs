@@ -0,0 +1,181 @@
+use std::time::{Duration, Instant};
+use eui48::MacAddress;
+use tui::{
+    layout::Rect,
+    widgets::{Block, Borders, canvas::{Canvas, Line}},
+    style::{Style, Modifier, Color},
+    symbols::Marker,
+    text::Span,
+    terminal::Frame
+};
+
+use super::{Found, LogEntry, Navigation, Page};
+use crate::{DeviceList, PRESENCE_GAP, state::State, ui::{self, Key}};
+
+/// Room for an address and a short manufacturer name ahead of each bar
+const LABEL: usize = 28;
+
+/// When each device arrived and left over the session, as a bar for each of its visits
+pub struct Timeline {
+    started: Instant,
+    state: ui::ListState,
+    /// The first row shown, kept so the selection stays in view
+    offset: usize,
+    /// The device on each row, in the order they arrived, as of the last render
+    rows: Vec<Option<MacAddress>>
+}
+impl Timeline {
+    pub fn new(started: Instant) -> Self {
+        Self {
+            started,
+            state: Default::default(),
+            offset: 0,
+            rows: vec![]
+        }
+    }
+}
+impl Page for Timeline {
+    fn name(&self) -> &'static str {
+        "Timeline"
+    }
+    fn short_name(&self) -> &'static str {
+        "Tml"
+    }
+
+    fn render(&mut self, frame: &mut Frame<ui::Backend>, area: Rect, devices: &mut DeviceList) {
+        let mut arrivals: Vec<_> = devices.iter()
+            .filter(|(_, device)| device.sent && !device.presence.is_empty())
+            .map(|(address, device)| (device.presence[0].0, *address))
+            .collect();
+        arrivals.sort();
+        self.rows = arrivals.into_iter().map(|(_, address)| Some(address)).collect();
+        self.state.set_item_count(self.rows.len());
+        let rows = &self.rows;
+        self.state.follow(rows, || super::newest(rows, devices));
+
+        // Replayed captures can run ahead of the clock
+        let now = devices.values().filter_map(|device| device.last_seen).max().unwrap_or_default().max(self.started.elapsed());
+        let width = area.width.saturating_sub(2) as usize;
+        let height = area.height.saturating_sub(2) as usize;
+        // The bottom row is the time axis
+        let shown = height.saturating_sub(1);
+        let selected = self.state.selected().unwrap_or(0);
+        if selected < self.offset {
+            self.offset = selected;
+        } else if shown > 0 && selected >= self.offset + shown {
+            self.offset = selected + 1 - shown;
+        }
+        let offset = self.offset;
+
+        let columns = width.saturating_sub(LABEL).max(1);
+        let x = |time: Duration| LABEL as f64 + (time.as_secs_f64() / now.as_secs_f64().max(1.0) * (columns - 1) as f64).round();
+        let clock = |time: Duration| {
+            let seconds = time.as_secs();
+            format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+        };
+        let devices = &*devices;
+        let canvas = Canvas::default()
+            .block(Block::default().borders(Borders::ALL).title(format!(
+                "Timeline ({} device(s)){}", self.rows.len(), super::following(&self.state)
+            )))
+            .marker(Marker::Block)
+            .x_bounds([0.0, width.saturating_sub(1) as f64])
+            .y_bounds([0.0, height.saturating_sub(1) as f64])
+            .paint(move |context| {
+                for (row, address) in rows.iter().enumerate().skip(offset).take(shown) {
+                    let (address, device) = match address.and_then(|address| Some((address, devices.get(&address)?))) {
+                        Some(found) => found,
+                        None => continue
+                    };
+                    let y = (height - 1 - (row - offset)) as f64;
+                    // Devices still about are brighter than those that have left
+                    let here = device.presence.last().map_or(false, |&(_, until)| until + PRESENCE_GAP >= now);
+                    let colour = match (row == selected, here) {
+                        (true, _) => Color::LightYellow,
+                        (false, true) => Color::LightGreen,
+                        (false, false) => Color::Cyan
+                    };
+                    for &(from, until) in device.presence.iter() {
+                        context.draw(&Line { x1: x(from), y1: y, x2: x(until), y2: y, color: colour });
+                    }
+                    let manufacturer = device.manufacturer.as_ref().map_or("", |manufacturer| manufacturer.name_short.as_str());
+                    let label = format!("{} {:.*}", address.to_hex_string(), LABEL - 19, manufacturer);
+                    let style = if row == selected { Style::reset().add_modifier(Modifier::REVERSED) } else { Style::reset() };
+                    context.print(0.0, y, Span::styled(label, style));
+                }
+                let end = clock(now);
+                context.print(LABEL as f64, 0.0, Span::styled(clock(Duration::default()), Style::reset().add_modifier(Modifier::DIM)));
+                context.print(width.saturating_sub(end.len()) as f64, 0.0, Span::styled(end, Style::reset().add_modifier(Modifier::DIM)));
+            });
+        frame.render_widget(canvas, area)
+    }
+
+    fn captured(&mut self, _: &LogEntry) {
+
+    }
+    fn key(&mut self, key: Key) {
+        if key == Key::Char('f') {
+            self.state.newest = !self.state.newest
+        }
+    }
+    fn keys(&self) -> Vec<(&'static str, &'static str)> {
+        vec![("f", "Follow new devices as they turn up, or stay on the selected one")]
+    }
+    fn grabs_input(&self) -> bool {
+        false
+    }
+    fn save(&self, state: &mut State) {
+        state.set("timeline.follow", self.state.newest);
+    }
+    fn restore(&mut self, state: &State) {
+        self.state.newest = state.flag("timeline.follow").unwrap_or(self.state.newest);
+    }
+    fn reset(&mut self) {
+        self.offset = 0;
+        self.state.restart()
+    }
+    fn freeze(&mut self, _: bool) {
+
+    }
+    fn selected(&self) -> Option<MacAddress> {
+        self.state.selected().and_then(|index| self.rows.get(index).copied().flatten())
+    }
+    fn search(&self, text: &str, devices: &DeviceList) -> Vec<Found> {
+        self.rows.iter()
+            .flatten()
+            .filter(|address| {
+                let manufacturer = devices.get(address).and_then(|device| device.manufacturer.as_ref());
+                address.to_hex_string().starts_with(&text.to_lowercase().replace('-', ":"))
+                    || manufacturer.map_or(false, |manufacturer| super::contains(&manufacturer.name_short, text))
+            })
+            .map(|address| Found::Device(*address))
+            .collect()
+    }
+    fn select(&mut self, found: &Found) {
+        if let Found::Device(address) = found {
+            self.state.pin(*address)
+        }
+    }
+    fn navigate(&mut self) -> Option<Navigation> {
+        None
+    }
+
+    fn up(&mut self) {
+        self.state.up()
+    }
+    fn top(&mut self) {
+        self.state.top()
+    }
+    fn down(&mut self) {
+        self.state.down()
+    }
+    fn bottom(&mut self) {
+        self.state.bottom()
+    }
+    fn left(&mut self) {
+
+    }
+    fn right(&mut self) {
+
+    }
+}
@@ -0,0 +1,220 @@
+use std::{cell::RefCell, collections::VecDeque, io::Write, rc::Rc, time::{Duration, Instant}};
+use eui48::MacAddress;
+use tui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    widgets::{Block, Borders, Gauge, Paragraph, Sparkline},
+    style::{Style, Modifier, Color},
+    text::{Span, Spans},
+    terminal::Frame
+};
+
+use super::{Found, LogEntry, Navigation, Page};
+use crate::{DeviceList, state::State, ui::{self, Key}};
+
+/// Signal strengths mapped onto the ends of the bar, weaker or stronger ones being clamped
+const WEAKEST: i8 = -95;
+const STRONGEST: i8 = -25;
+/// How many readings are kept for the history graph
+const HISTORY: usize = 512;
+/// Readings older than this are taken to mean the target has gone out of range
+const STALE: Duration = Duration::from_secs(5);
+/// The bell rings this often at the weakest signal, down to the fastest at the strongest
+const SLOWEST_BEEP: Duration = Duration::from_millis(2000);
+const FASTEST_BEEP: Duration = Duration::from_millis(100);
+
+/// How strong a signal is between the weakest and strongest expected, from 0 to 1
+fn strength(signal: i8) -> f64 {
+    (signal.max(WEAKEST).min(STRONGEST) - WEAKEST) as f64 / (STRONGEST - WEAKEST) as f64
+}
+
+/// Follows the signal strength of one device, for finding where it is by walking towards the strongest signal
+pub struct Track {
+    /// Shared with the capture loop, where the target is picked from whichever page has a device selected
+    target: Rc<RefCell<Option<MacAddress>>>,
+    /// The device readings were last gathered for, to notice when the target changes
+    tracking: Option<MacAddress>,
+    /// Signal strengths heard from the target, newest last
+    history: VecDeque<i8>,
+    /// When the target was last heard, and by which sensor
+    heard: Option<(Instant, String)>,
+    /// Ring the terminal bell faster as the signal gets stronger
+    beep: bool,
+    last_beep: Instant
+}
+impl Track {
+    pub fn new(target: Rc<RefCell<Option<MacAddress>>>) -> Self {
+        Self {
+            target,
+            tracking: None,
+            history: VecDeque::new(),
+            heard: None,
+            beep: false,
+            last_beep: Instant::now()
+        }
+    }
+    /// Start afresh if a different device has been picked since
+    fn follow_target(&mut self) {
+        let target = *self.target.borrow();
+        if target != self.tracking {
+            self.tracking = target;
+            self.history.clear();
+            self.heard = None;
+        }
+    }
+}
+impl Page for Track {
+    fn name(&self) -> &'static str {
+        "Track"
+    }
+    fn short_name(&self) -> &'static str {
+        "Trk"
+    }
+
+    fn render(&mut self, frame: &mut Frame<ui::Backend>, area: Rect, devices: &mut DeviceList) {
+        self.follow_target();
+        let target = match self.tracking {
+            Some(target) => target,
+            None => {
+                let help = Paragraph::new("Select a device on another tab and press t to track it")
+                    .block(Block::default().borders(Borders::ALL).title("Track"));
+                return frame.render_widget(help, area)
+            }
+        };
+        let current = self.history.back().copied().filter(|_| self.heard.as_ref().map_or(false, |(heard, _)| heard.elapsed() < STALE));
+
+        if let (true, Some(signal)) = (self.beep, current) {
+            let interval = SLOWEST_BEEP.as_secs_f64() - (SLOWEST_BEEP - FASTEST_BEEP).as_secs_f64() * strength(signal);
+            if self.last_beep.elapsed().as_secs_f64() >= interval {
+                let mut stdout = std::io::stdout();
+                let _ = stdout.write_all(b"\x07").and_then(|_| stdout.flush());
+                self.last_beep = Instant::now();
+            }
+        }
+
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(7), Constraint::Min(4), Constraint::Length(5)])
+            .split(area);
+        let title = match devices.get(&target).and_then(|device| device.manufacturer.as_ref()) {
+            Some(manufacturer) => format!("Tracking {} ({})", target.to_hex_string(), manufacturer.name_short),
+            None => format!("Tracking {}", target.to_hex_string())
+        };
+        let (label, ratio, colour) = match current {
+            Some(signal) => {
+                let ratio = strength(signal);
+                let colour = if ratio > 0.66 { Color::LightGreen } else if ratio > 0.33 { Color::LightYellow } else { Color::LightRed };
+                (format!("{} dBm", signal), ratio, colour)
+            }
+            None => ("Not heard recently".to_string(), 0.0, Color::DarkGray)
+        };
+        frame.render_widget(
+            Gauge::default()
+                .block(Block::default().borders(Borders::ALL).title(title))
+                .gauge_style(Style::reset().fg(colour).add_modifier(Modifier::BOLD))
+                .label(Span::styled(label, Style::reset().add_modifier(Modifier::BOLD)))
+                .ratio(ratio),
+            areas[0]
+        );
+
+        let width = areas[1].width.saturating_sub(2) as usize;
+        let history: Vec<u64> = self.history.iter()
+            .skip(self.history.len().saturating_sub(width))
+            .map(|&signal| (strength(signal) * 100.0) as u64 + 1)
+            .collect();
+        frame.render_widget(
+            Sparkline::default()
+                .block(Block::default().borders(Borders::ALL).title("Signal History"))
+                .style(Style::reset().fg(Color::LightCyan))
+                .max(101)
+                .data(&history),
+            areas[1]
+        );
+
+        let heard = match &self.heard {
+            Some((heard, sensor)) => format!("{:.1}s ago by {}", heard.elapsed().as_secs_f64(), sensor),
+            None => "never".to_string()
+        };
+        let peak = self.history.iter().copied().max();
+        let info = Paragraph::new(vec![
+            Spans::from(vec![Span::raw("  Last heard: "), Span::styled(heard, Style::reset().fg(Color::LightCyan))]),
+            Spans::from(vec![
+                Span::raw("  Strongest: "),
+                Span::styled(peak.map_or_else(|| "-".to_string(), |peak| format!("{} dBm", peak)), Style::reset().fg(Color::LightCyan))
+            ]),
+            Spans::from(vec![
+                Span::raw("  Beep: "),
+                Span::styled(if self.beep { "on" } else { "off" }, Style::reset().fg(Color::LightCyan)),
+                Span::raw(" (b to toggle)")
+            ])
+        ]).block(Block::default().borders(Borders::ALL));
+        frame.render_widget(info, areas[2])
+    }
+
+    fn captured(&mut self, entry: &LogEntry) {
+        self.follow_target();
+        if let (Some(target), Some(signal)) = (self.tracking, entry.signal) {
+            if entry.summary.transmitter == Some(target) {
+                self.history.push_back(signal);
+                if self.history.len() > HISTORY {
+                    self.history.pop_front();
+                }
+                self.heard = Some((Instant::now(), entry.sensor.clone()));
+            }
+        }
+    }
+    fn key(&mut self, key: Key) {
+        if key == Key::Char('b') {
+            self.beep = !self.beep
+        }
+    }
+    fn keys(&self) -> Vec<(&'static str, &'static str)> {
+        vec![("b", "Beep faster as the signal gets stronger")]
+    }
+    fn grabs_input(&self) -> bool {
+        false
+    }
+    fn save(&self, state: &mut State) {
+        state.set("track.beep", self.beep);
+    }
+    fn restore(&mut self, state: &State) {
+        self.beep = state.flag("track.beep").unwrap_or(self.beep);
+    }
+    fn reset(&mut self) {
+        self.history.clear();
+        self.heard = None;
+    }
+    fn freeze(&mut self, _: bool) {
+
+    }
+    fn selected(&self) -> Option<MacAddress> {
+        self.tracking
+    }
+    fn search(&self, _: &str, _: &DeviceList) -> Vec<Found> {
+        vec![]
+    }
+    fn select(&mut self, _: &Found) {
+        
+    }
+    fn navigate(&mut self) -> Option<Navigation> {
+        None
+    }
+
+    fn up(&mut self) {
+
+    }
+    fn top(&mut self) {
+
+    }
+    fn down(&mut self) {
+
+    }
+    fn bottom(&mut self) {
+
+    }
+    fn left(&mut self) {
+
+    }
+    fn right(&mut self) {
+
+    }
+}
@@ -0,0 +1,187 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path
+};
+use pcap::Packet;
+
+const SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const INTERFACE_DESCRIPTION: u32 = 1;
+const ENHANCED_PACKET: u32 = 6;
+/// Written in the byte order of the writer, so readers can tell which that was
+const BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+const LINKTYPE_IEEE802_11_RADIOTAP: u16 = 127;
+
+const OPT_ENDOFOPT: u16 = 0;
+const OPT_COMMENT: u16 = 1;
+const SHB_USERAPPL: u16 = 4;
+const IF_NAME: u16 = 2;
+
+/// Writes pcapng, which unlike pcap keeps which sensor heard each frame, along with a comment on it that Wireshark shows
+pub struct Writer(BufWriter<File>);
+impl Writer {
+    /// Start a file with an interface for each sensor, in order, returning it with the bytes written so far
+    pub fn create(path: impl AsRef<Path>, sensors: &[String]) -> io::Result<(Self, u64)> {
+        let mut writer = Self(BufWriter::new(File::create(path)?));
+        let mut section = vec![];
+        section.extend_from_slice(&BYTE_ORDER_MAGIC.to_ne_bytes());
+        section.extend_from_slice(&1u16.to_ne_bytes());
+        section.extend_from_slice(&0u16.to_ne_bytes());
+        // The length of the section isn't known up front
+        section.extend_from_slice(&(-1i64).to_ne_bytes());
+        option(&mut section, SHB_USERAPPL, concat!("blockade-recon ", env!("CARGO_PKG_VERSION")).as_bytes());
+        end_options(&mut section);
+        let mut written = writer.block(SECTION_HEADER, &section)?;
+        for sensor in sensors {
+            let mut interface = vec![];
+            interface.extend_from_slice(&LINKTYPE_IEEE802_11_RADIOTAP.to_ne_bytes());
+            interface.extend_from_slice(&0u16.to_ne_bytes());
+            // No limit on how much of each frame was captured
+            interface.extend_from_slice(&0u32.to_ne_bytes());
+            option(&mut interface, IF_NAME, sensor.as_bytes());
+            end_options(&mut interface);
+            written += writer.block(INTERFACE_DESCRIPTION, &interface)?;
+        }
+        Ok((writer, written))
+    }
+    /// Add a frame heard by the sensor at an index, returning the bytes written
+    pub fn write(&mut self, sensor: usize, packet: &Packet, comment: Option<&str>) -> io::Result<u64> {
+        // In microseconds since the Unix epoch, the resolution assumed without an if_tsresol option
+        let time = packet.header.ts.tv_sec as u64 * 1_000_000 + packet.header.ts.tv_usec as u64;
+        let mut body = Vec::with_capacity(packet.data.len() + 64);
+        body.extend_from_slice(&(sensor as u32).to_ne_bytes());
+        body.extend_from_slice(&((time >> 32) as u32).to_ne_bytes());
+        body.extend_from_slice(&(time as u32).to_ne_bytes());
+        body.extend_from_slice(&(packet.data.len() as u32).to_ne_bytes());
+        body.extend_from_slice(&packet.header.len.to_ne_bytes());
+        body.extend_from_slice(packet.data);
+        pad(&mut body);
+        if let Some(comment) = comment.filter(|comment| !comment.is_empty()) {
+            option(&mut body, OPT_COMMENT, comment.as_bytes());
+            end_options(&mut body);
+        }
+        self.block(ENHANCED_PACKET, &body)
+    }
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+    /// Write a block, whose length is given both before and after its body
+    fn block(&mut self, kind: u32, body: &[u8]) -> io::Result<u64> {
+        let length = (body.len() + 12) as u32;
+        self.0.write_all(&kind.to_ne_bytes())?;
+        self.0.write_all(&length.to_ne_bytes())?;
+        self.0.write_all(body)?;
+        self.0.write_all(&length.to_ne_bytes())?;
+        Ok(length as u64)
+    }
+}
+
+/// Options are padded to a multiple of four bytes, like block bodies
+fn option(body: &mut Vec<u8>, code: u16, value: &[u8]) {
+    // Longer values don't fit the length field, and are cut between characters as they are all text
+    let mut end = value.len().min(u16::MAX as usize - 3);
+    while end < value.len() && value[end] & 0xc0 == 0x80 {
+        end -= 1;
+    }
+    let value = &value[..end];
+    body.extend_from_slice(&code.to_ne_bytes());
+    body.extend_from_slice(&(value.len() as u16).to_ne_bytes());
+    body.extend_from_slice(value);
+    pad(body);
+}
+fn end_options(body: &mut Vec<u8>) {
+    body.extend_from_slice(&OPT_ENDOFOPT.to_ne_bytes());
+    body.extend_from_slice(&0u16.to_ne_bytes());
+}
+fn pad(body: &mut Vec<u8>) {
+    body.resize((body.len() + 3) / 4 * 4, 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+    use pcap::PacketHeader;
+
+    /// The kind and body of each block in a file, checking the lengths around them agree
+    fn blocks(mut data: &[u8]) -> Vec<(u32, Vec<u8>)> {
+        let u32_at = |data: &[u8], at: usize| u32::from_ne_bytes(data[at..at + 4].try_into().unwrap());
+        let mut blocks = vec![];
+        while !data.is_empty() {
+            let length = u32_at(data, 4) as usize;
+            assert_eq!(length % 4, 0, "blocks are padded to four bytes");
+            assert_eq!(u32_at(data, length - 4) as usize, length, "the trailing length matches");
+            blocks.push((u32_at(data, 0), data[8..length - 4].to_vec()));
+            data = &data[length..];
+        }
+        blocks
+    }
+    /// Each option in a body, from an offset, up to the end of options
+    fn options(body: &[u8], mut at: usize) -> Vec<(u16, Vec<u8>)> {
+        let u16_at = |at: usize| u16::from_ne_bytes(body[at..at + 2].try_into().unwrap());
+        let mut options = vec![];
+        loop {
+            let (code, length) = (u16_at(at), u16_at(at + 2) as usize);
+            if code == OPT_ENDOFOPT {
+                assert_eq!(at + 4, body.len(), "nothing follows the end of options");
+                return options
+            }
+            options.push((code, body[at + 4..at + 4 + length].to_vec()));
+            at += 4 + (length + 3) / 4 * 4;
+        }
+    }
+    /// A file unique to the test, removed when dropped
+    struct Temporary(std::path::PathBuf);
+    impl Drop for Temporary {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let file = Temporary(std::env::temp_dir().join(format!("blockade-recon-{}.pcapng", std::process::id())));
+        let sensors = ["wlan0".to_string(), "remote:wlan1mon".to_string()];
+        let (mut writer, mut written) = Writer::create(&file.0, &sensors).unwrap();
+        let header = PacketHeader { ts: libc::timeval { tv_sec: 1_700_000_000, tv_usec: 250_000 }, caplen: 5, len: 1500 };
+        written += writer.write(1, &Packet::new(&header, b"frame"), Some("Deauthentication")).unwrap();
+        written += writer.write(0, &Packet::new(&header, b"frame"), Some("")).unwrap();
+        writer.flush().unwrap();
+        let data = std::fs::read(&file.0).unwrap();
+        assert_eq!(written, data.len() as u64);
+
+        let blocks = blocks(&data);
+        let kinds: Vec<_> = blocks.iter().map(|(kind, _)| *kind).collect();
+        assert_eq!(kinds, [SECTION_HEADER, INTERFACE_DESCRIPTION, INTERFACE_DESCRIPTION, ENHANCED_PACKET, ENHANCED_PACKET]);
+        let section = &blocks[0].1;
+        assert_eq!(section[..4], BYTE_ORDER_MAGIC.to_ne_bytes());
+        assert!(matches!(&options(section, 16)[..], [(SHB_USERAPPL, name)] if name.starts_with(b"blockade-recon ")));
+        for ((_, interface), sensor) in blocks[1..3].iter().zip(sensors.iter()) {
+            assert_eq!(interface[..2], LINKTYPE_IEEE802_11_RADIOTAP.to_ne_bytes());
+            assert_eq!(options(interface, 8), [(IF_NAME, sensor.as_bytes().to_vec())]);
+        }
+
+        let time = 1_700_000_000_250_000u64;
+        let packet = &blocks[3].1;
+        assert_eq!(packet[..4], 1u32.to_ne_bytes());
+        assert_eq!(packet[4..8], ((time >> 32) as u32).to_ne_bytes());
+        assert_eq!(packet[8..12], (time as u32).to_ne_bytes());
+        assert_eq!(packet[12..16], 5u32.to_ne_bytes());
+        assert_eq!(packet[16..20], 1500u32.to_ne_bytes());
+        assert_eq!(&packet[20..25], b"frame");
+        assert_eq!(options(packet, 28), [(OPT_COMMENT, b"Deauthentication".to_vec())]);
+        // Without a comment there are no options at all
+        assert_eq!(blocks[4].1.len(), 28);
+    }
+    #[test]
+    fn long_options() {
+        let mut body = vec![];
+        option(&mut body, OPT_COMMENT, format!("a{}", "é".repeat(40_000)).as_bytes());
+        let length = u16::from_ne_bytes([body[2], body[3]]) as usize;
+        assert_eq!(length, 65_531, "cut before a character that doesn't fit");
+        assert!(std::str::from_utf8(&body[4..4 + length]).is_ok());
+        assert_eq!(body.len() % 4, 0);
+        end_options(&mut body);
+        assert_eq!(options(&body, 0).len(), 1);
+    }
+}
@@ -0,0 +1,222 @@
+use std::fmt;
+
+use crate::wifi::Tag;
+
+/// The privacy bit of the beacon capability field, set when any encryption is required
+const CAPABILITY_PRIVACY: u16 = 1 << 4;
+/// RSN capability bits for management frame protection
+const MFP_REQUIRED: u16 = 1 << 6;
+const MFP_CAPABLE: u16 = 1 << 7;
+/// The OUI of suites defined by 802.11 itself
+const IEEE: [u8; 3] = [0x00, 0x0f, 0xac];
+/// The Microsoft OUI and vendor element type of the original WPA element
+const MICROSOFT: [u8; 3] = [0x00, 0x50, 0xf2];
+const WPA: u8 = 1;
+const TKIP: u8 = 2;
+
+/// The generation of security a network offers, weakest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Open,
+    /// Opportunistic wireless encryption, encrypted but anyone may join
+    Owe,
+    Wep,
+    Wpa,
+    Wpa2,
+    Wpa3
+}
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", match self {
+            Self::Open => "Open",
+            Self::Owe => "OWE",
+            Self::Wep => "WEP",
+            Self::Wpa => "WPA",
+            Self::Wpa2 => "WPA2",
+            Self::Wpa3 => "WPA3"
+        })
+    }
+}
+
+/// Whether management frames are protected, weakest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Pmf {
+    Disabled,
+    /// Used with clients that support it
+    Capable,
+    Required
+}
+impl fmt::Display for Pmf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", match self {
+            Self::Disabled => "disabled",
+            Self::Capable => "optional",
+            Self::Required => "required"
+        })
+    }
+}
+
+/// The security a network advertises in its beacons
+/// Where several ways to connect are offered, as in WPA2/WPA3 transition mode, this is the weakest of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Security {
+    pub level: Level,
+    /// Clients authenticate with 802.1X rather than a shared key
+    pub enterprise: bool,
+    pub pmf: Pmf,
+    /// TKIP is allowed for unicast traffic, as it is by anything still supporting WPA
+    pub tkip: bool
+}
+impl Security {
+    pub fn from_beacon(capabilities: u16, tags: &[Tag]) -> Self {
+        let wpa = tags.iter().any(|tag| matches!(tag, Tag::VendorSpecific { vendor: MICROSOFT, kind: Some(WPA) }));
+        let rsn = tags.iter().find_map(|tag| match tag {
+            Tag::Rsn { pairwise, akms, capabilities } => Some((pairwise, akms, *capabilities)),
+            _ => None
+        });
+        let (level, enterprise, pmf, tkip) = match rsn {
+            Some((pairwise, akms, capabilities)) => {
+                let offered: Vec<_> = akms.iter().filter(|akm| akm[..3] == IEEE).map(|akm| akm_level(akm[3])).collect();
+                let level = offered.iter().map(|&(level, _)| level).min().unwrap_or(Level::Wpa2);
+                let pmf = if capabilities & MFP_REQUIRED != 0 {
+                    Pmf::Required
+                } else if capabilities & MFP_CAPABLE != 0 {
+                    Pmf::Capable
+                } else {
+                    Pmf::Disabled
+                };
+                (
+                    if wpa { Level::Wpa } else { level },
+                    !offered.is_empty() && offered.iter().all(|&(_, enterprise)| enterprise),
+                    pmf,
+                    wpa || pairwise.iter().any(|suite| suite[..3] == IEEE && suite[3] == TKIP)
+                )
+            }
+            None if wpa => (Level::Wpa, false, Pmf::Disabled, true),
+            None if capabilities & CAPABILITY_PRIVACY != 0 => (Level::Wep, false, Pmf::Disabled, false),
+            None => (Level::Open, false, Pmf::Disabled, false)
+        };
+        Self {
+            level,
+            enterprise,
+            pmf,
+            tkip
+        }
+    }
+}
+impl fmt::Display for Security {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.level {
+            Level::Wpa | Level::Wpa2 | Level::Wpa3 => write!(f, "{}-{}", self.level, if self.enterprise { "Enterprise" } else { "Personal" })?,
+            level => write!(f, "{}", level)?
+        }
+        if self.level >= Level::Wpa2 {
+            write!(f, ", PMF {}", self.pmf)?;
+        }
+        if self.tkip {
+            write!(f, ", TKIP")?;
+        }
+        Ok(())
+    }
+}
+
+/// The level of an 802.11 AKM suite, and whether it is 802.1X
+fn akm_level(suite: u8) -> (Level, bool) {
+    match suite {
+        1 | 3 | 5 => (Level::Wpa2, true),
+        11 | 12 | 13 => (Level::Wpa3, true),
+        8 | 9 | 24 | 25 => (Level::Wpa3, false),
+        18 => (Level::Owe, false),
+        _ => (Level::Wpa2, false)
+    }
+}
+
+/// The weakest security a network of some SSID may advertise, such as WPA2-Enterprise with PMF required
+#[derive(Debug, Clone)]
+pub struct Policy {
+    pub ssid: String,
+    pub level: Level,
+    pub enterprise: bool,
+    pub pmf: Pmf,
+    /// Whether TKIP may be offered, by default only when less than WPA2 is required
+    pub tkip: Option<bool>
+}
+impl Policy {
+    /// A policy allowing anything, to be narrowed by `set`
+    pub fn new(ssid: String) -> Self {
+        Self {
+            ssid,
+            level: Level::Open,
+            enterprise: false,
+            pmf: Pmf::Disabled,
+            tkip: None
+        }
+    }
+    /// Apply a setting from the config file, one of `security`, `pmf` or `tkip`
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        let invalid = || Error::Invalid(key.to_string(), value.to_string());
+        match key {
+            "security" => {
+                let (level, kind) = value.split_once('-').map_or((value, None), |(level, kind)| (level, Some(kind)));
+                self.level = match level.to_lowercase().as_str() {
+                    "open" => Level::Open,
+                    "owe" => Level::Owe,
+                    "wep" => Level::Wep,
+                    "wpa" => Level::Wpa,
+                    "wpa2" => Level::Wpa2,
+                    "wpa3" => Level::Wpa3,
+                    _ => return Err(invalid())
+                };
+                self.enterprise = match kind.map(str::to_lowercase).as_deref() {
+                    None | Some("personal") => false,
+                    Some("enterprise") => true,
+                    _ => return Err(invalid())
+                };
+            }
+            "pmf" => self.pmf = match value {
+                "disabled" | "false" => Pmf::Disabled,
+                "optional" | "capable" => Pmf::Capable,
+                "required" | "true" => Pmf::Required,
+                _ => return Err(invalid())
+            },
+            "tkip" => self.tkip = match value {
+                "true" => Some(true),
+                "false" => Some(false),
+                _ => return Err(invalid())
+            },
+            _ => return Err(Error::UnknownSetting(key.to_string()))
+        }
+        Ok(())
+    }
+    /// Every way in which a network's security falls short of this policy
+    pub fn violations(&self, security: &Security) -> Vec<String> {
+        let mut violations = vec![];
+        if security.level < self.level {
+            violations.push(format!("offers {} where {} is required", security.level, self.level));
+        }
+        if self.enterprise && !security.enterprise {
+            violations.push("accepts a shared key where 802.1X is required".to_string());
+        }
+        if security.pmf < self.pmf {
+            violations.push(format!("PMF is {} where it must be {}", security.pmf, self.pmf));
+        }
+        if security.tkip && !self.tkip.unwrap_or(self.level < Level::Wpa2) {
+            violations.push("allows TKIP".to_string());
+        }
+        violations
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    UnknownSetting(String),
+    Invalid(String, String)
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownSetting(key) => write!(f, "unknown policy setting {:?}, expected security, pmf or tkip", key),
+            Self::Invalid(key, value) => write!(f, "{:?} is not a valid {} setting", value, key)
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use crate::wifi::Tag;
+
+/// Channels in a country element's subbands are numbered one apart up to here, and four apart above
+const LAST_2GHZ_CHANNEL: u8 = 14;
+/// The constant of the free space path loss formula with distance in metres and frequency in MHz
+const FREE_SPACE_CONSTANT: f64 = 27.55;
+
+/// What an access point says about its transmit power, from its country, power constraint and TPC report elements
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Power {
+    /// The power it reports transmitting at in dBm, from a TPC report
+    pub reported: Option<i8>,
+    /// The most its country allows on the channel it was heard on, in dBm
+    pub limit: Option<i8>,
+    /// How far below the country's limit it tells clients to stay, in dB
+    pub constraint: Option<u8>
+}
+impl Power {
+    /// Read from a beacon or probe response heard on a channel, or None if it says nothing about power
+    pub fn from_beacon(tags: &[Tag], channel: Option<u32>) -> Option<Self> {
+        let mut power = Self::default();
+        for tag in tags {
+            match tag {
+                Tag::Country { limits, .. } => power.limit = channel.and_then(|channel| limit(limits, channel)),
+                Tag::PowerConstraint(constraint) => power.constraint = Some(*constraint),
+                Tag::TpcReport { power: reported } => power.reported = Some(*reported),
+                _ => ()
+            }
+        }
+        Some(power).filter(|power| *power != Self::default())
+    }
+    /// The most the access point may transmit at, its country's limit less any constraint it advertises
+    pub fn allowed(&self) -> Option<i8> {
+        Some(self.limit?.saturating_sub(self.constraint.unwrap_or(0).min(i8::MAX as u8) as i8))
+    }
+    /// How many dB the reported power is above the country's limit, if it is
+    /// The constraint is left out, as it only binds clients, and some drivers report their power loosely
+    pub fn excess(&self) -> Option<i8> {
+        Some(self.reported?.saturating_sub(self.limit?)).filter(|&excess| excess > 0)
+    }
+    /// The rough link budget from the reported power to a signal heard at a frequency:
+    /// the path loss in dB, and the distance in metres that loss would take in free space
+    pub fn link_budget(&self, signal: i8, frequency: u32) -> Option<(i16, f64)> {
+        let loss = self.reported? as i16 - signal as i16;
+        let metres = 10f64.powf((loss as f64 + FREE_SPACE_CONSTANT - 20.0 * (frequency as f64).log10()) / 20.0);
+        Some((loss, metres))
+    }
+}
+
+/// The transmit power limit for a channel from the subbands of a country element, by first channel, count and limit
+fn limit(limits: &[(u8, u8, i8)], channel: u32) -> Option<i8> {
+    limits.iter()
+        .find(|&&(first, count, _)| {
+            let (first, count) = (first as u32, count as u32);
+            let spacing = if first <= LAST_2GHZ_CHANNEL as u32 { 1 } else { 4 };
+            channel >= first && channel < first + count * spacing && (channel - first) % spacing == 0
+        })
+        .map(|&(_, _, limit)| limit)
+}
@@ -0,0 +1,46 @@
+use eui48::MacAddress;
+
+use crate::KnownDevice;
+
+/// How much a probing client gives away about itself
+pub struct Assessment {
+    /// Out of 100, higher leaks less
+    pub score: u8,
+    pub reasons: Vec<String>
+}
+impl Assessment {
+    /// Grade a device, or None if it has never probed for networks
+    pub fn of(address: MacAddress, device: &KnownDevice) -> Option<Self> {
+        if !device.probing {
+            return None
+        }
+        let mut score: i32 = 100;
+        let mut reasons = vec![];
+        if address.is_universal() {
+            score -= 30;
+            reasons.push("Uses its real, vendor assigned MAC address".to_string());
+        } else {
+            reasons.push("Uses a randomised MAC address".to_string());
+        }
+        if !device.probes.is_empty() {
+            // Each remembered network narrows down who the owner is and where they have been
+            score -= (device.probes.len() as i32 * 10).min(60);
+            reasons.push(format!("Asked for {} network(s) by name", device.probes.len()));
+        } else {
+            reasons.push("Only sends wildcard probes".to_string());
+        }
+        Some(Self {
+            score: score.max(0) as u8,
+            reasons
+        })
+    }
+    pub fn grade(&self) -> char {
+        match self.score {
+            90..=100 => 'A',
+            75..=89 => 'B',
+            60..=74 => 'C',
+            40..=59 => 'D',
+            _ => 'F'
+        }
+    }
+}
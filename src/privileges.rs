@@ -0,0 +1,112 @@
+use std::{env, fmt, fs, io};
+
+pub const CAP_NET_ADMIN: u32 = 12;
+pub const CAP_NET_RAW: u32 = 13;
+
+const LINUX_CAPABILITY_VERSION_3: u32 = 0x20080522;
+
+#[repr(C)]
+struct CapabilityHeader {
+    version: u32,
+    pid: libc::c_int
+}
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+struct CapabilityData {
+    effective: u32,
+    permitted: u32,
+    inheritable: u32
+}
+
+/// Read a capability set of this process from /proc
+fn capabilities(set: &str) -> io::Result<u64> {
+    let status = fs::read_to_string("/proc/self/status")?;
+    status.lines()
+        .find_map(|line| line.strip_prefix(set))
+        .and_then(|mask| u64::from_str_radix(mask.trim(), 16).ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "unable to read process capabilities"))
+}
+
+/// Check that the process may capture, and put the interface into monitor mode if requested
+pub fn check(monitor: bool) -> Result<(), Missing> {
+    // Without /proc there is no way to tell, so let libpcap report the problem instead
+    let effective = match capabilities("CapEff:") {
+        Ok(effective) => effective,
+        Err(_) => return Ok(())
+    };
+    let mut missing = vec![];
+    if effective & 1 << CAP_NET_RAW == 0 {
+        missing.push("CAP_NET_RAW");
+    }
+    if monitor && effective & 1 << CAP_NET_ADMIN == 0 {
+        missing.push("CAP_NET_ADMIN");
+    }
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(Missing(missing))
+    }
+}
+
+/// Give up every capability except those in `keep`
+/// When started through sudo, also switch back to the invoking user
+pub fn drop(keep: &[u32]) -> io::Result<()> {
+    let permitted = capabilities("CapPrm:")?;
+    let keep = keep.iter().fold(0u64, |mask, capability| mask | 1 << capability) & permitted;
+
+    if unsafe { libc::geteuid() } == 0 {
+        if let (Some(uid), Some(gid)) = (sudo_id("SUDO_UID"), sudo_id("SUDO_GID")) {
+            unsafe {
+                if libc::prctl(libc::PR_SET_KEEPCAPS, 1 as libc::c_ulong, 0 as libc::c_ulong, 0 as libc::c_ulong, 0 as libc::c_ulong) != 0
+                    || libc::setgroups(0, std::ptr::null()) != 0
+                    || libc::setgid(gid) != 0
+                    || libc::setuid(uid) != 0 {
+                    return Err(io::Error::last_os_error())
+                }
+            }
+        }
+    }
+
+    let header = CapabilityHeader {
+        version: LINUX_CAPABILITY_VERSION_3,
+        pid: 0
+    };
+    let data = [
+        CapabilityData {
+            effective: keep as u32,
+            permitted: keep as u32,
+            inheritable: 0
+        },
+        CapabilityData {
+            effective: (keep >> 32) as u32,
+            permitted: (keep >> 32) as u32,
+            inheritable: 0
+        }
+    ];
+    unsafe {
+        if libc::syscall(libc::SYS_capset, &header as *const CapabilityHeader, data.as_ptr()) != 0
+            || libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1 as libc::c_ulong, 0 as libc::c_ulong, 0 as libc::c_ulong, 0 as libc::c_ulong) != 0 {
+            return Err(io::Error::last_os_error())
+        }
+    }
+    Ok(())
+}
+
+fn sudo_id(variable: &str) -> Option<u32> {
+    env::var(variable).ok()?.parse().ok()
+}
+
+/// Capabilities the process needs but does not have
+#[derive(Debug)]
+pub struct Missing(Vec<&'static str>);
+impl fmt::Display for Missing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let binary = env::current_exe().map(|path| path.display().to_string()).unwrap_or_else(|_| "blockade-recon".to_string());
+        write!(
+            f,
+            "missing {}. Run as root, or grant the capabilities once with `sudo setcap cap_net_raw,cap_net_admin=eip {}`",
+            self.0.join(" and "),
+            binary
+        )
+    }
+}
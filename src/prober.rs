@@ -0,0 +1,123 @@
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    hash::{BuildHasher, Hasher},
+    time::{Duration, Instant}
+};
+use eui48::MacAddress;
+
+use crate::{DeviceList, KnownDevice, Role};
+
+/// How often a round of probe requests is sent
+const INTERVAL: Duration = Duration::from_secs(5);
+/// Most probe requests sent in one round, so a busy area isn't flooded
+const MAX_PROBES: usize = 16;
+/// How many times a hidden network is asked whether it has a name before giving up on it
+const ATTEMPTS: u8 = 3;
+/// A radiotap header with no fields, leaving the rate and retries to the driver
+const RADIOTAP: [u8; 8] = [0, 0, 8, 0, 0, 0, 0, 0];
+/// The 802.11b and 802.11g rates offered, in units of 500kbps with the basic rates marked
+const RATES: [u8; 8] = [0x82, 0x84, 0x8b, 0x96, 0x0c, 0x12, 0x18, 0x24];
+const EXTENDED_RATES: [u8; 4] = [0x30, 0x48, 0x60, 0x6c];
+
+/// Sends directed probe requests from the capture interface with --active, so hidden networks answer with their names
+/// sooner than waiting for a client to join them, and networks asked for by name answer even when not beaconing loudly
+pub struct Prober {
+    /// The address probes are sent from, made up so the sensor's own isn't given away
+    pub address: MacAddress,
+    /// Network names to ask for from every access point in range, and to try on hidden ones
+    ssids: Vec<String>,
+    /// Names already tried on each hidden access point, and how many times
+    attempts: HashMap<(MacAddress, String), u8>,
+    sequence: u16,
+    round: Option<Instant>,
+    pub sent: u64,
+    /// Why the last probe request couldn't be sent
+    pub error: Option<String>
+}
+impl Prober {
+    pub fn new(ssids: Vec<String>) -> Self {
+        Self {
+            address: random_address(),
+            ssids,
+            attempts: HashMap::new(),
+            sequence: 0,
+            round: None,
+            sent: 0,
+            error: None
+        }
+    }
+    /// The probe requests to send now, if a round is due, to the access points heard by a sensor on the channel it is on
+    /// Hidden networks are asked with no name, then with every name given and every name their clients have asked for
+    pub fn due(&mut self, devices: &DeviceList, sensor: &str, channel: Option<u32>) -> Vec<Vec<u8>> {
+        if self.round.map_or(false, |round| round.elapsed() < INTERVAL) {
+            return vec![]
+        }
+        self.round = Some(Instant::now());
+
+        let mut asked_by_clients: HashMap<MacAddress, Vec<&String>> = HashMap::new();
+        for device in devices.values() {
+            if let (Some(Role::Station), Some(bssid)) = (device.role, device.associated) {
+                asked_by_clients.entry(bssid).or_default().extend(device.probes.keys());
+            }
+        }
+        let still_hidden = |device: &KnownDevice| device.role == Some(Role::AccessPoint)
+            && device.hidden
+            && device.beacon.as_deref().map_or(true, |ssid| ssid.chars().all(|c| c == '\0'));
+        // Forgotten once the access point leaves the list or gives its name away, so they don't pile up over a long capture
+        self.attempts.retain(|(bssid, _), _| devices.get(bssid).map_or(false, still_hidden));
+
+        let mut probes: Vec<_> = self.ssids.iter().map(|ssid| (MacAddress::broadcast(), ssid.clone())).collect();
+        let hidden = devices.iter().filter(|(_, device)| still_hidden(device)
+            // One on another channel wouldn't hear it, though while hopping the channel may not be known
+            && channel.map_or(true, |channel| device.sensors.get(sensor).and_then(|sighting| sighting.channel()) == Some(channel)));
+        let wildcard = String::new();
+        for (&bssid, _) in hidden {
+            let candidates = std::iter::once(&wildcard)
+                .chain(self.ssids.iter())
+                .chain(asked_by_clients.get(&bssid).into_iter().flatten().copied());
+            for ssid in candidates {
+                if probes.len() >= MAX_PROBES {
+                    break
+                }
+                let attempts = self.attempts.entry((bssid, ssid.clone())).or_insert(0);
+                if *attempts < ATTEMPTS {
+                    *attempts += 1;
+                    probes.push((bssid, ssid.clone()));
+                }
+            }
+        }
+        probes.truncate(MAX_PROBES);
+        probes.into_iter()
+            .map(|(destination, ssid)| {
+                self.sequence = (self.sequence + 1) % 4096;
+                probe_request(self.address, destination, ssid.as_bytes(), self.sequence)
+            })
+            .collect()
+    }
+}
+
+/// A probe request for a network name, or any with an empty one, with its radiotap header, ready to be injected
+/// Sent to the broadcast address it is answered by every access point with the name, or to one directly by just that one
+pub fn probe_request(source: MacAddress, destination: MacAddress, ssid: &[u8], sequence: u16) -> Vec<u8> {
+    let mut frame = RADIOTAP.to_vec();
+    // Frame control for a management frame of the probe request subtype, then the duration left to the hardware
+    frame.extend_from_slice(&[0x40, 0x00, 0x00, 0x00]);
+    frame.extend_from_slice(destination.as_bytes());
+    frame.extend_from_slice(source.as_bytes());
+    frame.extend_from_slice(destination.as_bytes());
+    frame.extend_from_slice(&(sequence << 4).to_le_bytes());
+    let ssid = &ssid[..ssid.len().min(32)];
+    frame.extend_from_slice(&[0, ssid.len() as u8]);
+    frame.extend_from_slice(ssid);
+    frame.extend_from_slice(&[1, RATES.len() as u8]);
+    frame.extend_from_slice(&RATES);
+    frame.extend_from_slice(&[50, EXTENDED_RATES.len() as u8]);
+    frame.extend_from_slice(&EXTENDED_RATES);
+    frame
+}
+
+/// A locally administered unicast address, as clients randomising their address use
+fn random_address() -> MacAddress {
+    let bytes = RandomState::new().build_hasher().finish().to_le_bytes();
+    MacAddress::new([(bytes[0] & 0xfc) | 0x02, bytes[1], bytes[2], bytes[3], bytes[4], bytes[5]])
+}
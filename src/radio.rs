@@ -0,0 +1,300 @@
+use std::time::Duration;
+use clap::ArgMatches;
+use eui48::MacAddress;
+
+use crate::{
+    alerts::Alert, capture, cli::{self, failed, Failure}, control, dashboard, export, hopper::Hopper, nl80211, outputs::{Captures, Reports}, privileges,
+    regions::Plan, remote, watchdog::Watchdog, DeviceList
+};
+
+/// How interfaces are opened, tuned and watched over, from the command line
+pub struct Settings {
+    /// Whether interfaces are put into monitor mode as they are opened
+    pub monitor: bool,
+    pub filter: Option<String>,
+    /// Whether interfaces are opened by a privileged capture helper rather than here
+    pub privsep: bool,
+    /// Whether frames are injected into the first interface to probe actively
+    pub active: bool,
+    /// The channel to tune each local interface to, in order
+    pub channels: Vec<u32>,
+    /// Seconds without a frame before the first interface is thought wedged, or 0 never to
+    pub watchdog: u64,
+    pub bounce: bool,
+    /// The channels the first interface hops across, every one the radio and region allow when empty
+    pub hop: Option<Vec<u32>>,
+    /// Milliseconds spent on each channel when hopping
+    pub dwell: u64,
+    pub region: Option<&'static Plan>,
+    pub dfs: bool
+}
+impl Settings {
+    /// Read the settings for `interface` and the interfaces after it, which are left alone if `remote_only`
+    /// Remote sensors are tuned and hop on their own
+    pub fn parse(args: &ArgMatches, interface: &str, remote_only: bool) -> Result<Self, Failure> {
+        let channels = args.values_of("channel").filter(|_| !remote_only).into_iter().flatten()
+            .map(str::parse::<u32>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(failed("The specified channel is not a number"))?;
+        let watchdog = args.value_of("watchdog").unwrap_or("30").parse::<u64>().map_err(failed("The watchdog timeout is not a number of seconds"))?;
+        let mut hop = if !remote_only && (args.is_present("hop") || args.is_present("hop_channels")) {
+            Some(args.value_of("hop_channels").map(cli::parse_channels).transpose()
+                .map_err(failed("The channels to hop across are not a list of numbers"))?
+                .unwrap_or_default())
+        } else {
+            None
+        };
+        let region = args.value_of("region").map(Plan::find).transpose().map_err(failed("Unable to use the region's channel plan"))?;
+        let dfs = !args.is_present("no_dfs");
+        if let Some(plan) = region {
+            plan.check(&channels, dfs).map_err(failed("The channel to tune to is outside the region's channel plan"))?;
+            match &mut hop {
+                // Hopping across everything means everything both the region and the radio allow
+                Some(hop) if hop.is_empty() => {
+                    let supported = supported_channels(interface);
+                    *hop = plan.channels(dfs).into_iter()
+                        .filter(|channel| supported.as_ref().map_or(true, |supported| supported.contains(channel)))
+                        .collect();
+                }
+                Some(hop) => plan.check(hop, dfs).map_err(failed("A channel to hop across is outside the region's channel plan"))?,
+                None => ()
+            }
+        }
+        let dwell = args.value_of("dwell").unwrap_or("250").parse::<u64>().map_err(failed("The dwell time is not a number of milliseconds"))?;
+        Ok(Self {
+            monitor: !args.is_present("dont_monitor"),
+            filter: args.value_of("bpf").map(String::from),
+            privsep: args.is_present("privsep"),
+            active: args.is_present("active"),
+            channels,
+            watchdog,
+            bounce: args.is_present("watchdog_bounce"),
+            hop,
+            dwell,
+            region,
+            dfs
+        })
+    }
+
+    /// Open a local interface, or have a capture helper open it, which watches over and hops the first interface itself
+    fn open(&self, interface: &str, channel: Option<u32>, first: bool) -> Result<capture::Source, Failure> {
+        if self.privsep {
+            let helper = capture::Helper::spawn(capture::HelperOptions {
+                interface,
+                monitor: self.monitor,
+                // Starting locked can't be undone without a way to tell the helper, so a locked channel is just tuned to
+                channel,
+                watchdog: Some(self.watchdog).filter(|_| first),
+                bounce: self.bounce && first,
+                hop: self.hop.clone().filter(|_| first && channel.is_none()),
+                dwell: self.dwell,
+                filter: self.filter.as_deref()
+            }).map_err(failed("Unable to start the capture helper"))?;
+            return Ok(capture::Source::Helper(helper))
+        }
+        let capture = capture::open(interface, self.monitor, self.filter.as_deref()).map_err(failed("Unable to open the capture device"))?;
+        // Frames can only be injected from the thread the capture is read on
+        Ok(if first && self.active { capture::Source::Local(capture) } else { capture::Source::threaded(capture) })
+    }
+}
+
+/// Open every interface after the first of the `local` ones, and connect to every remote sensor not standing in for it
+pub fn listeners(sensors: &[String], local: usize, settings: &Settings, remote: &remote::ClientConfig) -> Result<Vec<(usize, capture::Source)>, Failure> {
+    sensors.iter().enumerate().skip(1)
+        .map(|(index, name)| Ok((index, if index >= local {
+            remote::connect(name, remote).map_err(failed("Unable to connect to the remote sensor"))?
+        } else {
+            settings.open(name, settings.channels.get(index).copied(), false)?
+        })))
+        .collect()
+}
+
+/// The first interface, which is tuned, hopped, watched over and controlled from here, where any others only listen
+pub struct Radio {
+    pub name: String,
+    pub source: capture::Source,
+    pub watchdog: Watchdog,
+    pub hopper: Option<Hopper>,
+    /// The channel the interface is on, as far as is known
+    pub channel: Option<u32>,
+    pub hop_error: Option<String>,
+    pub settings: Settings
+}
+impl Radio {
+    /// Open the first interface, or connect to the remote sensor standing in for it with `remote`,
+    /// or stand idle while a session is replayed
+    pub fn open(name: String, settings: Settings, remote: Option<&remote::ClientConfig>, replaying: bool) -> Result<Self, Failure> {
+        let tuned = settings.channels.first().copied();
+        let source = match remote {
+            _ if replaying => capture::Source::idle(),
+            Some(config) => remote::connect(&name, config).map_err(failed("Unable to connect to the remote sensor"))?,
+            None => settings.open(&name, tuned, true)?
+        };
+        if settings.active && !matches!(source, capture::Source::Local(_)) {
+            return Err(Failure::new("Unable to probe actively", capture::Error::CannotInject))
+        }
+        let channel = tuned.or_else(|| {
+            if replaying {
+                return None
+            }
+            let interface = nl80211::Nl80211::connect().ok()?.interface(&name).ok()?;
+            interface.frequency.and_then(nl80211::frequency_to_channel)
+        });
+        let hopper = match &settings.hop {
+            Some(channels) if !settings.privsep => {
+                let channels = Some(channels.as_slice()).filter(|channels| !channels.is_empty());
+                let mut hopper = Hopper::new(&name, channels, Duration::from_millis(settings.dwell)).map_err(failed("Unable to hop channels"))?;
+                hopper.locked = tuned.is_some();
+                hopper.channel = channel;
+                Some(hopper)
+            }
+            _ => None
+        };
+        Ok(Self {
+            watchdog: Watchdog::new(Some(Duration::from_secs(settings.watchdog)).filter(|timeout| !timeout.is_zero()), settings.bounce),
+            name,
+            source,
+            hopper,
+            channel,
+            hop_error: None,
+            settings
+        })
+    }
+
+    /// Tune this and the other `local` interfaces of `sensors` to the channels asked for them, giving any that couldn't be
+    /// A capture helper tunes the interfaces after the first itself
+    pub fn tune(&self, sensors: &[String], local: usize) -> Vec<Failure> {
+        self.settings.channels.iter().zip(sensors).take(local).enumerate()
+            .filter(|(index, _)| *index == 0 || !self.settings.privsep)
+            .filter_map(|(_, (&channel, name))| {
+                let error = nl80211::tune(name, channel).err()?;
+                Some(Failure::new(format!("Unable to tune {} to channel {}", name, channel), error))
+            })
+            .collect()
+    }
+
+    /// Whether the capture is reopened here once the interface has been restarted, which needs the privileges to and network access
+    pub fn reopens(&self) -> bool {
+        self.watchdog.bounce && !self.settings.privsep
+    }
+
+    /// The capabilities still needed once the capture is open, to reopen it or tune the interface
+    /// A capture helper restarts the interface and hops channels itself so nothing needs to be kept here
+    pub fn privileges(&self, monitors: bool) -> &'static [u32] {
+        if self.reopens() {
+            &[privileges::CAP_NET_ADMIN, privileges::CAP_NET_RAW]
+        } else if self.hopper.is_some() || monitors {
+            &[privileges::CAP_NET_ADMIN]
+        } else {
+            &[]
+        }
+    }
+
+    /// Whether channels are being hopped across, here or by a capture helper
+    pub fn hopping(&self) -> bool {
+        self.settings.hop.is_some() && !self.hopper.as_ref().map_or(false, |hopper| hopper.locked)
+    }
+
+    /// Restart the interface if it has gone quiet and reopen the capture on it, and hop on to the next channel when it is time
+    pub fn tick(&mut self) -> Result<(), Failure> {
+        let reopen = match &mut self.source {
+            capture::Source::Local(_) | capture::Source::Threaded(_) => self.watchdog.restart(&self.name) == Some(true),
+            capture::Source::Helper(helper) => {
+                for result in helper.restarts.drain(..) {
+                    self.watchdog.bounced(result)
+                }
+                false
+            }
+            capture::Source::Thread(_) => false
+        };
+        if reopen {
+            let capture = capture::open(&self.name, self.settings.monitor, self.settings.filter.as_deref()).map_err(failed("Unable to reopen the capture device"))?;
+            // The old thread stops as its reader is dropped
+            self.source = match self.source {
+                capture::Source::Local(_) => capture::Source::Local(capture),
+                _ => capture::Source::threaded(capture)
+            };
+            if let Some(&channel) = self.settings.channels.first() {
+                let _ = nl80211::tune(&self.name, channel);
+            }
+        }
+        match (&mut self.hopper, &self.source) {
+            (Some(hopper), _) => {
+                if let Err(error) = hopper.tick() {
+                    self.hop_error = Some(error.to_string());
+                }
+                self.channel = hopper.channel;
+            }
+            (None, capture::Source::Helper(helper)) if helper.channel.is_some() => self.channel = helper.channel,
+            _ => ()
+        }
+        Ok(())
+    }
+
+    /// Follow a network to the channel it announced a move to when locked onto its channel, saying how that went
+    pub fn follow(&mut self, bssid: MacAddress, channel: u32) -> Option<Result<String, String>> {
+        let current = self.channel;
+        let hopper = self.hopper.as_mut().filter(|hopper| hopper.locked && current != Some(channel))?;
+        let result = hopper.tune(channel)
+            .map(|_| format!("Followed {} to channel {}", bssid.to_hex_string(), channel))
+            .map_err(|error| format!("Unable to follow {} to channel {}: {}", bssid.to_hex_string(), channel, error));
+        self.channel = hopper.channel;
+        Some(result)
+    }
+
+    /// Answer a request made to the remote control
+    pub fn answer(&mut self, request: control::Request, devices: &mut DeviceList, captures: &mut Captures, reports: &Reports, alerts: &[Alert]) {
+        match request.command {
+            control::Command::Status => request.ok(vec![
+                ("sensor", export::json_string(&self.name)),
+                ("channel", self.channel.map_or_else(|| "null".to_string(), |channel| channel.to_string())),
+                ("hopping", self.hopping().to_string()),
+                ("saving", (captures.saving && captures.recorder.is_some()).to_string()),
+                ("devices", devices.len().to_string())
+            ]),
+            control::Command::Channel(_) | control::Command::Lock(_) if self.hopper.is_none() =>
+                request.error(409, "changing channel needs --hop, and can't be done with --privsep"),
+            control::Command::Channel(channel) => match self.settings.region.map_or(Ok(()), |plan| plan.check(&[channel], self.settings.dfs)) {
+                Err(error) => request.error(409, &error.to_string()),
+                Ok(()) => match self.hopper.as_mut().unwrap().tune(channel) {
+                    Ok(()) => {
+                        self.channel = Some(channel);
+                        request.ok(vec![("channel", channel.to_string())])
+                    }
+                    Err(error) => request.error(409, &error.to_string())
+                }
+            },
+            control::Command::Lock(locked) => {
+                self.hopper.as_mut().unwrap().locked = locked;
+                request.ok(vec![("locked", locked.to_string())])
+            }
+            control::Command::Saving(true) if captures.recorder.is_none() => request.error(409, "frames aren't saved with --no-save"),
+            control::Command::Saving(start) => {
+                captures.saving = start;
+                request.ok(vec![("saving", start.to_string())])
+            }
+            control::Command::Tag(address, ref tag) => match devices.get_mut(&address) {
+                Some(device) => {
+                    device.tags.insert(tag.clone());
+                    let tags: Vec<_> = device.tags.iter().map(|tag| export::json_string(tag)).collect();
+                    request.ok(vec![("tags", format!("[{}]", tags.join(",")))])
+                }
+                None => request.error(404, "no such device has been seen")
+            }
+            control::Command::Devices => request.respond(200, &dashboard::devices(devices, &reports.survey, &reports.fields.json)),
+            control::Command::Networks => request.respond(200, &dashboard::networks(devices, &reports.survey.anonymizer)),
+            control::Command::Alerts => request.respond(200, &dashboard::alerts(alerts, &reports.survey.anonymizer)),
+            control::Command::Dashboard => request.page(dashboard::PAGE)
+        }
+    }
+}
+
+/// The 2.4GHz and 5GHz channels an interface's radio can tune to, if it can be asked
+fn supported_channels(interface: &str) -> Option<Vec<u32>> {
+    let mut nl80211 = nl80211::Nl80211::connect().ok()?;
+    let wiphy = nl80211.interface(interface).ok()?.wiphy;
+    Some(nl80211.wiphy(wiphy).ok()?.usable_frequencies()
+        .filter(|frequency| frequency.mhz < 5900)
+        .filter_map(nl80211::Frequency::channel)
+        .collect())
+}
@@ -0,0 +1,128 @@
+use std::{
+    collections::HashSet,
+    fmt,
+    fs,
+    io,
+    path::{Path, PathBuf},
+    time::Duration
+};
+use oui::OuiDatabase;
+use pcap::Capture;
+use radiotap::Radiotap;
+
+use crate::{DeviceList, Heard, acks::AckTiming, alerts::{Alert, Alerts}, export::Json, stats, wifi};
+
+/// How often alerts are checked for in capture time, as they are once a second in a live capture
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The captures making up a session, being either a single capture or every capture in a folder, such as a rotated save file
+pub fn captures(session: &Path) -> Result<Vec<PathBuf>, Error> {
+    if !session.is_dir() {
+        return Ok(vec![session.to_path_buf()])
+    }
+    let mut captures = vec![];
+    for entry in fs::read_dir(session).map_err(Error::Read)? {
+        let path = entry.map_err(Error::Read)?.path();
+        if path.extension().map_or(false, |extension| extension == "pcap") {
+            captures.push(path);
+        }
+    }
+    // Rotated files are numbered with leading zeros so they sort in the order they were written
+    captures.sort();
+    Ok(captures)
+}
+
+/// Run earlier sessions back through the current detectors, giving every alert raised along with the session it was raised in
+/// Each session is replayed from scratch, with times in its alerts counted from its first frame
+pub fn replay(sessions: &[PathBuf], oui_db: &OuiDatabase, mut alerts: Alerts) -> Result<Vec<(PathBuf, Alert)>, Error> {
+    let mut findings = vec![];
+    for session in sessions {
+        session_devices(session, oui_db, &mut alerts)?;
+        findings.extend(alerts.take_fresh().into_iter().map(|alert| (session.clone(), alert)));
+        // Every session starts with nothing raised, as each live capture would have
+        alerts.reset();
+    }
+    Ok(findings)
+}
+
+/// Replay one session, checking for alerts as a live capture would, giving the devices it found
+pub fn session_devices(session: &Path, oui_db: &OuiDatabase, alerts: &mut Alerts) -> Result<DeviceList, Error> {
+    let sensor = session.file_stem().map_or_else(|| session.display().to_string(), |name| name.to_string_lossy().to_string());
+    let mut devices = DeviceList::default();
+    let mut ack_timing = AckTiming::new();
+    let mut started = None;
+    let mut time = Duration::default();
+    let mut checked = Duration::default();
+    for path in captures(session)? {
+        let mut capture = Capture::from_file(&path).map_err(|error| Error::Pcap(path.clone(), error))?;
+        loop {
+            let packet = match capture.next() {
+                Ok(packet) => packet,
+                Err(pcap::Error::NoMorePackets) => break,
+                Err(error) => return Err(Error::Pcap(path, error))
+            };
+            let micros = packet.header.ts.tv_sec as u64 * 1_000_000 + packet.header.ts.tv_usec as u64;
+            time = Duration::from_micros(micros.saturating_sub(*started.get_or_insert(micros)));
+            let (radiotap, data) = match Radiotap::parse(packet.data) {
+                Ok(parsed) => parsed,
+                Err(_) => continue
+            };
+            let signal = radiotap.antenna_signal.map(|signal| signal.value);
+            let micros = radiotap.tsft.map_or(micros, |tsft| tsft.value);
+            let ack = wifi::Summary::parse(data).ok().and_then(|summary| ack_timing.frame(&sensor, micros, &summary));
+            let heard = Heard {
+                sensor: &sensor,
+                time,
+                signal,
+                frequency: radiotap.channel.map(|channel| channel.freq as u32),
+                noise: radiotap.antenna_noise.map(|noise| noise.value),
+                snr: stats::snr(&radiotap),
+                quality: radiotap.lock_quality.map(|quality| quality.value)
+            };
+            devices.ingest(oui_db, heard, ack, wifi::Frame::parse(data).ok());
+            if time >= checked + CHECK_INTERVAL {
+                alerts.check(&devices, time);
+                checked = time;
+            }
+        }
+    }
+    alerts.check(&devices, time);
+    Ok(devices)
+}
+
+/// The keys of alerts already raised, as read from an events file written by --events or --headless
+pub fn known(path: &Path) -> Result<HashSet<String>, Error> {
+    let mut known = HashSet::new();
+    for (index, line) in fs::read_to_string(path).map_err(Error::Read)?.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue
+        }
+        let event = Json::parse(line).ok_or(Error::Malformed(index + 1))?;
+        if event.get("event").and_then(Json::as_str) != Some("alert") {
+            continue
+        }
+        let alert = event.get("alert");
+        match (alert.and_then(|alert| alert.get("kind")?.as_str()), alert.and_then(|alert| alert.get("address")?.as_str())) {
+            (Some(kind), Some(address)) => known.insert(format!("{}/{}", kind, address)),
+            _ => return Err(Error::Malformed(index + 1))
+        };
+    }
+    Ok(known)
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Read(io::Error),
+    Pcap(PathBuf, pcap::Error),
+    /// A line of the events file that couldn't be understood
+    Malformed(usize)
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Read(error) => write!(f, "{}", error),
+            Self::Pcap(path, error) => write!(f, "{}: {}", path.display(), error),
+            Self::Malformed(line) => write!(f, "line {} of the events file is not a valid event", line)
+        }
+    }
+}
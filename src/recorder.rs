@@ -0,0 +1,171 @@
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant}
+};
+use pcap::{Capture, Linktype, Packet, PacketHeader, Savefile};
+
+use crate::{anonymize::Anonymizer, pcapng};
+
+/// Bytes taken by the pcap file header, and by the header of each record
+const FILE_HEADER: u64 = 24;
+const RECORD_HEADER: u64 = 16;
+
+/// When to move on to a new save file
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Rotation {
+    pub size: Option<u64>,
+    pub interval: Option<Duration>
+}
+impl Rotation {
+    pub fn is_enabled(&self) -> bool {
+        self.size.is_some() || self.interval.is_some()
+    }
+}
+
+/// An open save file in either format
+enum Output {
+    Pcap(Savefile),
+    /// Chosen by a .pcapng extension, keeping which sensor heard each frame and a comment on it
+    Pcapng(pcapng::Writer)
+}
+
+/// Saves captured frames to a pcap or pcapng file, starting a numbered one whenever the current file is big or old enough
+/// so that long sessions don't grow a single file without limit
+pub struct Recorder {
+    path: PathBuf,
+    rotation: Rotation,
+    /// Named as interfaces in pcapng files
+    sensors: Vec<String>,
+    output: Output,
+    /// Applied to every frame before it is saved
+    anonymizer: Anonymizer,
+    /// Every file written this session, the last being the one in use
+    files: Vec<PathBuf>,
+    written: u64,
+    opened: Instant
+}
+impl Recorder {
+    pub fn create(path: impl Into<PathBuf>, rotation: Rotation, sensors: &[String], anonymizer: Anonymizer) -> Result<Self, Error> {
+        let path = path.into();
+        let first = if rotation.is_enabled() { numbered(&path, 1) } else { path.clone() };
+        let sensors = sensors.to_vec();
+        let (output, written) = open_output(&first, &sensors)?;
+        Ok(Self {
+            output,
+            path,
+            rotation,
+            sensors,
+            anonymizer,
+            files: vec![first],
+            written,
+            opened: Instant::now()
+        })
+    }
+    /// True if frames are saved with a comment, which is then worth working out
+    pub fn annotates(&self) -> bool {
+        matches!(self.output, Output::Pcapng(_))
+    }
+    /// Save a frame heard by the sensor at an index, with a comment on it if the format keeps one
+    pub fn write(&mut self, sensor: usize, packet: &Packet, comment: Option<&str>) -> Result<(), Error> {
+        let full = self.rotation.size.map_or(false, |size| self.written >= size);
+        let old = self.rotation.interval.map_or(false, |interval| self.opened.elapsed() >= interval);
+        if full || old {
+            self.rotate()?;
+        }
+        let data = self.anonymizer.frame(packet.data);
+        let header = PacketHeader { caplen: data.len() as u32, ..*packet.header };
+        let packet = &Packet::new(&header, &data);
+        self.written += match &mut self.output {
+            Output::Pcap(savefile) => {
+                savefile.write(packet);
+                RECORD_HEADER + packet.data.len() as u64
+            }
+            Output::Pcapng(writer) => writer.write(sensor, packet, comment).map_err(|error| Error::Write(self.files.last().unwrap().clone(), error))?
+        };
+        Ok(())
+    }
+    fn rotate(&mut self) -> Result<(), Error> {
+        self.switch(numbered(&self.path, self.files.len() + 1))
+    }
+    /// Finish the current file and carry on saving to another, as each segment of a scheduled capture is
+    pub fn switch(&mut self, next: PathBuf) -> Result<(), Error> {
+        self.flush()?;
+        let (output, written) = open_output(&next, &self.sensors)?;
+        self.output = output;
+        self.files.push(next);
+        self.written = written;
+        self.opened = Instant::now();
+        Ok(())
+    }
+    pub fn flush(&mut self) -> Result<(), Error> {
+        match &mut self.output {
+            Output::Pcap(savefile) => savefile.flush().map_err(Error::Flush),
+            Output::Pcapng(writer) => writer.flush().map_err(|error| Error::Write(self.files.last().unwrap().clone(), error))
+        }
+    }
+    /// The file frames are being saved to
+    pub fn current(&self) -> &Path {
+        self.files.last().unwrap(/* Never empty */)
+    }
+    /// Every file written this session, oldest first
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+}
+
+/// Create a save file in the format its extension names, along with the bytes written to it so far
+fn open_output(path: &Path, sensors: &[String]) -> Result<(Output, u64), Error> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) if extension.eq_ignore_ascii_case("pcapng") => pcapng::Writer::create(path, sensors)
+            .map(|(writer, written)| (Output::Pcapng(writer), written))
+            .map_err(|error| Error::Write(path.to_path_buf(), error)),
+        _ => Ok((Output::Pcap(open(path)?), FILE_HEADER))
+    }
+}
+
+/// Create a radiotap pcap file
+pub fn open(path: &Path) -> Result<Savefile, Error> {
+    Capture::dead(Linktype::IEEE802_11_RADIOTAP)
+        .and_then(|dead| dead.savefile(path))
+        .map_err(|error| Error::Create(path.to_path_buf(), error))
+}
+
+/// capture.pcap becomes capture-001.pcap, capture-002.pcap and so on
+fn numbered(path: &Path, index: usize) -> PathBuf {
+    let stem = path.file_stem().map_or_else(|| "capture".into(), |stem| stem.to_string_lossy());
+    let name = match path.extension() {
+        Some(extension) => format!("{}-{:03}.{}", stem, index, extension.to_string_lossy()),
+        None => format!("{}-{:03}", stem, index)
+    };
+    path.with_file_name(name)
+}
+
+/// Parse a size such as 100M, with an optional K, M or G suffix in powers of 1024
+pub fn parse_size(size: &str) -> Result<u64, std::num::ParseIntError> {
+    let size = size.trim();
+    let (number, multiplier) = match size.chars().last().map(|unit| unit.to_ascii_uppercase()) {
+        Some('K') => (&size[..size.len() - 1], 1 << 10),
+        Some('M') => (&size[..size.len() - 1], 1 << 20),
+        Some('G') => (&size[..size.len() - 1], 1 << 30),
+        _ => (size, 1)
+    };
+    Ok(number.parse::<u64>()? * multiplier)
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Create(PathBuf, pcap::Error),
+    Flush(pcap::Error),
+    /// Creating or writing a pcapng file, which is written without libpcap
+    Write(PathBuf, io::Error)
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Create(path, error) => write!(f, "unable to create save file {}: {}", path.display(), error),
+            Self::Flush(error) => write!(f, "unable to write to the save file: {}", error),
+            Self::Write(path, error) => write!(f, "unable to write to save file {}: {}", path.display(), error)
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use std::fmt;
+
+/// The 2.4GHz and 5GHz channels a regulatory domain permits, as inclusive ranges of channel numbers with whether they need DFS
+/// Channels within a 5GHz range are four apart, as 20MHz channels are numbered
+#[derive(Debug)]
+pub struct Plan {
+    pub name: &'static str,
+    /// ISO 3166-1 alpha-2 country codes following the plan
+    pub countries: &'static [&'static str],
+    ranges: &'static [(u32, u32, bool)]
+}
+
+/// A summary of the common regulatory domains, for keeping the hopper on channels that may be used
+/// Local rules change and have exceptions, so check them before relying on this for compliance
+pub const PLANS: [Plan; 7] = [
+    Plan {
+        name: "FCC",
+        countries: &["US", "PR", "GU", "VI", "AS", "MP"],
+        ranges: &[(1, 11, false), (36, 48, false), (52, 64, true), (100, 144, true), (149, 165, false)]
+    },
+    Plan {
+        name: "ISED",
+        countries: &["CA"],
+        // 120 to 128 are kept clear for weather radar
+        ranges: &[(1, 11, false), (36, 48, false), (52, 64, true), (100, 116, true), (132, 144, true), (149, 165, false)]
+    },
+    Plan {
+        name: "ETSI",
+        countries: &[
+            "AT", "BE", "BG", "CH", "CY", "CZ", "DE", "DK", "EE", "ES", "FI", "FR", "GB", "GR", "HR", "HU", "IE", "IS",
+            "IT", "LI", "LT", "LU", "LV", "MT", "NL", "NO", "PL", "PT", "RO", "SE", "SI", "SK"
+        ],
+        ranges: &[(1, 13, false), (36, 48, false), (52, 64, true), (100, 140, true)]
+    },
+    Plan {
+        name: "MIC",
+        countries: &["JP"],
+        ranges: &[(1, 13, false), (36, 48, false), (52, 64, true), (100, 144, true)]
+    },
+    Plan {
+        name: "SRRC",
+        countries: &["CN"],
+        ranges: &[(1, 13, false), (36, 48, false), (52, 64, true), (149, 165, false)]
+    },
+    Plan {
+        name: "ACMA",
+        countries: &["AU", "NZ"],
+        ranges: &[(1, 13, false), (36, 48, false), (52, 64, true), (100, 116, true), (132, 144, true), (149, 165, false)]
+    },
+    Plan {
+        name: "WPC",
+        countries: &["IN"],
+        ranges: &[(1, 13, false), (36, 48, false), (52, 64, true), (100, 144, true), (149, 165, false)]
+    }
+];
+
+impl Plan {
+    /// The plan followed by a country, by its two letter code in either case
+    pub fn find(country: &str) -> Result<&'static Self, Error> {
+        PLANS.iter()
+            .find(|plan| plan.countries.iter().any(|code| code.eq_ignore_ascii_case(country)))
+            .ok_or_else(|| Error::Unknown(country.to_string()))
+    }
+    /// Every channel permitted, leaving out those needing DFS unless asked for
+    pub fn channels(&self, dfs: bool) -> Vec<u32> {
+        self.ranges.iter()
+            .filter(|&&(_, _, needs_dfs)| dfs || !needs_dfs)
+            .flat_map(|&(first, last, _)| (first..=last).step_by(if first < 36 { 1 } else { 4 }))
+            .collect()
+    }
+    /// Whether a channel is permitted, and if so whether it needs DFS
+    pub fn permits(&self, channel: u32) -> Option<bool> {
+        self.ranges.iter()
+            .find(|&&(first, last, _)| (first..=last).contains(&channel) && (first < 36 || (channel - first) % 4 == 0))
+            .map(|&(_, _, dfs)| dfs)
+    }
+    /// Fail on the first channel not permitted, or needing DFS when it isn't to be used
+    pub fn check(&'static self, channels: &[u32], dfs: bool) -> Result<(), Error> {
+        for &channel in channels {
+            match self.permits(channel) {
+                None => return Err(Error::NotPermitted(channel, self)),
+                Some(true) if !dfs => return Err(Error::Dfs(channel)),
+                Some(_) => ()
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Unknown(String),
+    NotPermitted(u32, &'static Plan),
+    /// A channel needing DFS when --no-dfs was given
+    Dfs(u32)
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Unknown(country) => write!(f, "no channel plan is known for {:?}, expected a country code such as US, GB or JP", country),
+            Self::NotPermitted(channel, plan) => write!(f, "channel {} isn't permitted by {}", channel, plan.name),
+            Self::Dfs(channel) => write!(f, "channel {} needs DFS, which --no-dfs leaves out", channel)
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use std::{
+    convert::TryFrom,
+    fmt,
+    fs,
+    io::{self, BufWriter, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::Path,
+    sync::Arc,
+    time::Duration
+};
+
+use crate::{capture::{self, HelperOptions, Streamer}, control, nl80211};
+
+/// Sent by a client before anything else, followed by the length of its token and the token
+const HELLO: &[u8; 4] = b"BKR1";
+/// The most a token may take up
+const MAX_TOKEN: u32 = 4096;
+/// Sent by a sensor in answer to the hello, before it starts streaming
+const ACCEPTED: u8 = 0;
+const REFUSED: u8 = 1;
+/// How long either end waits on the other while connecting, and a sensor waits on a client that has stopped reading
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How to reach remote sensors, read from `key=value` lines
+///
+/// `token` is presented to every sensor, and `ca` names a PEM file of the certificate authority
+/// their certificates are signed by, to connect with TLS
+#[derive(Default)]
+pub struct ClientConfig {
+    token: Option<String>,
+    tls: Option<Arc<rustls::ClientConfig>>
+}
+impl ClientConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let contents = fs::read_to_string(path).map_err(|error| Error::Config(control::Error::Config(error)))?;
+        let (mut token, mut ca) = (None, None);
+        for line in contents.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let mut parts = line.splitn(2, '=');
+            let (name, value) = (parts.next().unwrap_or_default().trim(), parts.next().map(str::trim));
+            match (name, value) {
+                ("token", Some(value)) if !value.is_empty() => token = Some(value.to_string()),
+                ("ca", Some(value)) => ca = Some(value.to_string()),
+                _ => return Err(Error::Setting(line.to_string()))
+            }
+        }
+        let tls = match ca {
+            Some(ca) => {
+                let mut roots = rustls::RootCertStore::empty();
+                for item in control::read_pem(&ca).map_err(Error::Config)? {
+                    if let rustls_pemfile::Item::X509Certificate(cert) = item {
+                        roots.add(&rustls::Certificate(cert)).map_err(|error| Error::Certificate(error.to_string()))?;
+                    }
+                }
+                if roots.is_empty() {
+                    return Err(Error::Certificate(format!("no certificates in {}", ca)))
+                }
+                Some(Arc::new(rustls::ClientConfig::builder()
+                    .with_safe_defaults()
+                    .with_root_certificates(roots)
+                    .with_no_client_auth()))
+            }
+            None => None
+        };
+        Ok(Self {
+            token,
+            tls
+        })
+    }
+}
+
+/// A connection to or from a sensor, encrypted if TLS is configured
+enum Stream {
+    Plain(TcpStream),
+    Server(Box<rustls::StreamOwned<rustls::ServerConnection, TcpStream>>),
+    Client(Box<rustls::StreamOwned<rustls::ClientConnection, TcpStream>>)
+}
+impl Read for Stream {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(stream) => stream.read(buffer),
+            Self::Server(stream) => stream.read(buffer),
+            Self::Client(stream) => stream.read(buffer)
+        }
+    }
+}
+impl Write for Stream {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(stream) => stream.write(buffer),
+            Self::Server(stream) => stream.write(buffer),
+            Self::Client(stream) => stream.write(buffer)
+        }
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(stream) => stream.flush(),
+            Self::Server(stream) => stream.flush(),
+            Self::Client(stream) => stream.flush()
+        }
+    }
+}
+
+/// Connect to a sensor at `host:port`, giving a source of the frames it streams
+/// TLS needs the sensor to be named by the host name on its certificate rather than its IP address
+pub fn connect(address: &str, config: &ClientConfig) -> Result<capture::Source, Error> {
+    let socket = TcpStream::connect(address).map_err(Error::Io)?;
+    socket.set_read_timeout(Some(TIMEOUT)).map_err(Error::Io)?;
+    let mut stream = match &config.tls {
+        Some(tls) => {
+            let host = address.rsplit_once(':').map_or(address, |(host, _)| host).trim_start_matches('[').trim_end_matches(']');
+            let name = rustls::ServerName::try_from(host).map_err(|_| Error::Certificate(format!("{} is not a host name that can be checked against a certificate", host)))?;
+            let connection = rustls::ClientConnection::new(tls.clone(), name).map_err(Error::Tls)?;
+            Stream::Client(Box::new(rustls::StreamOwned::new(connection, socket)))
+        }
+        None => Stream::Plain(socket)
+    };
+    let token = config.token.as_deref().unwrap_or_default();
+    stream.write_all(HELLO).map_err(Error::Io)?;
+    stream.write_all(&(token.len() as u32).to_le_bytes()).map_err(Error::Io)?;
+    stream.write_all(token.as_bytes()).map_err(Error::Io)?;
+    stream.flush().map_err(Error::Io)?;
+    let mut answer = [0];
+    stream.read_exact(&mut answer).map_err(Error::Io)?;
+    if answer[0] != ACCEPTED {
+        return Err(Error::Refused)
+    }
+    // Frames may be few and far between on a quiet channel
+    match &stream {
+        Stream::Plain(socket) => socket.set_read_timeout(None),
+        Stream::Client(stream) => stream.sock.set_read_timeout(None),
+        Stream::Server(_) => Ok(())
+    }.map_err(Error::Io)?;
+    Ok(capture::Source::remote(stream))
+}
+
+/// The body of the sensor subcommand: capture on an interface and stream every frame to one client at a time
+/// Only returns on failure, as a client going away just means waiting for the next
+pub fn serve(address: &str, config: control::Config, options: HelperOptions) -> Result<(), Error> {
+    let listener = TcpListener::bind(address).map_err(Error::Io)?;
+    let local = listener.local_addr().map_err(Error::Io)?;
+    if config.token.is_none() && !local.ip().is_loopback() {
+        return Err(Error::Unauthenticated)
+    }
+    let (interface, channel) = (options.interface, options.channel);
+    let mut streamer = Streamer::open(options).map_err(Error::Capture)?;
+    if let Some(channel) = channel {
+        if let Err(error) = nl80211::tune(interface, channel) {
+            eprintln!("Unable to tune {} to channel {}: {}", interface, channel, error);
+        }
+    }
+    streamer.drop_privileges().map_err(Error::Capture)?;
+    eprintln!("Streaming {} on {}", interface, local);
+    for socket in listener.incoming() {
+        let socket = match socket {
+            Ok(socket) => socket,
+            Err(_) => continue
+        };
+        let peer = socket.peer_addr().map_or_else(|_| "a client".to_string(), |peer| peer.to_string());
+        let stream = match accept(socket, &config) {
+            Ok(stream) => stream,
+            Err(error) => {
+                eprintln!("Refused {}: {}", peer, error);
+                continue
+            }
+        };
+        eprintln!("Streaming to {}", peer);
+        match streamer.stream(&mut BufWriter::new(stream)) {
+            // Most likely the client went away, so wait for the next
+            Err(capture::Error::Io(error)) => eprintln!("Stopped streaming to {}: {}", peer, error),
+            Err(error) => return Err(Error::Capture(error)),
+            Ok(()) => ()
+        }
+    }
+    Ok(())
+}
+
+/// Check a new client's token, answering whether it may stay
+fn accept(socket: TcpStream, config: &control::Config) -> Result<Stream, Error> {
+    socket.set_read_timeout(Some(TIMEOUT)).map_err(Error::Io)?;
+    // A client that stops reading would otherwise hold up the capture forever
+    socket.set_write_timeout(Some(TIMEOUT)).map_err(Error::Io)?;
+    let mut stream = match &config.tls {
+        Some(tls) => {
+            let connection = rustls::ServerConnection::new(tls.clone()).map_err(Error::Tls)?;
+            Stream::Server(Box::new(rustls::StreamOwned::new(connection, socket)))
+        }
+        None => Stream::Plain(socket)
+    };
+    let mut hello = [0; 8];
+    stream.read_exact(&mut hello).map_err(Error::Io)?;
+    let length = u32::from_le_bytes([hello[4], hello[5], hello[6], hello[7]]);
+    if &hello[..4] != HELLO || length > MAX_TOKEN {
+        return Err(Error::Malformed)
+    }
+    let mut token = vec![0; length as usize];
+    stream.read_exact(&mut token).map_err(Error::Io)?;
+    let authorised = config.token.as_deref().map_or(true, |expected| control::same(&String::from_utf8_lossy(&token), expected));
+    stream.write_all(&[if authorised { ACCEPTED } else { REFUSED }]).map_err(Error::Io)?;
+    stream.flush().map_err(Error::Io)?;
+    if !authorised {
+        return Err(Error::Refused)
+    }
+    Ok(stream)
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Capture(capture::Error),
+    Config(control::Error),
+    Setting(String),
+    Certificate(String),
+    Tls(rustls::Error),
+    /// Listening beyond this host without a token
+    Unauthenticated,
+    /// The other end doesn't speak the same protocol
+    Malformed,
+    /// The token was wrong or missing
+    Refused
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{}", error),
+            Self::Capture(error) => write!(f, "{}", error),
+            Self::Config(error) => write!(f, "{}", error),
+            Self::Setting(line) => write!(f, "unknown setting `{}`, expected token or ca", line),
+            Self::Certificate(problem) => write!(f, "{}", problem),
+            Self::Tls(error) => write!(f, "unable to set up TLS: {}", error),
+            Self::Unauthenticated => write!(f, "a token must be set with --sensor-config to listen on anything but a loopback address"),
+            Self::Malformed => write!(f, "not a blockade-recon client"),
+            Self::Refused => write!(f, "the token was not accepted")
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Seek, SeekFrom, Write},
+    path::{Path, PathBuf}
+};
+
+/// The rfkill switch belonging to a wireless interface
+#[derive(Debug)]
+pub struct Switch {
+    path: PathBuf,
+    /// The soft block, opened for writing while the process still may so it can be lifted after privileges are dropped
+    control: Option<File>,
+    /// Blocked in software, can be undone with `unblock`
+    pub soft: bool,
+    /// Blocked by a physical switch or firmware
+    pub hard: bool
+}
+impl Switch {
+    /// Find the rfkill switch of an interface, or None if it doesn't have one
+    pub fn find(interface: &str) -> io::Result<Option<Self>> {
+        let phy = Path::new("/sys/class/net").join(interface).join("phy80211");
+        if !phy.exists() {
+            return Ok(None)
+        }
+        for entry in fs::read_dir(&phy)? {
+            let entry = entry?;
+            if entry.file_name().to_string_lossy().starts_with("rfkill") {
+                let mut switch = Self {
+                    control: OpenOptions::new().write(true).open(entry.path().join("soft")).ok(),
+                    path: entry.path(),
+                    soft: false,
+                    hard: false
+                };
+                switch.refresh()?;
+                return Ok(Some(switch))
+            }
+        }
+        Ok(None)
+    }
+    pub fn refresh(&mut self) -> io::Result<()> {
+        self.soft = read_flag(&self.path.join("soft"))?;
+        self.hard = read_flag(&self.path.join("hard"))?;
+        Ok(())
+    }
+    pub fn blocked(&self) -> bool {
+        self.soft || self.hard
+    }
+    /// True if a soft block can be lifted from here, which needs the switch to have been opened with privileges
+    pub fn can_unblock(&self) -> bool {
+        self.control.is_some()
+    }
+    /// Lift a soft block. A hard block can only be lifted physically
+    pub fn unblock(&mut self) -> io::Result<()> {
+        let control = self.control.as_mut().ok_or_else(|| io::Error::new(io::ErrorKind::PermissionDenied, "not permitted to change the rfkill switch"))?;
+        control.seek(SeekFrom::Start(0))?;
+        control.write_all(b"0")?;
+        self.refresh()
+    }
+    pub fn describe(&self) -> &'static str {
+        match (self.soft, self.hard) {
+            (_, true) => "The radio is hard blocked by rfkill, check the hardware wireless switch",
+            (true, false) => "The radio is soft blocked by rfkill",
+            (false, false) => "The radio is not blocked"
+        }
+    }
+}
+
+fn read_flag(path: &Path) -> io::Result<bool> {
+    Ok(fs::read_to_string(path)?.trim() != "0")
+}
@@ -0,0 +1,80 @@
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime}
+};
+use pcap::{Packet, PacketHeader};
+
+use crate::{anonymize::Anonymizer, capture::Frame, recorder};
+
+/// The most frame data held when only a number of seconds is given, so a flood can't exhaust memory
+const MAX_SIZE: u64 = 512 << 20;
+
+/// Keeps the most recent frames in memory, for as long or as much as asked, so what was just heard can be saved
+/// on demand without saving everything
+pub struct Ring {
+    /// How far back frames are kept, if limited by time
+    span: Option<Duration>,
+    /// How many bytes of frames are kept
+    limit: u64,
+    /// Dumps are created in this folder
+    folder: PathBuf,
+    /// Applied to every frame as it is dumped
+    anonymizer: Anonymizer,
+    buffer: VecDeque<(Instant, Frame)>,
+    /// Bytes of frame data held
+    size: u64,
+    /// Every dump written so far
+    pub files: Vec<PathBuf>
+}
+impl Ring {
+    pub fn new(span: Option<Duration>, limit: Option<u64>, folder: &Path, anonymizer: Anonymizer) -> Self {
+        Self {
+            span,
+            limit: limit.unwrap_or(MAX_SIZE),
+            folder: folder.to_path_buf(),
+            anonymizer,
+            buffer: VecDeque::new(),
+            size: 0,
+            files: vec![]
+        }
+    }
+    pub fn frame(&mut self, frame: &Frame) {
+        let now = Instant::now();
+        self.size += frame.data.len() as u64;
+        self.buffer.push_back((now, frame.clone()));
+        while let Some((arrived, oldest)) = self.buffer.front() {
+            let expired = self.span.map_or(false, |span| now.duration_since(*arrived) > span);
+            if !expired && self.size <= self.limit {
+                break
+            }
+            self.size -= oldest.data.len() as u64;
+            self.buffer.pop_front();
+        }
+    }
+    /// Write every frame held to a pcap file named for when it was written, keeping them held
+    pub fn dump(&mut self) -> Result<&Path, recorder::Error> {
+        let written = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |since| since.as_millis());
+        let path = self.folder.join(format!("ring-{}.pcap", written));
+        let mut savefile = recorder::open(&path)?;
+        for (_, frame) in self.buffer.iter() {
+            let data = self.anonymizer.frame(&frame.data);
+            let header = PacketHeader { caplen: data.len() as u32, ..frame.header };
+            savefile.write(&Packet::new(&header, &data));
+        }
+        savefile.flush().map_err(recorder::Error::Flush)?;
+        self.files.push(path);
+        Ok(self.files.last().unwrap(/* Pushed above */))
+    }
+    /// How many frames are held
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+    /// How far back the frames held go
+    pub fn covers(&self) -> Duration {
+        self.buffer.front().map_or_else(Duration::default, |(arrived, _)| arrived.elapsed())
+    }
+}
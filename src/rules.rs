@@ -0,0 +1,161 @@
+use std::{collections::HashSet, fmt, time::Duration};
+use eui48::MacAddress;
+use regex::Regex;
+
+use crate::{DeviceList, KnownDevice, alerts::Alerts, hook::{self, Hook}};
+
+/// How long a watchlist's command may run before it is killed
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Devices to look out for, from a `[watch."name"]` section, and what to do when one turns up
+/// A device is on the watchlist if it matches any of the addresses, prefixes or the SSID pattern
+#[derive(Debug, Clone)]
+pub struct Watch {
+    pub name: String,
+    pub addresses: Vec<MacAddress>,
+    /// Starts of addresses, such as an OUI, as lowercase hex without separators
+    pub prefixes: Vec<String>,
+    /// Matched against the SSID a device beacons and every SSID it probes for
+    pub ssid: Option<Regex>,
+    /// Mark the device on the Devices tab
+    pub highlight: bool,
+    /// Ring the terminal bell
+    pub beep: bool,
+    /// Raise a `watchlist` alert, and so pass it on to the event log, email and `--alert-command`
+    pub log: bool,
+    /// Run with `sh -c`, given the alert as JSON on its standard input
+    pub command: Option<String>
+}
+impl Watch {
+    /// A watchlist matching nothing, to be filled in by `set`
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            addresses: vec![],
+            prefixes: vec![],
+            ssid: None,
+            highlight: false,
+            beep: false,
+            log: false,
+            command: None
+        }
+    }
+    /// Apply a setting from the config file, one of `addresses`, `oui`, `ssid`, `actions` or `command`
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        let invalid = |item: &str| Error::Invalid(key.to_string(), item.to_string());
+        let items = || value.split(',').map(str::trim).filter(|item| !item.is_empty());
+        match key {
+            "addresses" => for address in items() {
+                self.addresses.push(address.parse().map_err(|_| invalid(address))?);
+            }
+            "oui" => for prefix in items() {
+                let hex: String = prefix.chars().filter(|c| !matches!(c, ':' | '-' | '.')).collect::<String>().to_lowercase();
+                if hex.is_empty() || hex.len() > 12 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(invalid(prefix))
+                }
+                self.prefixes.push(hex);
+            }
+            "ssid" => self.ssid = Some(Regex::new(value).map_err(Error::Regex)?),
+            "actions" => for action in items() {
+                match action {
+                    "highlight" => self.highlight = true,
+                    "beep" => self.beep = true,
+                    "log" => self.log = true,
+                    _ => return Err(invalid(action))
+                }
+            }
+            "command" => self.command = Some(value.to_string()),
+            _ => return Err(Error::UnknownSetting(key.to_string()))
+        }
+        Ok(())
+    }
+    /// Why a device is on this watchlist, if it is
+    pub fn reason(&self, address: &MacAddress, device: &KnownDevice) -> Option<String> {
+        if self.addresses.contains(address) {
+            return Some(format!("address {}", address.to_hex_string()))
+        }
+        let hex = address.to_hex_string().replace(':', "");
+        if let Some(prefix) = self.prefixes.iter().find(|prefix| hex.starts_with(prefix.as_str())) {
+            return Some(format!("address starting {}", prefix))
+        }
+        let ssid = self.ssid.as_ref()?;
+        if let Some(beacon) = device.beacon.as_deref().filter(|beacon| ssid.is_match(beacon)) {
+            return Some(format!("beaconing {:?}", beacon))
+        }
+        device.probes.keys().find(|probe| ssid.is_match(probe)).map(|probe| format!("probing for {:?}", probe))
+    }
+}
+
+/// Every watchlist, firing each one's actions once for each device it matches
+pub struct Rules {
+    watches: Vec<Watch>,
+    /// The command each watchlist runs, in order
+    hooks: Vec<Option<Hook>>,
+    /// Watchlists that have already fired, by index, for each device
+    fired: HashSet<(usize, MacAddress)>
+}
+impl Rules {
+    /// Must be started before the sandbox is applied, as the watchlists' commands are run from threads of their own
+    pub fn spawn(watches: Vec<Watch>) -> Self {
+        let hooks = watches.iter()
+            .map(|watch| watch.command.as_ref().map(|command| Hook::spawn(hook::Config {
+                command: command.clone(),
+                timeout: COMMAND_TIMEOUT,
+                // Each watchlist fires once per device, so there are no repeats to hold back
+                interval: Duration::from_secs(0)
+            })))
+            .collect();
+        Self {
+            watches,
+            hooks,
+            fired: HashSet::new()
+        }
+    }
+    /// Look over every device for any newly on a watchlist and act on them, returning whether to ring the bell
+    pub fn check(&mut self, devices: &mut DeviceList, alerts: &mut Alerts, time: Duration) -> bool {
+        let mut beep = false;
+        for (index, watch) in self.watches.iter().enumerate() {
+            for (&address, device) in devices.iter_mut() {
+                if self.fired.contains(&(index, address)) {
+                    continue
+                }
+                let reason = match watch.reason(&address, device) {
+                    Some(reason) => reason,
+                    None => continue
+                };
+                self.fired.insert((index, address));
+                if watch.highlight {
+                    device.watched = Some(watch.name.clone());
+                }
+                beep |= watch.beep;
+                if watch.log || self.hooks[index].is_some() {
+                    let alert = alerts.watched(address, format!("On the {:?} watchlist: {}", watch.name, reason), time, watch.log);
+                    if let Some(hook) = &self.hooks[index] {
+                        hook.alert(&alert);
+                    }
+                }
+            }
+        }
+        beep
+    }
+    /// Forget which devices have been seen, so the watchlists fire again for them
+    pub fn reset(&mut self) {
+        self.fired.clear();
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    UnknownSetting(String),
+    Invalid(String, String),
+    Regex(regex::Error)
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownSetting(key) => write!(f, "unknown watchlist setting {:?}, expected addresses, oui, ssid, actions or command", key),
+            Self::Invalid(key, value) => write!(f, "{:?} is not a valid {} setting", value, key),
+            Self::Regex(error) => write!(f, "invalid ssid regex: {}", error)
+        }
+    }
+}
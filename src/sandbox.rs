@@ -0,0 +1,185 @@
+use std::{ffi::CString, fmt, io, os::unix::ffi::OsStrExt, path::Path};
+
+// Landlock syscalls have the same numbers on every architecture
+const SYS_LANDLOCK_CREATE_RULESET: libc::c_long = 444;
+const SYS_LANDLOCK_ADD_RULE: libc::c_long = 445;
+const SYS_LANDLOCK_RESTRICT_SELF: libc::c_long = 446;
+
+const LANDLOCK_RULE_PATH_BENEATH: libc::c_int = 1;
+
+const ACCESS_EXECUTE: u64 = 1 << 0;
+const ACCESS_WRITE_FILE: u64 = 1 << 1;
+const ACCESS_READ_FILE: u64 = 1 << 2;
+const ACCESS_READ_DIR: u64 = 1 << 3;
+const ACCESS_MAKE_DIR: u64 = 1 << 7;
+const ACCESS_MAKE_REG: u64 = 1 << 8;
+/// Every filesystem access right known to the first landlock ABI
+const ACCESS_ALL: u64 = (1 << 13) - 1;
+
+#[repr(C)]
+struct RulesetAttr {
+    handled_access_fs: u64
+}
+#[repr(C, packed)]
+struct PathBeneathAttr {
+    allowed_access: u64,
+    parent_fd: i32
+}
+
+const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+
+const BPF_LD_W_ABS: u16 = 0x20;
+const BPF_JEQ_K: u16 = 0x15;
+const BPF_JGE_K: u16 = 0x35;
+const BPF_RET_K: u16 = 0x06;
+
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: u32 = 0xc000_003e;
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH: u32 = 0xc000_00b7;
+/// Never used, as the sandbox is refused on architectures without a value above
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+const AUDIT_ARCH: u32 = 0;
+/// Syscall numbers at or above this belong to the x32 ABI, which would otherwise slip past the filter
+#[cfg(target_arch = "x86_64")]
+const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+#[cfg(not(target_arch = "x86_64"))]
+const X32_SYSCALL_BIT: u32 = u32::MAX;
+
+// Offsets into struct seccomp_data
+const DATA_NR: u32 = 0;
+const DATA_ARCH: u32 = 4;
+const DATA_ARG0: u32 = 16;
+
+#[repr(C)]
+struct SockFilter {
+    code: u16,
+    jt: u8,
+    jf: u8,
+    k: u32
+}
+#[repr(C)]
+struct SockFprog {
+    len: libc::c_ushort,
+    filter: *const SockFilter
+}
+
+const fn statement(code: u16, k: u32) -> SockFilter {
+    SockFilter { code, jt: 0, jf: 0, k }
+}
+const fn jump(code: u16, k: u32, jt: u8, jf: u8) -> SockFilter {
+    SockFilter { code, jt, jf, k }
+}
+
+/// Confine the calling thread, and any it starts from then on, once it no longer needs to open captures or reach the network:
+/// only local sockets may be created, and files may only be touched in the current directory, /sys
+/// and the folders of the save file and of any exports segmented by --segment, where new files are created
+pub fn apply(outputs: &[&Path]) -> Result<(), Error> {
+    if !cfg!(any(target_arch = "x86_64", target_arch = "aarch64")) {
+        return Err(Error::Unsupported)
+    }
+    unsafe {
+        if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1 as libc::c_ulong, 0 as libc::c_ulong, 0 as libc::c_ulong, 0 as libc::c_ulong) != 0 {
+            return Err(Error::NoNewPrivileges(io::Error::last_os_error()))
+        }
+    }
+    restrict_sockets().map_err(Error::Seccomp)?;
+    restrict_filesystem(outputs).map_err(Error::Landlock)
+}
+
+fn restrict_filesystem(outputs: &[&Path]) -> io::Result<()> {
+    let attr = RulesetAttr {
+        handled_access_fs: ACCESS_ALL
+    };
+    let ruleset = unsafe {
+        libc::syscall(SYS_LANDLOCK_CREATE_RULESET, &attr as *const RulesetAttr, std::mem::size_of::<RulesetAttr>(), 0)
+    };
+    if ruleset < 0 {
+        return Err(io::Error::last_os_error())
+    }
+    let ruleset = ruleset as libc::c_int;
+
+    let result = (|| {
+        // Exports and the save file are written alongside where blockade was started, with a folder per exported device
+        allow(ruleset, Path::new("."), ACCESS_READ_FILE | ACCESS_READ_DIR | ACCESS_WRITE_FILE | ACCESS_MAKE_REG | ACCESS_MAKE_DIR)?;
+        // rfkill state is polled, and written to unblock the radio
+        allow(ruleset, Path::new("/sys"), ACCESS_READ_FILE | ACCESS_READ_DIR | ACCESS_WRITE_FILE)?;
+        // Rotated save files and incident files are created beside the first, which is read back when extracting a device's frames,
+        // and each segment's exports beside the first segment's
+        for folder in outputs.iter().filter_map(|output| output.parent()).filter(|folder| !folder.as_os_str().is_empty()) {
+            allow(ruleset, folder, ACCESS_READ_FILE | ACCESS_WRITE_FILE | ACCESS_MAKE_REG)?;
+        }
+        if unsafe { libc::syscall(SYS_LANDLOCK_RESTRICT_SELF, ruleset, 0) } != 0 {
+            return Err(io::Error::last_os_error())
+        }
+        Ok(())
+    })();
+    unsafe { libc::close(ruleset); }
+    result
+}
+
+fn allow(ruleset: libc::c_int, path: &Path, access: u64) -> io::Result<()> {
+    let path = CString::new(path.as_os_str().as_bytes()).map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+    let fd = unsafe { libc::open(path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error())
+    }
+    let rule = PathBeneathAttr {
+        allowed_access: access & !ACCESS_EXECUTE,
+        parent_fd: fd
+    };
+    let result = unsafe { libc::syscall(SYS_LANDLOCK_ADD_RULE, ruleset, LANDLOCK_RULE_PATH_BENEATH, &rule as *const PathBeneathAttr, 0) };
+    let error = io::Error::last_os_error();
+    unsafe { libc::close(fd); }
+    if result != 0 {
+        return Err(error)
+    }
+    Ok(())
+}
+
+fn restrict_sockets() -> io::Result<()> {
+    let deny = SECCOMP_RET_ERRNO | libc::EACCES as u32;
+    let filter = [
+        statement(BPF_LD_W_ABS, DATA_ARCH),
+        jump(BPF_JEQ_K, AUDIT_ARCH, 1, 0),
+        statement(BPF_RET_K, deny),
+        statement(BPF_LD_W_ABS, DATA_NR),
+        jump(BPF_JGE_K, X32_SYSCALL_BIT, 0, 1),
+        statement(BPF_RET_K, deny),
+        jump(BPF_JEQ_K, libc::SYS_socket as u32, 0, 3),
+        statement(BPF_LD_W_ABS, DATA_ARG0),
+        jump(BPF_JEQ_K, libc::AF_UNIX as u32, 1, 0),
+        statement(BPF_RET_K, deny),
+        statement(BPF_RET_K, SECCOMP_RET_ALLOW)
+    ];
+    let program = SockFprog {
+        len: filter.len() as libc::c_ushort,
+        filter: filter.as_ptr()
+    };
+    if unsafe { libc::prctl(libc::PR_SET_SECCOMP, libc::SECCOMP_MODE_FILTER as libc::c_ulong, &program as *const SockFprog as libc::c_ulong, 0 as libc::c_ulong, 0 as libc::c_ulong) } != 0 {
+        return Err(io::Error::last_os_error())
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The seccomp filter only knows the architectures it checks syscalls are made for
+    Unsupported,
+    NoNewPrivileges(io::Error),
+    Landlock(io::Error),
+    Seccomp(io::Error)
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Unsupported => write!(f, "sandboxing is only supported on x86_64 and aarch64"),
+            Self::NoNewPrivileges(error) => write!(f, "unable to forbid gaining privileges: {}", error),
+            Self::Landlock(error) if error.raw_os_error() == Some(libc::ENOSYS) || error.raw_os_error() == Some(libc::EOPNOTSUPP) =>
+                write!(f, "landlock is not supported or not enabled by this kernel"),
+            Self::Landlock(error) => write!(f, "unable to restrict filesystem access: {}", error),
+            Self::Seccomp(error) => write!(f, "unable to restrict network access: {}", error)
+        }
+    }
+}
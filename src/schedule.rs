@@ -0,0 +1,130 @@
+use std::{
+    fmt, mem,
+    path::{Path, PathBuf},
+    ptr,
+    time::{Duration, Instant, SystemTime}
+};
+
+/// When an unattended capture stops, and how often it moves on to a fresh segment, for scripted overnight surveys
+pub struct Schedule {
+    /// How long to capture for before stopping as if asked to
+    pub duration: Option<Duration>,
+    /// How long each segment lasts, after which the save file and exports carry on in new files
+    pub segment: Option<Duration>,
+    started: Instant,
+    segment_started: Instant,
+    /// The Unix time the current segment started, which its files are named for
+    segment_time: u64,
+    /// Which segment is being captured, counting from 1
+    pub segments: u32
+}
+impl Schedule {
+    pub fn new(duration: Option<Duration>, segment: Option<Duration>) -> Self {
+        Self {
+            duration,
+            segment,
+            started: Instant::now(),
+            segment_started: Instant::now(),
+            segment_time: unix_time(),
+            segments: 1
+        }
+    }
+    /// True once the capture has run for as long as it was asked to
+    pub fn finished(&self) -> bool {
+        self.duration.map_or(false, |duration| self.started.elapsed() >= duration)
+    }
+    /// True once the current segment has run its course, moving on to the next
+    pub fn next_segment(&mut self) -> bool {
+        match self.segment {
+            Some(segment) if self.segment_started.elapsed() >= segment => {
+                // Kept to the schedule rather than drifting by however late this was noticed
+                self.segment_started += segment;
+                self.segment_time += segment.as_secs();
+                self.segments += 1;
+                true
+            }
+            _ => false
+        }
+    }
+    /// A file as named for the current segment, or as it is when not segmenting
+    pub fn path(&self, path: impl AsRef<Path>) -> PathBuf {
+        let path = path.as_ref();
+        if self.segment.is_none() {
+            return path.to_path_buf()
+        }
+        timestamped(path, self.segment_time)
+    }
+    /// The time left until the capture stops, if it is going to
+    pub fn remaining(&self) -> Option<Duration> {
+        self.duration.map(|duration| duration.saturating_sub(self.started.elapsed()))
+    }
+}
+
+/// devices.csv becomes devices-1700000000.csv, named for the Unix time given
+fn timestamped(path: &Path, time: u64) -> PathBuf {
+    let stem = path.file_stem().map_or_else(|| "capture".into(), |stem| stem.to_string_lossy());
+    let name = match path.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, time, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, time)
+    };
+    path.with_file_name(name)
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |since| since.as_secs())
+}
+
+/// Parse when to start capturing, either a time of day such as 22:30 or 22:30:15, taken as the next time the
+/// local clock reads it, or a Unix time
+pub fn parse_start(text: &str) -> Result<SystemTime, Error> {
+    let text = text.trim();
+    if let Ok(time) = text.parse::<u64>() {
+        return Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(time))
+    }
+    let parts: Vec<_> = text.split(':').map(str::parse::<i32>).collect();
+    let (hour, minute, second) = match parts.as_slice() {
+        [Ok(hour), Ok(minute)] => (*hour, *minute, 0),
+        [Ok(hour), Ok(minute), Ok(second)] => (*hour, *minute, *second),
+        _ => return Err(Error::Invalid(text.to_string()))
+    };
+    if !(0..24).contains(&hour) || !(0..60).contains(&minute) || !(0..60).contains(&second) {
+        return Err(Error::OutOfRange(text.to_string()))
+    }
+    // mktime works out the daylight saving and carries a day past the end of the month over
+    let start = unsafe {
+        let now = libc::time(ptr::null_mut());
+        let mut tm: libc::tm = mem::zeroed();
+        libc::localtime_r(&now, &mut tm);
+        let at = |day_offset| {
+            let mut tm = tm;
+            tm.tm_mday += day_offset;
+            tm.tm_hour = hour;
+            tm.tm_min = minute;
+            tm.tm_sec = second;
+            tm.tm_isdst = -1;
+            libc::mktime(&mut tm)
+        };
+        match at(0) {
+            today if today > now => today,
+            _ => at(1)
+        }
+    };
+    if start < 0 {
+        return Err(Error::OutOfRange(text.to_string()))
+    }
+    Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(start as u64))
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Invalid(String),
+    OutOfRange(String)
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Invalid(text) => write!(f, "{:?} is neither a time of day such as 22:30 nor a Unix time", text),
+            Self::OutOfRange(text) => write!(f, "{:?} is not a time of day", text)
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+    process
+};
+use eui48::MacAddress;
+use pcap::Capture;
+use radiotap::Radiotap;
+
+use crate::{
+    DeviceList,
+    Role,
+    alerts::{Alerts, Kind},
+    export::{Format, Json},
+    inventory,
+    manuf,
+    policy::Policy,
+    reanalyse,
+    store::{Fields, Memory, Record, Store},
+    survey::Survey,
+    wifi
+};
+
+/// A capture built into blockade with something for every detector to find, so a build can be checked without an adapter
+const SAMPLE: &[u8] = include_bytes!("../samples/selftest.pcap");
+const FRAMES: usize = 21;
+/// The access point the sample is centred on, beaconing `CoffeeShop` with WPA2, under an Apple OUI
+const ACCESS_POINT: [u8; 6] = [0x00, 0x1b, 0x63, 0x11, 0x22, 0x33];
+/// Completes the first half of a handshake with the access point after probing for `CorpWiFi`
+const CLIENT: [u8; 6] = [0x3a, 0x7d, 0x1e, 0x44, 0x55, 0x66];
+/// Hides its SSID, which a client gives away as `Backroom` by associating
+const HIDDEN: [u8; 6] = [0x00, 0x1b, 0x63, 0x44, 0x55, 0x66];
+/// Probes with a 1.5Mbps rate no standard radio uses
+const ODD_RATES: [u8; 6] = [0x7a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f];
+/// Relays frames over WDS
+const REPEATER: [u8; 6] = [0x00, 0x1b, 0x63, 0x77, 0x88, 0x99];
+
+/// The outcome of checking one part of blockade, with what was found either way
+pub struct Check {
+    pub component: &'static str,
+    pub result: Result<String, String>
+}
+
+/// Run the sample capture through each part of blockade in turn, stopping early only where later parts depend on an earlier one
+pub fn run() -> Vec<Check> {
+    let mut checks = vec![];
+    let path = std::env::temp_dir().join(format!("blockade-selftest-{}.pcap", process::id()));
+    if let Err(error) = fs::write(&path, SAMPLE) {
+        checks.push(Check { component: "capture file", result: Err(format!("unable to write the sample to {}: {}", path.display(), error)) });
+        return checks
+    }
+    let frames = read_frames(&path);
+    checks.push(Check { component: "capture file", result: frames.as_ref().map(|frames| format!("{} frames read with libpcap", frames.len())).map_err(Clone::clone) });
+    if let Ok(frames) = frames {
+        checks.push(Check { component: "radiotap", result: radiotap(&frames) });
+        checks.push(Check { component: "frame parser", result: parser(&frames) });
+    }
+    let oui_db = match manuf::load_default() {
+        Ok(oui_db) => oui_db,
+        Err(error) => {
+            checks.push(Check { component: "oui database", result: Err(error.to_string()) });
+            let _ = fs::remove_file(&path);
+            return checks
+        }
+    };
+    checks.push(Check {
+        component: "oui database",
+        result: match oui_db.query_by_mac(&MacAddress::new(ACCESS_POINT)) {
+            Ok(Some(entry)) => Ok(format!("{} is made by {}", MacAddress::new(ACCESS_POINT).to_hex_string(), entry.name_short)),
+            _ => Err(format!("no manufacturer found for {}", MacAddress::new(ACCESS_POINT).to_hex_string()))
+        }
+    });
+
+    // A policy of its own, so the policy detector is checked whatever the config file says
+    let mut policy = Policy::new("CoffeeShop".to_string());
+    if let Err(error) = policy.set("security", "wpa3") {
+        checks.push(Check { component: "detectors", result: Err(error.to_string()) });
+    }
+    let mut alerts = Alerts::new(HashMap::new(), HashMap::new(), vec![policy]);
+    let devices = reanalyse::session_devices(&path, &oui_db, &mut alerts);
+    let _ = fs::remove_file(&path);
+    let devices = match devices {
+        Ok(devices) => devices,
+        Err(error) => {
+            checks.push(Check { component: "device tracking", result: Err(error.to_string()) });
+            return checks
+        }
+    };
+    checks.push(Check { component: "device tracking", result: tracking(&devices) });
+    checks.push(Check { component: "detectors", result: detectors(&mut alerts) });
+    let survey = Survey::new(Some("selftest"), None, None);
+    checks.push(Check { component: "csv export", result: csv(&devices, &survey) });
+    checks.push(Check { component: "json export", result: json(&devices, &survey) });
+    checks.push(Check { component: "device store", result: stored(&devices, &survey) });
+    checks
+}
+
+fn read_frames(path: &Path) -> Result<Vec<Vec<u8>>, String> {
+    let mut capture = Capture::from_file(path).map_err(|error| error.to_string())?;
+    let mut frames = vec![];
+    loop {
+        match capture.next() {
+            Ok(packet) => frames.push(packet.data.to_vec()),
+            Err(pcap::Error::NoMorePackets) => break,
+            Err(error) => return Err(error.to_string())
+        }
+    }
+    if frames.len() != FRAMES {
+        return Err(format!("expected {} frames but read {}", FRAMES, frames.len()))
+    }
+    Ok(frames)
+}
+
+fn radiotap(frames: &[Vec<u8>]) -> Result<String, String> {
+    for (index, frame) in frames.iter().enumerate() {
+        let (radiotap, _) = Radiotap::parse(frame).map_err(|error| format!("frame {}: {:?}", index + 1, error))?;
+        if radiotap.channel.is_none() || radiotap.antenna_signal.is_none() {
+            return Err(format!("frame {}: the channel or signal is missing", index + 1))
+        }
+    }
+    Ok(format!("{} headers read with their channel and signal", frames.len()))
+}
+
+fn parser(frames: &[Vec<u8>]) -> Result<String, String> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for (index, frame) in frames.iter().enumerate() {
+        let data = Radiotap::parse(frame).map(|(_, data)| data).map_err(|error| format!("frame {}: {:?}", index + 1, error))?;
+        wifi::Summary::parse(data).map_err(|error| format!("frame {}: header {}", index + 1, error))?;
+        let kind = match wifi::Frame::parse(data).map_err(|error| format!("frame {}: {}", index + 1, error))? {
+            wifi::Frame::Beacon { .. } => "beacon",
+            wifi::Frame::ProbeRequest { .. } => "probe request",
+            wifi::Frame::ProbeResponse { .. } => "probe response",
+            wifi::Frame::AssociationRequest { .. } => "association request",
+            wifi::Frame::Data { .. } => "data",
+            wifi::Frame::Wds { .. } => "wds",
+            _ => "other"
+        };
+        *counts.entry(kind).or_default() += 1;
+    }
+    let expected = [("beacon", 11), ("probe request", 3), ("probe response", 1), ("association request", 1), ("data", 4), ("wds", 1)];
+    for &(kind, count) in expected.iter() {
+        let parsed = counts.get(kind).copied().unwrap_or_default();
+        if parsed != count {
+            return Err(format!("expected {} {} frame(s) but parsed {}", count, kind, parsed))
+        }
+    }
+    Ok(format!("{} frames parsed as expected", frames.len()))
+}
+
+fn tracking(devices: &DeviceList) -> Result<String, String> {
+    let device = |address: [u8; 6]| devices.get(&MacAddress::new(address)).ok_or_else(|| format!("{} was not tracked", MacAddress::new(address).to_hex_string()));
+    let access_point = device(ACCESS_POINT)?;
+    if access_point.beacon.as_deref() != Some("CoffeeShop") || access_point.role != Some(Role::AccessPoint) {
+        return Err("the access point's SSID or role is wrong".to_string())
+    }
+    if !access_point.handshake_complete() {
+        return Err("the handshake with the access point was missed".to_string())
+    }
+    let client = device(CLIENT)?;
+    if !client.probes.contains_key("CorpWiFi") || client.associated != Some(MacAddress::new(ACCESS_POINT)) {
+        return Err("the client's probes or access point are wrong".to_string())
+    }
+    if device(HIDDEN)?.beacon.as_deref() != Some("Backroom") {
+        return Err("the hidden network's SSID was not revealed".to_string())
+    }
+    Ok(format!("{} devices tracked as expected", devices.len()))
+}
+
+fn detectors(alerts: &mut Alerts) -> Result<String, String> {
+    let raised = alerts.take_fresh();
+    let expected = [
+        (Kind::SpoofedBssid, ACCESS_POINT),
+        (Kind::Policy, ACCESS_POINT),
+        (Kind::HiddenSsid, HIDDEN),
+        (Kind::OddRates, ODD_RATES),
+        (Kind::Repeater, REPEATER)
+    ];
+    let missed: Vec<_> = expected.iter()
+        .filter(|&&(kind, address)| !raised.iter().any(|alert| alert.kind == kind && alert.address == MacAddress::new(address)))
+        .map(|(kind, _)| kind.name())
+        .collect();
+    if !missed.is_empty() {
+        return Err(format!("missed {}", missed.join(", ")))
+    }
+    Ok(format!("{} alert(s) raised, covering every detector", raised.len()))
+}
+
+fn csv(devices: &DeviceList, survey: &Survey) -> Result<String, String> {
+    let mut output = vec![];
+    inventory::write(&mut output, Format::Csv, &Fields::default(), devices, survey).map_err(|error| error.to_string())?;
+    let output = String::from_utf8(output).map_err(|error| error.to_string())?;
+    let mut lines = output.lines();
+    if !lines.next().map_or(false, |header| header.starts_with("address,")) {
+        return Err("the header is missing".to_string())
+    }
+    let rows = lines.count();
+    // At least one row per device, and one per sensor that heard it
+    if rows < devices.len() {
+        return Err(format!("expected at least {} rows but wrote {}", devices.len(), rows))
+    }
+    Ok(format!("{} rows written", rows))
+}
+
+fn json(devices: &DeviceList, survey: &Survey) -> Result<String, String> {
+    let mut output = vec![];
+    inventory::write(&mut output, Format::Json, &Fields::default(), devices, survey).map_err(|error| error.to_string())?;
+    let output = String::from_utf8(output).map_err(|error| error.to_string())?;
+    match Json::parse(&output).as_ref().and_then(|json| json.get("devices")) {
+        Some(Json::Array(exported)) if exported.len() == devices.len() => Ok(format!("{} devices written and read back", exported.len())),
+        Some(Json::Array(exported)) => Err(format!("expected {} devices but wrote {}", devices.len(), exported.len())),
+        _ => Err("the output isn't valid JSON with a list of devices".to_string())
+    }
+}
+
+fn stored(devices: &DeviceList, survey: &Survey) -> Result<String, String> {
+    let records = Record::all(devices, survey);
+    for record in records.iter() {
+        let read = Json::parse(&record.json(&Fields::default())).as_ref().and_then(Record::from_json);
+        if read.as_ref() != Some(record) {
+            return Err(format!("{} changed when written as JSON and read back", record.address))
+        }
+    }
+    let mut store = Memory::default();
+    store.put_all(&records).map_err(|error| error.to_string())?;
+    if store.all().map_err(|error| error.to_string())? != records {
+        return Err("the records read back differ from those stored".to_string())
+    }
+    Ok(format!("{} records stored and read back", records.len()))
+}
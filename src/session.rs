@@ -0,0 +1,244 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+    time::{Duration, Instant}
+};
+use pcap::{Capture, Linktype, Offline, PacketHeader};
+
+use crate::{capture::Frame, gps::Fix};
+
+/// Starts every session file, with the version of the format last
+const MAGIC: &[u8; 8] = b"BRSESS\0\x01";
+/// Most frames handed on in one go, so the interface keeps drawing while a fast replay catches up
+const MAX_BATCH: usize = 10_000;
+
+const HAS_CHANNEL: u8 = 1 << 0;
+const HAS_FIX: u8 = 1 << 1;
+
+/// A frame as it was taken in, with what was known around it that a pcap file can't keep
+pub struct Record {
+    /// Since the capture started
+    pub time: Duration,
+    /// Which of the session's sensors heard it, by index
+    pub sensor: usize,
+    /// The channel the sensor was tuned or hopped to at the time
+    pub channel: Option<u32>,
+    /// Where the sensor was at the time
+    pub fix: Option<Fix>,
+    pub frame: Frame
+}
+
+/// Records every frame taken in, with its timing, channel and position, to be replayed with `--replay`
+pub struct Writer(BufWriter<File>);
+impl Writer {
+    /// Start a session file naming the sensors its frames are heard by, in order
+    pub fn create(path: impl AsRef<Path>, sensors: &[String]) -> io::Result<Self> {
+        let mut output = BufWriter::new(File::create(path)?);
+        output.write_all(MAGIC)?;
+        output.write_all(&[sensors.len().min(u8::MAX as usize) as u8])?;
+        for sensor in sensors.iter().take(u8::MAX as usize) {
+            output.write_all(&[sensor.len().min(u8::MAX as usize) as u8])?;
+            output.write_all(&sensor.as_bytes()[..sensor.len().min(u8::MAX as usize)])?;
+        }
+        Ok(Self(output))
+    }
+    /// Add a frame taken in at a time since the capture started, by the sensor at an index, with its context
+    pub fn write(&mut self, time: Duration, sensor: usize, channel: Option<u32>, fix: Option<Fix>, frame: &Frame) -> io::Result<()> {
+        let output = &mut self.0;
+        let flags = if channel.is_some() { HAS_CHANNEL } else { 0 } | if fix.is_some() { HAS_FIX } else { 0 };
+        output.write_all(&(time.as_micros() as u64).to_le_bytes())?;
+        output.write_all(&[sensor as u8, flags])?;
+        if let Some(channel) = channel {
+            output.write_all(&channel.to_le_bytes())?;
+        }
+        if let Some(fix) = fix {
+            output.write_all(&fix.latitude.to_le_bytes())?;
+            output.write_all(&fix.longitude.to_le_bytes())?;
+        }
+        output.write_all(&(frame.header.ts.tv_sec as i64).to_le_bytes())?;
+        output.write_all(&(frame.header.ts.tv_usec as i64).to_le_bytes())?;
+        output.write_all(&frame.header.len.to_le_bytes())?;
+        output.write_all(&(frame.data.len() as u32).to_le_bytes())?;
+        output.write_all(&frame.data)
+    }
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Where replayed frames are read from
+enum Input {
+    Session(BufReader<File>),
+    /// A pcap file, read with `--read`, along with when its first frame was captured in microseconds
+    Pcap(Box<Capture<Offline>>, Option<u64>)
+}
+
+/// Plays a session file, or a pcap file, back at its original pace, or faster
+pub struct Replay {
+    input: Input,
+    /// The sensors the session was captured by, in the order records refer to them
+    pub sensors: Vec<String>,
+    /// How many times faster than it was captured, or None to take frames in as fast as they can be read
+    pub speed: Option<f64>,
+    /// How far into the session the replay has got
+    pub position: Duration,
+    polled: Instant,
+    /// Read ahead, and not yet due
+    next: Option<Record>,
+    /// Every record has been handed on
+    pub finished: bool
+}
+impl Replay {
+    pub fn open(path: impl AsRef<Path>, speed: Option<f64>) -> io::Result<Self> {
+        let mut input = BufReader::new(File::open(path)?);
+        let mut magic = [0; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a blockade-recon session file"))
+        }
+        let mut sensors = vec![];
+        for _ in 0..read_u8(&mut input)? {
+            let mut name = vec![0; read_u8(&mut input)? as usize];
+            input.read_exact(&mut name)?;
+            sensors.push(String::from_utf8_lossy(&name).to_string());
+        }
+        if sensors.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "the session names no sensors"))
+        }
+        Ok(Self::new(Input::Session(input), sensors, speed))
+    }
+    /// Read a pcap file saved elsewhere, by this or any other radiotap capture, as if heard by one sensor named after it
+    pub fn pcap(path: impl AsRef<Path>, speed: Option<f64>) -> Result<Self, pcap::Error> {
+        let path = path.as_ref();
+        let capture = Capture::from_file(path)?;
+        if capture.get_datalink() != Linktype::IEEE802_11_RADIOTAP {
+            return Err(pcap::Error::PcapError("the capture has no radiotap headers".to_string()))
+        }
+        let sensor = path.file_stem().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().to_string());
+        Ok(Self::new(Input::Pcap(Box::new(capture), None), vec![sensor], speed))
+    }
+    fn new(input: Input, sensors: Vec<String>, speed: Option<f64>) -> Self {
+        Self {
+            input,
+            sensors,
+            speed,
+            position: Duration::from_secs(0),
+            polled: Instant::now(),
+            next: None,
+            finished: false
+        }
+    }
+    /// Every record that has come due since the last call, keeping still while paused
+    pub fn due(&mut self, paused: bool) -> io::Result<Vec<Record>> {
+        let now = Instant::now();
+        if let (false, Some(speed)) = (paused, self.speed) {
+            self.position += now.duration_since(self.polled).mul_f64(speed);
+        }
+        self.polled = now;
+        let mut due = vec![];
+        while due.len() < MAX_BATCH && !self.finished && !(paused && self.speed.is_none()) {
+            if self.next.is_none() {
+                self.next = self.read()?;
+                self.finished = self.next.is_none();
+            }
+            match self.next.take() {
+                Some(record) if record.time <= self.position || self.speed.is_none() => {
+                    self.position = self.position.max(record.time);
+                    due.push(record)
+                }
+                record => {
+                    self.next = record;
+                    break
+                }
+            }
+        }
+        Ok(due)
+    }
+    /// Read the next record, or None at the end of the file, which may have been cut off part way through one
+    fn read(&mut self) -> io::Result<Option<Record>> {
+        let input = match &mut self.input {
+            Input::Session(input) => input,
+            Input::Pcap(capture, started) => return match capture.next() {
+                Ok(packet) => {
+                    let micros = packet.header.ts.tv_sec as u64 * 1_000_000 + packet.header.ts.tv_usec as u64;
+                    Ok(Some(Record {
+                        time: Duration::from_micros(micros.saturating_sub(*started.get_or_insert(micros))),
+                        sensor: 0,
+                        channel: None,
+                        fix: None,
+                        frame: Frame::from(packet)
+                    }))
+                }
+                Err(pcap::Error::NoMorePackets) => Ok(None),
+                Err(error) => Err(io::Error::new(io::ErrorKind::InvalidData, error.to_string()))
+            }
+        };
+        let mut time = [0; 8];
+        match input.read_exact(&mut time) {
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            result => result?
+        }
+        match read_record(input, Duration::from_micros(u64::from_le_bytes(time))) {
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            Ok(record) if record.sensor >= self.sensors.len() => Err(io::Error::new(io::ErrorKind::InvalidData, "a frame names a sensor the session doesn't have")),
+            record => record.map(Some)
+        }
+    }
+}
+
+/// The rest of a record, after the time it starts with
+fn read_record(input: &mut impl Read, time: Duration) -> io::Result<Record> {
+    let sensor = read_u8(input)? as usize;
+    let flags = read_u8(input)?;
+    let channel = if flags & HAS_CHANNEL != 0 { Some(read_u32(input)?) } else { None };
+    let fix = if flags & HAS_FIX != 0 {
+        Some(Fix { latitude: read_f64(input)?, longitude: read_f64(input)? })
+    } else {
+        None
+    };
+    let tv_sec = read_i64(input)?;
+    let tv_usec = read_i64(input)?;
+    let len = read_u32(input)?;
+    let caplen = read_u32(input)?;
+    let mut data = vec![0; caplen as usize];
+    input.read_exact(&mut data)?;
+    Ok(Record {
+        time,
+        sensor,
+        channel,
+        fix,
+        frame: Frame {
+            header: PacketHeader {
+                ts: libc::timeval {
+                    tv_sec: tv_sec as libc::time_t,
+                    tv_usec: tv_usec as libc::suseconds_t
+                },
+                caplen,
+                len
+            },
+            data
+        }
+    })
+}
+
+fn read_u8(input: &mut impl Read) -> io::Result<u8> {
+    let mut bytes = [0; 1];
+    input.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+fn read_u32(input: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+fn read_i64(input: &mut impl Read) -> io::Result<i64> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(i64::from_le_bytes(bytes))
+}
+fn read_f64(input: &mut impl Read) -> io::Result<f64> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(f64::from_le_bytes(bytes))
+}
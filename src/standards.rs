@@ -0,0 +1,61 @@
+use std::fmt;
+
+use crate::wifi::Tag;
+
+/// The Wi-Fi generation, channel widths and spatial streams an access point advertises
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Standards {
+    /// The newest Wi-Fi generation supported, from 4 for 802.11n to 7 for 802.11be, or None for 802.11a/b/g alone
+    pub generation: Option<u8>,
+    /// The widest channel supported, in MHz
+    pub width: u16,
+    pub streams: u8
+}
+impl Standards {
+    pub fn from_tags(tags: &[Tag]) -> Self {
+        let mut standards = Self {
+            generation: None,
+            width: 20,
+            streams: 1
+        };
+        for tag in tags {
+            let (generation, width, streams) = match *tag {
+                Tag::HtCapabilities { forty, streams } => (4, if forty { 40 } else { 20 }, streams),
+                Tag::VhtCapabilities { width, streams } => (5, width, streams),
+                Tag::HeCapabilities { width, streams } => (6, width, streams),
+                // EHT capabilities aren't parsed any further, so the widths and streams come from the older elements alongside
+                Tag::EhtCapabilities => (7, 20, 1),
+                _ => continue
+            };
+            standards.generation = standards.generation.max(Some(generation));
+            standards.width = standards.width.max(width);
+            standards.streams = standards.streams.max(streams);
+        }
+        standards
+    }
+    /// A short label such as `Wi-Fi 6`, for lists
+    pub fn badge(&self) -> String {
+        match self.generation {
+            Some(generation) => format!("Wi-Fi {}", generation),
+            None => "a/b/g".to_string()
+        }
+    }
+    pub fn standard(&self) -> &'static str {
+        match self.generation {
+            Some(4) => "802.11n",
+            Some(5) => "802.11ac",
+            Some(6) => "802.11ax",
+            Some(7) => "802.11be",
+            _ => "802.11a/b/g"
+        }
+    }
+}
+impl fmt::Display for Standards {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.generation {
+            Some(_) => write!(f, "{} ({})", self.badge(), self.standard())?,
+            None => write!(f, "{}", self.standard())?
+        }
+        write!(f, ", up to {}MHz, {} spatial stream(s)", self.width, self.streams)
+    }
+}
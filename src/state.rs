@@ -0,0 +1,60 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::PathBuf
+};
+
+/// UI settings remembered between runs, as `key=value` lines
+#[derive(Debug, Default)]
+pub struct State(BTreeMap<String, String>);
+impl State {
+    /// Where state is kept, following the XDG base directory specification
+    pub fn path() -> Option<PathBuf> {
+        let base = match env::var_os("XDG_STATE_HOME").filter(|path| !path.is_empty()) {
+            Some(path) => PathBuf::from(path),
+            None => PathBuf::from(env::var_os("HOME")?).join(".local/state")
+        };
+        Some(base.join("blockade-recon/state"))
+    }
+    /// Open the state file, creating it if needed, and read what it holds
+    /// The file is kept open so it can be written on exit without access to its directory
+    pub fn open() -> io::Result<(File, Self)> {
+        let path = Self::path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory to keep state in"))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new().read(true).write(true).create(true).open(path)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let state = contents.lines()
+            .filter_map(|line| {
+                let mut parts = line.splitn(2, '=');
+                Some((parts.next()?.to_string(), parts.next()?.to_string()))
+            })
+            .collect();
+        Ok((file, Self(state)))
+    }
+    pub fn save(&self, file: &mut File) -> io::Result<()> {
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        for (key, value) in self.0.iter() {
+            writeln!(file, "{}={}", key, value.replace('\n', " "))?;
+        }
+        file.flush()
+    }
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+    pub fn set(&mut self, key: &str, value: impl ToString) {
+        self.0.insert(key.to_string(), value.to_string());
+    }
+    pub fn remove(&mut self, key: &str) {
+        self.0.remove(key);
+    }
+    /// Read a flag saved as `true` or `false`
+    pub fn flag(&self, key: &str) -> Option<bool> {
+        self.get(key).and_then(|value| value.parse().ok())
+    }
+}
@@ -0,0 +1,306 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    time::{Duration, Instant}
+};
+
+use radiotap::Radiotap;
+
+use crate::{
+    nl80211,
+    wifi::{self, Kind, Summary}
+};
+
+/// How many seconds of throughput are kept for graphing
+const HISTORY: usize = 600;
+/// The OFDM preamble and header sent ahead of every frame
+const PREAMBLE: Duration = Duration::from_micros(20);
+/// Assumed for frames whose rate radiotap doesn't give, such as those sent at an MCS, being the lowest OFDM rate
+const ASSUMED_RATE: f32 = 6.0;
+
+/// Roughly how long a frame of a given length took to send at a rate in Mbps
+pub fn airtime(length: usize, rate: Option<f32>) -> Duration {
+    let rate = rate.filter(|&rate| rate > 0.0).unwrap_or(ASSUMED_RATE);
+    PREAMBLE + Duration::from_secs_f64(length as f64 * 8.0 / (rate as f64 * 1_000_000.0))
+}
+
+/// How far a frame's signal stood above the noise in dB, from the signal and noise floor in dBm where the adapter
+/// gives both, or else from the pair it gives in dB above its own reference, which differ all the same
+pub fn snr(radiotap: &Radiotap) -> Option<i16> {
+    if let (Some(signal), Some(noise)) = (radiotap.antenna_signal, radiotap.antenna_noise) {
+        return Some(signal.value as i16 - noise.value as i16)
+    }
+    match (radiotap.antenna_signal_db, radiotap.antenna_noise_db) {
+        (Some(signal), Some(noise)) => Some(signal.value as i16 - noise.value as i16),
+        _ => None
+    }
+}
+
+/// Signal to noise ratios and noise floors over the frames that reported them
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SignalQuality {
+    /// Frames giving a signal to noise ratio
+    pub frames: u64,
+    snr_total: i64,
+    pub lowest: Option<i16>,
+    pub highest: Option<i16>,
+    noise_frames: u64,
+    noise_total: i64,
+    /// The most recent noise floor in dBm
+    pub noise: Option<i8>
+}
+impl SignalQuality {
+    pub fn heard(&mut self, snr: Option<i16>, noise: Option<i8>) {
+        if let Some(snr) = snr {
+            self.frames += 1;
+            self.snr_total += snr as i64;
+            self.lowest = Some(self.lowest.map_or(snr, |lowest| lowest.min(snr)));
+            self.highest = Some(self.highest.map_or(snr, |highest| highest.max(snr)));
+        }
+        if let Some(noise) = noise {
+            self.noise_frames += 1;
+            self.noise_total += noise as i64;
+            self.noise = Some(noise);
+        }
+    }
+    /// The mean signal to noise ratio in dB
+    pub fn mean(&self) -> Option<i16> {
+        if self.frames == 0 {
+            return None
+        }
+        Some((self.snr_total as f64 / self.frames as f64).round() as i16)
+    }
+    /// The mean noise floor in dBm
+    pub fn noise_floor(&self) -> Option<i8> {
+        if self.noise_frames == 0 {
+            return None
+        }
+        Some((self.noise_total as f64 / self.noise_frames as f64).round() as i8)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.frames == 0 && self.noise_frames == 0
+    }
+}
+/// Such as "SNR 24dB (12 to 40dB), noise floor -92dBm"
+impl fmt::Display for SignalQuality {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let (Some(mean), Some(lowest), Some(highest)) = (self.mean(), self.lowest, self.highest) {
+            write!(f, "SNR {}dB ({} to {}dB)", mean, lowest, highest)?;
+            if self.noise_frames > 0 {
+                write!(f, ", ")?;
+            }
+        }
+        match self.noise_floor() {
+            Some(noise) => write!(f, "noise floor {}dBm", noise),
+            None => Ok(())
+        }
+    }
+}
+
+/// How much each frame's signal counts towards the rolling average, so it follows a device that moves within a few dozen frames
+const SIGNAL_WEIGHT: f32 = 1.0 / 8.0;
+
+/// The signal a device was heard with, by any sensor, over every frame reporting one
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SignalStrength {
+    /// The most recent signal in dBm
+    pub last: Option<i8>,
+    pub lowest: Option<i8>,
+    pub highest: Option<i8>,
+    /// A rolling average, weighted towards recent frames
+    average: Option<f32>
+}
+impl SignalStrength {
+    pub fn heard(&mut self, signal: i8) {
+        self.last = Some(signal);
+        self.lowest = Some(self.lowest.map_or(signal, |lowest| lowest.min(signal)));
+        self.highest = Some(self.highest.map_or(signal, |highest| highest.max(signal)));
+        self.average = Some(self.average.map_or(signal as f32, |average| average + (signal as f32 - average) * SIGNAL_WEIGHT));
+    }
+    /// The rolling average in dBm
+    pub fn average(&self) -> Option<i8> {
+        self.average.map(|average| average.round() as i8)
+    }
+}
+/// Such as "-54dBm, averaging -58dBm (-71 to -49dBm)"
+impl fmt::Display for SignalStrength {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.last, self.average(), self.lowest, self.highest) {
+            (Some(last), Some(average), Some(lowest), Some(highest)) => write!(f, "{}dBm, averaging {}dBm ({} to {}dBm)", last, average, lowest, highest),
+            _ => write!(f, "no signal reported")
+        }
+    }
+}
+
+/// Counters libpcap keeps for a capture
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Drops {
+    pub received: u32,
+    /// Dropped because the capture couldn't keep up
+    pub dropped: u32,
+    /// Dropped by the interface or its driver
+    pub interface: u32
+}
+
+/// The stages each frame is handled in, timed with `--profile`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Waiting on and reading frames from the capture
+    Capture,
+    Radiotap,
+    /// Reading the 802.11 headers and body
+    Parse,
+    /// Updating devices, pages and everything else a frame feeds
+    Update,
+    /// Drawing the interface
+    Render
+}
+impl Stage {
+    pub const ALL: [Stage; 5] = [Stage::Capture, Stage::Radiotap, Stage::Parse, Stage::Update, Stage::Render];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Capture => "Capture",
+            Stage::Radiotap => "Radiotap parse",
+            Stage::Parse => "802.11 parse",
+            Stage::Update => "Device update",
+            Stage::Render => "Render"
+        }
+    }
+}
+
+/// Time spent in each stage, in the order of `Stage::ALL`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Profile {
+    /// The last whole second
+    pub last: [Duration; 5],
+    /// The second under way
+    current: [Duration; 5],
+    second: u64
+}
+
+/// Running totals over every frame captured
+pub struct Stats {
+    started: Instant,
+    /// Frames of each type and subtype, by its name
+    pub subtypes: BTreeMap<(Kind, u8), (&'static str, u64)>,
+    pub frames: u64,
+    pub bytes: u64,
+    /// Frames too short or broken to read the headers of
+    pub malformed: u64,
+    /// Frames whose headers were read but whose body couldn't be, by why
+    pub unreadable: BTreeMap<String, u64>,
+    /// Data frames counted but otherwise skipped while sampling at high frame rates
+    pub sampled_out: u64,
+    /// Frames and bytes heard in each second, the last being the second under way
+    pub per_second: VecDeque<(u64, u64)>,
+    /// Seconds since the capture started, of the second under way
+    second: u64,
+    /// libpcap's counters for each sensor captured from in this process
+    pub drops: BTreeMap<String, Drops>,
+    /// Time spent in each stage, when profiling
+    pub profile: Option<Profile>,
+    /// Signal to noise ratios and noise floors heard on each channel
+    pub channels: BTreeMap<u32, SignalQuality>
+}
+impl Stats {
+    pub fn new(started: Instant) -> Self {
+        Self {
+            started,
+            subtypes: BTreeMap::new(),
+            frames: 0,
+            bytes: 0,
+            malformed: 0,
+            unreadable: BTreeMap::new(),
+            sampled_out: 0,
+            per_second: VecDeque::from(vec![(0, 0)]),
+            second: 0,
+            drops: BTreeMap::new(),
+            profile: None,
+            channels: BTreeMap::new()
+        }
+    }
+    /// Start timing each stage frames are handled in
+    pub fn profile(&mut self) {
+        self.profile = Some(Profile { second: self.started.elapsed().as_secs(), ..Profile::default() })
+    }
+    /// Count time spent in a stage towards the second under way, if profiling
+    pub fn time(&mut self, stage: Stage, spent: Duration) {
+        let second = self.started.elapsed().as_secs();
+        if let Some(profile) = &mut self.profile {
+            if second > profile.second {
+                // A second with nothing timed in it leaves nothing to show
+                profile.last = if second == profile.second + 1 { profile.current } else { Default::default() };
+                profile.current = Default::default();
+                profile.second = second;
+            }
+            profile.current[stage as usize] += spent;
+        }
+    }
+    /// Count a frame heard at the given time since the capture started
+    pub fn frame(&mut self, time: Duration, summary: &Summary) {
+        self.subtypes.entry((summary.kind, summary.subtype)).or_insert((summary.subtype_name(), 0)).1 += 1;
+        self.frames += 1;
+        self.bytes += summary.length as u64;
+        let second = self.second(time);
+        second.0 += 1;
+        second.1 += summary.length as u64;
+    }
+    /// Count the signal to noise ratio and noise floor of a frame towards the channel it was heard on
+    pub fn signal_quality(&mut self, frequency: Option<u32>, snr: Option<i16>, noise: Option<i8>) {
+        if snr.is_none() && noise.is_none() {
+            return
+        }
+        if let Some(channel) = frequency.and_then(nl80211::frequency_to_channel) {
+            self.channels.entry(channel).or_default().heard(snr, noise);
+        }
+    }
+    pub fn malformed(&mut self) {
+        self.malformed += 1
+    }
+    pub fn unreadable(&mut self, error: &wifi::Error) {
+        *self.unreadable.entry(error.to_string()).or_default() += 1
+    }
+    /// Close off seconds with nothing heard, so quiet periods show in the graphs
+    pub fn advance(&mut self) {
+        let now = self.started.elapsed();
+        self.second(now);
+    }
+    fn second(&mut self, time: Duration) -> &mut (u64, u64) {
+        // Frames held while paused arrive late, and are counted in the second under way
+        while self.second < time.as_secs() {
+            self.per_second.push_back((0, 0));
+            if self.per_second.len() > HISTORY {
+                self.per_second.pop_front();
+            }
+            self.second += 1;
+        }
+        self.per_second.back_mut().unwrap(/* Never empty */)
+    }
+    /// Frames heard in the last whole second
+    pub fn last_second(&self) -> u64 {
+        let complete = self.per_second.len().saturating_sub(1);
+        self.per_second.get(complete.wrapping_sub(1)).map_or(0, |&(frames, _)| frames)
+    }
+    /// Frames and bytes per second over the last whole seconds, oldest first
+    pub fn throughput(&self, seconds: usize) -> (Vec<u64>, Vec<u64>) {
+        let complete = self.per_second.len().saturating_sub(1);
+        self.per_second.iter()
+            .take(complete)
+            .skip(complete.saturating_sub(seconds))
+            .copied()
+            .unzip()
+    }
+    pub fn reset(&mut self) {
+        let drops = std::mem::take(&mut self.drops);
+        let profiling = self.profile.is_some();
+        let second = self.started.elapsed().as_secs();
+        *self = Self::new(self.started);
+        self.second = second;
+        // libpcap's counters run for the life of the capture, so they are kept
+        self.drops = drops;
+        if profiling {
+            self.profile();
+        }
+    }
+}
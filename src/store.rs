@@ -0,0 +1,586 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt,
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Seek, SeekFrom, Write},
+    path::Path
+};
+use eui48::MacAddress;
+
+use crate::{KnownDevice, DeviceList, Role, clusters, export::{self, Format, Json}, nl80211, survey::Survey};
+
+/// Every field of a record, in the order they are written, where `sightings` covers the sensor, channel, band and frequency,
+/// and `location` where the device was heard loudest
+pub const FIELDS: [&str; 14] = ["address", "device", "manufacturer", "role", "ssid", "bssid", "probes", "tags", "frames", "first_seen", "last_seen", "signal", "location", "sightings"];
+
+/// The fields of a record written to some sink, or all of them
+#[derive(Debug, Clone, Default)]
+pub struct Fields(Option<BTreeSet<&'static str>>);
+impl Fields {
+    /// Parse a comma separated list of field names
+    pub fn parse(list: &str) -> Result<Self, Error> {
+        let mut fields = BTreeSet::new();
+        for name in list.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            fields.insert(*FIELDS.iter().find(|&&field| field == name).ok_or_else(|| Error::UnknownField(name.to_string()))?);
+        }
+        Ok(Self(Some(fields)))
+    }
+    pub fn includes(&self, field: &str) -> bool {
+        self.0.as_ref().map_or(true, |fields| fields.contains(field))
+    }
+    /// A copy of a record with the fields left out emptied, for sinks that can't leave them out altogether
+    /// The address is kept regardless, as records are told apart by it
+    pub fn redact(&self, record: &Record) -> Record {
+        let mut record = record.clone();
+        if !self.includes("device") { record.device = None }
+        if !self.includes("manufacturer") { record.manufacturer = None }
+        if !self.includes("role") { record.role = None }
+        if !self.includes("ssid") { record.ssid = None }
+        if !self.includes("bssid") { record.bssid = None }
+        if !self.includes("probes") { record.probes.clear() }
+        if !self.includes("tags") { record.tags.clear() }
+        if !self.includes("frames") { record.frames = 0 }
+        if !self.includes("first_seen") { record.first_seen = None }
+        if !self.includes("last_seen") { record.last_seen = None }
+        if !self.includes("signal") { record.signal = None }
+        if !self.includes("location") { record.location = None }
+        if !self.includes("sightings") { record.sightings.clear() }
+        record
+    }
+}
+
+/// The fields written by each kind of sink, from the `[fields]` section of the config file,
+/// so a CSV for sharing can leave out what the JSON and store keep
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    pub csv: Fields,
+    pub json: Fields,
+    pub store: Fields
+}
+impl Selection {
+    pub fn set(&mut self, sink: &str, list: &str) -> Result<(), Error> {
+        let fields = Fields::parse(list)?;
+        match sink {
+            "csv" => self.csv = fields,
+            "json" => self.json = fields,
+            "store" => self.store = fields,
+            _ => return Err(Error::UnknownSink(sink.to_string()))
+        }
+        Ok(())
+    }
+    pub fn format(&self, format: Format) -> &Fields {
+        match format {
+            Format::Csv => &self.csv,
+            Format::Json => &self.json
+        }
+    }
+}
+
+/// A device as stored and exported, with times in seconds since the Unix epoch
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub address: String,
+    /// The first address of the probable device this one belongs to, when it is grouped with others
+    pub device: Option<String>,
+    pub manufacturer: Option<String>,
+    /// `ap` or `client`
+    pub role: Option<String>,
+    pub ssid: Option<String>,
+    /// The access point a client is associated with
+    pub bssid: Option<String>,
+    pub probes: Vec<String>,
+    /// Labels given by the operator, in order
+    pub tags: Vec<String>,
+    pub frames: u64,
+    pub first_seen: Option<u64>,
+    pub last_seen: Option<u64>,
+    pub signal: Option<i8>,
+    /// Where the device was heard loudest as the sensor moved with --gps
+    pub location: Option<Location>,
+    /// How each sensor heard the device, in order of sensor name
+    pub sightings: Vec<SightingRecord>
+}
+impl Record {
+    pub fn of(address: &MacAddress, device: &KnownDevice, survey: &Survey) -> Self {
+        let unix = |time: Option<std::time::Duration>| time.map(|time| survey.started_unix() + time.as_secs());
+        let anonymizer = &survey.anonymizer;
+        let mut probes: Vec<_> = device.probes.keys().map(|ssid| anonymizer.ssid(ssid).into_owned()).collect();
+        probes.sort();
+        Self {
+            address: anonymizer.address_string(address),
+            device: None,
+            manufacturer: device.manufacturer.as_ref().map(|entry| entry.name_short.clone()),
+            role: device.role.map(|role| match role {
+                Role::AccessPoint => "ap",
+                Role::Station => "client"
+            }.to_string()),
+            ssid: device.beacon.as_deref().map(|ssid| anonymizer.ssid(ssid).into_owned()),
+            bssid: device.associated.map(|bssid| anonymizer.address_string(&bssid)),
+            probes,
+            tags: device.tags.iter().cloned().collect(),
+            frames: device.sensors.values().map(|sighting| sighting.frames).sum(),
+            first_seen: unix(device.first_seen),
+            last_seen: unix(device.last_seen),
+            signal: device.strongest_sensor().and_then(|(_, sighting)| sighting.signal),
+            location: device.geo.strongest.map(|point| Location {
+                latitude: point.fix.latitude,
+                longitude: point.fix.longitude,
+                signal: point.signal,
+                time: survey.started_unix() + point.time.as_secs()
+            }),
+            sightings: {
+                let mut sightings: Vec<_> = device.sensors.iter()
+                    .map(|(sensor, sighting)| SightingRecord {
+                        sensor: sensor.clone(),
+                        frames: sighting.frames,
+                        signal: sighting.signal,
+                        frequency: sighting.frequency
+                    })
+                    .collect();
+                sightings.sort_by(|l, r| l.sensor.cmp(&r.sensor));
+                sightings
+            }
+        }
+    }
+    /// A record for every device tracked in order of address, naming the probable device of those grouped with others
+    pub fn all(devices: &DeviceList, survey: &Survey) -> Vec<Self> {
+        let probable = clusters::probable_devices(devices);
+        let device_of: HashMap<_, _> = probable.iter()
+            .flat_map(|cluster| cluster.iter().map(move |address| (address, cluster[0])))
+            .collect();
+        let mut addresses: Vec<_> = devices.keys().collect();
+        addresses.sort();
+        addresses.into_iter()
+            .map(|address| Self {
+                device: device_of.get(address).map(|device| survey.anonymizer.address_string(device)),
+                ..Self::of(address, &devices[address], survey)
+            })
+            .collect()
+    }
+    /// The record as a JSON object of the given fields
+    pub fn json(&self, fields: &Fields) -> String {
+        let null = || "null".to_string();
+        let string = |value: &str| export::json_string(value);
+        export::json_object(vec![
+            ("address", export::json_string(&self.address)),
+            ("device", self.device.as_deref().map_or_else(null, string)),
+            ("manufacturer", self.manufacturer.as_deref().map_or_else(null, string)),
+            ("role", self.role.as_deref().map_or_else(null, string)),
+            ("ssid", self.ssid.as_deref().map_or_else(null, string)),
+            ("bssid", self.bssid.as_deref().map_or_else(null, string)),
+            ("probes", format!("[{}]", self.probes.iter().map(|ssid| export::json_string(ssid)).collect::<Vec<_>>().join(","))),
+            ("tags", format!("[{}]", self.tags.iter().map(|tag| export::json_string(tag)).collect::<Vec<_>>().join(","))),
+            ("frames", self.frames.to_string()),
+            ("first_seen", self.first_seen.map_or_else(null, |time| time.to_string())),
+            ("last_seen", self.last_seen.map_or_else(null, |time| time.to_string())),
+            ("signal", self.signal.map_or_else(null, |signal| signal.to_string())),
+            ("location", self.location.as_ref().map_or_else(null, Location::json)),
+            ("sightings", format!("[{}]", self.sightings.iter().map(SightingRecord::json).collect::<Vec<_>>().join(",")))
+        ].into_iter().filter(|(field, _)| fields.includes(field)))
+    }
+    /// Read back a record written by `json`, where fields left out are taken as empty
+    pub fn from_json(json: &Json) -> Option<Self> {
+        let string = |key| json.get(key).and_then(Json::as_str).map(String::from);
+        let number = |key| json.get(key).and_then(Json::as_f64);
+        Some(Self {
+            address: string("address")?,
+            device: string("device"),
+            manufacturer: string("manufacturer"),
+            role: string("role"),
+            ssid: string("ssid"),
+            bssid: string("bssid"),
+            probes: match json.get("probes") {
+                Some(Json::Array(probes)) => probes.iter().filter_map(Json::as_str).map(String::from).collect(),
+                Some(_) => return None,
+                None => vec![]
+            },
+            tags: match json.get("tags") {
+                Some(Json::Array(tags)) => tags.iter().filter_map(Json::as_str).map(String::from).collect(),
+                Some(_) => return None,
+                None => vec![]
+            },
+            frames: number("frames").unwrap_or_default() as u64,
+            first_seen: number("first_seen").map(|time| time as u64),
+            last_seen: number("last_seen").map(|time| time as u64),
+            signal: number("signal").map(|signal| signal as i8),
+            location: match json.get("location") {
+                Some(Json::Null) | None => None,
+                Some(location) => Some(Location::from_json(location)?)
+            },
+            // Missing from records stored before sightings were
+            sightings: match json.get("sightings") {
+                Some(Json::Array(sightings)) => sightings.iter().map(SightingRecord::from_json).collect::<Option<_>>()?,
+                Some(_) => return None,
+                None => vec![]
+            }
+        })
+    }
+}
+
+/// Where a device was heard loudest, with how loud and when, in seconds since the Unix epoch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Location {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub signal: Option<i8>,
+    pub time: u64
+}
+impl Location {
+    pub fn json(&self) -> String {
+        export::json_object(vec![
+            ("latitude", self.latitude.to_string()),
+            ("longitude", self.longitude.to_string()),
+            ("signal", self.signal.map_or_else(|| "null".to_string(), |signal| signal.to_string())),
+            ("time", self.time.to_string())
+        ])
+    }
+    pub fn from_json(json: &Json) -> Option<Self> {
+        let number = |key| json.get(key).and_then(Json::as_f64);
+        Some(Self {
+            latitude: number("latitude")?,
+            longitude: number("longitude")?,
+            signal: number("signal").map(|signal| signal as i8),
+            time: number("time")? as u64
+        })
+    }
+}
+
+/// How one sensor heard a device, along with the channel it was last heard on
+#[derive(Debug, Clone, PartialEq)]
+pub struct SightingRecord {
+    pub sensor: String,
+    pub frames: u64,
+    pub signal: Option<i8>,
+    /// In MHz
+    pub frequency: Option<u32>
+}
+impl SightingRecord {
+    pub fn channel(&self) -> Option<u32> {
+        self.frequency.and_then(nl80211::frequency_to_channel)
+    }
+    pub fn band(&self) -> Option<String> {
+        self.frequency.and_then(nl80211::frequency_to_band).map(|band| band.to_string())
+    }
+    pub fn json(&self) -> String {
+        let null = || "null".to_string();
+        export::json_object(vec![
+            ("sensor", export::json_string(&self.sensor)),
+            ("frames", self.frames.to_string()),
+            ("signal", self.signal.map_or_else(null, |signal| signal.to_string())),
+            ("channel", self.channel().map_or_else(null, |channel| channel.to_string())),
+            ("band", self.band().map_or_else(null, |band| export::json_string(&band))),
+            ("frequency", self.frequency.map_or_else(null, |frequency| frequency.to_string()))
+        ])
+    }
+    /// Read back a sighting written by `json`, where the channel and band follow from the frequency
+    pub fn from_json(json: &Json) -> Option<Self> {
+        let number = |key| json.get(key).and_then(Json::as_f64);
+        Some(Self {
+            sensor: json.get("sensor")?.as_str()?.to_string(),
+            frames: number("frames")? as u64,
+            signal: number("signal").map(|signal| signal as i8),
+            frequency: number("frequency").map(|frequency| frequency as u32)
+        })
+    }
+}
+
+/// Somewhere devices are kept, so the interface, headless sensors and later analysis can each pick what suits them
+pub trait Store {
+    /// Add a record, replacing any kept for the same address
+    fn put(&mut self, record: &Record) -> Result<(), Error>;
+    fn put_all(&mut self, records: &[Record]) -> Result<(), Error> {
+        records.iter().try_for_each(|record| self.put(record))
+    }
+    /// The latest record kept for every address, in order of address
+    fn all(&mut self) -> Result<Vec<Record>, Error>;
+}
+
+/// Open a store from a specification of `memory`, `jsonl:PATH` or `sqlite:PATH`, keeping only the given fields
+/// Files are opened straight away, so this should be done before the sandbox is applied
+pub fn open(spec: &str, fields: &Fields) -> Result<Box<dyn Store>, Error> {
+    match spec.split_once(':') {
+        _ if spec == "memory" => Ok(Box::new(Memory::default())),
+        Some(("jsonl", path)) => Ok(Box::new(Jsonl::open(path, fields.clone())?)),
+        #[cfg(feature = "sqlite")]
+        Some(("sqlite", path)) => Ok(Box::new(Sqlite::open(path)?)),
+        _ => Err(Error::Unsupported(spec.to_string()))
+    }
+}
+
+/// Kept only for as long as the capture runs
+#[derive(Default)]
+pub struct Memory(BTreeMap<String, Record>);
+impl Store for Memory {
+    fn put(&mut self, record: &Record) -> Result<(), Error> {
+        self.0.insert(record.address.clone(), record.clone());
+        Ok(())
+    }
+    fn all(&mut self) -> Result<Vec<Record>, Error> {
+        Ok(self.0.values().cloned().collect())
+    }
+}
+
+/// Newline delimited JSON only ever appended to, so every change to a device is kept and the file can be followed as it grows
+pub struct Jsonl(File, Fields);
+impl Jsonl {
+    pub fn open(path: impl AsRef<Path>, fields: Fields) -> Result<Self, Error> {
+        Ok(Self(OpenOptions::new().read(true).append(true).create(true).open(path)?, fields))
+    }
+}
+impl Store for Jsonl {
+    fn put(&mut self, record: &Record) -> Result<(), Error> {
+        // Written whole so a reader following the file never sees part of a line
+        self.0.write_all(format!("{}\n", record.json(&self.1)).as_bytes())?;
+        Ok(())
+    }
+    fn all(&mut self) -> Result<Vec<Record>, Error> {
+        // Appends always go to the end, so reading from the start doesn't disturb them
+        self.0.seek(SeekFrom::Start(0))?;
+        let mut records = BTreeMap::new();
+        for (index, line) in BufReader::new(&self.0).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue
+            }
+            let record = Json::parse(&line).as_ref().and_then(Record::from_json).ok_or(Error::Malformed(index + 1))?;
+            records.insert(record.address.clone(), record);
+        }
+        Ok(records.into_iter().map(|(_, record)| record).collect())
+    }
+}
+
+/// An SQLite database with a row for every device, for querying once the capture is over
+#[cfg(feature = "sqlite")]
+pub struct Sqlite(rusqlite::Connection);
+#[cfg(feature = "sqlite")]
+impl Sqlite {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let connection = rusqlite::Connection::open(path)?;
+        // A journal file couldn't be created once the sandbox is applied
+        connection.execute_batch("
+            PRAGMA journal_mode = MEMORY;
+            CREATE TABLE IF NOT EXISTS devices (
+                address TEXT PRIMARY KEY,
+                manufacturer TEXT,
+                role TEXT,
+                ssid TEXT,
+                probes TEXT NOT NULL,
+                frames INTEGER NOT NULL,
+                first_seen INTEGER,
+                last_seen INTEGER,
+                signal INTEGER,
+                device TEXT,
+                tags TEXT,
+                bssid TEXT,
+                latitude REAL,
+                longitude REAL,
+                location_signal INTEGER,
+                location_time INTEGER
+            );
+            CREATE TABLE IF NOT EXISTS sightings (
+                address TEXT NOT NULL,
+                sensor TEXT NOT NULL,
+                frames INTEGER NOT NULL,
+                signal INTEGER,
+                channel INTEGER,
+                band TEXT,
+                frequency INTEGER,
+                PRIMARY KEY (address, sensor)
+            );
+        ")?;
+        // Databases made by earlier versions lack the columns added since
+        let columns: Vec<String> = connection.prepare("SELECT name FROM pragma_table_info('devices')")?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        for column in ["device", "tags", "bssid"].iter().filter(|&&column| !columns.iter().any(|existing| existing == column)) {
+            connection.execute_batch(&format!("ALTER TABLE devices ADD COLUMN {} TEXT", column))?;
+        }
+        Ok(Self(connection))
+    }
+}
+#[cfg(feature = "sqlite")]
+impl Store for Sqlite {
+    fn put(&mut self, record: &Record) -> Result<(), Error> {
+        self.put_all(std::slice::from_ref(record))
+    }
+    fn put_all(&mut self, records: &[Record]) -> Result<(), Error> {
+        // One transaction for the lot, as each one waits for the disk
+        let transaction = self.0.transaction()?;
+        for record in records {
+            let list = |items: &[String]| format!("[{}]", items.iter().map(|item| export::json_string(item)).collect::<Vec<_>>().join(","));
+            transaction.execute(
+                "INSERT OR REPLACE INTO devices (
+                    address, manufacturer, role, ssid, probes, frames, first_seen, last_seen, signal, device, tags, bssid,
+                    latitude, longitude, location_signal, location_time
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+                rusqlite::params![
+                    record.address,
+                    record.manufacturer,
+                    record.role,
+                    record.ssid,
+                    list(&record.probes),
+                    record.frames as i64,
+                    record.first_seen.map(|time| time as i64),
+                    record.last_seen.map(|time| time as i64),
+                    record.signal,
+                    record.device,
+                    list(&record.tags),
+                    record.bssid,
+                    record.location.map(|location| location.latitude),
+                    record.location.map(|location| location.longitude),
+                    record.location.and_then(|location| location.signal),
+                    record.location.map(|location| location.time as i64)
+                ]
+            )?;
+            transaction.execute("DELETE FROM sightings WHERE address = ?1", rusqlite::params![record.address])?;
+            for sighting in record.sightings.iter() {
+                transaction.execute(
+                    "INSERT INTO sightings VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    rusqlite::params![
+                        record.address,
+                        sighting.sensor,
+                        sighting.frames as i64,
+                        sighting.signal,
+                        sighting.channel().map(i64::from),
+                        sighting.band(),
+                        sighting.frequency.map(i64::from)
+                    ]
+                )?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+    fn all(&mut self) -> Result<Vec<Record>, Error> {
+        let mut statement = self.0.prepare("
+            SELECT address, manufacturer, role, ssid, probes, frames, first_seen, last_seen, signal, device, tags, bssid,
+                latitude, longitude, location_signal, location_time
+            FROM devices ORDER BY address
+        ")?;
+        let rows = statement.query_map([], |row| Ok((
+            Record {
+                address: row.get(0)?,
+                device: row.get(9)?,
+                manufacturer: row.get(1)?,
+                role: row.get(2)?,
+                ssid: row.get(3)?,
+                bssid: row.get(11)?,
+                probes: vec![],
+                tags: vec![],
+                frames: row.get::<_, i64>(5)? as u64,
+                first_seen: row.get::<_, Option<i64>>(6)?.map(|time| time as u64),
+                last_seen: row.get::<_, Option<i64>>(7)?.map(|time| time as u64),
+                signal: row.get(8)?,
+                location: match (row.get::<_, Option<f64>>(12)?, row.get::<_, Option<f64>>(13)?, row.get::<_, Option<i64>>(15)?) {
+                    (Some(latitude), Some(longitude), Some(time)) => Some(Location {
+                        latitude,
+                        longitude,
+                        signal: row.get(14)?,
+                        time: time as u64
+                    }),
+                    _ => None
+                },
+                sightings: vec![]
+            },
+            row.get::<_, String>(4)?,
+            // Empty in rows stored before tags were
+            row.get::<_, Option<String>>(10)?.unwrap_or_else(|| "[]".to_string())
+        )))?;
+        let mut records = vec![];
+        let list = |json: &str| match Json::parse(json) {
+            Some(Json::Array(items)) => Some(items.iter().filter_map(Json::as_str).map(String::from).collect()),
+            _ => None
+        };
+        for (index, row) in rows.enumerate() {
+            let (mut record, probes, tags) = row?;
+            record.probes = list(&probes).ok_or(Error::Malformed(index + 1))?;
+            record.tags = list(&tags).ok_or(Error::Malformed(index + 1))?;
+            records.push(record);
+        }
+        let mut statement = self.0.prepare("SELECT address, sensor, frames, signal, frequency FROM sightings ORDER BY address, sensor")?;
+        let sightings = statement.query_map([], |row| Ok((
+            row.get::<_, String>(0)?,
+            SightingRecord {
+                sensor: row.get(1)?,
+                frames: row.get::<_, i64>(2)? as u64,
+                signal: row.get(3)?,
+                frequency: row.get::<_, Option<i64>>(4)?.map(|frequency| frequency as u32)
+            }
+        )))?;
+        for sighting in sightings {
+            let (address, sighting) = sighting?;
+            if let Some(record) = records.iter_mut().find(|record| record.address == address) {
+                record.sightings.push(sighting);
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// Keeps a store in step with the device list, writing only the devices that changed since they were last written
+pub struct Mirror {
+    store: Box<dyn Store>,
+    /// Applied before comparing, so a change only to fields left out isn't written
+    fields: Fields,
+    written: HashMap<String, Record>
+}
+impl Mirror {
+    pub fn new(mut store: Box<dyn Store>, fields: Fields) -> Result<Self, Error> {
+        let written = store.all()?.into_iter().map(|record| (record.address.clone(), record)).collect();
+        Ok(Self {
+            store,
+            fields,
+            written
+        })
+    }
+    pub fn sync(&mut self, devices: &DeviceList, survey: &Survey) -> Result<(), Error> {
+        let changed: Vec<_> = Record::all(devices, survey).iter()
+            .map(|record| self.fields.redact(record))
+            .filter(|record| self.written.get(&record.address) != Some(record))
+            .collect();
+        if changed.is_empty() {
+            return Ok(())
+        }
+        self.store.put_all(&changed)?;
+        self.written.extend(changed.into_iter().map(|record| (record.address.clone(), record)));
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// A stored record that couldn't be read, by line or row number
+    Malformed(usize),
+    #[cfg(feature = "sqlite")]
+    Sqlite(rusqlite::Error),
+    Unsupported(String),
+    /// A field or sink named in the `[fields]` section that doesn't exist
+    UnknownField(String),
+    UnknownSink(String)
+}
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for Error {
+    fn from(error: rusqlite::Error) -> Self {
+        Self::Sqlite(error)
+    }
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{}", error),
+            Self::Malformed(line) => write!(f, "stored record {} is unreadable", line),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(error) => write!(f, "{}", error),
+            Self::Unsupported(spec) if spec.starts_with("sqlite:") => write!(f, "SQLite support was not built in, rebuild with --features sqlite"),
+            Self::Unsupported(spec) => write!(f, "unknown store {:?}, expected memory, jsonl:PATH or sqlite:PATH", spec),
+            Self::UnknownField(field) => write!(f, "unknown field {:?}, expected one of {}", field, FIELDS.join(", ")),
+            Self::UnknownSink(sink) => write!(f, "unknown sink {:?}, expected csv, json or store", sink)
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use std::{collections::HashMap, time::Duration};
+use eui48::MacAddress;
+
+use crate::twins::Fingerprint;
+
+/// How long an access point's unchanged beacons are only counted before one is taken in whole again,
+/// so what changes without changing the fingerprint, such as the load it reports, stays current
+const REFRESH: Duration = Duration::from_secs(10);
+
+/// Keeps the noise of busy venues out of the device list, so memory and the interface go on nearby devices
+#[derive(Debug, Default)]
+pub struct Suppression {
+    /// Devices not yet tracked are only added once heard at or above this signal in dBm
+    pub min_signal: Option<i8>,
+    /// Beacons unchanged since the access point's last are counted rather than taken in whole
+    pub collapse_beacons: bool,
+    /// Devices are only added once heard sending, rather than from acknowledgements inferred to be theirs
+    pub ignore_acked: bool,
+    /// When each access point's beacons were last taken in whole
+    refreshed: HashMap<MacAddress, Duration>,
+    /// Frames from untracked devices left out for being too weak
+    pub weak: u64,
+    /// Beacons only counted for being the same as the last
+    pub collapsed: u64,
+    /// Acknowledgements left out for being all that was heard of a device not yet tracked
+    pub acked: u64
+}
+impl Suppression {
+    pub fn is_enabled(&self) -> bool {
+        self.min_signal.is_some() || self.collapse_beacons || self.ignore_acked
+    }
+    /// True if a frame from a device not yet tracked is too weak to add it
+    pub fn too_weak(&mut self, signal: Option<i8>) -> bool {
+        let weak = matches!((self.min_signal, signal), (Some(min), Some(signal)) if signal < min);
+        self.weak += weak as u64;
+        weak
+    }
+    /// True if a beacon need only be counted, being the same as the last taken in whole from its access point
+    pub fn collapse(&mut self, source: MacAddress, fingerprint: &Fingerprint, earlier: Option<&Fingerprint>, time: Duration) -> bool {
+        if !self.collapse_beacons {
+            return false
+        }
+        let fresh = self.refreshed.get(&source).map_or(false, |&refreshed| time < refreshed + REFRESH);
+        if fresh && earlier == Some(fingerprint) {
+            self.collapsed += 1;
+            return true
+        }
+        self.refreshed.insert(source, time);
+        false
+    }
+    /// Forget what was counted, keeping the settings
+    pub fn clear(&mut self) {
+        self.refreshed.clear();
+        self.weak = 0;
+        self.collapsed = 0;
+        self.acked = 0;
+    }
+}
@@ -0,0 +1,72 @@
+use std::{fmt, fs, io, path::Path, time::SystemTime};
+
+use crate::anonymize::Anonymizer;
+
+/// Details describing a capture session, carried into everything it produces
+#[derive(Debug, Clone)]
+pub struct Survey {
+    /// The name of the survey or site this session belongs to
+    pub name: Option<String>,
+    /// Who ran the capture
+    pub operator: Option<String>,
+    /// A free-form description of where the capture took place
+    pub location: Option<String>,
+    pub started: SystemTime,
+    /// What addresses and network names are replaced with in everything written, if anything
+    pub anonymizer: Anonymizer
+}
+impl Survey {
+    pub fn new(name: Option<&str>, operator: Option<&str>, location: Option<&str>) -> Self {
+        Self {
+            name: name.map(String::from),
+            operator: operator.map(String::from),
+            location: location.map(String::from),
+            started: SystemTime::now(),
+            anonymizer: Anonymizer::new(false)
+        }
+    }
+    /// Seconds since the Unix epoch at which the session started
+    pub fn started_unix(&self) -> u64 {
+        self.started.duration_since(SystemTime::UNIX_EPOCH).map(|since| since.as_secs()).unwrap_or(0)
+    }
+    /// The metadata as key-value pairs, skipping anything not given
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = vec![];
+        if let Some(name) = &self.name {
+            fields.push(("survey", name.clone()));
+        }
+        if let Some(operator) = &self.operator {
+            fields.push(("operator", operator.clone()));
+        }
+        if let Some(location) = &self.location {
+            fields.push(("location", location.clone()));
+        }
+        fields.push(("started", self.started_unix().to_string()));
+        fields
+    }
+    /// Whether any details were given for this session
+    pub fn is_named(&self) -> bool {
+        self.name.is_some() || self.operator.is_some() || self.location.is_some()
+    }
+    /// Write the metadata as `key=value` lines, for outputs which cannot carry it themselves
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let lines: Vec<_> = self.fields().into_iter()
+            .map(|(key, value)| format!("{}={}\n", key, value.replace('\n', " ")))
+            .collect();
+        fs::write(path, lines.concat())
+    }
+}
+impl fmt::Display for Survey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.name, &self.location) {
+            (Some(name), Some(location)) => write!(f, "{} @ {}", name, location),
+            (Some(name), None) => write!(f, "{}", name),
+            (None, Some(location)) => write!(f, "{}", location),
+            (None, None) => write!(f, "Unnamed survey")
+        }?;
+        if let Some(operator) = &self.operator {
+            write!(f, " ({})", operator)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+use std::{collections::hash_map::DefaultHasher, hash::{Hash, Hasher}};
+
+use crate::wifi::Tag;
+
+/// Capability bits an access point flips on its own as older clients come and go, ie. short preamble and short slot time
+const VOLATILE_CAPABILITIES: u16 = 1 << 5 | 1 << 10;
+
+/// What an access point's beacons say about it, which stays the same for as long as it runs unchanged,
+/// so a change points to it being restarted with a new configuration or impersonated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fingerprint {
+    pub ssid: String,
+    /// The channel the beacon names, rather than the one it was heard on, which on 2.4GHz can be a neighbour
+    pub channel: Option<u8>,
+    pub capabilities: u16,
+    pub interval: u16,
+    /// A hash of the elements, leaving out those whose contents change from beacon to beacon
+    pub elements: u64
+}
+impl Fingerprint {
+    pub fn from_beacon(ssid: &str, capabilities: u16, interval: u16, tags: &[Tag]) -> Self {
+        let mut hasher = DefaultHasher::new();
+        for tag in tags {
+            match tag {
+                Tag::BssLoad { .. } | Tag::TpcReport { .. } | Tag::ChannelSwitch { .. } => (),
+                tag => format!("{:?}", tag).hash(&mut hasher)
+            }
+        }
+        Self {
+            ssid: ssid.to_string(),
+            channel: tags.iter().find_map(|tag| match *tag {
+                Tag::DsParameter { channel } => Some(channel),
+                _ => None
+            }),
+            capabilities: capabilities & !VOLATILE_CAPABILITIES,
+            interval,
+            elements: hasher.finish()
+        }
+    }
+    /// What differs from an earlier fingerprint of the same access point, leaving out the channel if it announced the move
+    pub fn changes(&self, earlier: &Self, announced: Option<u32>) -> Vec<String> {
+        let mut changes = vec![];
+        if self.ssid != earlier.ssid {
+            changes.push(format!("SSID {:?} to {:?}", earlier.ssid, self.ssid));
+        }
+        if self.channel != earlier.channel && self.channel.map(u32::from) != announced {
+            let channel = |channel: Option<u8>| channel.map_or_else(|| "none".to_string(), |channel| channel.to_string());
+            changes.push(format!("channel {} to {}", channel(earlier.channel), channel(self.channel)));
+        }
+        if self.capabilities != earlier.capabilities {
+            changes.push(format!("capabilities {:#06x} to {:#06x}", earlier.capabilities, self.capabilities));
+        }
+        if self.interval != earlier.interval {
+            changes.push(format!("beacon interval {} to {} TU", earlier.interval, self.interval));
+        }
+        if self.elements != earlier.elements {
+            changes.push("information elements".to_string());
+        }
+        changes
+    }
+}
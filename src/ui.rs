@@ -1,21 +1,195 @@
-use std::{thread, sync::mpsc::{self, Receiver}, ops::{Deref, DerefMut}};
-use termion::{event::Key, input::{MouseTerminal, TermRead}, raw::{IntoRawMode, RawTerminal}, screen::AlternateScreen};
+use std::{fmt, io::Write, panic, thread, sync::{Mutex, mpsc::{self, Receiver}}, ops::{Deref, DerefMut}, time::{Duration, SystemTime}};
+use eui48::MacAddress;
+#[cfg(not(feature = "crossterm-backend"))]
+use termion::{input::{MouseTerminal, TermRead}, raw::{IntoRawMode, RawTerminal}, screen::AlternateScreen};
+#[cfg(not(feature = "crossterm-backend"))]
+use tui::backend::TermionBackend;
+#[cfg(feature = "crossterm-backend")]
+use crossterm::{event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers}, execute, terminal};
+#[cfg(feature = "crossterm-backend")]
+use tui::backend::CrosstermBackend;
 use tui::{
-    backend::TermionBackend,
-    layout::{Alignment, Constraint, Layout},
-    widgets::{Block, Borders, Paragraph},
+    buffer::Buffer,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    widgets::{Block, Borders, Clear, Paragraph, Widget},
     style::{Style, Modifier, Color},
     text::{Spans, Span}
 };
 
+/// Terminals narrower than this get compact layouts, leaving room for an 80 column SSH session
+pub const NARROW_WIDTH: u16 = 100;
+
+/// Whether an area is too narrow for the full layout
+pub fn narrow(area: Rect) -> bool {
+    area.width < NARROW_WIDTH
+}
+
+/// A byte count in powers of 1024, eg. 1.5M
+pub fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        bytes if bytes >= 1 << 30 => format!("{:.1}G", bytes as f64 / (1u64 << 30) as f64),
+        bytes if bytes >= 1 << 20 => format!("{:.1}M", bytes as f64 / (1u64 << 20) as f64),
+        bytes if bytes >= 1 << 10 => format!("{:.1}K", bytes as f64 / (1u64 << 10) as f64),
+        bytes => format!("{}B", bytes)
+    }
+}
+
+/// Draws colours as others over whatever has been drawn already, for the colours chosen in the config file
+pub struct Palette<'a>(pub &'a [(Color, Color)]);
+impl Widget for Palette<'_> {
+    fn render(self, area: Rect, buffer: &mut Buffer) {
+        if self.0.is_empty() {
+            return
+        }
+        let swap = |colour: &mut Color| if let Some((_, to)) = self.0.iter().find(|(from, _)| from == colour) {
+            *colour = *to
+        };
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                let cell = buffer.get_mut(x, y);
+                swap(&mut cell.fg);
+                swap(&mut cell.bg);
+            }
+        }
+    }
+}
+
+/// Lists what each key does in a box over whatever has been drawn already, in titled sections
+pub struct Help<'a>(pub Vec<(&'a str, Vec<(String, &'a str)>)>);
+impl Widget for Help<'_> {
+    fn render(self, area: Rect, buffer: &mut Buffer) {
+        let width = self.0.iter().flat_map(|(_, keys)| keys.iter().map(|(key, _)| key.chars().count())).max().unwrap_or(0);
+        let mut lines = vec![];
+        for (title, keys) in self.0.into_iter().filter(|(_, keys)| !keys.is_empty()) {
+            if !lines.is_empty() {
+                lines.push(Spans::from(""));
+            }
+            lines.push(Spans::from(Span::styled(title, Style::reset().add_modifier(Modifier::UNDERLINED))));
+            for (key, description) in keys {
+                lines.push(Spans::from(vec![
+                    Span::styled(format!("{:width$}  ", key, width = width), Style::reset().add_modifier(Modifier::BOLD)),
+                    Span::raw(description)
+                ]));
+            }
+        }
+        // Centred, and only as big as it needs to be
+        let box_width = (lines.iter().map(Spans::width).max().unwrap_or(0) as u16 + 2).min(area.width);
+        let box_height = (lines.len() as u16 + 2).min(area.height);
+        let area = Rect::new(area.x + (area.width - box_width) / 2, area.y + (area.height - box_height) / 2, box_width, box_height);
+        Clear.render(area, buffer);
+        Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title("Keys, any key to close"))
+            .style(Style::reset())
+            .render(area, buffer);
+    }
+}
+
+/// Divide an area into a list and a detail pane showing the selected item
+/// Side by side there isn't room for either on a narrow terminal, so the details go underneath
+pub fn split(area: Rect) -> (Rect, Rect) {
+    let areas = Layout::default()
+        .direction(if narrow(area) { Direction::Vertical } else { Direction::Horizontal })
+        .margin(0)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+    (areas[0], areas[1])
+}
+
+/// An error that ended the program, already shown on screen and printed again once the terminal is restored
+pub struct Fatal {
+    pub location: String,
+    pub message: String,
+    pub reason: String
+}
+impl fmt::Display for Fatal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Error @ {}: {}: {}", self.location, self.message, self.reason)
+    }
+}
+
+/// The message of the last panic, kept until the terminal is restored
+static PANIC_MESSAGE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Keep panic messages rather than printing them, as anything printed to the alternate screen is lost
+pub fn defer_panic_messages() {
+    panic::set_hook(Box::new(|info| {
+        if let Ok(mut message) = PANIC_MESSAGE.lock() {
+            *message = Some(info.to_string());
+        }
+    }))
+}
+/// The message of the last panic, once it is safe to print
+pub fn panic_message() -> Option<String> {
+    PANIC_MESSAGE.lock().ok()?.take()
+}
+
+/// Termion is used unless built with the `crossterm-backend` feature, for terminals termion doesn't support such as on Windows
+#[cfg(not(feature = "crossterm-backend"))]
+pub use termion::event::Key;
+#[cfg(not(feature = "crossterm-backend"))]
 pub type Backend = TermionBackend<AlternateScreen<MouseTerminal<RawTerminal<std::io::Stdout>>>>;
+#[cfg(feature = "crossterm-backend")]
+pub type Backend = CrosstermBackend<std::io::Stdout>;
 pub type Terminal = tui::Terminal<Backend>;
 
+/// A key press, the same as termion's so that either backend is handled alike
+#[cfg(feature = "crossterm-backend")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    Backspace,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    BackTab,
+    Delete,
+    Insert,
+    F(u8),
+    Char(char),
+    Alt(char),
+    Ctrl(char),
+    Null,
+    Esc
+}
+#[cfg(feature = "crossterm-backend")]
+impl Key {
+    fn from_event(event: event::KeyEvent) -> Self {
+        match (event.code, event.modifiers) {
+            (KeyCode::Char(c), modifiers) if modifiers.contains(KeyModifiers::CONTROL) => Self::Ctrl(c),
+            (KeyCode::Char(c), modifiers) if modifiers.contains(KeyModifiers::ALT) => Self::Alt(c),
+            (KeyCode::Char(c), _) => Self::Char(c),
+            (KeyCode::Enter, _) => Self::Char('\n'),
+            (KeyCode::Tab, _) => Self::Char('\t'),
+            (KeyCode::Backspace, _) => Self::Backspace,
+            (KeyCode::Left, _) => Self::Left,
+            (KeyCode::Right, _) => Self::Right,
+            (KeyCode::Up, _) => Self::Up,
+            (KeyCode::Down, _) => Self::Down,
+            (KeyCode::Home, _) => Self::Home,
+            (KeyCode::End, _) => Self::End,
+            (KeyCode::PageUp, _) => Self::PageUp,
+            (KeyCode::PageDown, _) => Self::PageDown,
+            (KeyCode::BackTab, _) => Self::BackTab,
+            (KeyCode::Delete, _) => Self::Delete,
+            (KeyCode::Insert, _) => Self::Insert,
+            (KeyCode::F(n), _) => Self::F(n),
+            (KeyCode::Esc, _) => Self::Esc,
+            (KeyCode::Null, _) => Self::Null
+        }
+    }
+}
+
 pub struct Ui {
     pub input: Input,
-    pub terminal: Terminal
+    /// None when running headless, where there is nothing to draw and problems go to stderr instead
+    terminal: Option<Terminal>
 }
 impl Ui {
+    #[cfg(not(feature = "crossterm-backend"))]
     pub fn new() -> Self {
         let backend = TermionBackend::new(
             AlternateScreen::from(
@@ -28,9 +202,46 @@ impl Ui {
         let input = Input::new();
         Self {
             input,
-            terminal
+            terminal: Some(terminal)
+        }
+    }
+    #[cfg(feature = "crossterm-backend")]
+    pub fn new() -> Self {
+        terminal::enable_raw_mode().expect("Unable to switch stdout to raw mode");
+        let mut stdout = std::io::stdout();
+        execute!(stdout, terminal::EnterAlternateScreen, EnableMouseCapture).expect("Unable to switch to the alternate screen");
+        let backend = CrosstermBackend::new(stdout);
+        let terminal = tui::Terminal::new(backend).expect("Unable to create TUI");
+        let input = Input::new();
+        Self {
+            input,
+            terminal: Some(terminal)
         }
     }
+    /// No terminal at all, leaving stdout free for events and taking no input
+    pub fn headless() -> Self {
+        Self {
+            input: Input::none(),
+            terminal: None
+        }
+    }
+    /// Draw a frame, unless headless
+    pub fn draw<F: FnOnce(&mut tui::terminal::Frame<Backend>)>(&mut self, draw: F) -> std::io::Result<()> {
+        match &mut self.terminal {
+            Some(terminal) => terminal.draw(draw),
+            None => Ok(())
+        }
+    }
+    /// Ring the terminal bell, unless headless
+    pub fn bell(&mut self) {
+        if let Some(terminal) = &mut self.terminal {
+            let backend = terminal.backend_mut();
+            let _ = backend.write_all(b"\x07").and_then(|_| backend.flush());
+        }
+    }
+    pub fn size(&self) -> Option<Rect> {
+        self.terminal.as_ref()?.size().ok()
+    }
     pub fn error(&mut self, location: String, message: &str, error: &dyn std::fmt::Display) {
         let spans = vec![
             Spans::from(vec![
@@ -46,7 +257,81 @@ impl Ui {
                 Span::styled(format!("\"{}\"", error), Style::default().fg(Color::LightRed))
             ])
         ];
-        self.terminal.draw(|frame| {
+        self.notice(spans);
+    }
+    /// Show a non-fatal problem and wait for a key press before continuing
+    pub fn warning(&mut self, message: &str, reason: &dyn std::fmt::Display) {
+        if self.terminal.is_none() {
+            return eprintln!("Warning: {}: {}", message, reason)
+        }
+        let spans = vec![
+            Spans::from(vec![
+                Span::styled("Warning", Style::default().fg(Color::Yellow))
+            ]),
+            Spans::from(vec![
+                Span::styled(message, Style::default().add_modifier(Modifier::BOLD))
+            ]),
+            Spans::from(vec![
+                Span::from("Reason: "),
+                Span::styled(format!("\"{}\"", reason), Style::default().fg(Color::LightYellow))
+            ]),
+            Spans::from(""),
+            Spans::from(vec![
+                Span::styled("Press any key to continue", Style::default().add_modifier(Modifier::DIM))
+            ])
+        ];
+        self.notice(spans);
+    }
+    /// Ask a yes or no question, returning true if the user answers yes
+    pub fn confirm(&mut self, question: &str, detail: &str) -> bool {
+        let spans = vec![
+            Spans::from(vec![
+                Span::styled(question, Style::default().add_modifier(Modifier::BOLD))
+            ]),
+            Spans::from(detail),
+            Spans::from(""),
+            Spans::from(vec![
+                Span::styled("Press y to confirm, any other key to cancel", Style::default().add_modifier(Modifier::DIM))
+            ])
+        ];
+        matches!(self.notice(spans), Some(Key::Char('y')) | Some(Key::Char('Y')))
+    }
+    /// Count down to a time, returning true once it comes, or false if q or escape is pressed first
+    pub fn wait_until(&mut self, start: SystemTime, message: &str) -> bool {
+        if self.terminal.is_none() {
+            eprintln!("{}", message);
+            if let Ok(wait) = start.duration_since(SystemTime::now()) {
+                thread::sleep(wait)
+            }
+            return true
+        }
+        let _ = self.input.stdin.try_iter().count();
+        while let Ok(left) = start.duration_since(SystemTime::now()) {
+            let seconds = left.as_secs();
+            self.centred(vec![
+                Spans::from(vec![
+                    Span::styled(message, Style::default().add_modifier(Modifier::BOLD))
+                ]),
+                Spans::from(format!("Starting in {}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)),
+                Spans::from(""),
+                Spans::from(vec![
+                    Span::styled("Press q or escape to give up", Style::default().add_modifier(Modifier::DIM))
+                ])
+            ]);
+            if let Ok(Key::Char('q')) | Ok(Key::Esc) = self.input.stdin.recv_timeout(left.min(Duration::from_secs(1))) {
+                return false
+            }
+        }
+        true
+    }
+    /// Draw a centred message and wait for a key press, returning it
+    fn notice(&mut self, spans: Vec<Spans>) -> Option<Key> {
+        self.centred(spans);
+        let _ = self.input.stdin.try_iter().count();
+        self.input.stdin.recv().ok()
+    }
+    fn centred(&mut self, spans: Vec<Spans>) {
+        self.draw(|frame| {
             frame.render_widget(
                 Paragraph::new(spans)
                     .style(Style::reset())
@@ -54,14 +339,27 @@ impl Ui {
                 Layout::default().margin(3).constraints(vec![Constraint::Percentage(100)]).split(frame.size())[0]
             );
         }).expect("Unable to draw to stdout");
-        let _ = self.input.stdin.try_iter().count();
-        let _ = self.input.stdin.recv();
+    }
+}
+
+/// Termion restores the terminal as its wrappers are dropped, but crossterm has to be told
+#[cfg(feature = "crossterm-backend")]
+impl Drop for Ui {
+    fn drop(&mut self) {
+        if let Some(screen) = &mut self.terminal {
+            let _ = execute!(screen.backend_mut(), terminal::LeaveAlternateScreen, DisableMouseCapture);
+            let _ = terminal::disable_raw_mode();
+        }
     }
 }
 
 pub struct ListState{
     state: tui::widgets::ListState,
-    item_count: usize
+    item_count: usize,
+    /// The device the selection stays on as rows come and go, forgotten when the selection is moved so the move takes effect
+    pinned: Option<MacAddress>,
+    /// Move the selection to the newest device as they turn up, until it is moved by hand
+    pub newest: bool
 }
 impl ListState {
     pub fn with_item_count(item_count: usize) -> Self {
@@ -69,8 +367,33 @@ impl ListState {
         state.select(Some(0));
         Self {
             state,
-            item_count
+            item_count,
+            pinned: None,
+            newest: false
+        }
+    }
+    /// Keep the selection on the device it was on, or move it to the newest device while following new ones,
+    /// given the device on each row, if any, once the rows have been laid out
+    pub fn follow(&mut self, rows: &[Option<MacAddress>], newest: impl FnOnce() -> Option<MacAddress>) {
+        let target = if self.newest { newest() } else { self.pinned };
+        if let Some(index) = target.and_then(|target| rows.iter().position(|&row| row == Some(target))) {
+            self.state.select(Some(index));
         }
+        self.pinned = self.state.selected().and_then(|index| rows.get(index).copied().flatten());
+    }
+    /// Go back to the top as the rows are replaced, still following new devices if it was
+    pub fn restart(&mut self) {
+        self.pinned = None;
+        self.state.select(Some(0))
+    }
+    /// Move the selection to a device once the rows are next laid out, no longer following new devices
+    pub fn pin(&mut self, address: MacAddress) {
+        self.newest = false;
+        self.pinned = Some(address);
+    }
+    fn unpin(&mut self) {
+        self.pinned = None;
+        self.newest = false;
     }
     pub fn set_item_count(&mut self, item_count: usize) {
         if let Some(selected) = self.state.selected() {
@@ -81,6 +404,7 @@ impl ListState {
         self.item_count = item_count;
     }
     pub fn up(&mut self) {
+        self.unpin();
         if let Some(selected) = self.state.selected() {
             if selected <= 0 {
                 self.state.select(Some(self.item_count.saturating_sub(1)))
@@ -90,6 +414,7 @@ impl ListState {
         }
     }
     pub fn down(&mut self) {
+        self.unpin();
         if let Some(selected) = self.state.selected() {
             if selected >= self.item_count.saturating_sub(1) {
                 self.state.select(Some(0))
@@ -99,20 +424,17 @@ impl ListState {
         }
     }
     pub fn top(&mut self) {
+        self.unpin();
         self.state.select(Some(0))
     }
     pub fn bottom(&mut self) {
+        self.unpin();
         self.state.select(Some(self.item_count.saturating_sub(1)))
     }
 }
 impl Default for ListState {
     fn default() -> Self {
-        let mut state = tui::widgets::ListState::default();
-        state.select(Some(0));
-        Self {
-            state,
-            item_count: 0
-        }
+        Self::with_item_count(0)
     }
 }
 impl Deref for ListState {
@@ -129,15 +451,26 @@ impl DerefMut for ListState {
 
 pub struct TabState<'a> {
     pub titles: Vec<Spans<'a>>,
+    /// Abbreviated titles for narrow terminals
+    pub short_titles: Vec<Spans<'a>>,
     pub index: usize
 }
 impl<'a> TabState<'a> {
-    pub fn new(titles: Vec<Spans<'a>>) -> Self {
+    pub fn new(titles: Vec<Spans<'a>>, short_titles: Vec<Spans<'a>>) -> Self {
         Self {
             titles,
+            short_titles,
             index: 0
         }
     }
+    /// The titles that fit in the given width
+    pub fn titles_for(&self, area: Rect) -> Vec<Spans<'a>> {
+        if narrow(area) {
+            self.short_titles.clone()
+        } else {
+            self.titles.clone()
+        }
+    }
     pub fn select(&mut self, index: usize) {
         self.index = index.clamp(0, self.titles.len() - 1)
     }
@@ -163,6 +496,7 @@ pub struct Input {
 impl Input {
     pub fn new() -> Input {
         let (tx, rx) = mpsc::channel();
+        #[cfg(not(feature = "crossterm-backend"))]
         thread::spawn(move || {
             let mut keys = std::io::stdin().keys();
             while let Some(key) = keys.next() {
@@ -171,6 +505,20 @@ impl Input {
                 }
             }
         });
+        #[cfg(feature = "crossterm-backend")]
+        thread::spawn(move || {
+            while let Ok(event) = event::read() {
+                if let Event::Key(key) = event {
+                    tx.send(Key::from_event(key)).expect("Input channel unexpectedly closed")
+                }
+            }
+        });
+        Self {
+            stdin: rx
+        }
+    }    /// Input that never comes, as the sender is gone
+    pub fn none() -> Input {
+        let (_, rx) = mpsc::channel();
         Self {
             stdin: rx
         }
@@ -183,11 +531,22 @@ macro_rules! expect {
         match $result {
             Ok(t) => t,
             Err(e) => {
-                use tui::{style::{Color, Modifier, Style}, text::{Spans, Span}};
                 let location = format!("{}:{}:{}", file!(), line!(), column!());
-                $ui.error(location, $msg, &e);
-                Err(e).expect($msg)
+                $ui.error(location.clone(), $msg, &e);
+                // Unwinds rather than exiting so the terminal is restored on the way out
+                std::panic::resume_unwind(Box::new($crate::ui::Fatal {
+                    location,
+                    message: $msg.to_string(),
+                    reason: e.to_string()
+                }))
             }
         }
     };
+    // A failure from the library, which says itself what was being done
+    ($ui:expr => $result:expr) => {
+        match $result {
+            Ok(t) => t,
+            Err(failure) => expect!($ui => Err(failure.reason), failure.message.as_str())
+        }
+    };
 }
\ No newline at end of file
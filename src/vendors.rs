@@ -0,0 +1,70 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::{self, Write},
+    time::{Duration, Instant}
+};
+use eui48::MacAddress;
+use oui::OuiEntry;
+
+use crate::{KnownDevice, export::{self, Format}, survey::Survey};
+
+/// Distinct devices seen per manufacturer, bucketed by time since the session started
+pub struct VendorTimeline {
+    bucket: Duration,
+    started: Instant,
+    buckets: Vec<BTreeMap<String, HashSet<MacAddress>>>
+}
+impl VendorTimeline {
+    pub fn new(bucket: Duration) -> Self {
+        Self {
+            bucket,
+            started: Instant::now(),
+            buckets: vec![]
+        }
+    }
+    /// Note that a device was heard just now, if its manufacturer is known
+    pub fn record(&mut self, address: MacAddress, device: &KnownDevice) {
+        let manufacturer = match &device.manufacturer {
+            Some(OuiEntry { name_short, .. }) => name_short.as_str(),
+            None => return
+        };
+        let index = (self.started.elapsed().as_secs() / self.bucket.as_secs().max(1)) as usize;
+        if self.buckets.len() <= index {
+            self.buckets.resize_with(index + 1, Default::default);
+        }
+        let bucket = &mut self.buckets[index];
+        if let Some(devices) = bucket.get_mut(manufacturer) {
+            devices.insert(address);
+        } else {
+            bucket.insert(manufacturer.to_string(), Some(address).into_iter().collect());
+        }
+    }
+    pub fn write(&self, output: &mut impl Write, format: Format, survey: &Survey) -> io::Result<()> {
+        let bucket_seconds = self.bucket.as_secs().max(1);
+        let start = |index: usize| survey.started_unix() + index as u64 * bucket_seconds;
+        match format {
+            Format::Csv => {
+                writeln!(output, "bucket_start,manufacturer,devices")?;
+                for (index, bucket) in self.buckets.iter().enumerate() {
+                    for (manufacturer, devices) in bucket {
+                        writeln!(output, "{},{},{}", start(index), export::csv_field(manufacturer), devices.len())?;
+                    }
+                }
+            }
+            Format::Json => {
+                let buckets: Vec<_> = self.buckets.iter().enumerate()
+                    .map(|(index, bucket)| export::json_object(vec![
+                        ("start", start(index).to_string()),
+                        ("manufacturers", export::json_object(bucket.iter().map(|(manufacturer, devices)| (manufacturer, devices.len().to_string()))))
+                    ]))
+                    .collect();
+                writeln!(output, "{}", export::json_object(vec![
+                    ("survey", export::json_object(survey.fields().into_iter().map(|(key, value)| (key, export::json_string(&value))))),
+                    ("bucket_seconds", bucket_seconds.to_string()),
+                    ("buckets", format!("[{}]", buckets.join(",")))
+                ]))?;
+            }
+        }
+        output.flush()
+    }
+}
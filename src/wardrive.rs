@@ -0,0 +1,208 @@
+use std::{
+    fs::File,
+    io::{self, Seek, SeekFrom, Write},
+    path::Path,
+    time::Duration
+};
+use eui48::MacAddress;
+
+use crate::{DeviceList, KnownDevice, Role, export, policy::{Level, Security}, survey::Survey};
+
+const DAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// A file format wardriving tools and databases take networks in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Kismet's XML log of networks and their clients, which most older tools read
+    Netxml,
+    /// For uploading to WiGLE
+    Wigle
+}
+impl Format {
+    /// Pick a format from the extension of a path, defaulting to WiGLE CSV
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("netxml") => Self::Netxml,
+            _ => Self::Wigle
+        }
+    }
+}
+
+/// Replace the contents of a file with every access point heard
+/// The file is rewritten rather than reopened so it can be kept open from before the sandbox was applied
+pub fn rewrite(file: &mut File, format: Format, devices: &DeviceList, survey: &Survey) -> io::Result<()> {
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    write(file, format, devices, survey)
+}
+
+/// Write every access point with its SSID, channel, encryption, when it was heard and, with --gps, where it was heard loudest
+pub fn write(output: &mut impl Write, format: Format, devices: &DeviceList, survey: &Survey) -> io::Result<()> {
+    let anonymizer = &survey.anonymizer;
+    let unix = |time: Option<Duration>| survey.started_unix() + time.map_or(0, |time| time.as_secs());
+    let mut networks: Vec<_> = devices.iter().filter(|(_, device)| device.role == Some(Role::AccessPoint)).collect();
+    networks.sort_by_key(|(address, _)| **address);
+    match format {
+        Format::Wigle => {
+            writeln!(output, "WigleWifi-1.4,appRelease={},model=blockade-recon,release={0},device=blockade-recon,display=,board=,brand=", env!("CARGO_PKG_VERSION"))?;
+            writeln!(output, "MAC,SSID,AuthMode,FirstSeen,Channel,RSSI,CurrentLatitude,CurrentLongitude,AltitudeMeters,AccuracyMeters,Type")?;
+            for (address, device) in networks {
+                let strongest = device.geo.strongest;
+                let signal = strongest.and_then(|point| point.signal).or(device.signal_strength.highest);
+                writeln!(
+                    output,
+                    "{},{},{},{},{},{},{},{},0,0,WIFI",
+                    anonymizer.address_string(address),
+                    export::csv_field(&name(device).map_or_else(String::new, |ssid| anonymizer.ssid(ssid).into_owned())),
+                    wigle_auth(device.security.as_ref()),
+                    timestamp(unix(device.first_seen)),
+                    channel(device).map_or_else(String::new, |(channel, _)| channel.to_string()),
+                    signal.map_or_else(String::new, |signal| signal.to_string()),
+                    strongest.map_or(0.0, |point| point.fix.latitude),
+                    strongest.map_or(0.0, |point| point.fix.longitude)
+                )?;
+            }
+            Ok(())
+        }
+        Format::Netxml => {
+            writeln!(output, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+            writeln!(output, r#"<!DOCTYPE detection-run SYSTEM "http://kismetwireless.net/kismet-3.1.0.dtd">"#)?;
+            writeln!(output, r#"<detection-run kismet-version="blockade-recon {}" start-time="{}">"#, env!("CARGO_PKG_VERSION"), ctime(survey.started_unix()))?;
+            for (number, (address, device)) in networks.into_iter().enumerate() {
+                let (first, last) = (ctime(unix(device.first_seen)), ctime(unix(device.last_seen)));
+                let frames: u64 = device.sensors.values().map(|sighting| sighting.frames).sum();
+                writeln!(output, r#"<wireless-network number="{}" type="infrastructure" first-time="{}" last-time="{}">"#, number + 1, first, last)?;
+                writeln!(output, r#"<SSID first-time="{}" last-time="{}"><type>Beacon</type><packets>{}</packets>"#, first, last, frames)?;
+                for encryption in kismet_encryption(device.security.as_ref()) {
+                    writeln!(output, "<encryption>{}</encryption>", encryption)?;
+                }
+                match name(device) {
+                    Some(ssid) => writeln!(output, r#"<essid cloaked="{}">{}</essid>"#, device.hidden, export::xml(&anonymizer.ssid(ssid)))?,
+                    None => writeln!(output, r#"<essid cloaked="true"></essid>"#)?
+                }
+                writeln!(output, "</SSID>")?;
+                writeln!(output, "<BSSID>{}</BSSID>", anonymizer.address_string(address).to_uppercase())?;
+                writeln!(output, "<manuf>{}</manuf>", export::xml(device.manufacturer.as_ref().map_or("Unknown", |entry| entry.name_short.as_str())))?;
+                if let Some((channel, frequency)) = channel(device) {
+                    writeln!(output, "<channel>{}</channel><freqmhz>{} {}</freqmhz>", channel, frequency, frames)?;
+                }
+                writeln!(output, "<packets><total>{}</total></packets>", frames)?;
+                if let (Some(last), Some(lowest), Some(highest)) = (device.signal_strength.last, device.signal_strength.lowest, device.signal_strength.highest) {
+                    writeln!(
+                        output,
+                        "<snr-info><last_signal_dbm>{}</last_signal_dbm><min_signal_dbm>{}</min_signal_dbm><max_signal_dbm>{}</max_signal_dbm></snr-info>",
+                        last, lowest, highest
+                    )?;
+                }
+                if let Some(strongest) = device.geo.strongest {
+                    let fixes = || device.geo.points.iter().map(|point| point.fix).chain(Some(strongest.fix));
+                    let latitudes = || fixes().map(|fix| fix.latitude);
+                    let longitudes = || fixes().map(|fix| fix.longitude);
+                    writeln!(
+                        output,
+                        "<gps-info><min-lat>{}</min-lat><min-lon>{}</min-lon><max-lat>{}</max-lat><max-lon>{}</max-lon><peak-lat>{}</peak-lat><peak-lon>{}</peak-lon></gps-info>",
+                        latitudes().fold(f64::INFINITY, f64::min),
+                        longitudes().fold(f64::INFINITY, f64::min),
+                        latitudes().fold(f64::NEG_INFINITY, f64::max),
+                        longitudes().fold(f64::NEG_INFINITY, f64::max),
+                        strongest.fix.latitude,
+                        strongest.fix.longitude
+                    )?;
+                }
+                let mut clients: Vec<(&MacAddress, &KnownDevice)> = devices.iter().filter(|(_, client)| client.associated == Some(*address)).collect();
+                clients.sort_by_key(|(client, _)| **client);
+                for (number, (client, device)) in clients.into_iter().enumerate() {
+                    writeln!(
+                        output,
+                        r#"<wireless-client number="{}" type="established" first-time="{}" last-time="{}"><client-mac>{}</client-mac><client-manuf>{}</client-manuf><packets><total>{}</total></packets></wireless-client>"#,
+                        number + 1,
+                        ctime(unix(device.first_seen)),
+                        ctime(unix(device.last_seen)),
+                        anonymizer.address_string(client).to_uppercase(),
+                        export::xml(device.manufacturer.as_ref().map_or("Unknown", |entry| entry.name_short.as_str())),
+                        device.sensors.values().map(|sighting| sighting.frames).sum::<u64>()
+                    )?;
+                }
+                writeln!(output, "</wireless-network>")?;
+            }
+            writeln!(output, "</detection-run>")
+        }
+    }
+}
+
+/// The network name an access point beacons, unless it hides it behind an empty name or one of NULs
+fn name(device: &KnownDevice) -> Option<&str> {
+    device.beacon.as_deref().filter(|ssid| !ssid.chars().all(|c| c == '\0'))
+}
+
+/// The channel and frequency the access point was heard loudest on
+fn channel(device: &KnownDevice) -> Option<(u32, u32)> {
+    let (_, sighting) = device.strongest_sensor()?;
+    Some((sighting.channel()?, sighting.frequency?))
+}
+
+/// The security of a network as WiGLE's AuthMode gives it, which is how Android describes it
+fn wigle_auth(security: Option<&Security>) -> String {
+    let security = match security {
+        Some(security) => security,
+        None => return String::new()
+    };
+    let (key, cipher) = match security.level {
+        Level::Open => return "[ESS]".to_string(),
+        Level::Wep => return "[WEP][ESS]".to_string(),
+        Level::Owe => return "[OWE][ESS]".to_string(),
+        Level::Wpa => (if security.enterprise { "EAP" } else { "PSK" }, "TKIP"),
+        Level::Wpa2 => (if security.enterprise { "EAP" } else { "PSK" }, if security.tkip { "CCMP+TKIP" } else { "CCMP" }),
+        Level::Wpa3 => (if security.enterprise { "EAP" } else { "SAE" }, "CCMP")
+    };
+    format!("[{}-{}-{}][ESS]", security.level, key, cipher)
+}
+
+/// The security of a network as Kismet lists it, one entry per method
+fn kismet_encryption(security: Option<&Security>) -> Vec<&'static str> {
+    let security = match security {
+        Some(security) => security,
+        None => return vec![]
+    };
+    let key = if security.enterprise { "WPA+MGT" } else { "WPA+PSK" };
+    match security.level {
+        Level::Open => vec!["None"],
+        Level::Wep => vec!["WEP"],
+        Level::Owe => vec!["WPA+AES-CCM", "WPA+OWE"],
+        Level::Wpa => vec![key, "WPA+TKIP"],
+        Level::Wpa2 if security.tkip => vec![key, "WPA+AES-CCM", "WPA+TKIP"],
+        Level::Wpa2 => vec![key, "WPA+AES-CCM"],
+        Level::Wpa3 if security.enterprise => vec![key, "WPA+AES-CCM"],
+        Level::Wpa3 => vec!["WPA+SAE", "WPA+AES-CCM"]
+    }
+}
+
+/// A Unix time as WiGLE gives it, in UTC
+fn timestamp(unix: u64) -> String {
+    let (year, month, day, _) = date(unix);
+    format!("{}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, unix / 3600 % 24, unix / 60 % 60, unix % 60)
+}
+
+/// A Unix time as Kismet gives it, the way ctime does but in UTC
+fn ctime(unix: u64) -> String {
+    let (year, month, day, weekday) = date(unix);
+    format!("{} {} {:>2} {:02}:{:02}:{:02} {}", DAYS[weekday], MONTHS[month - 1], day, unix / 3600 % 24, unix / 60 % 60, unix % 60, year)
+}
+
+/// The year, month from 1, day of the month and day of the week from Sunday of a Unix time in UTC
+fn date(unix: u64) -> (u64, usize, u64, usize) {
+    let days = unix / 86_400;
+    // 1970 started on a Thursday
+    let weekday = ((days + 4) % 7) as usize;
+    // Counted from the 1st of March in the year 0, so leap days fall at the end of each year
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    (era * 400 + year_of_era + (month <= 2) as u64, month as usize, day, weekday)
+}
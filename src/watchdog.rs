@@ -0,0 +1,93 @@
+use std::time::{Duration, Instant};
+
+use crate::netdev;
+
+/// How long an interface is left down when restarting it
+const SETTLE: Duration = Duration::from_millis(500);
+
+/// Notices when a capture that should be busy stops delivering frames
+pub struct Watchdog {
+    timeout: Option<Duration>,
+    last_frame: Instant,
+    /// True once the current silence has been reported
+    tripped: bool,
+    /// Whether the interface should be restarted when the capture goes quiet
+    pub bounce: bool,
+    pub bounces: usize,
+    /// The reason the last restart attempt failed, if it did
+    pub bounce_error: Option<String>,
+    /// When the interface was taken down to be restarted, until it is brought back up
+    down: Option<Instant>
+}
+impl Watchdog {
+    /// A timeout of `None` disables the watchdog
+    pub fn new(timeout: Option<Duration>, bounce: bool) -> Self {
+        Self {
+            timeout,
+            last_frame: Instant::now(),
+            tripped: false,
+            bounce,
+            bounces: 0,
+            bounce_error: None,
+            down: None
+        }
+    }
+    pub fn frame(&mut self) {
+        self.last_frame = Instant::now();
+        self.tripped = false;
+    }
+    /// How long the capture has been silent for, if longer than the timeout
+    pub fn silence(&self) -> Option<Duration> {
+        let timeout = self.timeout?;
+        let silence = self.last_frame.elapsed();
+        if silence >= timeout {
+            Some(silence)
+        } else {
+            None
+        }
+    }
+    /// Returns true only the first time a silence is noticed so it is acted upon once
+    pub fn check(&mut self) -> bool {
+        if !self.tripped && self.silence().is_some() {
+            self.tripped = true;
+            true
+        } else {
+            false
+        }
+    }
+    /// Take an interface down when the capture goes quiet and back up once it has settled, which is often enough to
+    /// recover a wedged adapter, a step at a time so whoever calls this regularly is never held up in between
+    /// Gives whether the interface came back up, needing the capture to be opened again, once a restart is over
+    pub fn restart(&mut self, interface: &str) -> Option<bool> {
+        if let Some(down) = self.down {
+            if down.elapsed() < SETTLE {
+                return None
+            }
+            self.down = None;
+            let result = netdev::set_up(interface, true);
+            let up = result.is_ok();
+            self.bounced(result);
+            return Some(up)
+        }
+        if !self.bounce || !self.check() {
+            return None
+        }
+        match netdev::set_up(interface, false) {
+            Ok(()) => {
+                self.down = Some(Instant::now());
+                None
+            }
+            Err(error) => {
+                self.bounced(Err(error));
+                Some(false)
+            }
+        }
+    }
+    /// Record a restart of the interface, giving it a full timeout to recover
+    pub fn bounced(&mut self, result: std::io::Result<()>) {
+        self.bounces += 1;
+        self.bounce_error = result.err().map(|error| error.to_string());
+        self.last_frame = Instant::now();
+        self.tripped = false;
+    }
+}
@@ -1,37 +1,424 @@
+use std::fmt;
 use eui48::MacAddress;
 
+use crate::layer3;
+
+/// The length of the header of a management frame, up to its body
+const MANAGEMENT_HEADER: usize = 24;
+/// The frame check sequence ending every frame
+const FCS: usize = 4;
+/// The bits of a duration field or association response that make up an association ID
+const AID_MASK: u16 = 0x3fff;
+
 #[derive(Debug)]
 pub enum Tag {
     Ssid(String),
+    /// Rates in units of 500kbps, with the top bit set on those every station must support
     SupportedRates(Vec<u8>),
+    /// The channel the access point is on, as it says itself
+    DsParameter {
+        channel: u8
+    },
+    /// The rates that didn't fit in the supported rates element
+    ExtendedRates(Vec<u8>),
+    /// Which dozing clients the access point is holding frames for, and when it next delivers group traffic
+    Tim {
+        /// Beacons until the next DTIM, with 0 meaning this beacon is one
+        dtim_count: u8,
+        /// How many beacons apart DTIMs are
+        dtim_period: u8,
+        /// Group traffic is held for after this DTIM
+        group: bool,
+        /// The association IDs of the clients frames are held for
+        aids: Vec<u16>
+    },
     Country {
         code: [u8; 2],
+        /// The most power allowed in each subband, as its first channel, number of channels and limit in dBm
+        limits: Vec<(u8, u8, i8)>
+    },
+    /// How many dB below its country's limit the access point tells clients to stay
+    PowerConstraint(u8),
+    /// The power the access point is transmitting at, in dBm
+    TpcReport {
+        power: i8
+    },
+    /// The network uses WPA2 or later
+    Rsn {
+        /// Cipher suites for unicast traffic, as the OUI and suite type
+        pairwise: Vec<[u8; 4]>,
+        /// Authentication and key management suites, as the OUI and suite type
+        akms: Vec<[u8; 4]>,
+        capabilities: u16
+    },
+    /// The access point is about to move to another channel, as for radar under DFS
+    ChannelSwitch {
+        channel: u8,
+        /// Beacon intervals left before the switch, with 0 meaning any time now
+        count: u8
+    },
+    /// The access point's own account of how busy it is
+    BssLoad {
+        stations: u16,
+        /// The fraction of time the channel was sensed busy, out of 255
+        utilisation: u8
+    },
+    Interworking {
+        /// The kind of network as far as billing and access goes, eg. a free public network
+        access_network_type: u8,
+        /// The network claims to reach the internet
+        internet: bool,
+        /// The venue group and type, such as a hotel in the mercantile group
+        venue: Option<(u8, u8)>,
+        /// Identifies the hotspot as a whole, shared by every access point in it
+        hessid: Option<MacAddress>
+    },
+    RoamingConsortium {
+        /// Organisation identifiers of roaming partners whose credentials are accepted
+        ois: Vec<Vec<u8>>
     },
     VendorSpecific {
         vendor: [u8; 3],
+        /// The vendor defined type of the element, if present
+        kind: Option<u8>
     },
-    Unknown
+    /// Wi-Fi Protected Setup details, in which devices often name their maker and model
+    Wps {
+        manufacturer: Option<String>,
+        model: Option<String>,
+        model_number: Option<String>,
+        device_name: Option<String>,
+        /// Whether an access point says it has been set up, where one that hasn't may hand its settings to anyone
+        configured: Option<bool>,
+        /// The access point has stopped taking PINs, usually after too many wrong guesses
+        locked: bool
+    },
+    /// Pairs an open network with the hidden OWE network that encrypts the same one, naming the other of the two
+    OweTransition {
+        bssid: MacAddress,
+        ssid: String
+    },
+    /// The radio supports 802.11n, or Wi-Fi 4
+    HtCapabilities {
+        /// 40MHz channels are supported as well as 20MHz
+        forty: bool,
+        /// Spatial streams received, going by the MCS indices supported
+        streams: u8
+    },
+    /// The radio supports 802.11ac, or Wi-Fi 5
+    VhtCapabilities {
+        /// The widest channel supported, in MHz
+        width: u16,
+        streams: u8
+    },
+    /// The radio supports 802.11ax, or Wi-Fi 6
+    HeCapabilities {
+        width: u16,
+        streams: u8
+    },
+    /// The radio supports 802.11be, or Wi-Fi 7
+    EhtCapabilities,
+    /// Ties an access point to the others run by one multi-link device, as 802.11be access points do across bands
+    MultiLink {
+        /// The address of the multi-link device as a whole, which clients associate with
+        mld: Option<MacAddress>,
+        /// The other links described by the element, by link ID, with their own address when given
+        links: Vec<(u8, Option<MacAddress>)>
+    },
+    /// Any other element, by its ID
+    Unknown(u8)
 }
 impl Tag {
     /// Parse a single management tag, removing itself from the start of the given buffer
-    pub fn parse(data: &mut &[u8]) -> Result<Self> {
-        let &length = data.get(1).ok_or(Error::UnexpectedEof)?;
-        let tag = data[0];
-        let (d, other) = data.split_at(2 + length as usize);
-        *data = other;
-        let data = &d[2..];
+    pub fn parse(buffer: &mut &[u8]) -> Result<Self> {
+        let all = *buffer;
+        let tag = byte(all, 0)?;
+        let length = byte(all, 1)? as usize;
+        let data = slice(all, 2, 2 + length)?;
+        *buffer = &all[2 + length..];
         Ok(match tag {
             0x00 => Self::Ssid(String::from_utf8_lossy(data).to_string()),
             0x01 => Self::SupportedRates(data.to_vec()),
+            0x03 => Self::DsParameter {
+                channel: byte(data, 0)?
+            },
+            0x05 => Self::tim(data)?,
             0x07 => Self::Country {
-                code: [data[0], data[1]]
+                code: [byte(data, 0)?, byte(data, 1)?],
+                // Triplets follow the code and environment, where a first channel of 201 or more starts an operating class instead
+                limits: data.get(3..).unwrap_or_default()
+                    .chunks_exact(3)
+                    .filter(|triplet| triplet[0] < 201)
+                    .map(|triplet| (triplet[0], triplet[1], triplet[2] as i8))
+                    .collect()
+            },
+            0x0b => Self::BssLoad {
+                stations: u16_le(data, 0)?,
+                utilisation: byte(data, 2)?
+            },
+            0x20 => Self::PowerConstraint(byte(data, 0)?),
+            0x23 => Self::TpcReport {
+                power: byte(data, 0)? as i8
+            },
+            0x25 => Self::ChannelSwitch {
+                channel: byte(data, 1)?,
+                count: byte(data, 2)?
+            },
+            0x2d => Self::HtCapabilities {
+                forty: u16_le(data, 0)? & 1 << 1 != 0,
+                // The receive MCS bitmask follows the capability info and A-MPDU parameters, a byte for each stream
+                streams: slice(data, 3, 7)?.iter().filter(|&&mcs| mcs != 0).count() as u8
+            },
+            0x30 => Self::rsn(data)?,
+            0x32 => Self::ExtendedRates(data.to_vec()),
+            // The extended form adds the operating class of the new channel
+            0x3c => Self::ChannelSwitch {
+                channel: byte(data, 2)?,
+                count: byte(data, 3)?
+            },
+            0xbf => Self::VhtCapabilities {
+                width: match byte(data, 0)? >> 2 & 0b11 {
+                    0 => 80,
+                    _ => 160
+                },
+                // The receive MCS map follows the capability info
+                streams: streams(u16_le(data, 4)?)
+            },
+            0x6b => {
+                let options = byte(data, 0)?;
+                // Both the venue and HESSID are optional, so the length says which are present
+                let (venue, hessid) = match data.len() {
+                    3 => (Some((data[1], data[2])), None),
+                    7 => (None, Some(mac(data, 1)?)),
+                    9 => (Some((data[1], data[2])), Some(mac(data, 3)?)),
+                    _ => (None, None)
+                };
+                Self::Interworking {
+                    access_network_type: options & 0b1111,
+                    internet: options & 0b10000 != 0,
+                    venue,
+                    hessid
+                }
+            }
+            0x6f => Self::RoamingConsortium {
+                ois: Self::roaming_consortium(data)?
             },
+            0xdd if data.get(..4) == Some(&[0x50, 0x6f, 0x9a, 0x1c]) => {
+                let length = byte(data, 10)? as usize;
+                Self::OweTransition {
+                    bssid: mac(data, 4)?,
+                    ssid: String::from_utf8_lossy(slice(data, 11, 11 + length)?).to_string()
+                }
+            }
+            0xdd if data.get(..4) == Some(&[0x00, 0x50, 0xf2, 0x04]) => Self::wps(&data[4..])?,
             0xdd => Self::VendorSpecific {
-                vendor: [data[0], data[1], data[2]]
+                vendor: [byte(data, 0)?, byte(data, 1)?, byte(data, 2)?],
+                kind: data.get(3).copied()
             },
-            _ => Self::Unknown
+            // Newer elements are extensions, identified by their first byte
+            0xff => match data.get(0) {
+                Some(107) => Self::multi_link(&data[1..])?,
+                Some(35) => Self::he_capabilities(&data[1..])?,
+                Some(108) => Self::EhtCapabilities,
+                _ => Self::Unknown(tag)
+            },
+            id => Self::Unknown(id)
+        })
+    }
+    /// A traffic indication map, whose partial virtual bitmap has a bit per association ID from an offset
+    fn tim(data: &[u8]) -> Result<Self> {
+        let control = byte(data, 2)?;
+        // The offset is in the top seven bits and counts pairs of bytes, so is already the byte offset with the bottom bit cleared
+        let offset = (control & 0xfe) as u16;
+        let aids = data.get(3..).unwrap_or_default().iter().enumerate()
+            .flat_map(|(index, &bits)| (0..8u16).filter(move |bit| bits >> bit & 1 != 0).map(move |bit| (offset + index as u16) * 8 + bit))
+            // ID 0 stands for the group, given by the bottom bit of the control instead
+            .filter(|&aid| aid != 0)
+            .collect();
+        Ok(Self::Tim {
+            dtim_count: byte(data, 0)?,
+            dtim_period: byte(data, 1)?,
+            group: control & 1 != 0,
+            aids
+        })
+    }
+    /// The suites and capabilities of an RSN element, where anything after the version may be left off
+    fn rsn(data: &[u8]) -> Result<Self> {
+        // Skip the version and group cipher
+        let mut rest = data.get(6..).unwrap_or_default();
+        let mut suites = || -> Result<Vec<[u8; 4]>> {
+            if rest.len() < 2 {
+                return Ok(vec![])
+            }
+            let count = u16::from_le_bytes([rest[0], rest[1]]) as usize;
+            let list = slice(rest, 2, 2 + count * 4)?;
+            rest = &rest[2 + count * 4..];
+            Ok(list.chunks(4).map(|suite| [suite[0], suite[1], suite[2], suite[3]]).collect())
+        };
+        let pairwise = suites()?;
+        let akms = suites()?;
+        let capabilities = match rest {
+            [low, high, ..] => u16::from_le_bytes([*low, *high]),
+            _ => 0
+        };
+        Ok(Self::Rsn {
+            pairwise,
+            akms,
+            capabilities
+        })
+    }
+    /// The identifying attributes of a WPS element, which unlike other elements are big endian
+    fn wps(mut data: &[u8]) -> Result<Self> {
+        const MANUFACTURER: u16 = 0x1021;
+        const MODEL_NAME: u16 = 0x1023;
+        const MODEL_NUMBER: u16 = 0x1024;
+        const DEVICE_NAME: u16 = 0x1011;
+        const STATE: u16 = 0x1044;
+        const SETUP_LOCKED: u16 = 0x1057;
+        const CONFIGURED: u8 = 2;
+        let (mut manufacturer, mut model, mut model_number, mut device_name) = (None, None, None, None);
+        let (mut configured, mut locked) = (None, false);
+        while !data.is_empty() {
+            let kind = u16::from_be_bytes([byte(data, 0)?, byte(data, 1)?]);
+            let length = u16::from_be_bytes([byte(data, 2)?, byte(data, 3)?]) as usize;
+            let value = slice(data, 4, 4 + length)?;
+            data = &data[4 + length..];
+            // Padded with NULs and spaces by some devices, and left blank by others
+            let text = String::from_utf8_lossy(value).trim_matches(|c: char| c == '\0' || c.is_whitespace()).to_string();
+            let text = Some(text).filter(|text| !text.is_empty());
+            match kind {
+                MANUFACTURER => manufacturer = text,
+                MODEL_NAME => model = text,
+                MODEL_NUMBER => model_number = text,
+                DEVICE_NAME => device_name = text,
+                STATE => configured = value.first().map(|&state| state == CONFIGURED),
+                SETUP_LOCKED => locked = value.first().map_or(false, |&locked| locked != 0),
+                _ => ()
+            }
+        }
+        Ok(Self::Wps {
+            manufacturer,
+            model,
+            model_number,
+            device_name,
+            configured,
+            locked
+        })
+    }
+    /// The MLD address and links given by a basic multi-link element
+    fn multi_link(data: &[u8]) -> Result<Self> {
+        const BASIC: u16 = 0;
+        const PER_STA_PROFILE: u8 = 0;
+        const STA_MAC_PRESENT: u16 = 1 << 5;
+        let control = u16_le(data, 0)?;
+        // Probe request and reconfiguration variants don't carry an MLD address in the same place
+        if control & 0b111 != BASIC {
+            return Ok(Self::MultiLink { mld: None, links: vec![] })
+        }
+        let common_length = byte(data, 2)? as usize;
+        let mld = mac(data, 3)?;
+        let mut rest = slice(data, 2 + common_length, data.len())?;
+        let mut links = vec![];
+        while rest.len() >= 2 {
+            let (id, length) = (rest[0], rest[1] as usize);
+            let body = slice(rest, 2, 2 + length)?;
+            rest = &rest[2 + length..];
+            if id != PER_STA_PROFILE || body.len() < 2 {
+                continue
+            }
+            let sta_control = u16::from_le_bytes([body[0], body[1]]);
+            // The STA info starts with its own length, followed by the link's address if present
+            let address = match (sta_control & STA_MAC_PRESENT != 0, body.get(3..9)) {
+                (true, Some(address)) => Some(MacAddress::from_bytes(address)?),
+                _ => None
+            };
+            links.push(((sta_control & 0b1111) as u8, address));
+        }
+        Ok(Self::MultiLink {
+            mld: Some(mld),
+            links
         })
     }
+    /// The widest channel and streams given by an HE capabilities element, after its extension ID
+    fn he_capabilities(data: &[u8]) -> Result<Self> {
+        // The channel width set is the first byte of the PHY capabilities, which follow six bytes of MAC capabilities
+        // Its bits from the second are 40MHz in 2.4GHz, 40 and 80MHz in 5GHz, 160MHz, then 80+80MHz
+        let widths = byte(data, 6)?;
+        let width = if widths & 0b11000 != 0 {
+            160
+        } else if widths & 0b100 != 0 {
+            80
+        } else if widths & 0b10 != 0 {
+            40
+        } else {
+            20
+        };
+        Ok(Self::HeCapabilities {
+            width,
+            // The receive MCS map for 80MHz and narrower follows the eleven bytes of PHY capabilities
+            streams: streams(u16_le(data, 17)?)
+        })
+    }
+    /// The organisation identifiers advertised in a roaming consortium element
+    fn roaming_consortium(data: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let lengths = byte(data, 1)?;
+        let mut rest = slice(data, 2, data.len())?;
+        let mut ois = vec![];
+        // Up to three are carried, the last taking up whatever remains
+        for length in [lengths & 0b1111, lengths >> 4].iter().map(|&length| length as usize) {
+            if length == 0 || rest.len() < length {
+                break
+            }
+            let (oi, remaining) = rest.split_at(length);
+            ois.push(oi.to_vec());
+            rest = remaining;
+        }
+        if !rest.is_empty() {
+            ois.push(rest.to_vec());
+        }
+        Ok(ois)
+    }
+    /// Every rate a device advertised across its supported and extended rates elements, in the order given
+    pub fn rates(tags: &[Self]) -> Vec<u8> {
+        tags.iter()
+            .flat_map(|tag| match tag {
+                Self::SupportedRates(rates) | Self::ExtendedRates(rates) => rates.as_slice(),
+                _ => &[]
+            })
+            .copied()
+            .collect()
+    }
+    /// The IDs of the elements in the order given, with the vendor of vendor specific ones, eg. `0,1,50,221:0050f2`
+    /// Drivers lay out their probe requests in a fixed way, so this stays the same when a device randomises its address
+    pub fn layout(tags: &[Self]) -> String {
+        let ids: Vec<_> = tags.iter()
+            .map(|tag| match tag {
+                Self::Ssid(_) => "0".to_string(),
+                Self::SupportedRates(_) => "1".to_string(),
+                Self::DsParameter { .. } => "3".to_string(),
+                Self::Tim { .. } => "5".to_string(),
+                Self::Country { .. } => "7".to_string(),
+                Self::PowerConstraint(_) => "32".to_string(),
+                Self::TpcReport { .. } => "35".to_string(),
+                Self::ChannelSwitch { .. } => "37".to_string(),
+                Self::BssLoad { .. } => "11".to_string(),
+                Self::Rsn { .. } => "48".to_string(),
+                Self::ExtendedRates(_) => "50".to_string(),
+                Self::Interworking { .. } => "107".to_string(),
+                Self::RoamingConsortium { .. } => "111".to_string(),
+                Self::VendorSpecific { vendor, .. } => format!("221:{:02x}{:02x}{:02x}", vendor[0], vendor[1], vendor[2]),
+                Self::Wps { .. } => "221:0050f2".to_string(),
+                Self::OweTransition { .. } => "221:506f9a".to_string(),
+                Self::HtCapabilities { .. } => "45".to_string(),
+                Self::VhtCapabilities { .. } => "191".to_string(),
+                Self::HeCapabilities { .. } => "255:35".to_string(),
+                Self::EhtCapabilities => "255:108".to_string(),
+                Self::MultiLink { .. } => "255:107".to_string(),
+                Self::Unknown(id) => id.to_string()
+            })
+            .collect();
+        ids.join(",")
+    }
     /// Parse all of the management tags inside of a given buffer
     pub fn parse_all(mut data: &[u8]) -> Result<Vec<Self>> {
         let mut tags = vec![];
@@ -42,6 +429,74 @@ impl Tag {
     }
 }
 
+/// An element of an ANQP response, in which a hotspot describes itself to a client before it associates
+#[derive(Debug)]
+pub enum Anqp {
+    VenueName(Vec<String>),
+    RoamingConsortium(Vec<Vec<u8>>),
+    NaiRealm(Vec<String>),
+    DomainName(Vec<String>),
+    Unknown
+}
+impl Anqp {
+    /// Parse a single ANQP element, removing itself from the start of the given buffer
+    pub fn parse(data: &mut &[u8]) -> Result<Self> {
+        let all = *data;
+        let id = u16_le(all, 0)?;
+        let length = u16_le(all, 2)? as usize;
+        let payload = slice(all, 4, 4 + length)?;
+        *data = &all[4 + length..];
+        Ok(match id {
+            // Venue info comes first, followed by the name in each language
+            258 => Self::VenueName(Self::duples(payload.get(2..).unwrap_or_default())?
+                .into_iter()
+                .filter(|name| name.len() > 3)
+                .map(|name| String::from_utf8_lossy(&name[3..]).to_string())
+                .collect()),
+            261 => Self::RoamingConsortium(Self::duples(payload)?.into_iter().map(|oi| oi.to_vec()).collect()),
+            263 => Self::NaiRealm(Self::nai_realms(payload)?),
+            268 => Self::DomainName(Self::duples(payload)?
+                .into_iter()
+                .map(|name| String::from_utf8_lossy(name).to_string())
+                .collect()),
+            _ => Self::Unknown
+        })
+    }
+    /// Split a run of length prefixed fields
+    fn duples(mut data: &[u8]) -> Result<Vec<&[u8]>> {
+        let mut duples = vec![];
+        while let Some(&length) = data.first() {
+            let duple = slice(data, 1, 1 + length as usize)?;
+            duples.push(duple);
+            data = &data[1 + length as usize..];
+        }
+        Ok(duples)
+    }
+    fn nai_realms(data: &[u8]) -> Result<Vec<String>> {
+        let count = u16_le(data, 0)?;
+        let mut data = &data[2..];
+        let mut realms = vec![];
+        for _ in 0..count {
+            let length = u16_le(data, 0)? as usize;
+            let entry = slice(data, 2, 2 + length)?;
+            data = &data[2 + length..];
+            // Skipping the encoding, the realms are a semicolon separated list followed by the accepted EAP methods
+            let realm_length = byte(entry, 1)? as usize;
+            let realm = slice(entry, 2, 2 + realm_length)?;
+            realms.extend(String::from_utf8_lossy(realm).split(';').map(String::from));
+        }
+        Ok(realms)
+    }
+    /// Parse all of the ANQP elements inside of a given buffer
+    pub fn parse_all(mut data: &[u8]) -> Result<Vec<Self>> {
+        let mut elements = vec![];
+        while !data.is_empty() {
+            elements.push(Self::parse(&mut data)?)
+        }
+        Ok(elements)
+    }
+}
+
 #[derive(Debug)]
 pub enum FrameType {
     AssociationRequest,
@@ -49,9 +504,19 @@ pub enum FrameType {
     ReassociationRequest,
     ReassociationResponse,
     ProbeRequest,
+    ProbeResponse,
     Beacon,
+    Disassociation,
+    Deauthentication,
+    Action,
+    BlockAckRequest,
+    BlockAck,
+    PsPoll,
+    Rts,
+    Cts,
     Ack,
     Reserved,
+    Data,
     Unknown
 }
 impl FrameType {
@@ -62,9 +527,19 @@ impl FrameType {
             (0, 2) => Self::ReassociationRequest,
             (0, 3) => Self::ReassociationResponse,
             (0, 4) => Self::ProbeRequest,
+            (0, 5) => Self::ProbeResponse,
             (0, 8) => Self::Beacon,
+            (0, 10) => Self::Disassociation,
+            (0, 12) => Self::Deauthentication,
+            (0, 13) => Self::Action,
+            (1, 8) => Self::BlockAckRequest,
+            (1, 9) => Self::BlockAck,
+            (1, 10) => Self::PsPoll,
+            (1, 11) => Self::Rts,
+            (1, 12) => Self::Cts,
             (1, 13) => Self::Ack,
             (2, 13) => Self::Reserved,
+            (2, _) => Self::Data,
             _ => Self::Unknown
         }
     }
@@ -77,11 +552,30 @@ pub enum Frame {
         source: MacAddress,
         bssid: MacAddress,
         ssid: String,
+        capabilities: u16,
+        /// How often the access point means to send beacons, in time units of 1024µs
+        interval: u16,
         tags: Vec<Tag>
     },
     Ack {
         receiver: MacAddress,
     },
+    /// Clears the air for a frame to follow, naming only who it is for, which may be the sender itself
+    Cts {
+        receiver: MacAddress
+    },
+    /// A request to send, a block ack or a block ack request, only exchanged between devices already talking
+    Control {
+        receiver: MacAddress,
+        transmitter: MacAddress
+    },
+    /// A dozing station asking its access point for frames held for it, which only an associated station can do
+    PsPoll {
+        bssid: MacAddress,
+        station: MacAddress,
+        /// The association ID the access point gave the station, which its TIM lists while holding frames for it
+        aid: u16
+    },
     ProbeRequest {
         destination: MacAddress,
         source: MacAddress,
@@ -89,17 +583,78 @@ pub enum Frame {
         ssid: String,
         tags: Vec<Tag>
     },
+    /// An access point letting a station join, or not, and giving it an association ID if it does
+    AssociationResponse {
+        destination: MacAddress,
+        source: MacAddress,
+        bssid: MacAddress,
+        /// 0 for success
+        status: u16,
+        aid: u16
+    },
+    /// A station asking to join a network by name, which reveals the SSID of a hidden access point
+    AssociationRequest {
+        source: MacAddress,
+        bssid: MacAddress,
+        ssid: String,
+        tags: Vec<Tag>
+    },
+    ProbeResponse {
+        destination: MacAddress,
+        source: MacAddress,
+        bssid: MacAddress,
+        ssid: String,
+        capabilities: u16,
+        tags: Vec<Tag>
+    },
+    /// A data frame passing between a station and the access point it is associated with
+    Data {
+        bssid: MacAddress,
+        station: MacAddress,
+        /// True if the station sent the frame, false if the access point did
+        from_station: bool,
+        /// A QoS data frame, as sent by anything from 802.11n on
+        qos: bool,
+        /// The length of the whole frame
+        length: usize,
+        /// Which message of the WPA 4-way handshake this is, from 1 to 4, if it is one
+        eapol: Option<u8>,
+        /// Addresses and hostnames given away by an unencrypted body
+        announcements: Vec<layer3::Announcement>
+    },
+    /// A device telling another it has been disconnected, as deauthentication attacks send over and over to knock clients off
+    Deauthentication {
+        destination: MacAddress,
+        source: MacAddress,
+        bssid: MacAddress,
+        /// Why, unless protected management frames encrypted it
+        reason: Option<u16>,
+        /// A disassociation, which leaves the client authenticated, rather than a deauthentication
+        disassociation: bool
+    },
+    /// A data frame relayed between two access points over a wireless distribution system, as repeaters do
+    Wds {
+        receiver: MacAddress,
+        transmitter: MacAddress
+    },
+    /// An access point announcing in an action frame of its own that it is about to move to another channel
+    ChannelSwitch {
+        source: MacAddress,
+        channel: u8
+    },
+    /// A generic advertisement service response carrying ANQP elements
+    GasResponse {
+        destination: MacAddress,
+        source: MacAddress,
+        elements: Vec<Anqp>
+    },
     Unknown
 }
 impl Frame {
     pub fn parse(packet: &[u8]) -> Result<Self> {
-        if packet.len() < 10 {
-            return Err(Error::UnexpectedEof)
-        }
-        let frame_control = packet[0];
-        let flags = packet[1];
-        let duration = u16::from_le_bytes([packet[2], packet[3]]);
-        let address1 = MacAddress::from_bytes(&packet[4..10])?;
+        let frame_control = byte(packet, 0)?;
+        let flags = byte(packet, 1)?;
+        let address1 = mac(packet, 4)?;
 
         let version = frame_control & 0b11;
         if version != 0 {
@@ -107,14 +662,89 @@ impl Frame {
         }
         let frame_type = FrameType::new((frame_control >> 2) & 0b11, (frame_control >> 4) & 0b1111);
 
+        // Management frames share one header of three addresses and the sequence control before their body
+        let body = || slice(packet, MANAGEMENT_HEADER, packet.len());
         match frame_type {
-            FrameType::Beacon => Self::beacon(address1, MacAddress::from_bytes(&packet[10..16])?, MacAddress::from_bytes(&packet[16..22])?, u16::from_le_bytes([packet[22], packet[23]]), &packet[24..]),
+            FrameType::Beacon => Self::beacon(address1, mac(packet, 10)?, mac(packet, 16)?, u16_le(packet, 22)?, body()?),
+            FrameType::Action => Self::action(address1, mac(packet, 10)?, body()?),
             FrameType::Ack => Ok(Self::Ack { receiver: address1 }),
-            FrameType::ProbeRequest => Self::probe_request(address1, MacAddress::from_bytes(&packet[10..16])?, MacAddress::from_bytes(&packet[16..22])?, u16::from_le_bytes([packet[22], packet[23]]), &packet[24..]),
+            FrameType::Cts => Ok(Self::Cts { receiver: address1 }),
+            FrameType::Rts | FrameType::BlockAck | FrameType::BlockAckRequest => Ok(Self::Control {
+                receiver: address1,
+                transmitter: Self::control_transmitter(packet)?
+            }),
+            // The duration field carries the association ID instead, with the top two bits set
+            FrameType::PsPoll => Ok(Self::PsPoll { bssid: address1, station: mac(packet, 10)?, aid: u16_le(packet, 2)? & AID_MASK }),
+            FrameType::Deauthentication | FrameType::Disassociation => Ok(Self::Deauthentication {
+                destination: address1,
+                source: mac(packet, 10)?,
+                bssid: mac(packet, 16)?,
+                // The protected flag
+                reason: if flags & 0x40 == 0 { Some(u16_le(packet, MANAGEMENT_HEADER)?) } else { None },
+                disassociation: matches!(frame_type, FrameType::Disassociation)
+            }),
+            FrameType::ProbeRequest => Self::probe_request(address1, mac(packet, 10)?, mac(packet, 16)?, u16_le(packet, 22)?, body()?),
+            FrameType::AssociationRequest | FrameType::ReassociationRequest => Self::association_request(
+                mac(packet, 10)?,
+                mac(packet, 16)?,
+                matches!(frame_type, FrameType::ReassociationRequest),
+                body()?
+            ),
+            FrameType::AssociationResponse | FrameType::ReassociationResponse => Ok(Self::AssociationResponse {
+                destination: address1,
+                source: mac(packet, 10)?,
+                bssid: mac(packet, 16)?,
+                // After the capabilities
+                status: u16_le(packet, MANAGEMENT_HEADER + 2)?,
+                aid: u16_le(packet, MANAGEMENT_HEADER + 4)? & AID_MASK
+            }),
+            FrameType::ProbeResponse => Self::probe_response(address1, mac(packet, 10)?, mac(packet, 16)?, u16_le(packet, 22)?, body()?),
+            FrameType::Data => Self::data(
+                flags,
+                (frame_control >> 4) & 0b1111,
+                address1,
+                mac(packet, 10)?,
+                packet
+            ),
             _ => Ok(Self::Unknown)
         }
     }
 
+    /// The transmitter of a control frame, which may have its group bit set to signal that the bandwidth is negotiable
+    fn control_transmitter(packet: &[u8]) -> Result<MacAddress> {
+        let mut address = [0; 6];
+        address.copy_from_slice(slice(packet, 10, 16)?);
+        address[0] &= !1;
+        Ok(MacAddress::new(address))
+    }
+    /// Who sent the frame, where it says
+    pub fn transmitter(&self) -> Option<MacAddress> {
+        match self {
+            Self::Beacon { source, .. }
+                | Self::ProbeRequest { source, .. }
+                | Self::AssociationRequest { source, .. }
+                | Self::AssociationResponse { source, .. }
+                | Self::ProbeResponse { source, .. }
+                | Self::Deauthentication { source, .. }
+                | Self::ChannelSwitch { source, .. }
+                | Self::GasResponse { source, .. } => Some(*source),
+            Self::Control { transmitter, .. } | Self::Wds { transmitter, .. } => Some(*transmitter),
+            Self::PsPoll { station, .. } => Some(*station),
+            Self::Data { bssid, station, from_station, .. } => Some(if *from_station { *station } else { *bssid }),
+            Self::Ack { .. } | Self::Cts { .. } | Self::Unknown => None
+        }
+    }
+    /// The access point announcing a move and the channel it is moving to, whether in its beacons or an action frame
+    pub fn channel_switch(&self) -> Option<(MacAddress, u8)> {
+        match self {
+            Self::Beacon { source, tags, .. } | Self::ProbeResponse { source, tags, .. } => tags.iter().find_map(|tag| match tag {
+                Tag::ChannelSwitch { channel, .. } => Some((*source, *channel)),
+                _ => None
+            }),
+            Self::ChannelSwitch { source, channel } => Some((*source, *channel)),
+            _ => None
+        }
+    }
     /// Get the MacAddress of the sender of the packet
     /// Though all packets are sent by *someone*, not all packets advertise such
     pub fn sender(&self) -> Option<MacAddress> {
@@ -125,21 +755,156 @@ impl Frame {
     }
 
     pub fn beacon(destination: MacAddress, source: MacAddress, bssid: MacAddress, sequence_control: u16, data: &[u8]) -> Result<Self> {
-        let timestamp = u64::from_le_bytes([data[0], data[1], data[2], data[3], data[4], data[5], data[6], data[7]]);
-        let beacon_interval = u16::from_le_bytes([data[8], data[9]]);
-        let capabilities = u16::from_le_bytes([data[10], data[11]]);
-        let tags = Tag::parse_all(&data[12..data.len() - 4])?;
-        let ssid = tags.iter().find_map(|tag| if let Tag::Ssid(ssid) = tag { Some(ssid.clone()) } else { None }).ok_or(Error::MissingTag("SSID"))?;
+        let (ssid, capabilities, interval, tags) = Self::advertisement(data)?;
         Ok(Self::Beacon {
             destination,
             source,
             bssid,
             ssid,
+            capabilities,
+            interval,
+            tags
+        })
+    }
+    pub fn probe_response(destination: MacAddress, source: MacAddress, bssid: MacAddress, sequence_control: u16, data: &[u8]) -> Result<Self> {
+        let (ssid, capabilities, _, tags) = Self::advertisement(data)?;
+        Ok(Self::ProbeResponse {
+            destination,
+            source,
+            bssid,
+            ssid,
+            capabilities,
             tags
         })
     }
+    /// The body shared by beacons and probe responses, giving the SSID, capabilities, beacon interval and tags
+    fn advertisement(data: &[u8]) -> Result<(String, u16, u16, Vec<Tag>)> {
+        // The timestamp comes first, then the interval and capabilities, with the frame check sequence at the end
+        let beacon_interval = u16_le(data, 8)?;
+        let capabilities = u16_le(data, 10)?;
+        let tags = Tag::parse_all(slice(data, 12, data.len().saturating_sub(FCS))?)?;
+        let ssid = tags.iter().find_map(|tag| if let Tag::Ssid(ssid) = tag { Some(ssid.clone()) } else { None }).ok_or(Error::MissingTag("SSID"))?;
+        Ok((ssid, capabilities, beacon_interval, tags))
+    }
+    pub fn action(destination: MacAddress, source: MacAddress, data: &[u8]) -> Result<Self> {
+        const SPECTRUM_MANAGEMENT: u8 = 0;
+        const PUBLIC: u8 = 4;
+        const CHANNEL_SWITCH: u8 = 4;
+        const GAS_INITIAL_RESPONSE: u8 = 11;
+        const GAS_COMEBACK_RESPONSE: u8 = 13;
+        let data = &data[..data.len().saturating_sub(FCS)];
+        match (data.get(0), data.get(1)) {
+            // The announcement element, usually followed by others such as the new channel's width
+            (Some(&SPECTRUM_MANAGEMENT), Some(&CHANNEL_SWITCH)) => return Ok(Tag::parse_all(slice(data, 2, data.len())?)?
+                .into_iter()
+                .find_map(|tag| match tag {
+                    Tag::ChannelSwitch { channel, .. } => Some(Self::ChannelSwitch { source, channel }),
+                    _ => None
+                })
+                .unwrap_or(Self::Unknown)),
+            // The extended announcement's fields without an element header: the mode, operating class, channel and count
+            (Some(&PUBLIC), Some(&CHANNEL_SWITCH)) => return Ok(Self::ChannelSwitch { source, channel: byte(data, 4)? }),
+            _ => ()
+        }
+        // Skip the dialog token, status code, any fragment ID and the comeback delay
+        let fixed = match (data.get(0), data.get(1)) {
+            (Some(&PUBLIC), Some(&GAS_INITIAL_RESPONSE)) => 7,
+            (Some(&PUBLIC), Some(&GAS_COMEBACK_RESPONSE)) => 8,
+            _ => return Ok(Self::Unknown)
+        };
+        let data = slice(data, fixed, data.len())?;
+        // Then the advertisement protocol element, which is assumed to be ANQP
+        let protocol_length = byte(data, 1)? as usize;
+        let data = slice(data, 2 + protocol_length, data.len())?;
+        let length = u16_le(data, 0)? as usize;
+        let response = slice(data, 2, 2 + length)?;
+        Ok(Self::GasResponse {
+            destination,
+            source,
+            elements: Anqp::parse_all(response)?
+        })
+    }
+    pub fn association_request(source: MacAddress, bssid: MacAddress, reassociation: bool, data: &[u8]) -> Result<Self> {
+        // Capabilities and the listen interval, then the current access point when reassociating
+        let fixed = if reassociation { 10 } else { 4 };
+        let data = slice(data, fixed, data.len().saturating_sub(FCS))?;
+        let tags = Tag::parse_all(data)?;
+        let ssid = tags.iter().find_map(|tag| if let Tag::Ssid(ssid) = tag { Some(ssid.clone()) } else { None }).ok_or(Error::MissingTag("SSID"))?;
+        Ok(Self::AssociationRequest {
+            source,
+            bssid,
+            ssid,
+            tags
+        })
+    }
+    /// Work out which side of a data frame is the access point from the distribution system bits
+    pub fn data(flags: u8, subtype: u8, address1: MacAddress, address2: MacAddress, packet: &[u8]) -> Result<Self> {
+        const TO_DS: u8 = 0b01;
+        const FROM_DS: u8 = 0b10;
+        const TO_DS_FROM_DS: u8 = TO_DS | FROM_DS;
+        // Subtypes 8 and up are the QoS variants of the plain ones
+        let qos = subtype & 0b1000 != 0;
+        let length = packet.len();
+        let body = Self::body(flags, subtype, packet);
+        let eapol = body.and_then(Self::eapol);
+        // The address a frame from the access point was sent from comes third, being a station or a host beyond
+        let announce = |source| body.map_or_else(Vec::new, |body| layer3::parse(source, body));
+        Ok(match flags & (TO_DS | FROM_DS) {
+            TO_DS => Self::Data { bssid: address1, station: address2, from_station: true, qos, length, eapol, announcements: announce(address2) },
+            FROM_DS => Self::Data {
+                bssid: address2,
+                station: address1,
+                from_station: false,
+                qos,
+                length,
+                eapol,
+                announcements: mac(packet, 16).map_or_else(|_| vec![], announce)
+            },
+            TO_DS_FROM_DS => Self::Wds { receiver: address1, transmitter: address2 },
+            // Ad-hoc traffic doesn't involve an access point
+            _ => Self::Unknown
+        })
+    }
+    /// The readable body of a data frame, starting from its LLC header
+    fn body(flags: u8, subtype: u8, packet: &[u8]) -> Option<&[u8]> {
+        const PROTECTED: u8 = 0x40;
+        const ORDER: u8 = 0x80;
+        // Null frames carry no body, and an encrypted body can't be read
+        if subtype & 0b0100 != 0 || flags & PROTECTED != 0 {
+            return None
+        }
+        let qos = subtype & 0b1000 != 0;
+        let header = 24 + if qos { 2 } else { 0 } + if qos && flags & ORDER != 0 { 4 } else { 0 };
+        packet.get(header..)
+    }
+    /// Which message of the 4-way handshake a data frame carries, going by the flags of its EAPOL key
+    fn eapol(body: &[u8]) -> Option<u8> {
+        const SNAP_EAPOL: [u8; 8] = [0xaa, 0xaa, 0x03, 0x00, 0x00, 0x00, 0x88, 0x8e];
+        const EAPOL_KEY: u8 = 3;
+        const KEY_PAIRWISE: u16 = 1 << 3;
+        const KEY_INSTALL: u16 = 1 << 6;
+        const KEY_ACK: u16 = 1 << 7;
+        const KEY_MIC: u16 = 1 << 8;
+        const KEY_SECURE: u16 = 1 << 9;
+        if body.get(..8)? != SNAP_EAPOL || *body.get(9)? != EAPOL_KEY {
+            return None
+        }
+        // After the EAPOL header comes the key descriptor type, then the key information
+        let info = u16::from_be_bytes([*body.get(13)?, *body.get(14)?]);
+        // Group key handshakes refresh the broadcast key, and aren't part of the 4-way handshake
+        if info & KEY_PAIRWISE == 0 {
+            return None
+        }
+        Some(match (info & KEY_ACK != 0, info & KEY_MIC != 0, info & KEY_INSTALL != 0, info & KEY_SECURE != 0) {
+            (true, false, _, _) => 1,
+            (true, true, true, _) => 3,
+            (false, true, _, false) => 2,
+            (false, true, _, true) => 4,
+            _ => return None
+        })
+    }
     pub fn probe_request(destination: MacAddress, source: MacAddress, bssid: MacAddress, sequence_control: u16, data: &[u8]) -> Result<Self> {
-        let tags = Tag::parse_all(&data[0..data.len() - 4])?;
+        let tags = Tag::parse_all(&data[..data.len().saturating_sub(FCS)])?;
         let ssid = tags.iter().find_map(|tag| if let Tag::Ssid(ssid) = tag { Some(ssid.clone()) } else { None }).ok_or(Error::MissingTag("SSID"))?;
         Ok(Self::ProbeRequest {
             destination,
@@ -151,6 +916,108 @@ impl Frame {
     }
 }
 
+/// The broad class of an 802.11 frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Kind {
+    Management,
+    Control,
+    Data,
+    Extension
+}
+
+/// The header fields common to every frame, available even when the body cannot be understood
+#[derive(Debug, Clone)]
+pub struct Summary {
+    pub kind: Kind,
+    pub subtype: u8,
+    pub receiver: MacAddress,
+    /// Absent for frames like ACK and CTS, which only name who they are for
+    pub transmitter: Option<MacAddress>,
+    pub length: usize
+}
+impl Summary {
+    pub fn parse(packet: &[u8]) -> Result<Self> {
+        let frame_control = byte(packet, 0)?;
+        let receiver = mac(packet, 4)?;
+        let kind = match (frame_control >> 2) & 0b11 {
+            0 => Kind::Management,
+            1 => Kind::Control,
+            2 => Kind::Data,
+            _ => Kind::Extension
+        };
+        let subtype = frame_control >> 4;
+        let transmitter = match (kind, subtype) {
+            (Kind::Control, 12) | (Kind::Control, 13) => None,
+            (Kind::Control, 8) | (Kind::Control, 9) | (Kind::Control, 11) => Frame::control_transmitter(packet).ok(),
+            _ if packet.len() >= 16 => Some(mac(packet, 10)?),
+            _ => None
+        };
+        Ok(Self {
+            kind,
+            subtype,
+            receiver,
+            transmitter,
+            length: packet.len()
+        })
+    }
+    pub fn is_beacon(&self) -> bool {
+        self.kind == Kind::Management && self.subtype == 8
+    }
+    pub fn subtype_name(&self) -> &'static str {
+        match (self.kind, self.subtype) {
+            (Kind::Management, 0) => "assoc-req",
+            (Kind::Management, 1) => "assoc-resp",
+            (Kind::Management, 2) => "reassoc-req",
+            (Kind::Management, 3) => "reassoc-resp",
+            (Kind::Management, 4) => "probe-req",
+            (Kind::Management, 5) => "probe-resp",
+            (Kind::Management, 6) => "timing-adv",
+            (Kind::Management, 8) => "beacon",
+            (Kind::Management, 9) => "atim",
+            (Kind::Management, 10) => "disassoc",
+            (Kind::Management, 11) => "auth",
+            (Kind::Management, 12) => "deauth",
+            (Kind::Management, 13) => "action",
+            (Kind::Management, 14) => "action-noack",
+            (Kind::Control, 4) => "beamforming-poll",
+            (Kind::Control, 5) => "ndp-announce",
+            (Kind::Control, 7) => "control-wrapper",
+            (Kind::Control, 8) => "block-ack-req",
+            (Kind::Control, 9) => "block-ack",
+            (Kind::Control, 10) => "ps-poll",
+            (Kind::Control, 11) => "rts",
+            (Kind::Control, 12) => "cts",
+            (Kind::Control, 13) => "ack",
+            (Kind::Control, 14) => "cf-end",
+            (Kind::Data, 0) => "data",
+            (Kind::Data, 4) => "null",
+            (Kind::Data, 8) => "qos-data",
+            (Kind::Data, 12) => "qos-null",
+            (Kind::Data, _) => "data-other",
+            (Kind::Extension, _) => "extension",
+            _ => "reserved"
+        }
+    }
+}
+
+/// The bytes from `start` up to `end`, failing rather than panicking if they run past the end of the buffer
+fn slice(data: &[u8], start: usize, end: usize) -> Result<&[u8]> {
+    data.get(start..end).ok_or(Error::UnexpectedEof)
+}
+fn byte(data: &[u8], index: usize) -> Result<u8> {
+    data.get(index).copied().ok_or(Error::UnexpectedEof)
+}
+fn u16_le(data: &[u8], index: usize) -> Result<u16> {
+    Ok(u16::from_le_bytes([byte(data, index)?, byte(data, index + 1)?]))
+}
+/// Spatial streams supported by a VHT or HE MCS map, which gives two bits for each stream with 3 meaning unsupported
+fn streams(map: u16) -> u8 {
+    (0..8).filter(|stream| map >> (stream * 2) & 0b11 != 0b11).count() as u8
+}
+fn mac(data: &[u8], index: usize) -> Result<MacAddress> {
+    Ok(MacAddress::from_bytes(slice(data, index, index + 6)?)?)
+}
+
 type Result<T> = std::result::Result<T, Error>;
 #[derive(Debug)]
 pub enum Error {
@@ -164,4 +1031,257 @@ impl From<eui48::ParseError> for Error {
     fn from(error: eui48::ParseError) -> Self {
         Self::InvalidMac(error)
     }
-}
\ No newline at end of file
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnexpectedEof => write!(f, "cut short"),
+            Self::InvalidVersion(version) => write!(f, "unknown protocol version {}", version),
+            Self::UnrecognisedFrameType => write!(f, "unrecognised frame type"),
+            Self::MissingTag(tag) => write!(f, "missing its {} element", tag),
+            Self::InvalidMac(error) => write!(f, "invalid address: {}", error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACCESS_POINT: [u8; 6] = [0xa4, 0xc3, 0xf0, 0x11, 0x22, 0x33];
+    const CLIENT: [u8; 6] = [0x3a, 0x7d, 0x1e, 0x44, 0x55, 0x66];
+    const CCMP: [u8; 4] = [0x00, 0x0f, 0xac, 0x04];
+    const PSK: [u8; 4] = [0x00, 0x0f, 0xac, 0x02];
+    const PSK_SHA256: [u8; 4] = [0x00, 0x0f, 0xac, 0x08];
+
+    fn access_point() -> MacAddress {
+        MacAddress::new(ACCESS_POINT)
+    }
+    fn client() -> MacAddress {
+        MacAddress::new(CLIENT)
+    }
+    /// A frame from the fuzzing corpus, each of which ends in a made up frame check sequence
+    fn seed(name: &str) -> Vec<u8> {
+        std::fs::read(format!("{}/fuzz/seeds/frame/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap()
+    }
+    fn parse(name: &str) -> Frame {
+        Frame::parse(&seed(name)).unwrap_or_else(|error| panic!("{} didn't parse: {}", name, error))
+    }
+    fn assert_rsn(tags: &[Tag]) {
+        match tags.iter().find(|tag| matches!(tag, Tag::Rsn { .. })) {
+            Some(Tag::Rsn { pairwise, akms, capabilities }) => {
+                assert_eq!(pairwise, &[CCMP]);
+                assert_eq!(akms, &[PSK, PSK_SHA256]);
+                assert_eq!(*capabilities, 0x00c0);
+            }
+            _ => panic!("no RSN element in {:?}", tags)
+        }
+    }
+
+    #[test]
+    fn beacon() {
+        match parse("beacon") {
+            Frame::Beacon { destination, source, bssid, ssid, interval, tags, .. } => {
+                assert_eq!((destination, source, bssid), (MacAddress::broadcast(), access_point(), access_point()));
+                assert_eq!(ssid, "CoffeeShop");
+                assert_eq!(interval, 100);
+                assert_eq!(Tag::layout(&tags), "0,1,50,3,7,11,48,221:0050f2");
+                assert_eq!(Tag::rates(&tags), [0x82, 0x84, 0x8b, 0x96, 0x0c, 0x12, 0x18, 0x24, 0x30, 0x48, 0x60, 0x6c]);
+                assert!(tags.iter().any(|tag| matches!(tag, Tag::DsParameter { channel: 6 })));
+                assert!(tags.iter().any(|tag| matches!(tag, Tag::Country { code, limits } if code == b"AU" && limits == &[(1, 13, 20)])));
+                assert!(tags.iter().any(|tag| matches!(tag, Tag::BssLoad { stations: 3, utilisation: 0x40 })));
+                assert_rsn(&tags);
+            }
+            frame => panic!("parsed as {:?}", frame)
+        }
+    }
+
+    #[test]
+    fn hidden_beacon() {
+        match parse("beacon-hidden") {
+            Frame::Beacon { source, ssid, tags, .. } => {
+                assert_eq!(source, access_point());
+                assert_eq!(ssid, "\0".repeat(8));
+                assert_eq!(Tag::layout(&tags), "0,1,50");
+            }
+            frame => panic!("parsed as {:?}", frame)
+        }
+    }
+
+    #[test]
+    fn hotspot_beacon() {
+        match parse("beacon-hotspot") {
+            Frame::Beacon { ssid, tags, .. } => {
+                assert_eq!(ssid, "Passpoint");
+                assert!(tags.iter().any(|tag| matches!(
+                    tag,
+                    Tag::Interworking { access_network_type: 2, internet: true, venue: Some((2, 5)), hessid: Some(hessid) } if *hessid == access_point()
+                )));
+                assert!(tags.iter().any(|tag| matches!(
+                    tag,
+                    Tag::RoamingConsortium { ois } if ois == &[vec![0x00, 0x50, 0x6f], vec![0x9a, 0x00], vec![0x1b, 0xc5, 0x04]]
+                )));
+            }
+            frame => panic!("parsed as {:?}", frame)
+        }
+    }
+
+    #[test]
+    fn multi_link_beacon() {
+        match parse("beacon-mld") {
+            Frame::Beacon { ssid, tags, .. } => {
+                assert_eq!(ssid, "Home7");
+                assert_eq!(Tag::layout(&tags), "0,1,50,255:108,255:107");
+                let mld = MacAddress::new([0xa6, 0xc3, 0xf0, 0x11, 0x22, 0x33]);
+                let link = MacAddress::new([0xa4, 0xc3, 0xf0, 0x11, 0x22, 0x35]);
+                assert!(tags.iter().any(|tag| matches!(tag, Tag::MultiLink { mld: Some(address), links } if *address == mld && links == &[(1, Some(link))])));
+            }
+            frame => panic!("parsed as {:?}", frame)
+        }
+    }
+
+    #[test]
+    fn owe_transition_beacon() {
+        match parse("beacon-owe") {
+            Frame::Beacon { ssid, tags, .. } => {
+                assert_eq!(ssid, "Guest");
+                let owe = MacAddress::new([0xa4, 0xc3, 0xf0, 0x11, 0x22, 0x34]);
+                assert!(tags.iter().any(|tag| matches!(tag, Tag::OweTransition { bssid, ssid } if *bssid == owe && ssid == "Guest-OWE")));
+            }
+            frame => panic!("parsed as {:?}", frame)
+        }
+    }
+
+    #[test]
+    fn probe_response() {
+        match parse("probe-response") {
+            Frame::ProbeResponse { destination, source, bssid, ssid, tags, .. } => {
+                assert_eq!((destination, source, bssid), (client(), access_point(), access_point()));
+                assert_eq!(ssid, "CoffeeShop");
+                assert_rsn(&tags);
+            }
+            frame => panic!("parsed as {:?}", frame)
+        }
+    }
+
+    #[test]
+    fn probe_requests() {
+        match parse("probe-request-directed") {
+            Frame::ProbeRequest { destination, source, bssid, ssid, tags } => {
+                assert_eq!((destination, source, bssid), (MacAddress::broadcast(), client(), MacAddress::broadcast()));
+                assert_eq!(ssid, "CorpWiFi");
+                assert_eq!(Tag::layout(&tags), "0,1,50");
+            }
+            frame => panic!("parsed as {:?}", frame)
+        }
+        match parse("probe-request-wildcard") {
+            Frame::ProbeRequest { source, ssid, tags, .. } => {
+                assert_eq!(source, client());
+                assert_eq!(ssid, "");
+                assert_eq!(Tag::layout(&tags), "0,1,50,45,221:0050f2");
+                assert!(tags.iter().any(|tag| matches!(tag, Tag::VendorSpecific { vendor: [0x00, 0x50, 0xf2], kind: Some(8) })));
+            }
+            frame => panic!("parsed as {:?}", frame)
+        }
+    }
+
+    #[test]
+    fn association_requests() {
+        match parse("association-request") {
+            Frame::AssociationRequest { source, bssid, ssid, tags } => {
+                assert_eq!((source, bssid), (client(), access_point()));
+                assert_eq!(ssid, "CoffeeShop");
+                assert_rsn(&tags);
+            }
+            frame => panic!("parsed as {:?}", frame)
+        }
+        match parse("reassociation-request") {
+            Frame::AssociationRequest { source, bssid, ssid, tags } => {
+                assert_eq!((source, bssid), (client(), access_point()));
+                assert_eq!(ssid, "CoffeeShop");
+                assert_eq!(Tag::layout(&tags), "0,1,50");
+            }
+            frame => panic!("parsed as {:?}", frame)
+        }
+    }
+
+    #[test]
+    fn gas_response() {
+        match parse("gas-response") {
+            Frame::GasResponse { destination, source, elements } => {
+                assert_eq!((destination, source), (client(), access_point()));
+                match elements.as_slice() {
+                    [Anqp::DomainName(domains), Anqp::NaiRealm(realms)] => {
+                        assert_eq!(domains, &["example.org"]);
+                        assert_eq!(realms, &["example.org"]);
+                    }
+                    elements => panic!("elements parsed as {:?}", elements)
+                }
+            }
+            frame => panic!("parsed as {:?}", frame)
+        }
+    }
+
+    #[test]
+    fn control_frames() {
+        assert!(matches!(parse("ack"), Frame::Ack { receiver } if receiver == client()));
+        assert!(matches!(parse("cts"), Frame::Cts { receiver } if receiver == client()));
+        assert!(matches!(parse("rts"), Frame::Control { receiver, transmitter } if receiver == access_point() && transmitter == client()));
+        for name in &["ack", "cts"] {
+            let summary = Summary::parse(&seed(name)).unwrap();
+            assert_eq!((summary.kind, summary.receiver, summary.transmitter), (Kind::Control, client(), None));
+        }
+    }
+
+    #[test]
+    fn data_frames() {
+        match parse("data-to-ds") {
+            Frame::Data { bssid, station, from_station, qos, eapol, .. } => {
+                assert_eq!((bssid, station, from_station, qos, eapol), (access_point(), client(), true, false, None));
+            }
+            frame => panic!("parsed as {:?}", frame)
+        }
+        match parse("qos-data-from-ds") {
+            Frame::Data { bssid, station, from_station, qos, eapol, .. } => {
+                assert_eq!((bssid, station, from_station, qos, eapol), (access_point(), client(), false, true, None));
+            }
+            frame => panic!("parsed as {:?}", frame)
+        }
+        match parse("null") {
+            Frame::Data { bssid, station, from_station, length, .. } => {
+                assert_eq!((bssid, station, from_station, length), (access_point(), client(), true, 28));
+            }
+            frame => panic!("parsed as {:?}", frame)
+        }
+        let repeater = MacAddress::new([0xa4, 0xc3, 0xf0, 0x99, 0x88, 0x77]);
+        assert!(matches!(parse("wds"), Frame::Wds { receiver, transmitter } if receiver == access_point() && transmitter == repeater));
+    }
+
+    #[test]
+    fn handshake() {
+        for message in 1..=4 {
+            match parse(&format!("eapol-message-{}", message)) {
+                Frame::Data { bssid, station, from_station, eapol, .. } => {
+                    assert_eq!((bssid, station), (access_point(), client()));
+                    // The access point sends the odd messages and the client the even ones
+                    assert_eq!(from_station, message % 2 == 0);
+                    assert_eq!(eapol, Some(message));
+                }
+                frame => panic!("message {} parsed as {:?}", message, frame)
+            }
+        }
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(matches!(Frame::parse(&seed("empty")), Err(Error::UnexpectedEof)));
+        assert!(matches!(Frame::parse(&seed("header-only")), Err(Error::UnexpectedEof)));
+        assert!(matches!(Frame::parse(&seed("beacon-truncated")), Err(Error::UnexpectedEof)));
+        assert!(matches!(Frame::parse(&seed("element-overrun")), Err(Error::UnexpectedEof)));
+        assert!(matches!(Frame::parse(&seed("bad-version")), Err(Error::InvalidVersion(1))));
+        // The header is still enough to say what the frame is and who it is for
+        let summary = Summary::parse(&seed("header-only")).unwrap();
+        assert!(summary.is_beacon());
+        assert_eq!((summary.receiver, summary.transmitter), (MacAddress::broadcast(), None));
+    }
+}
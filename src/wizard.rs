@@ -0,0 +1,134 @@
+use std::{env, fs, io, path::{Path, PathBuf}};
+use tui::{
+    style::{Modifier, Style},
+    text::Spans,
+    widgets::{Block, Borders, List, ListItem}
+};
+
+use crate::{interfaces::Capability, nl80211::{self, BandKind, InterfaceType, Nl80211}, ui::{self, Key, Ui}};
+
+/// The channels 2.4GHz radios are usually spread across, which between them cover the band without overlapping
+const NON_OVERLAPPING: [u32; 3] = [1, 6, 11];
+
+/// Walk through picking an interface, monitor mode, channels and where to save on a first run with no config file,
+/// then write the choices to it so later runs start straight away
+/// Returns false if escape was pressed at any step, in which case nothing is written
+pub fn run(ui: &mut Ui, path: &Path) -> io::Result<bool> {
+    let mut options = vec![];
+
+    let interface = match crate::pick_interface(ui, true) {
+        Some(interface) => interface,
+        None => return Ok(false)
+    };
+    options.push(format!("interface = {:?}", interface));
+
+    let mut nl80211 = Nl80211::connect().ok();
+    let capability = Capability::probe(nl80211.as_mut(), &interface);
+    if capability.mode != Some(InterfaceType::Monitor)
+        && !ui.confirm(&format!("Put {} in monitor mode while capturing?", interface), "Without it only traffic to and from this machine is heard")
+    {
+        options.push("dont-monitor = true".to_string());
+    }
+
+    // Offer only the bands and channels the radio can use, falling back on hopping everything if it can't be asked
+    let bands: Vec<(BandKind, Vec<u32>)> = nl80211.as_mut()
+        .and_then(|nl80211| {
+            let wiphy = nl80211.interface(&interface).ok()?.wiphy;
+            nl80211.wiphy(wiphy).ok()
+        })
+        .map(|wiphy| wiphy.bands.iter()
+            .map(|band| (band.kind, band.frequencies.iter().filter(|frequency| !frequency.disabled).filter_map(nl80211::Frequency::channel).collect::<Vec<_>>()))
+            .filter(|(_, channels)| !channels.is_empty())
+            .collect())
+        .unwrap_or_default();
+    let all: Vec<u32> = bands.iter().flat_map(|(_, channels)| channels.iter().copied()).collect();
+    // Each with the option it sets, or None to go on to pick a channel to stay on
+    let mut choices = vec![("Hop across every channel".to_string(), Some("hop = true".to_string()))];
+    if bands.len() > 1 {
+        for (kind, channels) in bands.iter() {
+            choices.push((format!("Hop across {} channels only", kind), Some(hop_channels(channels))));
+        }
+    }
+    if NON_OVERLAPPING.iter().all(|channel| all.contains(channel)) {
+        choices.push(("Hop across channels 1, 6 and 11".to_string(), Some(hop_channels(&NON_OVERLAPPING))));
+    }
+    if !all.is_empty() {
+        choices.push(("Stay on one channel".to_string(), None));
+    }
+    let titles: Vec<_> = choices.iter().map(|(title, _)| title.as_str()).collect();
+    match choose(ui, "Which channels should be listened to?", &titles)?.map(|index| choices.swap_remove(index).1) {
+        Some(Some(option)) => options.push(option),
+        Some(None) => {
+            let titles: Vec<_> = all.iter().map(|channel| format!("Channel {}", channel)).collect();
+            match choose(ui, "Which channel?", &titles.iter().map(String::as_str).collect::<Vec<_>>())? {
+                Some(index) => options.push(format!("channel = {}", all[index])),
+                None => return Ok(false)
+            }
+        }
+        None => return Ok(false)
+    }
+
+    let data = data_path();
+    let mut titles = vec!["Save captures and exports in the directory blockade-recon is started from".to_string()];
+    if let Some(data) = &data {
+        titles.push(format!("Save captures and exports in {}", data.display()));
+    }
+    titles.push("Don't save captured frames".to_string());
+    match (choose(ui, "Where should captures be saved?", &titles.iter().map(String::as_str).collect::<Vec<_>>())?, data) {
+        (None, _) => return Ok(false),
+        (Some(0), _) => (),
+        (Some(1), Some(data)) => {
+            fs::create_dir_all(&data)?;
+            options.push(format!("output = {:?}", data.join("capture.pcap").display().to_string()));
+            options.push(format!("export = {:?}", data.join("devices.csv").display().to_string()));
+        }
+        (Some(_), _) => options.push("no-save = true".to_string())
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut text = String::from("# Written by blockade-recon on its first run, and read on every run since\n# Options given on the command line take precedence\n");
+    for option in options {
+        text.push_str(&option);
+        text.push('\n');
+    }
+    fs::write(path, text)?;
+    Ok(true)
+}
+
+fn hop_channels(channels: &[u32]) -> String {
+    let channels: Vec<_> = channels.iter().map(u32::to_string).collect();
+    format!("hop-channels = {:?}", channels.join(","))
+}
+
+/// Where captures and exports are kept if asked to, following the XDG base directory specification
+fn data_path() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_DATA_HOME").filter(|path| !path.is_empty()) {
+        Some(path) => PathBuf::from(path),
+        None => PathBuf::from(env::var_os("HOME")?).join(".local/share")
+    };
+    Some(base.join("blockade-recon"))
+}
+
+/// Ask a question with a list of answers, returning the index of the one picked or None if escape was pressed
+fn choose(ui: &mut Ui, question: &str, answers: &[&str]) -> io::Result<Option<usize>> {
+    let items: Vec<_> = answers.iter().map(|answer| ListItem::new(Spans::from(*answer))).collect();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(question))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("> ");
+    let mut list_state = ui::ListState::with_item_count(answers.len());
+    loop {
+        ui.draw(|f| f.render_stateful_widget(list.clone(), f.size(), &mut *list_state))?;
+        match ui.input.stdin.recv() {
+            Ok(Key::Esc) | Err(_) => return Ok(None),
+            Ok(Key::Up) | Ok(Key::Char('w')) => list_state.up(),
+            Ok(Key::Down) | Ok(Key::Char('s')) => list_state.down(),
+            Ok(Key::PageUp) => list_state.top(),
+            Ok(Key::PageDown) => list_state.bottom(),
+            Ok(Key::Char('\n')) => return Ok(list_state.selected()),
+            _ => ()
+        }
+    }
+}